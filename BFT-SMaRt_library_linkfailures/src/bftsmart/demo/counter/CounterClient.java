@@ -33,9 +33,11 @@ public class CounterClient {
 
     public static void main(String[] args) throws IOException {
         if (args.length < 2) {
-            System.out.println("Usage: java ... CounterClient <process id> <increment> [<number of operations>]");
+            System.out.println("Usage: java ... CounterClient <process id> <increment> [<number of operations>] [<interval ms>] [<payload size>]");
             System.out.println("       if <increment> equals 0 the request will be read-only");
             System.out.println("       default <number of operations> equals 1000");
+            System.out.println("       <interval ms> is the pause between two operations, 0 by default");
+            System.out.println("       <payload size> is the size of a request in bytes, at least and by default 4");
             System.exit(-1);
         }
 
@@ -47,24 +49,37 @@ public class CounterClient {
 
             int inc = Integer.parseInt(args[1]);
             int numberOfOps = (args.length > 2) ? Integer.parseInt(args[2]) : 1000;
+            int interval = (args.length > 3) ? Integer.parseInt(args[3]) : 0;
+            int payloadSize = (args.length > 4) ? Math.max(4, Integer.parseInt(args[4])) : 4;
 
             for (int i = 0; i < numberOfOps; i++) {
 
-                ByteArrayOutputStream out = new ByteArrayOutputStream(4);
+                ByteArrayOutputStream out = new ByteArrayOutputStream(payloadSize);
                 new DataOutputStream(out).writeInt(inc);
+                out.write(new byte[payloadSize - 4]);
 
                 System.out.print("Invocation " + i);
+                long start = System.nanoTime();
                 byte[] reply = (inc == 0)?
                         counterProxy.invokeUnordered(out.toByteArray()):
                 	counterProxy.invokeOrdered(out.toByteArray()); //magic happens here
                 
                 if(reply != null) {
                     int newValue = new DataInputStream(new ByteArrayInputStream(reply)).readInt();
-                    System.out.println(", returned value: " + newValue);
+                    long latency = (System.nanoTime() - start) / 1000;
+                    System.out.println(", returned value: " + newValue + ", latency: " + latency + " us");
                 } else {
                     System.out.println(", ERROR! Exiting.");
                     break;
                 }
+
+                if (interval > 0) {
+                    try {
+                        Thread.sleep(interval);
+                    } catch (InterruptedException e) {
+                        break;
+                    }
+                }
             }
         } catch(IOException | NumberFormatException e){
             counterProxy.close();
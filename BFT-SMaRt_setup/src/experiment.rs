@@ -0,0 +1,215 @@
+//! The experiment matrix: every node count is run for a number of rounds with the same client
+//! workload. It is read from the section '[experiment]' of an ini, missing keys keep their
+//! defaults:
+//!
+//! ```text
+//! [experiment]
+//! nodes = 4 7 10
+//! rounds = 3
+//! clients = 2
+//! requests = 1000
+//! interval_ms = 10
+//! payload_size = 64
+//! duration_s = 60
+//! ready_timeout_s = 30
+//! shutdown_timeout_s = 5
+//! output = results
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use tini::Ini;
+
+/// The section of the ini holding the experiment
+const SECTION: &str = "experiment";
+
+/// Parameters of an experiment
+#[derive(Debug, Clone, PartialEq)]
+pub struct Experiment {
+    /// Number of replicas, each is run for all rounds
+    pub nodes: Vec<usize>,
+    /// Number of rounds of each node count
+    pub rounds: usize,
+    /// Number of clients issuing requests at the same time
+    pub clients: usize,
+    /// Number of requests of each client
+    pub requests: usize,
+    /// Pause (ms) of a client between receiving a reply and issuing the next request
+    pub interval_ms: u64,
+    /// Size of a request in bytes, at least 4
+    pub payload_size: usize,
+    /// Time (s) a round may take at most
+    pub duration_s: u64,
+    /// Time (s) a replica may take to start and, after all started, to get ready
+    pub ready_timeout_s: u64,
+    /// Time (s) the processes may take to exit at the end of a round before they are killed
+    pub shutdown_timeout_s: u64,
+    /// Directory the results are written to
+    pub output: PathBuf,
+}
+
+/// The counter demo with a single client, as run without an experiment file
+impl Default for Experiment {
+    fn default() -> Self {
+        Experiment {
+            nodes: vec![4],
+            rounds: 1,
+            clients: 1,
+            requests: 1,
+            interval_ms: 0,
+            payload_size: 4,
+            duration_s: 15,
+            ready_timeout_s: 30,
+            shutdown_timeout_s: 5,
+            output: PathBuf::from("results"),
+        }
+    }
+}
+
+impl Experiment {
+    /// Reads the experiment from the file at `path`
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Experiment::from_str(&text)
+    }
+
+    /// Checks the parameters, returns all violations at once
+    pub fn validate(&self) -> Result<(), String> {
+        let mut errors = Vec::new();
+        if self.nodes.is_empty() || self.nodes.iter().any(|n| *n < 4) {
+            errors.push("nodes must list node counts of at least 4".to_string());
+        }
+        for (name, value) in &[
+            ("rounds", self.rounds),
+            ("clients", self.clients),
+            ("requests", self.requests),
+            ("ready_timeout_s", self.ready_timeout_s as usize),
+        ] {
+            if *value == 0 {
+                errors.push(format!("{} must be at least 1", name));
+            }
+        }
+        if self.payload_size < 4 {
+            errors.push("payload_size must be at least 4".to_string());
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+impl FromStr for Experiment {
+    type Err = String;
+
+    /// Parses the keys of the section '[experiment]', returns all misconfigured keys at once
+    fn from_str(text: &str) -> Result<Self, String> {
+        let ini = Ini::from_buffer(text);
+        let mut experiment = Experiment::default();
+        let mut errors = Vec::new();
+
+        for (section, _) in ini.iter() {
+            if section != SECTION {
+                errors.push(format!("unknown section [{}]", section));
+            }
+        }
+        match ini.iter_section(SECTION) {
+            Some(keys) => {
+                for (key, value) in keys {
+                    if let Err(e) = experiment.set(key, value) {
+                        errors.push(e);
+                    }
+                }
+            }
+            None => errors.push(format!("no section [{}]", SECTION)),
+        }
+
+        if !errors.is_empty() {
+            return Err(errors.join("\n"));
+        }
+        experiment.validate()?;
+        Ok(experiment)
+    }
+}
+
+impl Experiment {
+    // sets the parameter `key` to `value`
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let invalid = || format!("can't parse '{}' of [{}]{}", value, SECTION, key);
+
+        match key {
+            "nodes" => {
+                self.nodes = value
+                    .split_whitespace()
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| invalid())?
+            }
+            "rounds" => self.rounds = value.parse().map_err(|_| invalid())?,
+            "clients" => self.clients = value.parse().map_err(|_| invalid())?,
+            "requests" => self.requests = value.parse().map_err(|_| invalid())?,
+            "interval_ms" => self.interval_ms = value.parse().map_err(|_| invalid())?,
+            "payload_size" => self.payload_size = value.parse().map_err(|_| invalid())?,
+            "duration_s" => self.duration_s = value.parse().map_err(|_| invalid())?,
+            "ready_timeout_s" => self.ready_timeout_s = value.parse().map_err(|_| invalid())?,
+            "shutdown_timeout_s" => {
+                self.shutdown_timeout_s = value.parse().map_err(|_| invalid())?
+            }
+            "output" => self.output = PathBuf::from(value),
+            _ => return Err(format!("unknown key [{}]{}", SECTION, key)),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn experiment_is_read_from_its_section() {
+        let experiment = Experiment::from_str(
+            "; the matrix\n[experiment]\nnodes = 4 7\nrounds = 3\npayload_size = 64\n\
+             output = out/bftsmart\n",
+        )
+        .unwrap();
+        assert_eq!(experiment.nodes, vec![4, 7]);
+        assert_eq!(experiment.rounds, 3);
+        assert_eq!(experiment.payload_size, 64);
+        assert_eq!(experiment.output, PathBuf::from("out/bftsmart"));
+        // missing keys keep their defaults
+        assert_eq!(experiment.clients, 1);
+        assert_eq!(experiment.ready_timeout_s, 30);
+    }
+
+    #[test]
+    fn all_misconfigured_keys_are_reported() {
+        let errors = Experiment::from_str(
+            "[experiment]\nnodes = 4 x\nrounds = -1\nretries = 2\n\n[replicas]\ncount = 4\n",
+        )
+        .unwrap_err();
+        let mut errors: Vec<&str> = errors.lines().collect();
+        errors.sort_unstable();
+        assert_eq!(
+            errors,
+            vec![
+                "can't parse '-1' of [experiment]rounds",
+                "can't parse '4 x' of [experiment]nodes",
+                "unknown key [experiment]retries",
+                "unknown section [replicas]",
+            ]
+        );
+
+        assert!(Experiment::from_str("nodes = 4\n")
+            .unwrap_err()
+            .contains("no section [experiment]"));
+        assert_eq!(
+            Experiment::from_str("[experiment]\nnodes = 3\npayload_size = 2\n").unwrap_err(),
+            "nodes must list node counts of at least 4\npayload_size must be at least 4"
+        );
+    }
+}
@@ -0,0 +1,58 @@
+//! Starting the BFT-SMaRt processes. The services and the client are started as Java processes
+//! directly instead of via the runscripts, so the harness runs wherever Java is installed.
+
+use std::env;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Entries of the classpath, relative to the directory of a replica or the client
+const DEFAULT_CLASSPATH: [&str; 2] = ["bin/BFT-SMaRt.jar", "lib/*"];
+
+/// Name of the Java executable in `$JAVA_HOME/bin`
+#[cfg(windows)]
+const JAVA_EXECUTABLE: &str = "java.exe";
+#[cfg(unix)]
+const JAVA_EXECUTABLE: &str = "java";
+
+/// The Java runtime and classpath the processes are started with
+#[derive(Debug, Clone, PartialEq)]
+pub struct JavaRuntime {
+    java: PathBuf,
+    classpath: OsString,
+}
+
+impl JavaRuntime {
+    /// `java` defaults to `$JAVA_HOME/bin/java` if set and to `java` on the path otherwise. The
+    /// entries of `classpath` are separated like in the PATH of the platform, i.e. by ';' on
+    /// Windows and by ':' elsewhere.
+    pub fn new(java: Option<&str>, classpath: Option<&str>) -> Result<Self, String> {
+        let java = match java {
+            Some(java) => PathBuf::from(java),
+            None => match env::var_os("JAVA_HOME") {
+                Some(home) => Path::new(&home).join("bin").join(JAVA_EXECUTABLE),
+                None => PathBuf::from(JAVA_EXECUTABLE),
+            },
+        };
+        let classpath = match classpath {
+            Some(classpath) => OsString::from(classpath),
+            None => env::join_paths(DEFAULT_CLASSPATH.iter())
+                .map_err(|e| format!("Invalid default classpath: {}", e))?,
+        };
+        Ok(JavaRuntime { java, classpath })
+    }
+
+    /// Command running the main method of `class` with `args`, in the directory `dir` of a replica
+    /// or the client
+    pub fn command<S: AsRef<str>>(&self, dir: &Path, class: &str, args: &[S]) -> Command {
+        let mut command = Command::new(&self.java);
+        command
+            .arg("-Dlogback.configurationFile=./config/logback.xml")
+            .arg("-cp")
+            .arg(&self.classpath)
+            .arg(class)
+            .args(args.iter().map(AsRef::as_ref))
+            .current_dir(dir);
+        command
+    }
+}
@@ -1,258 +1,449 @@
 extern crate log;
 
+mod experiment;
+mod java;
+mod results;
+mod supervisor;
+
+use experiment::Experiment;
 use getopts::Options;
+use java::JavaRuntime;
+use results::{Completion, RoundResults};
 use std::env;
+use std::fs;
 use std::io::Write;
-use std::io::{BufRead, BufReader, BufWriter};
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind};
 use std::net::{TcpListener, TcpStream};
-use std::process::{Command, Stdio};
+use std::path::Path;
+use std::process::{self, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
-use std::fs;
+use std::time::{Duration, Instant};
+use supervisor::{Exit, Supervisor};
+
+/// Interval of checking the health of the processes while waiting for them
+const HEALTH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Messages of the replicas on the handshake socket
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Handshake {
+    /// The replica started
+    Hello,
+    /// The replica is ready to process operations
+    Ready,
+}
 
 fn main() {
-    //    let path_client = "cmd.exe";
-    //    let client_args = vec!(
-    //        "java",
-    //        "-version"
-    ////        "-Dlogback.configurationFile=\"./config/logback.xml\"",
-    ////        "-cp",
-    ////        "\"bin/BFT-SMaRt.jar;lib/*\"",
-    //////        "runscripts/smartrun.sh",
-    ////        "bftsmart.demo.counter.CounterClient",
-    ////        "1001",
-    ////        "1",
-    ////        "1",
-    //    );
-    //
-    //    //todo start client
-    //    let mut client_process = Command::new(path_client)
-    //        .args(&client_args)
-    //        .current_dir("./client/")
-    //        .stdout(Stdio::piped())
-    //        .stderr(Stdio::piped())
-    //        .output()
-    //        .unwrap();
-    //
-    //    thread::sleep(Duration::from_secs(3));
-    //
-    ////    let output = client_process.wait_with_output().expect("Failed to read stdout");
-    //    println!("status: {}", client_process.status);
-    ////    println!("{}", String::from_utf8_lossy(&output.stdout));
-    //    io::stdout().write_all(&client_process.stdout).unwrap();
-    ////    thread::sleep(Duration::from_secs(10));
-    //
-    ////    client_process.kill();
-    //
-    //    return;
-
-    //    let path_bash = "c:/Program Files/Git/usr/bin/mintty.exe";
-    let path_bash = "c:/Program Files/Git/git-bash.exe";
-
     let args: Vec<String> = env::args().collect();
     let mut opts = Options::new();
-    opts.optopt("n", "nodes", "number of nodes", "NODES");
-    opts.optopt("r", "rounds", "number of rounds", "ROUNDS");
+    opts.optopt(
+        "e",
+        "experiment",
+        "experiment file, see src/experiment.rs for its keys",
+        "FILE",
+    );
+    opts.optopt(
+        "n",
+        "nodes",
+        "number of nodes, overrides the experiment",
+        "NODES",
+    );
+    opts.optopt(
+        "r",
+        "rounds",
+        "number of rounds, overrides the experiment",
+        "ROUNDS",
+    );
+    opts.optopt(
+        "j",
+        "java",
+        "java executable, $JAVA_HOME/bin/java or java on the path by default",
+        "JAVA",
+    );
+    opts.optopt(
+        "c",
+        "classpath",
+        "classpath relative to the replica directories, bin/BFT-SMaRt.jar and lib/* by default",
+        "CLASSPATH",
+    );
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
-        Err(f) => panic!(f.to_string()),
+        Err(f) => panic!("{}", f),
     };
 
-    let nodes = matches.opt_str("n").unwrap().parse::<usize>().unwrap();
-    let rounds = matches.opt_str("r").unwrap().parse::<usize>().unwrap();
-//    debug!("Starting for n = {}", &nodes);
-
-//    println!("Path to client: {}", path_client);
-//    println!("Path to nodes: {:?}", path_nodes);
-//    println!("Argument for nodes: {:?}", node_args);
-
-    for r in 1..=rounds {
-
-        let path_client = "./client/";
-        let client_args = vec![
-            //        "--nodaemon",
-            //        "-o",
-            //        "AppID=GitForWindows.Bash",
-            //        "-o",
-            //        "AppLaunchCmd=\"C:\\Program Files\\Git\\git-bash.exe\"",
-            //        "-o",
-            //        "AppName=\"Git Bash\"",
-            //        "-i",
-            //        "\"C:\\Program Files\\Git\\git-bash.exe\"",
-            //        "--store-taskbar-properties",
-            //        "-- /usr/bin/bash",
-            //        "--login",
-            //        "--i",
-            "runscripts/smartrun.sh",
-            "bftsmart.demo.counter.CounterClient",
-            "1001",
-            "1",
-            "1",
-        ];
-
-        let (sender_kill_client, receiver_kill_client) = mpsc::channel();
-
-        let mut path_nodes = Vec::new();
-        let mut node_args = Vec::new();
-        let mut vec_sender_kill_node = Vec::new();
-        let mut vec_receiver_kill_node = Vec::new();
-        for i in 1..=nodes {
-            path_nodes.push(format!("./r{}/", i));
-            node_args.push(vec![
-                "runscripts/smartrun.sh".to_string(),
-                "bftsmart.demo.counter.CounterServer".to_string(),
-                (i-1).to_string(),
-            ]);
-            let (tx, rx) = mpsc::channel();
-            vec_sender_kill_node.push(tx);
-            vec_receiver_kill_node.push(rx);
-        }
-
-        // delete the old config gile if it exists
-        for path in &path_nodes {
-            let mut config = path.to_string();
-            config.push_str("config/currentView");
-            fs::remove_file(config);
+    let mut experiment = match matches.opt_str("e") {
+        Some(path) => Experiment::from_file(Path::new(&path)).unwrap_or_else(|e| panic!("{}", e)),
+        None => Experiment::default(),
+    };
+    if let Some(nodes) = matches.opt_str("n") {
+        experiment.nodes = vec![nodes.parse::<usize>().unwrap()];
+    }
+    if let Some(rounds) = matches.opt_str("r") {
+        experiment.rounds = rounds.parse::<usize>().unwrap();
+    }
+    experiment
+        .validate()
+        .unwrap_or_else(|e| panic!("Invalid experiment:\n{}", e));
+    let java = JavaRuntime::new(
+        matches.opt_str("j").as_deref(),
+        matches.opt_str("c").as_deref(),
+    )
+    .unwrap_or_else(|e| panic!("{}", e));
+
+    let supervisor = Supervisor::new();
+    supervisor
+        .kill_on_interrupt()
+        .unwrap_or_else(|e| panic!("{}", e));
+
+    let mut failed_rounds = 0;
+    for &nodes in &experiment.nodes {
+        for r in 1..=experiment.rounds {
+            match run_round(&java, &experiment, &supervisor, nodes, r) {
+                Ok(results) => {
+                    println!(
+                        "Successful round {} with {} nodes, {} of {} requests returned",
+                        r,
+                        nodes,
+                        results.completions.len(),
+                        results.issued
+                    );
+                    match results.write(&experiment.output) {
+                        Ok((csv, log)) => println!("  {}\n  {}", csv.display(), log.display()),
+                        Err(e) => println!("Failed to write the results: {}", e),
+                    }
+                }
+                Err(e) => {
+                    println!("Round {} with {} nodes failed: {}", r, nodes, e);
+                    failed_rounds += 1;
+                }
+            }
+            // the replicas release their ports only a while after they exited
+            thread::sleep(Duration::from_secs(10));
         }
+    }
 
-        thread::sleep(Duration::from_millis(500));
-
-        // accept connections and process them serially
-        let listener = TcpListener::bind("127.0.0.1:9437").unwrap();
-        let (sender_counter, receiver_counter) = mpsc::channel();
-        thread::spawn(move || handle_stream(vec_receiver_kill_node, sender_counter, listener, nodes));
-
-        let listener_client = TcpListener::bind("127.0.0.1:9438").unwrap();
-        thread::spawn(move || handle_client_client(receiver_kill_client, listener_client));
-
-        //start the services
-        let mut vec_process_nodes = Vec::new();
-
-        for _ in 1..=nodes {
-            let args = node_args.remove(0);
-            let path = path_nodes.remove(0);
-
-            vec_process_nodes.push(
-                Command::new(path_bash)
-                    .args(args)
-                    .current_dir(path)
-                    .stdout(Stdio::null())
-                    .stderr(Stdio::null())
-                    .spawn()
-                    .unwrap(),
-            );
-            thread::sleep(Duration::from_millis(1000));
-        }
+    if failed_rounds > 0 {
+        process::exit(1);
+    }
+}
 
-        let mut cnt = 0;
+/// Runs the round `r` with `nodes` replicas and returns the replies of the clients. All processes of
+/// the round are stopped afterwards, also if the round fails.
+fn run_round(
+    java: &JavaRuntime,
+    experiment: &Experiment,
+    supervisor: &Supervisor,
+    nodes: usize,
+    r: usize,
+) -> Result<RoundResults, String> {
+    let (sender_kill_client, receiver_kill_client) = mpsc::channel();
+
+    let mut vec_sender_kill_node = Vec::new();
+    let mut vec_receiver_kill_node = Vec::new();
+    for i in 1..=nodes {
+        // delete the old config file if it exists
+        let _ = fs::remove_file(format!("./r{}/config/currentView", i));
+        let (tx, rx) = mpsc::channel();
+        vec_sender_kill_node.push(tx);
+        vec_receiver_kill_node.push(rx);
+    }
 
-        loop {
-            if let Ok(_) = receiver_counter.recv() {
-                cnt += 1;
-//                println!("Node serivces ready: {}", cnt)
-            }
+    let ready_timeout = Duration::from_secs(experiment.ready_timeout_s);
+    let listener = TcpListener::bind("127.0.0.1:9437")
+        .map_err(|e| format!("Failed to listen for the replicas: {}", e))?;
+    let listener_client = TcpListener::bind("127.0.0.1:9438")
+        .map_err(|e| format!("Failed to listen for the clients: {}", e))?;
+
+    // the replicas start one after the other, each within the timeout
+    let deadline = Instant::now() + ready_timeout * nodes as u32;
+    let accepting = Arc::new(AtomicBool::new(true));
+    let (sender_handshake, receiver_handshake) = mpsc::channel();
+    let accepting_nodes = accepting.clone();
+    thread::spawn(move || {
+        handle_stream(
+            vec_receiver_kill_node,
+            sender_handshake,
+            listener,
+            deadline,
+            &accepting_nodes,
+        )
+    });
+
+    let clients = experiment.clients;
+    let deadline = deadline + ready_timeout;
+    let accepting_clients = accepting.clone();
+    thread::spawn(move || {
+        handle_client_client(
+            receiver_kill_client,
+            listener_client,
+            clients,
+            deadline,
+            &accepting_clients,
+        )
+    });
+
+    let outcome = measure_round(
+        java,
+        experiment,
+        supervisor,
+        nodes,
+        r,
+        &mut Readiness::new(receiver_handshake),
+    );
+
+    // ask all processes to stop and kill those that do not
+    accepting.store(false, Ordering::SeqCst);
+    for sender in vec_sender_kill_node {
+        let _ = sender.send(());
+    }
+    let _ = sender_kill_client.send(());
+
+    let exits = supervisor.shutdown(Duration::from_secs(experiment.shutdown_timeout_s));
+    let failed: Vec<String> = exits
+        .iter()
+        .filter(|(_, exit)| match exit {
+            Exit::Exited(status) => !status.success(),
+            Exit::Killed => true,
+        })
+        .map(|(name, exit)| format!("{}: {}", name, exit))
+        .collect();
+
+    match outcome {
+        Ok(_) if !failed.is_empty() => Err(format!(
+            "processes did not shut down cleanly\n  {}",
+            failed.join("\n  ")
+        )),
+        outcome => outcome,
+    }
+}
 
-            if cnt == nodes {
-//                println!("All node services indicated 'ready'");
-                break;
+/// Starts the replicas and, once they are ready, the clients, and collects the replies until all
+/// requests returned or the time of the round is up
+fn measure_round(
+    java: &JavaRuntime,
+    experiment: &Experiment,
+    supervisor: &Supervisor,
+    nodes: usize,
+    r: usize,
+    readiness: &mut Readiness,
+) -> Result<RoundResults, String> {
+    let ready_timeout = Duration::from_secs(experiment.ready_timeout_s);
+
+    //start the services
+    for i in 1..=nodes {
+        let name = format!("replica {}", i - 1);
+        let mut command = java.command(
+            Path::new(&format!("./r{}/", i)),
+            "bftsmart.demo.counter.CounterServer",
+            &[(i - 1).to_string()],
+        );
+        command.stdout(Stdio::null()).stderr(Stdio::null());
+        supervisor
+            .spawn(&name, &mut command)
+            .map_err(|e| format!("Failed to start {}: {}", name, e))?;
+        readiness.wait_until(i, 0, ready_timeout, supervisor)?;
+    }
+    readiness.wait_until(nodes, nodes, ready_timeout, supervisor)?;
+
+    // start the clients, each reports its replies on stdout
+    let start = Instant::now();
+    let (sender_completion, receiver_completion) = mpsc::channel();
+    for c in 0..experiment.clients {
+        let name = format!("client {}", 1001 + c);
+        let client_args = [
+            (1001 + c).to_string(),
+            "1".to_string(),
+            experiment.requests.to_string(),
+            experiment.interval_ms.to_string(),
+            experiment.payload_size.to_string(),
+        ];
+        let mut command = java.command(
+            Path::new("./client/"),
+            "bftsmart.demo.counter.CounterClient",
+            &client_args,
+        );
+        command.stdout(Stdio::piped()).stderr(Stdio::null());
+        let output = supervisor
+            .spawn(&name, &mut command)
+            .map_err(|e| format!("Failed to start {}: {}", name, e))?
+            .expect("the stdout of the client is piped");
+        let sender = sender_completion.clone();
+        thread::spawn(move || results::collect_replies(c, output, start, sender));
+    }
+    drop(sender_completion);
+
+    // wait until all requests returned or the time of the round is up
+    let issued = experiment.clients * experiment.requests;
+    let deadline = start + Duration::from_secs(experiment.duration_s);
+    let mut completions: Vec<Completion> = Vec::new();
+    while completions.len() < issued && Instant::now() < deadline {
+        let left = deadline.saturating_duration_since(Instant::now());
+        match receiver_completion.recv_timeout(left.min(HEALTH_INTERVAL)) {
+            Ok(completion) => completions.push(completion),
+            Err(RecvTimeoutError::Timeout) => supervisor.check_health()?,
+            Err(RecvTimeoutError::Disconnected) => {
+                supervisor.check_health()?;
+                return Err("the output of all clients closed".to_string());
             }
         }
+    }
 
-        let mut client_process = Command::new(path_bash)
-            .args(&client_args)
-            .current_dir(path_client)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
-            .unwrap();
+    Ok(RoundResults {
+        nodes,
+        round: r,
+        requests: experiment.requests,
+        issued,
+        duration_ms: start.elapsed().as_millis() as u64,
+        completions,
+    })
+}
 
-        //    println!("id: {}", client_process.id());
+/// Counts the handshake messages of the replicas
+struct Readiness {
+    receiver: Receiver<Handshake>,
+    started: usize,
+    ready: usize,
+}
 
-        //wait a bit
-        thread::sleep(Duration::from_secs(15));
-        //    client_process.kill();
+impl Readiness {
+    fn new(receiver: Receiver<Handshake>) -> Self {
+        Readiness {
+            receiver,
+            started: 0,
+            ready: 0,
+        }
+    }
 
-        //send kill signals to all processes
-        for sender in vec_sender_kill_node {
-            sender.send(()).unwrap();
+    /// Waits up to `timeout` until `started` replicas said hello and `ready` are ready, fails early
+    /// if a process exits meanwhile
+    fn wait_until(
+        &mut self,
+        started: usize,
+        ready: usize,
+        timeout: Duration,
+        supervisor: &Supervisor,
+    ) -> Result<(), String> {
+        let deadline = Instant::now() + timeout;
+        while self.started < started || self.ready < ready {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left == Duration::from_secs(0) {
+                return Err(format!(
+                    "timed out after {}s with {} replicas started and {} ready",
+                    timeout.as_secs(),
+                    self.started,
+                    self.ready
+                ));
+            }
+            match self.receiver.recv_timeout(left.min(HEALTH_INTERVAL)) {
+                Ok(Handshake::Hello) => self.started += 1,
+                Ok(Handshake::Ready) => self.ready += 1,
+                Err(RecvTimeoutError::Timeout) => supervisor.check_health()?,
+                Err(RecvTimeoutError::Disconnected) => {
+                    supervisor.check_health()?;
+                    return Err("the handshake with the replicas stopped".to_string());
+                }
+            }
         }
-        sender_kill_client.send(()).unwrap();
+        Ok(())
+    }
+}
 
-        thread::sleep(Duration::from_secs(1));
-        if let Ok(_) = client_process.try_wait() {
-            println!("Successful round {}", r);
-            thread::sleep(Duration::from_secs(10));
-        } else {
-            println!("Something went wrong shutting down the client");
-            break;
+/// Accepts the next connection on `listener`, gives up at `deadline` or once `accepting` is unset
+fn accept_before(
+    listener: &TcpListener,
+    deadline: Instant,
+    accepting: &AtomicBool,
+) -> Option<TcpStream> {
+    if listener.set_nonblocking(true).is_err() {
+        return None;
+    }
+    while Instant::now() < deadline && accepting.load(Ordering::SeqCst) {
+        match listener.accept() {
+            Ok((stream, _)) => return stream.set_nonblocking(false).ok().map(|_| stream),
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(HEALTH_INTERVAL / 10),
+            Err(_) => return None,
         }
     }
+    None
 }
 
 fn handle_stream(
-    mut vec_receiver_kill_node: Vec<Receiver<()>>,
-    sender_counter: Sender<()>,
+    vec_receiver_kill_node: Vec<Receiver<()>>,
+    sender_handshake: Sender<Handshake>,
     listener: TcpListener,
-    nodes: usize,
+    deadline: Instant,
+    accepting: &AtomicBool,
 ) {
-    let mut cnt = 0;
-    for stream in listener.incoming() {
-        cnt += 1;
-        let receiver_kill_node = vec_receiver_kill_node.pop().unwrap();
-        let sender_counter_clone = sender_counter.clone();
-        thread::spawn(move || {
-            handle_client(
-                receiver_kill_node,
-                sender_counter_clone,
-                stream.unwrap(),
-            )
-        });
-        if cnt == nodes {
-            break;
-        }
+    for receiver_kill_node in vec_receiver_kill_node {
+        let stream = match accept_before(&listener, deadline, accepting) {
+            Some(stream) => stream,
+            None => return,
+        };
+        let sender = sender_handshake.clone();
+        thread::spawn(move || handle_client(receiver_kill_node, sender, stream));
     }
 }
 
-fn handle_client(receiver_kill_node: Receiver<()>, sender_counter: Sender<()>, stream: TcpStream) {
-    let mut reader = BufReader::new(stream.try_clone().unwrap());
+fn handle_client(
+    receiver_kill_node: Receiver<()>,
+    sender_handshake: Sender<Handshake>,
+    stream: TcpStream,
+) {
+    let mut reader = match stream.try_clone() {
+        Ok(stream) => BufReader::new(stream),
+        Err(_) => return,
+    };
     let mut writer = BufWriter::new(stream);
 
     loop {
         let mut msg = String::new();
-        if reader.read_line(&mut msg).is_err() {
-//            println!("Reading from socket failed, canceling connection");
-            return;
+        match reader.read_line(&mut msg) {
+            // the replica closed the connection or failed
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
         }
-        let msg = msg.trim();
-
-        if msg.eq(&"hello".to_string()) {
-//            println!("A node service was activated");
-        } else if msg.eq(&"ready".to_string()) {
-//            println!("A node service indicated ready");
-            sender_counter.send(()).unwrap();
-            break;
+
+        match msg.trim() {
+            "hello" => {
+                let _ = sender_handshake.send(Handshake::Hello);
+            }
+            "ready" => {
+                let _ = sender_handshake.send(Handshake::Ready);
+                break;
+            }
+            _ => {}
         }
     }
 
-    if let Ok(_) = receiver_kill_node.recv() {
-        writer.write("done".as_bytes()).unwrap();
-        writer.flush().unwrap();
+    // the replica may already be gone, it is killed then anyway
+    if receiver_kill_node.recv().is_ok() {
+        let _ = writer.write_all("done".as_bytes());
+        let _ = writer.flush();
     }
-//    println!("received kill command for node, listener thread stopping");
 }
 
-fn handle_client_client(receiver_kill: Receiver<()>, listener: TcpListener){
-    for stream in listener.incoming(){
-//        println!("Client connected");
-        let stream = stream.unwrap();
-        let mut writer = BufWriter::new(stream);
+fn handle_client_client(
+    receiver_kill: Receiver<()>,
+    listener: TcpListener,
+    clients: usize,
+    deadline: Instant,
+    accepting: &AtomicBool,
+) {
+    let mut writers = Vec::new();
+    while writers.len() < clients {
+        match accept_before(&listener, deadline, accepting) {
+            Some(stream) => writers.push(BufWriter::new(stream)),
+            None => break,
+        }
+    }
 
-        if let Ok(_) = receiver_kill.recv() {
-            writer.write("done".as_bytes()).unwrap();
-            writer.flush().unwrap();
+    if receiver_kill.recv().is_ok() {
+        for writer in &mut writers {
+            let _ = writer.write_all("done".as_bytes());
+            let _ = writer.flush();
         }
-        break;
     }
 }
@@ -0,0 +1,215 @@
+//! Results of a round: the replies the clients report on their stdout are collected and written as
+//! CSV and as result log in the format of the simulation, so both can be evaluated side by side.
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::time::Instant;
+
+/// A request of a client that returned
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Completion {
+    /// Index of the client, starting with 0
+    pub client: usize,
+    /// Index of the request of the client, starting with 0
+    pub invocation: u64,
+    /// Time (ms) since the start of the round the reply arrived at
+    pub time_ms: u64,
+    /// Time (us) between issuing the request and the reply
+    pub latency_us: u64,
+}
+
+/// Parses a line 'Invocation <i>, returned value: <v>[, latency: <l> us]' of the counter client,
+/// returns the invocation and, if reported, the latency (us). Clients built before the latency was
+/// printed leave it out.
+pub fn parse_reply(line: &str) -> Option<(u64, Option<u64>)> {
+    let rest = line.trim().strip_prefix("Invocation ")?;
+    let mut fields = rest.split(", ");
+    let invocation = fields.next()?.parse().ok()?;
+    fields.next()?.strip_prefix("returned value: ")?;
+    let latency = fields
+        .next()
+        .and_then(|field| field.strip_prefix("latency: "))
+        .and_then(|field| field.trim_end_matches(" us").parse().ok());
+    Some((invocation, latency))
+}
+
+/// Reads the stdout of the client with the index `client` until it closes and sends every reply.
+/// Without a reported latency, the time since the previous reply of the client is taken instead.
+pub fn collect_replies<R: Read>(
+    client: usize,
+    output: R,
+    start: Instant,
+    sender: Sender<Completion>,
+) {
+    let mut previous = start.elapsed();
+    for line in BufReader::new(output).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        if let Some((invocation, latency_us)) = parse_reply(&line) {
+            let now = start.elapsed();
+            let latency_us =
+                latency_us.unwrap_or_else(|| now.saturating_sub(previous).as_micros() as u64);
+            previous = now;
+            let completion = Completion {
+                client,
+                invocation,
+                time_ms: now.as_millis() as u64,
+                latency_us,
+            };
+            if sender.send(completion).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// The replies of a round of the experiment
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundResults {
+    pub nodes: usize,
+    pub round: usize,
+    /// Number of requests each client issues
+    pub requests: usize,
+    /// Number of requests issued by all clients
+    pub issued: usize,
+    /// Time (ms) the round took
+    pub duration_ms: u64,
+    pub completions: Vec<Completion>,
+}
+
+impl RoundResults {
+    /// Writes the CSV and the result log of the round to `dir`, returns their paths
+    pub fn write(&self, dir: &Path) -> io::Result<(PathBuf, PathBuf)> {
+        fs::create_dir_all(dir)?;
+        let csv = dir.join(format!("latency_{:0>3}_{:0>3}.csv", self.nodes, self.round));
+        let log = dir.join(format!(
+            "result_{:0>3}_{:0>3}_bftsmart.log",
+            self.nodes, self.round
+        ));
+
+        let mut writer = BufWriter::new(File::create(&csv)?);
+        self.write_csv(&mut writer)?;
+        writer.flush()?;
+
+        let mut writer = BufWriter::new(File::create(&log)?);
+        self.write_result_log(&mut writer)?;
+        writer.flush()?;
+
+        Ok((csv, log))
+    }
+
+    /// One line per reply: client, invocation, time (ms) and latency (us)
+    pub fn write_csv<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "client,invocation,time_ms,latency_us")?;
+        for c in &self.completions {
+            writeln!(
+                writer,
+                "{},{},{},{}",
+                c.client, c.invocation, c.time_ms, c.latency_us
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The lines the simulation logs for the requests of the clients: 'request' when issued,
+    /// 'completed' when the reply arrived and the summary of all requests at the end. The requests
+    /// of the clients are numbered one after the other, starting with 1.
+    pub fn write_result_log<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut lines = Vec::new();
+        for c in &self.completions {
+            let id = c.client as u64 * self.requests as u64 + c.invocation + 1;
+            let issued = c.time_ms.saturating_sub(c.latency_us / 1000);
+            lines.push((issued, format!("{};-1;{};request", issued, id)));
+            lines.push((c.time_ms, format!("{};-1;{};completed", c.time_ms, id)));
+        }
+        lines.sort_by_key(|(time, _)| *time);
+        for (_, line) in &lines {
+            writeln!(writer, "{}", line)?;
+        }
+
+        let done = self.completions.len();
+        let latencies = self.completions.iter().map(|c| c.latency_us / 1000);
+        let mean = if done == 0 {
+            0.0
+        } else {
+            latencies.clone().sum::<u64>() as f64 / done as f64
+        };
+        let throughput = if self.duration_ms == 0 {
+            0.0
+        } else {
+            done as f64 * 1000.0 / self.duration_ms as f64
+        };
+        writeln!(
+            writer,
+            "{};-1;requests;{};{};{:.1};{};{:.2}",
+            self.duration_ms,
+            self.issued,
+            done,
+            mean,
+            latencies.max().unwrap_or(0),
+            throughput
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replies_of_the_counter_client_are_parsed() {
+        assert_eq!(
+            parse_reply("Invocation 3, returned value: 4, latency: 1250 us"),
+            Some((3, Some(1250)))
+        );
+        // clients built before the latency was printed
+        assert_eq!(
+            parse_reply("  Invocation 0, returned value: 1"),
+            Some((0, None))
+        );
+        assert_eq!(parse_reply("Invocation x, returned value: 1"), None);
+        assert_eq!(parse_reply("Invocation 1, value: 1"), None);
+        assert_eq!(parse_reply("Sending request"), None);
+    }
+
+    #[test]
+    fn result_log_is_written_in_the_format_of_the_simulation() {
+        let completion = |client, invocation, time_ms, latency_us| Completion {
+            client,
+            invocation,
+            time_ms,
+            latency_us,
+        };
+        let results = RoundResults {
+            nodes: 4,
+            round: 1,
+            requests: 2,
+            issued: 4,
+            duration_ms: 2000,
+            completions: vec![
+                completion(0, 0, 30, 20_000),
+                completion(1, 0, 25, 5_000),
+                completion(0, 1, 60, 30_000),
+            ],
+        };
+
+        let mut log = Vec::new();
+        results.write_result_log(&mut log).unwrap();
+        assert_eq!(
+            String::from_utf8(log).unwrap().lines().collect::<Vec<_>>(),
+            vec![
+                "10;-1;1;request",
+                "20;-1;3;request",
+                "25;-1;3;completed",
+                "30;-1;1;completed",
+                "30;-1;2;request",
+                "60;-1;2;completed",
+                "2000;-1;requests;4;3;18.3;30;1.50",
+            ]
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Supervision of the Java processes of a round. Every process is registered when it is started, so
+//! it can be checked while the round runs and is killed if it does not stop in time, also when the
+//! harness itself is stopped with Ctrl-C.
+
+use std::fmt;
+use std::io;
+use std::process::{self, Child, ChildStdout, Command, ExitStatus};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Interval of polling the processes while waiting for them to exit
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How a process ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exit {
+    /// The process exited by itself
+    Exited(ExitStatus),
+    /// The process was killed after it did not exit in time
+    Killed,
+}
+
+impl fmt::Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Exit::Exited(status) => write!(f, "{}", status),
+            Exit::Killed => write!(f, "killed after the shutdown timeout"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Supervised {
+    name: String,
+    child: Child,
+}
+
+/// The processes of a round, cloned handles share them
+#[derive(Debug, Clone, Default)]
+pub struct Supervisor {
+    processes: Arc<Mutex<Vec<Supervised>>>,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Supervisor::default()
+    }
+
+    /// Kills all processes and exits the harness on Ctrl-C
+    pub fn kill_on_interrupt(&self) -> Result<(), String> {
+        let supervisor = self.clone();
+        ctrlc::set_handler(move || {
+            println!("Interrupted, killing all processes");
+            supervisor.kill_all();
+            process::exit(130);
+        })
+        .map_err(|e| format!("Failed to handle Ctrl-C: {}", e))
+    }
+
+    /// Starts `command` as the process `name`, returns its stdout if it is piped
+    pub fn spawn(&self, name: &str, command: &mut Command) -> io::Result<Option<ChildStdout>> {
+        let mut child = command.spawn()?;
+        let stdout = child.stdout.take();
+        self.lock().push(Supervised {
+            name: name.to_string(),
+            child,
+        });
+        Ok(stdout)
+    }
+
+    /// Fails with the first process that already exited
+    pub fn check_health(&self) -> Result<(), String> {
+        for process in self.lock().iter_mut() {
+            match process.child.try_wait() {
+                Ok(None) => {}
+                Ok(Some(status)) => {
+                    return Err(format!("{} exited unexpectedly ({})", process.name, status))
+                }
+                Err(e) => return Err(format!("Failed to check {}: {}", process.name, e)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits up to `timeout` for all processes to exit and kills the remaining ones, returns how
+    /// each process ended. The processes are no longer supervised afterwards.
+    pub fn shutdown(&self, timeout: Duration) -> Vec<(String, Exit)> {
+        let deadline = Instant::now() + timeout;
+        let mut exits = Vec::new();
+
+        loop {
+            {
+                let mut processes = self.lock();
+                let overdue = Instant::now() >= deadline;
+                let mut i = 0;
+                while i < processes.len() {
+                    let exit = match processes[i].child.try_wait() {
+                        Ok(Some(status)) => Some(Exit::Exited(status)),
+                        _ if overdue => {
+                            let _ = processes[i].child.kill();
+                            let _ = processes[i].child.wait();
+                            Some(Exit::Killed)
+                        }
+                        _ => None,
+                    };
+                    match exit {
+                        Some(exit) => exits.push((processes.remove(i).name, exit)),
+                        None => i += 1,
+                    }
+                }
+                if processes.is_empty() {
+                    return exits;
+                }
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Kills all processes right away
+    pub fn kill_all(&self) {
+        for mut process in self.lock().drain(..) {
+            let _ = process.child.kill();
+            let _ = process.child.wait();
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Supervised>> {
+        // a panic while holding the lock does not make the list of processes invalid
+        self.processes
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -0,0 +1,60 @@
+/***************************************************************************************************
+Benchmarks of the primitives behind the crypto costs: the digests SHA-256 and BLAKE3, the keyed
+BLAKE3 used as MAC and ed25519 signatures. Compare them with mac_cost, sign_cost and verify_cost of
+the [crypto] section, or let the simulation measure them itself with 'crypto.calibrate'.
+***************************************************************************************************/
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use mc_utils::crypto::digest::{self, DigestAlgorithm};
+use mc_utils::crypto::ed25519::SigningKey;
+
+const MESSAGE_SIZES: [usize; 3] = [64, 1024, 16384];
+
+/// SHA-256 against BLAKE3 on messages of growing size
+fn digests(c: &mut Criterion) {
+    let mut group = c.benchmark_group("digest");
+    for &size in MESSAGE_SIZES.iter() {
+        let message = vec![0x5a; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        for &algorithm in [DigestAlgorithm::Sha256, DigestAlgorithm::Blake3].iter() {
+            group.bench_with_input(
+                BenchmarkId::new(format!("{:?}", algorithm), size),
+                &message,
+                |b, message| b.iter(|| black_box(algorithm.digest(message))),
+            );
+        }
+    }
+    group.finish();
+}
+
+/// The keyed BLAKE3 MAC of a message
+fn mac(c: &mut Criterion) {
+    let key = [7; 32];
+    let mut group = c.benchmark_group("mac");
+    for &size in MESSAGE_SIZES.iter() {
+        let message = vec![0x5a; size];
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &message, |b, message| {
+            b.iter(|| black_box(digest::mac(&key, message)))
+        });
+    }
+    group.finish();
+}
+
+/// Signing and verifying a message with ed25519
+fn ed25519(c: &mut Criterion) {
+    let key = SigningKey::from_seed(&[7; 32]);
+    let verifying_key = key.verifying_key();
+    let message = vec![0x5a; MESSAGE_SIZES[1]];
+    let signature = key.sign(&message, None);
+
+    let mut group = c.benchmark_group("ed25519");
+    group.bench_function("sign", |b| b.iter(|| black_box(key.sign(&message, None))));
+    group.bench_function("verify", |b| {
+        b.iter(|| black_box(verifying_key.valid_sig(&message, None, &signature)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, digests, mac, ed25519);
+criterion_main!(benches);
@@ -0,0 +1,166 @@
+/***************************************************************************************************
+Benchmarks of the hot paths of the simulation: the event queue, the quorum handling of PBFT, the
+network and a complete run. Run them with `cargo bench` before and after changes to the scheduler
+or the protocols to detect performance regressions.
+***************************************************************************************************/
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+use rand::Rng;
+
+use bft_simulation::network::Network;
+use bft_simulation::node::pbft::messages::{
+    ClientRequest, CommitMessage, PBFTMessage, PrePrepareMessage, PrepareMessage,
+};
+use bft_simulation::node::pbft::state::ReplicaState;
+use bft_simulation::node::request::RequestId;
+use bft_simulation::simulation::config::PBFT_CLIENT_ID;
+use bft_simulation::simulation::event::{Broadcast, Event, Message};
+use bft_simulation::simulation::run_simulation;
+use bft_simulation::simulation::scheduler::SchedulerKind;
+use bft_simulation::simulation::settings::Settings;
+use bft_simulation::simulation::time::Time;
+
+const QUEUE_EVENTS: u64 = 1_000_000;
+const CLUSTER_SIZES: [u32; 5] = [4, 10, 25, 50, 100];
+const RUN_REQUESTS: u32 = 10_000;
+
+/// Pushes events with random times into the queue and pops all of them in order
+fn event_queue(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let times: Vec<u64> = (0..QUEUE_EVENTS)
+        .map(|_| rng.gen_range(0, QUEUE_EVENTS))
+        .collect();
+
+    let mut group = c.benchmark_group("event_queue");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(QUEUE_EVENTS));
+    let events = || {
+        times
+            .iter()
+            .map(|time| Event::new_broadcast(1, 2, Message::Dummy, Time::new(*time)))
+            .collect::<Vec<Event>>()
+    };
+    group.bench_function("push_pop", |b| {
+        b.iter_batched(
+            events,
+            |events| {
+                let mut queue = BinaryHeap::with_capacity(events.len());
+                for event in events {
+                    queue.push(Reverse(event));
+                }
+                while let Some(Reverse(event)) = queue.pop() {
+                    black_box(event);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    // the schedulers of `simulation.scheduler`
+    for (name, kind) in &[
+        ("heap", SchedulerKind::Heap),
+        ("buckets", SchedulerKind::Buckets),
+        ("calendar", SchedulerKind::Calendar),
+    ] {
+        group.bench_function(BenchmarkId::new("scheduler", name), |b| {
+            b.iter_batched(
+                events,
+                |events| {
+                    let mut queue = kind.build();
+                    for event in events {
+                        queue.push(event);
+                    }
+                    while let Some(event) = queue.pop_next() {
+                        black_box(event);
+                    }
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// The messages a backup receives to commit a single request in a cluster of `n` replicas
+fn agreement_messages(n: u32) -> Vec<PBFTMessage> {
+    let c_req = ClientRequest::new(RequestId::new(PBFT_CLIENT_ID, 1), 1);
+    let mut messages = vec![PBFTMessage::PrePrepare(PrePrepareMessage::new(
+        c_req, 1, 1, 1,
+    ))];
+    messages.extend((3..=n).map(|id| PBFTMessage::Prepare(PrepareMessage::new(c_req, 1, 1, id))));
+    messages.extend(
+        (1..=n)
+            .filter(|id| *id != 2)
+            .map(|id| PBFTMessage::Commit(CommitMessage::new(c_req, 1, 1, id))),
+    );
+    messages
+}
+
+/// A backup collects the prepare and commit quorums of a single request
+fn pbft_quorum(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pbft_quorum");
+    for &n in CLUSTER_SIZES.iter() {
+        let messages = agreement_messages(n);
+        group.throughput(Throughput::Elements(messages.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &messages, |b, messages| {
+            b.iter_batched(
+                || (ReplicaState::new(2, n), messages.clone()),
+                |(mut replica, messages)| {
+                    for message in messages {
+                        black_box(replica.handle_message(message, Time::new(1)).unwrap());
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// A replica sends a message to every other replica
+fn network_broadcast(c: &mut Criterion) {
+    let settings = Settings::default();
+    let mut group = c.benchmark_group("network_broadcast");
+    for &n in CLUSTER_SIZES.iter() {
+        let mut network = Network::new(n, &settings.network);
+        let message = Message::Dummy;
+        group.throughput(Throughput::Elements(u64::from(n - 1)));
+        group.bench_function(BenchmarkId::from_parameter(n), |b| {
+            b.iter(|| {
+                for to in 2..=n {
+                    let broadcast = Broadcast::new(1, to, message.clone());
+                    black_box(network.handle_broadcast(Time::new(1), broadcast));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+/// A complete PBFT run of four replicas
+fn end_to_end(c: &mut Criterion) {
+    let mut settings = Settings::default();
+    settings.simulation.requests = RUN_REQUESTS;
+    settings.simulation.liveness_bound = 0;
+
+    let mut group = c.benchmark_group("end_to_end");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(u64::from(RUN_REQUESTS)));
+    group.bench_function("pbft_4_replicas", |b| {
+        b.iter(|| black_box(run_simulation(settings.clone())))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    event_queue,
+    pbft_quorum,
+    network_broadcast,
+    end_to_end
+);
+criterion_main!(benches);
@@ -0,0 +1,10 @@
+// Generates the messages and the service of the gRPC control API, see 'proto/control.proto'. Only
+// built with the feature 'grpc', the other builds need neither protoc nor its outputs.
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/control.proto");
+        tonic_build::compile_protos("proto/control.proto")
+            .unwrap_or_else(|e| panic!("Failed to compile proto/control.proto: {}", e));
+    }
+}
@@ -0,0 +1,38 @@
+/***************************************************************************************************
+Feeds arbitrary sequences of PBFT messages into a replica of a cluster of four. Invalid messages
+must be reported as `ProtocolError`, the replica must neither panic nor execute backwards. Run it
+with `cargo fuzz run pbft_replica` from the crate directory.
+***************************************************************************************************/
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+use bft_simulation::node::pbft::messages::PBFTMessage;
+use bft_simulation::node::pbft::state::ReplicaState;
+use bft_simulation::simulation::time::Time;
+
+const NODES: u32 = 4;
+
+/// The receiving replica and its receptions, each after a delay in microseconds
+#[derive(Debug, Arbitrary)]
+struct Input {
+    id: u8,
+    receptions: Vec<(u16, PBFTMessage)>,
+}
+
+fuzz_target!(|input: Input| {
+    let mut replica = ReplicaState::new(u32::from(input.id) % NODES + 1, NODES);
+    let mut time = 0;
+    for (delay, message) in input.receptions {
+        time += u64::from(delay);
+        let last_executed = replica.last_executed();
+        let executed = replica.executed().len();
+
+        let _ = replica.handle_message(message, Time::from_micros(time));
+
+        assert!(replica.last_executed() >= last_executed);
+        assert!(replica.executed().len() >= executed);
+    }
+});
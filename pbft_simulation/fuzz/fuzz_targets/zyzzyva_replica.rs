@@ -0,0 +1,42 @@
+/***************************************************************************************************
+Feeds arbitrary sequences of Zyzzyva messages into a replica of a cluster of four. Invalid messages
+must be reported as `ProtocolError`, the replica must not panic. Speculative executions may be
+rolled back, only the committed history is checked. Run it with `cargo fuzz run zyzzyva_replica`
+from the crate directory.
+***************************************************************************************************/
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+use bft_simulation::node::zyzzyva::messages::ZyzzyvaMessage;
+use bft_simulation::node::zyzzyva::state::State;
+use bft_simulation::simulation::time::Time;
+
+const NODES: u32 = 4;
+
+/// The receiving replica, whether it executes speculatively and its receptions, each after a
+/// delay in microseconds
+#[derive(Debug, Arbitrary)]
+struct Input {
+    id: u8,
+    speculative: bool,
+    receptions: Vec<(u16, ZyzzyvaMessage)>,
+}
+
+fuzz_target!(|input: Input| {
+    let mut replica =
+        State::new(u32::from(input.id) % NODES + 1, NODES).speculative_execution(input.speculative);
+    let mut time = 0;
+    for (delay, message) in input.receptions {
+        time += u64::from(delay);
+        let last_executed = replica.last_executed();
+
+        let _ = replica.handle_message(message, Time::from_micros(time));
+
+        if !input.speculative {
+            assert!(replica.last_executed() >= last_executed);
+        }
+    }
+});
@@ -0,0 +1,147 @@
+//! SHA-256 and BLAKE3 digests of requests and batches of requests
+
+extern crate blake3;
+extern crate sha2;
+
+use self::sha2::{Digest as _, Sha256};
+use std::str::FromStr;
+
+/// Length of the digests in bytes
+pub const DIGEST_LENGTH: usize = 32;
+
+/// A digest of `DIGEST_LENGTH` bytes
+pub type Digest = [u8; DIGEST_LENGTH];
+
+/// The hash function of the digests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    /// SHA-256
+    Sha256,
+    /// BLAKE3, several times faster than SHA-256 on long inputs
+    Blake3,
+}
+
+impl FromStr for DigestAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha256" => Ok(DigestAlgorithm::Sha256),
+            "blake3" => Ok(DigestAlgorithm::Blake3),
+            _ => Err(format!(
+                "digest '{}' is not available, allowed are 'sha256', 'blake3'",
+                s
+            )),
+        }
+    }
+}
+
+impl DigestAlgorithm {
+    /// The digest of a request
+    pub fn digest(self, data: &[u8]) -> Digest {
+        match self {
+            DigestAlgorithm::Sha256 => sha256(data),
+            DigestAlgorithm::Blake3 => blake3(data),
+        }
+    }
+
+    /// The digest of a batch of requests. Every request is prefixed with its length, so batches
+    /// that split the same bytes differently have different digests.
+    pub fn digest_batch<'a, I>(self, requests: I) -> Digest
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        match self {
+            DigestAlgorithm::Sha256 => {
+                let mut hasher = Sha256::new();
+                for request in requests {
+                    hasher.update((request.len() as u64).to_be_bytes());
+                    hasher.update(request);
+                }
+                to_digest(&hasher.finalize())
+            }
+            DigestAlgorithm::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                for request in requests {
+                    hasher.update(&(request.len() as u64).to_be_bytes());
+                    hasher.update(request);
+                }
+                hasher.finalize().into()
+            }
+        }
+    }
+}
+
+/// The SHA-256 digest of `data`
+pub fn sha256(data: &[u8]) -> Digest {
+    to_digest(&Sha256::digest(data))
+}
+
+/// The BLAKE3 digest of `data`
+pub fn blake3(data: &[u8]) -> Digest {
+    blake3::hash(data).into()
+}
+
+/// A MAC of `data` with the shared `key`, keyed BLAKE3
+pub fn mac(key: &[u8; 32], data: &[u8]) -> Digest {
+    blake3::keyed_hash(key, data).into()
+}
+
+fn to_digest(bytes: &[u8]) -> Digest {
+    let mut digest = [0; DIGEST_LENGTH];
+    digest.copy_from_slice(bytes);
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(digest: &[u8]) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn digests_match_the_known_vectors() {
+        assert_eq!(
+            hex(&sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex(&DigestAlgorithm::Sha256.digest(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex(&blake3(b"")),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert_eq!(
+            hex(&DigestAlgorithm::Blake3.digest(b"abc")),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+    }
+
+    #[test]
+    fn batches_that_split_the_same_bytes_differ() {
+        for &algorithm in [DigestAlgorithm::Sha256, DigestAlgorithm::Blake3].iter() {
+            let batch = |requests: &[&[u8]]| algorithm.digest_batch(requests.iter().copied());
+            assert_eq!(batch(&[b"ab", b"c"]), batch(&[b"ab", b"c"]));
+            assert_ne!(batch(&[b"ab", b"c"]), batch(&[b"a", b"bc"]));
+            assert_ne!(batch(&[b"abc"]), algorithm.digest(b"abc"));
+        }
+    }
+
+    #[test]
+    fn macs_depend_on_the_key() {
+        assert_eq!(mac(&[1; 32], b"request"), mac(&[1; 32], b"request"));
+        assert_ne!(mac(&[1; 32], b"request"), mac(&[2; 32], b"request"));
+        assert_ne!(mac(&[1; 32], b"request"), blake3(b"request"));
+    }
+
+    #[test]
+    fn algorithms_are_parsed() {
+        assert_eq!("sha256".parse(), Ok(DigestAlgorithm::Sha256));
+        assert_eq!("blake3".parse(), Ok(DigestAlgorithm::Blake3));
+        assert!("md5".parse::<DigestAlgorithm>().is_err());
+    }
+}
@@ -0,0 +1,136 @@
+//! ed25519 signatures, without OpenSSL
+
+extern crate ed25519_dalek;
+
+use self::ed25519_dalek::{Keypair, PublicKey, SecretKey, Signature, Signer, Verifier};
+use std::convert::TryFrom;
+
+pub use self::ed25519_dalek::{PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
+
+/// The key pair of a signer, derived from a seed, e.g. the id of a node and the seed of a run
+pub struct SigningKey {
+    keypair: Keypair,
+}
+
+impl SigningKey {
+    /// The key pair of the secret key `seed`
+    pub fn from_seed(seed: &[u8; 32]) -> Self {
+        let secret = SecretKey::from_bytes(seed).expect("A secret key has 32 bytes");
+        let public = PublicKey::from(&secret);
+        SigningKey {
+            keypair: Keypair { secret, public },
+        }
+    }
+
+    /// The public key the signatures are verified with
+    pub fn verifying_key(&self) -> VerifyingKey {
+        VerifyingKey {
+            public: self.keypair.public,
+        }
+    }
+
+    /// sign data1 and optional data2
+    pub fn sign(&self, data1: &[u8], data2: Option<&[u8]>) -> [u8; SIGNATURE_LENGTH] {
+        let signature = match data2 {
+            Some(data2) => self.keypair.sign(&[data1, data2].concat()),
+            None => self.keypair.sign(data1),
+        };
+        signature.to_bytes()
+    }
+}
+
+/// The public key of a signer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifyingKey {
+    public: PublicKey,
+}
+
+impl VerifyingKey {
+    /// The key of its encoding, `None` if the bytes are not a point of the curve
+    pub fn from_bytes(bytes: &[u8; PUBLIC_KEY_LENGTH]) -> Option<Self> {
+        PublicKey::from_bytes(bytes)
+            .ok()
+            .map(|public| VerifyingKey { public })
+    }
+
+    /// The encoding of the key
+    pub fn to_bytes(&self) -> [u8; PUBLIC_KEY_LENGTH] {
+        self.public.to_bytes()
+    }
+
+    /// if signature 'sig' on data1 and optional data2 is valid for this key
+    pub fn valid_sig(&self, data1: &[u8], data2: Option<&[u8]>, sig: &[u8]) -> bool {
+        let signature = match Signature::try_from(sig) {
+            Ok(signature) => signature,
+            Err(_) => return false,
+        };
+        let verified = match data2 {
+            Some(data2) => self.public.verify(&[data1, data2].concat(), &signature),
+            None => self.public.verify(data1, &signature),
+        };
+        verified.is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn signatures_match_the_known_vector() {
+        // test 1 of RFC 8032, section 7.1
+        let mut seed = [0; 32];
+        seed.copy_from_slice(&from_hex(
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+        ));
+        let key = SigningKey::from_seed(&seed);
+
+        assert_eq!(
+            key.verifying_key().to_bytes().to_vec(),
+            from_hex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a")
+        );
+        assert_eq!(
+            key.sign(b"", None).to_vec(),
+            from_hex(
+                "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bac\
+                 c61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"
+            )
+        );
+    }
+
+    #[test]
+    fn signatures_are_verified() {
+        let key = SigningKey::from_seed(&[7; 32]);
+        let verifying_key = VerifyingKey::from_bytes(&key.verifying_key().to_bytes()).unwrap();
+
+        let signature = key.sign(b"request", None);
+        assert!(verifying_key.valid_sig(b"request", None, &signature));
+        // the optional second part is signed as if it was appended to the first
+        let signature = key.sign(b"req", Some(b"uest"));
+        assert!(verifying_key.valid_sig(b"req", Some(b"uest"), &signature));
+        assert!(verifying_key.valid_sig(b"request", None, &signature));
+    }
+
+    #[test]
+    fn tampered_signatures_are_rejected() {
+        let key = SigningKey::from_seed(&[7; 32]).verifying_key();
+        let signature = SigningKey::from_seed(&[7; 32]).sign(b"request", None);
+
+        assert!(!key.valid_sig(b"requesT", None, &signature));
+        assert!(!key.valid_sig(b"request", Some(b"!"), &signature));
+        let mut tampered = signature;
+        tampered[0] ^= 1;
+        assert!(!key.valid_sig(b"request", None, &tampered));
+        assert!(!key.valid_sig(b"request", None, &signature[1..]));
+
+        let other = SigningKey::from_seed(&[8; 32]).verifying_key();
+        assert!(!other.valid_sig(b"request", None, &signature));
+    }
+}
@@ -0,0 +1,12 @@
+//! consist of some cryptograhpic-helpers: signatures with the keys of OpenSSL (feature 'crypto'),
+//! ed25519 signatures and the digests of requests and batches in pure Rust (feature 'rust-crypto')
+
+#[cfg(feature = "rust-crypto")]
+pub mod digest;
+#[cfg(feature = "rust-crypto")]
+pub mod ed25519;
+#[cfg(feature = "crypto")]
+mod pkey;
+
+#[cfg(feature = "crypto")]
+pub use self::pkey::{sign, valid_sig};
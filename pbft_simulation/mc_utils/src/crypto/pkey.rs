@@ -1,4 +1,4 @@
-//! consist of some cryptograhpic-helpers
+//! signatures with the keys of OpenSSL
 
 extern crate openssl;
 
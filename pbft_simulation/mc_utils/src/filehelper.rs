@@ -5,10 +5,11 @@ extern crate log;
 
 use self::log::{debug, error};
 use glob::glob;
+use std::ffi::OsString;
 use std::fs;
 use std::io;
 use std::io::{BufRead, Read, Write};
-use std::path;
+use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
 ///
@@ -35,41 +36,50 @@ impl FileHelper {
     }
 
     /// Deletes a directory
-    pub fn delete_dir(dir: &str) -> Result<(), io::Error> {
-        debug!("Deleting directory: {}", dir);
+    pub fn delete_dir<P: AsRef<Path>>(dir: P) -> Result<(), io::Error> {
+        let dir = dir.as_ref();
+        debug!("Deleting directory: {}", dir.display());
         fs::remove_dir_all(dir)?;
         Ok(())
     }
 
-    /// Writes the content to a file (Creates it, if it doesn't exist and overwrites, if it does)
-    pub fn write_to_file(file_path: &str, content: &str) -> Result<(), io::Error> {
-        debug!("Creating new file: {}", file_path);
-        let mut only_path = String::new();
-        let length = file_path.split('/').count();
-        for (i, v) in file_path.split('/').enumerate() {
-            if v.is_empty() || i == length - 1 {
-                continue;
-            }
-            only_path = only_path + v + "/";
+    /// Creates the directories of the path of a file, if they don't exist
+    pub fn create_parent_dirs<P: AsRef<Path>>(file_path: P) -> Result<(), io::Error> {
+        match file_path.as_ref().parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => fs::create_dir_all(dir),
+            _ => Ok(()),
         }
+    }
 
-        // debug!("Creating path '{}'", only_path);
-        fs::create_dir_all(only_path)?;
-        let mut file = fs::File::create(file_path)?;
-        file.write_all(content.as_bytes())?;
-        Ok(())
+    /// Writes the content to a file (Creates it and its directories, if they don't exist and
+    /// overwrites it, if it does)
+    pub fn write_to_file<P: AsRef<Path>>(file_path: P, content: &str) -> Result<(), io::Error> {
+        let file_path = file_path.as_ref();
+        debug!("Creating new file: {}", file_path.display());
+        FileHelper::create_parent_dirs(file_path)?;
+        fs::write(file_path, content)
+    }
+
+    /// Writes the content to a file like `write_to_file`, but atomically: readers see either the
+    /// old or the whole new content, even if the process dies while writing (see `AtomicFile`)
+    pub fn write_atomic<P: AsRef<Path>>(file_path: P, content: &[u8]) -> Result<(), io::Error> {
+        let mut file = AtomicFile::create(file_path)?;
+        file.write_all(content)?;
+        file.commit()
     }
 
     /// Removes a file
-    pub fn remove_file(file_path: &str) -> Result<(), io::Error> {
-        debug!("Deleting file: {}", file_path);
+    pub fn remove_file<P: AsRef<Path>>(file_path: P) -> Result<(), io::Error> {
+        let file_path = file_path.as_ref();
+        debug!("Deleting file: {}", file_path.display());
         fs::remove_file(file_path)?;
         Ok(())
     }
 
     /// Appends the content to a file (Creates it, if it doesn't exist)
-    pub fn append_to_file(file_path: &str, content: &str) -> Result<(), io::Error> {
-        debug!("Writing to file: {}", file_path);
+    pub fn append_to_file<P: AsRef<Path>>(file_path: P, content: &str) -> Result<(), io::Error> {
+        let file_path = file_path.as_ref();
+        debug!("Writing to file: {}", file_path.display());
         let mut file = fs::OpenOptions::new()
             .append(true)
             .create(true)
@@ -79,8 +89,9 @@ impl FileHelper {
     }
 
     /// Appends the content to a file and adds a newline afterwards (Creates the file, if it doesn't exist)
-    pub fn append_to_file_ln(file_path: &str, content: &str) -> Result<(), io::Error> {
-        debug!("Writing to file: {}", file_path);
+    pub fn append_to_file_ln<P: AsRef<Path>>(file_path: P, content: &str) -> Result<(), io::Error> {
+        let file_path = file_path.as_ref();
+        debug!("Writing to file: {}", file_path.display());
         let mut file = fs::OpenOptions::new()
             .append(true)
             .create(true)
@@ -91,12 +102,12 @@ impl FileHelper {
     }
 
     /// Returns all files in a directory
-    pub fn get_all_files_in(path: &str) -> Result<Vec<fs::DirEntry>, io::Error> {
-        fs::read_dir(&path)?.collect()
+    pub fn get_all_files_in<P: AsRef<Path>>(path: P) -> Result<Vec<fs::DirEntry>, io::Error> {
+        fs::read_dir(path)?.collect()
     }
 
     /// Creates an empty file
-    pub fn create_file(file_path: &str) -> Result<(), io::Error> {
+    pub fn create_file<P: AsRef<Path>>(file_path: P) -> Result<(), io::Error> {
         let _ = fs::OpenOptions::new()
             .write(true)
             .create(true)
@@ -106,32 +117,38 @@ impl FileHelper {
     }
 
     /// Returns the 'SystemTime' of the 'last modified' field of the file
-    pub fn get_last_modified(file_path: &str) -> Result<SystemTime, io::Error> {
+    pub fn get_last_modified<P: AsRef<Path>>(file_path: P) -> Result<SystemTime, io::Error> {
         let metadata = fs::metadata(file_path)?;
         metadata.modified()
     }
 
     /// Copies a file
-    pub fn copy_file(file_from: &str, file_to: &str) -> io::Result<()> {
-        debug!("Copying {} to {}", file_from, file_to);
+    pub fn copy_file<P: AsRef<Path>, Q: AsRef<Path>>(file_from: P, file_to: Q) -> io::Result<()> {
+        let (file_from, file_to) = (file_from.as_ref(), file_to.as_ref());
+        debug!("Copying {} to {}", file_from.display(), file_to.display());
         fs::copy(file_from, file_to)?;
         Ok(())
     }
 
     /// Renames a file
-    pub fn rename_file(file_from: &str, file_to: &str) -> Result<(), io::Error> {
-        debug!("Renaming {} to {}", file_from, file_to);
+    pub fn rename_file<P: AsRef<Path>, Q: AsRef<Path>>(
+        file_from: P,
+        file_to: Q,
+    ) -> Result<(), io::Error> {
+        let (file_from, file_to) = (file_from.as_ref(), file_to.as_ref());
+        debug!("Renaming {} to {}", file_from.display(), file_to.display());
         fs::rename(file_from, file_to)
     }
 
     /// Returns true if the file exists
-    pub fn file_exists(file_path: &str) -> bool {
-        path::Path::new(file_path).exists()
+    pub fn file_exists<P: AsRef<Path>>(file_path: P) -> bool {
+        file_path.as_ref().exists()
     }
 
     /// Reads the content of a file and returns a String (using String::from_utf8())
-    pub fn read_file_to_string(file_path: &str) -> Result<String, io::Error> {
-        debug!("Reading content of {}", file_path);
+    pub fn read_file_to_string<P: AsRef<Path>>(file_path: P) -> Result<String, io::Error> {
+        let file_path = file_path.as_ref();
+        debug!("Reading content of {}", file_path.display());
         let mut content: Vec<u8> = Vec::new();
         let mut file = fs::OpenOptions::new().read(true).open(file_path)?;
         let _ = file.read_to_end(&mut content)?;
@@ -145,8 +162,8 @@ impl FileHelper {
     }
 
     /// Reads the content of a file from a given position and return a String (using String::from_utf8())
-    pub fn read_file_from_position_to_string(
-        file_path: &str,
+    pub fn read_file_from_position_to_string<P: AsRef<Path>>(
+        file_path: P,
         idx: usize,
         content: &mut String,
     ) -> Result<usize, io::Error> {
@@ -174,8 +191,191 @@ impl FileHelper {
     }
 
     /// Returns the amount of lines of a file
-    pub fn amount_of_lines_in_file(file_path: &str) -> Result<usize, io::Error> {
+    pub fn amount_of_lines_in_file<P: AsRef<Path>>(file_path: P) -> Result<usize, io::Error> {
         let file = fs::File::open(file_path)?;
         Ok(io::BufReader::new(file).lines().count())
     }
 }
+
+/// A file that is written to a temporary file next to it and moved into place by `commit`, so its
+/// path never holds a partly written file. A file dropped without `commit` is discarded.
+#[derive(Debug)]
+pub struct AtomicFile {
+    path: PathBuf,
+    temp_path: PathBuf,
+    /// The temporary file until it is committed
+    file: Option<fs::File>,
+}
+
+impl AtomicFile {
+    /// Creates the temporary file of the file at `file_path` and the missing directories
+    pub fn create<P: AsRef<Path>>(file_path: P) -> Result<Self, io::Error> {
+        let path = file_path.as_ref().to_path_buf();
+        let name = path.file_name().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not the path of a file", path.display()),
+            )
+        })?;
+        let mut temp_name = OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(".tmp");
+        let temp_path = path.with_file_name(temp_name);
+
+        FileHelper::create_parent_dirs(&path)?;
+        let file = fs::File::create(&temp_path)?;
+        Ok(AtomicFile {
+            path,
+            temp_path,
+            file: Some(file),
+        })
+    }
+
+    /// The path the file is moved to by `commit`
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes the content to the disk and replaces the file at `path` with it, the temporary file
+    /// is removed if that fails
+    pub fn commit(mut self) -> Result<(), io::Error> {
+        let file = self.file.take().expect("An AtomicFile is committed once");
+        let synced = file.sync_all();
+        // the file is closed before it is moved, some platforms cannot move open files
+        drop(file);
+        debug!(
+            "Moving {} to {}",
+            self.temp_path.display(),
+            self.path.display()
+        );
+        let result = synced.and_then(|()| fs::rename(&self.temp_path, &self.path));
+        if result.is_err() {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+        result
+    }
+
+    fn file(&mut self) -> &mut fs::File {
+        self.file
+            .as_mut()
+            .expect("An AtomicFile is only written to until it is committed")
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file().flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+/*******************************************************************************
+ * TESTS
+ ******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // an empty directory of the test `name`
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mc_utils_filehelper_{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn atomic_file_is_in_place_once_committed() {
+        let dir = test_dir("commit");
+        let path = dir.join("nested").join("result.json");
+
+        let mut file = AtomicFile::create(&path).unwrap();
+        file.write_all(b"{}").unwrap();
+        assert_eq!(file.path(), path.as_path());
+        assert!(!path.exists());
+        file.commit().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{}");
+        // the temporary file is gone
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn atomic_file_dropped_without_commit_is_discarded() {
+        let dir = test_dir("drop");
+        let path = dir.join("result.json");
+        FileHelper::write_to_file(&path, "old").unwrap();
+
+        let mut file = AtomicFile::create(&path).unwrap();
+        file.write_all(b"new").unwrap();
+        drop(file);
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn atomic_file_replaces_an_existing_file() {
+        let dir = test_dir("overwrite");
+        let path = dir.join("result.json");
+        FileHelper::write_to_file(&path, "a longer old content").unwrap();
+
+        FileHelper::write_atomic(&path, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(dir).unwrap();
+
+        assert!(AtomicFile::create(Path::new("/")).is_err());
+    }
+
+    #[test]
+    fn files_are_addressed_by_paths() {
+        let dir = test_dir("paths");
+        let path = dir.join("sub").join("log.txt");
+
+        FileHelper::write_to_file(&path, "first\n").unwrap();
+        FileHelper::append_to_file(&path, "second").unwrap();
+        FileHelper::append_to_file_ln(path.as_path(), "").unwrap();
+        FileHelper::append_to_file_ln(&path, "third").unwrap();
+        assert!(FileHelper::file_exists(&path));
+        assert_eq!(
+            FileHelper::read_file_to_string(&path).unwrap(),
+            "first\nsecond\nthird\n"
+        );
+        assert_eq!(FileHelper::amount_of_lines_in_file(&path).unwrap(), 3);
+
+        let mut rest = String::new();
+        assert_eq!(
+            FileHelper::read_file_from_position_to_string(&path, 6, &mut rest).unwrap(),
+            19
+        );
+        assert_eq!(rest, "second\nthird\n");
+        assert!(FileHelper::read_file_from_position_to_string(&path, 20, &mut rest).is_err());
+
+        let copy = dir.join("sub").join("copy.txt");
+        let renamed = dir.join("renamed.txt");
+        FileHelper::copy_file(&path, &copy).unwrap();
+        FileHelper::rename_file(&copy, &renamed).unwrap();
+        assert!(!FileHelper::file_exists(&copy));
+        assert_eq!(FileHelper::get_all_files_in(&dir).unwrap().len(), 2);
+
+        FileHelper::remove_file(&renamed).unwrap();
+        FileHelper::create_file(&path).unwrap();
+        assert_eq!(FileHelper::read_file_to_string(&path).unwrap(), "");
+        FileHelper::delete_dir(&dir).unwrap();
+        assert!(!FileHelper::file_exists(&dir));
+    }
+}
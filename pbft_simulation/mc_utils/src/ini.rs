@@ -5,11 +5,12 @@ extern crate tini;
 
 use log::debug;
 pub use std::env;
+use std::fmt;
 use std::fmt::Debug;
 use std::fs::File;
 use std::io::Read;
 use std::str::FromStr;
-use tini::Ini;
+pub use tini::Ini;
 
 /// get the ini file reference
 pub fn get_ini(path: &str) -> Ini {
@@ -108,3 +109,523 @@ where
     }
     result
 }
+
+/// Source of the values a `Schema` is loaded from, e.g. an ini or a config file in another format
+pub trait ConfigSource {
+    /// Returns the value of `[section]key` as text, `None` if the key is missing
+    fn value(&self, section: &str, key: &str) -> Option<String>;
+
+    /// Returns the names of the sections in the source, so sections unknown to a `Schema` (e.g.
+    /// misspelled ones) are reported. Sources that cannot list their sections return none.
+    fn sections(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+impl ConfigSource for Ini {
+    fn value(&self, section: &str, key: &str) -> Option<String> {
+        self.get(section, key)
+    }
+
+    fn sections(&self) -> Vec<String> {
+        self.iter().map(|(name, _)| name.to_string()).collect()
+    }
+}
+
+/// Type of the value of a key in a `Schema`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueType {
+    /// 'true' or 'false'
+    Bool,
+    /// Signed integer
+    Int,
+    /// Unsigned integer
+    UInt,
+    /// Floating point number
+    Float,
+    /// Any text
+    Text,
+    /// One of the given words
+    OneOf(Vec<&'static str>),
+    /// Whitespace separated list of values of the inner type, at least one
+    List(Box<ValueType>),
+}
+
+impl ValueType {
+    /// Checks that `value` is of this type
+    fn check(&self, value: &str) -> Result<(), String> {
+        let valid = match self {
+            ValueType::Bool => value.parse::<bool>().is_ok(),
+            ValueType::Int => value.parse::<i64>().is_ok(),
+            ValueType::UInt => value.parse::<u64>().is_ok(),
+            ValueType::Float => value.parse::<f64>().is_ok(),
+            ValueType::Text => true,
+            ValueType::OneOf(words) => words.contains(&value),
+            ValueType::List(inner) => {
+                value.split_whitespace().next().is_some()
+                    && value.split_whitespace().all(|v| inner.check(v).is_ok())
+            }
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(self.to_string())
+        }
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValueType::Bool => write!(f, "'true' or 'false'"),
+            ValueType::Int => write!(f, "an integer"),
+            ValueType::UInt => write!(f, "an unsigned integer"),
+            ValueType::Float => write!(f, "a number"),
+            ValueType::Text => write!(f, "a text"),
+            ValueType::OneOf(words) => write!(f, "one of '{}'", words.join("', '")),
+            ValueType::List(inner) => write!(f, "a list of {}", inner),
+        }
+    }
+}
+
+/// Definition of a key in a `Schema`. Keys without a default are required.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyDef {
+    section: &'static str,
+    key: &'static str,
+    value_type: ValueType,
+    default: Option<&'static str>,
+    range: Option<(f64, f64)>,
+    keywords: Vec<&'static str>,
+}
+
+impl KeyDef {
+    /// Defines the required key `[section]key`
+    pub fn new(section: &'static str, key: &'static str, value_type: ValueType) -> Self {
+        KeyDef {
+            section,
+            key,
+            value_type,
+            default: None,
+            range: None,
+            keywords: Vec::new(),
+        }
+    }
+
+    /// Makes the key optional, `value` is used if the ini does not contain it
+    pub fn default(mut self, value: &'static str) -> Self {
+        self.default = Some(value);
+        self
+    }
+
+    /// Restricts numbers to the inclusive range from `min` to `max`
+    pub fn range(mut self, min: f64, max: f64) -> Self {
+        self.range = Some((min, max));
+        self
+    }
+
+    /// Allows `keyword` (e.g. 'auto' or 'none') in place of a value of the type
+    pub fn or_keyword(mut self, keyword: &'static str) -> Self {
+        self.keywords.push(keyword);
+        self
+    }
+
+    /// Checks `value` against the definition
+    fn check(&self, value: &str) -> Result<(), SchemaError> {
+        if self.keywords.contains(&value) {
+            return Ok(());
+        }
+        let invalid = |expected: String| SchemaError::Invalid {
+            section: self.section.to_string(),
+            key: self.key.to_string(),
+            value: value.to_string(),
+            expected,
+        };
+
+        if let Err(expected) = self.value_type.check(value) {
+            let expected = self
+                .keywords
+                .iter()
+                .fold(expected, |e, keyword| format!("{} or '{}'", e, keyword));
+            return Err(invalid(expected));
+        }
+        if let Some((min, max)) = self.range {
+            let out_of_range = value
+                .split_whitespace()
+                .filter_map(|v| v.parse::<f64>().ok())
+                .any(|v| v < min || v > max);
+            if out_of_range {
+                return Err(invalid(format!("a value from {} to {}", min, max)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A misconfigured key found by `Schema::load`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaError {
+    /// A required key is missing
+    Missing {
+        /// Section of the key
+        section: String,
+        /// Name of the key
+        key: String,
+    },
+    /// The value of a key is not of its type or out of its range
+    Invalid {
+        /// Section of the key
+        section: String,
+        /// Name of the key
+        key: String,
+        /// The value found in the ini
+        value: String,
+        /// Description of the values allowed
+        expected: String,
+    },
+    /// The source contains a section without any key of the schema
+    UnknownSection {
+        /// Name of the section
+        section: String,
+    },
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SchemaError::Missing { section, key } => write!(f, "no key [{}]{}", section, key),
+            SchemaError::Invalid {
+                section,
+                key,
+                value,
+                expected,
+            } => write!(
+                f,
+                "[{}]{} is '{}', expected {}",
+                section, key, value, expected
+            ),
+            SchemaError::UnknownSection { section } => write!(f, "unknown section [{}]", section),
+        }
+    }
+}
+
+/// Typed definition of the keys of an ini. The whole ini is checked at once, so a misconfigured
+/// ini is reported completely instead of failing at the first key read.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schema {
+    keys: Vec<KeyDef>,
+}
+
+impl Schema {
+    /// Creates a schema without keys
+    pub fn new() -> Self {
+        Schema::default()
+    }
+
+    /// Adds the definition of a key
+    pub fn key(mut self, key: KeyDef) -> Self {
+        self.keys.push(key);
+        self
+    }
+
+    /// Reads all keys of the schema from the source, the defaults filled in for missing keys.
+    /// Returns all errors found if any key is missing or invalid or a section is unknown.
+    pub fn load(&self, conf: &dyn ConfigSource) -> Result<IniValues, Vec<SchemaError>> {
+        self.load_layers(&[conf])
+    }
+
+    /// Reads all keys of the schema from the layers, a value of a later layer takes precedence over
+    /// the values of the layers before it and the defaults are used for keys missing in all layers.
+    /// Returns all errors found if any key is missing or invalid or a section is unknown.
+    pub fn load_layers(&self, layers: &[&dyn ConfigSource]) -> Result<IniValues, Vec<SchemaError>> {
+        let mut values = Vec::new();
+        let mut errors = Vec::new();
+
+        for def in &self.keys {
+            let value = layers
+                .iter()
+                .rev()
+                .find_map(|layer| layer.value(def.section, def.key));
+            let value = match value.or_else(|| def.default.map(str::to_string)) {
+                Some(value) => value,
+                None => {
+                    errors.push(SchemaError::Missing {
+                        section: def.section.to_string(),
+                        key: def.key.to_string(),
+                    });
+                    continue;
+                }
+            };
+            match def.check(&value) {
+                Ok(()) => values.push((format!("{}.{}", def.section, def.key), value)),
+                Err(error) => errors.push(error),
+            }
+        }
+
+        for layer in layers {
+            for section in layer.sections() {
+                let known = self.keys.iter().any(|def| def.section == section);
+                let error = SchemaError::UnknownSection { section };
+                if !known && !errors.contains(&error) {
+                    errors.push(error);
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(IniValues { values })
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Values given as 'section.key=value', e.g. on the command line
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overrides {
+    values: Vec<(String, String)>,
+}
+
+impl Overrides {
+    /// Parses arguments of the form 'section.key=value', a leading '--' is allowed
+    pub fn parse<I, S>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut overrides = Overrides::default();
+        for arg in args {
+            let arg = arg.as_ref();
+            let mut parts = arg.trim_start_matches("--").splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) if name.contains('.') => overrides
+                    .values
+                    .push((name.to_string(), value.to_string())),
+                _ => return Err(format!("'{}' is not of the form 'section.key=value'", arg)),
+            }
+        }
+        Ok(overrides)
+    }
+}
+
+impl ConfigSource for Overrides {
+    fn value(&self, section: &str, key: &str) -> Option<String> {
+        let name = format!("{}.{}", section, key);
+        self.values
+            .iter()
+            .rev()
+            .find(|(n, _)| *n == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    fn sections(&self) -> Vec<String> {
+        self.values
+            .iter()
+            .filter_map(|(name, _)| name.split('.').next())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// Merges the layers of a config without passing them through the environment. The precedence is
+/// the reverse order the layers are added in, the defaults of the schema come last, e.g.
+///
+/// ```ignore
+/// ConfigBuilder::new(schema)
+///     .layer(get_ini("simulation.ini"))
+///     .layer(Overrides::parse(env::args().skip(1))?)
+///     .build()
+/// ```
+///
+/// takes the command line over the ini over the defaults.
+pub struct ConfigBuilder {
+    schema: Schema,
+    layers: Vec<Box<dyn ConfigSource>>,
+}
+
+impl ConfigBuilder {
+    /// Starts with the defaults of `schema`
+    pub fn new(schema: Schema) -> Self {
+        ConfigBuilder {
+            schema,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Adds a layer that takes precedence over all layers added before
+    pub fn layer<S: ConfigSource + 'static>(mut self, source: S) -> Self {
+        self.layers.push(Box::new(source));
+        self
+    }
+
+    /// Checks the merged values against the schema, see `Schema::load_layers`
+    pub fn build(&self) -> Result<IniValues, Vec<SchemaError>> {
+        let layers: Vec<&dyn ConfigSource> = self.layers.iter().map(|l| l.as_ref()).collect();
+        self.schema.load_layers(&layers)
+    }
+}
+
+/// The values of an ini checked by `Schema::load`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IniValues {
+    values: Vec<(String, String)>,
+}
+
+impl IniValues {
+    /// Returns the value of `[section]key`, `None` if the key is not part of the schema or the
+    /// value cannot be parsed into `T`
+    pub fn get<T: FromStr>(&self, section: &str, key: &str) -> Option<T> {
+        let name = format!("{}.{}", section, key);
+        self.values
+            .iter()
+            .find(|(n, _)| *n == name)
+            .and_then(|(_, value)| value.parse().ok())
+    }
+
+    /// Returns the value of `[section]key`, which the schema must contain
+    pub fn require<T>(&self, section: &str, key: &str) -> T
+    where
+        T: FromStr,
+    {
+        self.get(section, key)
+            .unwrap_or_else(|| panic!("Can't read [{}]{} into desired variable", section, key))
+    }
+
+    /// Returns the whitespace separated list of `[section]key`, which the schema must contain
+    pub fn get_vec<T>(&self, section: &str, key: &str) -> Vec<T>
+    where
+        T: FromStr,
+    {
+        self.require::<String>(section, key)
+            .split_whitespace()
+            .map(|value| {
+                value.parse().unwrap_or_else(|_| {
+                    panic!("Can't read [{}]{} into desired variable", section, key)
+                })
+            })
+            .collect()
+    }
+
+    /// Exports all values to the environment as 'section.key'
+    #[deprecated(note = "read the values directly instead of passing them via the environment")]
+    pub fn export(&self) {
+        for (name, value) in &self.values {
+            debug!("{} is {}", name, value);
+            env::set_var(name, value);
+        }
+    }
+}
+
+/*******************************************************************************
+ * TESTS
+ ******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Schema {
+        Schema::new()
+            .key(KeyDef::new("node", "nodes", ValueType::UInt).range(4.0, 100.0))
+            .key(KeyDef::new("node", "verbose", ValueType::Bool).default("false"))
+            .key(KeyDef::new("network", "loss", ValueType::Float).default("0"))
+            .key(
+                KeyDef::new(
+                    "network",
+                    "delays",
+                    ValueType::List(Box::new(ValueType::UInt)),
+                )
+                .or_keyword("none")
+                .default("none"),
+            )
+            .key(KeyDef::new(
+                "run",
+                "mode",
+                ValueType::OneOf(vec!["fast", "exact"]),
+            ))
+    }
+
+    fn errors(ini: &str) -> Vec<String> {
+        schema()
+            .load(&Ini::from_buffer(ini))
+            .unwrap_err()
+            .iter()
+            .map(|e| e.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn missing_keys_are_reported_or_defaulted() {
+        assert_eq!(
+            errors("[node]\nverbose = true\n"),
+            vec!["no key [node]nodes", "no key [run]mode"]
+        );
+
+        let values = schema()
+            .load(&Ini::from_buffer(
+                "[node]\nnodes = 4\n\n[run]\nmode = fast\n",
+            ))
+            .unwrap();
+        assert_eq!(values.get::<u32>("node", "nodes"), Some(4));
+        assert_eq!(values.get::<bool>("node", "verbose"), Some(false));
+        assert_eq!(
+            values.get::<String>("network", "delays"),
+            Some("none".to_string())
+        );
+        assert_eq!(values.get::<u32>("node", "unknown"), None);
+    }
+
+    #[test]
+    fn values_of_the_wrong_type_are_reported() {
+        assert_eq!(
+            errors(
+                "[node]\nnodes = -4\nverbose = yes\n\n[network]\nloss = high\ndelays = 10 x\n\n\
+                 [run]\nmode = slow\n"
+            ),
+            vec![
+                "[node]nodes is '-4', expected an unsigned integer",
+                "[node]verbose is 'yes', expected 'true' or 'false'",
+                "[network]loss is 'high', expected a number",
+                "[network]delays is '10 x', expected a list of an unsigned integer or 'none'",
+                "[run]mode is 'slow', expected one of 'fast', 'exact'",
+            ]
+        );
+        assert_eq!(
+            errors("[node]\nnodes = 2\n\n[run]\nmode = fast\n"),
+            vec!["[node]nodes is '2', expected a value from 4 to 100"]
+        );
+    }
+
+    #[test]
+    fn unknown_sections_are_reported() {
+        assert_eq!(
+            errors("[node]\nnodes = 4\n\n[run]\nmode = fast\n\n[netwrk]\nloss = 0.1\n"),
+            vec!["unknown section [netwrk]"]
+        );
+
+        let ini = Ini::from_buffer("[node]\nnodes = 4\n\n[run]\nmode = fast\n");
+        let overrides = Overrides::parse(vec!["netwrk.loss=0.1", "--netwrk.delays=5"]).unwrap();
+        assert_eq!(
+            schema().load_layers(&[&ini, &overrides]).unwrap_err(),
+            vec![SchemaError::UnknownSection {
+                section: "netwrk".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn later_layers_take_precedence() {
+        let ini = Ini::from_buffer("[node]\nnodes = 4\n\n[run]\nmode = fast\n");
+        let overrides = Overrides::parse(vec!["node.nodes=7", "--node.nodes=10"]).unwrap();
+        let values = ConfigBuilder::new(schema())
+            .layer(ini)
+            .layer(overrides)
+            .build()
+            .unwrap();
+        assert_eq!(values.get::<u32>("node", "nodes"), Some(10));
+        assert_eq!(
+            values.get::<String>("run", "mode"),
+            Some("fast".to_string())
+        );
+    }
+}
@@ -4,7 +4,9 @@
 
 #[macro_use]
 pub mod conv;
+#[cfg(any(feature = "crypto", feature = "rust-crypto"))]
 pub mod crypto;
 pub mod filehelper;
 pub mod ini;
+#[cfg(feature = "logging")]
 pub mod log;
@@ -0,0 +1,40 @@
+extern crate bft_simulation;
+
+use std::time::Duration;
+
+use bft_simulation::emulation::run_emulation;
+use bft_simulation::simulation::config::{initialize_logging, load_config};
+use bft_simulation::simulation::output::RunId;
+use bft_simulation::simulation::settings::Settings;
+
+/// Runs the configured nodes and client requests over real TCP connections on localhost, takes the
+/// same arguments as the simulation, e.g. 'emulation simulation.ini node.nodes_vec=4'
+fn main() {
+    let config = load_config(std::env::args().skip(1));
+    // the debug logs go to the directory of this process, every emulation writes its results to
+    // a directory of its own
+    if let Err(e) = initialize_logging(&config, &RunId::new()) {
+        eprintln!("Failed to initialize the logging: {}", e);
+    }
+
+    let settings = Settings::from_values(&config);
+    let timeout = Duration::from_secs(config.require("emulation", "timeout"));
+    for n in config.get_vec::<u32>("node", "nodes_vec") {
+        let mut settings = settings.clone();
+        settings.node.nodes = n;
+
+        let report = run_emulation(settings, timeout)
+            .unwrap_or_else(|e| panic!("Failed to open the sockets of the nodes: {}", e));
+        println!(
+            "n = {}: {} of {} requests done after {}, mean latency {:.1}ms",
+            n,
+            report.requests.done,
+            report.requests.issued,
+            report.end_time.since_start(),
+            report.requests.mean_latency
+        );
+        if let Err(divergence) = report.agreement {
+            panic!("Safety violated: {}", divergence);
+        }
+    }
+}
@@ -0,0 +1,26 @@
+extern crate bft_simulation;
+
+use std::process;
+
+use bft_simulation::simulation::config::{initialize_logging, load_config};
+use bft_simulation::simulation::grpc;
+use bft_simulation::simulation::output::RunId;
+use bft_simulation::simulation::settings::Settings;
+
+/// Serves the gRPC control API on '[grpc]address', takes the same arguments as the simulation, e.g.
+/// 'grpc_server simulation.ini grpc.address=0.0.0.0:50051'. The runs take the settings of the
+/// arguments until a client configures others.
+fn main() {
+    let config = load_config(std::env::args().skip(1));
+    // the debug logs go to the directory of this process, every run writes its results to a
+    // directory of its own
+    if let Err(e) = initialize_logging(&config, &RunId::new()) {
+        eprintln!("Failed to initialize the logging: {}", e);
+    }
+
+    let address = config.require::<String>("grpc", "address");
+    if let Err(e) = grpc::serve(&address, Settings::from_values(&config)) {
+        eprintln!("Failed to serve the control API on {}: {}", address, e);
+        process::exit(1);
+    }
+}
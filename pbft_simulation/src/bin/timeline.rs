@@ -0,0 +1,42 @@
+extern crate bft_simulation;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::process;
+
+use bft_simulation::simulation::causality::CausalityTrace;
+use bft_simulation::simulation::timeline::Timeline;
+
+/// Renders the timeline of a finished run as HTML, e.g.
+/// 'timeline log/<run>/causality_004.json log/<run>/result_004_1000_10.log 12 > timeline.html'
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 || args.len() > 4 {
+        eprintln!(
+            "usage: {} <causality json> <result log> [request id]",
+            args[0]
+        );
+        process::exit(1);
+    }
+
+    let request = args.get(3).map(|r| {
+        r.parse()
+            .unwrap_or_else(|_| panic!("'{}' is not a request id", r))
+    });
+
+    let file = File::open(&args[1]).expect("Failed to open the causality trace");
+    let trace = CausalityTrace::read_json(BufReader::new(file))
+        .expect("Failed to read the causality trace");
+    let log = fs::read_to_string(&args[2]).expect("Failed to read the result log");
+
+    let mut timeline = Timeline::new(request);
+    timeline.add_messages(trace.records());
+    timeline.add_result_log(&log);
+
+    let title = match request {
+        Some(r) => format!("{}, request {}", args[2], r),
+        None => args[2].clone(),
+    };
+    print!("{}", timeline.to_html(&title));
+}
@@ -0,0 +1,451 @@
+/***************************************************************************************************
+Runs the nodes over real TCP connections instead of the event queue. Every node gets a thread and a
+socket on localhost, messages travel as frames of the wire format (see `network::codec`) over one
+connection per pair of nodes and timers expire in real time. The nodes are the very same as in the
+simulation, so protocol logic validated in the simulation can be smoke-tested under a real network
+stack without rewriting it.
+
+The simulated network (delays, omissions, topology, gossip) is not applied, the real one decides.
+The processing delay and the computation a node asks for are waited for before it sends.
+***************************************************************************************************/
+
+use std::collections::HashMap;
+use std::io::{self, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{debug, info, warn};
+
+use crate::network::codec;
+use crate::node::factory::NodeConstructor;
+use crate::node::{Node, NodeOutput};
+use crate::simulation::config::{NodeConfig, RequestBatchConfig, ResultLogger, SimulationConfig};
+use crate::simulation::event::{EventType, Message, Reception};
+use crate::simulation::report::SimulationReport;
+use crate::simulation::results::ResultEvent;
+use crate::simulation::results_writer::ResultsWriter;
+use crate::simulation::safety::{self, ExecutionRecord};
+use crate::simulation::settings::Settings;
+use crate::simulation::snapshot;
+use crate::simulation::statistics::{self, RunStatistics, TrafficStatistics};
+use crate::simulation::time::Time;
+
+/// The run ends once no node handled anything for this long after the last client request
+const IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Runs the nodes of the `settings` on localhost until they are idle after the configured client
+/// requests, or for `timeout` at most, and reports the run like `run_simulation`. The times in the
+/// report are milliseconds of real time since the start.
+pub fn run_emulation(settings: Settings, timeout: Duration) -> io::Result<SimulationReport> {
+    let mut config = SimulationConfig::new(settings);
+    let number_of_nodes = config.number_of_nodes;
+    // the clients that are nodes of their own follow the replicas
+    let total_nodes = config.total_nodes();
+    let results = ResultLogger::new();
+    results.collect();
+    if let Some(writer) = ResultsWriter::for_run(&config.run, &config.settings) {
+        results.write_to(writer);
+    }
+    snapshot::write_next_to_result_log(&config.settings, &config.run);
+
+    let mut listeners = Vec::with_capacity(total_nodes as usize);
+    let mut addresses = HashMap::with_capacity(total_nodes as usize);
+    for id in 1..=total_nodes {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        addresses.insert(id, listener.local_addr()?);
+        listeners.push((id, listener));
+    }
+
+    let context = Arc::new(Context {
+        start: Instant::now(),
+        addresses,
+        results: results.clone(),
+        traffic: Mutex::new(TrafficStatistics::default()),
+        last_activity: AtomicU64::new(0),
+        handled: AtomicU64::new(0),
+        running: AtomicBool::new(true),
+    });
+    info!(
+        "Emulation started for n = {} of type {:?}",
+        number_of_nodes, config.node_type
+    );
+
+    // start the nodes, each listens for the connections of its peers
+    let mut inboxes = HashMap::with_capacity(total_nodes as usize);
+    let mut nodes = Vec::with_capacity(total_nodes as usize);
+    for (id, listener) in listeners {
+        let (sender, receiver) = mpsc::channel();
+        let acceptor_context = Arc::clone(&context);
+        let acceptor_sender = sender.clone();
+        thread::spawn(move || accept_peers(listener, acceptor_sender, &acceptor_context));
+
+        let node_config = config.create_node_config(&results);
+        let constructor = config.constructor_of(&node_config);
+        let node_context = Arc::clone(&context);
+        nodes.push(thread::spawn(move || {
+            NodeHost::new(node_config, constructor, node_context).run(receiver)
+        }));
+        inboxes.insert(id, sender);
+    }
+
+    // the clients hand their requests to the nodes directly, like in the simulation
+    let settings = Arc::clone(&config.settings);
+    let requests = RequestBatchConfig::new(
+        settings.simulation.requests,
+        settings.simulation.request_interval,
+    )
+    .create_events(
+        &mut 1,
+        Time::new(0),
+        config.node_type,
+        number_of_nodes,
+        settings.node.clients,
+        settings
+            .pbft
+            .client_target
+            .resolve(&settings, number_of_nodes),
+    );
+    for event in requests {
+        if let EventType::Reception(reception) = event.event_type {
+            sleep_until(context.start + Duration::from_micros(event.time.micros()));
+            context.touch();
+            if let Some(inbox) = inboxes.get(&reception.id) {
+                let _ = inbox.send(Input::Message(reception.message));
+            }
+        }
+    }
+
+    // wait until the nodes are done with the requests
+    while context.start.elapsed() < timeout
+        && context.now().milli() < context.last_activity.load(Ordering::SeqCst) + idle_millis()
+    {
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    let end_time = context.now();
+    for inbox in inboxes.values() {
+        let _ = inbox.send(Input::Stop);
+    }
+    let mut executions: Vec<ExecutionRecord> = nodes
+        .into_iter()
+        .filter_map(|node| node.join().expect("A node of the emulation panicked"))
+        .collect();
+    executions.sort_by_key(|r| r.node_id);
+    context.stop_accepting();
+
+    let (lines, overdue, requests, views) = finish_results(&settings, &context, end_time);
+    let agreement = safety::check_agreement(&executions);
+    if let Err(divergence) = &agreement {
+        warn!("Safety violated: {}", divergence);
+    }
+    let traffic = context.traffic.lock().unwrap().clone();
+
+    Ok(SimulationReport {
+        settings: (*settings).clone(),
+        end_time,
+        events: context.handled.load(Ordering::SeqCst),
+        results: lines,
+        traffic,
+        executions,
+        agreement,
+        overdue,
+        requests,
+        views,
+    })
+}
+
+fn idle_millis() -> u64 {
+    IDLE_TIMEOUT.as_millis() as u64
+}
+
+/// Writes the reports to the result log, the node threads logged to it without the statistics of
+/// the run, so its lines are replayed to them
+fn finish_results(
+    settings: &Settings,
+    context: &Context,
+    end_time: Time,
+) -> (
+    Vec<String>,
+    Vec<u64>,
+    statistics::RequestSummary,
+    statistics::ViewSummary,
+) {
+    let results = &context.results;
+    let mut lines = results.take_lines();
+    results.collect();
+
+    let mut statistics = RunStatistics::new(settings);
+    for line in &lines {
+        let mut fields = line.splitn(3, ';');
+        let time = fields.next().and_then(|t| t.parse().ok()).map(Time::new);
+        let event = fields.nth(1).and_then(ResultEvent::parse);
+        if let (Some(time), Some(event)) = (time, event) {
+            statistics.record(time, &event);
+        }
+    }
+
+    context.traffic.lock().unwrap().report(end_time, results);
+    let (overdue, requests, views) = statistics.report(end_time, results);
+    results.log(end_time, None, &ResultEvent::Finished);
+    results.finish();
+
+    lines.extend(results.take_lines());
+    (lines, overdue, requests, views)
+}
+
+/// Inputs of a node thread
+#[derive(Debug)]
+enum Input {
+    Message(Arc<Message>),
+    Stop,
+}
+
+/// State shared by all threads of the emulation
+#[derive(Debug)]
+struct Context {
+    start: Instant,
+    /// Addresses the nodes listen on
+    addresses: HashMap<u32, SocketAddr>,
+    results: ResultLogger,
+    traffic: Mutex<TrafficStatistics>,
+    /// Time (ms) a node last handled a message or timer
+    last_activity: AtomicU64,
+    /// Number of messages and timers handled
+    handled: AtomicU64,
+    /// Unset once the nodes stopped, the listeners stop accepting then
+    running: AtomicBool,
+}
+
+impl Context {
+    /// Time since the start of the emulation
+    fn now(&self) -> Time {
+        Time::from_micros(self.start.elapsed().as_micros() as u64)
+    }
+
+    fn touch(&self) {
+        self.last_activity
+            .fetch_max(self.now().milli(), Ordering::SeqCst);
+    }
+
+    /// Stops the listeners, each is woken up by a last connection
+    fn stop_accepting(&self) {
+        self.running.store(false, Ordering::SeqCst);
+        for address in self.addresses.values() {
+            let _ = TcpStream::connect(address);
+        }
+    }
+}
+
+/// Accepts the connections of the peers and forwards their messages to the node
+fn accept_peers(listener: TcpListener, inbox: Sender<Input>, context: &Context) {
+    for stream in listener.incoming() {
+        if !context.running.load(Ordering::SeqCst) {
+            return;
+        }
+        match stream {
+            Ok(stream) => {
+                let inbox = inbox.clone();
+                thread::spawn(move || receive_messages(stream, inbox));
+            }
+            Err(e) => warn!(target: "emulation", "Failed to accept a peer: {}", e),
+        }
+    }
+}
+
+/// Reads the messages of a peer, one per frame, until the connection or the node stops
+fn receive_messages(stream: TcpStream, inbox: Sender<Input>) {
+    let mut reader = BufReader::new(stream);
+    loop {
+        match codec::read_frame(&mut reader) {
+            Ok(Some((from, message))) => {
+                debug!(target: "emulation", "Received from {}: {:?}", from, &message);
+                if inbox.send(Input::Message(Arc::new(message))).is_err() {
+                    return;
+                }
+            }
+            Ok(None) => return,
+            Err(e) if e.is_recoverable() => {
+                warn!(target: "emulation", "Dropped a message: {}", e)
+            }
+            Err(e) => {
+                warn!(target: "emulation", "Closed the connection of a peer: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// Hosts a single node: hands it the messages and expired timers and carries out its outputs
+#[derive(Debug)]
+struct NodeHost {
+    id: u32,
+    node: Box<dyn Node>,
+    settings: Arc<Settings>,
+    context: Arc<Context>,
+    /// Connections to the peers, opened on the first message
+    connections: HashMap<u32, TcpStream>,
+    /// Deadlines of the pending timers, by token
+    timers: HashMap<u64, Instant>,
+}
+
+impl NodeHost {
+    fn new(config: NodeConfig, constructor: NodeConstructor, context: Arc<Context>) -> Self {
+        NodeHost {
+            id: config.id,
+            settings: Arc::clone(&config.settings),
+            node: constructor(config),
+            context,
+            connections: HashMap::new(),
+            timers: HashMap::new(),
+        }
+    }
+
+    /// Handles the inputs until stopped, returns the execution of the node
+    fn run(mut self, inbox: Receiver<Input>) -> Option<ExecutionRecord> {
+        let outputs = self.node.on_start(self.context.now());
+        if outputs.is_some() {
+            self.handle_outputs(outputs);
+        }
+        loop {
+            let next_timer = self
+                .timers
+                .iter()
+                .min_by_key(|(_, deadline)| **deadline)
+                .map(|(token, deadline)| (*token, *deadline));
+
+            let input = match next_timer {
+                Some((token, deadline)) => {
+                    match inbox.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                        Ok(input) => input,
+                        Err(RecvTimeoutError::Timeout) => {
+                            self.timers.remove(&token);
+                            self.context.touch();
+                            let outputs = self.node.handle_timer(token, self.context.now());
+                            self.handle_outputs(outputs);
+                            continue;
+                        }
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                None => match inbox.recv() {
+                    Ok(input) => input,
+                    Err(_) => break,
+                },
+            };
+
+            match input {
+                Input::Message(message) => {
+                    self.context.touch();
+                    let reception = Reception::new(self.id, message);
+                    let outputs = self.node.handle_event(reception, self.context.now());
+                    self.handle_outputs(outputs);
+                }
+                Input::Stop => break,
+            }
+        }
+        let now = self.context.now();
+        for event in self.node.on_stop(now) {
+            self.context.results.log(now, Some(self.id), &event);
+        }
+        self.node.execution()
+    }
+
+    /// Carries out the outputs of the node. Messages leave the node after the processing delay and
+    /// all computation the node requested before them, like in the simulation.
+    fn handle_outputs(&mut self, outputs: Option<Vec<NodeOutput>>) {
+        self.context.handled.fetch_add(1, Ordering::SeqCst);
+        let outputs = match outputs {
+            Some(outputs) => outputs,
+            None => return,
+        };
+        let handled = Instant::now();
+        let processing_delay = self.settings.node.processing_delay_of(self.id);
+        let mut compute = 0;
+
+        for output in outputs {
+            let send_time =
+                handled + Duration::from_millis(processing_delay) + Duration::from_micros(compute);
+
+            match output {
+                NodeOutput::Send { to, message, size } => {
+                    sleep_until(send_time);
+                    self.send(to, &message, size);
+                }
+                NodeOutput::Gossip { message, size } => {
+                    sleep_until(send_time);
+                    for to in 1..=self.context.addresses.len() as u32 {
+                        if to != self.id {
+                            self.send(to, &message, size);
+                        }
+                    }
+                }
+                NodeOutput::DeliverToClient { to, message } => {
+                    sleep_until(send_time);
+                    self.send(to, &message, 0);
+                }
+                NodeOutput::SetTimer { token, duration } => {
+                    self.timers
+                        .insert(token, Instant::now() + Duration::from_millis(duration));
+                }
+                NodeOutput::CancelTimer { token } => {
+                    self.timers.remove(&token);
+                }
+                NodeOutput::Compute { micros } => compute += micros,
+                NodeOutput::Log { event } => {
+                    self.context
+                        .results
+                        .log(self.context.now(), Some(self.id), &event)
+                }
+            }
+        }
+    }
+
+    /// Writes `message` to the connection to node `to`, it is lost if the connection fails
+    fn send(&mut self, to: u32, message: &Message, size: u32) {
+        let address = match self.context.addresses.get(&to) {
+            Some(address) => *address,
+            None => {
+                warn!(target: "emulation", "Node {} sent a message to the unknown node {}", self.id, to);
+                return;
+            }
+        };
+        self.context
+            .traffic
+            .lock()
+            .unwrap()
+            .record_sent(message, size);
+
+        let frame = match codec::encode(self.id, message) {
+            Ok(frame) => frame,
+            Err(e) => {
+                warn!(target: "emulation", "Failed to encode a message of node {}: {}", self.id, e);
+                return;
+            }
+        };
+
+        let written = match self.connections.get_mut(&to) {
+            Some(stream) => stream.write_all(&frame),
+            None => TcpStream::connect(address).and_then(|mut stream| {
+                stream.set_nodelay(true)?;
+                stream.write_all(&frame)?;
+                self.connections.insert(to, stream);
+                Ok(())
+            }),
+        };
+        if let Err(e) = written {
+            warn!(target: "emulation", "Node {} lost a message to node {}: {}", self.id, to, e);
+            self.connections.remove(&to);
+        }
+    }
+}
+
+fn sleep_until(deadline: Instant) {
+    let now = Instant::now();
+    if deadline > now {
+        thread::sleep(deadline - now);
+    }
+}
@@ -1,8 +1,30 @@
+#[cfg(feature = "fuzz")]
+extern crate arbitrary;
+extern crate bincode;
 extern crate log;
+#[cfg(feature = "file-logging")]
 extern crate log4rs;
 extern crate mc_utils;
+#[cfg(feature = "grpc")]
+extern crate prost;
+#[cfg(feature = "python")]
+extern crate pyo3;
 extern crate rand;
+extern crate rayon;
+#[cfg(feature = "sqlite")]
+extern crate rusqlite;
+extern crate serde;
+extern crate serde_json;
+extern crate serde_yaml;
+#[cfg(feature = "grpc")]
+extern crate tokio;
+#[cfg(feature = "grpc")]
+extern crate tonic;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
 
+/// Running the nodes over real TCP connections instead of the simulated network
+pub mod emulation;
 /// Everything related to the network
 pub mod network;
 /// Everything related to nodes
@@ -1,34 +1,88 @@
 extern crate bft_simulation;
 
+use std::process;
 use std::thread;
 
+use log::info;
+
 use bft_simulation::simulation::config::{
-    initialize_ini, initialize_logging, RequestBatchConfig, SimulationConfig,
+    initialize_logging, load_config, RequestBatchConfig, SimulationConfig,
 };
+use bft_simulation::simulation::control::ControlServer;
+use bft_simulation::simulation::determinism;
 use bft_simulation::simulation::event::{AdminType, EventType};
+use bft_simulation::simulation::metrics;
+use bft_simulation::simulation::output::RunId;
+use bft_simulation::simulation::settings::Settings;
 use bft_simulation::simulation::Simulation;
 
 fn main() {
-    // read settings from the ini
-    initialize_ini();
+    // read settings: command line overrides > config file (simulation.ini by default) > defaults
+    let config = load_config(std::env::args().skip(1));
+    let settings = Settings::from_values(&config);
+    // all files of this process go to the directory of its run
+    let run = RunId::seeded(settings.simulation.seed);
     //initialize logger
-    initialize_logging();
+    if let Err(e) = initialize_logging(&config, &run) {
+        eprintln!("Failed to initialize the logging: {}", e);
+    }
+    info!("Writing the files of the run to {}", run.dir());
+    // serve live metrics if enabled
+    if config.require::<bool>("metrics", "enabled") {
+        let address = config.require::<String>("metrics", "address");
+        if let Err(e) = metrics::serve(&address) {
+            eprintln!("Failed to serve metrics on {}: {}", address, e);
+        }
+    }
 
-    let node_vec = mc_utils::ini::env2var_vec::<u32>("node.nodes_vec");
-    for n in node_vec {
-        mc_utils::ini::env::set_var("node.nodes", n.to_string());
+    // accept commands on the control socket if enabled
+    let control = if config.require::<bool>("control", "enabled") {
+        let address = config.require::<String>("control", "address");
+        Some(ControlServer::serve(&address).expect("Failed to open the control socket"))
+    } else {
+        None
+    };
+
+    let node_vec = config.get_vec::<u32>("node", "nodes_vec");
 
+    // run every configuration twice with the seed of the run and compare the result logs
+    if settings.simulation.self_check {
+        for n in node_vec {
+            let mut settings = settings.clone();
+            settings.node.nodes = n;
+            settings.simulation.seed = Some(run.seed);
+            if let Err(nondeterminism) = determinism::check(settings) {
+                eprintln!("{}", nondeterminism);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+    for n in node_vec {
         // initialize a new simulation
-        let config_sim = SimulationConfig::default();
+        let config_sim = SimulationConfig::new(settings.clone()).run(run.clone());
         let mut simulation = Simulation::new(config_sim.number_of_nodes(n));
 
+        if let Some(control) = &control {
+            control.attach(simulation.get_sender());
+        }
+
+        // a scenario replaces the configured requests
+        if let Some(scenario) = &settings.simulation.scenario {
+            simulation
+                .run_scenario(scenario)
+                .unwrap_or_else(|e| panic!("Failed to read the scenario {}: {}", scenario, e));
+            continue;
+        }
+
         // get channels to send events to the simulation queue
         let s = simulation.get_sender();
-
+        let requests = settings.simulation.requests;
+        let request_interval = settings.simulation.request_interval;
         thread::spawn(move || {
             // add some requests
             s.send(EventType::Admin(AdminType::ClientRequests(
-                RequestBatchConfig::new(mc_utils::ini::env2var("simulation.requests"), 1000),
+                RequestBatchConfig::new(requests, request_interval),
             )))
             .unwrap();
         });
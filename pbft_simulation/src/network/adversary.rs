@@ -0,0 +1,135 @@
+/***************************************************************************************************
+Adversarial scheduling of the network. Beyond the random delays and losses, an adversary sees every
+message the network delivers and chooses its delay, up to the bound Δ of partial synchrony
+(`network.adversary_delta`). Choosing the delays reorders the messages, so worst-case schedules of a
+protocol can be explored programmatically. The adversary cannot drop messages.
+***************************************************************************************************/
+
+use std::collections::HashSet;
+use std::fmt::Debug;
+
+use crate::simulation::event::Message;
+use crate::simulation::time::{Duration, Time};
+
+/// A message the network is about to deliver
+#[derive(Debug)]
+pub struct Delivery<'a> {
+    pub from: u32,
+    pub to: u32,
+    pub message: &'a Message,
+    /// When the message was sent
+    pub sent: Time,
+    /// The delay the network drew for the message
+    pub delay: Duration,
+}
+
+impl Delivery<'_> {
+    /// When the message arrives with the delay of the network
+    pub fn arrival(&self) -> Time {
+        self.sent + self.delay
+    }
+}
+
+/// Decides when the messages of the network arrive
+pub trait Adversary: Debug + Send {
+    /// Returns the delay of `delivery`, a longer one than the bound Δ is cut to Δ
+    fn delay(&mut self, delivery: &Delivery) -> Duration;
+}
+
+/// Delays every message from or to one of the nodes by Δ, e.g. to slow down the primary as much as
+/// partial synchrony allows. All other messages take the delay of the network.
+#[derive(Debug, Clone)]
+pub struct SlowNodes {
+    nodes: HashSet<u32>,
+    delta: Duration,
+}
+
+impl SlowNodes {
+    pub fn new(nodes: impl IntoIterator<Item = u32>, delta: Duration) -> Self {
+        SlowNodes {
+            nodes: nodes.into_iter().collect(),
+            delta,
+        }
+    }
+}
+
+impl Adversary for SlowNodes {
+    fn delay(&mut self, delivery: &Delivery) -> Duration {
+        if self.nodes.contains(&delivery.from) || self.nodes.contains(&delivery.to) {
+            self.delta
+        } else {
+            delivery.delay
+        }
+    }
+}
+
+/// The adversary of a network with the bound Δ it is held to
+#[derive(Debug)]
+pub(crate) struct BoundedAdversary {
+    adversary: Box<dyn Adversary>,
+    delta: Duration,
+}
+
+impl BoundedAdversary {
+    pub(crate) fn new(adversary: Box<dyn Adversary>, delta: Duration) -> Self {
+        BoundedAdversary { adversary, delta }
+    }
+
+    /// The delay the adversary chooses for `delivery`, at most Δ
+    pub(crate) fn delay(&mut self, delivery: &Delivery) -> Duration {
+        self.adversary.delay(delivery).min(self.delta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Forever;
+
+    impl Adversary for Forever {
+        fn delay(&mut self, _: &Delivery) -> Duration {
+            Duration::from_millis(u64::MAX)
+        }
+    }
+
+    fn delivery(message: &Message, from: u32, to: u32) -> Delivery<'_> {
+        Delivery {
+            from,
+            to,
+            message,
+            sent: Time::new(10),
+            delay: Duration::from_millis(5),
+        }
+    }
+
+    #[test]
+    /// Check that the delays of an adversary are cut to the bound
+    fn check_delay_is_bounded() {
+        let mut adversary = BoundedAdversary::new(Box::new(Forever), Duration::from_millis(50));
+        assert_eq!(
+            adversary.delay(&delivery(&Message::Dummy, 1, 2)),
+            Duration::from_millis(50)
+        );
+        assert_eq!(delivery(&Message::Dummy, 1, 2).arrival(), Time::new(15));
+    }
+
+    #[test]
+    /// Check that only the messages from and to the slow nodes take the bound
+    fn check_slow_nodes() {
+        let mut adversary = SlowNodes::new(vec![1], Duration::from_millis(50));
+        assert_eq!(
+            adversary.delay(&delivery(&Message::Dummy, 1, 2)),
+            Duration::from_millis(50)
+        );
+        assert_eq!(
+            adversary.delay(&delivery(&Message::Dummy, 3, 1)),
+            Duration::from_millis(50)
+        );
+        assert_eq!(
+            adversary.delay(&delivery(&Message::Dummy, 2, 3)),
+            Duration::from_millis(5)
+        );
+    }
+}
@@ -0,0 +1,195 @@
+/***************************************************************************************************
+Wire format of the messages for runs over a real network, e.g. the emulation over TCP. The nodes
+exchange the very same `Message` as in the simulation, so both share the definitions of the
+messages. A frame carries the sender and the message:
+
+    | length (u32, big endian) | version (u8) | bincode of (sender: u32, message: Message) |
+
+The length counts the bytes after it. A peer of another version is rejected frame by frame instead
+of misreading its messages, `WIRE_VERSION` changes with every incompatible change of the messages.
+***************************************************************************************************/
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use bincode::Options;
+
+use crate::simulation::event::Message;
+
+/// Version of the wire format written by this build, only frames of this version are read
+pub const WIRE_VERSION: u8 = 1;
+/// Longest frame (bytes after the length) a node reads, a longer one is taken for a broken stream
+pub const MAX_FRAME_SIZE: u32 = 64 << 20;
+
+/// A frame that cannot be written or read
+#[derive(Debug)]
+pub enum CodecError {
+    Io(io::Error),
+    /// The frame is of another version of the wire format
+    Version(u8),
+    /// The frame announces more bytes than `MAX_FRAME_SIZE`
+    TooLarge(usize),
+    /// The frame is of this version, but does not hold a message
+    Malformed(String),
+}
+
+impl CodecError {
+    /// Whether the stream can go on with the next frame, the length of the broken frame was read
+    pub fn is_recoverable(&self) -> bool {
+        matches!(self, CodecError::Version(_) | CodecError::Malformed(_))
+    }
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CodecError::Io(e) => write!(f, "{}", e),
+            CodecError::Version(version) => write!(
+                f,
+                "frame of wire format version {}, expected {}",
+                version, WIRE_VERSION
+            ),
+            CodecError::TooLarge(length) => write!(
+                f,
+                "frame of {} bytes, at most {} are allowed",
+                length, MAX_FRAME_SIZE
+            ),
+            CodecError::Malformed(reason) => write!(f, "malformed frame: {}", reason),
+        }
+    }
+}
+
+impl Error for CodecError {}
+
+impl From<io::Error> for CodecError {
+    fn from(e: io::Error) -> Self {
+        CodecError::Io(e)
+    }
+}
+
+// the encoding of the payload, its collections can't announce more bytes than a frame holds
+fn options() -> impl Options {
+    bincode::DefaultOptions::new().with_limit(u64::from(MAX_FRAME_SIZE))
+}
+
+/// Encodes the frame of `message` sent by node `from`, including its length
+pub fn encode(from: u32, message: &Message) -> Result<Vec<u8>, CodecError> {
+    let payload = options()
+        .serialize(&(from, message))
+        .map_err(|e| CodecError::Malformed(e.to_string()))?;
+    let length = payload.len() + 1;
+    if length > MAX_FRAME_SIZE as usize {
+        return Err(CodecError::TooLarge(length));
+    }
+    let mut frame = Vec::with_capacity(4 + length);
+    frame.extend_from_slice(&(length as u32).to_be_bytes());
+    frame.push(WIRE_VERSION);
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+/// Decodes the sender and the message of a frame without its length
+pub fn decode(body: &[u8]) -> Result<(u32, Message), CodecError> {
+    match body.split_first() {
+        Some((&WIRE_VERSION, payload)) => options()
+            .deserialize(payload)
+            .map_err(|e| CodecError::Malformed(e.to_string())),
+        Some((&version, _)) => Err(CodecError::Version(version)),
+        None => Err(CodecError::Malformed("empty frame".to_string())),
+    }
+}
+
+/// Writes the frame of `message` sent by node `from` to `writer`
+pub fn write_frame<W: Write>(
+    writer: &mut W,
+    from: u32,
+    message: &Message,
+) -> Result<(), CodecError> {
+    writer.write_all(&encode(from, message)?)?;
+    Ok(())
+}
+
+/// Reads the next frame of `reader`, `None` once the stream ended between two frames
+pub fn read_frame<R: Read>(reader: &mut R) -> Result<Option<(u32, Message)>, CodecError> {
+    let mut prefix = [0; 4];
+    match reader.read_exact(&mut prefix) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let length = u32::from_be_bytes(prefix);
+    if length > MAX_FRAME_SIZE {
+        return Err(CodecError::TooLarge(length as usize));
+    }
+    let mut body = vec![0; length as usize];
+    reader.read_exact(&mut body)?;
+    decode(&body).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::dag::messages::{ClientRequest, DAGMessage, Vertex};
+    use crate::node::request::RequestId;
+    use std::io::Cursor;
+
+    fn vertex() -> Message {
+        let batch = vec![ClientRequest::new(RequestId::new(5, 3), 7)];
+        Message::DAG(DAGMessage::Vertex(Vertex::new(
+            2,
+            4,
+            batch,
+            vec![1, 2, 3],
+            vec![(0, 4)],
+        )))
+    }
+
+    #[test]
+    fn check_frames_are_read_as_written() {
+        let mut stream = Vec::new();
+        write_frame(&mut stream, 4, &vertex()).unwrap();
+        write_frame(&mut stream, 1, &Message::Dummy).unwrap();
+        let length = u32::from_be_bytes([stream[0], stream[1], stream[2], stream[3]]);
+        assert_eq!(stream[4], WIRE_VERSION);
+
+        let mut reader = Cursor::new(stream);
+        assert_eq!(read_frame(&mut reader).unwrap(), Some((4, vertex())));
+        assert_eq!(reader.position(), 4 + u64::from(length));
+        assert_eq!(read_frame(&mut reader).unwrap(), Some((1, Message::Dummy)));
+        assert_eq!(read_frame(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn check_frames_of_other_versions_are_skipped() {
+        let mut frame = encode(2, &vertex()).unwrap();
+        frame[4] = WIRE_VERSION + 1;
+        write_frame(&mut frame, 3, &Message::Dummy).unwrap();
+
+        let mut reader = Cursor::new(frame);
+        let error = read_frame(&mut reader).unwrap_err();
+        assert!(matches!(error, CodecError::Version(v) if v == WIRE_VERSION + 1));
+        assert!(error.is_recoverable());
+        assert_eq!(read_frame(&mut reader).unwrap(), Some((3, Message::Dummy)));
+    }
+
+    #[test]
+    fn check_broken_streams_are_rejected() {
+        let mut reader = Cursor::new((MAX_FRAME_SIZE + 1).to_be_bytes().to_vec());
+        let error = read_frame(&mut reader).unwrap_err();
+        assert!(matches!(error, CodecError::TooLarge(_)));
+        assert!(!error.is_recoverable());
+
+        // the stream ends within a frame
+        let mut frame = encode(2, &vertex()).unwrap();
+        frame.truncate(frame.len() - 1);
+        assert!(matches!(
+            read_frame(&mut Cursor::new(frame)),
+            Err(CodecError::Io(_))
+        ));
+
+        let garbage = [&[0, 0, 0, 3, WIRE_VERSION][..], &[0xff, 0xff]].concat();
+        let error = read_frame(&mut Cursor::new(garbage)).unwrap_err();
+        assert!(matches!(error, CodecError::Malformed(_)));
+    }
+}
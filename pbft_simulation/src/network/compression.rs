@@ -0,0 +1,73 @@
+/***************************************************************************************************
+Compression of large messages, e.g. certificates and batches. The sender compresses a message of at
+least `threshold` bytes before it leaves the node and the receiver decompresses it before handling
+it, both spend `cost` per byte of the uncompressed message. On the wire, the message takes `ratio`
+of its size, which shortens its transfer on links with a limited `network.bandwidth`.
+***************************************************************************************************/
+
+use serde::Serialize;
+
+/// The compression of the messages, the [compression] section
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Compression {
+    /// Messages of at least this many bytes are compressed, `None` sends all messages as they are
+    pub threshold: Option<u32>,
+    /// Size of a compressed message relative to its uncompressed size
+    pub ratio: f64,
+    /// CPU time (µs) per byte of the uncompressed message, for compressing as for decompressing
+    pub cost: f64,
+}
+
+/// A message as it is sent compressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compressed {
+    /// Size (bytes) on the wire
+    pub size: u32,
+    /// CPU time (µs) of the sender to compress the message and of the receiver to decompress it
+    pub cost: u64,
+}
+
+impl Compression {
+    /// Sends all messages as they are
+    pub fn none() -> Self {
+        Compression {
+            threshold: None,
+            ratio: 1.0,
+            cost: 0.0,
+        }
+    }
+
+    /// The compressed message of `size` bytes, `None` if it is sent as is
+    pub fn apply(&self, size: u32) -> Option<Compressed> {
+        match self.threshold {
+            Some(threshold) if size > 0 && size >= threshold => Some(Compressed {
+                size: ((f64::from(size) * self.ratio).ceil() as u32).max(1),
+                cost: (f64::from(size) * self.cost).round() as u64,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_only_large_messages_are_compressed() {
+        let compression = Compression {
+            threshold: Some(1000),
+            ratio: 0.25,
+            cost: 0.01,
+        };
+        assert_eq!(compression.apply(999), None);
+        assert_eq!(
+            compression.apply(4096),
+            Some(Compressed {
+                size: 1024,
+                cost: 41
+            })
+        );
+        assert_eq!(Compression::none().apply(1_000_000), None);
+    }
+}
@@ -0,0 +1,127 @@
+/***************************************************************************************************
+Conditions of single links that change over simulated time, e.g. the links between node 1 and 2
+take 500ms from 10s to 20s. A degraded link takes its own delay and loses messages with its own
+probability, until it is restored to the conditions of the rest of the network. The changes are
+network events, so they can be scheduled by the [network] section or by scenarios.
+***************************************************************************************************/
+
+use serde::Serialize;
+
+use crate::network::event::NetworkEvent;
+use crate::simulation::event::Event;
+use crate::simulation::time::Time;
+
+/// The conditions of the links from `from` to `to`, `None` stands for any node. `None` for the
+/// delay or the loss keeps that of the network.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LinkCondition {
+    pub from: Option<u32>,
+    pub to: Option<u32>,
+    /// Delay (ms) of a hop over the link, instead of the delay between `delay_min` and `delay_max`
+    pub delay: Option<u64>,
+    /// Omission probability of the link
+    pub loss: Option<f64>,
+}
+
+impl LinkCondition {
+    /// Returns `true` if the link from `from` to `to` is one of the links
+    pub fn matches(&self, from: u32, to: u32) -> bool {
+        self.from.unwrap_or(from) == from && self.to.unwrap_or(to) == to
+    }
+
+    /// Links named by both ends take precedence over links with a wildcard
+    pub fn is_exact(&self) -> bool {
+        self.from.is_some() && self.to.is_some()
+    }
+
+    /// Returns `true` if the condition applies to the same links as `from` to `to`
+    pub fn is_for(&self, from: Option<u32>, to: Option<u32>) -> bool {
+        self.from == from && self.to == to
+    }
+
+    /// A link without a delay and a loss of its own has the conditions of the network
+    pub fn is_restored(&self) -> bool {
+        self.delay.is_none() && self.loss.is_none()
+    }
+
+    // the network events that set the delay and the loss of the links, `None` restores them
+    fn events(&self, delay: Option<u64>, probability: Option<f64>) -> Vec<NetworkEvent> {
+        let (from, to) = (self.from, self.to);
+        let mut events = Vec::new();
+        if self.delay.is_some() {
+            events.push(NetworkEvent::SetLinkDelay { from, to, delay });
+        }
+        if self.loss.is_some() {
+            events.push(NetworkEvent::SetLossRate {
+                from,
+                to,
+                probability,
+            });
+        }
+        events
+    }
+}
+
+/// A period (ms) in which links are degraded, `None` as the end keeps them degraded
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScheduledCondition {
+    pub start: u64,
+    pub end: Option<u64>,
+    pub condition: LinkCondition,
+}
+
+impl ScheduledCondition {
+    /// Converts the period to the network events that degrade the links and restore them
+    pub fn into_events(self) -> Vec<Event> {
+        let degrade = self
+            .condition
+            .events(self.condition.delay, self.condition.loss)
+            .into_iter()
+            .map(|e| Event::new_network(e, Time::new(self.start)));
+        let restore = self.end.into_iter().flat_map(|end| {
+            self.condition
+                .events(None, None)
+                .into_iter()
+                .map(move |e| Event::new_network(e, Time::new(end)))
+        });
+        degrade.chain(restore).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::event::EventType;
+
+    #[test]
+    fn check_scheduled_condition_degrades_and_restores() {
+        let condition = LinkCondition {
+            from: Some(1),
+            to: None,
+            delay: Some(500),
+            loss: Some(0.2),
+        };
+        assert!(condition.matches(1, 4));
+        assert!(!condition.matches(4, 1));
+        assert!(!condition.is_exact());
+
+        let events = ScheduledCondition {
+            start: 10_000,
+            end: Some(20_000),
+            condition,
+        }
+        .into_events();
+        assert_eq!(
+            events.iter().map(|e| e.time.milli()).collect::<Vec<_>>(),
+            vec![10_000, 10_000, 20_000, 20_000]
+        );
+        assert_eq!(
+            events[3].event_type,
+            EventType::Network(NetworkEvent::SetLossRate {
+                from: Some(1),
+                to: None,
+                probability: None
+            })
+        );
+    }
+}
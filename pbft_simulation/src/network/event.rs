@@ -0,0 +1,33 @@
+/***************************************************************************************************
+Events that reconfigure the network at their simulated time, e.g. a link that slows down, a
+partition or another topology. Scenarios and the [network] section schedule them, see
+`Event::new_network`.
+***************************************************************************************************/
+
+use serde::{Deserialize, Serialize};
+
+/// A change of the network, `None` as an end of a link stands for any node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum NetworkEvent {
+    /// A hop over the links from `from` to `to` takes `delay` ms plus the jitter, `None` restores
+    /// the delay of the network
+    SetLinkDelay {
+        from: Option<u32>,
+        to: Option<u32>,
+        delay: Option<u64>,
+    },
+    /// The links from `from` to `to` lose messages with `probability`, `None` restores the loss of
+    /// the network
+    SetLossRate {
+        from: Option<u32>,
+        to: Option<u32>,
+        probability: Option<f64>,
+    },
+    /// The given nodes are cut off from all other nodes, replacing any previous partition
+    PartitionStart(Vec<u32>),
+    /// Removes the partition
+    PartitionEnd,
+    /// The messages are routed over the given topology from now on, 'mesh', 'star', 'ring',
+    /// 'clusters' or 'matrix' with the parameters of the [network] section
+    TopologyChange(String),
+}
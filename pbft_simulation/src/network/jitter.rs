@@ -0,0 +1,100 @@
+/***************************************************************************************************
+Jitter of the network delay. It is drawn for every hop on top of the base delay between
+`delay_min` and `delay_max`, so the tail of the delays can be varied without moving their median.
+***************************************************************************************************/
+
+use std::f64::consts::PI;
+
+use rand::Rng;
+use serde::Serialize;
+
+/// The process the jitter is drawn from, all parameters in ms
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Jitter {
+    None,
+    /// Normally distributed around 0, a delay shortened below 0 is 0
+    Gaussian {
+        std_dev: f64,
+    },
+    /// A heavy tail (Pareto distribution shifted to start at 0, i.e. Lomax), the smaller `shape`
+    /// the heavier the tail. Its median is `scale * (2^(1 / shape) - 1)`.
+    Pareto {
+        scale: f64,
+        shape: f64,
+    },
+}
+
+impl Jitter {
+    /// Draws the jitter (ms) of a single hop
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        match *self {
+            Jitter::None => 0.0,
+            Jitter::Gaussian { std_dev } => {
+                // Box-Muller transform
+                let u1 = 1.0 - rng.gen::<f64>();
+                let u2 = rng.gen::<f64>();
+                std_dev * (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+            }
+            Jitter::Pareto { scale, shape } => {
+                let u = 1.0 - rng.gen::<f64>();
+                scale * (u.powf(-1.0 / shape) - 1.0)
+            }
+        }
+    }
+
+    /// Adds the jitter of a single hop to the `base` delay (µs)
+    pub fn apply<R: Rng>(&self, base: u64, rng: &mut R) -> u64 {
+        match self {
+            Jitter::None => base,
+            _ => (base as f64 + self.sample(rng) * 1000.0).round().max(0.0) as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn samples(jitter: Jitter) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut samples: Vec<f64> = (0..20_000).map(|_| jitter.sample(&mut rng)).collect();
+        samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        samples
+    }
+
+    #[test]
+    fn check_gaussian_jitter_keeps_the_median() {
+        let samples = samples(Jitter::Gaussian { std_dev: 10.0 });
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        let variance =
+            samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / samples.len() as f64;
+        assert!(mean.abs() < 0.5, "mean {}", mean);
+        assert!(
+            (variance.sqrt() - 10.0).abs() < 0.5,
+            "std dev {}",
+            variance.sqrt()
+        );
+
+        let mut rng = StdRng::seed_from_u64(7);
+        // a delay is not shortened below 0
+        let jitter = Jitter::Gaussian { std_dev: 1000.0 };
+        assert!((0..100).any(|_| jitter.apply(5, &mut rng) == 0));
+        assert_eq!(Jitter::None.apply(5, &mut rng), 5);
+    }
+
+    #[test]
+    fn check_pareto_jitter_has_a_heavy_tail() {
+        let samples = samples(Jitter::Pareto {
+            scale: 10.0,
+            shape: 1.5,
+        });
+        assert!(samples[0] >= 0.0);
+        let median = samples[samples.len() / 2];
+        let expected = 10.0 * (2f64.powf(1.0 / 1.5) - 1.0);
+        assert!((median - expected).abs() < 0.5, "median {}", median);
+        // the 99.9th percentile is far beyond the median
+        assert!(samples[samples.len() * 999 / 1000] > 50.0 * median);
+    }
+}
@@ -2,61 +2,799 @@
 Everything related to the network.
 ***************************************************************************************************/
 
+use std::collections::{BTreeMap, HashSet};
+
 use log::debug;
-use mc_utils::ini::env2var;
-use rand::Rng;
-use rand::rngs::ThreadRng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::simulation::event::{Broadcast, Event, Gossip, Message};
+use crate::simulation::settings::{LinkLoss, NetworkSettings};
+use crate::simulation::statistics::TrafficStatistics;
+use crate::simulation::time::{Duration, Time};
+use adversary::{Adversary, BoundedAdversary, Delivery};
+use conditions::LinkCondition;
+use event::NetworkEvent;
+use jitter::Jitter;
+use topology::{build_topology, Topology};
 
-use crate::simulation::event::{Broadcast, Event};
-use crate::simulation::time::Time;
+pub mod adversary;
+pub mod codec;
+pub mod compression;
+pub mod conditions;
+pub mod event;
+pub mod jitter;
+pub mod topology;
 
 /// Network abstraction
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Network {
     omission_prob: f64,
-    delay_min: u32,
-    delay_max: u32,
-    my_rng: ThreadRng,
+    // Links whose omission probability differs from `omission_prob`
+    link_loss: Vec<LinkLoss>,
+    // Links that are currently degraded, they take precedence over `link_loss`
+    conditions: Vec<LinkCondition>,
+    // The settings the network was built from, a new topology takes its parameters from them
+    settings: NetworkSettings,
+    // Gilbert-Elliott model of bursty loss, every link turns bad with `burst_enter` and good again
+    // with `burst_exit` per message. A bad link loses messages with `burst_loss`.
+    burst_enter: f64,
+    burst_exit: f64,
+    burst_loss: f64,
+    // Links that are currently bad
+    bad_links: HashSet<(u32, u32)>,
+    // Messages delayed longer than this (µs) are dropped
+    ttl: Option<u64>,
+    // Range of the delay (µs) of a hop
+    delay_min: u64,
+    delay_max: u64,
+    // Drawn for every hop on top of the delay between `delay_min` and `delay_max`
+    jitter: Jitter,
+    my_rng: StdRng,
+    // Counters of the traffic per message type
+    traffic: TrafficStatistics,
+    // Routes the messages between the nodes
+    topology: Box<dyn Topology>,
+    number_of_nodes: u32,
+    // Number of nodes each node relays a gossip to
+    gossip_fanout: u32,
+    // Number of relay rounds of a gossip
+    gossip_rounds: u32,
+    // Latency added by relaying a gossip (µs)
+    gossip_hop_delay: u64,
+    // Nodes that crashed, they neither send nor receive
+    crashed: HashSet<u32>,
+    // Nodes cut off from all other nodes
+    partition: HashSet<u32>,
+    // Byzantine nodes and the probability that a message of them carries an invalid authenticator
+    byzantine: HashSet<u32>,
+    invalid_authentication: f64,
+    // Chooses the delays of the messages instead of the network, up to its bound
+    adversary: Option<BoundedAdversary>,
+    // Bandwidth (Mbit/s) of every link, `None` transfers messages of any size at once
+    bandwidth: Option<f64>,
 }
 impl Network {
-    pub fn new() -> Self {
+    pub fn new(number_of_nodes: u32, settings: &NetworkSettings) -> Self {
         Network {
-            omission_prob: env2var("network.omission_probability"),
-            delay_min: env2var("network.delay_min"),
-            delay_max: env2var("network.delay_max"),
-            my_rng: rand::thread_rng(),
+            omission_prob: settings.omission_probability,
+            link_loss: settings.loss.clone(),
+            conditions: Vec::new(),
+            settings: settings.clone(),
+            burst_enter: settings.burst_enter,
+            burst_exit: settings.burst_exit,
+            burst_loss: settings.burst_loss,
+            bad_links: HashSet::new(),
+            ttl: settings.ttl.map(|ttl| ttl * 1000),
+            delay_min: (settings.delay_min * 1000.0).round() as u64,
+            delay_max: (settings.delay_max * 1000.0).round() as u64,
+            jitter: settings.jitter,
+            my_rng: StdRng::from_entropy(),
+            traffic: TrafficStatistics::default(),
+            topology: build_topology(number_of_nodes, settings),
+            number_of_nodes,
+            gossip_fanout: settings.gossip_fanout,
+            gossip_rounds: settings.gossip_rounds,
+            gossip_hop_delay: settings.gossip_hop_delay * 1000,
+            crashed: HashSet::new(),
+            partition: HashSet::new(),
+            byzantine: HashSet::new(),
+            invalid_authentication: 0.0,
+            adversary: None,
+            bandwidth: settings.bandwidth,
+        }
+    }
+
+    /// Draws the delays, losses and relays from `seed` instead of the entropy of the system, so a
+    /// run can be repeated
+    pub fn seed(&mut self, seed: u64) {
+        self.my_rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Lets a message of any of the `nodes` carry an invalid authenticator with `probability`
+    pub fn forge_authentication(&mut self, nodes: &[u32], probability: f64) {
+        self.byzantine = nodes.iter().copied().collect();
+        self.invalid_authentication = probability;
+    }
+
+    /// Lets `adversary` choose the delay of every message, up to `network.adversary_delta`.
+    /// Messages with a fixed delay are not subject to it.
+    pub fn set_adversary(&mut self, adversary: Box<dyn Adversary>) {
+        let delta = Duration::from_millis(self.settings.adversary_delta);
+        self.adversary = Some(BoundedAdversary::new(adversary, delta));
+    }
+
+    // the delay (µs) of a message the network delivers after `delay`, chosen by the adversary if
+    // there is one
+    fn adversarial_delay(
+        &mut self,
+        from: u32,
+        to: u32,
+        message: &Message,
+        sent: Time,
+        delay: u64,
+    ) -> u64 {
+        match &mut self.adversary {
+            Some(adversary) => adversary
+                .delay(&Delivery {
+                    from,
+                    to,
+                    message,
+                    sent,
+                    delay: Duration::from_micros(delay),
+                })
+                .micros(),
+            None => delay,
+        }
+    }
+
+    // decides whether a message of `from` carries an invalid authenticator
+    fn is_forged(&mut self, from: u32) -> bool {
+        self.invalid_authentication > 0.0
+            && self.byzantine.contains(&from)
+            && self.my_rng.gen::<f64>() < self.invalid_authentication
+    }
+
+    /// Crashes the node `id`, all messages from and to the node are lost
+    pub fn crash(&mut self, id: u32) {
+        self.crashed.insert(id);
+    }
+
+    /// Returns `true` if the node `id` crashed
+    pub fn is_crashed(&self, id: u32) -> bool {
+        self.crashed.contains(&id)
+    }
+
+    /// Cuts the `nodes` off from all other nodes, replacing any previous partition
+    pub fn partition(&mut self, nodes: Vec<u32>) {
+        self.partition = nodes.into_iter().collect();
+    }
+
+    /// Removes the partition
+    pub fn heal(&mut self) {
+        self.partition.clear();
+    }
+
+    /// Reconfigures the network
+    pub fn handle_network_event(&mut self, network_event: NetworkEvent) {
+        match network_event {
+            NetworkEvent::SetLinkDelay { from, to, delay } => {
+                self.set_condition(from, to, |c| c.delay = delay)
+            }
+            NetworkEvent::SetLossRate {
+                from,
+                to,
+                probability,
+            } => self.set_condition(from, to, |c| c.loss = probability),
+            NetworkEvent::PartitionStart(nodes) => self.partition(nodes),
+            NetworkEvent::PartitionEnd => self.heal(),
+            NetworkEvent::TopologyChange(topology) => {
+                let settings = NetworkSettings {
+                    topology,
+                    ..self.settings.clone()
+                };
+                self.topology = build_topology(self.number_of_nodes, &settings);
+            }
         }
     }
 
+    // changes the condition of the links from `from` to `to`, it takes precedence over the earlier
+    // conditions of overlapping links. Links without a delay and a loss of their own are restored.
+    fn set_condition(
+        &mut self,
+        from: Option<u32>,
+        to: Option<u32>,
+        set: impl FnOnce(&mut LinkCondition),
+    ) {
+        let mut condition = match self.conditions.iter().position(|c| c.is_for(from, to)) {
+            Some(index) => self.conditions.remove(index),
+            None => LinkCondition {
+                from,
+                to,
+                delay: None,
+                loss: None,
+            },
+        };
+        set(&mut condition);
+        if !condition.is_restored() {
+            self.conditions.push(condition);
+        }
+    }
+
+    // the condition of the link from `from` to `to` that sets `value`, the last matching condition
+    // wins and links named by both ends take precedence over links with a wildcard
+    fn condition<T>(
+        &self,
+        from: u32,
+        to: u32,
+        value: impl Fn(&LinkCondition) -> Option<T>,
+    ) -> Option<T> {
+        let matching = || {
+            self.conditions
+                .iter()
+                .rev()
+                .filter(|c| c.matches(from, to))
+                .filter_map(|c| value(c).map(|v| (c.is_exact(), v)))
+        };
+        matching()
+            .find(|(exact, _)| *exact)
+            .or_else(|| matching().next())
+            .map(|(_, v)| v)
+    }
+
+    // messages between `from` and `to` are lost if either crashed or the partition separates them
+    fn is_cut(&self, from: u32, to: u32) -> bool {
+        self.is_crashed(from)
+            || self.is_crashed(to)
+            || self.partition.contains(&from) != self.partition.contains(&to)
+    }
+
+    // the omission probability of the link from `from` to `to`, the last matching link wins and
+    // links named by both ends take precedence over links with a wildcard
+    fn omission_probability(&self, from: u32, to: u32) -> f64 {
+        if let Some(loss) = self.condition(from, to, |c| c.loss) {
+            return loss;
+        }
+        let matching = || self.link_loss.iter().rev().filter(|l| l.matches(from, to));
+        matching()
+            .find(|l| l.is_exact())
+            .or_else(|| matching().next())
+            .map_or(self.omission_prob, |l| l.probability)
+    }
+
+    // moves the link from `from` to `to` on to its state for the next message, returns `true` if
+    // the link is bad
+    fn is_bad(&mut self, from: u32, to: u32) -> bool {
+        if self.burst_enter <= 0.0 {
+            return false;
+        }
+        let link = (from, to);
+        if self.bad_links.contains(&link) {
+            if self.my_rng.gen::<f64>() < self.burst_exit {
+                debug!(target: "simulation", "Link from {} to {} turns good", from, to);
+                self.bad_links.remove(&link);
+                return false;
+            }
+            true
+        } else if self.my_rng.gen::<f64>() < self.burst_enter {
+            debug!(target: "simulation", "Link from {} to {} turns bad", from, to);
+            self.bad_links.insert(link);
+            true
+        } else {
+            false
+        }
+    }
+
+    // decides whether the link from `from` to `to` loses a message
+    fn is_omitted(&mut self, from: u32, to: u32) -> bool {
+        let probability = if self.is_bad(from, to) {
+            self.burst_loss
+        } else {
+            self.omission_probability(from, to)
+        };
+        probability > 0.0 && self.my_rng.gen::<f64>() <= probability
+    }
+
+    // messages delayed for longer than the ttl are dropped
+    fn is_expired(&self, delay: u64) -> bool {
+        matches!(self.ttl, Some(ttl) if delay > ttl)
+    }
+
+    /// Returns the traffic that was handled by the network so far
+    pub fn traffic(&self) -> &TrafficStatistics {
+        &self.traffic
+    }
+
     /// Handles broadcasts on the network
     pub fn handle_broadcast(&mut self, time: Time, broadcast: Broadcast) -> Option<Event> {
-        // apply the omission probability
-        if !broadcast.reliable
-            && self.omission_prob > 0.0
-            && self.my_rng.gen::<f64>() <= self.omission_prob
-        {
-            debug!(target: "simulation", "Message is omitted: {:?}", &broadcast);
+        self.traffic.record_sent(&broadcast.message, broadcast.size);
+
+        let route = self.topology.route(broadcast.id_from, broadcast.id_to);
+
+        if self.is_cut(broadcast.id_from, broadcast.id_to) {
+            debug!(target: "simulation", "Message is cut off: {:?}", &broadcast);
+            self.traffic.record_dropped(&broadcast.message);
             return None;
         }
 
-        // set the delay to random value between the min and max value
+        // apply the omission probability, every hop of the route may lose the message
+        if !broadcast.reliable {
+            let mut hop_from = broadcast.id_from;
+            for &hop_to in &route {
+                if self.is_omitted(hop_from, hop_to) {
+                    debug!(target: "simulation", "Message is omitted: {:?}", &broadcast);
+                    self.traffic.record_dropped(&broadcast.message);
+                    return None;
+                }
+                hop_from = hop_to;
+            }
+        }
+
+        // accumulate the delay of all hops on the route
         let delay = match broadcast.fixed_delay {
-            Some(t) => t.milli(),
+            Some(t) => t.micros(),
             None => {
-                if self.delay_min == self.delay_max {
-                    u64::from(self.delay_min)
-                } else {
-                    self.my_rng
-                        .gen_range(u64::from(self.delay_min), u64::from(self.delay_max))
+                let mut delay = 0;
+                let mut hop_from = broadcast.id_from;
+                for &hop_to in &route {
+                    delay += self.hop_delay(hop_from, hop_to) + self.transfer(broadcast.size);
+                    hop_from = hop_to;
                 }
+                delay
             }
         };
 
+        if !broadcast.reliable && self.is_expired(delay) {
+            debug!(target: "simulation", "Message expired after {}µs: {:?}", delay, &broadcast);
+            self.traffic.record_dropped(&broadcast.message);
+            return None;
+        }
+        let delay = match broadcast.fixed_delay {
+            Some(_) => delay,
+            None => self.adversarial_delay(
+                broadcast.id_from,
+                broadcast.id_to,
+                &broadcast.message,
+                time,
+                delay,
+            ),
+        };
+
         // Create the respective reception event
-        Some(Event::new_reception(
-            broadcast.id_to,
-            broadcast.message,
-            time.add_milli(delay),
-        ))
+        let decompression = broadcast.decompression;
+        if self.is_forged(broadcast.id_from) {
+            debug!(target: "simulation", "Message carries an invalid authenticator: {:?}", &broadcast);
+            return Some(
+                Event::new_forged_reception(
+                    broadcast.id_to,
+                    broadcast.message,
+                    time + Duration::from_micros(delay),
+                )
+                .with_decompression(decompression),
+            );
+        }
+        Some(
+            Event::new_reception(
+                broadcast.id_to,
+                broadcast.message,
+                time + Duration::from_micros(delay),
+            )
+            .with_decompression(decompression),
+        )
+    }
+
+    /// Handles gossips on the network. In every round, each node that received the message in the
+    /// previous round relays it to `gossip_fanout` random nodes of `peers`, the nodes the gossip
+    /// spreads among. Once the relays are over, the sender sends the message directly to the peers
+    /// the gossip missed, so it reaches them as reliably as a message sent to them. Returns a
+    /// reception for every node the message reached, at the time of its first arrival.
+    pub fn handle_gossip(&mut self, time: Time, gossip: Gossip, peers: &[u32]) -> Vec<Event> {
+        // the relays forward the authenticator of the sender as is
+        let forged = self.is_forged(gossip.id_from);
+        // earliest delay after which each node received the message
+        let mut arrivals: BTreeMap<u32, u64> = BTreeMap::new();
+        arrivals.insert(gossip.id_from, 0);
+        let mut relays = vec![gossip.id_from];
+
+        for _ in 0..self.gossip_rounds {
+            let mut informed = Vec::new();
+
+            for &relay in &relays {
+                let sent_after = arrivals[&relay];
+                // any peer but the relay itself
+                let targets: Vec<u32> = peers.iter().copied().filter(|&p| p != relay).collect();
+                // a single node has nobody to relay the message to
+                if targets.is_empty() {
+                    continue;
+                }
+
+                for _ in 0..self.gossip_fanout {
+                    let target = targets[self.my_rng.gen_range(0, targets.len())];
+
+                    self.traffic.record_sent(&gossip.message, gossip.size);
+                    if self.is_cut(relay, target) || self.is_omitted(relay, target) {
+                        debug!(target: "simulation", "Gossip from {} to {} is omitted: {:?}", relay, target, &gossip);
+                        self.traffic.record_dropped(&gossip.message);
+                        continue;
+                    }
+
+                    let arrival = sent_after
+                        + self.gossip_hop_delay
+                        + self.hop_delay(relay, target)
+                        + self.transfer(gossip.size);
+                    if self.is_expired(arrival) {
+                        debug!(target: "simulation", "Gossip from {} to {} expired after {}µs: {:?}", relay, target, arrival, &gossip);
+                        self.traffic.record_dropped(&gossip.message);
+                        continue;
+                    }
+
+                    match arrivals.get(&target) {
+                        Some(&earlier) if earlier <= arrival => {}
+                        Some(_) => {
+                            arrivals.insert(target, arrival);
+                        }
+                        None => {
+                            arrivals.insert(target, arrival);
+                            informed.push(target);
+                        }
+                    }
+                }
+            }
+            relays = informed;
+        }
+
+        // the peers the relays missed get the message from the sender after the last relay
+        let relayed_after = arrivals.values().copied().max().unwrap_or(0);
+        let sender = gossip.id_from;
+        for &target in peers {
+            if target == sender || arrivals.contains_key(&target) {
+                continue;
+            }
+
+            self.traffic.record_sent(&gossip.message, gossip.size);
+            if self.is_cut(sender, target) || self.is_omitted(sender, target) {
+                debug!(target: "simulation", "Gossip from {} to {} is omitted: {:?}", sender, target, &gossip);
+                self.traffic.record_dropped(&gossip.message);
+                continue;
+            }
+
+            let arrival =
+                relayed_after + self.hop_delay(sender, target) + self.transfer(gossip.size);
+            if self.is_expired(arrival) {
+                debug!(target: "simulation", "Gossip from {} to {} expired after {}µs: {:?}", sender, target, arrival, &gossip);
+                self.traffic.record_dropped(&gossip.message);
+                continue;
+            }
+            arrivals.insert(target, arrival);
+        }
+
+        arrivals.remove(&gossip.id_from);
+        for (&id, delay) in arrivals.iter_mut() {
+            *delay = self.adversarial_delay(gossip.id_from, id, &gossip.message, time, *delay);
+        }
+        arrivals
+            .into_iter()
+            .map(|(id, delay)| {
+                let reception = if forged {
+                    Event::new_forged_reception(
+                        id,
+                        gossip.message.clone(),
+                        time + Duration::from_micros(delay),
+                    )
+                } else {
+                    Event::new_reception(
+                        id,
+                        gossip.message.clone(),
+                        time + Duration::from_micros(delay),
+                    )
+                };
+                reception.with_decompression(gossip.decompression)
+            })
+            .collect()
+    }
+
+    // the delay (µs) of a single hop, a degraded link takes its own delay plus the jitter instead
+    // of the delay of the network and the topology
+    fn hop_delay(&mut self, from: u32, to: u32) -> u64 {
+        match self.condition(from, to, |c| c.delay) {
+            Some(delay) => self.jitter.apply(delay * 1000, &mut self.my_rng),
+            None => self.random_delay() + self.topology.link_delay(from, to) * 1000,
+        }
+    }
+
+    // the time (µs) a message of `size` bytes takes over a link with the bandwidth of the network
+    fn transfer(&self, size: u32) -> u64 {
+        match self.bandwidth {
+            Some(bandwidth) => (f64::from(size) * 8.0 / bandwidth).round() as u64,
+            None => 0,
+        }
+    }
+
+    // set the delay (µs) to random value between the min and max value, plus the jitter
+    fn random_delay(&mut self) -> u64 {
+        let base = if self.delay_min == self.delay_max {
+            self.delay_min
+        } else {
+            self.my_rng.gen_range(self.delay_min, self.delay_max)
+        };
+        self.jitter.apply(base, &mut self.my_rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::event::{EventType, Message};
+    use crate::simulation::settings::Settings;
+
+    #[test]
+    fn check_link_loss_overrides_global_omission() {
+        let settings = NetworkSettings {
+            omission_probability: 0.1,
+            loss: vec![
+                LinkLoss {
+                    from: Some(1),
+                    to: Some(2),
+                    probability: 1.0,
+                },
+                LinkLoss {
+                    from: None,
+                    to: Some(2),
+                    probability: 0.3,
+                },
+                LinkLoss {
+                    from: Some(3),
+                    to: None,
+                    probability: 0.5,
+                },
+            ],
+            ..Settings::default().network
+        };
+        let mut network = Network::new(4, &settings);
+        assert_eq!(network.omission_probability(1, 2), 1.0);
+        assert_eq!(network.omission_probability(4, 2), 0.3);
+        assert_eq!(network.omission_probability(3, 2), 0.5);
+        assert_eq!(network.omission_probability(3, 1), 0.5);
+        assert_eq!(network.omission_probability(2, 1), 0.1);
+
+        // the loss is asymmetric, the way back is only subject to the global probability
+        network.omission_prob = 0.0;
+        for _ in 0..20 {
+            let lost = Broadcast::new(1, 2, Message::Dummy);
+            assert_eq!(network.handle_broadcast(Time::new(0), lost), None);
+            let back = Broadcast::new(2, 1, Message::Dummy);
+            assert!(network.handle_broadcast(Time::new(0), back).is_some());
+        }
+    }
+
+    #[test]
+    fn check_bursts_of_loss() {
+        let settings = NetworkSettings {
+            burst_enter: 0.05,
+            burst_exit: 0.2,
+            burst_loss: 1.0,
+            ..Settings::default().network
+        };
+        let mut network = Network::new(4, &settings);
+
+        // with the link losing messages only while bad, the losses come in runs of 1 / burst_exit
+        // messages on average
+        let mut lost = 0;
+        let mut bursts = 0;
+        let mut previous_lost = false;
+        for _ in 0..10_000 {
+            let broadcast = Broadcast::new(1, 2, Message::Dummy);
+            let is_lost = network.handle_broadcast(Time::new(0), broadcast).is_none();
+            if is_lost {
+                lost += 1;
+                if !previous_lost {
+                    bursts += 1;
+                }
+            }
+            previous_lost = is_lost;
+        }
+        assert!(bursts > 0);
+        assert!(lost / bursts >= 3, "{} losses in {} bursts", lost, bursts);
+
+        // the other links keep their own state
+        let mut network = Network::new(4, &settings);
+        network.bad_links.insert((1, 2));
+        network.burst_exit = 0.0;
+        assert!(network.is_bad(1, 2));
+        network.burst_enter = 1e-9;
+        assert!(!network.is_bad(2, 1));
+    }
+
+    #[test]
+    fn check_late_messages_expire() {
+        let settings = NetworkSettings {
+            delay_min: 50.0,
+            delay_max: 50.0,
+            ttl: Some(40),
+            ..Settings::default().network
+        };
+        let mut network = Network::new(4, &settings);
+        let late = Broadcast::new(1, 2, Message::Dummy);
+        assert_eq!(network.handle_broadcast(Time::new(0), late), None);
+        // deliveries to the client are not subject to the network
+        let reliable = Broadcast::new_custom(1, 2, Message::Dummy, true, None);
+        assert!(network.handle_broadcast(Time::new(0), reliable).is_some());
+
+        network.ttl = Some(50_000);
+        let in_time = Broadcast::new(1, 2, Message::Dummy);
+        assert!(network.handle_broadcast(Time::new(0), in_time).is_some());
+    }
+
+    #[test]
+    fn check_jitter_spreads_the_delays() {
+        let settings = NetworkSettings {
+            omission_probability: 0.0,
+            delay_min: 50.0,
+            delay_max: 50.0,
+            jitter: Jitter::Gaussian { std_dev: 10.0 },
+            ..Settings::default().network
+        };
+        let mut network = Network::new(4, &settings);
+        let delays: Vec<u64> = (0..1000)
+            .map(|_| {
+                let broadcast = Broadcast::new(1, 2, Message::Dummy);
+                network
+                    .handle_broadcast(Time::new(0), broadcast)
+                    .unwrap()
+                    .time
+                    .milli()
+            })
+            .collect();
+        let mean = delays.iter().sum::<u64>() as f64 / delays.len() as f64;
+        assert!((mean - 50.0).abs() < 2.0, "mean delay {}", mean);
+        assert!(delays.iter().any(|d| *d < 40) && delays.iter().any(|d| *d > 60));
+    }
+
+    #[test]
+    fn check_network_events_reconfigure_the_links() {
+        let settings = NetworkSettings {
+            omission_probability: 0.0,
+            delay_min: 50.0,
+            delay_max: 50.0,
+            ..Settings::default().network
+        };
+        let mut network = Network::new(4, &settings);
+        let delay = |network: &mut Network, from: u32, to: u32| {
+            network
+                .handle_broadcast(Time::new(0), Broadcast::new(from, to, Message::Dummy))
+                .map(|e| e.time.milli())
+        };
+
+        network.handle_network_event(NetworkEvent::SetLinkDelay {
+            from: Some(1),
+            to: Some(2),
+            delay: Some(500),
+        });
+        network.handle_network_event(NetworkEvent::SetLinkDelay {
+            from: None,
+            to: Some(2),
+            delay: Some(300),
+        });
+        network.handle_network_event(NetworkEvent::SetLossRate {
+            from: None,
+            to: Some(2),
+            probability: Some(1.0),
+        });
+        // the exact link keeps its own delay, the loss of the wildcard applies to it
+        assert_eq!(network.condition(1, 2, |c| c.delay), Some(500));
+        assert_eq!(network.omission_probability(1, 2), 1.0);
+        assert_eq!(delay(&mut network, 3, 2), None);
+        // the way back keeps the conditions of the network
+        assert_eq!(delay(&mut network, 2, 1), Some(50));
+
+        network.handle_network_event(NetworkEvent::SetLossRate {
+            from: None,
+            to: Some(2),
+            probability: None,
+        });
+        assert_eq!(delay(&mut network, 3, 2), Some(300));
+        network.handle_network_event(NetworkEvent::SetLinkDelay {
+            from: None,
+            to: Some(2),
+            delay: None,
+        });
+        assert_eq!(delay(&mut network, 1, 2), Some(500));
+        assert_eq!(delay(&mut network, 3, 2), Some(50));
+        assert_eq!(network.conditions.len(), 1);
+
+        network.handle_network_event(NetworkEvent::PartitionStart(vec![1]));
+        assert_eq!(delay(&mut network, 1, 2), None);
+        network.handle_network_event(NetworkEvent::PartitionEnd);
+        assert_eq!(delay(&mut network, 1, 2), Some(500));
+
+        // the star routes 3 to 4 via the hub 1
+        network.handle_network_event(NetworkEvent::TopologyChange("star".to_string()));
+        assert_eq!(
+            delay(&mut network, 3, 4),
+            Some(2 * (50 + settings.hub_delay))
+        );
+    }
+
+    #[test]
+    fn check_gossip_fans_out() {
+        let settings = NetworkSettings {
+            omission_probability: 0.0,
+            delay_min: 10.0,
+            delay_max: 10.0,
+            gossip_fanout: 2,
+            gossip_rounds: 3,
+            gossip_hop_delay: 5,
+            ..Settings::default().network
+        };
+        let receivers = |events: &[Event]| -> Vec<u32> {
+            events
+                .iter()
+                .map(|event| match &event.event_type {
+                    EventType::Reception(reception) => reception.id,
+                    other => panic!("Expected a reception, got {:?}", other),
+                })
+                .collect()
+        };
+
+        // a single node has nobody to relay to
+        let mut alone = Network::new(1, &settings);
+        assert!(alone
+            .handle_gossip(Time::new(0), Gossip::new(1, Message::Dummy, 8), &[1])
+            .is_empty());
+
+        let peers: Vec<u32> = (1..=8).collect();
+        let mut network = Network::new(8, &settings);
+        network.seed(3);
+        let events = network.handle_gossip(Time::new(0), Gossip::new(3, Message::Dummy, 8), &peers);
+        // every node is reached exactly once, the sender not at all
+        assert_eq!(receivers(&events), vec![1, 2, 4, 5, 6, 7, 8]);
+        // each hop takes the relay delay and the delay of the link
+        assert!(events.iter().all(|event| event.time.milli() >= 15));
+        // in each round, every node reached in the round before relays to `gossip_fanout` nodes,
+        // the sender sends to the nodes they missed
+        let sent = network.traffic().get("Dummy", "Dummy").unwrap().sent;
+        assert!((7..=2 * (1 + 2 + 4) + 6).contains(&sent));
+
+        // a single relay reaches one node, the sender sends to the others after it
+        let mut network = Network::new(
+            8,
+            &NetworkSettings {
+                gossip_fanout: 1,
+                gossip_rounds: 1,
+                ..settings.clone()
+            },
+        );
+        network.seed(3);
+        let events = network.handle_gossip(Time::new(0), Gossip::new(3, Message::Dummy, 8), &peers);
+        assert_eq!(receivers(&events), vec![1, 2, 4, 5, 6, 7, 8]);
+        let mut times: Vec<u64> = events.iter().map(|event| event.time.milli()).collect();
+        times.sort_unstable();
+        assert_eq!(times, vec![15, 25, 25, 25, 25, 25, 25]);
+        assert_eq!(network.traffic().get("Dummy", "Dummy").unwrap().sent, 7);
+
+        // relayed to enough nodes, a single round reaches all of them
+        let mut network = Network::new(
+            4,
+            &NetworkSettings {
+                gossip_fanout: 30,
+                gossip_rounds: 1,
+                ..settings.clone()
+            },
+        );
+        network.seed(3);
+        let events = network.handle_gossip(
+            Time::new(0),
+            Gossip::new(3, Message::Dummy, 8),
+            &[1, 2, 3, 4],
+        );
+        assert_eq!(receivers(&events), vec![1, 2, 4]);
+        assert!(events.iter().all(|event| event.time.milli() == 15));
+
+        // nodes that are not among the peers are never reached, nor do they relay
+        let mut network = Network::new(8, &settings);
+        network.seed(3);
+        let events =
+            network.handle_gossip(Time::new(0), Gossip::new(3, Message::Dummy, 8), &[2, 3, 4]);
+        assert_eq!(receivers(&events), vec![2, 4]);
     }
 }
@@ -0,0 +1,202 @@
+/***************************************************************************************************
+Topologies of the network. A topology decides over which nodes a message is routed and how much
+latency each link adds on top of the random network delay.
+***************************************************************************************************/
+
+use std::fmt::Debug;
+
+use crate::simulation::settings::NetworkSettings;
+
+/// All topologies need to implement this trait
+pub trait Topology: Debug {
+    /// Returns the hops of a message from `from` to `to`, excluding `from` and ending with `to`
+    fn route(&self, from: u32, to: u32) -> Vec<u32>;
+
+    /// Fixed latency (in ms) of the direct link between `from` and `to`
+    fn link_delay(&self, _from: u32, _to: u32) -> u64 {
+        0
+    }
+}
+
+// Helper function to generate the topology of the settings
+pub fn build_topology(number_of_nodes: u32, settings: &NetworkSettings) -> Box<dyn Topology> {
+    match settings.topology.as_str() {
+        "mesh" => Box::new(FullMesh),
+        "star" => Box::new(Star {
+            hub: settings.hub,
+            hub_delay: settings.hub_delay,
+        }),
+        "ring" => Box::new(Ring { number_of_nodes }),
+        "clusters" => Box::new(Clusters {
+            number_of_nodes,
+            clusters: settings.clusters,
+            cluster_delay: settings.cluster_delay,
+        }),
+        "matrix" => {
+            let n = number_of_nodes as usize;
+            if settings.latency.len() < n || settings.latency.iter().any(|row| row.len() < n) {
+                panic!("the latency matrix must cover all {} nodes", number_of_nodes);
+            }
+            Box::new(LatencyMatrix {
+                latency: settings.latency.clone(),
+            })
+        }
+        topology => panic!(
+            "topology '{}' is not available, allowed are 'mesh', 'star', 'ring', 'clusters', 'matrix'",
+            topology
+        ),
+    }
+}
+
+/// Every node is directly connected to every other node
+#[derive(Debug)]
+pub struct FullMesh;
+
+impl Topology for FullMesh {
+    fn route(&self, _from: u32, to: u32) -> Vec<u32> {
+        vec![to]
+    }
+}
+
+/// All nodes are connected to a single hub, which relays all messages between the other nodes
+#[derive(Debug)]
+pub struct Star {
+    pub hub: u32,
+    /// Latency added by every link to or from the hub
+    pub hub_delay: u64,
+}
+
+impl Topology for Star {
+    fn route(&self, from: u32, to: u32) -> Vec<u32> {
+        if from == self.hub || to == self.hub {
+            vec![to]
+        } else {
+            vec![self.hub, to]
+        }
+    }
+
+    fn link_delay(&self, _from: u32, _to: u32) -> u64 {
+        self.hub_delay
+    }
+}
+
+/// The nodes 1..=n form a bidirectional ring, messages take the shorter direction
+#[derive(Debug)]
+pub struct Ring {
+    pub number_of_nodes: u32,
+}
+
+impl Topology for Ring {
+    fn route(&self, from: u32, to: u32) -> Vec<u32> {
+        let n = self.number_of_nodes;
+        // ids start with 1, shift them to 0..n for the modulo arithmetic
+        let clockwise = (to + n - from) % n;
+        let step = if clockwise <= n - clockwise { 1 } else { n - 1 };
+
+        let mut route = Vec::new();
+        let mut current = from - 1;
+        while current != to - 1 {
+            current = (current + step) % n;
+            route.push(current + 1);
+        }
+        route
+    }
+}
+
+/// The nodes are split into data centers of consecutive ids, links between data centers add latency
+#[derive(Debug)]
+pub struct Clusters {
+    pub number_of_nodes: u32,
+    pub clusters: u32,
+    /// Latency added by a link between two data centers
+    pub cluster_delay: u64,
+}
+
+impl Clusters {
+    /// Returns the data center of the node `id`
+    pub fn cluster_of(&self, id: u32) -> u32 {
+        (id - 1) * self.clusters / self.number_of_nodes
+    }
+}
+
+impl Topology for Clusters {
+    fn route(&self, _from: u32, to: u32) -> Vec<u32> {
+        vec![to]
+    }
+
+    fn link_delay(&self, from: u32, to: u32) -> u64 {
+        if self.cluster_of(from) == self.cluster_of(to) {
+            0
+        } else {
+            self.cluster_delay
+        }
+    }
+}
+
+/// Every node is directly connected to every other node, each link has its own latency
+#[derive(Debug)]
+pub struct LatencyMatrix {
+    /// Latency of the link from node i + 1 to node j + 1 in row i, column j
+    pub latency: Vec<Vec<u64>>,
+}
+
+impl Topology for LatencyMatrix {
+    fn route(&self, _from: u32, to: u32) -> Vec<u32> {
+        vec![to]
+    }
+
+    fn link_delay(&self, from: u32, to: u32) -> u64 {
+        self.latency[from as usize - 1][to as usize - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::settings::Settings;
+
+    #[test]
+    fn check_star_routes_via_hub() {
+        let star = Star {
+            hub: 1,
+            hub_delay: 10,
+        };
+        assert_eq!(star.route(1, 3), vec![3]);
+        assert_eq!(star.route(3, 1), vec![1]);
+        assert_eq!(star.route(2, 3), vec![1, 3]);
+    }
+
+    #[test]
+    fn check_ring_takes_shorter_direction() {
+        let ring = Ring { number_of_nodes: 6 };
+        assert_eq!(ring.route(1, 3), vec![2, 3]);
+        assert_eq!(ring.route(1, 5), vec![6, 5]);
+        assert_eq!(ring.route(6, 1), vec![1]);
+        assert_eq!(ring.route(2, 5), vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn check_clusters_of_consecutive_ids() {
+        let clusters = Clusters {
+            number_of_nodes: 6,
+            clusters: 2,
+            cluster_delay: 100,
+        };
+        assert_eq!(clusters.link_delay(1, 3), 0);
+        assert_eq!(clusters.link_delay(3, 4), 100);
+        assert_eq!(clusters.link_delay(6, 4), 0);
+    }
+
+    #[test]
+    fn check_latency_matrix_of_the_settings() {
+        let settings = NetworkSettings {
+            topology: "matrix".to_string(),
+            latency: vec![vec![0, 10, 80], vec![10, 0, 80], vec![80, 70, 0]],
+            ..Settings::default().network
+        };
+        let matrix = build_topology(3, &settings);
+        assert_eq!(matrix.route(1, 3), vec![3]);
+        assert_eq!(matrix.link_delay(1, 2), 10);
+        assert_eq!(matrix.link_delay(3, 2), 70);
+    }
+}
@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::node::request::RequestId;
+use crate::simulation::event::{Message, Priority};
+
+/// Type defining the messages of the adaptive node. The messages of the hosted protocol are
+/// tagged with the instance they belong to, the switching sub-protocol aborts an instance and
+/// initializes the next one with the history the aborted instance committed.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+pub enum AdaptiveMessage {
+    ClientRequest(ClientRequest),
+    /// A client request whose execution is overdue, relayed so that the receivers time it as well
+    Panic(ClientRequest),
+    /// A message of the protocol run in `instance`
+    Instance {
+        instance: u64,
+        message: Arc<Message>,
+    },
+    Abort(AbortMessage),
+    Init(InitMessage),
+}
+
+/// Size of a client request: client id, sequence number and operation
+const CLIENT_REQUEST_SIZE: u32 = 12;
+/// Size of the instance tag of a wrapped message
+pub const INSTANCE_TAG_SIZE: u32 = 8;
+/// Size of the fields of the switching messages: instance, digest of the history and sender id
+const SWITCH_HEADER_SIZE: u32 = 44;
+
+impl AdaptiveMessage {
+    /// Name of the message type, the one of the wrapped message for instance messages.
+    pub fn message_type(&self) -> &'static str {
+        match self {
+            AdaptiveMessage::ClientRequest(_) => "ClientRequest",
+            AdaptiveMessage::Panic(_) => "Panic",
+            AdaptiveMessage::Instance { message, .. } => message.message_type(),
+            AdaptiveMessage::Abort(_) => "Abort",
+            AdaptiveMessage::Init(_) => "Init",
+        }
+    }
+
+    /// Priority class at an overloaded node, switching protocols is control traffic, the messages of an instance keep their class.
+    pub fn priority(&self) -> Priority {
+        match self {
+            AdaptiveMessage::ClientRequest(_) => Priority::Client,
+            AdaptiveMessage::Instance { message, .. } => message.priority(),
+            AdaptiveMessage::Panic(_) | AdaptiveMessage::Abort(_) | AdaptiveMessage::Init(_) => {
+                Priority::Control
+            }
+        }
+    }
+
+    /// Whether the sender appends the message to its log before it sends it, the messages of an
+    /// instance are persisted as by their protocol.
+    pub fn is_persisted(&self) -> bool {
+        match self {
+            AdaptiveMessage::Instance { message, .. } => message.is_persisted(),
+            _ => false,
+        }
+    }
+
+    /// Id of the client request the message belongs to, `None` for the switching messages.
+    pub fn request_id(&self) -> Option<u64> {
+        match self {
+            AdaptiveMessage::ClientRequest(m) | AdaptiveMessage::Panic(m) => {
+                Some(u64::from(m.id.sequence))
+            }
+            AdaptiveMessage::Instance { message, .. } => message.request_id(),
+            AdaptiveMessage::Abort(_) | AdaptiveMessage::Init(_) => None,
+        }
+    }
+
+    /// Size of the switching messages in bytes. The history is represented by its digest, the
+    /// requests it consists of are assumed to be known to the replicas already.
+    pub fn payload_size(&self) -> u32 {
+        match self {
+            AdaptiveMessage::ClientRequest(_) | AdaptiveMessage::Panic(_) => CLIENT_REQUEST_SIZE,
+            AdaptiveMessage::Instance { .. } => INSTANCE_TAG_SIZE,
+            AdaptiveMessage::Abort(_) | AdaptiveMessage::Init(_) => SWITCH_HEADER_SIZE,
+        }
+    }
+}
+
+/// Type defining a _client request_.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct ClientRequest {
+    /// The issuing client and its sequence number
+    pub id: RequestId,
+    pub operation: u32,
+}
+impl ClientRequest {
+    pub fn new(id: RequestId, operation: u32) -> Self {
+        ClientRequest { id, operation }
+    }
+}
+
+/// Type defining the _abort_ of `instance` by a replica, carrying the history of requests it
+/// executed up to and including the instance.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+pub struct AbortMessage {
+    pub instance: u64,
+    pub history: Arc<Vec<RequestId>>,
+    pub sender_id: u32,
+}
+impl AbortMessage {
+    pub fn new(instance: u64, history: Arc<Vec<RequestId>>, sender_id: u32) -> Self {
+        AbortMessage {
+            instance,
+            history,
+            sender_id,
+        }
+    }
+}
+
+/// Type defining the _initialization_ of `instance`, which continues from `history`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+pub struct InitMessage {
+    pub instance: u64,
+    pub history: Arc<Vec<RequestId>>,
+    pub sender_id: u32,
+}
+impl InitMessage {
+    pub fn new(instance: u64, history: Arc<Vec<RequestId>>, sender_id: u32) -> Self {
+        InitMessage {
+            instance,
+            history,
+            sender_id,
+        }
+    }
+}
@@ -0,0 +1,545 @@
+/***************************************************************************************************
+Adaptive node in the style of Abstract/Aliph. The node hosts one instance of a BFT protocol at a
+time, starting with the first one of 'adaptive.protocols'. A replica whose client request is not
+executed within 'adaptive.switch_timeout' relays the request to the others, which time it as well,
+and aborts the instance: it stops taking part and broadcasts its history, i.e. the requests it
+executed so far. Replicas join the abort once f+1 others aborted. A replica the others do not
+join within 'adaptive.switch_timeout' times its abort retransmissions rejoins the instance: it
+catches up on the messages it held back meanwhile and resubmits the requests of its clients. Its
+abort stays with the others until it aborts anew, which replaces it.
+The init leader of the next instance picks the longest history of a quorum of aborts, which
+contains every request a quorum executed, and broadcasts it. The replicas continue from this history
+with the next protocol of the list and resubmit the requests of their clients that are missing.
+A backup protocol, i.e. any but the first one, aborts on its own after executing
+'adaptive.backup_requests' requests, so the node returns to the first protocol.
+The init leader is fixed per instance, a crashed leader blocks the switch.
+***************************************************************************************************/
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use log::debug;
+
+use crate::node::quorum::QuorumConfig;
+use crate::node::request::RequestId;
+use crate::node::{build_node, Node, NodeOutput};
+use crate::simulation::config::{client_request, NodeConfig, ResultLogger};
+use crate::simulation::event::{Message, Reception};
+use crate::simulation::results::ResultEvent;
+use crate::simulation::safety::ExecutionRecord;
+use crate::simulation::settings::{AdaptiveSettings, Settings};
+use crate::simulation::time::Time;
+
+use self::messages::{
+    AbortMessage, AdaptiveMessage, ClientRequest, InitMessage, INSTANCE_TAG_SIZE,
+};
+
+pub mod messages;
+
+/// Timer tokens of the hosted instance are tagged with the instance above this bit
+const INSTANCE_SHIFT: u32 = 40;
+/// Timer of a client request, the instance and the sequence number of the request are encoded below
+const SWITCH_TIMER: u64 = 1 << 63;
+/// Timer resending the abort of the instance encoded below
+const RETRANSMIT_TIMER: u64 = 1 << 62;
+/// Number of times an abort is resent before the node rejoins the instance the others keep running
+const ABORT_RETRANSMISSIONS: u32 = 5;
+
+/// The `AdaptiveNode` hosts the node of the current protocol instance and switches to the next
+/// protocol when the instance is aborted.
+#[derive(Debug)]
+pub struct AdaptiveNode {
+    id: u32,
+    number_of_nodes: u32,
+    settings: Arc<Settings>,
+    results: ResultLogger,
+    quorum: QuorumConfig,
+    /// the current instance and the node running its protocol
+    instance: u64,
+    inner: Box<dyn Node>,
+    /// the history the current instance continues from
+    committed: Vec<RequestId>,
+    committed_ids: HashSet<RequestId>,
+    /// the node stopped taking part in the current instance
+    aborted: bool,
+    /// the histories of the aborts received, by instance and sender
+    aborts: BTreeMap<u64, BTreeMap<u32, Arc<Vec<RequestId>>>>,
+    /// the init of the current instance, handed to replicas still aborting an earlier one
+    last_init: Option<InitMessage>,
+    /// the client requests received by this node by sequence number, they are resubmitted to
+    /// the next instance until they are part of its history
+    pending: BTreeMap<u32, ClientRequest>,
+    /// the overdue client requests other replicas relayed, by sequence number
+    watched: BTreeMap<u32, RequestId>,
+    /// number of times the abort of the current instance was resent
+    retransmissions: u32,
+    /// messages of later instances, handled once the node switched to them
+    future: Vec<(u64, Reception)>,
+    /// messages of the current instance received while aborted, handled if the node rejoins it
+    held: Vec<Reception>,
+}
+
+impl AdaptiveNode {
+    pub fn new(config: NodeConfig) -> Self {
+        let settings = config.settings;
+
+        AdaptiveNode {
+            inner: build_node(NodeConfig {
+                node_type: settings.adaptive.protocol(0),
+                id: config.id,
+                number_of_nodes: config.number_of_nodes,
+                params: settings.params_of(settings.adaptive.protocol(0).name()),
+                settings: Arc::clone(&settings),
+                results: config.results.clone(),
+            }),
+            quorum: QuorumConfig::from_settings(config.number_of_nodes, &settings.quorum),
+            id: config.id,
+            number_of_nodes: config.number_of_nodes,
+            settings,
+            results: config.results,
+            instance: 0,
+            committed: Vec::new(),
+            committed_ids: HashSet::new(),
+            aborted: false,
+            aborts: BTreeMap::new(),
+            last_init: None,
+            pending: BTreeMap::new(),
+            watched: BTreeMap::new(),
+            retransmissions: 0,
+            future: Vec::new(),
+            held: Vec::new(),
+        }
+    }
+
+    fn adaptive(&self) -> &AdaptiveSettings {
+        &self.settings.adaptive
+    }
+
+    /// The replica initializing `instance`
+    fn init_leader(&self, instance: u64) -> u32 {
+        (instance % u64::from(self.number_of_nodes)) as u32 + 1
+    }
+
+    /// The requests executed by the hosted instance
+    fn instance_executed(&self) -> Vec<RequestId> {
+        self.inner
+            .execution()
+            .map(|record| record.executed)
+            .unwrap_or_default()
+    }
+
+    /// The requests executed so far, the history of the earlier instances included
+    fn history(&self) -> Vec<RequestId> {
+        let mut history = self.committed.clone();
+        history.extend(self.instance_executed());
+        history
+    }
+
+    /// Processes the message of a reception, `forged` and `decompression` are those of the
+    /// reception and are handed on with the messages of the hosted instance
+    fn process_message(
+        &mut self,
+        message: AdaptiveMessage,
+        forged: bool,
+        decompression: u64,
+        time: Time,
+    ) -> Option<Vec<NodeOutput>> {
+        let mut outputs = Vec::new();
+
+        match message {
+            AdaptiveMessage::ClientRequest(c_req) => {
+                self.pending.insert(c_req.id.sequence, c_req);
+                outputs.push(self.switch_timer(c_req.id));
+                if !self.aborted {
+                    self.submit(c_req, time, &mut outputs);
+                }
+            }
+            AdaptiveMessage::Panic(c_req) => {
+                if self.aborted || self.is_executed(c_req.id) {
+                    return None;
+                }
+                self.watched.insert(c_req.id.sequence, c_req.id);
+                outputs.push(self.switch_timer(c_req.id));
+            }
+            AdaptiveMessage::Instance { instance, message } => {
+                let reception = Reception {
+                    forged,
+                    decompression,
+                    ..Reception::new(self.id, message)
+                };
+                if instance > self.instance {
+                    // the others moved on, the current instance is of no use anymore
+                    self.future.push((instance, reception));
+                    if !self.aborted {
+                        self.abort(time, &mut outputs);
+                    }
+                } else if instance == self.instance && self.aborted {
+                    self.held.push(reception);
+                } else if instance == self.instance {
+                    let inner_outputs = self.inner.handle_event(reception, time);
+                    self.wrap(inner_outputs, &mut outputs);
+                    self.check_backup(time, &mut outputs);
+                }
+            }
+            AdaptiveMessage::Abort(abort) => {
+                if abort.instance < self.instance {
+                    // the sender missed the switch
+                    if let Some(init) = &self.last_init {
+                        let init = AdaptiveMessage::Init(init.clone());
+                        outputs.push(NodeOutput::Send {
+                            to: abort.sender_id,
+                            size: init.payload_size(),
+                            message: Arc::new(Message::Adaptive(init)),
+                        });
+                    }
+                } else {
+                    self.aborts
+                        .entry(abort.instance)
+                        .or_default()
+                        .insert(abort.sender_id, abort.history);
+                    self.check_aborts(time, &mut outputs);
+                }
+            }
+            AdaptiveMessage::Init(init) => {
+                if init.instance > self.instance {
+                    self.switch(init, time, &mut outputs);
+                }
+            }
+        }
+
+        Some(outputs)
+    }
+
+    /// Whether `id` is part of the history
+    fn is_executed(&self, id: RequestId) -> bool {
+        self.committed_ids.contains(&id) || self.instance_executed().contains(&id)
+    }
+
+    /// The timer after which the current instance is aborted if `id` is not executed
+    fn switch_timer(&self, id: RequestId) -> NodeOutput {
+        NodeOutput::SetTimer {
+            token: SWITCH_TIMER | self.instance << 32 | u64::from(id.sequence),
+            duration: self.adaptive().switch_timeout,
+        }
+    }
+
+    /// Hands the client request to the hosted instance
+    fn submit(&mut self, c_req: ClientRequest, time: Time, outputs: &mut Vec<NodeOutput>) {
+        let node_type = self.adaptive().protocol(self.instance);
+        let message = client_request(node_type, c_req.id, c_req.operation);
+        let inner_outputs = self
+            .inner
+            .handle_event(Reception::new(self.id, message), time);
+        self.wrap(inner_outputs, outputs);
+    }
+
+    /// Tags the outputs of the hosted instance with the instance
+    fn wrap(&self, inner_outputs: Option<Vec<NodeOutput>>, outputs: &mut Vec<NodeOutput>) {
+        // the copies of a multicast share the message, they also share the wrapped message
+        let mut shared: Option<(Arc<Message>, Arc<Message>)> = None;
+        let instance = self.instance;
+        let mut tag = |message: Arc<Message>| match &shared {
+            Some((inner, wrapped)) if Arc::ptr_eq(inner, &message) => Arc::clone(wrapped),
+            _ => {
+                let wrapped = Arc::new(Message::Adaptive(AdaptiveMessage::Instance {
+                    instance,
+                    message: Arc::clone(&message),
+                }));
+                shared = Some((message, Arc::clone(&wrapped)));
+                wrapped
+            }
+        };
+
+        for output in inner_outputs.into_iter().flatten() {
+            outputs.push(match output {
+                NodeOutput::Send { to, message, size } => NodeOutput::Send {
+                    to,
+                    message: tag(message),
+                    size: size + INSTANCE_TAG_SIZE,
+                },
+                NodeOutput::Gossip { message, size } => NodeOutput::Gossip {
+                    message: tag(message),
+                    size: size + INSTANCE_TAG_SIZE,
+                },
+                NodeOutput::DeliverToClient { to, message } => NodeOutput::DeliverToClient {
+                    to,
+                    message: tag(message),
+                },
+                NodeOutput::SetTimer { token, duration } => NodeOutput::SetTimer {
+                    token: instance << INSTANCE_SHIFT | token,
+                    duration,
+                },
+                NodeOutput::CancelTimer { token } => NodeOutput::CancelTimer {
+                    token: instance << INSTANCE_SHIFT | token,
+                },
+                output => output,
+            });
+        }
+    }
+
+    /// A backup instance hands back to the first protocol after executing its share of requests
+    fn check_backup(&mut self, time: Time, outputs: &mut Vec<NodeOutput>) {
+        let backup_requests = self.adaptive().backup_requests;
+        if self.aborted
+            || backup_requests == 0
+            || self
+                .instance
+                .is_multiple_of(self.adaptive().protocols.len() as u64)
+        {
+            return;
+        }
+        if self.instance_executed().len() as u64 >= backup_requests {
+            self.abort(time, outputs);
+        }
+    }
+
+    /// Stops taking part in the current instance and broadcasts the history
+    fn abort(&mut self, time: Time, outputs: &mut Vec<NodeOutput>) {
+        debug!(target: "node", "Adaptive {} aborts instance {} at {}ms", self.id, self.instance, time);
+        self.aborted = true;
+        let history = Arc::new(self.history());
+        self.aborts
+            .entry(self.instance)
+            .or_default()
+            .insert(self.id, Arc::clone(&history));
+        outputs.push(NodeOutput::Log {
+            event: ResultEvent::Aborted {
+                instance: self.instance,
+            },
+        });
+        self.broadcast_abort(history, outputs);
+        self.check_aborts(time, outputs);
+    }
+
+    /// Sends the abort of the current instance to all others, and resends it until the switch
+    fn broadcast_abort(&self, history: Arc<Vec<RequestId>>, outputs: &mut Vec<NodeOutput>) {
+        let abort = AdaptiveMessage::Abort(AbortMessage::new(self.instance, history, self.id));
+        let size = abort.payload_size();
+        let message = Arc::new(Message::Adaptive(abort));
+        for to in (1..=self.number_of_nodes).filter(|to| *to != self.id) {
+            outputs.push(NodeOutput::Send {
+                to,
+                message: Arc::clone(&message),
+                size,
+            });
+        }
+        outputs.push(NodeOutput::SetTimer {
+            token: RETRANSMIT_TIMER | self.instance,
+            duration: self.adaptive().switch_timeout,
+        });
+    }
+
+    /// Joins the abort of the current instance after f+1 others, and initializes the next instance
+    /// if this node is its init leader and a quorum aborted
+    fn check_aborts(&mut self, time: Time, outputs: &mut Vec<NodeOutput>) {
+        let aborts = match self.aborts.get(&self.instance) {
+            Some(aborts) => aborts,
+            None => return,
+        };
+
+        if !self.aborted {
+            if aborts.len() as u32 > self.quorum.f() {
+                self.abort(time, outputs);
+            }
+            return;
+        }
+
+        let next = self.instance + 1;
+        if self.init_leader(next) != self.id || !self.quorum.is_quorum(aborts.keys()) {
+            return;
+        }
+        // the instance executes requests in the same order on all replicas, the longest history
+        // extends all others
+        let history = aborts
+            .values()
+            .max_by_key(|history| history.len())
+            .map(Arc::clone)
+            .unwrap_or_default();
+        let init = InitMessage::new(next, history, self.id);
+
+        let size = AdaptiveMessage::Init(init.clone()).payload_size();
+        let message = Arc::new(Message::Adaptive(AdaptiveMessage::Init(init.clone())));
+        for to in (1..=self.number_of_nodes).filter(|to| *to != self.id) {
+            outputs.push(NodeOutput::Send {
+                to,
+                message: Arc::clone(&message),
+                size,
+            });
+        }
+        self.switch(init, time, outputs);
+    }
+
+    /// Continues from the history of `init` with the protocol of its instance
+    fn switch(&mut self, init: InitMessage, time: Time, outputs: &mut Vec<NodeOutput>) {
+        let node_type = self.adaptive().protocol(init.instance);
+        debug!(target: "node", "Adaptive {} switches to instance {} ({:?}) at {}ms", self.id, init.instance, node_type, time);
+        outputs.push(NodeOutput::CancelTimer {
+            token: RETRANSMIT_TIMER | self.instance,
+        });
+        outputs.push(NodeOutput::Log {
+            event: ResultEvent::Switched {
+                instance: init.instance,
+                protocol: format!("{:?}", node_type),
+            },
+        });
+
+        self.instance = init.instance;
+        self.aborted = false;
+        self.committed = init.history.to_vec();
+        self.committed_ids = self.committed.iter().copied().collect();
+        self.aborts = self.aborts.split_off(&self.instance);
+        self.inner = build_node(NodeConfig {
+            node_type,
+            id: self.id,
+            number_of_nodes: self.number_of_nodes,
+            params: self.settings.params_of(node_type.name()),
+            settings: Arc::clone(&self.settings),
+            results: self.results.clone(),
+        });
+        self.last_init = Some(init);
+        self.watched.clear();
+        self.retransmissions = 0;
+        self.held.clear();
+
+        // the requests of the own clients that did not make it into the history
+        let committed_ids = &self.committed_ids;
+        self.pending
+            .retain(|_, c_req| !committed_ids.contains(&c_req.id));
+        let pending: Vec<ClientRequest> = self.pending.values().copied().collect();
+        for c_req in pending {
+            outputs.push(self.switch_timer(c_req.id));
+            self.submit(c_req, time, outputs);
+        }
+
+        let instance = self.instance;
+        let (current, future): (Vec<_>, Vec<_>) = std::mem::take(&mut self.future)
+            .into_iter()
+            .filter(|(i, _)| *i >= instance)
+            .partition(|(i, _)| *i == instance);
+        self.future = future;
+        for (_, reception) in current {
+            let inner_outputs = self.inner.handle_event(reception, time);
+            self.wrap(inner_outputs, outputs);
+        }
+
+        self.check_backup(time, outputs);
+        self.check_aborts(time, outputs);
+    }
+
+    /// Takes part in the current instance again after the others did not join the abort: handles
+    /// the messages held back meanwhile and resubmits the requests of the own clients
+    fn rejoin(&mut self, time: Time, outputs: &mut Vec<NodeOutput>) {
+        debug!(target: "node", "Adaptive {} rejoins instance {} at {}ms", self.id, self.instance, time);
+        self.aborted = false;
+        self.retransmissions = 0;
+        if let Some(aborts) = self.aborts.get_mut(&self.instance) {
+            aborts.remove(&self.id);
+        }
+
+        for reception in std::mem::take(&mut self.held) {
+            let inner_outputs = self.inner.handle_event(reception, time);
+            self.wrap(inner_outputs, outputs);
+        }
+
+        let pending: Vec<ClientRequest> = self
+            .pending
+            .values()
+            .copied()
+            .filter(|c_req| !self.is_executed(c_req.id))
+            .collect();
+        for c_req in pending {
+            outputs.push(self.switch_timer(c_req.id));
+            self.submit(c_req, time, outputs);
+        }
+        self.check_backup(time, outputs);
+    }
+}
+
+impl Node for AdaptiveNode {
+    fn handle_event(&mut self, reception: Reception, time: Time) -> Option<Vec<NodeOutput>> {
+        debug!(target: "node", "Adaptive {} is processing a reception at {}ms: {:?}", self.id, time, &reception);
+
+        let (forged, decompression) = (reception.forged, reception.decompression);
+        match reception.into_message() {
+            Message::Adaptive(adaptive_message) => {
+                self.process_message(adaptive_message, forged, decompression, time)
+            }
+            _ => panic!("Received a non adaptive message for an adaptive node!"),
+        }
+    }
+
+    fn handle_timer(&mut self, token: u64, time: Time) -> Option<Vec<NodeOutput>> {
+        let mut outputs = Vec::new();
+
+        if token & SWITCH_TIMER != 0 {
+            let instance = (token & !SWITCH_TIMER) >> 32;
+            let sequence = token as u32;
+            let own = self.pending.get(&sequence).copied();
+            let id = match own {
+                Some(c_req) => c_req.id,
+                None => *self.watched.get(&sequence)?,
+            };
+            if instance != self.instance || self.aborted {
+                return None;
+            }
+            self.watched.remove(&sequence);
+            if self.committed_ids.contains(&id) {
+                self.pending.remove(&sequence);
+                return None;
+            }
+            if self.is_executed(id) {
+                return None;
+            }
+
+            if let Some(c_req) = own {
+                let panic = Arc::new(Message::Adaptive(AdaptiveMessage::Panic(c_req)));
+                for to in (1..=self.number_of_nodes).filter(|to| *to != self.id) {
+                    outputs.push(NodeOutput::Send {
+                        to,
+                        message: Arc::clone(&panic),
+                        size: AdaptiveMessage::Panic(c_req).payload_size(),
+                    });
+                }
+            }
+            self.abort(time, &mut outputs);
+        } else if token & RETRANSMIT_TIMER != 0 {
+            let instance = token & !RETRANSMIT_TIMER;
+            if instance != self.instance || !self.aborted {
+                return None;
+            }
+            if self.retransmissions == ABORT_RETRANSMISSIONS {
+                self.rejoin(time, &mut outputs);
+                return Some(outputs);
+            }
+            self.retransmissions += 1;
+            let history = Arc::clone(&self.aborts[&self.instance][&self.id]);
+            self.broadcast_abort(history, &mut outputs);
+        } else {
+            if token >> INSTANCE_SHIFT != self.instance || self.aborted {
+                return None;
+            }
+            let inner_outputs = self
+                .inner
+                .handle_timer(token & ((1 << INSTANCE_SHIFT) - 1), time);
+            self.wrap(inner_outputs, &mut outputs);
+            self.check_backup(time, &mut outputs);
+        }
+
+        Some(outputs)
+    }
+
+    /// The history of all instances. The digest summarizes the history instead of the application
+    /// state, the state of the hosted instance only covers the requests of this instance.
+    fn execution(&self) -> Option<ExecutionRecord> {
+        let executed = self.history();
+        let mut hasher = DefaultHasher::new();
+        executed.hash(&mut hasher);
+        Some(ExecutionRecord {
+            node_id: self.id,
+            executed,
+            digest: hasher.finish(),
+        })
+    }
+}
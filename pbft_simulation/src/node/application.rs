@@ -0,0 +1,104 @@
+/***************************************************************************************************
+The replicated application. Replicas hand every request they execute to their application in the
+order agreed on, so the state of correct replicas only differs while they lag behind. The digest
+summarizes the state, e.g. for checkpoints or to detect diverging replicas.
+***************************************************************************************************/
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+
+/// Number of keys the operations of the key-value application are spread over
+const KEY_SPACE: u32 = 64;
+
+/// A deterministic state machine executed by the replicas
+pub trait Application: Debug + Send {
+    /// Executes `operation` and returns its result
+    fn execute(&mut self, operation: u32) -> u32;
+    /// Digest of the current state
+    fn digest(&self) -> u64;
+    /// Copy of the current state, e.g. to roll back speculative executions to it
+    fn snapshot(&self) -> Box<dyn Application>;
+}
+
+/// Creates the application named `application`, as in 'node.application' of the ini
+pub fn from_name(application: &str) -> Box<dyn Application> {
+    match application {
+        "counter" => Box::new(CounterApplication::default()),
+        "kv" => Box::new(KeyValueApplication::default()),
+        _ => panic!(
+            "application '{}' is not available, allowed are 'counter', 'kv'",
+            application
+        ),
+    }
+}
+
+/// Adds every operation to a counter and returns the new value
+#[derive(Debug, Default, Clone)]
+pub struct CounterApplication {
+    value: u32,
+}
+
+impl Application for CounterApplication {
+    fn execute(&mut self, operation: u32) -> u32 {
+        self.value = self.value.wrapping_add(operation);
+        self.value
+    }
+
+    fn digest(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn snapshot(&self) -> Box<dyn Application> {
+        Box::new(self.clone())
+    }
+}
+
+/// Writes every operation to the key `operation % KEY_SPACE` and returns the value it replaced
+#[derive(Debug, Default, Clone)]
+pub struct KeyValueApplication {
+    store: BTreeMap<u32, u32>,
+}
+
+impl Application for KeyValueApplication {
+    fn execute(&mut self, operation: u32) -> u32 {
+        self.store
+            .insert(operation % KEY_SPACE, operation)
+            .unwrap_or(0)
+    }
+
+    fn digest(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.store.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn snapshot(&self) -> Box<dyn Application> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_digest_depends_on_order() {
+        let mut a = KeyValueApplication::default();
+        let mut b = KeyValueApplication::default();
+        assert_eq!(a.digest(), b.digest());
+
+        assert_eq!(a.execute(3), 0);
+        assert_eq!(a.execute(3 + KEY_SPACE), 3);
+        b.execute(3 + KEY_SPACE);
+        b.execute(3);
+        assert_ne!(a.digest(), b.digest());
+
+        let mut counter = CounterApplication::default();
+        assert_eq!(counter.execute(3), 3);
+        assert_eq!(counter.execute(4), 7);
+    }
+}
@@ -0,0 +1,244 @@
+/***************************************************************************************************
+Simulated costs and sizes of the cryptographic primitives used to authenticate protocol messages.
+Nothing is actually signed, the model only tells the nodes how long authenticating a message takes
+and how many bytes the authenticator adds to it. The costs are configured or, with
+`crypto.calibrate`, measured on the host by running keyed BLAKE3 and ed25519 of mc_utils.
+***************************************************************************************************/
+
+use std::hint::black_box;
+use std::str::FromStr;
+
+use mc_utils::crypto::digest;
+use mc_utils::crypto::ed25519::{SigningKey, SIGNATURE_LENGTH};
+use serde::Serialize;
+
+use crate::simulation::settings::CryptoSettings;
+use crate::simulation::wall_clock::Instant;
+
+/// The mechanism used to authenticate protocol messages
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub enum AuthenticationMode {
+    /// An authenticator, i.e. a vector holding one MAC per receiver
+    Mac,
+    /// A single digital signature, verifiable by everyone
+    Signature,
+}
+
+impl FromStr for AuthenticationMode {
+    type Err = String;
+
+    /// Parses the mode as it is written in the ini ('mac' or 'signature')
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "mac" => Ok(AuthenticationMode::Mac),
+            "signature" => Ok(AuthenticationMode::Signature),
+            _ => Err(format!(
+                "authentication '{}' is not available, allowed are 'mac', 'signature'",
+                mode
+            )),
+        }
+    }
+}
+
+/// Cost model of the authentication. Costs are given in microseconds, sizes in bytes.
+#[derive(Debug, Copy, Clone)]
+pub struct CryptoModel {
+    pub mode: AuthenticationMode,
+    /// Cost to compute or verify a single MAC
+    pub mac_cost: u64,
+    /// Cost to create a signature
+    pub sign_cost: u64,
+    /// Cost to verify a signature
+    pub verify_cost: u64,
+    /// Size of a single MAC
+    pub mac_size: u32,
+    /// Size of a signature
+    pub signature_size: u32,
+    /// Cost to create a share of a threshold signature
+    pub share_sign_cost: u64,
+    /// Cost to verify a single share
+    pub share_verify_cost: u64,
+    /// Cost to combine a single (verified) share into the aggregate signature
+    pub combine_cost: u64,
+    /// Cost to verify an aggregate signature, independent of the number of shares
+    pub aggregate_verify_cost: u64,
+    /// Size of a signature share
+    pub share_size: u32,
+    /// Size of an aggregate signature
+    pub aggregate_size: u32,
+}
+
+impl CryptoModel {
+    /// Creates the model for `mode` with the costs and sizes of the [crypto] section
+    pub fn new(mode: AuthenticationMode, settings: &CryptoSettings) -> Self {
+        CryptoModel {
+            mode,
+            mac_cost: settings.mac_cost,
+            sign_cost: settings.sign_cost,
+            verify_cost: settings.verify_cost,
+            mac_size: settings.mac_size,
+            signature_size: settings.signature_size,
+            share_sign_cost: settings.share_sign_cost,
+            share_verify_cost: settings.share_verify_cost,
+            combine_cost: settings.combine_cost,
+            aggregate_verify_cost: settings.aggregate_verify_cost,
+            share_size: settings.share_size,
+            aggregate_size: settings.aggregate_size,
+        }
+    }
+
+    /// Cost to authenticate a single message that is sent to `receivers` nodes
+    pub fn authentication_cost(&self, receivers: usize) -> u64 {
+        match self.mode {
+            AuthenticationMode::Mac => self.mac_cost * receivers as u64,
+            AuthenticationMode::Signature => self.sign_cost,
+        }
+    }
+
+    /// Cost to verify the authenticity of a received message
+    pub fn verification_cost(&self) -> u64 {
+        match self.mode {
+            AuthenticationMode::Mac => self.mac_cost,
+            AuthenticationMode::Signature => self.verify_cost,
+        }
+    }
+
+    /// Cost to combine `shares` verified shares into an aggregate signature. The aggregate keeps
+    /// the size `aggregate_size`, however many shares it combines.
+    pub fn combination_cost(&self, shares: usize) -> u64 {
+        self.combine_cost * shares as u64
+    }
+
+    /// Bytes added to a message that is sent to `receivers` nodes
+    pub fn authenticator_size(&self, receivers: usize) -> u32 {
+        match self.mode {
+            AuthenticationMode::Mac => self.mac_size * receivers as u32,
+            AuthenticationMode::Signature => self.signature_size,
+        }
+    }
+}
+
+/// Costs of the primitives measured on the host, in microseconds, see `measure_costs`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MeasuredCosts {
+    /// Keyed BLAKE3 of a message
+    pub mac_cost: u64,
+    /// ed25519 signature of a message
+    pub sign_cost: u64,
+    /// ed25519 verification of a signature
+    pub verify_cost: u64,
+    /// Size of an ed25519 signature
+    pub signature_size: u32,
+}
+
+impl MeasuredCosts {
+    /// Replaces the costs of the MACs and signatures of `settings` by the measured ones, the
+    /// threshold signatures keep theirs
+    pub fn apply(&self, settings: &mut CryptoSettings) {
+        settings.mac_cost = self.mac_cost;
+        settings.sign_cost = self.sign_cost;
+        settings.verify_cost = self.verify_cost;
+        settings.signature_size = self.signature_size;
+    }
+}
+
+/// Measures the mean costs of the primitives over `iterations` runs each on messages of
+/// `message_size` bytes, after one run to warm up. A cost is at least 1µs.
+pub fn measure_costs(message_size: usize, iterations: u32) -> MeasuredCosts {
+    let message = vec![0x5a; message_size];
+    let key = SigningKey::from_seed(&[7; 32]);
+    let verifying_key = key.verifying_key();
+    let signature = key.sign(&message, None);
+
+    MeasuredCosts {
+        mac_cost: mean_micros(iterations, || {
+            black_box(digest::mac(&[7; 32], &message));
+        }),
+        sign_cost: mean_micros(iterations, || {
+            black_box(key.sign(&message, None));
+        }),
+        verify_cost: mean_micros(iterations, || {
+            assert!(verifying_key.valid_sig(&message, None, &signature));
+        }),
+        signature_size: SIGNATURE_LENGTH as u32,
+    }
+}
+
+// the mean duration of `f` in whole microseconds, rounded up
+fn mean_micros<F: FnMut()>(iterations: u32, mut f: F) -> u64 {
+    f();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let nanos = start.elapsed().as_nanos() / u128::from(iterations.max(1));
+    nanos.div_ceil(1000).max(1) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(mode: AuthenticationMode) -> CryptoModel {
+        CryptoModel {
+            mode,
+            mac_cost: 2,
+            sign_cost: 1500,
+            verify_cost: 300,
+            mac_size: 10,
+            signature_size: 128,
+            share_sign_cost: 1000,
+            share_verify_cost: 2000,
+            combine_cost: 100,
+            aggregate_verify_cost: 2000,
+            share_size: 48,
+            aggregate_size: 48,
+        }
+    }
+
+    #[test]
+    /// An authenticator grows with the receivers, a signature does not
+    fn check_authentication_per_mode() {
+        let mac = model(AuthenticationMode::Mac);
+        let sig = model(AuthenticationMode::Signature);
+
+        assert_eq!(mac.authentication_cost(3), 6);
+        assert_eq!(mac.authenticator_size(3), 30);
+        assert_eq!(mac.verification_cost(), 2);
+        assert_eq!(sig.authentication_cost(3), 1500);
+        assert_eq!(sig.authenticator_size(3), 128);
+        assert_eq!(sig.verification_cost(), 300);
+    }
+
+    #[test]
+    /// Combining grows with the shares, the aggregate does not
+    fn check_threshold_signatures() {
+        let model = model(AuthenticationMode::Signature);
+
+        assert_eq!(model.combination_cost(3), 300);
+        assert_eq!(model.combination_cost(67), 6700);
+        assert_eq!(model.aggregate_size, model.share_size);
+    }
+
+    #[test]
+    /// The measured costs replace those of the MACs and signatures only
+    fn check_measured_costs_are_applied() {
+        let costs = measure_costs(256, 10);
+        assert!(costs.mac_cost >= 1);
+        assert!(costs.sign_cost >= 1 && costs.verify_cost >= 1);
+        assert_eq!(costs.signature_size, 64);
+
+        let mut settings = crate::simulation::settings::Settings::default().crypto;
+        costs.apply(&mut settings);
+        assert_eq!(settings.sign_cost, costs.sign_cost);
+        assert_eq!(settings.signature_size, 64);
+        assert_eq!(settings.share_sign_cost, 1000);
+    }
+
+    #[test]
+    fn check_authentication_mode_parses() {
+        assert_eq!("mac".parse(), Ok(AuthenticationMode::Mac));
+        assert_eq!("signature".parse(), Ok(AuthenticationMode::Signature));
+        assert!("rsa".parse::<AuthenticationMode>().is_err());
+    }
+}
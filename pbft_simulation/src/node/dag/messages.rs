@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+use crate::node::request::RequestId;
+use crate::simulation::event::Priority;
+
+/// Type defining the messages of the DAG protocol. The replicas only broadcast their vertices, the
+/// order of the requests follows from the structure of the DAG.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+pub enum DAGMessage {
+    ClientRequest(ClientRequest),
+    Vertex(Vertex),
+}
+
+/// Size of a client request: client id, sequence number and operation
+const CLIENT_REQUEST_SIZE: u32 = 12;
+/// Size of the fields of a vertex besides its batch and parents: round and author
+const VERTEX_HEADER_SIZE: u32 = 12;
+/// Size of the reference to a vertex of an earlier round, the digest of its certificate
+const REFERENCE_SIZE: u32 = 32;
+
+impl DAGMessage {
+    /// Name of the message type, e.g. for statistics.
+    pub fn message_type(&self) -> &'static str {
+        match self {
+            DAGMessage::ClientRequest(_) => "ClientRequest",
+            DAGMessage::Vertex(_) => "Vertex",
+        }
+    }
+
+    /// Priority class at an overloaded node, the vertices order the requests.
+    pub fn priority(&self) -> Priority {
+        match self {
+            DAGMessage::ClientRequest(_) => Priority::Client,
+            DAGMessage::Vertex(_) => Priority::Ordering,
+        }
+    }
+
+    /// Whether the sender appends the message to its log before it sends it, see `[storage]`.
+    pub fn is_persisted(&self) -> bool {
+        matches!(self, DAGMessage::Vertex(_))
+    }
+
+    /// Id of the client request the message belongs to, the first one of the batch of a vertex.
+    pub fn request_id(&self) -> Option<u64> {
+        let id = match self {
+            DAGMessage::ClientRequest(m) => m.id,
+            DAGMessage::Vertex(m) => m.batch.first()?.id,
+        };
+        Some(u64::from(id.sequence))
+    }
+
+    /// Size of the message in bytes, without its signature.
+    pub fn payload_size(&self) -> u32 {
+        match self {
+            DAGMessage::ClientRequest(_) => CLIENT_REQUEST_SIZE,
+            DAGMessage::Vertex(m) => {
+                VERTEX_HEADER_SIZE
+                    + CLIENT_REQUEST_SIZE * m.batch.len() as u32
+                    + REFERENCE_SIZE * (m.parents.len() + m.weak_parents.len()) as u32
+            }
+        }
+    }
+}
+
+/// Type defining a _client request_.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct ClientRequest {
+    /// The issuing client and its sequence number
+    pub id: RequestId,
+    pub operation: u32,
+}
+impl ClientRequest {
+    pub fn new(id: RequestId, operation: u32) -> Self {
+        ClientRequest { id, operation }
+    }
+}
+
+/// Type defining a _vertex_ of the DAG, the batch of client requests `author` proposes in `round`.
+/// A vertex is identified by its round and author.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
+pub struct Vertex {
+    pub round: u64,
+    pub author: u32,
+    pub batch: Vec<ClientRequest>,
+    /// Authors of the vertices of the previous round the vertex refers to, a quorum of them
+    pub parents: Vec<u32>,
+    /// Vertices of earlier rounds no other vertex refers to yet, as round and author, so their
+    /// batches are ordered as well
+    pub weak_parents: Vec<(u64, u32)>,
+}
+impl Vertex {
+    pub fn new(
+        round: u64,
+        author: u32,
+        batch: Vec<ClientRequest>,
+        parents: Vec<u32>,
+        weak_parents: Vec<(u64, u32)>,
+    ) -> Self {
+        Vertex {
+            round,
+            author,
+            batch,
+            parents,
+            weak_parents,
+        }
+    }
+
+    /// Round and author of the vertices the vertex refers to
+    pub fn references(&self) -> impl Iterator<Item = (u64, u32)> + '_ {
+        let round = self.round.saturating_sub(1);
+        self.parents
+            .iter()
+            .map(move |author| (round, *author))
+            .chain(self.weak_parents.iter().copied())
+    }
+}
@@ -0,0 +1,517 @@
+use std::collections::{BTreeMap, HashSet, VecDeque};
+
+use log::{debug, warn};
+
+use crate::node::application::{Application, CounterApplication};
+use crate::node::quorum::QuorumConfig;
+use crate::node::request::RequestId;
+use crate::simulation::config::ResultLogger;
+use crate::simulation::results::ResultEvent;
+use crate::simulation::time::Time;
+
+use super::messages::*;
+
+/// The output produced by this module. Consumed by the host running the `ReplicaState`.
+type Output = Vec<(u32, DAGMessage)>;
+
+/// Creates an `Output` such that the host broadcasts `msg_out` to all other
+/// replicas in the cluster.
+fn create_peer_broadcast_output(msg_out: DAGMessage, peers: &[u32]) -> Output {
+    peers.iter().map(|id| (*id, msg_out.clone())).collect()
+}
+
+/// The even rounds have an anchor, whose leader rotates through the replicas `1..=num_of_nodes`
+/// starting with `1` in round `2`. The odd rounds vote for the anchor of the round before.
+fn leader_of_round(round: u64, num_of_nodes: u32) -> Option<u32> {
+    if round == 0 || round % 2 == 1 {
+        return None;
+    }
+    Some(((round / 2 - 1) % u64::from(num_of_nodes)) as u32 + 1)
+}
+
+/// The type defining the state required for participating in a cluster running a
+/// DAG protocol in the style of Narwhal and Bullshark. In every round each replica
+/// broadcasts a vertex with a batch of its client requests, referring to a quorum of
+/// vertices of the previous round. The anchor of an even round is committed once
+/// `f + 1` vertices of the next round refer to it, its causal history is ordered by
+/// round and author. No further messages are exchanged for the ordering.
+///
+/// The vertices are broadcast best effort: a replica does not fetch a vertex it
+/// missed, and the vertices are identified by round and author instead of the
+/// certificates of Narwhal. Replicas only start a new round while requests are
+/// waiting to be ordered or another replica started it, so the DAG stops growing
+/// once the cluster is idle.
+///
+/// Exposes a single function for handling incoming DAG messages.
+#[derive(Debug)]
+pub struct ReplicaState {
+    id: u32,
+    /// The fixed number of nodes participating in the cluster.
+    num_of_nodes: u32,
+    /// Holds the IDs of other peers.
+    peers: Vec<u32>,
+    /// The quorum system, a vertex refers to a quorum of the previous round
+    quorum: QuorumConfig,
+    /// Maximal number of requests in the batch of a vertex
+    batch_size: usize,
+    /// The vertices whose causal history is complete, by round and author
+    dag: BTreeMap<u64, BTreeMap<u32, Vertex>>,
+    /// Vertices waiting for a vertex they refer to.
+    buffered: Vec<Vertex>,
+    /// Vertices that are referred to by another vertex of the DAG
+    referenced: HashSet<(u64, u32)>,
+    /// The last round the replica created a vertex in.
+    round: u64,
+    /// Client requests waiting for the next vertex of the replica.
+    pending: VecDeque<ClientRequest>,
+    /// The vertices whose batches are ordered, as round and author
+    delivered: HashSet<(u64, u32)>,
+    /// Number of vertices of the DAG with requests that are not ordered yet
+    undelivered_batches: usize,
+    /// The round of the last committed anchor, `0` before the first.
+    last_anchor: u64,
+    /// IDs of the ordered requests, they are executed once even if several vertices carry them.
+    ordered: HashSet<RequestId>,
+    /// Executes the ordered requests.
+    application: Box<dyn Application>,
+    /// The executed requests, in order.
+    executed: Vec<RequestId>,
+    /// The result log the phases of the requests are written to
+    results: ResultLogger,
+}
+
+impl ReplicaState {
+    /// Creates a new `ReplicaState` in round 0, the first vertices are created in round 1.
+    ///
+    /// Requires the parameter `num_of_nodes` to be at least `4`, otherwise it
+    /// `panics!` since at least 4 nodes are required for successful operation.
+    pub fn new(id: u32, num_of_nodes: u32) -> Self {
+        if num_of_nodes < 4 {
+            panic!("Need at least 4 DAG nodes but got only {}", num_of_nodes);
+        }
+
+        ReplicaState {
+            id,
+            num_of_nodes,
+            peers: (1..=num_of_nodes).filter(|i| *i != id).collect(),
+            quorum: QuorumConfig::new(num_of_nodes),
+            batch_size: 100,
+            dag: BTreeMap::new(),
+            buffered: Vec::new(),
+            referenced: HashSet::new(),
+            round: 0,
+            pending: VecDeque::new(),
+            delivered: HashSet::new(),
+            undelivered_batches: 0,
+            last_anchor: 0,
+            ordered: HashSet::new(),
+            application: Box::new(CounterApplication::default()),
+            executed: Vec::new(),
+            results: ResultLogger::default(),
+        }
+    }
+
+    /// Replaces the standard quorum system.
+    pub fn quorum(mut self, quorum: QuorumConfig) -> Self {
+        self.quorum = quorum;
+        self
+    }
+
+    /// Limits the number of requests in the batch of a vertex, at least one.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Replaces the default counter application executing the ordered requests.
+    pub fn application(mut self, application: Box<dyn Application>) -> Self {
+        self.application = application;
+        self
+    }
+
+    /// Writes the phases of the requests to `results` instead of discarding them.
+    pub fn result_logger(mut self, results: ResultLogger) -> Self {
+        self.results = results;
+        self
+    }
+
+    /// The last round the replica created a vertex in.
+    pub fn round(&self) -> u64 {
+        self.round
+    }
+
+    /// The round of the last committed anchor.
+    pub fn last_anchor(&self) -> u64 {
+        self.last_anchor
+    }
+
+    /// The executed requests, in the order of their execution.
+    pub fn executed(&self) -> &[RequestId] {
+        &self.executed
+    }
+
+    /// Digest of the application state after executing all ordered requests.
+    pub fn digest(&self) -> u64 {
+        self.application.digest()
+    }
+
+    /// Single exposed function that acts as a entry point for handling incoming
+    /// messages by peers or clients.
+    pub fn handle_message(&mut self, message: DAGMessage, time: Time) -> Option<Output> {
+        let output = match message {
+            DAGMessage::ClientRequest(m) => self.handle_client_request(m, time),
+            DAGMessage::Vertex(m) => self.handle_vertex(m, time),
+        };
+
+        match output.len() {
+            0 => None,
+            _ => Some(output),
+        }
+    }
+
+    fn handle_client_request(&mut self, msg_in: ClientRequest, time: Time) -> Output {
+        // a retransmission of a request that is waiting or ordered is dropped
+        if self.ordered.contains(&msg_in.id) || self.pending.contains(&msg_in) {
+            return Output::new();
+        }
+
+        self.results
+            .log(time, Some(self.id), &ResultEvent::Request(msg_in.id.into()));
+        self.pending.push_back(msg_in);
+        self.advance(time)
+    }
+
+    fn handle_vertex(&mut self, msg_in: Vertex, time: Time) -> Output {
+        if !self.is_valid(&msg_in) {
+            warn!(target: "node", "DAGNode {} received an invalid vertex of round {} from {}", self.id, msg_in.round, msg_in.author);
+            return Output::new();
+        }
+        if self.contains(msg_in.round, msg_in.author)
+            || self
+                .buffered
+                .iter()
+                .any(|v| v.round == msg_in.round && v.author == msg_in.author)
+        {
+            return Output::new();
+        }
+
+        self.buffered.push(msg_in);
+        // a vertex may complete the causal history of the buffered ones
+        while let Some(index) = self
+            .buffered
+            .iter()
+            .position(|v| v.references().all(|(r, a)| self.contains(r, a)))
+        {
+            let vertex = self.buffered.swap_remove(index);
+            self.insert(vertex);
+        }
+
+        self.commit(time);
+        self.advance(time)
+    }
+
+    /// A vertex of a round after the first has to refer to a quorum of the previous round.
+    fn is_valid(&self, vertex: &Vertex) -> bool {
+        let parents: HashSet<u32> = vertex.parents.iter().copied().collect();
+        vertex.round > 0
+            && (1..=self.num_of_nodes).contains(&vertex.author)
+            && parents.len() == vertex.parents.len()
+            && (vertex.round == 1 || self.quorum.is_quorum(&parents))
+            && vertex
+                .weak_parents
+                .iter()
+                .all(|(r, _)| *r + 1 < vertex.round)
+    }
+
+    /// Whether the DAG holds the vertex of `author` in `round`.
+    fn contains(&self, round: u64, author: u32) -> bool {
+        self.dag
+            .get(&round)
+            .is_some_and(|vertices| vertices.contains_key(&author))
+    }
+
+    /// Adds `vertex` to the DAG, all vertices it refers to are part of it.
+    fn insert(&mut self, vertex: Vertex) {
+        debug!(target: "node", "DAGNode {} adds the vertex of round {} from {}", self.id, vertex.round, vertex.author);
+        self.referenced.extend(vertex.references());
+        if !vertex.batch.is_empty() {
+            self.undelivered_batches += 1;
+        }
+        self.dag
+            .entry(vertex.round)
+            .or_default()
+            .insert(vertex.author, vertex);
+    }
+
+    /// The latest round of which the DAG holds a quorum of vertices, `0` if none.
+    fn quorum_round(&self) -> u64 {
+        self.dag
+            .iter()
+            .rev()
+            .find(|(_, vertices)| self.quorum.is_quorum(vertices.keys()))
+            .map_or(0, |(round, _)| *round)
+    }
+
+    /// The latest round of which the DAG holds any vertex, `0` if none.
+    fn latest_round(&self) -> u64 {
+        self.dag.keys().next_back().map_or(0, |round| *round)
+    }
+
+    /// Creates vertices while requests are waiting to be ordered and the DAG holds a quorum of
+    /// the previous round. A replica that fell behind continues after the latest such round.
+    /// An idle replica joins the latest round of the DAG, otherwise a replica that went on alone
+    /// while its last batch was ordered would never see a quorum of its round again.
+    fn advance(&mut self, time: Time) -> Output {
+        let mut output = Output::new();
+
+        while !self.pending.is_empty()
+            || self.undelivered_batches > 0
+            || self.latest_round() > self.round
+        {
+            let round = self.round.max(self.quorum_round()) + 1;
+            let parents: Vec<u32> = match self.dag.get(&(round - 1)) {
+                Some(vertices) if self.quorum.is_quorum(vertices.keys()) => {
+                    vertices.keys().copied().collect()
+                }
+                _ if round == 1 => Vec::new(),
+                _ => break,
+            };
+            // vertices of earlier rounds that no vertex refers to would never be ordered
+            let weak_parents: Vec<(u64, u32)> = self
+                .dag
+                .range(..round.saturating_sub(1))
+                .flat_map(|(r, vertices)| vertices.keys().map(move |a| (*r, *a)))
+                .filter(|v| !self.referenced.contains(v) && !self.delivered.contains(v))
+                .collect();
+
+            let size = self.pending.len().min(self.batch_size);
+            let batch: Vec<ClientRequest> = self.pending.drain(..size).collect();
+            let vertex = Vertex::new(round, self.id, batch, parents, weak_parents);
+            debug!(target: "node", "DAGNode {} creates the vertex of round {} with {} requests", self.id, round, vertex.batch.len());
+
+            self.round = round;
+            self.insert(vertex.clone());
+            output.append(&mut create_peer_broadcast_output(
+                DAGMessage::Vertex(vertex),
+                &self.peers,
+            ));
+            self.commit(time);
+        }
+
+        output
+    }
+
+    /// Whether a path of references leads from the vertex `from` to the vertex `to`.
+    fn reaches(&self, from: (u64, u32), to: (u64, u32)) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = vec![from];
+        while let Some(vertex) = stack.pop() {
+            if vertex == to {
+                return true;
+            }
+            if vertex.0 <= to.0 || !visited.insert(vertex) {
+                continue;
+            }
+            if let Some(v) = self.dag.get(&vertex.0).and_then(|r| r.get(&vertex.1)) {
+                stack.extend(v.references());
+            }
+        }
+        false
+    }
+
+    /// Commits the latest anchor with `f + 1` votes, i.e. vertices of the next round referring
+    /// to it, and orders the earlier anchors it reaches before it.
+    fn commit(&mut self, time: Time) {
+        let votes_needed = self.quorum.f() as usize + 1;
+        let latest = self.dag.keys().next_back().copied().unwrap_or(0);
+
+        let anchor = (self.last_anchor + 2..latest)
+            .rev()
+            .filter_map(|round| Some((round, leader_of_round(round, self.num_of_nodes)?)))
+            .filter(|(round, leader)| self.contains(*round, *leader))
+            .find(|(round, leader)| {
+                self.dag.get(&(round + 1)).map_or(0, |votes| {
+                    votes
+                        .values()
+                        .filter(|v| v.parents.contains(leader))
+                        .count()
+                }) >= votes_needed
+            });
+        let anchor = match anchor {
+            Some(anchor) => anchor,
+            None => return,
+        };
+
+        // the earlier anchors the committed one reaches are ordered first
+        let mut anchors = vec![anchor];
+        let mut round = anchor.0 - 2;
+        while round > self.last_anchor {
+            if let Some(leader) = leader_of_round(round, self.num_of_nodes) {
+                let head = anchors[anchors.len() - 1];
+                if self.contains(round, leader) && self.reaches(head, (round, leader)) {
+                    anchors.push((round, leader));
+                }
+            }
+            round -= 2;
+        }
+
+        self.last_anchor = anchor.0;
+        for anchor in anchors.into_iter().rev() {
+            debug!(target: "node", "DAGNode {} commits the anchor of round {} from {}", self.id, anchor.0, anchor.1);
+            self.deliver(anchor, time);
+        }
+    }
+
+    /// Orders the batches of the causal history of `anchor` that are not ordered yet, by round
+    /// and author, and executes their requests.
+    fn deliver(&mut self, anchor: (u64, u32), time: Time) {
+        let mut history = Vec::new();
+        let mut stack = vec![anchor];
+        while let Some(vertex) = stack.pop() {
+            if !self.delivered.insert(vertex) {
+                continue;
+            }
+            history.push(vertex);
+            stack.extend(self.dag[&vertex.0][&vertex.1].references());
+        }
+        history.sort_unstable();
+
+        for (round, author) in history {
+            let batch = &self.dag[&round][&author].batch;
+            if batch.is_empty() {
+                continue;
+            }
+            self.undelivered_batches -= 1;
+            for c_req in batch {
+                if !self.ordered.insert(c_req.id) {
+                    continue;
+                }
+                self.results.log(
+                    time,
+                    Some(self.id),
+                    &ResultEvent::CommittedLocal(c_req.id.into()),
+                );
+                self.results
+                    .log(time, Some(self.id), &ResultEvent::Executed(c_req.id.into()));
+                self.application.execute(c_req.operation);
+                self.executed.push(c_req.id);
+            }
+        }
+    }
+}
+
+/*******************************************************************************
+ * TESTS
+ ******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    /// Delivers all messages in the order they were sent, returns the number of messages
+    fn run(replicas: &mut [ReplicaState], requests: u32) -> usize {
+        let mut in_flight: VecDeque<(u32, DAGMessage)> = (1..=requests)
+            .map(|seq| {
+                let c_req = ClientRequest::new(RequestId::new(0, seq), seq);
+                (
+                    (seq - 1) % replicas.len() as u32 + 1,
+                    DAGMessage::ClientRequest(c_req),
+                )
+            })
+            .collect();
+        let mut messages = 0;
+
+        while let Some((to, message)) = in_flight.pop_front() {
+            messages += 1;
+            if let Some(output) = replicas[to as usize - 1].handle_message(message, Time::new(1)) {
+                in_flight.extend(output);
+            }
+        }
+        messages
+    }
+
+    #[test]
+    fn leaders_rotate_over_the_even_rounds() {
+        assert_eq!(leader_of_round(1, 4), None);
+        assert_eq!(leader_of_round(2, 4), Some(1));
+        assert_eq!(leader_of_round(4, 4), Some(2));
+        assert_eq!(leader_of_round(10, 4), Some(1));
+    }
+
+    #[test]
+    fn all_replicas_execute_in_the_same_order() {
+        let mut replicas: Vec<ReplicaState> = (1..=4).map(|id| ReplicaState::new(id, 4)).collect();
+        run(&mut replicas, 10);
+
+        for replica in &replicas {
+            assert_eq!(replica.executed().len(), 10);
+            assert_eq!(replica.executed(), replicas[0].executed());
+            assert_eq!(replica.digest(), replicas[0].digest());
+            assert_eq!(replica.undelivered_batches, 0);
+            assert!(replica.pending.is_empty());
+        }
+    }
+
+    #[test]
+    fn replicas_stop_once_everything_is_ordered() {
+        let mut replicas: Vec<ReplicaState> = (1..=4).map(|id| ReplicaState::new(id, 4)).collect();
+        // the run only ends once no replica creates further vertices
+        run(&mut replicas, 1);
+
+        let rounds: Vec<u64> = replicas.iter().map(|r| r.round()).collect();
+        // the anchor of round 2 needs the votes of round 3
+        assert!(rounds.iter().any(|round| *round >= 3), "{:?}", rounds);
+        assert!(rounds.iter().all(|round| *round <= 4), "{:?}", rounds);
+        assert!(replicas.iter().all(|r| r.last_anchor() == 2));
+    }
+
+    #[test]
+    fn idle_replicas_join_the_latest_round() {
+        let mut replica = ReplicaState::new(4, 4);
+        let first = |author| Vertex::new(1, author, Vec::new(), Vec::new(), Vec::new());
+        for author in 1..=3 {
+            replica.handle_message(DAGMessage::Vertex(first(author)), Time::new(1));
+        }
+        // without a request of its own the replica adds a vertex to round 1, but goes no further
+        assert_eq!(replica.round(), 1);
+        assert!(replica.contains(1, 4));
+
+        // a replica that went on alone gets a quorum for its next round once the others joined
+        let mut replicas: Vec<ReplicaState> = (1..=4).map(|id| ReplicaState::new(id, 4)).collect();
+        run(&mut replicas, 1);
+        let ahead = replicas.iter().max_by_key(|r| r.round()).unwrap().id;
+        let c_req = ClientRequest::new(RequestId::new(0, 2), 2);
+        let mut in_flight: VecDeque<(u32, DAGMessage)> =
+            vec![(ahead, DAGMessage::ClientRequest(c_req))].into();
+        while let Some((to, message)) = in_flight.pop_front() {
+            if let Some(output) = replicas[to as usize - 1].handle_message(message, Time::new(2)) {
+                in_flight.extend(output);
+            }
+        }
+        assert!(replicas.iter().all(|r| r.executed().len() == 2));
+    }
+
+    #[test]
+    fn vertices_wait_for_their_parents() {
+        let mut replica = ReplicaState::new(4, 4);
+        let first = |author| Vertex::new(1, author, Vec::new(), Vec::new(), Vec::new());
+        let second = Vertex::new(2, 1, Vec::new(), vec![1, 2, 3], Vec::new());
+
+        assert_eq!(
+            replica.handle_message(DAGMessage::Vertex(second), Time::new(1)),
+            None
+        );
+        assert!(!replica.contains(2, 1));
+        for author in 1..=3 {
+            replica.handle_message(DAGMessage::Vertex(first(author)), Time::new(2));
+        }
+        assert!(replica.contains(2, 1));
+        assert!(replica.buffered.is_empty());
+
+        // a vertex has to refer to a quorum of the previous round
+        let invalid = Vertex::new(3, 2, Vec::new(), vec![1], Vec::new());
+        assert!(!replica.is_valid(&invalid));
+    }
+}
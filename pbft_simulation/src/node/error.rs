@@ -0,0 +1,57 @@
+/***************************************************************************************************
+Errors of the protocol state machines. A replica can receive anything a Byzantine peer sends, so a
+message it cannot handle is reported as an error instead of a panic: the node logs it, drops it and
+writes it to the result log as invalid.
+***************************************************************************************************/
+
+use std::error::Error;
+use std::fmt;
+
+/// A message the replica `node` cannot handle
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProtocolError {
+    /// The message is meant for a node of another role, e.g. a reply sent to a replica
+    UnexpectedMessage {
+        node: u32,
+        message_type: &'static str,
+    },
+    /// The message contradicts the state of the replica, e.g. a second order of the same request
+    Conflict {
+        node: u32,
+        message_type: &'static str,
+        reason: String,
+    },
+}
+
+impl ProtocolError {
+    /// Name of the type of the invalid message within its protocol
+    pub fn message_type(&self) -> &'static str {
+        match self {
+            ProtocolError::UnexpectedMessage { message_type, .. }
+            | ProtocolError::Conflict { message_type, .. } => message_type,
+        }
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProtocolError::UnexpectedMessage { node, message_type } => write!(
+                f,
+                "Replica {} received a {} message, which is not meant for it",
+                node, message_type
+            ),
+            ProtocolError::Conflict {
+                node,
+                message_type,
+                reason,
+            } => write!(
+                f,
+                "Replica {} received a conflicting {} message: {}",
+                node, message_type, reason
+            ),
+        }
+    }
+}
+
+impl Error for ProtocolError {}
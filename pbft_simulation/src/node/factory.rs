@@ -0,0 +1,189 @@
+/***************************************************************************************************
+The registry of the node types a simulation can be run with. The built-in protocols are registered
+under the names of 'node_type' in the ini, crates using the simulation as a library register their
+own nodes next to them:
+
+    let mut factory = NodeFactory::default();
+    factory.register("my_pbft", NodeType::PBFT, |config| Box::new(MyNode::new(config)));
+    let config = SimulationConfig::new(settings).nodes_from(factory, "my_pbft");
+
+A registered node speaks the messages of a built-in protocol, whose client requests the simulation
+sends to it.
+***************************************************************************************************/
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::node::adaptive::AdaptiveNode;
+use crate::node::{
+    DAGNode, DummyNode, Node, NodeType, PBFTClientNode, PBFTNode, SBFTNode, StewardNode,
+    TendermintNode, ZyzzyvaClientNode, ZyzzyvaNode,
+};
+use crate::simulation::config::NodeConfig;
+
+/// Creates a node from its config
+pub type NodeConstructor = fn(NodeConfig) -> Box<dyn Node>;
+
+/// A registered node type
+#[derive(Clone, Copy)]
+struct Registration {
+    /// The protocol whose messages the node speaks
+    protocol: NodeType,
+    constructor: NodeConstructor,
+}
+
+/// Constructors of the node types, keyed by name
+#[derive(Clone)]
+pub struct NodeFactory {
+    registrations: BTreeMap<String, Registration>,
+}
+
+impl NodeFactory {
+    /// A factory without any node types
+    pub fn empty() -> Self {
+        NodeFactory {
+            registrations: BTreeMap::new(),
+        }
+    }
+
+    /// Registers the node type `name` speaking the messages of `protocol`, replacing a node type
+    /// of the same name
+    pub fn register(
+        &mut self,
+        name: &str,
+        protocol: NodeType,
+        constructor: NodeConstructor,
+    ) -> &mut Self {
+        self.registrations.insert(
+            name.to_string(),
+            Registration {
+                protocol,
+                constructor,
+            },
+        );
+        self
+    }
+
+    /// The names of the registered node types
+    pub fn names(&self) -> Vec<&str> {
+        self.registrations.keys().map(String::as_str).collect()
+    }
+
+    /// The protocol and constructor of the node type `name`
+    pub fn resolve(&self, name: &str) -> Result<(NodeType, NodeConstructor), String> {
+        self.registrations
+            .get(name)
+            .map(|r| (r.protocol, r.constructor))
+            .ok_or_else(|| {
+                format!(
+                    "node type '{}' is not registered, available are '{}'",
+                    name,
+                    self.names().join("', '")
+                )
+            })
+    }
+}
+
+/// A factory of the built-in node types
+impl Default for NodeFactory {
+    fn default() -> Self {
+        let mut factory = NodeFactory::empty();
+        factory
+            .register("dummy", NodeType::Dummy, |config| {
+                Box::new(DummyNode::new(config))
+            })
+            .register("pbft", NodeType::PBFT, |config| {
+                Box::new(PBFTNode::new(config))
+            })
+            .register("pbft_client", NodeType::PBFTClient, |config| {
+                Box::new(PBFTClientNode::new(config))
+            })
+            .register("zyzzyva", NodeType::Zyzzyva, |config| {
+                Box::new(ZyzzyvaNode::new(config))
+            })
+            .register("zyzzyva_client", NodeType::ZyzzyvaClient, |config| {
+                Box::new(ZyzzyvaClientNode::new(config))
+            })
+            .register("sbft", NodeType::SBFT, |config| {
+                Box::new(SBFTNode::new(config))
+            })
+            .register("tendermint", NodeType::Tendermint, |config| {
+                Box::new(TendermintNode::new(config))
+            })
+            .register("adaptive", NodeType::Adaptive, |config| {
+                Box::new(AdaptiveNode::new(config))
+            })
+            .register("dag", NodeType::DAG, |config| {
+                Box::new(DAGNode::new(config))
+            })
+            .register("steward", NodeType::Steward, |config| {
+                Box::new(StewardNode::new(config))
+            });
+        factory
+    }
+}
+
+impl fmt::Debug for NodeFactory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NodeFactory")
+            .field("names", &self.names())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::NodeOutput;
+    use crate::simulation::config::SimulationConfig;
+    use crate::simulation::event::Reception;
+    use crate::simulation::settings::Settings;
+    use crate::simulation::time::Time;
+    use crate::simulation::Simulation;
+
+    /// A PBFT replica that never answers
+    #[derive(Debug)]
+    struct MuteNode;
+
+    impl Node for MuteNode {
+        fn handle_event(&mut self, _reception: Reception, _time: Time) -> Option<Vec<NodeOutput>> {
+            None
+        }
+    }
+
+    #[test]
+    fn check_builtin_node_types_are_registered() {
+        let factory = NodeFactory::default();
+        assert_eq!(
+            factory.names(),
+            vec![
+                "adaptive",
+                "dag",
+                "dummy",
+                "pbft",
+                "pbft_client",
+                "sbft",
+                "steward",
+                "tendermint",
+                "zyzzyva",
+                "zyzzyva_client"
+            ]
+        );
+        assert_eq!(factory.resolve("pbft").map(|r| r.0), Ok(NodeType::PBFT));
+        assert!(factory.resolve("rbft").is_err());
+    }
+
+    #[test]
+    fn check_registered_node_type_is_simulated() {
+        let mut factory = NodeFactory::default();
+        factory.register("mute", NodeType::PBFT, |_| Box::new(MuteNode));
+
+        let mut settings = Settings::default();
+        settings.simulation.requests = 1;
+        let config = SimulationConfig::new(settings).nodes_from(factory, "mute");
+        assert_eq!(config.node_type, NodeType::PBFT);
+
+        let report = Simulation::new(config).run_to_completion();
+        assert!(report.executions.is_empty());
+    }
+}
@@ -1,41 +1,230 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::sync::Arc;
 
-use log::debug;
+use log::{debug, warn};
+use serde::Serialize;
 
+use crate::node::crypto::{AuthenticationMode, CryptoModel};
+use crate::node::dag::state::ReplicaState as DAGState;
+use crate::node::error::ProtocolError;
+use crate::node::factory::NodeFactory;
+use crate::node::pbft::client::Client as PBFTClient;
+use crate::node::pbft::messages::{Dissemination, PBFTMessage};
 use crate::node::pbft::state::ReplicaState as PBFTState;
+use crate::node::quorum::QuorumConfig;
+use crate::node::request::RequestId;
+use crate::node::sbft::messages::SBFTMessage;
+use crate::node::sbft::state::ReplicaState as SBFTState;
+use crate::node::steward::messages::StewardMessage;
+use crate::node::steward::state::ReplicaState as StewardState;
+use crate::node::tendermint::messages::{TendermintMessage, Timeout, TimeoutStep};
+use crate::node::tendermint::state::ReplicaState as TendermintState;
+use crate::node::zyzzyva::client::Client as ZyzzyvaClient;
+use crate::node::zyzzyva::messages::{ClientTimeout, ZyzzyvaMessage};
 use crate::node::zyzzyva::state::State as ZyzzyvaState;
-use crate::simulation::config::NodeConfig;
-use crate::simulation::event::{Event, Message, Reception};
+use crate::simulation::config::{NodeConfig, PBFT_CLIENT_ID};
+use crate::simulation::event::{Message, Priority, Reception};
+use crate::simulation::results::ResultEvent;
+use crate::simulation::safety::ExecutionRecord;
+use crate::simulation::settings::TendermintSettings;
 use crate::simulation::time::Time;
 
+pub mod adaptive;
+pub mod application;
+pub mod crypto;
+pub mod dag;
+pub mod error;
+pub mod factory;
 pub mod pbft;
+#[cfg(test)]
+mod properties;
+pub mod quorum;
+pub mod request;
+pub mod sbft;
+pub mod steward;
+pub mod tendermint;
+pub mod timer;
 pub mod zyzzyva;
 /***************************************************************************************************
 Contains everything related to nodes.
 The 'Node' trait must be implemented for all nodes that shall participate in the simulation. Currently, the only required function to implement is 'handle_event'.
 ***************************************************************************************************/
 
-#[derive(Debug, Copy, Clone)]
+/// The actions a node asks the simulation for after handling an event. The simulation turns them
+/// into events and assigns their timing, so nodes do not have to know about the network.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NodeOutput {
+    /// Send `message` of `size` bytes to node `to` over the network. The receivers of a multicast
+    /// share the message.
+    Send {
+        to: u32,
+        message: Arc<Message>,
+        size: u32,
+    },
+    /// Disseminate `message` of `size` bytes to all other nodes via gossip
+    Gossip { message: Arc<Message>, size: u32 },
+    /// Hand `message` to node `to` reliably and without network delay. Used for the link between a
+    /// client and the cluster, which is not part of the simulated network.
+    DeliverToClient { to: u32, message: Arc<Message> },
+    /// Request a timer firing after `duration` ms on the local clock, `token` is handed back
+    SetTimer { token: u64, duration: u64 },
+    /// Cancel the timer with `token`
+    CancelTimer { token: u64 },
+    /// The node computes for `micros` microseconds, delaying all subsequent sends
+    Compute { micros: u64 },
+    /// Write `event` to the result log of the node
+    Log { event: ResultEvent },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
 pub enum NodeType {
     Dummy,
     PBFT,
     Zyzzyva,
     RBFT,
+    SBFT,
+    Tendermint,
+    Adaptive,
+    DAG,
+    Steward,
+    /// The clients of PBFT and Zyzzyva, which take part in the protocols as nodes of their own
+    PBFTClient,
+    ZyzzyvaClient,
 }
 
-/// All nodes need to implement this trait
-pub trait Node: Debug {
+impl NodeType {
+    /// Parses the node type as it is written in the ini
+    pub fn from_config(node_type: &str) -> Self {
+        match node_type {
+            "dummy" => NodeType::Dummy,
+            "pbft" => NodeType::PBFT,
+            "zyzzyva" => NodeType::Zyzzyva,
+            "rbft" => NodeType::RBFT,
+            "sbft" => NodeType::SBFT,
+            "tendermint" => NodeType::Tendermint,
+            "adaptive" => NodeType::Adaptive,
+            "dag" => NodeType::DAG,
+            "steward" => NodeType::Steward,
+            _ => panic!(
+                "node_type in ini is not available, allowed are 'dummy', 'pbft', 'zyzzyva', 'rbft', 'sbft', 'tendermint', 'adaptive', 'dag', 'steward'"
+            ),
+        }
+    }
+
+    /// The name of the node type as it is written in the ini
+    pub fn name(&self) -> &'static str {
+        match self {
+            NodeType::Dummy => "dummy",
+            NodeType::PBFT => "pbft",
+            NodeType::Zyzzyva => "zyzzyva",
+            NodeType::RBFT => "rbft",
+            NodeType::SBFT => "sbft",
+            NodeType::Tendermint => "tendermint",
+            NodeType::Adaptive => "adaptive",
+            NodeType::DAG => "dag",
+            NodeType::Steward => "steward",
+            NodeType::PBFTClient => "pbft_client",
+            NodeType::ZyzzyvaClient => "zyzzyva_client",
+        }
+    }
+
+    /// The node type of the clients if they are nodes of the simulation, the clients of the other
+    /// protocols hand their requests to the replicas directly
+    pub fn client_type(&self) -> Option<NodeType> {
+        match self {
+            NodeType::PBFT => Some(NodeType::PBFTClient),
+            NodeType::Zyzzyva => Some(NodeType::ZyzzyvaClient),
+            _ => None,
+        }
+    }
+}
+
+/// All nodes need to implement this trait. Nodes are `Send`, so the receptions of distinct nodes at
+/// the same time can be handled on the threads of `simulation.threads`
+pub trait Node: Debug + Send {
     /// called from the simulation when an event for the node was in the queue, e.g. a 'reception event' containing a message designated to the node
-    fn handle_event(&mut self, reception: Reception, time: Time) -> Option<Vec<Event>>;
+    fn handle_event(&mut self, reception: Reception, time: Time) -> Option<Vec<NodeOutput>>;
+
+    /// like `handle_event`, but pushes the outputs to `outputs`, a buffer the simulation reuses for
+    /// all receptions. Nodes on the hot path override it so no vector is allocated per message
+    fn handle_event_into(
+        &mut self,
+        reception: Reception,
+        time: Time,
+        outputs: &mut Vec<NodeOutput>,
+    ) {
+        if let Some(mut handled) = self.handle_event(reception, time) {
+            outputs.append(&mut handled);
+        }
+    }
+
+    /// called from the simulation before any event is handled, e.g. to set the initial timers
+    fn on_start(&mut self, _time: Time) -> Option<Vec<NodeOutput>> {
+        None
+    }
+
+    /// called from the simulation when a timer the node requested fired, `token` is the one chosen by the node
+    fn handle_timer(&mut self, _token: u64, _time: Time) -> Option<Vec<NodeOutput>> {
+        None
+    }
+
+    /// called from the simulation once the run ended, the returned events are written to the result
+    /// log of the node, e.g. final statistics
+    fn on_stop(&mut self, _time: Time) -> Vec<ResultEvent> {
+        Vec::new()
+    }
+
+    /// the requests the node executed so far, `None` for nodes that do not execute requests
+    fn execution(&self) -> Option<ExecutionRecord> {
+        None
+    }
+
+    /// the priority class of a message waiting for the node while it is overloaded, by default the
+    /// one its protocol assigns
+    fn priority(&self, message: &Message) -> Priority {
+        message.priority()
+    }
+}
+
+// the outputs of a replica dropping a message with an invalid authenticator, the message is only
+// known to be forged once it was verified
+fn reject(crypto: &CryptoModel, reception: &Reception) -> Vec<NodeOutput> {
+    debug!(target: "node", "Node {} rejects a message with an invalid authenticator: {:?}", reception.id, reception.message);
+    vec![
+        NodeOutput::Compute {
+            micros: crypto.verification_cost(),
+        },
+        NodeOutput::Log {
+            event: ResultEvent::Rejected {
+                protocol: reception.message.protocol().to_string(),
+                message_type: reception.message.message_type().to_string(),
+            },
+        },
+    ]
 }
 
-// Helper function to generate a dynamic node from the given NodeConfig
+// the outputs of a replica dropping a message of `protocol` it cannot handle, it was verified before
+fn drop_invalid(crypto: &CryptoModel, protocol: &str, error: ProtocolError) -> Vec<NodeOutput> {
+    warn!(target: "node", "{}, the message is dropped", error);
+    vec![
+        NodeOutput::Compute {
+            micros: crypto.verification_cost(),
+        },
+        NodeOutput::Log {
+            event: ResultEvent::Invalid {
+                protocol: protocol.to_string(),
+                message_type: error.message_type().to_string(),
+            },
+        },
+    ]
+}
+
+// Helper function to generate a dynamic node of a built-in node type from the given NodeConfig
 pub fn build_node(config: NodeConfig) -> Box<dyn Node> {
-    match &config.node_type {
-        NodeType::Dummy => Box::new(DummyNode::new(config)),
-        NodeType::PBFT => Box::new(PBFTNode::new(config)),
-        NodeType::Zyzzyva => Box::new(ZyzzyvaNode::new(config)),
-        _ => panic!("Only 'dummy', 'PBFT' and 'Zyzzyva' nodes are currently implemented!"),
+    match NodeFactory::default().resolve(config.node_type.name()) {
+        Ok((_, constructor)) => constructor(config),
+        Err(e) => panic!("{}", e),
     }
 }
 
@@ -55,34 +244,25 @@ impl DummyNode {
 }
 
 impl Node for DummyNode {
-    fn handle_event(&mut self, reception: Reception, time: Time) -> Option<Vec<Event>> {
+    fn handle_event(&mut self, reception: Reception, _time: Time) -> Option<Vec<NodeOutput>> {
         debug!(target: "node", "DummyNode is processing a reception: {:?}", &reception);
-        let time_current = time;
-        let mut return_events = Vec::new();
+        let mut outputs = Vec::new();
+        let send = |to| NodeOutput::Send {
+            to,
+            message: Arc::new(Message::Dummy),
+            size: 0,
+        };
 
         if self.id == 1 {
-            return_events.push(Event::new_broadcast(
-                self.id,
-                2,
-                Message::Dummy,
-                time_current.add_milli(5),
-            ));
-            return_events.push(Event::new_broadcast(
-                self.id,
-                2,
-                Message::Dummy,
-                time_current.add_milli(10),
-            ));
+            outputs.push(send(2));
+            outputs.push(NodeOutput::Compute { micros: 5000 });
+            outputs.push(send(2));
         } else if self.id == 2 {
-            return_events.push(Event::new_broadcast(
-                self.id,
-                1,
-                Message::Dummy,
-                time_current.add_milli(50),
-            ));
+            outputs.push(NodeOutput::Compute { micros: 45000 });
+            outputs.push(send(1));
         }
 
-        Some(return_events)
+        Some(outputs)
     }
 }
 
@@ -98,61 +278,678 @@ pub struct PBFTNode {
     id: u32,
     /// holds the state required to take part in a PBFT cluster.
     state: PBFTState,
+    /// costs and sizes of the configured authentication (MACs or signatures)
+    crypto: CryptoModel,
+    /// disseminate PrePrepare messages via gossip instead of sending them to every peer
+    gossip_pre_prepare: bool,
+    /// size of the body of a client request
+    request_size: u32,
+    /// how the bodies of the requests reach the backups
+    dissemination: Dissemination,
 }
 
 impl PBFTNode {
     /// Creates a new `PBFTNode` by initializing the `ReplicaState`.
     /// The `ReplicaState` contains the state required for the PBFT operation.
     pub fn new(config: NodeConfig) -> Self {
+        let settings = &config.settings;
+
         PBFTNode {
-            state: PBFTState::new(config.id, config.number_of_nodes),
+            state: PBFTState::new(config.id, config.number_of_nodes)
+                .watermark_window(settings.pbft.watermark_window)
+                .instances(settings.pbft.ordering_instances(config.number_of_nodes))
+                .quorum(QuorumConfig::from_settings(
+                    config.number_of_nodes,
+                    &settings.quorum,
+                ))
+                .application(application::from_name(&settings.node.application))
+                .dissemination(settings.pbft.dissemination)
+                .result_logger(config.results.clone()),
             id: config.id,
+            crypto: CryptoModel::new(settings.pbft.authentication, &settings.crypto),
+            gossip_pre_prepare: settings.pbft.gossip_pre_prepare,
+            request_size: settings.pbft.request_size,
+            dissemination: settings.pbft.dissemination,
         }
     }
 }
 
 impl Node for PBFTNode {
-    fn handle_event(&mut self, reception: Reception, time: Time) -> Option<Vec<Event>> {
-        debug!(target: "node", "PBFTNode {} is processing a reception at {}ms: {:?}", self.id, time.to_string(), &reception);
+    fn handle_event(&mut self, reception: Reception, time: Time) -> Option<Vec<NodeOutput>> {
+        let mut outputs = Vec::new();
+        self.handle_event_into(reception, time, &mut outputs);
+        if outputs.is_empty() {
+            None
+        } else {
+            Some(outputs)
+        }
+    }
+
+    fn handle_event_into(
+        &mut self,
+        reception: Reception,
+        time: Time,
+        outputs: &mut Vec<NodeOutput>,
+    ) {
+        debug!(target: "node", "PBFTNode {} is processing a reception at {}ms: {:?}", self.id, time, &reception);
+        if reception.forged {
+            outputs.append(&mut reject(&self.crypto, &reception));
+            return;
+        }
 
-        match reception.message {
+        match reception.into_message() {
             Message::PBFT(pbft_message) => {
-                if let Some(out_events) = self.state.handle_message(pbft_message, time) {
-                    let mut events = Vec::<Event>::with_capacity(out_events.len());
-
-                    for (recv_id, msg) in out_events {
-                        events.push(Event::new_broadcast(
-                            self.id,
-                            recv_id,
-                            Message::PBFT(msg),
-                            // TODO: provide a more realistic value
-                            time.add_milli(5),
-                        ))
+                let out_events = match self.state.handle_message(pbft_message, time) {
+                    Ok(out_events) => out_events,
+                    Err(error) => {
+                        outputs.append(&mut drop_invalid(&self.crypto, "PBFT", error));
+                        return;
                     }
+                };
+                if let Some(out_events) = out_events {
+                    outputs.reserve(out_events.len() + 2);
+                    // the received message has to be verified before anything is sent
+                    outputs.push(NodeOutput::Compute {
+                        micros: self.crypto.verification_cost(),
+                    });
+
+                    // the output holds a multicast as consecutive entries of the same message,
+                    // its authenticator is computed once for all of the receivers
+                    let mut start = 0;
+                    while start < out_events.len() {
+                        let msg = out_events[start].1.clone();
+                        let receivers = out_events[start..]
+                            .iter()
+                            .take_while(|(_, m)| *m == msg)
+                            .count();
+
+                        // the clients of the protocols built on PBFT are not simulated as nodes,
+                        // the replies to them are only recorded
+                        if let PBFTMessage::ClientResponse(reply) = msg {
+                            if reply.id.client_id == PBFT_CLIENT_ID {
+                                outputs.push(NodeOutput::Log {
+                                    event: ResultEvent::Reply(reply.id.into()),
+                                });
+                                start += receivers;
+                                continue;
+                            }
+                        }
 
-                    return Some(events);
+                        outputs.push(NodeOutput::Compute {
+                            micros: self.crypto.authentication_cost(receivers),
+                        });
+                        let size = msg.payload_size_of(self.request_size, self.dissemination)
+                            + self.crypto.authenticator_size(receivers);
+
+                        if self.gossip_pre_prepare && receivers > 1 {
+                            if let PBFTMessage::PrePrepare(_) = msg {
+                                outputs.push(NodeOutput::Gossip {
+                                    message: Arc::new(Message::PBFT(msg)),
+                                    size,
+                                });
+                                start += receivers;
+                                continue;
+                            }
+                        }
+
+                        let message = Arc::new(Message::PBFT(msg));
+                        for (recv_id, _) in &out_events[start..start + receivers] {
+                            outputs.push(NodeOutput::Send {
+                                to: *recv_id,
+                                message: Arc::clone(&message),
+                                size,
+                            })
+                        }
+                        start += receivers;
+                    }
                 }
-                None
             }
             _ => {
                 panic!("Received a non pbft message for a pbft node!");
             }
         }
     }
+
+    fn execution(&self) -> Option<ExecutionRecord> {
+        Some(ExecutionRecord {
+            node_id: self.id,
+            executed: self.state.executed().to_vec(),
+            digest: self.state.digest(),
+        })
+    }
+}
+
+/// The `PBFTClientNode` hosts a PBFT client, the clients get the ids after the replicas
+#[derive(Debug)]
+pub struct PBFTClientNode {
+    id: u32,
+    client: PBFTClient,
+    /// costs and sizes of the configured authentication (MACs or signatures)
+    crypto: CryptoModel,
+    /// size of the body of a client request
+    request_size: u32,
+    /// how the bodies of the requests reach the backups
+    dissemination: Dissemination,
+}
+
+impl PBFTClientNode {
+    /// Creates the client of the replicas `1..=config.number_of_nodes`
+    pub fn new(config: NodeConfig) -> Self {
+        let settings = &config.settings;
+        PBFTClientNode {
+            client: PBFTClient::new(config.id, config.number_of_nodes)
+                .target(
+                    settings
+                        .pbft
+                        .client_target
+                        .resolve(settings, config.number_of_nodes),
+                )
+                .quorum(QuorumConfig::from_settings(
+                    config.number_of_nodes,
+                    &settings.quorum,
+                ))
+                .dissemination(settings.pbft.dissemination)
+                .result_logger(config.results.clone()),
+            id: config.id,
+            crypto: CryptoModel::new(settings.pbft.authentication, &settings.crypto),
+            request_size: settings.pbft.request_size,
+            dissemination: settings.pbft.dissemination,
+        }
+    }
+}
+
+impl Node for PBFTClientNode {
+    fn handle_event(&mut self, reception: Reception, time: Time) -> Option<Vec<NodeOutput>> {
+        debug!(target: "node", "PBFT client {} is processing a reception at {}ms: {:?}", self.id, time, &reception);
+        if reception.forged {
+            return Some(reject(&self.crypto, &reception));
+        }
+
+        let pbft_message = match reception.into_message() {
+            Message::PBFT(pbft_message) => pbft_message,
+            _ => panic!("Received a non pbft message for a pbft client!"),
+        };
+        // the replies have to be verified, the requests come from the simulation
+        let mut outputs = match pbft_message {
+            PBFTMessage::ClientResponse(_) => vec![NodeOutput::Compute {
+                micros: self.crypto.verification_cost(),
+            }],
+            _ => Vec::new(),
+        };
+        for (recv_id, msg) in self.client.handle_message(pbft_message, time)? {
+            outputs.push(NodeOutput::Compute {
+                micros: self.crypto.authentication_cost(1),
+            });
+            outputs.push(NodeOutput::Send {
+                to: recv_id,
+                size: msg.payload_size_of(self.request_size, self.dissemination)
+                    + self.crypto.authenticator_size(1),
+                message: Arc::new(Message::PBFT(msg)),
+            });
+        }
+        Some(outputs)
+    }
+}
+
+/*******************************************************************************
+ * SBFT node
+ ******************************************************************************/
+
+/// The `SBFTNode` acts as a host for a single replica of the linear PBFT of SBFT. Votes are shares of
+/// a threshold signature, the collector combines them into certificates carrying the aggregate.
+#[derive(Debug)]
+pub struct SBFTNode {
+    // id of the node
+    id: u32,
+    /// holds the state required to take part in a SBFT cluster.
+    state: SBFTState,
+    /// costs and sizes of the signatures and threshold signatures
+    crypto: CryptoModel,
+}
+
+impl SBFTNode {
+    /// Creates a new `SBFTNode` by initializing the `ReplicaState`.
+    pub fn new(config: NodeConfig) -> Self {
+        let settings = &config.settings;
+
+        SBFTNode {
+            state: SBFTState::new(config.id, config.number_of_nodes)
+                .quorum(QuorumConfig::from_settings(
+                    config.number_of_nodes,
+                    &settings.quorum,
+                ))
+                .application(application::from_name(&settings.node.application))
+                .result_logger(config.results.clone()),
+            id: config.id,
+            crypto: CryptoModel::new(AuthenticationMode::Signature, &settings.crypto),
+        }
+    }
+}
+
+impl Node for SBFTNode {
+    fn handle_event(&mut self, reception: Reception, time: Time) -> Option<Vec<NodeOutput>> {
+        debug!(target: "node", "SBFTNode {} is processing a reception at {}ms: {:?}", self.id, time, &reception);
+        if reception.forged {
+            return Some(reject(&self.crypto, &reception));
+        }
+
+        let sbft_message = match reception.into_message() {
+            Message::SBFT(sbft_message) => sbft_message,
+            _ => panic!("Received a non sbft message for a sbft node!"),
+        };
+
+        let verification_cost = match &sbft_message {
+            SBFTMessage::SignShare(_) => self.crypto.share_verify_cost,
+            SBFTMessage::Certificate(_) => self.crypto.aggregate_verify_cost,
+            _ => self.crypto.verify_cost,
+        };
+        let out_events = self.state.handle_message(sbft_message, time)?;
+
+        let mut outputs = Vec::<NodeOutput>::with_capacity(out_events.len() + 2);
+        // the received message has to be verified before anything is sent
+        outputs.push(NodeOutput::Compute {
+            micros: verification_cost,
+        });
+
+        // the copies of a multicast are consecutive, they are signed once and share the message
+        let mut shared: Option<Arc<Message>> = None;
+
+        for (recv_id, msg) in out_events {
+            let (cost, authenticator_size) = match &msg {
+                SBFTMessage::SignShare(_) => (self.crypto.share_sign_cost, self.crypto.share_size),
+                SBFTMessage::Certificate(certificate) => (
+                    self.crypto.combination_cost(certificate.shares as usize),
+                    self.crypto.aggregate_size,
+                ),
+                _ => (self.crypto.sign_cost, self.crypto.signature_size),
+            };
+            let size = msg.payload_size() + authenticator_size;
+            let message = match shared.take() {
+                Some(message) if matches!(&*message, Message::SBFT(m) if *m == msg) => message,
+                _ => {
+                    outputs.push(NodeOutput::Compute { micros: cost });
+                    Arc::new(Message::SBFT(msg))
+                }
+            };
+            shared = Some(Arc::clone(&message));
+            outputs.push(NodeOutput::Send {
+                to: recv_id,
+                message,
+                size,
+            });
+        }
+
+        Some(outputs)
+    }
+
+    fn execution(&self) -> Option<ExecutionRecord> {
+        Some(ExecutionRecord {
+            node_id: self.id,
+            executed: self.state.executed().to_vec(),
+            digest: self.state.digest(),
+        })
+    }
+}
+
+/*******************************************************************************
+ * Tendermint node
+ ******************************************************************************/
+
+/// The `TendermintNode` acts as a host for a single Tendermint replica. It turns the timeouts the
+/// replica asks for into timers and signs the proposals and votes.
+#[derive(Debug)]
+pub struct TendermintNode {
+    // id of the node
+    id: u32,
+    /// holds the state required to take part in a Tendermint cluster.
+    state: TendermintState,
+    /// costs and sizes of the signatures
+    crypto: CryptoModel,
+    /// durations of the timeouts
+    settings: TendermintSettings,
+    /// the timeouts of the pending timers, by token
+    timeouts: HashMap<u64, Timeout>,
+    next_token: u64,
+}
+
+impl TendermintNode {
+    /// Creates a new `TendermintNode` by initializing the `ReplicaState`.
+    pub fn new(config: NodeConfig) -> Self {
+        let settings = &config.settings;
+
+        TendermintNode {
+            state: TendermintState::new(config.id, config.number_of_nodes)
+                .quorum(QuorumConfig::from_settings(
+                    config.number_of_nodes,
+                    &settings.quorum,
+                ))
+                .application(application::from_name(&settings.node.application))
+                .result_logger(config.results.clone()),
+            id: config.id,
+            crypto: CryptoModel::new(AuthenticationMode::Signature, &settings.crypto),
+            settings: settings.tendermint.clone(),
+            timeouts: HashMap::new(),
+            next_token: 0,
+        }
+    }
+
+    /// Duration of `timeout`, later rounds wait longer
+    fn duration(&self, timeout: &Timeout) -> u64 {
+        let base = match timeout.step {
+            TimeoutStep::Propose => self.settings.timeout_propose,
+            TimeoutStep::Prevote => self.settings.timeout_prevote,
+            TimeoutStep::Precommit => self.settings.timeout_precommit,
+            TimeoutStep::Retransmit => return self.settings.retransmit_interval,
+        };
+        base + timeout.round * self.settings.timeout_delta
+    }
+
+    /// Lets the state handle the message and converts its output
+    fn process_message(
+        &mut self,
+        tendermint_message: TendermintMessage,
+        time: Time,
+    ) -> Option<Vec<NodeOutput>> {
+        // timeouts and client requests are not signed, a commit carries the signed precommits
+        let verification_cost = match &tendermint_message {
+            TendermintMessage::Proposal(_) | TendermintMessage::Vote(_) => self.crypto.verify_cost,
+            TendermintMessage::Commit(m) => self.crypto.verify_cost * u64::from(m.precommits),
+            _ => 0,
+        };
+        let out_events = self.state.handle_message(tendermint_message, time)?;
+
+        let mut outputs = Vec::<NodeOutput>::with_capacity(out_events.len() + 2);
+        if verification_cost > 0 {
+            outputs.push(NodeOutput::Compute {
+                micros: verification_cost,
+            });
+        }
+
+        // the copies of a multicast are consecutive, they are signed once and share the message
+        let mut shared: Option<Arc<Message>> = None;
+
+        for (recv_id, msg) in out_events {
+            if let TendermintMessage::Timeout(timeout) = msg {
+                let token = self.next_token;
+                self.next_token += 1;
+                outputs.push(NodeOutput::SetTimer {
+                    token,
+                    duration: self.duration(&timeout),
+                });
+                self.timeouts.insert(token, timeout);
+                continue;
+            }
+
+            let size = match &msg {
+                TendermintMessage::Gossip(_) => msg.payload_size(),
+                TendermintMessage::Commit(m) => {
+                    msg.payload_size() + m.precommits * self.crypto.signature_size
+                }
+                _ => msg.payload_size() + self.crypto.signature_size,
+            };
+            let message = match shared.take() {
+                Some(message) if matches!(&*message, Message::Tendermint(m) if *m == msg) => {
+                    message
+                }
+                _ => {
+                    if let TendermintMessage::Proposal(_) | TendermintMessage::Vote(_) = msg {
+                        outputs.push(NodeOutput::Compute {
+                            micros: self.crypto.sign_cost,
+                        });
+                    }
+                    Arc::new(Message::Tendermint(msg))
+                }
+            };
+            shared = Some(Arc::clone(&message));
+            outputs.push(NodeOutput::Send {
+                to: recv_id,
+                message,
+                size,
+            });
+        }
+
+        Some(outputs)
+    }
+}
+
+impl Node for TendermintNode {
+    fn handle_event(&mut self, reception: Reception, time: Time) -> Option<Vec<NodeOutput>> {
+        debug!(target: "node", "Tendermint {} is processing a reception at {}ms: {:?}", self.id, time, &reception);
+        if reception.forged {
+            return Some(reject(&self.crypto, &reception));
+        }
+
+        match reception.into_message() {
+            Message::Tendermint(tendermint_message) => {
+                self.process_message(tendermint_message, time)
+            }
+            _ => panic!("Received a non tendermint message for a tendermint node!"),
+        }
+    }
+
+    fn handle_timer(&mut self, token: u64, time: Time) -> Option<Vec<NodeOutput>> {
+        let timeout = self.timeouts.remove(&token)?;
+        self.process_message(TendermintMessage::Timeout(timeout), time)
+    }
+
+    fn execution(&self) -> Option<ExecutionRecord> {
+        Some(ExecutionRecord {
+            node_id: self.id,
+            executed: self.state.executed().to_vec(),
+            digest: self.state.digest(),
+        })
+    }
+}
+
+/*******************************************************************************
+ * DAG node
+ ******************************************************************************/
+
+/// The `DAGNode` acts as a host for a single replica of the DAG protocol. It signs its vertices and
+/// verifies the vertices of the other replicas, the ordering needs no further messages.
+#[derive(Debug)]
+pub struct DAGNode {
+    // id of the node
+    id: u32,
+    /// holds the state required to take part in a DAG cluster.
+    state: DAGState,
+    /// costs and sizes of the signatures
+    crypto: CryptoModel,
+}
+
+impl DAGNode {
+    /// Creates a new `DAGNode` by initializing the `ReplicaState`.
+    pub fn new(config: NodeConfig) -> Self {
+        let settings = &config.settings;
+
+        DAGNode {
+            state: DAGState::new(config.id, config.number_of_nodes)
+                .quorum(QuorumConfig::from_settings(
+                    config.number_of_nodes,
+                    &settings.quorum,
+                ))
+                .batch_size(settings.dag.batch_size)
+                .application(application::from_name(&settings.node.application))
+                .result_logger(config.results.clone()),
+            id: config.id,
+            crypto: CryptoModel::new(AuthenticationMode::Signature, &settings.crypto),
+        }
+    }
+}
+
+impl Node for DAGNode {
+    fn handle_event(&mut self, reception: Reception, time: Time) -> Option<Vec<NodeOutput>> {
+        debug!(target: "node", "DAGNode {} is processing a reception at {}ms: {:?}", self.id, time, &reception);
+        if reception.forged {
+            return Some(reject(&self.crypto, &reception));
+        }
+
+        let dag_message = match reception.into_message() {
+            Message::DAG(dag_message) => dag_message,
+            _ => panic!("Received a non dag message for a dag node!"),
+        };
+        let out_events = self.state.handle_message(dag_message, time)?;
+
+        let mut outputs = Vec::<NodeOutput>::with_capacity(out_events.len() + 2);
+        // the received message has to be verified before anything is sent
+        outputs.push(NodeOutput::Compute {
+            micros: self.crypto.verify_cost,
+        });
+
+        // the copies of a vertex are consecutive, it is signed once and the copies share it
+        let mut shared: Option<Arc<Message>> = None;
+
+        for (recv_id, msg) in out_events {
+            let size = msg.payload_size() + self.crypto.signature_size;
+            let message = match shared.take() {
+                Some(message) if matches!(&*message, Message::DAG(m) if *m == msg) => message,
+                _ => {
+                    outputs.push(NodeOutput::Compute {
+                        micros: self.crypto.sign_cost,
+                    });
+                    Arc::new(Message::DAG(msg))
+                }
+            };
+            shared = Some(Arc::clone(&message));
+            outputs.push(NodeOutput::Send {
+                to: recv_id,
+                message,
+                size,
+            });
+        }
+
+        Some(outputs)
+    }
+
+    fn execution(&self) -> Option<ExecutionRecord> {
+        Some(ExecutionRecord {
+            node_id: self.id,
+            executed: self.state.executed().to_vec(),
+            digest: self.state.digest(),
+        })
+    }
+}
+
+/*******************************************************************************
+ * Steward node
+ ******************************************************************************/
+
+/// The `StewardNode` acts as a host for a single replica of the hierarchical Steward protocol. The
+/// sites are the data centers of the 'clusters' topology, `network.clusters` of them. Votes within a
+/// site are shares of a threshold signature, the messages between the sites carry the aggregate.
+#[derive(Debug)]
+pub struct StewardNode {
+    // id of the node
+    id: u32,
+    /// holds the state required to take part in a Steward cluster.
+    state: StewardState,
+    /// costs and sizes of the signatures and threshold signatures
+    crypto: CryptoModel,
+}
+
+impl StewardNode {
+    /// Creates a new `StewardNode` by initializing the `ReplicaState`.
+    pub fn new(config: NodeConfig) -> Self {
+        let settings = &config.settings;
+
+        StewardNode {
+            state: StewardState::new(
+                config.id,
+                config.number_of_nodes,
+                settings.network.clusters,
+            )
+            .application(application::from_name(&settings.node.application))
+            .result_logger(config.results.clone()),
+            id: config.id,
+            crypto: CryptoModel::new(AuthenticationMode::Signature, &settings.crypto),
+        }
+    }
+}
+
+impl Node for StewardNode {
+    fn handle_event(&mut self, reception: Reception, time: Time) -> Option<Vec<NodeOutput>> {
+        debug!(target: "node", "StewardNode {} is processing a reception at {}ms: {:?}", self.id, time, &reception);
+        if reception.forged {
+            return Some(reject(&self.crypto, &reception));
+        }
+
+        let steward_message = match reception.into_message() {
+            Message::Steward(steward_message) => steward_message,
+            _ => panic!("Received a non steward message for a steward node!"),
+        };
+
+        let verification_cost = match &steward_message {
+            StewardMessage::LocalVote(_) => self.crypto.share_verify_cost,
+            StewardMessage::Proposal(_) | StewardMessage::Accept(_) => {
+                self.crypto.aggregate_verify_cost
+            }
+            _ => self.crypto.verify_cost,
+        };
+        let out_events = self.state.handle_message(steward_message, time)?;
+
+        let mut outputs = Vec::<NodeOutput>::with_capacity(out_events.len() + 2);
+        // the received message has to be verified before anything is sent
+        outputs.push(NodeOutput::Compute {
+            micros: verification_cost,
+        });
+
+        // the copies of a multicast are consecutive, they are signed once and share the message
+        let mut shared: Option<Arc<Message>> = None;
+
+        for (recv_id, msg) in out_events {
+            let (cost, authenticator_size) = match &msg {
+                StewardMessage::LocalVote(_) => {
+                    (self.crypto.share_sign_cost, self.crypto.share_size)
+                }
+                StewardMessage::Proposal(_) | StewardMessage::Accept(_) => (
+                    self.crypto.combination_cost(self.state.local_quorum()),
+                    self.crypto.aggregate_size,
+                ),
+                _ => (self.crypto.sign_cost, self.crypto.signature_size),
+            };
+            let size = msg.payload_size() + authenticator_size;
+            let message = match shared.take() {
+                Some(message) if matches!(&*message, Message::Steward(m) if *m == msg) => message,
+                _ => {
+                    outputs.push(NodeOutput::Compute { micros: cost });
+                    Arc::new(Message::Steward(msg))
+                }
+            };
+            shared = Some(Arc::clone(&message));
+            outputs.push(NodeOutput::Send {
+                to: recv_id,
+                message,
+                size,
+            });
+        }
+
+        Some(outputs)
+    }
+
+    fn execution(&self) -> Option<ExecutionRecord> {
+        Some(ExecutionRecord {
+            node_id: self.id,
+            executed: self.state.executed().to_vec(),
+            digest: self.state.digest(),
+        })
+    }
 }
 
 /*******************************************************************************
  * Zyzzyva node
  ******************************************************************************/
 
-/// The `PBFTNode` acts as a host for a single replica. It holds the `ReplicaState`
-/// required for the participation in a PBFT cluster.
+/// The `ZyzzyvaNode` acts as a host for a single replica, the client is a `ZyzzyvaClientNode`.
 #[derive(Debug)]
 pub struct ZyzzyvaNode {
     // id of the node
     id: u32,
-    /// holds the state required to take part in a PBFT cluster.
+    /// holds the state required to take part in a Zyzzyva cluster.
     state: ZyzzyvaState,
+    /// cost (µs) of rolling back a single speculative execution
+    rollback_cost: u64,
+    /// costs and sizes of the configured authentication (MACs or signatures)
+    crypto: CryptoModel,
 }
 
 impl ZyzzyvaNode {
@@ -160,60 +957,200 @@ impl ZyzzyvaNode {
     /// The `ReplicaState` contains the state required for the PBFT operation.
     pub fn new(config: NodeConfig) -> Self {
         ZyzzyvaNode {
-            state: ZyzzyvaState::new(config.id, config.number_of_nodes),
+            state: ZyzzyvaState::new(config.id, config.number_of_nodes)
+                .quorum(QuorumConfig::from_settings(
+                    config.number_of_nodes,
+                    &config.settings.quorum,
+                ))
+                .application(application::from_name(&config.settings.node.application))
+                .result_logger(config.results.clone())
+                .speculative_execution(config.settings.zyzzyva.speculative_execution),
             id: config.id,
+            rollback_cost: config.settings.zyzzyva.rollback_cost,
+            crypto: CryptoModel::new(
+                config.settings.zyzzyva.authentication,
+                &config.settings.crypto,
+            ),
+        }
+    }
+
+    /// Lets the state handle the message and converts its output
+    fn process_message(
+        &mut self,
+        zyzzyva_message: ZyzzyvaMessage,
+        time: Time,
+    ) -> Option<Vec<NodeOutput>> {
+        let out_events = match self.state.handle_message(zyzzyva_message, time) {
+            Ok(out_events) => out_events,
+            Err(error) => return Some(drop_invalid(&self.crypto, "Zyzzyva", error)),
+        };
+        // rolling back invalidated speculative executions delays everything the replica sends
+        let rolled_back = self.state.take_rolled_back();
+        let mut outputs = Vec::<NodeOutput>::new();
+        if rolled_back > 0 {
+            outputs.push(NodeOutput::Compute {
+                micros: rolled_back * self.rollback_cost,
+            });
         }
+        let out_events = match out_events {
+            Some(out_events) => out_events,
+            None if outputs.is_empty() => return None,
+            None => return Some(outputs),
+        };
+        zyzzyva_sends(&self.crypto, &out_events, &mut outputs);
+        Some(outputs)
+    }
+}
+
+// converts the messages a Zyzzyva node sends into outputs pushed to `outputs`. The copies of a
+// multicast are consecutive entries of the same message, they share the message and its
+// authenticator is computed once for all of the receivers
+fn zyzzyva_sends(
+    crypto: &CryptoModel,
+    out_events: &[(u32, ZyzzyvaMessage)],
+    outputs: &mut Vec<NodeOutput>,
+) {
+    let mut start = 0;
+    while start < out_events.len() {
+        let msg = &out_events[start].1;
+        let receivers = out_events[start..]
+            .iter()
+            .take_while(|(_, m)| m == msg)
+            .count();
+        outputs.push(NodeOutput::Compute {
+            micros: crypto.authentication_cost(receivers),
+        });
+        let size = msg.payload_size() + crypto.authenticator_size(receivers);
+        let message = Arc::new(Message::Zyzzyva(msg.clone()));
+        for (recv_id, _) in &out_events[start..start + receivers] {
+            outputs.push(NodeOutput::Send {
+                to: *recv_id,
+                message: Arc::clone(&message),
+                size,
+            });
+        }
+        start += receivers;
     }
 }
 
 impl Node for ZyzzyvaNode {
-    fn handle_event(&mut self, reception: Reception, time: Time) -> Option<Vec<Event>> {
-        debug!(target: "node", "Zyzzyva {} is processing a reception at {}ms: {:?}", self.id, time.to_string(), &reception);
+    fn handle_event(&mut self, reception: Reception, time: Time) -> Option<Vec<NodeOutput>> {
+        debug!(target: "node", "Zyzzyva {} is processing a reception at {}ms: {:?}", self.id, time, &reception);
+        if reception.forged {
+            return Some(reject(&self.crypto, &reception));
+        }
 
-        match reception.message {
+        match reception.into_message() {
             Message::Zyzzyva(zyzzyva_message) => {
-                if let Some(out_events) = self.state.handle_message(zyzzyva_message, time) {
-                    let mut events = Vec::<Event>::with_capacity(out_events.len());
-
-                    for (recv_id, msg) in out_events {
-                        match msg {
-                            zyzzyva::messages::ZyzzyvaMessage::ClientTimeout(_) => {
-                                events.push(Event::new_timeout(
-                                    recv_id,
-                                    Message::Zyzzyva(msg),
-                                    time,
-                                ));
-                            }
-                            zyzzyva::messages::ZyzzyvaMessage::ClientRequest(_) => {
-                                events.push(Event::new_broadcast_custom(
-                                    self.id,
-                                    recv_id,
-                                    Message::Zyzzyva(msg),
-                                    // TODO: provide a more realistic value
-                                    time.add_milli(5),
-                                    true,
-                                    Some(Time::new(0)),
-                                ));
-                            }
-                            _ => {
-                                events.push(Event::new_broadcast(
-                                    self.id,
-                                    recv_id,
-                                    Message::Zyzzyva(msg),
-                                    // TODO: provide a more realistic value
-                                    time.add_milli(5),
-                                ));
-                            }
-                        }
-                    }
-
-                    return Some(events);
-                }
-                None
+                let mut outputs = self.process_message(zyzzyva_message, time)?;
+                // the received message has to be verified before anything is sent
+                outputs.insert(
+                    0,
+                    NodeOutput::Compute {
+                        micros: self.crypto.verification_cost(),
+                    },
+                );
+                Some(outputs)
             }
             _ => {
                 panic!("Received a non node.pbft message for a node.pbft node!");
             }
         }
     }
+
+    fn execution(&self) -> Option<ExecutionRecord> {
+        Some(ExecutionRecord {
+            node_id: self.id,
+            executed: self.state.executed().to_vec(),
+            digest: self.state.digest(),
+        })
+    }
+}
+
+/// The `ZyzzyvaClientNode` hosts the Zyzzyva client, it gets the ids after the replicas
+#[derive(Debug)]
+pub struct ZyzzyvaClientNode {
+    id: u32,
+    client: ZyzzyvaClient,
+    /// duration (ms) after which the client times out on a request
+    client_timeout: u64,
+    /// costs and sizes of the configured authentication (MACs or signatures)
+    crypto: CryptoModel,
+}
+
+impl ZyzzyvaClientNode {
+    /// Creates the client of the replicas `1..=config.number_of_nodes`
+    pub fn new(config: NodeConfig) -> Self {
+        ZyzzyvaClientNode {
+            client: ZyzzyvaClient::new(config.id, config.number_of_nodes)
+                .quorum(QuorumConfig::from_settings(
+                    config.number_of_nodes,
+                    &config.settings.quorum,
+                ))
+                .result_logger(config.results.clone())
+                .speculative_execution(config.settings.zyzzyva.speculative_execution),
+            id: config.id,
+            client_timeout: config.settings.node.client_timeout,
+            crypto: CryptoModel::new(
+                config.settings.zyzzyva.authentication,
+                &config.settings.crypto,
+            ),
+        }
+    }
+
+    /// Lets the client handle the message and converts its output
+    fn process_message(
+        &mut self,
+        zyzzyva_message: ZyzzyvaMessage,
+        time: Time,
+    ) -> Option<Vec<NodeOutput>> {
+        let mut outputs = Vec::new();
+        let mut sends = Vec::new();
+        for (recv_id, msg) in self.client.handle_message(zyzzyva_message, time)? {
+            match msg {
+                // the request id serves as token of the timer
+                ZyzzyvaMessage::ClientTimeout(timeout) => outputs.push(NodeOutput::SetTimer {
+                    token: u64::from(timeout.req_id.sequence),
+                    duration: self.client_timeout,
+                }),
+                ZyzzyvaMessage::ClientRequest(_) => outputs.push(NodeOutput::DeliverToClient {
+                    to: recv_id,
+                    message: Arc::new(Message::Zyzzyva(msg)),
+                }),
+                _ => sends.push((recv_id, msg)),
+            }
+        }
+        zyzzyva_sends(&self.crypto, &sends, &mut outputs);
+        Some(outputs)
+    }
+}
+
+impl Node for ZyzzyvaClientNode {
+    fn handle_event(&mut self, reception: Reception, time: Time) -> Option<Vec<NodeOutput>> {
+        debug!(target: "node", "Zyzzyva client {} is processing a reception at {}ms: {:?}", self.id, time, &reception);
+        if reception.forged {
+            return Some(reject(&self.crypto, &reception));
+        }
+
+        match reception.into_message() {
+            Message::Zyzzyva(zyzzyva_message) => {
+                let mut outputs = self.process_message(zyzzyva_message, time)?;
+                // the received message has to be verified before anything is sent
+                outputs.insert(
+                    0,
+                    NodeOutput::Compute {
+                        micros: self.crypto.verification_cost(),
+                    },
+                );
+                Some(outputs)
+            }
+            _ => panic!("Received a non-Zyzzyva message for a Zyzzyva client!"),
+        }
+    }
+
+    fn handle_timer(&mut self, token: u64, time: Time) -> Option<Vec<NodeOutput>> {
+        // the request id serves as token of the timer
+        let timeout = ClientTimeout::new(RequestId::new(self.id, token as u32));
+        self.process_message(ZyzzyvaMessage::ClientTimeout(timeout), time)
+    }
 }
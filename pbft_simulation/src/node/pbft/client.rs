@@ -0,0 +1,188 @@
+use std::collections::{HashMap, HashSet};
+
+use log::warn;
+
+use super::messages::*;
+use crate::node::quorum::{QuorumConfig, ReplicaSet};
+use crate::node::request::RequestId;
+use crate::simulation::config::{ClientTarget, ResultLogger};
+use crate::simulation::results::ResultEvent;
+use crate::simulation::time::Time;
+
+type Output = Vec<(u32, PBFTMessage)>;
+
+/// The PBFT client. It receives the requests from the simulation, sends them to a replica and
+/// completes them once f + 1 replicas sent the same result, of which at least one is correct.
+#[derive(Debug)]
+pub struct Client {
+    id: u32,
+    /// The replicas that replied to the pending requests, by their result
+    pending: HashMap<RequestId, HashMap<u32, ReplicaSet>>,
+    /// Completed requests, subsequent replies to them are ignored.
+    completed: HashSet<RequestId>,
+    num_of_replicas: u32,
+    /// The replica each request is sent to
+    target: ClientTarget,
+    /// How the bodies of the requests reach the backups
+    dissemination: Dissemination,
+    /// Number of matching replies that complete a request
+    replies_needed: usize,
+    /// The result log the phases of the requests are written to
+    results: ResultLogger,
+}
+
+impl Client {
+    /// Creates the client `id` of a cluster of the replicas `1..=num_of_replicas`, which sends
+    /// its requests to the primary.
+    pub fn new(id: u32, num_of_replicas: u32) -> Self {
+        Client {
+            id,
+            pending: HashMap::new(),
+            completed: HashSet::new(),
+            num_of_replicas,
+            target: ClientTarget::Node(1),
+            dissemination: Dissemination::Inline,
+            replies_needed: QuorumConfig::new(num_of_replicas).f() as usize + 1,
+            results: ResultLogger::default(),
+        }
+    }
+
+    /// Writes the phases of the requests to `results` instead of discarding them.
+    pub fn result_logger(mut self, results: ResultLogger) -> Self {
+        self.results = results;
+        self
+    }
+
+    /// Sends the requests to the replicas picked by `target`.
+    pub fn target(mut self, target: ClientTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// With `Dissemination::Client` the client sends the bodies of its requests to all replicas.
+    pub fn dissemination(mut self, dissemination: Dissemination) -> Self {
+        self.dissemination = dissemination;
+        self
+    }
+
+    /// Replaces the standard quorum system of the replicas, a request needs f + 1 replies.
+    pub fn quorum(mut self, quorum: QuorumConfig) -> Self {
+        self.replies_needed = quorum.f() as usize + 1;
+        self
+    }
+
+    pub fn handle_message(&mut self, message: PBFTMessage, time: Time) -> Option<Output> {
+        match message {
+            PBFTMessage::ClientRequest(m) => self.handle_client_request(m, time),
+            PBFTMessage::ClientResponse(m) if !self.completed.contains(&m.id) => {
+                self.handle_client_response(m, time)
+            }
+            PBFTMessage::ClientResponse(_) => None,
+            _ => {
+                warn!(target: "node", "Client {} dropped a {} message, which is meant for the replicas", self.id, message.message_type());
+                None
+            }
+        }
+    }
+
+    /// The simulation hands a request to the client, which sends it to its target replica.
+    fn handle_client_request(&mut self, msg_in: ClientRequest, time: Time) -> Option<Output> {
+        self.results
+            .log(time, Some(self.id), &ResultEvent::Request(msg_in.id.into()));
+        self.pending.insert(msg_in.id, HashMap::new());
+
+        let replica = self
+            .target
+            .pick(u64::from(msg_in.id.sequence), self.num_of_replicas);
+        let mut output = vec![(replica, PBFTMessage::ClientRequest(msg_in))];
+        // the other replicas get the body, the primary orders it by its digest
+        if self.dissemination == Dissemination::Client {
+            output.extend(
+                (1..=self.num_of_replicas)
+                    .filter(|id| *id != replica)
+                    .map(|id| (id, PBFTMessage::Request(msg_in))),
+            );
+        }
+        Some(output)
+    }
+
+    /// Completes the request with the first result that f + 1 replicas agree on.
+    fn handle_client_response(&mut self, msg_in: ClientResponse, time: Time) -> Option<Output> {
+        let replies = match self.pending.get_mut(&msg_in.id) {
+            Some(replies) => replies,
+            None => {
+                warn!(target: "node", "Client {} dropped a reply to request {}, which it did not send", self.id, msg_in.id);
+                return None;
+            }
+        };
+        let matching = replies.entry(msg_in.result).or_default();
+        matching.insert(msg_in.sender_id);
+
+        if matching.len() >= self.replies_needed {
+            self.results.log(
+                time,
+                Some(self.id),
+                &ResultEvent::Completed(msg_in.id.into()),
+            );
+            self.pending.remove(&msg_in.id);
+            self.completed.insert(msg_in.id);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(sender_id: u32, result: u32) -> PBFTMessage {
+        PBFTMessage::ClientResponse(ClientResponse {
+            id: RequestId::new(5, 1),
+            result,
+            sender_id,
+        })
+    }
+
+    #[test]
+    fn completes_after_f_plus_one_matching_replies() {
+        let results = ResultLogger::new();
+        results.collect();
+        let mut client = Client::new(5, 4).result_logger(results.clone());
+
+        let c_req = ClientRequest::new(RequestId::new(5, 1), 1);
+        assert_eq!(
+            client.handle_message(PBFTMessage::ClientRequest(c_req), Time::new(10)),
+            Some(vec![(1, PBFTMessage::ClientRequest(c_req))])
+        );
+
+        // a faulty replica alone cannot complete the request
+        client.handle_message(reply(4, 13), Time::new(20));
+        client.handle_message(reply(2, 1), Time::new(30));
+        assert!(!client.completed.contains(&c_req.id));
+        client.handle_message(reply(2, 1), Time::new(35));
+        assert!(!client.completed.contains(&c_req.id));
+        client.handle_message(reply(3, 1), Time::new(40));
+        client.handle_message(reply(1, 1), Time::new(50));
+
+        assert_eq!(
+            results.take_lines(),
+            vec!["10;5;1;request", "40;5;1;completed"]
+        );
+    }
+
+    #[test]
+    fn drops_stray_messages() {
+        let mut client = Client::new(5, 4);
+        let c_req = ClientRequest::new(RequestId::new(5, 1), 1);
+        let pre_prepare = PrePrepareMessage::new(c_req, 1, 1, 1);
+
+        assert_eq!(
+            client.handle_message(PBFTMessage::PrePrepare(pre_prepare), Time::new(10)),
+            None
+        );
+        // a reply to a request the client did not send
+        assert_eq!(client.handle_message(reply(2, 1), Time::new(20)), None);
+        assert!(client.pending.is_empty());
+        assert!(client.completed.is_empty());
+    }
+}
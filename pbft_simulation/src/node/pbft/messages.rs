@@ -1,31 +1,174 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::node::request::RequestId;
+use crate::simulation::event::Priority;
+
 /// Type defining (currently) possible _PBFT messages_ that can be send by
 /// replicas or clients.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
 pub enum PBFTMessage {
     ClientRequest(ClientRequest),
     ClientResponse(ClientResponse),
+    /// The body of a request that is ordered by its digest, see `Dissemination`
+    Request(ClientRequest),
     PrePrepare(PrePrepareMessage),
     Prepare(PrepareMessage),
     Commit(CommitMessage),
+    FetchState(FetchStateMessage),
+    StateResponse(StateResponseMessage),
+}
+
+/// Size of a client request: client id, sequence number and operation
+pub const CLIENT_REQUEST_SIZE: u32 = 12;
+/// Size of the digest of a client request
+pub const DIGEST_SIZE: u32 = 32;
+/// Size of the fields shared by all quorum messages: view, sequence number and sender id
+const QUORUM_HEADER_SIZE: u32 = 20;
+
+impl PBFTMessage {
+    /// Name of the message type, e.g. for statistics.
+    pub fn message_type(&self) -> &'static str {
+        match self {
+            PBFTMessage::ClientRequest(_) => "ClientRequest",
+            PBFTMessage::ClientResponse(_) => "ClientResponse",
+            PBFTMessage::Request(_) => "Request",
+            PBFTMessage::PrePrepare(_) => "PrePrepare",
+            PBFTMessage::Prepare(_) => "Prepare",
+            PBFTMessage::Commit(_) => "Commit",
+            PBFTMessage::FetchState(_) => "FetchState",
+            PBFTMessage::StateResponse(_) => "StateResponse",
+        }
+    }
+
+    /// Priority class at an overloaded node, the state transfer is control traffic.
+    pub fn priority(&self) -> Priority {
+        match self {
+            PBFTMessage::ClientRequest(_)
+            | PBFTMessage::ClientResponse(_)
+            | PBFTMessage::Request(_) => Priority::Client,
+            PBFTMessage::PrePrepare(_) | PBFTMessage::Prepare(_) | PBFTMessage::Commit(_) => {
+                Priority::Ordering
+            }
+            PBFTMessage::FetchState(_) | PBFTMessage::StateResponse(_) => Priority::Control,
+        }
+    }
+
+    /// Whether the sender appends the message to its log before it sends it, see `[storage]`.
+    pub fn is_persisted(&self) -> bool {
+        matches!(
+            self,
+            PBFTMessage::PrePrepare(_) | PBFTMessage::Prepare(_) | PBFTMessage::Commit(_)
+        )
+    }
+
+    /// Id of the client request the message belongs to, if it belongs to a single one.
+    pub fn request_id(&self) -> Option<u64> {
+        match self {
+            PBFTMessage::ClientRequest(m) => Some(u64::from(m.id.sequence)),
+            PBFTMessage::ClientResponse(m) => Some(u64::from(m.id.sequence)),
+            PBFTMessage::Request(m) => Some(u64::from(m.id.sequence)),
+            PBFTMessage::PrePrepare(m) => Some(u64::from(m.c_req.id.sequence)),
+            PBFTMessage::Prepare(m) => Some(u64::from(m.c_req.id.sequence)),
+            PBFTMessage::Commit(m) => Some(u64::from(m.c_req.id.sequence)),
+            PBFTMessage::FetchState(_) | PBFTMessage::StateResponse(_) => None,
+        }
+    }
+
+    /// Size of the message in bytes, without its authenticator.
+    pub fn payload_size(&self) -> u32 {
+        self.payload_size_of(CLIENT_REQUEST_SIZE, Dissemination::Inline)
+    }
+
+    /// Size of the message in bytes, without its authenticator, for requests of `request_size`
+    /// bytes. Unless the requests are inline the quorum messages carry their digests.
+    pub fn payload_size_of(&self, request_size: u32, dissemination: Dissemination) -> u32 {
+        let ordered = match dissemination {
+            Dissemination::Inline => request_size,
+            Dissemination::Primary | Dissemination::Client => DIGEST_SIZE,
+        };
+        match self {
+            PBFTMessage::ClientRequest(_) | PBFTMessage::Request(_) => request_size,
+            PBFTMessage::ClientResponse(_) => 16,
+            PBFTMessage::PrePrepare(_) => QUORUM_HEADER_SIZE + ordered,
+            PBFTMessage::Prepare(_) => QUORUM_HEADER_SIZE + ordered,
+            PBFTMessage::Commit(_) => QUORUM_HEADER_SIZE + ordered,
+            PBFTMessage::FetchState(_) => 12,
+            PBFTMessage::StateResponse(m) => 4 + m.entries.len() as u32 * (8 + request_size),
+        }
+    }
+}
+
+/// How the bodies of the requests reach the backups
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Dissemination {
+    /// The PrePrepare carries the request
+    Inline,
+    /// The primary sends the request to the backups, the PrePrepare carries its digest
+    Primary,
+    /// The client multicasts the request to all replicas, the PrePrepare carries its digest
+    Client,
 }
 
+impl FromStr for Dissemination {
+    type Err = String;
+
+    fn from_str(dissemination: &str) -> Result<Self, Self::Err> {
+        match dissemination {
+            "inline" => Ok(Dissemination::Inline),
+            "primary" => Ok(Dissemination::Primary),
+            "client" => Ok(Dissemination::Client),
+            _ => Err(format!(
+                "dissemination '{}' is not available, allowed are 'inline', 'primary', 'client'",
+                dissemination
+            )),
+        }
+    }
+}
+
+/// Client id of the null requests leaders order to fill the sequence numbers of their instance
+pub const NULL_CLIENT_ID: u32 = u32::MAX;
+
 /// Type defining a _client request_.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
 pub struct ClientRequest {
+    /// The issuing client and its sequence number
+    pub id: RequestId,
     pub operation: u32,
-    pub sender_id: u32,
+}
+
+impl ClientRequest {
+    pub fn new(id: RequestId, operation: u32) -> Self {
+        ClientRequest { id, operation }
+    }
+
+    /// A request without an operation, ordered at `seq_number` by a leader that has no client
+    /// request for it
+    pub fn null(seq_number: u64) -> Self {
+        ClientRequest::new(RequestId::new(NULL_CLIENT_ID, seq_number as u32), 0)
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.id.client_id == NULL_CLIENT_ID
+    }
 }
 
 /// Type defining a _client response_ message send by replicas after successfully
 /// committing locally.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
 pub struct ClientResponse {
+    pub id: RequestId,
     pub result: u32,
     pub sender_id: u32,
 }
 
 /// Type defining a _Pre-Prepare_ message send by the _primary_.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
 pub struct PrePrepareMessage {
     pub c_req: ClientRequest,
     pub view: u64,
@@ -45,7 +188,8 @@ impl PrePrepareMessage {
 }
 
 /// Type defining a _Prepare_ message send by _backups_.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
 pub struct PrepareMessage {
     pub c_req: ClientRequest,
     pub view: u64,
@@ -65,7 +209,8 @@ impl PrepareMessage {
 }
 
 /// Type defining a _Commit_ message send by the _primary_ and _backups_.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
 pub struct CommitMessage {
     pub c_req: ClientRequest,
     pub view: u64,
@@ -83,3 +228,38 @@ impl CommitMessage {
         }
     }
 }
+
+/// Type defining a _Fetch-State_ message send by a replica that fell behind
+/// its peers, i.e. saw a sequence number beyond its high watermark.
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct FetchStateMessage {
+    /// The sequence number up to which the sender executed all requests
+    pub last_executed: u64,
+    pub sender_id: u32,
+}
+
+impl FetchStateMessage {
+    pub fn new(last_executed: u64, sender_id: u32) -> Self {
+        FetchStateMessage {
+            last_executed,
+            sender_id,
+        }
+    }
+}
+
+/// Type defining a _State-Response_ message answering a `FetchStateMessage`.
+/// Holds all requests the sender committed after the requester's `last_executed`.
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
+pub struct StateResponseMessage {
+    /// Pairs of sequence number and the committed request
+    pub entries: Vec<(u64, ClientRequest)>,
+    pub sender_id: u32,
+}
+
+impl StateResponseMessage {
+    pub fn new(entries: Vec<(u64, ClientRequest)>, sender_id: u32) -> Self {
+        StateResponseMessage { entries, sender_id }
+    }
+}
@@ -1,8 +1,15 @@
-use std::collections::{HashMap, HashSet};
-
-use log::warn;
-
-use crate::simulation::config::log_result;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
+
+use log::{debug, warn};
+use serde::Serialize;
+
+use crate::node::application::{Application, CounterApplication};
+use crate::node::error::ProtocolError;
+use crate::node::quorum::{QuorumConfig, ReplicaSet};
+use crate::node::request::RequestId;
+use crate::simulation::config::ResultLogger;
+use crate::simulation::results::ResultEvent;
 use crate::simulation::time::Time;
 
 use super::messages::*;
@@ -10,38 +17,85 @@ use super::messages::*;
 /// The output produced by this module. Consumed by the host running the `ReplicaState`.
 type Output = Vec<(u32, PBFTMessage)>;
 
+/// Default distance between the low and the high watermark.
+pub const DEFAULT_WATERMARK_WINDOW: u64 = 100;
+
 /// Creates an `Output` such that the host broadcasts `msg_out` to all other
 /// replicas in the cluster.
 fn create_peer_broadcast_output(msg_out: PBFTMessage, peers: &Vec<u32>) -> Output {
     let mut output = Output::with_capacity(peers.len());
 
     for id in peers {
-        output.push((*id, msg_out));
+        output.push((*id, msg_out.clone()));
     }
 
     output
 }
 
-/// The type defining allowed Prepare (1st) quorum messages.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
-enum PrepareQuorumMessage {
-    PrePrepareMessage(PrePrepareMessage),
-    PrepareMessage(PrepareMessage),
+/// The primary rotates through the replicas `1..=num_of_nodes`, starting with `1` in view `1`.
+fn primary_of_view(view: u64, num_of_nodes: u32) -> u32 {
+    ((view.saturating_sub(1) % u64::from(num_of_nodes)) + 1) as u32
+}
+
+/// The bucket of a client request with the client's sequence number `sequence`, i.e. the
+/// instance ordering it out of `instances` instances.
+fn bucket_of(sequence: u32, instances: u32) -> usize {
+    (u64::from(sequence) % u64::from(instances)) as usize
+}
+
+/// The replica leading the instance that orders the client request with the client's
+/// sequence number `sequence` in `view`, e.g. for clients sending to the primary directly.
+pub fn leader_of_request(sequence: u32, view: u64, instances: u32, num_of_nodes: u32) -> u32 {
+    primary_of_view(view + bucket_of(sequence, instances) as u64, num_of_nodes)
+}
+
+/// How long a replica stays primary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum LeaderRotation {
+    /// The primary of a view orders all requests until a view change
+    Stable,
+    /// The primary rotates through the replicas with every sequence number, as with rotating
+    /// coordinators. Each replica leads an instance, see `ReplicaState::instances`.
+    PerRequest,
+}
+
+impl FromStr for LeaderRotation {
+    type Err = String;
+
+    fn from_str(rotation: &str) -> Result<Self, Self::Err> {
+        match rotation {
+            "stable" => Ok(LeaderRotation::Stable),
+            "per_request" => Ok(LeaderRotation::PerRequest),
+            _ => Err(format!(
+                "leader rotation '{}' is not available, allowed are 'stable', 'per_request'",
+                rotation
+            )),
+        }
+    }
 }
 
-/// The type defining allowed roles for replicas.
-#[derive(Debug, PartialEq, Eq)]
-pub enum ReplicaRole {
-    Primary,
-    Backup,
+/// The progress of a request at a replica, see `ReplicaState::status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryStatus {
+    /// The replica has no entry of the request.
+    Unknown,
+    /// Prepares or Commits of the request arrived, but not its _PrePrepare_.
+    Pending,
+    /// The _PrePrepare_ of the primary arrived.
+    PrePrepared,
+    /// A _Prepare_ quorum was collected.
+    Prepared,
+    /// A _Commit_ quorum was collected or the request arrived with a state transfer.
+    /// The entry was removed from the log.
+    CommittedLocal,
 }
 
 /// The type defining an entry of the replica's log. An entry stores the request
 /// and all related information required by the protocol.
 ///
 /// Thus, holds the `view` and `seq_number` assigned by the primary for the client request
-/// as well as all associated messages like the original _client request_ and
-/// quorum messages for the _Prepare_ and the _Commit_ quorum.
+/// as well as the original _client request_ and the senders of the quorum messages for the
+/// _Prepare_ and the _Commit_ quorum.
 #[derive(Debug)]
 pub struct LogEntry {
     /// View number assigned by the primary to this request
@@ -51,10 +105,12 @@ pub struct LogEntry {
     // -------------------- Associated Messages --------------------
     /// The original client request
     client_request: ClientRequest,
-    /// All prepare quorum messages for this entry
-    prepare_quorum: HashSet<PrepareQuorumMessage>,
-    /// All commit quorum messages for this entry
-    commit_quorum: HashSet<CommitMessage>,
+    /// `true` once the _PrePrepare_ message of the primary was received
+    pre_prepared: bool,
+    /// Senders of the prepare quorum messages (the _PrePrepare_ included) for this entry
+    prepare_quorum: ReplicaSet,
+    /// Senders of the commit quorum messages for this entry
+    commit_quorum: ReplicaSet,
     // --------------------     Predicates      --------------------
     /// `true` as soon as replica has collected a _Prepare_ quorum for this entry.
     prepared: bool,
@@ -68,8 +124,9 @@ impl LogEntry {
             view,
             seq_number,
             client_request,
-            prepare_quorum: HashSet::new(),
-            commit_quorum: HashSet::new(),
+            pre_prepared: false,
+            prepare_quorum: ReplicaSet::new(),
+            commit_quorum: ReplicaSet::new(),
             committed_local: false,
             prepared: false,
         }
@@ -83,14 +140,70 @@ impl LogEntry {
         self.has_pre_prepare_message() && self.prepare_quorum.len() >= quorum_size
     }
 
+    /// Like `has_commit_quorum_of`, but weighs the senders with the voting weights of `quorum`
+    pub fn has_commit_quorum_in(&self, quorum: &QuorumConfig) -> bool {
+        quorum.is_quorum_set(&self.commit_quorum)
+    }
+
+    /// Like `has_prepare_quorum_of`, but weighs the senders with the voting weights of `quorum`
+    pub fn has_prepare_quorum_in(&self, quorum: &QuorumConfig) -> bool {
+        self.has_pre_prepare_message() && quorum.is_quorum_set(&self.prepare_quorum)
+    }
+
     fn has_pre_prepare_message(&self) -> bool {
-        if let Some(_) = self.prepare_quorum.iter().find(|msg| match msg {
-            PrepareQuorumMessage::PrePrepareMessage(_) => true,
-            _ => false,
-        }) {
-            true
+        self.pre_prepared
+    }
+
+    /// The view the primary assigned to the request.
+    pub fn view(&self) -> u64 {
+        self.view
+    }
+
+    /// The sequence number the primary assigned to the request.
+    pub fn seq_number(&self) -> u64 {
+        self.seq_number
+    }
+
+    /// The client request of the entry.
+    pub fn client_request(&self) -> &ClientRequest {
+        &self.client_request
+    }
+
+    /// `true` once the _PrePrepare_ of the primary arrived.
+    pub fn is_pre_prepared(&self) -> bool {
+        self.pre_prepared
+    }
+
+    /// `true` once a _Prepare_ quorum was collected.
+    pub fn is_prepared(&self) -> bool {
+        self.prepared
+    }
+
+    /// `true` once a _Commit_ quorum was collected.
+    pub fn is_committed_local(&self) -> bool {
+        self.committed_local
+    }
+
+    /// The senders of the _PrePrepare_ and the _Prepares_ collected so far.
+    pub fn prepare_quorum(&self) -> &ReplicaSet {
+        &self.prepare_quorum
+    }
+
+    /// The senders of the _Commits_ collected so far.
+    pub fn commit_quorum(&self) -> &ReplicaSet {
+        &self.commit_quorum
+    }
+
+    /// The progress of the request according to this entry.
+    pub fn status(&self) -> EntryStatus {
+        if self.committed_local {
+            EntryStatus::CommittedLocal
+        } else if self.prepared {
+            EntryStatus::Prepared
+        } else if self.pre_prepared {
+            EntryStatus::PrePrepared
         } else {
-            false
+            EntryStatus::Pending
         }
     }
 }
@@ -101,30 +214,60 @@ impl LogEntry {
 #[derive(Debug)]
 pub struct ReplicaState {
     id: u32,
-    log: HashMap<u32, LogEntry>,
+    log: HashMap<RequestId, LogEntry>,
     /// For garbage collection purposes we store here IDs of locally
     /// commited requests. This allows us to remove the associated log entry and
     /// ignore all subsequent incoming messages related to the request.
-    cl_reqs: HashSet<u32>,
+    cl_reqs: HashSet<RequestId>,
+    /// Replies to all committed requests. A retransmitted request is answered
+    /// from here instead of being executed again.
+    replies: HashMap<RequestId, ClientResponse>,
     /// The fixed number of nodes participating in the cluster.
     num_of_nodes: u32,
     /// The view number in which the replica currently operates.
     current_view: u64,
-    /// Used only by the `primary` to assign the next sequence number.
+    /// Used only by leaders: the last sequence number the replica assigned in its instance.
     next_seq_num: u64,
-    /// The index of the last committed entry in the `log`.
-    last_commited_index: usize,
-    /// Specifies the current role of the replica in the cluster.
-    role: ReplicaRole,
+    /// The number of parallel ordering instances. Instance `i` orders the sequence
+    /// numbers `i + 1`, `i + 1 + instances`, ... and is led by the primary of the view
+    /// `current_view + i`, as in Mir-BFT and ISS.
+    instances: u32,
+    /// Per instance, the requests it committed locally that wait for the preceding
+    /// sequence numbers of the other instances to be merged into the total order.
+    instance_logs: Vec<BTreeMap<u64, ClientRequest>>,
+    /// Requests committed locally, ordered by their sequence number. Used to
+    /// serve state transfers to lagging replicas.
+    committed: BTreeMap<u64, ClientRequest>,
+    /// The sequence number up to which all requests are committed locally,
+    /// i.e. the low watermark.
+    last_executed: u64,
+    /// Distance between the low and the high watermark.
+    watermark_window: u64,
+    /// The sequence number that triggered the outstanding state transfer, if any.
+    state_transfer: Option<u64>,
+    /// Senders of the state responses holding an entry, it is applied once f + 1 replicas sent it
+    state_responses: HashMap<(u64, ClientRequest), ReplicaSet>,
     /// Holds the IDs of other peers.
     peers: Vec<u32>,
-    /// The minimal size of a quorum (2 * f + 1) s.t. f < n/3, n = num_of_nodes
-    quorum_size: usize,
+    /// The quorum system, by default quorums of 2 * f + 1 replicas for n = 3 * f + 1
+    quorum: QuorumConfig,
+    /// Executes the committed requests in the order of their sequence numbers.
+    application: Box<dyn Application>,
+    /// The executed requests, in order.
+    executed: Vec<RequestId>,
+    /// The result log the phases of the requests are written to
+    results: ResultLogger,
+    /// How the bodies of the requests reach the backups.
+    dissemination: Dissemination,
+    /// Bodies of requests ordered by their digest that arrived before their PrePrepare.
+    bodies: HashSet<RequestId>,
+    /// PrePrepares carrying a digest that wait for the body of their request.
+    waiting: HashMap<RequestId, PrePrepareMessage>,
 }
 
 impl ReplicaState {
-    /// Creates a new `ReplicaState` with `current_view` set to 1. Thus,
-    /// the (fixed) primary is always the node with id `1`.
+    /// Creates a new `ReplicaState` with `current_view` set to 1 and a single
+    /// instance. Thus, the (fixed) primary is always the node with id `1`.
     ///
     /// Requires the parameter `num_of_nodes` to be at least `4`, otherwise it
     /// `panics!` since at least 4 nodes are required for successful operation.
@@ -133,117 +276,434 @@ impl ReplicaState {
             panic!("Need at least 4 PBFT nodes but got only {}", num_of_nodes);
         }
 
-        let nn = num_of_nodes as usize;
-        let f: usize = nn / 3 + nn % 3 - 1;
-        let initial_view = 1;
-
         ReplicaState {
             id,
             num_of_nodes,
-            role: match id == (initial_view % num_of_nodes) {
-                true => ReplicaRole::Primary,
-                false => ReplicaRole::Backup,
-            },
-            current_view: initial_view as u64,
+            current_view: 1,
             next_seq_num: 0,
+            instances: 1,
+            instance_logs: vec![BTreeMap::new()],
             log: HashMap::new(),
             cl_reqs: HashSet::new(),
-            last_commited_index: 0,
+            replies: HashMap::new(),
+            committed: BTreeMap::new(),
+            last_executed: 0,
+            watermark_window: DEFAULT_WATERMARK_WINDOW,
+            state_transfer: None,
+            state_responses: HashMap::new(),
             peers: (1..=num_of_nodes)
                 .into_iter()
                 .filter(|i| *i != id)
                 .collect(),
-            quorum_size: 2 * f + 1 as usize,
+            quorum: QuorumConfig::new(num_of_nodes),
+            application: Box::new(CounterApplication::default()),
+            executed: Vec::new(),
+            results: ResultLogger::default(),
+            dissemination: Dissemination::Inline,
+            bodies: HashSet::new(),
+            waiting: HashMap::new(),
+        }
+    }
+
+    /// Orders the requests by their digests, their bodies are disseminated as given.
+    pub fn dissemination(mut self, dissemination: Dissemination) -> Self {
+        self.dissemination = dissemination;
+        self
+    }
+
+    /// Replaces the standard quorum system, e.g. to override f or the quorum size or
+    /// to assign voting weights to the replicas.
+    pub fn quorum(mut self, quorum: QuorumConfig) -> Self {
+        self.quorum = quorum;
+        self
+    }
+
+    /// Replaces the default counter application executing the committed requests.
+    pub fn application(mut self, application: Box<dyn Application>) -> Self {
+        self.application = application;
+        self
+    }
+
+    /// Writes the phases of the requests to `results` instead of discarding them.
+    pub fn result_logger(mut self, results: ResultLogger) -> Self {
+        self.results = results;
+        self
+    }
+
+    /// Splits the sequence numbers among `instances` parallel ordering instances with
+    /// round-robin leaders. A client request is ordered by the instance of its bucket.
+    ///
+    /// `panics!` if there are more instances than replicas to lead them.
+    pub fn instances(mut self, instances: u32) -> Self {
+        if instances == 0 || instances > self.num_of_nodes {
+            panic!(
+                "Need 1 to {} PBFT instances but got {}",
+                self.num_of_nodes, instances
+            );
+        }
+        self.instances = instances;
+        self.instance_logs = vec![BTreeMap::new(); instances as usize];
+        self
+    }
+
+    /// The sequence number up to which all requests are executed.
+    pub fn last_executed(&self) -> u64 {
+        self.last_executed
+    }
+
+    /// The id of the replica.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// The view the replica currently operates in.
+    pub fn current_view(&self) -> u64 {
+        self.current_view
+    }
+
+    /// The primary of the current view, the leader of the first instance.
+    pub fn primary(&self) -> u32 {
+        self.leader_of(0)
+    }
+
+    /// The quorum system of the replica.
+    pub fn quorum_config(&self) -> &QuorumConfig {
+        &self.quorum
+    }
+
+    /// The entry of the request `request_id` while it is in progress. Committed
+    /// requests are removed from the log, see `status`.
+    pub fn entry(&self, request_id: &RequestId) -> Option<&LogEntry> {
+        self.log.get(request_id)
+    }
+
+    /// The entries of all requests in progress.
+    pub fn entries(&self) -> impl Iterator<Item = (&RequestId, &LogEntry)> {
+        self.log.iter()
+    }
+
+    /// The progress of the request `request_id` at the replica.
+    pub fn status(&self, request_id: &RequestId) -> EntryStatus {
+        if self.cl_reqs.contains(request_id) {
+            return EntryStatus::CommittedLocal;
         }
+        self.log
+            .get(request_id)
+            .map_or(EntryStatus::Unknown, LogEntry::status)
+    }
+
+    /// The executed requests, in the order of their execution.
+    pub fn executed(&self) -> &[RequestId] {
+        &self.executed
+    }
+
+    /// Digest of the application state after executing all requests up to `last_executed`.
+    pub fn digest(&self) -> u64 {
+        self.application.digest()
+    }
+
+    /// Sets the distance between the low and the high watermark. A replica that
+    /// sees a sequence number beyond its high watermark starts a state transfer.
+    pub fn watermark_window(mut self, watermark_window: u64) -> Self {
+        self.watermark_window = watermark_window;
+        self
     }
 
     /// Single exposed function that acts as a entry point for handling incoming
-    /// messages by peers or clients.
-    pub fn handle_message(&mut self, message: PBFTMessage, time: Time) -> Option<Output> {
+    /// messages by peers or clients. Messages the replica cannot handle, e.g. replies
+    /// meant for the clients, are returned as `ProtocolError`.
+    pub fn handle_message(
+        &mut self,
+        message: PBFTMessage,
+        time: Time,
+    ) -> Result<Option<Output>, ProtocolError> {
         // we only process a message if we not already committed locally the
         // associated request
-        if self.can_ignore_message(message) {
-            return None;
+        if self.can_ignore_message(&message) {
+            return Ok(None);
         }
 
-        match message {
+        // messages delivered after the view they were sent in ended are discarded
+        if self.is_stale(&message) {
+            debug!(target: "node", "PBFTNode {} discards a message of a past view: {:?}", self.id, &message);
+            return Ok(None);
+        }
+
+        let fetch_state = self.check_high_watermark(&message);
+
+        let output = match message {
             PBFTMessage::ClientRequest(m) => self.handle_client_request(m, time),
+            PBFTMessage::Request(m) => self.handle_request_body(m, time),
             PBFTMessage::PrePrepare(m) => self.handle_pre_prepare_message(m, time),
             PBFTMessage::Prepare(m) => self.handle_prepare_message(m, time),
             PBFTMessage::Commit(m) => self.handle_commit_message(m, time),
-            PBFTMessage::ClientResponse(_) => panic!("Replica should not receive a ClientResponse"),
+            PBFTMessage::FetchState(m) => self.handle_fetch_state(m),
+            PBFTMessage::StateResponse(m) => self.handle_state_response(m, time),
+            PBFTMessage::ClientResponse(_) => {
+                return Err(ProtocolError::UnexpectedMessage {
+                    node: self.id,
+                    message_type: message.message_type(),
+                })
+            }
+        };
+
+        match fetch_state {
+            Some(mut fetch_state) => {
+                let mut output = output.unwrap_or_default();
+                output.append(&mut fetch_state);
+                Ok(Some(output))
+            }
+            None => Ok(output),
         }
     }
 
-    /// Gets the `id` of the primary for the current view.
-    fn curr_primary(&self) -> u32 {
-        (self.current_view % (self.num_of_nodes as u64)) as u32
+    /// Gets the `id` of the leader of `instance` in the current view.
+    fn leader_of(&self, instance: usize) -> u32 {
+        primary_of_view(self.current_view + instance as u64, self.num_of_nodes)
+    }
+
+    /// Gets the instance `self` leads in the current view, if any.
+    fn led_instance(&self) -> Option<usize> {
+        (0..self.instances as usize).find(|instance| self.leader_of(*instance) == self.id)
     }
 
-    /// Checks if `self` is the primary for the current view.
-    fn is_primary(&self) -> bool {
-        self.role == ReplicaRole::Primary
+    /// Gets the instance ordering the sequence number `seq_number`.
+    fn instance_of_seq(&self, seq_number: u64) -> usize {
+        (seq_number.saturating_sub(1) % u64::from(self.instances)) as usize
     }
 
-    /// Increments the sequence number counter and returns the value.
-    fn next_seq_num(&mut self) -> u64 {
-        self.next_seq_num += 1;
+    /// Gets the instance ordering the client request `id`, i.e. its bucket.
+    fn instance_of_request(&self, id: RequestId) -> usize {
+        bucket_of(id.sequence, self.instances)
+    }
+
+    /// Gets the sequence number the leader of `instance` assigns next.
+    fn peek_seq_num(&self, instance: usize) -> u64 {
+        match self.next_seq_num {
+            0 => instance as u64 + 1,
+            last => last + u64::from(self.instances),
+        }
+    }
+
+    /// Advances the sequence number counter of the led `instance` and returns the value.
+    fn next_seq_num(&mut self, instance: usize) -> u64 {
+        self.next_seq_num = self.peek_seq_num(instance);
         self.next_seq_num
     }
 
+    /// Writes a phase of `c_req` to the result log. Null requests are not logged.
+    fn log_phase(&self, time: Time, c_req: &ClientRequest, event: fn(u64) -> ResultEvent) {
+        if !c_req.is_null() {
+            self.results
+                .log(time, Some(self.id), &event(c_req.id.into()));
+        }
+    }
+
+    /// Gets the sequence number beyond which the replica considers itself lagging.
+    fn high_watermark(&self) -> u64 {
+        self.last_executed + self.watermark_window
+    }
+
     /// Checks if we can ignore the `message`. Returns `true` iff for the associated
     /// request we already committed locally and the incoming message is of type
-    /// `PBFTMessage::Prepare` or `PBFTMessage::Commit`
-    fn can_ignore_message(&self, message: PBFTMessage) -> bool {
+    /// `PBFTMessage::PrePrepare`, `PBFTMessage::Prepare` or `PBFTMessage::Commit`
+    fn can_ignore_message(&self, message: &PBFTMessage) -> bool {
         match message {
-            PBFTMessage::Prepare(m) => self.cl_reqs.contains(&m.c_req.operation),
-            PBFTMessage::Commit(m) => self.cl_reqs.contains(&m.c_req.operation),
+            PBFTMessage::Request(m) => self.cl_reqs.contains(&m.id),
+            PBFTMessage::PrePrepare(m) => self.cl_reqs.contains(&m.c_req.id),
+            PBFTMessage::Prepare(m) => self.cl_reqs.contains(&m.c_req.id),
+            PBFTMessage::Commit(m) => self.cl_reqs.contains(&m.c_req.id),
             _ => false,
         }
     }
 
+    /// Checks if `message` belongs to a view before the current one. Such messages
+    /// arrived too late to take part in the agreement of their view.
+    fn is_stale(&self, message: &PBFTMessage) -> bool {
+        let view = match message {
+            PBFTMessage::PrePrepare(m) => m.view,
+            PBFTMessage::Prepare(m) => m.view,
+            PBFTMessage::Commit(m) => m.view,
+            _ => return false,
+        };
+        view < self.current_view
+    }
+
+    /// Checks if `message` carries a sequence number beyond the high watermark.
+    /// If so, we fell behind and ask all peers for the missing state.
+    fn check_high_watermark(&mut self, message: &PBFTMessage) -> Option<Output> {
+        let (seq_number, sender_id) = match message {
+            PBFTMessage::PrePrepare(m) => (m.seq_number, m.sender_id),
+            PBFTMessage::Prepare(m) => (m.seq_number, m.sender_id),
+            PBFTMessage::Commit(m) => (m.seq_number, m.sender_id),
+            _ => return None,
+        };
+
+        if seq_number <= self.high_watermark() {
+            return None;
+        }
+
+        // only a single state transfer at a time, unless we fell behind even further
+        if let Some(requested_at) = self.state_transfer {
+            if seq_number <= requested_at + self.watermark_window {
+                return None;
+            }
+        }
+
+        debug!(target: "node", "PBFTNode {} saw sequence number {} of {} beyond its high watermark {}, fetching state", self.id, seq_number, sender_id, self.high_watermark());
+
+        self.state_transfer = Some(seq_number);
+
+        Some(create_peer_broadcast_output(
+            PBFTMessage::FetchState(FetchStateMessage::new(self.last_executed, self.id)),
+            &self.peers,
+        ))
+    }
+
+    /// Stores a locally committed request in the log of its instance and merges the
+    /// logs of the instances. Returns the replies to the clients of the executed requests.
+    fn mark_committed(&mut self, seq_number: u64, c_req: ClientRequest, time: Time) -> Output {
+        // update the committed local set so we ignore subsequent incoming messages
+        // related to this request
+        self.cl_reqs.insert(c_req.id);
+        self.committed.insert(seq_number, c_req);
+        self.bodies.remove(&c_req.id);
+        self.waiting.remove(&c_req.id);
+
+        let instance = self.instance_of_seq(seq_number);
+        self.instance_logs[instance].insert(seq_number, c_req);
+        self.merge(time)
+    }
+
+    /// Merges the logs of the instances into the total order: the next sequence number
+    /// is taken from the log of the instance ordering it. Executes all requests that are
+    /// no longer preceded by a gap, advancing `last_executed` over their sequence numbers.
+    /// Each executed request is answered with a reply to its client.
+    fn merge(&mut self, time: Time) -> Output {
+        let mut output = Output::new();
+        loop {
+            let seq_number = self.last_executed + 1;
+            let instance = self.instance_of_seq(seq_number);
+            let c_req = match self.instance_logs[instance].remove(&seq_number) {
+                Some(c_req) => c_req,
+                None => break,
+            };
+            self.last_executed = seq_number;
+
+            if c_req.is_null() {
+                continue;
+            }
+            let result = self.application.execute(c_req.operation);
+            self.log_phase(time, &c_req, ResultEvent::Executed);
+            let reply = ClientResponse {
+                id: c_req.id,
+                result,
+                sender_id: self.id,
+            };
+            self.replies.insert(c_req.id, reply);
+            output.push((c_req.id.client_id, PBFTMessage::ClientResponse(reply)));
+            self.executed.push(c_req.id);
+        }
+        output
+    }
+
+    /// Assigns `seq_number` to `c_req` as the leader of its instance and sends the
+    /// _PrePrepare_ to the backups.
+    fn pre_prepare(&mut self, c_req: ClientRequest, seq_number: u64) -> Output {
+        let mut entry = LogEntry::new(self.current_view, seq_number, c_req);
+        let preprepare = PrePrepareMessage::new(c_req, self.current_view, seq_number, self.id);
+
+        entry.pre_prepared = true;
+        entry.prepare_quorum.insert(preprepare.sender_id);
+
+        self.log.insert(c_req.id, entry);
+
+        // the primary disseminates the body ahead of the PrePrepare carrying its digest
+        let mut output = match self.dissemination {
+            Dissemination::Primary if !c_req.is_null() => {
+                create_peer_broadcast_output(PBFTMessage::Request(c_req), &self.peers)
+            }
+            _ => Output::new(),
+        };
+        output.append(&mut create_peer_broadcast_output(
+            PBFTMessage::PrePrepare(preprepare),
+            &self.peers,
+        ));
+        output
+    }
+
+    /// Records the body of the request `id` if the requests are ordered by their digests,
+    /// returns the PrePrepare that waited for it, if any.
+    fn receive_body(&mut self, id: RequestId) -> Option<PrePrepareMessage> {
+        if self.dissemination == Dissemination::Inline || self.cl_reqs.contains(&id) {
+            return None;
+        }
+        self.bodies.insert(id);
+        self.waiting.remove(&id)
+    }
+
+    /// Handles the body of a request disseminated by the primary or the client.
+    fn handle_request_body(&mut self, msg_in: ClientRequest, time: Time) -> Option<Output> {
+        let pre_prepare = self.receive_body(msg_in.id)?;
+        self.handle_pre_prepare_message(pre_prepare, time)
+    }
+
+    /// Orders null requests at the sequence numbers of the instance led by `self` that
+    /// precede `seq_number`, which another instance ordered already. Otherwise an instance
+    /// short of client requests would hold up the total order.
+    fn fill_led_instance(&mut self, seq_number: u64) -> Output {
+        let mut output = Output::new();
+        if self.instances == 1 {
+            return output;
+        }
+        if let Some(instance) = self.led_instance() {
+            while self.peek_seq_num(instance) < seq_number {
+                let null_seq_number = self.next_seq_num(instance);
+                debug!(target: "node", "PBFTNode {} fills sequence number {} of instance {} with a null request", self.id, null_seq_number, instance);
+                output.append(
+                    &mut self.pre_prepare(ClientRequest::null(null_seq_number), null_seq_number),
+                );
+            }
+        }
+        output
+    }
+
     /// Updates the predicates for a log entry associated with the `req_id`.
-    fn update_prediactes(&mut self, req_id: u32, mut output: Output, time: Time) -> Option<Output> {
+    fn update_prediactes(
+        &mut self,
+        req_id: RequestId,
+        mut output: Output,
+        time: Time,
+    ) -> Option<Output> {
         let entry = self.log.get_mut(&req_id).unwrap();
 
         // `prepared` predicate check
-        if !entry.prepared && entry.has_prepare_quorum_of(self.quorum_size) {
-            log_result(
-                time,
-                Some(self.id),
-                &format!("{};prepared", entry.client_request.operation),
-            );
-
+        if !entry.prepared && entry.has_prepare_quorum_in(&self.quorum) {
             entry.prepared = true;
 
             let commit =
                 CommitMessage::new(entry.client_request, entry.view, entry.seq_number, self.id);
 
-            entry.commit_quorum.insert(commit);
+            entry.commit_quorum.insert(commit.sender_id);
 
             // send batch of commit messages since we prepared
             output.append(&mut create_peer_broadcast_output(
                 PBFTMessage::Commit(commit),
                 &self.peers,
             ));
+            self.log_phase(time, &commit.c_req, ResultEvent::Prepared);
         }
 
         // `committed_local` prediacte check
-        if entry.prepared && !entry.committed_local && entry.has_commit_quorum_of(self.quorum_size)
-        {
-            log_result(
-                time,
-                Some(self.id),
-                &format!("{};committed_local", entry.client_request.operation),
-            );
-
+        let entry = self.log.get_mut(&req_id).unwrap();
+        if entry.prepared && !entry.committed_local && entry.has_commit_quorum_in(&self.quorum) {
             entry.committed_local = true;
+            let (seq_number, c_req) = (entry.seq_number, entry.client_request);
+            self.log_phase(time, &c_req, ResultEvent::CommittedLocal);
 
             // we don't need the entry anymore. Therefore, remove it from the log
             self.log.remove(&req_id);
-            // update the committed local set so we ignore subsequent incoming messages
-            // related to this request
-            self.cl_reqs.insert(req_id);
+            output.append(&mut self.mark_committed(seq_number, c_req, time));
         }
 
         match output.len() {
@@ -254,32 +714,39 @@ impl ReplicaState {
 
     /// Handles incoming client requests.
     fn handle_client_request(&mut self, msg_in: ClientRequest, time: Time) -> Option<Output> {
-        if self.is_primary() {
-            log_result(
-                time,
-                Some(self.id),
-                &format!("{};request", msg_in.operation),
-            );
+        // a retransmission of a committed request is answered with the cached reply
+        if let Some(reply) = self.replies.get(&msg_in.id) {
+            debug!(target: "node", "PBFTNode {} resends the reply to client request {}", self.id, msg_in.id);
+            return Some(vec![(
+                msg_in.id.client_id,
+                PBFTMessage::ClientResponse(*reply),
+            )]);
+        }
 
-            let seq_number = self.next_seq_num();
-            let mut entry = LogEntry::new(self.current_view, seq_number, msg_in);
-            let preprepare = PrePrepareMessage::new(msg_in, self.current_view, seq_number, self.id);
+        // the body of a request the primary ordered by its digest already
+        if let Some(pre_prepare) = self.receive_body(msg_in.id) {
+            return self.handle_pre_prepare_message(pre_prepare, time);
+        }
 
-            entry
-                .prepare_quorum
-                .insert(PrepareQuorumMessage::PrePrepareMessage(preprepare));
+        // a retransmission of a request in progress is dropped, it is ordered already
+        if self.log.contains_key(&msg_in.id) || self.cl_reqs.contains(&msg_in.id) {
+            return None;
+        }
 
-            self.log.insert(msg_in.operation, entry);
+        let instance = self.instance_of_request(msg_in.id);
+        let leader = self.leader_of(instance);
 
-            return Some(create_peer_broadcast_output(
-                PBFTMessage::PrePrepare(preprepare),
-                &self.peers,
-            ));
+        if leader == self.id {
+            self.log_phase(time, &msg_in, ResultEvent::Request);
+
+            let seq_number = self.next_seq_num(instance);
+            return Some(self.pre_prepare(msg_in, seq_number));
         }
 
-        warn!(target: "node", "Non-primary PBFTNode {} received a client request", self.id);
+        // clients do not know the current leaders, so misdirected requests are forwarded to them
+        debug!(target: "node", "PBFTNode {} forwards client request {} to the leader {} of instance {}", self.id, msg_in.id, leader, instance);
 
-        None
+        Some(vec![(leader, PBFTMessage::ClientRequest(msg_in))])
     }
 
     fn handle_pre_prepare_message(
@@ -287,8 +754,17 @@ impl ReplicaState {
         msg_in: PrePrepareMessage,
         time: Time,
     ) -> Option<Output> {
-        if self.curr_primary() == msg_in.sender_id {
-            let req_id = msg_in.c_req.operation;
+        // a PrePrepare carrying a digest waits for the body of its request
+        if self.dissemination != Dissemination::Inline
+            && !msg_in.c_req.is_null()
+            && !self.bodies.contains(&msg_in.c_req.id)
+        {
+            self.waiting.insert(msg_in.c_req.id, msg_in);
+            return None;
+        }
+
+        if self.leader_of(self.instance_of_seq(msg_in.seq_number)) == msg_in.sender_id {
+            let req_id = msg_in.c_req.id;
             let entry = match self.log.get_mut(&req_id) {
                 Some(entry) => entry,
                 None => {
@@ -300,70 +776,121 @@ impl ReplicaState {
                 }
             };
 
-            log_result(time, Some(self.id), &format!("{};pre-prepared", req_id));
-
+            let c_req = entry.client_request;
             let prepare =
                 PrepareMessage::new(entry.client_request, entry.view, entry.seq_number, self.id);
 
-            entry
-                .prepare_quorum
-                .insert(PrepareQuorumMessage::PrePrepareMessage(msg_in));
-            entry
-                .prepare_quorum
-                .insert(PrepareQuorumMessage::PrepareMessage(prepare));
+            entry.pre_prepared = true;
+            entry.prepare_quorum.insert(msg_in.sender_id);
+            entry.prepare_quorum.insert(prepare.sender_id);
 
-            let output = create_peer_broadcast_output(PBFTMessage::Prepare(prepare), &self.peers);
+            let mut output =
+                create_peer_broadcast_output(PBFTMessage::Prepare(prepare), &self.peers);
+            self.log_phase(time, &c_req, ResultEvent::PrePrepared);
 
+            output.append(&mut self.fill_led_instance(msg_in.seq_number));
             return self.update_prediactes(req_id, output, time);
         }
 
-        warn!(target:"node", "PBFTNode {} received a PrePrepare message from non-primary peer {}", self.id, msg_in.sender_id);
+        warn!(target:"node", "PBFTNode {} received a PrePrepare message from peer {}, which does not lead the instance of sequence number {}", self.id, msg_in.sender_id, msg_in.seq_number);
 
         None
     }
 
     fn handle_prepare_message(&mut self, msg_in: PrepareMessage, time: Time) -> Option<Output> {
-        let req_id = msg_in.c_req.operation;
+        let req_id = msg_in.c_req.id;
 
         match self.log.get_mut(&req_id) {
             Some(entry) => {
-                entry
-                    .prepare_quorum
-                    .insert(PrepareQuorumMessage::PrepareMessage(msg_in));
+                entry.prepare_quorum.insert(msg_in.sender_id);
 
                 return self.update_prediactes(req_id, Output::new(), time);
             }
             None => {
                 let mut entry = LogEntry::new(msg_in.view, msg_in.seq_number, msg_in.c_req);
 
-                entry
-                    .prepare_quorum
-                    .insert(PrepareQuorumMessage::PrepareMessage(msg_in));
+                entry.prepare_quorum.insert(msg_in.sender_id);
 
-                self.log.insert(msg_in.c_req.operation, entry);
+                self.log.insert(msg_in.c_req.id, entry);
             }
         };
         None
     }
 
     fn handle_commit_message(&mut self, msg_in: CommitMessage, time: Time) -> Option<Output> {
-        let req_id = msg_in.c_req.operation;
+        let req_id = msg_in.c_req.id;
 
         match self.log.get_mut(&req_id) {
             Some(entry) => {
-                entry.commit_quorum.insert(msg_in);
+                entry.commit_quorum.insert(msg_in.sender_id);
 
                 return self.update_prediactes(req_id, Output::new(), time);
             }
             None => {
                 let mut entry = LogEntry::new(msg_in.view, msg_in.seq_number, msg_in.c_req);
 
-                entry.commit_quorum.insert(msg_in);
-                self.log.insert(msg_in.c_req.operation, entry);
+                entry.commit_quorum.insert(msg_in.sender_id);
+                self.log.insert(msg_in.c_req.id, entry);
             }
         }
         None
     }
+
+    /// Answers a lagging replica with all requests committed after its last
+    /// executed one.
+    fn handle_fetch_state(&mut self, msg_in: FetchStateMessage) -> Option<Output> {
+        let entries: Vec<(u64, ClientRequest)> = self
+            .committed
+            .range(msg_in.last_executed + 1..)
+            .map(|(seq_number, c_req)| (*seq_number, *c_req))
+            .collect();
+
+        if entries.is_empty() {
+            return None;
+        }
+
+        Some(vec![(
+            msg_in.sender_id,
+            PBFTMessage::StateResponse(StateResponseMessage::new(entries, self.id)),
+        )])
+    }
+
+    /// Applies the state received from the peers: every contained request that
+    /// f + 1 peers sent with the same sequence number counts as committed locally,
+    /// at least one of them is correct.
+    fn handle_state_response(
+        &mut self,
+        msg_in: StateResponseMessage,
+        time: Time,
+    ) -> Option<Output> {
+        let mut output = Output::new();
+        for (seq_number, c_req) in msg_in.entries {
+            if self.cl_reqs.contains(&c_req.id) {
+                continue;
+            }
+
+            let senders = self.state_responses.entry((seq_number, c_req)).or_default();
+            senders.insert(msg_in.sender_id);
+            if senders.len() as u32 <= self.quorum.f() {
+                continue;
+            }
+
+            self.state_transfer = None;
+            self.log_phase(time, &c_req, ResultEvent::StateTransfer);
+
+            self.log.remove(&c_req.id);
+            output.append(&mut self.mark_committed(seq_number, c_req, time));
+        }
+
+        let cl_reqs = &self.cl_reqs;
+        self.state_responses
+            .retain(|(_, c_req), _| !cl_reqs.contains(&c_req.id));
+
+        match output.len() {
+            0 => None,
+            _ => Some(output),
+        }
+    }
 }
 
 /*******************************************************************************
@@ -384,10 +911,7 @@ mod tests {
 
         let mut state = ReplicaState::new(1337, num_of_nodes);
 
-        let c_req = ClientRequest {
-            operation: 0,
-            sender_id: 0,
-        };
+        let c_req = ClientRequest::new(RequestId::new(0, 0), 0);
         let mut prepare_msg = PrepareMessage {
             c_req,
             view: 1,
@@ -400,7 +924,7 @@ mod tests {
             state.handle_prepare_message(prepare_msg, Time::new(32));
         }
 
-        if let Some(entry) = state.log.get(&c_req.operation) {
+        if let Some(entry) = state.log.get(&c_req.id) {
             assert!(entry.prepare_quorum.len() >= quorum_size as usize);
             assert_eq!(entry.has_prepare_quorum_of(quorum_size), false);
             assert_eq!(entry.prepared, false);
@@ -413,10 +937,7 @@ mod tests {
     fn state_transition_from_prepared_to_committed() {
         let num_of_nodes = 4;
         let mut state = ReplicaState::new(1337, num_of_nodes);
-        let c_req = ClientRequest {
-            operation: 0,
-            sender_id: 0,
-        };
+        let c_req = ClientRequest::new(RequestId::new(0, 0), 0);
         let mut commit_msg = CommitMessage {
             c_req,
             view: 1,
@@ -431,10 +952,10 @@ mod tests {
 
         // we cannot commit locally without being prepared, although we might have
         // a commit quorum present
-        if let Some(entry) = state.log.get(&c_req.operation) {
+        if let Some(entry) = state.log.get(&c_req.id) {
             assert_eq!(entry.committed_local, false);
             assert_eq!(entry.prepared, false);
-            assert_eq!(entry.has_commit_quorum_of(state.quorum_size), true);
+            assert_eq!(entry.has_commit_quorum_of(state.quorum.quorum_size()), true);
         } else {
             panic!("Entry should exist!");
         }
@@ -458,16 +979,333 @@ mod tests {
 
         for i in 1..num_of_nodes {
             prepare_msg.sender_id = i;
-            state.handle_prepare_message(prepare_msg, Time::new(32));
+            // like `handle_message`, skip the prepares of a request committed locally
+            if !state.can_ignore_message(&PBFTMessage::Prepare(prepare_msg)) {
+                state.handle_prepare_message(prepare_msg, Time::new(32));
+            }
         }
 
         // after becoming prepared and having a commit quorum collected we
-        // can finally commit locally
-        if let Some(entry) = state.log.get(&c_req.operation) {
+        // can finally commit locally, which moves the request from the log
+        // to the committed requests
+        assert!(!state.log.contains_key(&c_req.id));
+        assert!(state.cl_reqs.contains(&c_req.id));
+    }
+
+    #[test]
+    fn weighted_commit_quorum() {
+        // n = 5, f = 1: replicas 1 and 2 have weight 2, a quorum needs the weight 5
+        let mut state = ReplicaState::new(3, 5).quorum(QuorumConfig::wheat(5, 1, &[1, 2]));
+        let c_req = ClientRequest::new(RequestId::new(0, 0), 0);
+
+        state.handle_pre_prepare_message(PrePrepareMessage::new(c_req, 1, 1, 1), Time::new(32));
+        state.handle_prepare_message(PrepareMessage::new(c_req, 1, 1, 2), Time::new(32));
+
+        // the two heavy replicas and the replica itself prepared with only three replicas
+        if let Some(entry) = state.log.get(&c_req.id) {
             assert_eq!(entry.prepared, true);
-            assert_eq!(entry.committed_local, true);
+            assert_eq!(
+                entry.has_prepare_quorum_of(state.quorum.quorum_size()),
+                false
+            );
         } else {
             panic!("Entry should exist!");
         }
+
+        state.handle_commit_message(CommitMessage::new(c_req, 1, 1, 4), Time::new(33));
+        state.handle_commit_message(CommitMessage::new(c_req, 1, 1, 5), Time::new(33));
+        assert!(state.log.contains_key(&c_req.id));
+
+        state.handle_commit_message(CommitMessage::new(c_req, 1, 1, 1), Time::new(34));
+        assert!(!state.log.contains_key(&c_req.id));
+        assert_eq!(state.last_executed, 1);
+    }
+
+    #[test]
+    fn progress_of_requests_is_inspected() {
+        let mut state = ReplicaState::new(3, 4);
+        let c_req = ClientRequest::new(RequestId::new(0, 7), 7);
+        let other = ClientRequest::new(RequestId::new(0, 8), 8);
+        assert_eq!((state.current_view(), state.primary()), (1, 1));
+        assert_eq!(state.quorum_config().quorum_size(), 3);
+        assert_eq!(state.status(&c_req.id), EntryStatus::Unknown);
+
+        // the Prepare of replica 2 overtook the PrePrepare
+        let prepare = PBFTMessage::Prepare(PrepareMessage::new(c_req, 1, 1, 2));
+        state.handle_message(prepare, Time::new(32)).unwrap();
+        assert_eq!(state.status(&c_req.id), EntryStatus::Pending);
+        let pre_prepare = PBFTMessage::PrePrepare(PrePrepareMessage::new(c_req, 1, 1, 1));
+        state.handle_message(pre_prepare, Time::new(33)).unwrap();
+        let pre_prepare = PBFTMessage::PrePrepare(PrePrepareMessage::new(other, 1, 2, 1));
+        state.handle_message(pre_prepare, Time::new(34)).unwrap();
+        assert_eq!(state.status(&other.id), EntryStatus::PrePrepared);
+
+        let entry = state.entry(&c_req.id).unwrap();
+        assert_eq!(entry.status(), EntryStatus::Prepared);
+        assert_eq!((entry.view(), entry.seq_number()), (1, 1));
+        assert_eq!(entry.client_request(), &c_req);
+        assert!(entry.is_pre_prepared() && entry.is_prepared() && !entry.is_committed_local());
+        let prepared_by: Vec<u32> = entry.prepare_quorum().iter().collect();
+        assert_eq!(prepared_by, vec![1, 2, 3]);
+        assert_eq!(entry.commit_quorum().len(), 1);
+        assert_eq!(state.entries().count(), 2);
+
+        let commit = PBFTMessage::Commit(CommitMessage::new(c_req, 1, 1, 4));
+        state.handle_message(commit, Time::new(35)).unwrap();
+        assert_eq!(state.entry(&c_req.id).unwrap().commit_quorum().len(), 2);
+        let commit = PBFTMessage::Commit(CommitMessage::new(c_req, 1, 1, 2));
+        state.handle_message(commit, Time::new(36)).unwrap();
+        assert_eq!(state.status(&c_req.id), EntryStatus::CommittedLocal);
+        assert!(state.entry(&c_req.id).is_none());
+    }
+
+    #[test]
+    fn messages_of_past_views_are_discarded() {
+        let mut state = ReplicaState::new(3, 4);
+        state.current_view = 2;
+        let c_req = ClientRequest::new(RequestId::new(0, 7), 7);
+
+        let late = PBFTMessage::Prepare(PrepareMessage::new(c_req, 1, 1, 1));
+        assert_eq!(state.handle_message(late, Time::new(32)), Ok(None));
+        assert!(!state.log.contains_key(&c_req.id));
+
+        let current = PBFTMessage::Prepare(PrepareMessage::new(c_req, 2, 1, 1));
+        state.handle_message(current, Time::new(33)).unwrap();
+        assert!(state.log.contains_key(&c_req.id));
+    }
+
+    #[test]
+    fn backup_forwards_client_request_to_primary() {
+        let mut backup = ReplicaState::new(3, 4);
+        let c_req = ClientRequest::new(RequestId::new(0, 7), 7);
+
+        let output = backup.handle_client_request(c_req, Time::new(32));
+        assert_eq!(output, Some(vec![(1, PBFTMessage::ClientRequest(c_req))]));
+
+        // once the request is ordered, it is not forwarded again
+        backup.handle_pre_prepare_message(PrePrepareMessage::new(c_req, 1, 1, 1), Time::new(33));
+        assert_eq!(backup.handle_client_request(c_req, Time::new(34)), None);
+    }
+
+    #[test]
+    fn pre_prepare_with_digest_waits_for_the_body() {
+        let mut primary = ReplicaState::new(1, 4).dissemination(Dissemination::Primary);
+        let mut backup = ReplicaState::new(3, 4).dissemination(Dissemination::Primary);
+        let c_req = ClientRequest::new(RequestId::new(0, 7), 7);
+
+        // the primary disseminates the body before the PrePrepare
+        let output = primary.handle_client_request(c_req, Time::new(32)).unwrap();
+        let pre_prepare = PrePrepareMessage::new(c_req, 1, 1, 1);
+        assert_eq!(
+            output.iter().map(|(_, m)| m.clone()).collect::<Vec<_>>(),
+            vec![
+                PBFTMessage::Request(c_req),
+                PBFTMessage::Request(c_req),
+                PBFTMessage::Request(c_req),
+                PBFTMessage::PrePrepare(pre_prepare),
+                PBFTMessage::PrePrepare(pre_prepare),
+                PBFTMessage::PrePrepare(pre_prepare),
+            ]
+        );
+
+        // the PrePrepare overtook the body, the backup prepares once the body arrived
+        assert_eq!(
+            backup.handle_message(PBFTMessage::PrePrepare(pre_prepare), Time::new(33)),
+            Ok(None)
+        );
+        assert!(!backup.log.contains_key(&c_req.id));
+        let output = backup
+            .handle_message(PBFTMessage::Request(c_req), Time::new(34))
+            .unwrap()
+            .unwrap();
+        assert!(output
+            .iter()
+            .all(|(_, m)| matches!(m, PBFTMessage::Prepare(_))));
+        assert!(backup.log.contains_key(&c_req.id));
+
+        backup.mark_committed(1, c_req, Time::new(0));
+        assert!(backup.bodies.is_empty());
+        assert!(backup.waiting.is_empty());
+    }
+
+    #[test]
+    fn retransmitted_request_is_answered_from_reply_cache() {
+        let mut primary = ReplicaState::new(1, 4);
+        let c_req = ClientRequest::new(RequestId::new(31415, 7), 7);
+
+        assert!(primary
+            .handle_client_request(c_req, Time::new(32))
+            .is_some());
+        // the retransmission of a request in progress is not ordered twice
+        assert_eq!(primary.handle_client_request(c_req, Time::new(33)), None);
+        assert_eq!(primary.next_seq_num, 1);
+
+        let reply = ClientResponse {
+            id: c_req.id,
+            result: 7,
+            sender_id: 1,
+        };
+        // the client gets the reply once the request is executed
+        assert_eq!(
+            primary.mark_committed(1, c_req, Time::new(0)),
+            vec![(31415, PBFTMessage::ClientResponse(reply))]
+        );
+        for _ in 0..2 {
+            assert_eq!(
+                primary.handle_client_request(c_req, Time::new(34)),
+                Some(vec![(31415, PBFTMessage::ClientResponse(reply))])
+            );
+        }
+        assert_eq!(primary.next_seq_num, 1);
+    }
+
+    #[test]
+    fn instances_fill_gaps_and_merge_in_order() {
+        // replica 2 leads instance 1 of the sequence numbers 2, 4, ...
+        let mut leader = ReplicaState::new(2, 4).instances(2);
+
+        // requests of the other bucket are forwarded to the leader of instance 0
+        let c_req = ClientRequest::new(RequestId::new(0, 2), 2);
+        assert_eq!(
+            leader.handle_client_request(c_req, Time::new(32)),
+            Some(vec![(1, PBFTMessage::ClientRequest(c_req))])
+        );
+
+        let output = leader
+            .handle_pre_prepare_message(PrePrepareMessage::new(c_req, 1, 5, 1), Time::new(32))
+            .unwrap();
+        let filled: Vec<u64> = output
+            .iter()
+            .filter_map(|(_, m)| match m {
+                PBFTMessage::PrePrepare(m) if m.c_req.is_null() => Some(m.seq_number),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(filled, vec![2, 2, 2, 4, 4, 4]);
+
+        // the total order waits for the gap at sequence number 1 of instance 0
+        let first = ClientRequest::new(RequestId::new(0, 4), 4);
+        let third = ClientRequest::new(RequestId::new(0, 6), 6);
+        leader.mark_committed(2, ClientRequest::null(2), Time::new(0));
+        leader.mark_committed(3, third, Time::new(0));
+        assert_eq!(leader.last_executed, 0);
+
+        leader.mark_committed(1, first, Time::new(0));
+        assert_eq!(leader.last_executed, 3);
+        assert_eq!(leader.executed(), &[first.id, third.id]);
+    }
+
+    #[test]
+    fn primary_rotates_with_view() {
+        assert_eq!(primary_of_view(1, 4), 1);
+        assert_eq!(primary_of_view(4, 4), 4);
+        assert_eq!(primary_of_view(5, 4), 1);
+    }
+
+    #[test]
+    fn fetch_state_beyond_high_watermark() {
+        let mut lagging = ReplicaState::new(3, 4).watermark_window(2);
+        let mut peer = ReplicaState::new(2, 4).watermark_window(2);
+        let mut other_peer = ReplicaState::new(4, 4).watermark_window(2);
+
+        for seq_number in 1..=3 {
+            let c_req = ClientRequest::new(RequestId::new(0, seq_number as u32), seq_number as u32);
+            peer.mark_committed(seq_number, c_req, Time::new(0));
+            other_peer.mark_committed(seq_number, c_req, Time::new(0));
+        }
+
+        let c_req = ClientRequest::new(RequestId::new(0, 3), 3);
+        let output = lagging
+            .handle_message(
+                PBFTMessage::Prepare(PrepareMessage::new(c_req, 1, 3, 2)),
+                Time::new(32),
+            )
+            .unwrap()
+            .expect("A state transfer should be started");
+
+        let fetch_state = match output.last() {
+            Some((4, PBFTMessage::FetchState(m))) => *m,
+            other => panic!("Expected a FetchState to all peers, got {:?}", other),
+        };
+        assert_eq!(
+            output
+                .iter()
+                .filter(|(_, m)| matches!(m, PBFTMessage::FetchState(_)))
+                .count(),
+            3
+        );
+
+        let respond = |peer: &mut ReplicaState, lagging: &mut ReplicaState| {
+            let response = peer
+                .handle_message(PBFTMessage::FetchState(fetch_state), Time::new(33))
+                .unwrap()
+                .expect("The peer should answer with its state");
+            assert_eq!(response.len(), 1);
+            lagging
+                .handle_message(response[0].1.clone(), Time::new(34))
+                .unwrap();
+        };
+
+        // a single peer could make up the state
+        respond(&mut peer, &mut lagging);
+        assert_eq!(lagging.last_executed, 0);
+        assert!(lagging.state_transfer.is_some());
+
+        respond(&mut other_peer, &mut lagging);
+        assert_eq!(lagging.last_executed, 3);
+        assert_eq!(lagging.digest(), peer.digest());
+        assert_eq!(lagging.state_transfer, None);
+        assert!(lagging.state_responses.is_empty());
+        assert!(lagging.log.is_empty());
+    }
+
+    #[test]
+    fn state_responses_that_differ_are_not_applied() {
+        let mut lagging = ReplicaState::new(3, 4);
+        let c_req = ClientRequest::new(RequestId::new(0, 1), 1);
+        let forged = ClientRequest::new(RequestId::new(0, 1), 7);
+
+        let response = |entries, sender_id| {
+            PBFTMessage::StateResponse(StateResponseMessage::new(entries, sender_id))
+        };
+        lagging
+            .handle_message(response(vec![(1, c_req)], 2), Time::new(32))
+            .unwrap();
+        lagging
+            .handle_message(response(vec![(1, forged)], 4), Time::new(32))
+            .unwrap();
+        lagging
+            .handle_message(response(vec![(2, c_req)], 1), Time::new(32))
+            .unwrap();
+        // the same sender counts once
+        lagging
+            .handle_message(response(vec![(1, c_req)], 2), Time::new(32))
+            .unwrap();
+        assert_eq!(lagging.last_executed, 0);
+        assert!(!lagging.cl_reqs.contains(&c_req.id));
+
+        lagging
+            .handle_message(response(vec![(1, c_req)], 1), Time::new(33))
+            .unwrap();
+        assert_eq!(lagging.last_executed, 1);
+        assert_eq!(lagging.executed(), &[c_req.id]);
+    }
+
+    #[test]
+    fn client_responses_are_invalid_at_replicas() {
+        let mut state = ReplicaState::new(2, 4);
+        let reply = ClientResponse {
+            id: RequestId::new(0, 1),
+            result: 1,
+            sender_id: 3,
+        };
+
+        assert_eq!(
+            state.handle_message(PBFTMessage::ClientResponse(reply), Time::new(32)),
+            Err(ProtocolError::UnexpectedMessage {
+                node: 2,
+                message_type: "ClientResponse",
+            })
+        );
     }
 }
@@ -0,0 +1,211 @@
+/***************************************************************************************************
+Property tests of the protocols. The replicas of a cluster exchange their messages under random
+schedules: every step delivers a random in-flight message, which reorders them arbitrarily, and
+messages may be dropped or duplicated. Each schedule is derived from a fixed seed, so a failing run
+can be replayed, and the safety invariants are asserted after each run.
+***************************************************************************************************/
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::node::pbft::messages::{ClientRequest as PBFTCR, PBFTMessage};
+use crate::node::pbft::state::ReplicaState;
+use crate::node::request::RequestId;
+use crate::node::zyzzyva::client::Client as ZyzzyvaClient;
+use crate::node::zyzzyva::messages::{ClientRequest as ZyzzyvaCR, ZyzzyvaMessage};
+use crate::node::zyzzyva::state::State as ZyzzyvaState;
+use crate::simulation::config::PBFT_CLIENT_ID;
+use crate::simulation::safety::{check_agreement, ExecutionRecord};
+use crate::simulation::time::Time;
+
+/// Number of seeds every property is checked with
+const RUNS: u64 = 50;
+/// Upper bound of delivered messages per run, a safeguard against livelocks
+const MAX_STEPS: usize = 100_000;
+
+/// The faults of a random schedule
+#[derive(Debug, Clone, Copy)]
+struct Faults {
+    drop_probability: f64,
+    duplicate_probability: f64,
+}
+
+const RELIABLE: Faults = Faults {
+    drop_probability: 0.0,
+    duplicate_probability: 0.0,
+};
+
+/// Messages in flight, delivered in random order
+struct Schedule<M> {
+    rng: StdRng,
+    faults: Faults,
+    in_flight: Vec<(u32, M)>,
+    steps: usize,
+}
+
+impl<M: Clone> Schedule<M> {
+    fn new(seed: u64, faults: Faults) -> Self {
+        Schedule {
+            rng: StdRng::seed_from_u64(seed),
+            faults,
+            in_flight: Vec::new(),
+            steps: 0,
+        }
+    }
+
+    fn send(&mut self, to: u32, message: M) {
+        if self.rng.gen::<f64>() < self.faults.drop_probability {
+            return;
+        }
+        if self.rng.gen::<f64>() < self.faults.duplicate_probability {
+            self.in_flight.push((to, message.clone()));
+        }
+        self.in_flight.push((to, message));
+    }
+
+    /// Picks a random message in flight, the time of its delivery is the step counter
+    fn next(&mut self) -> Option<(u32, M, Time)> {
+        if self.in_flight.is_empty() || self.steps == MAX_STEPS {
+            return None;
+        }
+        self.steps += 1;
+        let index = self.rng.gen_range(0, self.in_flight.len());
+        let (to, message) = self.in_flight.swap_remove(index);
+        Some((to, message, Time::new(self.steps as u64)))
+    }
+}
+
+/// Asserts agreement and that no replica executed a request twice
+fn assert_safety(seed: u64, records: &[ExecutionRecord]) {
+    if let Err(divergence) = check_agreement(records) {
+        panic!("Seed {}: {}", seed, divergence);
+    }
+    for record in records {
+        let mut executed = record.executed.clone();
+        executed.sort();
+        executed.dedup();
+        assert_eq!(
+            executed.len(),
+            record.executed.len(),
+            "Seed {}: replica {} executed a request twice",
+            seed,
+            record.node_id
+        );
+    }
+}
+
+/// Runs a PBFT cluster of `n` replicas under the schedule of `seed`, the client sends its
+/// requests to random replicas
+fn run_pbft(seed: u64, n: u32, requests: u32, faults: Faults) -> Vec<ExecutionRecord> {
+    let mut replicas: Vec<ReplicaState> = (1..=n).map(|id| ReplicaState::new(id, n)).collect();
+    let mut schedule = Schedule::new(seed, faults);
+
+    for sequence in 1..=requests {
+        let to = schedule.rng.gen_range(1, n + 1);
+        let c_req = PBFTCR::new(RequestId::new(PBFT_CLIENT_ID, sequence), sequence);
+        schedule.send(to, PBFTMessage::ClientRequest(c_req));
+    }
+
+    while let Some((to, message, time)) = schedule.next() {
+        let output = replicas[to as usize - 1].handle_message(message, time);
+        if let Some(output) = output.expect("the replicas only exchange valid messages") {
+            for (to, message) in output {
+                // the client is not simulated
+                if to != PBFT_CLIENT_ID {
+                    schedule.send(to, message);
+                }
+            }
+        }
+    }
+
+    replicas
+        .iter()
+        .zip(1..)
+        .map(|(replica, node_id)| ExecutionRecord {
+            node_id,
+            executed: replica.executed().to_vec(),
+            digest: replica.digest(),
+        })
+        .collect()
+}
+
+/// Runs a Zyzzyva cluster of `n` replicas and their client `n + 1` under the schedule of `seed`
+fn run_zyzzyva(seed: u64, n: u32, requests: u32, faults: Faults) -> Vec<ExecutionRecord> {
+    let mut nodes: Vec<ZyzzyvaState> = (1..=n).map(|id| ZyzzyvaState::new(id, n)).collect();
+    let client_id = n + 1;
+    let mut client = ZyzzyvaClient::new(client_id, n);
+    let mut schedule = Schedule::new(seed, faults);
+
+    // the client hands the requests to the cluster itself, so they are never lost
+    for sequence in 1..=requests {
+        let c_req = ZyzzyvaCR::new(RequestId::new(client_id, sequence), sequence);
+        schedule
+            .in_flight
+            .push((client_id, ZyzzyvaMessage::ClientRequest(c_req)));
+    }
+
+    while let Some((to, message, time)) = schedule.next() {
+        let output = if to == client_id {
+            client.handle_message(message, time)
+        } else {
+            nodes[to as usize - 1]
+                .handle_message(message, time)
+                .expect("the replicas only exchange valid messages")
+        };
+        for (to, message) in output.into_iter().flatten() {
+            schedule.send(to, message);
+        }
+    }
+
+    nodes
+        .iter()
+        .zip(1..)
+        .map(|(node, node_id)| ExecutionRecord {
+            node_id,
+            executed: node.executed().to_vec(),
+            digest: node.digest(),
+        })
+        .collect()
+}
+
+#[test]
+fn pbft_executes_all_requests_in_order_under_reordering() {
+    for seed in 0..RUNS {
+        let records = run_pbft(seed, 4, 10, RELIABLE);
+        assert_safety(seed, &records);
+        for record in &records {
+            assert_eq!(
+                record.executed.len(),
+                10,
+                "Seed {}: replica {} did not execute all requests",
+                seed,
+                record.node_id
+            );
+        }
+    }
+}
+
+#[test]
+fn pbft_is_safe_under_drops_and_duplicates() {
+    let faults = Faults {
+        drop_probability: 0.2,
+        duplicate_probability: 0.1,
+    };
+    for seed in 0..RUNS {
+        for &n in &[4, 5, 7] {
+            assert_safety(seed, &run_pbft(seed, n, 10, faults));
+        }
+    }
+}
+
+#[test]
+fn zyzzyva_is_safe_under_reordering_and_drops() {
+    let faults = Faults {
+        drop_probability: 0.1,
+        duplicate_probability: 0.0,
+    };
+    for seed in 0..RUNS {
+        assert_safety(seed, &run_zyzzyva(seed, 4, 10, RELIABLE));
+        assert_safety(seed, &run_zyzzyva(seed, 4, 10, faults));
+    }
+}
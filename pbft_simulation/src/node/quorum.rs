@@ -0,0 +1,368 @@
+/***************************************************************************************************
+Quorum systems shared by the protocols. By default a system of n replicas tolerates f = (n - 1) / 3
+faulty replicas and uses the smallest quorums that intersect in at least f + 1 replicas. Both values
+can be overridden, and replicas can be given voting weights (e.g. WHEAT/AWARE), in which case a
+quorum is reached once the weights of its members add up to the quorum weight.
+***************************************************************************************************/
+
+use std::collections::HashMap;
+
+use crate::simulation::settings::QuorumSettings;
+
+/// Number of faulty replicas `n` replicas tolerate, f = (n - 1) / 3
+pub fn max_faults(n: u32) -> u32 {
+    n.saturating_sub(1) / 3
+}
+
+/// Number of replicas required to tolerate `f` faults, 3f + 1
+pub fn min_replicas(f: u32) -> u32 {
+    3 * f + 1
+}
+
+/// Checks that `n` replicas tolerate `f` faults
+pub fn check_fault_tolerance(n: u32, f: u32) -> Result<(), String> {
+    if n < min_replicas(f) {
+        return Err(format!(
+            "{} replicas cannot tolerate {} faults, at least {} are required",
+            n,
+            f,
+            min_replicas(f)
+        ));
+    }
+    Ok(())
+}
+
+/// The quorum system of a cluster of replicas
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuorumConfig {
+    n: u32,
+    f: u32,
+    quorum_size: u32,
+    /// Voting weight per replica, replicas without an entry have weight 1
+    weights: HashMap<u32, u32>,
+    /// Weight a quorum needs to reach, equals `quorum_size` without weights
+    quorum_weight: u32,
+}
+
+impl QuorumConfig {
+    /// Creates the standard quorum system for `n` replicas
+    pub fn new(n: u32) -> Self {
+        let f = max_faults(n);
+        let quorum_size = Self::intersecting_quorum_size(n, f);
+        QuorumConfig {
+            n,
+            f,
+            quorum_size,
+            weights: HashMap::new(),
+            quorum_weight: quorum_size,
+        }
+    }
+
+    /// Creates the quorum system for `n` replicas with the overrides of the [quorum] section,
+    /// `None` keeps the standard value
+    pub fn from_settings(n: u32, settings: &QuorumSettings) -> Self {
+        let mut config = QuorumConfig::new(n);
+        if let Some(f) = settings.f {
+            config = config.with_f(f);
+        }
+        if let Some(size) = settings.size {
+            config = config.with_quorum_size(size);
+        }
+
+        match settings.weights.as_str() {
+            "none" => {}
+            // the primary of the first view and its successors get the maximal weight
+            "wheat" => {
+                let best: Vec<u32> = (1..=2 * config.f).collect();
+                config = QuorumConfig::wheat(n, config.f, &best);
+            }
+            list => {
+                config = config.with_weights(parse_weights(list), 0);
+                config.quorum_weight = settings
+                    .quorum_weight
+                    .unwrap_or_else(|| config.auto_quorum_weight());
+            }
+        }
+        config
+    }
+
+    // smallest quorum size s.t. two quorums intersect in at least f + 1 replicas
+    fn intersecting_quorum_size(n: u32, f: u32) -> u32 {
+        (n + f + 2) / 2
+    }
+
+    /// Overrides the number of tolerated faults, the quorum size is derived from it
+    pub fn with_f(mut self, f: u32) -> Self {
+        if let Err(e) = check_fault_tolerance(self.n, f) {
+            panic!("{}", e);
+        }
+        self.f = f;
+        self.quorum_size = Self::intersecting_quorum_size(self.n, f);
+        self.quorum_weight = self.quorum_size;
+        self
+    }
+
+    /// Overrides the quorum size
+    pub fn with_quorum_size(mut self, quorum_size: u32) -> Self {
+        if quorum_size == 0 || quorum_size > self.n {
+            panic!(
+                "A quorum of {} replicas is not possible with {} replicas",
+                quorum_size, self.n
+            );
+        }
+        self.quorum_size = quorum_size;
+        self.quorum_weight = quorum_size;
+        self
+    }
+
+    /// Assigns voting weights, a quorum is reached once the weights of its members add up to
+    /// `quorum_weight`
+    pub fn with_weights(mut self, weights: HashMap<u32, u32>, quorum_weight: u32) -> Self {
+        self.weights = weights;
+        self.quorum_weight = quorum_weight;
+        self
+    }
+
+    /// Weighted voting as in WHEAT: with n = 3f + 1 + delta, the `best` 2f replicas get the weight
+    /// 1 + delta / f, all others 1, and a quorum needs the weight 2(f + delta) + 1. The weights are
+    /// scaled by f to keep them integral.
+    pub fn wheat(n: u32, f: u32, best: &[u32]) -> Self {
+        if f == 0 || n < min_replicas(f) {
+            panic!(
+                "WHEAT requires 1 <= f and n >= 3f + 1, got n = {} and f = {}",
+                n, f
+            );
+        }
+        if best.len() != 2 * f as usize {
+            panic!(
+                "WHEAT requires 2f = {} replicas with the maximal weight",
+                2 * f
+            );
+        }
+
+        let delta = n - min_replicas(f);
+        let weights = (1..=n)
+            .map(|id| {
+                let weight = if best.contains(&id) { f + delta } else { f };
+                (id, weight)
+            })
+            .collect();
+        QuorumConfig::new(n)
+            .with_f(f)
+            .with_weights(weights, f * (2 * (f + delta) + 1))
+    }
+
+    /// Smallest quorum weight s.t. two quorums intersect in more weight than any f replicas hold
+    /// together, i.e. (total weight + weight of the f heaviest replicas) / 2 + 1
+    pub fn auto_quorum_weight(&self) -> u32 {
+        let mut weights: Vec<u32> = (1..=self.n).map(|id| self.weight(id)).collect();
+        weights.sort_unstable_by(|a, b| b.cmp(a));
+        let total: u32 = weights.iter().sum();
+        let faulty: u32 = weights.iter().take(self.f as usize).sum();
+        (total + faulty) / 2 + 1
+    }
+
+    pub fn n(&self) -> u32 {
+        self.n
+    }
+
+    pub fn f(&self) -> u32 {
+        self.f
+    }
+
+    /// Number of replicas in a quorum, for unweighted voting
+    pub fn quorum_size(&self) -> usize {
+        self.quorum_size as usize
+    }
+
+    /// Voting weight of the replica `id`
+    pub fn weight(&self, id: u32) -> u32 {
+        self.weights.get(&id).copied().unwrap_or(1)
+    }
+
+    /// Weight a quorum needs to reach
+    pub fn quorum_weight(&self) -> u32 {
+        self.quorum_weight
+    }
+
+    /// Returns `true` if the given replicas form a quorum
+    pub fn is_quorum<'a, I: IntoIterator<Item = &'a u32>>(&self, replicas: I) -> bool {
+        let weight: u32 = replicas.into_iter().map(|id| self.weight(*id)).sum();
+        weight >= self.quorum_weight
+    }
+
+    /// Like `is_quorum`, without weights only the members of the set are counted
+    pub fn is_quorum_set(&self, replicas: &ReplicaSet) -> bool {
+        if self.weights.is_empty() {
+            return replicas.len() as u32 >= self.quorum_weight;
+        }
+        let weight: u32 = replicas.iter().map(|id| self.weight(id)).sum();
+        weight >= self.quorum_weight
+    }
+}
+
+/// A set of replica ids stored as a bitset, e.g. the senders of the votes for a request. It takes
+/// a bit per replica instead of a message, so large clusters can track many requests.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplicaSet {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl ReplicaSet {
+    pub fn new() -> Self {
+        ReplicaSet::default()
+    }
+
+    /// Adds the replica `id`, returns `false` if it was already a member
+    pub fn insert(&mut self, id: u32) -> bool {
+        let (word, bit) = (id as usize / 64, id % 64);
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+        if self.words[word] & (1 << bit) != 0 {
+            return false;
+        }
+        self.words[word] |= 1 << bit;
+        self.len += 1;
+        true
+    }
+
+    pub fn contains(&self, id: u32) -> bool {
+        match self.words.get(id as usize / 64) {
+            Some(word) => word & (1 << (id % 64)) != 0,
+            None => false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The members in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| (i * 64) as u32 + bit)
+        })
+    }
+}
+
+// parses weights of the form '<id>:<weight> <id>:<weight> ...'
+fn parse_weights(list: &str) -> HashMap<u32, u32> {
+    list.split_whitespace()
+        .map(|pair| {
+            let mut parts = pair.splitn(2, ':');
+            let id = parts.next().and_then(|id| id.parse().ok());
+            let weight = parts.next().and_then(|weight| weight.parse().ok());
+            match (id, weight) {
+                (Some(id), Some(weight)) => (id, weight),
+                _ => panic!(
+                    "Can't parse quorum weight '{}', allowed are 'none', 'wheat' or '<id>:<weight> ...'",
+                    pair
+                ),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_standard_quorums() {
+        // (n, f, quorum size)
+        for &(n, f, q) in &[(4, 1, 3), (5, 1, 4), (6, 1, 4), (7, 2, 5)] {
+            let config = QuorumConfig::new(n);
+            assert_eq!((config.f(), config.quorum_size()), (f, q), "n = {}", n);
+            // any two quorums intersect in at least f + 1 replicas
+            assert!(2 * q as u32 - n > f);
+        }
+    }
+
+    #[test]
+    fn check_fault_tolerance_of_all_cluster_sizes() {
+        assert_eq!(max_faults(4), 1);
+        assert_eq!(max_faults(7), 2);
+        assert_eq!(max_faults(10), 3);
+        for n in 1..=1000 {
+            let f = max_faults(n);
+            // the most faults n replicas tolerate, one more would need more replicas
+            assert!(min_replicas(f) <= n && n < min_replicas(f + 1), "n = {}", n);
+            assert_eq!(check_fault_tolerance(n, f), Ok(()));
+            assert!(check_fault_tolerance(n, f + 1).is_err());
+        }
+        // the smallest clusters use quorums of 2f + 1 replicas
+        for f in 1..=300 {
+            let config = QuorumConfig::new(min_replicas(f));
+            assert_eq!(config.f(), f);
+            assert_eq!(config.quorum_size() as u32, 2 * f + 1, "f = {}", f);
+        }
+    }
+
+    #[test]
+    fn check_overrides() {
+        let config = QuorumConfig::new(7).with_f(1);
+        assert_eq!(config.quorum_size(), 5);
+        assert_eq!(QuorumConfig::new(7).with_quorum_size(6).quorum_size(), 6);
+        assert!(QuorumConfig::new(4).is_quorum(&[1, 2, 3]));
+        assert!(!QuorumConfig::new(4).is_quorum(&[1, 2]));
+    }
+
+    #[test]
+    fn check_replica_set() {
+        let mut set = ReplicaSet::new();
+        assert!(set.insert(3));
+        assert!(!set.insert(3));
+        assert!(set.insert(64));
+        assert!(set.insert(299));
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(64) && !set.contains(63) && !set.contains(1000));
+        assert_eq!(set.iter().collect::<Vec<u32>>(), vec![3, 64, 299]);
+
+        let config = QuorumConfig::wheat(5, 1, &[1, 2]);
+        let mut senders = ReplicaSet::new();
+        for id in &[1, 3, 4] {
+            senders.insert(*id);
+        }
+        assert!(!config.is_quorum_set(&senders));
+        senders.insert(2);
+        assert!(config.is_quorum_set(&senders));
+        assert!(QuorumConfig::new(4).is_quorum_set(&set));
+    }
+
+    #[test]
+    #[should_panic]
+    fn check_too_many_faults() {
+        QuorumConfig::new(6).with_f(2);
+    }
+
+    #[test]
+    fn check_wheat_weights() {
+        // n = 5, f = 1, delta = 1: the two best replicas need only one more to form a quorum
+        let config = QuorumConfig::wheat(5, 1, &[1, 2]);
+        assert_eq!(config.weight(1), 2);
+        assert_eq!(config.weight(3), 1);
+        assert!(config.is_quorum(&[1, 2, 3]));
+        assert!(!config.is_quorum(&[1, 3, 4]));
+        assert!(config.is_quorum(&[1, 3, 4, 5]));
+        assert_eq!(config.auto_quorum_weight(), config.quorum_weight());
+    }
+
+    #[test]
+    fn check_auto_quorum_weight() {
+        assert_eq!(QuorumConfig::new(7).auto_quorum_weight(), 5);
+
+        let weights = parse_weights("1:3 2:2");
+        assert_eq!(weights.get(&1), Some(&3));
+        // weights 3 2 1 1 1 1 1 (total 10), f = 2 replicas hold at most 5
+        let config = QuorumConfig::new(7).with_weights(weights, 0);
+        assert_eq!(config.auto_quorum_weight(), 8);
+    }
+}
@@ -0,0 +1,58 @@
+/***************************************************************************************************
+Identity of client requests, shared by all protocols. A request is identified by the client that
+issued it and the sequence number the client assigned, so two clients may issue the same operation
+without their requests being mixed up.
+***************************************************************************************************/
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Identifies a client request by its client and the client's sequence number
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[derive(
+    Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize,
+)]
+pub struct RequestId {
+    pub client_id: u32,
+    pub sequence: u32,
+}
+
+impl RequestId {
+    pub fn new(client_id: u32, sequence: u32) -> Self {
+        RequestId {
+            client_id,
+            sequence,
+        }
+    }
+}
+
+/// Displayed as the sequence number, which is how the result logs and the tools reading them refer
+/// to requests. The workload generator numbers the requests of all clients consecutively, so the
+/// sequence number alone is unique within a simulation.
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.sequence)
+    }
+}
+
+/// The sequence number alone, as the result log refers to requests
+impl From<RequestId> for u64 {
+    fn from(id: RequestId) -> Self {
+        u64::from(id.sequence)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_request_ids_of_clients_differ() {
+        let a = RequestId::new(1, 7);
+        let b = RequestId::new(2, 7);
+        assert_ne!(a, b);
+        assert!(a < b);
+        assert_eq!(a.to_string(), "7");
+    }
+}
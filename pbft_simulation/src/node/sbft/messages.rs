@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+
+use crate::node::request::RequestId;
+use crate::simulation::event::Priority;
+
+/// Type defining the messages of the linear PBFT variant of SBFT. Replicas send their votes
+/// (signature shares) only to the collector, which broadcasts the combined certificate.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+pub enum SBFTMessage {
+    ClientRequest(ClientRequest),
+    PrePrepare(PrePrepareMessage),
+    SignShare(SignShareMessage),
+    Certificate(CertificateMessage),
+}
+
+/// Size of a client request: client id, sequence number and operation
+const CLIENT_REQUEST_SIZE: u32 = 12;
+/// Size of the fields shared by the ordering messages: phase, view, sequence number and sender id
+const ORDER_HEADER_SIZE: u32 = 21;
+
+impl SBFTMessage {
+    /// Name of the message type, e.g. for statistics.
+    pub fn message_type(&self) -> &'static str {
+        match self {
+            SBFTMessage::ClientRequest(_) => "ClientRequest",
+            SBFTMessage::PrePrepare(_) => "PrePrepare",
+            SBFTMessage::SignShare(m) => match m.phase {
+                Phase::Prepare => "PrepareShare",
+                Phase::Commit => "CommitShare",
+            },
+            SBFTMessage::Certificate(m) => match m.phase {
+                Phase::Prepare => "PrepareCertificate",
+                Phase::Commit => "CommitCertificate",
+            },
+        }
+    }
+
+    /// Priority class at an overloaded node, all but the client requests order requests.
+    pub fn priority(&self) -> Priority {
+        match self {
+            SBFTMessage::ClientRequest(_) => Priority::Client,
+            SBFTMessage::PrePrepare(_)
+            | SBFTMessage::SignShare(_)
+            | SBFTMessage::Certificate(_) => Priority::Ordering,
+        }
+    }
+
+    /// Whether the sender appends the message to its log before it sends it, see `[storage]`.
+    pub fn is_persisted(&self) -> bool {
+        matches!(self, SBFTMessage::PrePrepare(_) | SBFTMessage::SignShare(_))
+    }
+
+    /// Id of the client request the message belongs to.
+    pub fn request_id(&self) -> Option<u64> {
+        let id = match self {
+            SBFTMessage::ClientRequest(m) => m.id,
+            SBFTMessage::PrePrepare(m) => m.c_req.id,
+            SBFTMessage::SignShare(m) => m.c_req.id,
+            SBFTMessage::Certificate(m) => m.c_req.id,
+        };
+        Some(u64::from(id.sequence))
+    }
+
+    /// Size of the message in bytes, without its signature or signature share.
+    pub fn payload_size(&self) -> u32 {
+        match self {
+            SBFTMessage::ClientRequest(_) => CLIENT_REQUEST_SIZE,
+            _ => ORDER_HEADER_SIZE + CLIENT_REQUEST_SIZE,
+        }
+    }
+}
+
+/// The two voting phases of linear PBFT
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum Phase {
+    Prepare,
+    Commit,
+}
+
+/// Type defining a _client request_.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct ClientRequest {
+    /// The issuing client and its sequence number
+    pub id: RequestId,
+    pub operation: u32,
+}
+impl ClientRequest {
+    pub fn new(id: RequestId, operation: u32) -> Self {
+        ClientRequest { id, operation }
+    }
+}
+
+/// Type defining a _Pre-Prepare_ message send by the _primary_.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct PrePrepareMessage {
+    pub c_req: ClientRequest,
+    pub view: u64,
+    pub seq_number: u64,
+    pub sender_id: u32,
+}
+impl PrePrepareMessage {
+    pub fn new(c_req: ClientRequest, view: u64, seq_number: u64, sender_id: u32) -> Self {
+        PrePrepareMessage {
+            c_req,
+            view,
+            seq_number,
+            sender_id,
+        }
+    }
+}
+
+/// Type defining the vote of a replica in `phase`, a share of the threshold signature that is
+/// sent to the collector.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct SignShareMessage {
+    pub phase: Phase,
+    pub c_req: ClientRequest,
+    pub view: u64,
+    pub seq_number: u64,
+    pub sender_id: u32,
+}
+impl SignShareMessage {
+    pub fn new(
+        phase: Phase,
+        c_req: ClientRequest,
+        view: u64,
+        seq_number: u64,
+        sender_id: u32,
+    ) -> Self {
+        SignShareMessage {
+            phase,
+            c_req,
+            view,
+            seq_number,
+            sender_id,
+        }
+    }
+}
+
+/// Type defining the certificate the collector combined from a quorum of shares in `phase`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct CertificateMessage {
+    pub phase: Phase,
+    pub c_req: ClientRequest,
+    pub view: u64,
+    pub seq_number: u64,
+    /// Number of shares the certificate was combined from
+    pub shares: u32,
+    pub sender_id: u32,
+}
+impl CertificateMessage {
+    pub fn new(
+        phase: Phase,
+        c_req: ClientRequest,
+        view: u64,
+        seq_number: u64,
+        shares: u32,
+        sender_id: u32,
+    ) -> Self {
+        CertificateMessage {
+            phase,
+            c_req,
+            view,
+            seq_number,
+            shares,
+            sender_id,
+        }
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use log::{debug, warn};
+
+use crate::node::application::{Application, CounterApplication};
+use crate::node::quorum::{QuorumConfig, ReplicaSet};
+use crate::node::request::RequestId;
+use crate::simulation::config::ResultLogger;
+use crate::simulation::results::ResultEvent;
+use crate::simulation::time::Time;
+
+use super::messages::*;
+
+/// The output produced by this module. Consumed by the host running the `ReplicaState`.
+type Output = Vec<(u32, SBFTMessage)>;
+
+/// Creates an `Output` such that the host broadcasts `msg_out` to all other
+/// replicas in the cluster.
+fn create_peer_broadcast_output(msg_out: SBFTMessage, peers: &[u32]) -> Output {
+    peers.iter().map(|id| (*id, msg_out.clone())).collect()
+}
+
+/// The primary rotates through the replicas `1..=num_of_nodes`, starting with `1` in view `1`.
+fn primary_of_view(view: u64, num_of_nodes: u32) -> u32 {
+    ((view.saturating_sub(1) % u64::from(num_of_nodes)) + 1) as u32
+}
+
+/// The type defining an entry of the replica's log. The collector gathers the
+/// signature shares of both phases here, all other replicas only track whether
+/// the request is prepared.
+#[derive(Debug)]
+pub struct LogEntry {
+    c_req: ClientRequest,
+    view: u64,
+    seq_number: u64,
+    /// Senders of the prepare shares, only used by the collector
+    prepare_shares: ReplicaSet,
+    /// Senders of the commit shares, only used by the collector
+    commit_shares: ReplicaSet,
+    /// `true` as soon as the replica has seen a prepare certificate for this entry.
+    prepared: bool,
+}
+
+impl LogEntry {
+    pub fn new(c_req: ClientRequest, view: u64, seq_number: u64) -> Self {
+        LogEntry {
+            c_req,
+            view,
+            seq_number,
+            prepare_shares: ReplicaSet::new(),
+            commit_shares: ReplicaSet::new(),
+            prepared: false,
+        }
+    }
+}
+
+/// The type defining the state required for participating in a cluster running
+/// the linear PBFT of SBFT. The primary of the view also acts as the collector.
+///
+/// Exposes a single function for handling incoming SBFT messages.
+#[derive(Debug)]
+pub struct ReplicaState {
+    id: u32,
+    log: HashMap<RequestId, LogEntry>,
+    /// IDs of locally committed requests, subsequent messages related to them are ignored.
+    cl_reqs: HashSet<RequestId>,
+    /// The fixed number of nodes participating in the cluster.
+    num_of_nodes: u32,
+    /// The view number in which the replica currently operates.
+    current_view: u64,
+    /// Used only by the `primary` to assign the next sequence number.
+    next_seq_num: u64,
+    /// Requests committed locally, waiting for their predecessors to be executed.
+    committed: BTreeMap<u64, ClientRequest>,
+    /// The sequence number up to which all requests are executed.
+    last_executed: u64,
+    /// Holds the IDs of other peers.
+    peers: Vec<u32>,
+    /// The quorum system, a certificate combines the shares of a quorum
+    quorum: QuorumConfig,
+    /// Executes the committed requests in the order of their sequence numbers.
+    application: Box<dyn Application>,
+    /// The executed requests, in order.
+    executed: Vec<RequestId>,
+    /// The result log the phases of the requests are written to
+    results: ResultLogger,
+}
+
+impl ReplicaState {
+    /// Creates a new `ReplicaState` with `current_view` set to 1, i.e. the node
+    /// with id `1` is primary and collector.
+    ///
+    /// Requires the parameter `num_of_nodes` to be at least `4`, otherwise it
+    /// `panics!` since at least 4 nodes are required for successful operation.
+    pub fn new(id: u32, num_of_nodes: u32) -> Self {
+        if num_of_nodes < 4 {
+            panic!("Need at least 4 SBFT nodes but got only {}", num_of_nodes);
+        }
+
+        ReplicaState {
+            id,
+            log: HashMap::new(),
+            cl_reqs: HashSet::new(),
+            num_of_nodes,
+            current_view: 1,
+            next_seq_num: 0,
+            committed: BTreeMap::new(),
+            last_executed: 0,
+            peers: (1..=num_of_nodes).filter(|i| *i != id).collect(),
+            quorum: QuorumConfig::new(num_of_nodes),
+            application: Box::new(CounterApplication::default()),
+            executed: Vec::new(),
+            results: ResultLogger::default(),
+        }
+    }
+
+    /// Replaces the standard quorum system.
+    pub fn quorum(mut self, quorum: QuorumConfig) -> Self {
+        self.quorum = quorum;
+        self
+    }
+
+    /// Replaces the default counter application executing the committed requests.
+    pub fn application(mut self, application: Box<dyn Application>) -> Self {
+        self.application = application;
+        self
+    }
+
+    /// Writes the phases of the requests to `results` instead of discarding them.
+    pub fn result_logger(mut self, results: ResultLogger) -> Self {
+        self.results = results;
+        self
+    }
+
+    /// The sequence number up to which all requests are executed.
+    pub fn last_executed(&self) -> u64 {
+        self.last_executed
+    }
+
+    /// The executed requests, in the order of their execution.
+    pub fn executed(&self) -> &[RequestId] {
+        &self.executed
+    }
+
+    /// Digest of the application state after executing all requests up to `last_executed`.
+    pub fn digest(&self) -> u64 {
+        self.application.digest()
+    }
+
+    /// Gets the `id` of the collector for the current view, which is its primary.
+    pub fn collector(&self) -> u32 {
+        primary_of_view(self.current_view, self.num_of_nodes)
+    }
+
+    /// Single exposed function that acts as a entry point for handling incoming
+    /// messages by peers or clients.
+    pub fn handle_message(&mut self, message: SBFTMessage, time: Time) -> Option<Output> {
+        if self.can_ignore_message(&message) {
+            return None;
+        }
+
+        match message {
+            SBFTMessage::ClientRequest(m) => self.handle_client_request(m, time),
+            SBFTMessage::PrePrepare(m) => self.handle_pre_prepare(m, time),
+            SBFTMessage::SignShare(m) => self.handle_sign_share(m, time),
+            SBFTMessage::Certificate(m) => self.handle_certificate(m, time),
+        }
+    }
+
+    /// Messages of locally committed requests are ignored.
+    fn can_ignore_message(&self, message: &SBFTMessage) -> bool {
+        match message {
+            SBFTMessage::ClientRequest(m) => self.cl_reqs.contains(&m.id),
+            SBFTMessage::PrePrepare(m) => self.cl_reqs.contains(&m.c_req.id),
+            SBFTMessage::SignShare(m) => self.cl_reqs.contains(&m.c_req.id),
+            SBFTMessage::Certificate(m) => self.cl_reqs.contains(&m.c_req.id),
+        }
+    }
+
+    /// Gets the `id` of the primary for the current view.
+    fn curr_primary(&self) -> u32 {
+        primary_of_view(self.current_view, self.num_of_nodes)
+    }
+
+    /// Increments the sequence number counter and returns the value.
+    fn next_seq_num(&mut self) -> u64 {
+        self.next_seq_num += 1;
+        self.next_seq_num
+    }
+
+    /// Creates the share of the replica for the entry of `req_id` in `phase`.
+    fn sign_share(&self, phase: Phase, req_id: RequestId) -> SignShareMessage {
+        let entry = &self.log[&req_id];
+        SignShareMessage::new(phase, entry.c_req, entry.view, entry.seq_number, self.id)
+    }
+
+    /// Stores a locally committed request and executes all consecutively committed
+    /// requests, advancing `last_executed` over their sequence numbers.
+    fn mark_committed(&mut self, seq_number: u64, c_req: ClientRequest, time: Time) {
+        self.results.log(
+            time,
+            Some(self.id),
+            &ResultEvent::CommittedLocal(c_req.id.into()),
+        );
+
+        self.log.remove(&c_req.id);
+        self.cl_reqs.insert(c_req.id);
+        self.committed.insert(seq_number, c_req);
+
+        while let Some(c_req) = self.committed.remove(&(self.last_executed + 1)) {
+            self.results
+                .log(time, Some(self.id), &ResultEvent::Executed(c_req.id.into()));
+            self.application.execute(c_req.operation);
+            self.executed.push(c_req.id);
+            self.last_executed += 1;
+        }
+    }
+
+    fn handle_client_request(&mut self, msg_in: ClientRequest, time: Time) -> Option<Output> {
+        // a retransmission of a request in progress is dropped, it is ordered already
+        if self.log.contains_key(&msg_in.id) {
+            return None;
+        }
+
+        let primary = self.curr_primary();
+        if self.id != primary {
+            debug!(target: "node", "SBFTNode {} forwards client request {} to the primary {}", self.id, msg_in.id, primary);
+            return Some(vec![(primary, SBFTMessage::ClientRequest(msg_in))]);
+        }
+
+        self.results
+            .log(time, Some(self.id), &ResultEvent::Request(msg_in.id.into()));
+
+        let seq_number = self.next_seq_num();
+        let mut entry = LogEntry::new(msg_in, self.current_view, seq_number);
+        // the pre-prepare of the primary counts as its prepare share
+        entry.prepare_shares.insert(self.id);
+        self.log.insert(msg_in.id, entry);
+
+        let pre_prepare = PrePrepareMessage::new(msg_in, self.current_view, seq_number, self.id);
+        let output =
+            create_peer_broadcast_output(SBFTMessage::PrePrepare(pre_prepare), &self.peers);
+        self.collect(msg_in.id, output, time)
+    }
+
+    fn handle_pre_prepare(&mut self, msg_in: PrePrepareMessage, time: Time) -> Option<Output> {
+        if msg_in.sender_id != self.curr_primary() {
+            warn!(target: "node", "SBFTNode {} received a PrePrepare message from non-primary peer {}", self.id, msg_in.sender_id);
+            return None;
+        }
+        if self.log.contains_key(&msg_in.c_req.id) {
+            return None;
+        }
+
+        self.results.log(
+            time,
+            Some(self.id),
+            &ResultEvent::PrePrepared(msg_in.c_req.id.into()),
+        );
+
+        self.log.insert(
+            msg_in.c_req.id,
+            LogEntry::new(msg_in.c_req, msg_in.view, msg_in.seq_number),
+        );
+        let share = self.sign_share(Phase::Prepare, msg_in.c_req.id);
+
+        Some(vec![(self.collector(), SBFTMessage::SignShare(share))])
+    }
+
+    fn handle_sign_share(&mut self, msg_in: SignShareMessage, time: Time) -> Option<Output> {
+        if self.id != self.collector() {
+            warn!(target: "node", "SBFTNode {} is not the collector but received a share of {}", self.id, msg_in.sender_id);
+            return None;
+        }
+
+        let req_id = msg_in.c_req.id;
+        let entry = self.log.get_mut(&req_id)?;
+        match msg_in.phase {
+            Phase::Prepare => entry.prepare_shares.insert(msg_in.sender_id),
+            Phase::Commit => entry.commit_shares.insert(msg_in.sender_id),
+        };
+
+        self.collect(req_id, Output::new(), time)
+    }
+
+    /// Combines the shares of the entry of `req_id` into certificates once a quorum
+    /// of them was collected and broadcasts the certificates.
+    fn collect(&mut self, req_id: RequestId, mut output: Output, time: Time) -> Option<Output> {
+        let entry = self.log.get_mut(&req_id)?;
+
+        if !entry.prepared && self.quorum.is_quorum_set(&entry.prepare_shares) {
+            self.results
+                .log(time, Some(self.id), &ResultEvent::Prepared(req_id.into()));
+
+            entry.prepared = true;
+            entry.commit_shares.insert(self.id);
+
+            let certificate = CertificateMessage::new(
+                Phase::Prepare,
+                entry.c_req,
+                entry.view,
+                entry.seq_number,
+                entry.prepare_shares.len() as u32,
+                self.id,
+            );
+            output.append(&mut create_peer_broadcast_output(
+                SBFTMessage::Certificate(certificate),
+                &self.peers,
+            ));
+        }
+
+        if entry.prepared && self.quorum.is_quorum_set(&entry.commit_shares) {
+            let certificate = CertificateMessage::new(
+                Phase::Commit,
+                entry.c_req,
+                entry.view,
+                entry.seq_number,
+                entry.commit_shares.len() as u32,
+                self.id,
+            );
+            output.append(&mut create_peer_broadcast_output(
+                SBFTMessage::Certificate(certificate),
+                &self.peers,
+            ));
+
+            let (seq_number, c_req) = (entry.seq_number, entry.c_req);
+            self.mark_committed(seq_number, c_req, time);
+        }
+
+        match output.len() {
+            0 => None,
+            _ => Some(output),
+        }
+    }
+
+    fn handle_certificate(&mut self, msg_in: CertificateMessage, time: Time) -> Option<Output> {
+        if msg_in.sender_id != self.collector() {
+            warn!(target: "node", "SBFTNode {} received a certificate from non-collector peer {}", self.id, msg_in.sender_id);
+            return None;
+        }
+
+        let req_id = msg_in.c_req.id;
+        match msg_in.phase {
+            Phase::Prepare => {
+                // a replica that missed the pre-prepare learns the request from the certificate
+                let entry = self
+                    .log
+                    .entry(req_id)
+                    .or_insert_with(|| LogEntry::new(msg_in.c_req, msg_in.view, msg_in.seq_number));
+                if entry.prepared {
+                    return None;
+                }
+                entry.prepared = true;
+
+                self.results
+                    .log(time, Some(self.id), &ResultEvent::Prepared(req_id.into()));
+
+                let share = self.sign_share(Phase::Commit, req_id);
+                Some(vec![(self.collector(), SBFTMessage::SignShare(share))])
+            }
+            Phase::Commit => {
+                self.mark_committed(msg_in.seq_number, msg_in.c_req, time);
+                None
+            }
+        }
+    }
+}
+
+/*******************************************************************************
+ * TESTS
+ ******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shares_are_sent_to_the_collector() {
+        let mut backup = ReplicaState::new(3, 4);
+        let c_req = ClientRequest::new(RequestId::new(0, 1), 1);
+
+        let output = backup.handle_message(
+            SBFTMessage::PrePrepare(PrePrepareMessage::new(c_req, 1, 1, 1)),
+            Time::new(1),
+        );
+        let share = SignShareMessage::new(Phase::Prepare, c_req, 1, 1, 3);
+        assert_eq!(output, Some(vec![(1, SBFTMessage::SignShare(share))]));
+
+        // only the collector combines shares
+        assert_eq!(
+            backup.handle_message(SBFTMessage::SignShare(share), Time::new(2)),
+            None
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+
+use crate::node::request::RequestId;
+use crate::simulation::event::Priority;
+
+/// Type defining the messages of the hierarchical Steward protocol. The `Local*` messages and
+/// `Order` stay within a site, `Proposal` and `Accept` are exchanged between the representatives
+/// of the sites over the wide area.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+pub enum StewardMessage {
+    ClientRequest(ClientRequest),
+    /// The representative asks the replicas of its site to agree on a message of the site
+    LocalProposal(Phase, SiteMessage),
+    /// A replica agrees, a share of the threshold signature of its site
+    LocalVote(LocalVoteMessage),
+    /// The leading site assigns a sequence number to a request, signed by the site
+    Proposal(SiteMessage),
+    /// A site accepts the proposal of the leading site, signed by the site
+    Accept(SiteMessage),
+    /// The representative tells its site that the request is ordered
+    Order(SiteMessage),
+}
+
+/// Size of a client request: client id, sequence number and operation
+const CLIENT_REQUEST_SIZE: u32 = 12;
+/// Size of the fields shared by the ordering messages: phase, sequence number and site
+const ORDER_HEADER_SIZE: u32 = 13;
+/// Size of the sender of a local vote
+const SENDER_SIZE: u32 = 4;
+
+impl StewardMessage {
+    /// Name of the message type, e.g. for statistics.
+    pub fn message_type(&self) -> &'static str {
+        match self {
+            StewardMessage::ClientRequest(_) => "ClientRequest",
+            StewardMessage::LocalProposal(phase, _) => match phase {
+                Phase::Proposal => "LocalProposal",
+                Phase::Accept => "LocalAccept",
+            },
+            StewardMessage::LocalVote(m) => match m.phase {
+                Phase::Proposal => "ProposalShare",
+                Phase::Accept => "AcceptShare",
+            },
+            StewardMessage::Proposal(_) => "Proposal",
+            StewardMessage::Accept(_) => "Accept",
+            StewardMessage::Order(_) => "Order",
+        }
+    }
+
+    /// Priority class at an overloaded node, all but the client requests order requests.
+    pub fn priority(&self) -> Priority {
+        match self {
+            StewardMessage::ClientRequest(_) => Priority::Client,
+            _ => Priority::Ordering,
+        }
+    }
+
+    /// Whether the sender appends the message to its log before it sends it, see `[storage]`.
+    pub fn is_persisted(&self) -> bool {
+        matches!(
+            self,
+            StewardMessage::LocalVote(_) | StewardMessage::Proposal(_) | StewardMessage::Accept(_)
+        )
+    }
+
+    /// Id of the client request the message belongs to.
+    pub fn request_id(&self) -> Option<u64> {
+        let id = match self {
+            StewardMessage::ClientRequest(m) => m.id,
+            StewardMessage::LocalVote(m) => m.c_req.id,
+            StewardMessage::LocalProposal(_, m)
+            | StewardMessage::Proposal(m)
+            | StewardMessage::Accept(m)
+            | StewardMessage::Order(m) => m.c_req.id,
+        };
+        Some(u64::from(id.sequence))
+    }
+
+    /// Size of the message in bytes, without its signature or signature share.
+    pub fn payload_size(&self) -> u32 {
+        match self {
+            StewardMessage::ClientRequest(_) => CLIENT_REQUEST_SIZE,
+            StewardMessage::LocalVote(_) => ORDER_HEADER_SIZE + SENDER_SIZE + CLIENT_REQUEST_SIZE,
+            _ => ORDER_HEADER_SIZE + CLIENT_REQUEST_SIZE,
+        }
+    }
+}
+
+/// The two phases a site agrees on locally: the leading site on its proposal, the other sites
+/// on accepting it
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum Phase {
+    Proposal,
+    Accept,
+}
+
+/// Type defining a _client request_.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct ClientRequest {
+    /// The issuing client and its sequence number
+    pub id: RequestId,
+    pub operation: u32,
+}
+impl ClientRequest {
+    pub fn new(id: RequestId, operation: u32) -> Self {
+        ClientRequest { id, operation }
+    }
+}
+
+/// Type defining a message of a site about the request with the global sequence number `seq`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+pub struct SiteMessage {
+    pub seq: u64,
+    /// The site the message speaks for
+    pub site: u32,
+    pub c_req: ClientRequest,
+}
+impl SiteMessage {
+    pub fn new(seq: u64, site: u32, c_req: ClientRequest) -> Self {
+        SiteMessage { seq, site, c_req }
+    }
+}
+
+/// Type defining the vote of the replica `from` on a `LocalProposal` of its representative.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+pub struct LocalVoteMessage {
+    pub phase: Phase,
+    pub seq: u64,
+    pub from: u32,
+    pub c_req: ClientRequest,
+}
+impl LocalVoteMessage {
+    pub fn new(phase: Phase, seq: u64, from: u32, c_req: ClientRequest) -> Self {
+        LocalVoteMessage {
+            phase,
+            seq,
+            from,
+            c_req,
+        }
+    }
+}
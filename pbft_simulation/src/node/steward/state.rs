@@ -0,0 +1,419 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use log::debug;
+
+use crate::node::application::{Application, CounterApplication};
+use crate::node::quorum::QuorumConfig;
+use crate::node::request::RequestId;
+use crate::simulation::config::ResultLogger;
+use crate::simulation::results::ResultEvent;
+use crate::simulation::time::Time;
+
+use super::messages::*;
+
+/// The output produced by this module. Consumed by the host running the `ReplicaState`.
+type Output = Vec<(u32, StewardMessage)>;
+
+/// Creates an `Output` such that the host sends `msg_out` to all `receivers`.
+fn create_multicast_output(msg_out: StewardMessage, receivers: &[u32]) -> Output {
+    receivers.iter().map(|id| (*id, msg_out.clone())).collect()
+}
+
+/// The site whose representative assigns the sequence numbers, it does not change.
+const LEADING_SITE: u32 = 1;
+
+/// The replicas `1..=num_of_nodes` split into sites of consecutive ids, the same way the 'clusters'
+/// topology splits the nodes into data centers. The first replica of a site is its representative.
+#[derive(Debug, Clone)]
+pub struct Sites {
+    num_of_nodes: u32,
+    sites: u32,
+}
+
+impl Sites {
+    /// Requires every site to have at least `4` replicas, otherwise it `panics!` since a site
+    /// could not tolerate a fault.
+    pub fn new(num_of_nodes: u32, sites: u32) -> Self {
+        let sites = Sites {
+            num_of_nodes,
+            sites: sites.max(1),
+        };
+        if let Some(site) = (1..=sites.sites).find(|site| sites.members(*site).len() < 4) {
+            panic!(
+                "Need at least 4 Steward replicas per site but site {} of {} replicas in {} sites has only {}",
+                site,
+                num_of_nodes,
+                sites.sites,
+                sites.members(site).len()
+            );
+        }
+        sites
+    }
+
+    /// Number of sites
+    pub fn count(&self) -> u32 {
+        self.sites
+    }
+
+    /// The site (`1..=count`) of replica `id`
+    pub fn site_of(&self, id: u32) -> u32 {
+        (id - 1) * self.sites / self.num_of_nodes + 1
+    }
+
+    /// The replicas of `site`
+    pub fn members(&self, site: u32) -> Vec<u32> {
+        (1..=self.num_of_nodes)
+            .filter(|id| self.site_of(*id) == site)
+            .collect()
+    }
+
+    /// The replica speaking for `site` over the wide area
+    pub fn representative(&self, site: u32) -> u32 {
+        (1..=self.num_of_nodes)
+            .find(|id| self.site_of(*id) == site)
+            .unwrap_or(1)
+    }
+}
+
+/// The type defining the state required for participating in a cluster running the
+/// hierarchical Steward protocol. The replicas are grouped into sites, each site agrees
+/// locally on what its representative sends over the wide area: the leading site on
+/// the sequence number of a request (`Proposal`), the other sites on accepting it
+/// (`Accept`). A local agreement collects the signature shares of a quorum of the
+/// site into a threshold signature. A request is ordered once a majority of the sites
+/// accepted it, the representatives then tell their sites.
+///
+/// Only the normal case is simulated: the leading site and the representatives do not
+/// change and lost messages are not retransmitted.
+///
+/// Exposes a single function for handling incoming Steward messages.
+#[derive(Debug)]
+pub struct ReplicaState {
+    id: u32,
+    /// The sites of the cluster.
+    sites: Sites,
+    /// The site of the replica.
+    site: u32,
+    /// The representative of the site of the replica.
+    representative: u32,
+    /// The other replicas of the site.
+    site_peers: Vec<u32>,
+    /// The representatives of the other sites, only known to representatives.
+    remote_representatives: Vec<u32>,
+    /// The quorum system of the site.
+    quorum: QuorumConfig,
+    /// The next sequence number, only assigned by the representative of the leading site.
+    next_seq: u64,
+    /// The requests that got a sequence number.
+    assigned: HashSet<RequestId>,
+    /// The local agreements the representative started, by phase and sequence number.
+    local: HashMap<(Phase, u64), SiteMessage>,
+    /// The replicas of the site that voted for a local agreement.
+    votes: HashMap<(Phase, u64), HashSet<u32>>,
+    /// The proposals of the leading site, by sequence number.
+    proposals: HashMap<u64, ClientRequest>,
+    /// The sites that accepted a proposal, the leading site accepts by proposing.
+    accepts: HashMap<u64, HashSet<u32>>,
+    /// The ordered requests that are not executed yet, by sequence number.
+    ordered: BTreeMap<u64, ClientRequest>,
+    /// The sequence number of the last executed request.
+    last_executed: u64,
+    /// Executes the ordered requests.
+    application: Box<dyn Application>,
+    /// The executed requests, in order.
+    executed: Vec<RequestId>,
+    /// The result log the phases of the requests are written to
+    results: ResultLogger,
+}
+
+impl ReplicaState {
+    /// Creates a new `ReplicaState` of the replicas `1..=num_of_nodes` split into `sites`.
+    pub fn new(id: u32, num_of_nodes: u32, sites: u32) -> Self {
+        let sites = Sites::new(num_of_nodes, sites);
+        let site = sites.site_of(id);
+        let members = sites.members(site);
+        let representative = sites.representative(site);
+        let remote_representatives = if id == representative {
+            (1..=sites.count())
+                .filter(|s| *s != site)
+                .map(|s| sites.representative(s))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        ReplicaState {
+            id,
+            site,
+            representative,
+            site_peers: members.iter().copied().filter(|i| *i != id).collect(),
+            remote_representatives,
+            quorum: QuorumConfig::new(members.len() as u32),
+            sites,
+            next_seq: 1,
+            assigned: HashSet::new(),
+            local: HashMap::new(),
+            votes: HashMap::new(),
+            proposals: HashMap::new(),
+            accepts: HashMap::new(),
+            ordered: BTreeMap::new(),
+            last_executed: 0,
+            application: Box::new(CounterApplication::default()),
+            executed: Vec::new(),
+            results: ResultLogger::default(),
+        }
+    }
+
+    /// Replaces the default counter application executing the ordered requests.
+    pub fn application(mut self, application: Box<dyn Application>) -> Self {
+        self.application = application;
+        self
+    }
+
+    /// Writes the phases of the requests to `results` instead of discarding them.
+    pub fn result_logger(mut self, results: ResultLogger) -> Self {
+        self.results = results;
+        self
+    }
+
+    /// The site of the replica.
+    pub fn site(&self) -> u32 {
+        self.site
+    }
+
+    /// Number of signature shares a local agreement of the site combines.
+    pub fn local_quorum(&self) -> usize {
+        self.quorum.quorum_size()
+    }
+
+    /// The executed requests, in the order of their execution.
+    pub fn executed(&self) -> &[RequestId] {
+        &self.executed
+    }
+
+    /// Digest of the application state after executing all ordered requests.
+    pub fn digest(&self) -> u64 {
+        self.application.digest()
+    }
+
+    /// Single exposed function that acts as a entry point for handling incoming
+    /// messages by peers or clients.
+    pub fn handle_message(&mut self, message: StewardMessage, time: Time) -> Option<Output> {
+        let output = match message {
+            StewardMessage::ClientRequest(m) => self.handle_client_request(m, time),
+            StewardMessage::LocalProposal(phase, m) => self.handle_local_proposal(phase, m),
+            StewardMessage::LocalVote(m) => self.handle_local_vote(m, time),
+            StewardMessage::Proposal(m) => self.handle_proposal(m, time),
+            StewardMessage::Accept(m) => self.handle_accept(m, time),
+            StewardMessage::Order(m) => {
+                self.deliver(m.seq, m.c_req, time);
+                Output::new()
+            }
+        };
+
+        match output.len() {
+            0 => None,
+            _ => Some(output),
+        }
+    }
+
+    fn is_leader(&self) -> bool {
+        self.site == LEADING_SITE && self.id == self.representative
+    }
+
+    fn handle_client_request(&mut self, msg_in: ClientRequest, time: Time) -> Output {
+        // the representative of the leading site assigns the sequence numbers
+        if !self.is_leader() {
+            let leader = self.sites.representative(LEADING_SITE);
+            return vec![(leader, StewardMessage::ClientRequest(msg_in))];
+        }
+        if !self.assigned.insert(msg_in.id) {
+            return Output::new();
+        }
+
+        self.results
+            .log(time, Some(self.id), &ResultEvent::Request(msg_in.id.into()));
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.start_local(
+            Phase::Proposal,
+            SiteMessage::new(seq, self.site, msg_in),
+            time,
+        )
+    }
+
+    /// The representative asks its site to agree on `msg_out`, its own vote is counted.
+    fn start_local(&mut self, phase: Phase, msg_out: SiteMessage, time: Time) -> Output {
+        debug!(target: "node", "StewardNode {} starts the local {:?} of sequence number {}", self.id, phase, msg_out.seq);
+        self.local.insert((phase, msg_out.seq), msg_out);
+        self.votes
+            .entry((phase, msg_out.seq))
+            .or_default()
+            .insert(self.id);
+
+        let mut output = create_multicast_output(
+            StewardMessage::LocalProposal(phase, msg_out),
+            &self.site_peers,
+        );
+        output.append(&mut self.check_local(phase, msg_out.seq, time));
+        output
+    }
+
+    fn handle_local_proposal(&mut self, phase: Phase, msg_in: SiteMessage) -> Output {
+        if self.id == self.representative {
+            return Output::new();
+        }
+        let vote = LocalVoteMessage::new(phase, msg_in.seq, self.id, msg_in.c_req);
+        vec![(self.representative, StewardMessage::LocalVote(vote))]
+    }
+
+    fn handle_local_vote(&mut self, msg_in: LocalVoteMessage, time: Time) -> Output {
+        if self.id != self.representative || self.sites.site_of(msg_in.from) != self.site {
+            return Output::new();
+        }
+        self.votes
+            .entry((msg_in.phase, msg_in.seq))
+            .or_default()
+            .insert(msg_in.from);
+        self.check_local(msg_in.phase, msg_in.seq, time)
+    }
+
+    /// Once a quorum of the site voted, the representative combines the shares and sends the
+    /// message of the site over the wide area.
+    fn check_local(&mut self, phase: Phase, seq: u64, time: Time) -> Output {
+        let complete = self
+            .votes
+            .get(&(phase, seq))
+            .is_some_and(|votes| self.quorum.is_quorum(votes));
+        let msg_out = match self.local.get(&(phase, seq)) {
+            Some(msg_out) if complete => *msg_out,
+            _ => return Output::new(),
+        };
+        // the agreement is done, later votes are ignored
+        self.local.remove(&(phase, seq));
+        self.votes.remove(&(phase, seq));
+        debug!(target: "node", "StewardNode {} has the local {:?} of sequence number {}", self.id, phase, seq);
+
+        let wide_area = match phase {
+            Phase::Proposal => {
+                self.proposals.insert(seq, msg_out.c_req);
+                StewardMessage::Proposal(msg_out)
+            }
+            Phase::Accept => StewardMessage::Accept(msg_out),
+        };
+        self.accepts.entry(seq).or_default().insert(self.site);
+        let mut output = create_multicast_output(wide_area, &self.remote_representatives);
+        output.append(&mut self.check_order(seq, time));
+        output
+    }
+
+    fn handle_proposal(&mut self, msg_in: SiteMessage, time: Time) -> Output {
+        if self.id != self.representative
+            || self.proposals.contains_key(&msg_in.seq)
+            || self.is_ordered(msg_in.seq)
+        {
+            return Output::new();
+        }
+        self.proposals.insert(msg_in.seq, msg_in.c_req);
+        self.accepts
+            .entry(msg_in.seq)
+            .or_default()
+            .insert(msg_in.site);
+
+        let accept = SiteMessage::new(msg_in.seq, self.site, msg_in.c_req);
+        let mut output = self.start_local(Phase::Accept, accept, time);
+        output.append(&mut self.check_order(msg_in.seq, time));
+        output
+    }
+
+    fn handle_accept(&mut self, msg_in: SiteMessage, time: Time) -> Output {
+        if self.id != self.representative || self.is_ordered(msg_in.seq) {
+            return Output::new();
+        }
+        self.accepts
+            .entry(msg_in.seq)
+            .or_default()
+            .insert(msg_in.site);
+        self.check_order(msg_in.seq, time)
+    }
+
+    /// Once a majority of the sites accepted the proposal of `seq`, the representative tells its
+    /// site that the request is ordered.
+    fn check_order(&mut self, seq: u64, time: Time) -> Output {
+        // the own site may accept after a majority of the others did
+        if self.is_ordered(seq) {
+            self.accepts.remove(&seq);
+            return Output::new();
+        }
+        let majority = self
+            .accepts
+            .get(&seq)
+            .is_some_and(|sites| sites.len() as u32 > self.sites.count() / 2);
+        let c_req = match self.proposals.get(&seq) {
+            Some(c_req) if majority => *c_req,
+            _ => return Output::new(),
+        };
+        self.proposals.remove(&seq);
+        self.accepts.remove(&seq);
+
+        self.deliver(seq, c_req, time);
+        create_multicast_output(
+            StewardMessage::Order(SiteMessage::new(seq, self.site, c_req)),
+            &self.site_peers,
+        )
+    }
+
+    /// Whether the request of `seq` is known to be ordered.
+    fn is_ordered(&self, seq: u64) -> bool {
+        seq <= self.last_executed || self.ordered.contains_key(&seq)
+    }
+
+    /// Executes the ordered requests in the order of their sequence numbers.
+    fn deliver(&mut self, seq: u64, c_req: ClientRequest, time: Time) {
+        if seq <= self.last_executed {
+            return;
+        }
+        self.ordered.insert(seq, c_req);
+
+        while let Some(c_req) = self.ordered.remove(&(self.last_executed + 1)) {
+            self.last_executed += 1;
+            self.results.log(
+                time,
+                Some(self.id),
+                &ResultEvent::CommittedLocal(c_req.id.into()),
+            );
+            self.results
+                .log(time, Some(self.id), &ResultEvent::Executed(c_req.id.into()));
+            self.application.execute(c_req.operation);
+            self.executed.push(c_req.id);
+        }
+    }
+}
+
+/*******************************************************************************
+ * TESTS
+ ******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sites_split_consecutive_ids() {
+        let sites = Sites::new(12, 3);
+        assert_eq!(sites.members(2), vec![5, 6, 7, 8]);
+        assert_eq!(sites.site_of(9), 3);
+        assert_eq!(sites.representative(3), 9);
+
+        let uneven = Sites::new(13, 3);
+        let members: Vec<usize> = (1..=3).map(|s| uneven.members(s).len()).collect();
+        assert_eq!(members, vec![5, 4, 4]);
+        assert_eq!(uneven.representative(2), 6);
+    }
+
+    #[test]
+    #[should_panic]
+    fn sites_need_four_replicas() {
+        Sites::new(12, 4);
+    }
+}
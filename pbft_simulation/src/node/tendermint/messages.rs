@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+
+use crate::node::request::RequestId;
+use crate::simulation::event::Priority;
+
+/// Type defining the messages of Tendermint. Votes carry the proposed value itself, a replica that
+/// missed the proposal still learns the value it decides.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+pub enum TendermintMessage {
+    ClientRequest(ClientRequest),
+    /// A client request that is gossiped to the mempools of the other replicas
+    Gossip(ClientRequest),
+    Proposal(ProposalMessage),
+    Vote(VoteMessage),
+    /// A decision, sent to a replica lagging behind
+    Commit(CommitMessage),
+    /// A timeout requested by the replica itself, never sent over the network
+    Timeout(Timeout),
+}
+
+/// Size of a client request: client id, sequence number and operation
+const CLIENT_REQUEST_SIZE: u32 = 12;
+/// Size of the fields shared by proposals and votes: height, round and sender id
+const CONSENSUS_HEADER_SIZE: u32 = 20;
+
+impl TendermintMessage {
+    /// Name of the message type, e.g. for statistics.
+    pub fn message_type(&self) -> &'static str {
+        match self {
+            TendermintMessage::ClientRequest(_) => "ClientRequest",
+            TendermintMessage::Gossip(_) => "Gossip",
+            TendermintMessage::Proposal(_) => "Proposal",
+            TendermintMessage::Vote(m) => match m.step {
+                VoteStep::Prevote => "Prevote",
+                VoteStep::Precommit => "Precommit",
+            },
+            TendermintMessage::Commit(_) => "Commit",
+            TendermintMessage::Timeout(_) => "Timeout",
+        }
+    }
+
+    /// Priority class at an overloaded node, catching up on decisions is control traffic.
+    pub fn priority(&self) -> Priority {
+        match self {
+            TendermintMessage::ClientRequest(_) | TendermintMessage::Gossip(_) => Priority::Client,
+            TendermintMessage::Proposal(_) | TendermintMessage::Vote(_) => Priority::Ordering,
+            TendermintMessage::Commit(_) | TendermintMessage::Timeout(_) => Priority::Control,
+        }
+    }
+
+    /// Whether the sender appends the message to its log before it sends it, see `[storage]`.
+    pub fn is_persisted(&self) -> bool {
+        matches!(
+            self,
+            TendermintMessage::Proposal(_) | TendermintMessage::Vote(_)
+        )
+    }
+
+    /// Id of the client request the message belongs to, `None` for nil votes and timeouts.
+    pub fn request_id(&self) -> Option<u64> {
+        let id = match self {
+            TendermintMessage::ClientRequest(m) | TendermintMessage::Gossip(m) => m.id,
+            TendermintMessage::Proposal(m) => m.value.id,
+            TendermintMessage::Vote(m) => m.value?.id,
+            TendermintMessage::Commit(m) => m.value.id,
+            TendermintMessage::Timeout(_) => return None,
+        };
+        Some(u64::from(id.sequence))
+    }
+
+    /// Size of the message in bytes, without its signature.
+    pub fn payload_size(&self) -> u32 {
+        match self {
+            TendermintMessage::ClientRequest(_) | TendermintMessage::Gossip(_) => {
+                CLIENT_REQUEST_SIZE
+            }
+            // the valid round of a proposal
+            TendermintMessage::Proposal(_) => CONSENSUS_HEADER_SIZE + 8 + CLIENT_REQUEST_SIZE,
+            TendermintMessage::Vote(m) => match m.value {
+                Some(_) => CONSENSUS_HEADER_SIZE + 1 + CLIENT_REQUEST_SIZE,
+                None => CONSENSUS_HEADER_SIZE + 1,
+            },
+            TendermintMessage::Commit(_) => CONSENSUS_HEADER_SIZE + CLIENT_REQUEST_SIZE,
+            TendermintMessage::Timeout(_) => 0,
+        }
+    }
+}
+
+/// Type defining a _client request_, the values Tendermint decides on.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct ClientRequest {
+    /// The issuing client and its sequence number
+    pub id: RequestId,
+    pub operation: u32,
+}
+impl ClientRequest {
+    pub fn new(id: RequestId, operation: u32) -> Self {
+        ClientRequest { id, operation }
+    }
+}
+
+/// Type defining the _proposal_ of the proposer of `round` at `height`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct ProposalMessage {
+    pub height: u64,
+    pub round: u64,
+    pub value: ClientRequest,
+    /// The round in which the proposer saw a prevote quorum for `value`, if any
+    pub valid_round: Option<u64>,
+    pub sender_id: u32,
+}
+impl ProposalMessage {
+    pub fn new(
+        height: u64,
+        round: u64,
+        value: ClientRequest,
+        valid_round: Option<u64>,
+        sender_id: u32,
+    ) -> Self {
+        ProposalMessage {
+            height,
+            round,
+            value,
+            valid_round,
+            sender_id,
+        }
+    }
+}
+
+/// The two voting steps of a round
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum VoteStep {
+    Prevote,
+    Precommit,
+}
+
+/// Type defining a _prevote_ or _precommit_, `value` is `None` for a vote for nil.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct VoteMessage {
+    pub step: VoteStep,
+    pub height: u64,
+    pub round: u64,
+    pub value: Option<ClientRequest>,
+    pub sender_id: u32,
+}
+impl VoteMessage {
+    pub fn new(
+        step: VoteStep,
+        height: u64,
+        round: u64,
+        value: Option<ClientRequest>,
+        sender_id: u32,
+    ) -> Self {
+        VoteMessage {
+            step,
+            height,
+            round,
+            value,
+            sender_id,
+        }
+    }
+}
+
+/// Type defining the _decision_ of `height` in `round`. It carries the `precommits` signed
+/// precommits of the deciding round as certificate.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct CommitMessage {
+    pub height: u64,
+    pub round: u64,
+    pub value: ClientRequest,
+    pub precommits: u32,
+    pub sender_id: u32,
+}
+impl CommitMessage {
+    pub fn new(
+        height: u64,
+        round: u64,
+        value: ClientRequest,
+        precommits: u32,
+        sender_id: u32,
+    ) -> Self {
+        CommitMessage {
+            height,
+            round,
+            value,
+            precommits,
+            sender_id,
+        }
+    }
+}
+
+/// The steps of a round that are ended by a timeout, and the periodic retransmission of the
+/// messages of the round
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum TimeoutStep {
+    Propose,
+    Prevote,
+    Precommit,
+    Retransmit,
+}
+
+/// Type defining a _timeout_ of `step` in `round` at `height`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct Timeout {
+    pub step: TimeoutStep,
+    pub height: u64,
+    pub round: u64,
+}
+impl Timeout {
+    pub fn new(step: TimeoutStep, height: u64, round: u64) -> Self {
+        Timeout {
+            step,
+            height,
+            round,
+        }
+    }
+}
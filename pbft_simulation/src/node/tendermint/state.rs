@@ -0,0 +1,690 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use log::{debug, warn};
+
+use crate::node::application::{Application, CounterApplication};
+use crate::node::quorum::{QuorumConfig, ReplicaSet};
+use crate::node::request::RequestId;
+use crate::simulation::config::ResultLogger;
+use crate::simulation::results::ResultEvent;
+use crate::simulation::time::Time;
+
+use super::messages::*;
+
+/// The output produced by this module. Consumed by the host running the `ReplicaState`.
+type Output = Vec<(u32, TendermintMessage)>;
+
+/// The proposer rotates through the replicas `1..=num_of_nodes`, with every height and every round.
+fn proposer(height: u64, round: u64, num_of_nodes: u32) -> u32 {
+    ((height.saturating_sub(1) + round) % u64::from(num_of_nodes)) as u32 + 1
+}
+
+/// The step of the current round
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+enum Step {
+    Propose,
+    Prevote,
+    Precommit,
+}
+
+/// The proposal and the votes received for a single round
+#[derive(Debug, Default)]
+struct RoundMessages {
+    proposal: Option<ProposalMessage>,
+    prevotes: HashMap<u32, Option<ClientRequest>>,
+    precommits: HashMap<u32, Option<ClientRequest>>,
+}
+
+impl RoundMessages {
+    /// The replicas that voted for `value` in `step`
+    fn voters(&self, step: VoteStep, value: Option<ClientRequest>) -> ReplicaSet {
+        let mut voters = ReplicaSet::new();
+        for (id, _) in self.votes(step).iter().filter(|(_, v)| **v == value) {
+            voters.insert(*id);
+        }
+        voters
+    }
+
+    /// The replicas that voted for anything in `step`
+    fn all_voters(&self, step: VoteStep) -> ReplicaSet {
+        let mut voters = ReplicaSet::new();
+        for id in self.votes(step).keys() {
+            voters.insert(*id);
+        }
+        voters
+    }
+
+    /// The replicas that sent any message of this round
+    fn senders(&self) -> ReplicaSet {
+        let mut senders = self.all_voters(VoteStep::Prevote);
+        for id in self.precommits.keys() {
+            senders.insert(*id);
+        }
+        if let Some(proposal) = &self.proposal {
+            senders.insert(proposal.sender_id);
+        }
+        senders
+    }
+
+    fn votes(&self, step: VoteStep) -> &HashMap<u32, Option<ClientRequest>> {
+        match step {
+            VoteStep::Prevote => &self.prevotes,
+            VoteStep::Precommit => &self.precommits,
+        }
+    }
+}
+
+/// The type defining the state required for participating in a cluster running Tendermint.
+/// Every height decides a single client request in rounds of propose, prevote and precommit.
+///
+/// A replica only starts a height when its mempool holds a request or another replica is working
+/// on the height, so an idle cluster does not run through empty rounds.
+#[derive(Debug)]
+pub struct ReplicaState {
+    id: u32,
+    /// The fixed number of nodes participating in the cluster.
+    num_of_nodes: u32,
+    /// Holds the IDs of other peers.
+    peers: Vec<u32>,
+    /// The height (sequence number) of the next decision, starting with `1`.
+    height: u64,
+    round: u64,
+    step: Step,
+    /// `false` while the replica waits for something to decide at `height`
+    active: bool,
+    /// The value and the round the replica locked on at `height`
+    locked: Option<(ClientRequest, u64)>,
+    /// The value with a prevote quorum of the most recent round, re-proposed by the replica
+    valid: Option<(ClientRequest, u64)>,
+    /// Proposals and votes by height and round
+    rounds: BTreeMap<(u64, u64), RoundMessages>,
+    /// Whether the timeouts of the prevote and precommit steps were scheduled in `round`
+    prevote_timeout: bool,
+    precommit_timeout: bool,
+    /// Whether the replica saw the prevote quorum for the proposal of `round`
+    valid_in_round: bool,
+    /// Requests waiting to be decided, in the order they arrived
+    mempool: VecDeque<ClientRequest>,
+    /// Requests in the mempool or decided, gossip of them is ignored
+    known: HashSet<RequestId>,
+    decided: HashSet<RequestId>,
+    /// The quorum system of the prevotes and precommits
+    quorum: QuorumConfig,
+    /// Executes the decided requests in the order of their heights.
+    application: Box<dyn Application>,
+    /// The executed requests, in order.
+    executed: Vec<RequestId>,
+    /// The result log the phases of the requests are written to
+    results: ResultLogger,
+    /// The value decided at each height and the round it was decided in
+    decisions: Vec<(ClientRequest, u64)>,
+    /// The latest height whose decision was sent to a peer, by peer
+    helped: HashMap<u32, u64>,
+    /// The senders of the decisions received for `height`, by height and value. A decision is
+    /// taken over once f + 1 replicas sent it, so a single faulty replica cannot forge one.
+    commits: HashMap<(u64, ClientRequest), ReplicaSet>,
+}
+
+impl ReplicaState {
+    /// Creates a new `ReplicaState` waiting for a request at height `1`.
+    ///
+    /// Requires the parameter `num_of_nodes` to be at least `4`, otherwise it
+    /// `panics!` since at least 4 nodes are required for successful operation.
+    pub fn new(id: u32, num_of_nodes: u32) -> Self {
+        if num_of_nodes < 4 {
+            panic!(
+                "Need at least 4 Tendermint nodes but got only {}",
+                num_of_nodes
+            );
+        }
+
+        ReplicaState {
+            id,
+            num_of_nodes,
+            peers: (1..=num_of_nodes).filter(|i| *i != id).collect(),
+            height: 1,
+            round: 0,
+            step: Step::Propose,
+            active: false,
+            locked: None,
+            valid: None,
+            rounds: BTreeMap::new(),
+            prevote_timeout: false,
+            precommit_timeout: false,
+            valid_in_round: false,
+            mempool: VecDeque::new(),
+            known: HashSet::new(),
+            decided: HashSet::new(),
+            quorum: QuorumConfig::new(num_of_nodes),
+            application: Box::new(CounterApplication::default()),
+            executed: Vec::new(),
+            results: ResultLogger::default(),
+            decisions: Vec::new(),
+            helped: HashMap::new(),
+            commits: HashMap::new(),
+        }
+    }
+
+    /// Replaces the standard quorum system.
+    pub fn quorum(mut self, quorum: QuorumConfig) -> Self {
+        self.quorum = quorum;
+        self
+    }
+
+    /// Replaces the default counter application executing the decided requests.
+    pub fn application(mut self, application: Box<dyn Application>) -> Self {
+        self.application = application;
+        self
+    }
+
+    /// Writes the phases of the requests to `results` instead of discarding them.
+    pub fn result_logger(mut self, results: ResultLogger) -> Self {
+        self.results = results;
+        self
+    }
+
+    /// The height of the next decision.
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    /// The round the replica is in at the current height.
+    pub fn round(&self) -> u64 {
+        self.round
+    }
+
+    /// The executed requests, in the order of their execution.
+    pub fn executed(&self) -> &[RequestId] {
+        &self.executed
+    }
+
+    /// Digest of the application state after executing all decided requests.
+    pub fn digest(&self) -> u64 {
+        self.application.digest()
+    }
+
+    /// Single exposed function that acts as a entry point for handling incoming
+    /// messages by peers or clients and the timeouts requested by the replica.
+    pub fn handle_message(&mut self, message: TendermintMessage, time: Time) -> Option<Output> {
+        let mut output = Output::new();
+
+        match message {
+            TendermintMessage::ClientRequest(m) => {
+                if !self.add_to_mempool(m) {
+                    return None;
+                }
+                self.results
+                    .log(time, Some(self.id), &ResultEvent::Request(m.id.into()));
+                output.extend(
+                    self.peers
+                        .iter()
+                        .map(|id| (*id, TendermintMessage::Gossip(m))),
+                );
+            }
+            TendermintMessage::Gossip(m) => {
+                if !self.add_to_mempool(m) {
+                    return None;
+                }
+            }
+            TendermintMessage::Proposal(m) => {
+                if m.height < self.height {
+                    return self.help_catch_up(m.sender_id, m.height, m.round);
+                }
+                if m.sender_id != proposer(m.height, m.round, self.num_of_nodes) {
+                    warn!(target: "node", "Tendermint {} received a proposal from non-proposer {}", self.id, m.sender_id);
+                    return None;
+                }
+                let round = self.rounds.entry((m.height, m.round)).or_default();
+                if round.proposal.is_some() {
+                    return None;
+                }
+                round.proposal = Some(m);
+            }
+            TendermintMessage::Vote(m) => {
+                if m.height < self.height {
+                    return self.help_catch_up(m.sender_id, m.height, m.round);
+                }
+                let round = self.rounds.entry((m.height, m.round)).or_default();
+                let votes = match m.step {
+                    VoteStep::Prevote => &mut round.prevotes,
+                    VoteStep::Precommit => &mut round.precommits,
+                };
+                if votes.contains_key(&m.sender_id) {
+                    return None;
+                }
+                votes.insert(m.sender_id, m.value);
+            }
+            TendermintMessage::Commit(m) => {
+                if m.height != self.height || !self.is_valid(&m.value) {
+                    return None;
+                }
+                if (m.precommits as usize) < self.quorum.quorum_size() {
+                    warn!(target: "node", "Tendermint {} received a decision without precommit quorum from {}", self.id, m.sender_id);
+                    return None;
+                }
+                let senders = self.commits.entry((m.height, m.value)).or_default();
+                if !senders.insert(m.sender_id) || senders.len() as u32 <= self.quorum.f() {
+                    return None;
+                }
+                self.decide(m.value, m.round, time);
+            }
+            TendermintMessage::Timeout(m) => self.handle_timeout(m, &mut output),
+        }
+
+        self.evaluate(&mut output, time);
+
+        match output.len() {
+            0 => None,
+            _ => Some(output),
+        }
+    }
+
+    /// A replica still in the deciding round of `height` or later may have missed the decision, it
+    /// gets the decision with its precommit certificate. Each peer is answered once per height.
+    fn help_catch_up(&mut self, sender_id: u32, height: u64, round: u64) -> Option<Output> {
+        // there is no decision of height 0
+        let (value, decided_round) = *self.decisions.get(height.checked_sub(1)? as usize)?;
+        if round < decided_round || self.helped.get(&sender_id) >= Some(&height) {
+            return None;
+        }
+        self.helped.insert(sender_id, height);
+
+        debug!(target: "node", "Tendermint {} sends the decision of height {} to {}", self.id, height, sender_id);
+        let commit = CommitMessage::new(
+            height,
+            decided_round,
+            value,
+            self.quorum.quorum_size() as u32,
+            self.id,
+        );
+        Some(vec![(sender_id, TendermintMessage::Commit(commit))])
+    }
+
+    /// Adds a new request to the mempool, returns `false` if it was known already
+    fn add_to_mempool(&mut self, c_req: ClientRequest) -> bool {
+        if !self.known.insert(c_req.id) {
+            return false;
+        }
+        self.mempool.push_back(c_req);
+        true
+    }
+
+    /// Only undecided requests may be decided
+    fn is_valid(&self, value: &ClientRequest) -> bool {
+        !self.decided.contains(&value.id)
+    }
+
+    fn is_proposer(&self) -> bool {
+        self.id == proposer(self.height, self.round, self.num_of_nodes)
+    }
+
+    fn current(&self) -> Option<&RoundMessages> {
+        self.rounds.get(&(self.height, self.round))
+    }
+
+    fn handle_timeout(&mut self, timeout: Timeout, output: &mut Output) {
+        if !self.active || timeout.height != self.height || timeout.round != self.round {
+            return;
+        }
+
+        match (timeout.step, self.step) {
+            (TimeoutStep::Propose, Step::Propose) => self.vote(VoteStep::Prevote, None, output),
+            (TimeoutStep::Prevote, Step::Prevote) => self.vote(VoteStep::Precommit, None, output),
+            (TimeoutStep::Precommit, _) => self.start_round(self.round + 1, output),
+            (TimeoutStep::Retransmit, _) => self.retransmit(output),
+            _ => {}
+        }
+    }
+
+    /// Sends the proposal and votes of the replica in the current round again, so a round stalled
+    /// by lost messages completes eventually. Repeats until the replica leaves the round.
+    fn retransmit(&mut self, output: &mut Output) {
+        let (height, round) = (self.height, self.round);
+        if let Some(messages) = self.current() {
+            let mut own = Vec::new();
+            if let Some(proposal) = messages.proposal.filter(|p| p.sender_id == self.id) {
+                own.push(TendermintMessage::Proposal(proposal));
+            }
+            for step in [VoteStep::Prevote, VoteStep::Precommit].iter() {
+                if let Some(value) = messages.votes(*step).get(&self.id) {
+                    own.push(TendermintMessage::Vote(VoteMessage::new(
+                        *step, height, round, *value, self.id,
+                    )));
+                }
+            }
+            for message in own {
+                output.extend(self.peers.iter().map(|id| (*id, message.clone())));
+            }
+        }
+
+        output.push((
+            self.id,
+            TendermintMessage::Timeout(Timeout::new(TimeoutStep::Retransmit, height, round)),
+        ));
+    }
+
+    /// Enters `round` of the current height and proposes if the replica is its proposer
+    fn start_round(&mut self, round: u64, output: &mut Output) {
+        debug!(target: "node", "Tendermint {} starts round {} of height {}", self.id, round, self.height);
+
+        self.active = true;
+        self.round = round;
+        self.step = Step::Propose;
+        self.prevote_timeout = false;
+        self.precommit_timeout = false;
+        self.valid_in_round = false;
+
+        output.push((
+            self.id,
+            TendermintMessage::Timeout(Timeout::new(TimeoutStep::Propose, self.height, round)),
+        ));
+        output.push((
+            self.id,
+            TendermintMessage::Timeout(Timeout::new(TimeoutStep::Retransmit, self.height, round)),
+        ));
+        self.propose(output);
+    }
+
+    /// Proposes the valid value or the oldest request of the mempool, a proposer with an empty
+    /// mempool proposes as soon as a request arrives
+    fn propose(&mut self, output: &mut Output) {
+        if !self.is_proposer() || self.step != Step::Propose {
+            return;
+        }
+        if self.current().is_some_and(|round| round.proposal.is_some()) {
+            return;
+        }
+
+        let (value, valid_round) = match (self.valid, self.mempool.front()) {
+            (Some((value, round)), _) => (value, Some(round)),
+            (None, Some(value)) => (*value, None),
+            (None, None) => return,
+        };
+
+        let proposal = ProposalMessage::new(self.height, self.round, value, valid_round, self.id);
+        self.rounds
+            .entry((self.height, self.round))
+            .or_default()
+            .proposal = Some(proposal);
+        output.extend(
+            self.peers
+                .iter()
+                .map(|id| (*id, TendermintMessage::Proposal(proposal))),
+        );
+    }
+
+    /// Casts the vote of the replica in the current round and moves on to the next step
+    fn vote(&mut self, step: VoteStep, value: Option<ClientRequest>, output: &mut Output) {
+        let vote = VoteMessage::new(step, self.height, self.round, value, self.id);
+        let round = self.rounds.entry((self.height, self.round)).or_default();
+        match step {
+            VoteStep::Prevote => {
+                round.prevotes.insert(self.id, value);
+                self.step = Step::Prevote;
+            }
+            VoteStep::Precommit => {
+                round.precommits.insert(self.id, value);
+                self.step = Step::Precommit;
+            }
+        }
+
+        output.extend(
+            self.peers
+                .iter()
+                .map(|id| (*id, TendermintMessage::Vote(vote))),
+        );
+    }
+
+    /// Applies the rules of the protocol until none of them applies anymore
+    fn evaluate(&mut self, output: &mut Output, time: Time) {
+        loop {
+            if !self.active {
+                let height_started = self.rounds.keys().any(|(height, _)| *height == self.height);
+                if self.mempool.is_empty() && !height_started {
+                    return;
+                }
+                self.start_round(0, output);
+            }
+
+            if let Some((value, round)) = self.decision() {
+                self.decide(value, round, time);
+                continue;
+            }
+
+            if let Some(round) = self.skip_round() {
+                self.start_round(round, output);
+                continue;
+            }
+
+            if !self.evaluate_round(output) {
+                return;
+            }
+        }
+    }
+
+    /// A value with a precommit quorum in any round of the current height, and that round
+    fn decision(&self) -> Option<(ClientRequest, u64)> {
+        let height = self.height;
+        self.rounds
+            .range((height, 0)..=(height, u64::MAX))
+            .flat_map(|((_, r), round)| {
+                round
+                    .precommits
+                    .values()
+                    .filter_map(|value| *value)
+                    .filter(move |value| {
+                        self.quorum
+                            .is_quorum_set(&round.voters(VoteStep::Precommit, Some(*value)))
+                    })
+                    .map(move |value| (value, *r))
+            })
+            .find(|(value, _)| self.is_valid(value))
+    }
+
+    /// A later round of the current height that `f + 1` replicas are in already
+    fn skip_round(&self) -> Option<u64> {
+        self.rounds
+            .range((self.height, self.round + 1)..=(self.height, u64::MAX))
+            .find(|(_, round)| round.senders().len() as u32 > self.quorum.f())
+            .map(|((_, round), _)| *round)
+    }
+
+    /// Applies the rules of the current round, returns `true` if the state changed
+    fn evaluate_round(&mut self, output: &mut Output) -> bool {
+        let step = self.step;
+        let round = self.round;
+        self.propose(output);
+
+        let (proposal, prevotes_any, prevotes_nil, precommits_any, proposal_prevoted) = {
+            let messages = match self.current() {
+                Some(messages) => messages,
+                None => return false,
+            };
+            let proposal = messages.proposal;
+            (
+                proposal,
+                self.quorum
+                    .is_quorum_set(&messages.all_voters(VoteStep::Prevote)),
+                self.quorum
+                    .is_quorum_set(&messages.voters(VoteStep::Prevote, None)),
+                self.quorum
+                    .is_quorum_set(&messages.all_voters(VoteStep::Precommit)),
+                proposal.is_some_and(|p| {
+                    self.quorum
+                        .is_quorum_set(&messages.voters(VoteStep::Prevote, Some(p.value)))
+                }),
+            )
+        };
+
+        if self.step == Step::Propose {
+            if let Some(proposal) = proposal {
+                let value = proposal.value;
+                match proposal.valid_round {
+                    None => {
+                        let acceptable = self.is_valid(&value)
+                            && self.locked.is_none_or(|(locked, _)| locked == value);
+                        self.vote(
+                            VoteStep::Prevote,
+                            Some(value).filter(|_| acceptable),
+                            output,
+                        );
+                    }
+                    Some(valid_round) if valid_round < round => {
+                        let quorum_in_valid_round = self
+                            .rounds
+                            .get(&(self.height, valid_round))
+                            .is_some_and(|r| {
+                                self.quorum
+                                    .is_quorum_set(&r.voters(VoteStep::Prevote, Some(value)))
+                            });
+                        if quorum_in_valid_round {
+                            let acceptable = self.is_valid(&value)
+                                && self.locked.is_none_or(|(locked, locked_round)| {
+                                    locked_round <= valid_round || locked == value
+                                });
+                            self.vote(
+                                VoteStep::Prevote,
+                                Some(value).filter(|_| acceptable),
+                                output,
+                            );
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if self.step == Step::Prevote && prevotes_any && !self.prevote_timeout {
+            self.prevote_timeout = true;
+            output.push((
+                self.id,
+                TendermintMessage::Timeout(Timeout::new(TimeoutStep::Prevote, self.height, round)),
+            ));
+        }
+
+        if self.step >= Step::Prevote && proposal_prevoted && !self.valid_in_round {
+            let value = proposal.expect("a prevoted proposal").value;
+            if self.is_valid(&value) {
+                self.valid_in_round = true;
+                if self.step == Step::Prevote {
+                    self.locked = Some((value, round));
+                    self.vote(VoteStep::Precommit, Some(value), output);
+                }
+                self.valid = Some((value, round));
+            }
+        }
+
+        if self.step == Step::Prevote && prevotes_nil {
+            self.vote(VoteStep::Precommit, None, output);
+        }
+
+        if precommits_any && !self.precommit_timeout {
+            self.precommit_timeout = true;
+            output.push((
+                self.id,
+                TendermintMessage::Timeout(Timeout::new(
+                    TimeoutStep::Precommit,
+                    self.height,
+                    round,
+                )),
+            ));
+        }
+
+        self.step != step
+    }
+
+    /// Executes `value`, decided in `round`, and moves on to the next height
+    fn decide(&mut self, value: ClientRequest, round: u64, time: Time) {
+        self.results.log(
+            time,
+            Some(self.id),
+            &ResultEvent::CommittedLocal(value.id.into()),
+        );
+        self.results
+            .log(time, Some(self.id), &ResultEvent::Executed(value.id.into()));
+
+        self.application.execute(value.operation);
+        self.executed.push(value.id);
+        self.decisions.push((value, round));
+        self.decided.insert(value.id);
+        self.known.insert(value.id);
+        self.mempool.retain(|c_req| c_req.id != value.id);
+
+        self.height += 1;
+        self.round = 0;
+        self.step = Step::Propose;
+        self.active = false;
+        self.locked = None;
+        self.valid = None;
+        let height = self.height;
+        self.rounds.retain(|(h, _), _| *h >= height);
+        self.commits.retain(|(h, _), _| *h >= height);
+    }
+}
+
+/*******************************************************************************
+ * TESTS
+ ******************************************************************************/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decision_is_taken_over_from_f_plus_one_replicas() {
+        let mut replica = ReplicaState::new(1, 4);
+        let c_req = ClientRequest::new(RequestId::new(0, 1), 1);
+        let commit = |precommits, sender_id| {
+            TendermintMessage::Commit(CommitMessage::new(1, 0, c_req, precommits, sender_id))
+        };
+
+        // a single replica may forge a decision
+        replica.handle_message(commit(3, 2), Time::new(1));
+        replica.handle_message(commit(3, 2), Time::new(1));
+        assert_eq!(replica.height(), 1);
+        // a decision without precommit quorum is no certificate
+        replica.handle_message(commit(2, 3), Time::new(1));
+        assert_eq!(replica.height(), 1);
+
+        replica.handle_message(commit(3, 4), Time::new(1));
+        assert_eq!(replica.height(), 2);
+        assert_eq!(replica.executed(), &[c_req.id]);
+    }
+
+    #[test]
+    fn messages_of_height_zero_are_ignored() {
+        let mut replica = ReplicaState::new(1, 4);
+        let vote = VoteMessage::new(VoteStep::Prevote, 0, 0, None, 2);
+        let c_req = ClientRequest::new(RequestId::new(0, 1), 1);
+        let proposal = ProposalMessage::new(0, 0, c_req, None, 2);
+
+        assert_eq!(
+            replica.handle_message(TendermintMessage::Vote(vote), Time::new(1)),
+            None
+        );
+        assert_eq!(
+            replica.handle_message(TendermintMessage::Proposal(proposal), Time::new(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn idle_replica_does_not_start_rounds() {
+        let mut replica = ReplicaState::new(1, 4);
+        let timeout = Timeout::new(TimeoutStep::Precommit, 1, 0);
+
+        assert_eq!(
+            replica.handle_message(TendermintMessage::Timeout(timeout), Time::new(1)),
+            None
+        );
+        assert_eq!(replica.round(), 0);
+    }
+
+    #[test]
+    fn proposer_rotates() {
+        assert_eq!(proposer(1, 0, 4), 1);
+        assert_eq!(proposer(1, 1, 4), 2);
+        assert_eq!(proposer(2, 0, 4), 2);
+        assert_eq!(proposer(4, 1, 4), 1);
+    }
+}
@@ -0,0 +1,164 @@
+/***************************************************************************************************
+Local clocks and timers of the nodes.
+***************************************************************************************************/
+
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use crate::simulation::event::{Event, TimerFired, TimerRequest};
+use crate::simulation::settings::NodeSettings;
+use crate::simulation::time::{Duration, Time};
+
+/// The local clock of a node. It deviates from the global simulation time by a fixed offset (ms)
+/// and runs faster (positive drift) or slower (negative drift) than the global time, which
+/// stretches or shrinks the timers of the node.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Clock {
+    offset: i64,
+    drift: f64,
+}
+
+impl Clock {
+    pub fn new(offset: i64, drift: f64) -> Self {
+        Clock { offset, drift }
+    }
+
+    /// Creates a clock with offset and drift drawn uniformly from the bounds of the settings, the
+    /// random numbers are drawn from `rng`, e.g. the generator of a seeded run
+    pub fn random_with<R: Rng>(settings: &NodeSettings, rng: &mut R) -> Self {
+        let offset_max = settings.clock_offset_max;
+        let drift_max = settings.clock_drift_max;
+
+        let offset = if offset_max > 0 {
+            rng.gen_range(-offset_max, offset_max + 1)
+        } else {
+            0
+        };
+        let drift = if drift_max > 0.0 {
+            rng.gen_range(-drift_max, drift_max)
+        } else {
+            0.0
+        };
+
+        Clock::new(offset, drift)
+    }
+
+    /// Reads the local clock at the global `time`, a clock behind the global time reads 0 until
+    /// it starts
+    pub fn local_time(&self, time: Time) -> Time {
+        let local = time.micros() as f64 * (1.0 + self.drift) + self.offset as f64 * 1000.0;
+        Time::from_micros(local.max(0.0).round() as u64)
+    }
+
+    /// Global time (ms) that passes until the local clock advanced by `duration` ms
+    pub fn global_duration(&self, duration: u64) -> u64 {
+        (duration as f64 / (1.0 + self.drift)).round() as u64
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::new(0, 0.0)
+    }
+}
+
+/// Bookkeeping of the timers of all nodes. A timer is identified by the node and a token chosen by
+/// the node, setting a timer again with the same token replaces the previous one.
+#[derive(Debug, Default)]
+pub struct Timers {
+    clocks: HashMap<u32, Clock>,
+    // generation of each active timer, so replaced timers can be told apart from the current one
+    active: HashMap<(u32, u64), u64>,
+    next_generation: u64,
+}
+
+impl Timers {
+    /// Sets the local clock of the node `id`, nodes without a clock use the global time
+    pub fn add_clock(&mut self, id: u32, clock: Clock) {
+        self.clocks.insert(id, clock);
+    }
+
+    /// Returns the local clock of the node `id`
+    pub fn clock(&self, id: u32) -> Clock {
+        self.clocks.get(&id).copied().unwrap_or_default()
+    }
+
+    /// Registers the requested timer and returns the event firing it once the timer expired on
+    /// the local clock of the node
+    pub fn set_timer(&mut self, request: TimerRequest, time: Time) -> Event {
+        self.next_generation += 1;
+        self.active
+            .insert((request.id, request.token), self.next_generation);
+
+        let expiry =
+            time + Duration::from_millis(self.clock(request.id).global_duration(request.duration));
+        Event::new_timer_fired(request.id, request.token, self.next_generation, expiry)
+    }
+
+    /// Cancels the timer, returns `false` if it was not active anymore
+    pub fn cancel_timer(&mut self, id: u32, token: u64) -> bool {
+        self.active.remove(&(id, token)).is_some()
+    }
+
+    /// Called for an expired timer. Returns `false` if the timer was cancelled or replaced in the
+    /// meantime, i.e. the node must not be notified.
+    pub fn fire(&mut self, fired: &TimerFired) -> bool {
+        match self.active.get(&(fired.id, fired.token)) {
+            Some(generation) if *generation == fired.generation => {
+                self.active.remove(&(fired.id, fired.token));
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::event::EventType;
+
+    #[test]
+    fn check_clock_skew() {
+        let clock = Clock::new(-5, 0.1);
+        assert_eq!(clock.local_time(Time::new(100)), Time::new(105));
+        assert_eq!(clock.global_duration(110), 100);
+        assert_eq!(Clock::new(-5, 0.0).local_time(Time::new(1)), Time::new(0));
+        assert_eq!(Clock::new(0, -0.5).global_duration(50), 100);
+    }
+
+    fn fired(event: Event) -> TimerFired {
+        match event.event_type {
+            EventType::TimerFired(fired) => fired,
+            _ => panic!("Setting a timer must return a TimerFired event"),
+        }
+    }
+
+    #[test]
+    fn check_timer_uses_local_clock() {
+        let mut timers = Timers::default();
+        timers.add_clock(1, Clock::new(0, 0.25));
+
+        let event = timers.set_timer(TimerRequest::new(1, 7, 500), Time::new(100));
+        assert_eq!(event.time, Time::new(500));
+
+        let event = timers.set_timer(TimerRequest::new(2, 7, 500), Time::new(100));
+        assert_eq!(event.time, Time::new(600));
+    }
+
+    #[test]
+    fn check_cancelled_and_replaced_timers_do_not_fire() {
+        let mut timers = Timers::default();
+
+        let cancelled = fired(timers.set_timer(TimerRequest::new(1, 1, 400), Time::new(100)));
+        let replaced = fired(timers.set_timer(TimerRequest::new(1, 2, 400), Time::new(100)));
+        let current = fired(timers.set_timer(TimerRequest::new(1, 2, 400), Time::new(200)));
+
+        assert!(timers.cancel_timer(1, 1));
+        assert!(!timers.fire(&cancelled));
+        assert!(!timers.fire(&replaced));
+        assert!(timers.fire(&current));
+        assert!(!timers.fire(&current));
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::{HashMap, HashSet};
+
+use log::warn;
+
+use super::messages::*;
+use crate::node::quorum::{QuorumConfig, ReplicaSet};
+use crate::node::request::RequestId;
+use crate::simulation::config::ResultLogger;
+use crate::simulation::results::ResultEvent;
+use crate::simulation::time::Time;
+
+type Output = Vec<(u32, ZyzzyvaMessage)>;
+
+/// A request of the client that is not completed yet.
+#[derive(Debug)]
+struct PendingRequest {
+    commit_certificate: HashSet<SpeculativeResponse>,
+    local_commits: ReplicaSet,
+    timed_out: bool,
+}
+
+/// The Zyzzyva client. It receives the requests from the simulation, hands them to the primary
+/// and completes them with the speculative responses, or with the local commits of the replicas
+/// once it sent them a commit certificate.
+#[derive(Debug)]
+pub struct Client {
+    id: u32,
+    pending: HashMap<RequestId, PendingRequest>,
+    /// Completed requests, subsequent messages about them are ignored.
+    completed: HashSet<RequestId>,
+    num_of_replicas: u32,
+    current_view: u64,
+    replicas: Vec<u32>,
+    quorum_size: usize,
+    /// Whether the replicas execute speculatively, otherwise every request needs a commit
+    /// certificate.
+    speculative: bool,
+    /// The result log the phases of the requests are written to
+    results: ResultLogger,
+}
+
+impl Client {
+    /// Creates the client `id` of a cluster of the replicas `1..=num_of_replicas`.
+    pub fn new(id: u32, num_of_replicas: u32) -> Self {
+        Client {
+            id,
+            pending: HashMap::new(),
+            completed: HashSet::new(),
+            num_of_replicas,
+            current_view: 1,
+            replicas: (1..=num_of_replicas).collect(),
+            quorum_size: QuorumConfig::new(num_of_replicas).quorum_size(),
+            speculative: true,
+            results: ResultLogger::default(),
+        }
+    }
+
+    /// Writes the phases of the requests to `results` instead of discarding them.
+    pub fn result_logger(mut self, results: ResultLogger) -> Self {
+        self.results = results;
+        self
+    }
+
+    /// Without speculative execution the client always collects a commit certificate.
+    pub fn speculative_execution(mut self, speculative: bool) -> Self {
+        self.speculative = speculative;
+        self
+    }
+
+    /// Replaces the standard quorum system of the replicas.
+    pub fn quorum(mut self, quorum: QuorumConfig) -> Self {
+        self.quorum_size = quorum.quorum_size();
+        self
+    }
+
+    pub fn handle_message(
+        &mut self,
+        zyzzyva_message: ZyzzyvaMessage,
+        time: Time,
+    ) -> Option<Output> {
+        match zyzzyva_message {
+            ZyzzyvaMessage::ClientRequest(m) => self.handle_client_request(m),
+            ZyzzyvaMessage::ClientTimeout(m) if !self.completed.contains(&m.req_id) => {
+                self.handle_client_timeout(m, time)
+            }
+            ZyzzyvaMessage::SpeculativeResponse(m) if !self.completed.contains(&m.c_req.id) => {
+                self.handle_speculative_response(m, time)
+            }
+            ZyzzyvaMessage::LocalCommit(m) if !self.completed.contains(&m.c_req.id) => {
+                self.handle_local_commit(m, time)
+            }
+            ZyzzyvaMessage::OrderRequest(_) | ZyzzyvaMessage::Commit(_) => {
+                warn!(target: "node", "Client {} dropped a {} message, which is meant for the replicas", self.id, zyzzyva_message.message_type());
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Gets the `id` of the primary for the current view.
+    fn curr_primary(&self) -> u32 {
+        (self.current_view % (self.num_of_replicas as u64)) as u32
+    }
+
+    fn complete(&mut self, req_id: RequestId, time: Time) {
+        self.results
+            .log(time, Some(self.id), &ResultEvent::Completed(req_id.into()));
+        self.pending.remove(&req_id);
+        self.completed.insert(req_id);
+    }
+
+    // the commit certificate of the request for all replicas
+    fn commit(&self, req_id: RequestId, certificate: Vec<SpeculativeResponse>) -> Output {
+        let commit = ZyzzyvaMessage::Commit(Commit::new(req_id, certificate, self.id));
+        self.replicas
+            .iter()
+            .map(|id| (*id, commit.clone()))
+            .collect()
+    }
+
+    /// The simulation hands a request to the client, which sends it to the primary and times it.
+    fn handle_client_request(&mut self, msg_in: ClientRequest) -> Option<Output> {
+        let request = ClientRequest::new(msg_in.id, msg_in.operation);
+        self.pending.insert(
+            msg_in.id,
+            PendingRequest {
+                commit_certificate: HashSet::new(),
+                local_commits: ReplicaSet::new(),
+                timed_out: false,
+            },
+        );
+
+        Some(vec![
+            (self.curr_primary(), ZyzzyvaMessage::ClientRequest(request)),
+            // add a timeout event for the client itself.
+            (
+                self.id,
+                ZyzzyvaMessage::ClientTimeout(ClientTimeout::new(msg_in.id)),
+            ),
+        ])
+    }
+
+    fn handle_client_timeout(&mut self, msg_in: ClientTimeout, time: Time) -> Option<Output> {
+        let entry = self.pending.get_mut(&msg_in.req_id)?;
+        entry.timed_out = true;
+        let cert_len = entry.commit_certificate.len();
+
+        // Zyzzyva 4.b, without speculative execution the commit may have been lost
+        if cert_len >= self.quorum_size && (cert_len < self.replicas.len() || !self.speculative) {
+            let certificate = entry.commit_certificate.iter().copied().collect();
+            return Some(self.commit(msg_in.req_id, certificate));
+        }
+
+        // Zyzzyva 4.c
+        if cert_len < self.quorum_size {
+            self.results.log(
+                time,
+                Some(self.id),
+                &ResultEvent::TimedOut(msg_in.req_id.into()),
+            );
+        }
+        None
+    }
+
+    fn handle_speculative_response(
+        &mut self,
+        msg_in: SpeculativeResponse,
+        time: Time,
+    ) -> Option<Output> {
+        let entry = match self.pending.get_mut(&msg_in.c_req.id) {
+            Some(entry) => entry,
+            None => {
+                warn!(target: "node", "Client {} dropped a speculative response to request {}, which it did not send", self.id, msg_in.c_req.id);
+                return None;
+            }
+        };
+        // in case we timed-out we only accept commit messages for the associated request
+        if entry.timed_out {
+            return None;
+        }
+
+        let cert = &mut entry.commit_certificate;
+        cert.insert(msg_in);
+
+        if cert.len() == self.quorum_size {
+            self.results.log(
+                time,
+                Some(self.id),
+                &ResultEvent::CommitCertificate(msg_in.c_req.id.into()),
+            );
+
+            // without speculative execution the responses carry no result, the request completes
+            // only with the commit certificate
+            if !self.speculative {
+                let certificate = cert.iter().copied().collect();
+                return Some(self.commit(msg_in.c_req.id, certificate));
+            }
+        }
+
+        // Zyzzyva 4.a
+        if cert.len() == self.num_of_replicas as usize && self.speculative {
+            self.complete(msg_in.c_req.id, time);
+        }
+        None
+    }
+
+    fn handle_local_commit(&mut self, msg_in: LocalCommit, time: Time) -> Option<Output> {
+        let entry = match self.pending.get_mut(&msg_in.c_req.id) {
+            Some(entry) => entry,
+            None => {
+                warn!(target: "node", "Client {} dropped a local commit of request {}, which it did not send", self.id, msg_in.c_req.id);
+                return None;
+            }
+        };
+        entry.local_commits.insert(msg_in.sender_id);
+
+        if entry.local_commits.len() >= self.quorum_size {
+            self.complete(msg_in.c_req.id, time);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_stray_messages() {
+        let mut client = Client::new(5, 4);
+        let c_req = ClientRequest::new(RequestId::new(5, 1), 1);
+
+        // messages meant for the replicas
+        let order_request = OrderRequest::new(c_req, 1, 1, 1);
+        assert_eq!(
+            client.handle_message(ZyzzyvaMessage::OrderRequest(order_request), Time::new(10)),
+            None
+        );
+        let commit = Commit::new(c_req.id, Vec::new(), 2);
+        assert_eq!(
+            client.handle_message(ZyzzyvaMessage::Commit(commit), Time::new(10)),
+            None
+        );
+
+        // responses to a request the client did not send
+        let response = SpeculativeResponse::new(c_req, 1, 1, 2);
+        assert_eq!(
+            client.handle_message(ZyzzyvaMessage::SpeculativeResponse(response), Time::new(20)),
+            None
+        );
+        let local_commit = LocalCommit::new(c_req, 1, 1, 2);
+        assert_eq!(
+            client.handle_message(ZyzzyvaMessage::LocalCommit(local_commit), Time::new(30)),
+            None
+        );
+        assert!(client.pending.is_empty());
+        assert!(client.completed.is_empty());
+    }
+}
@@ -0,0 +1,245 @@
+/***************************************************************************************************
+Execution model of a Zyzzyva replica. With speculative execution a replica executes a request as
+soon as the primary orders it and keeps the speculative history apart from the final state, which a
+commit certificate establishes. A conflicting order invalidates the speculation: the replica rolls
+back to the final state and re-executes the surviving prefix of its history. Without speculative
+execution requests are executed once they are final, nothing is ever rolled back.
+***************************************************************************************************/
+
+use std::collections::BTreeMap;
+
+use crate::node::application::Application;
+use crate::node::request::RequestId;
+
+use super::messages::ClientRequest;
+
+#[derive(Debug)]
+pub struct Execution {
+    speculative: bool,
+    /// The state after executing all requests up to `last_executed`
+    application: Box<dyn Application>,
+    /// The state after executing all final requests, only kept with speculative execution
+    final_state: Box<dyn Application>,
+    /// The requests by sequence number, as ordered by the primary or a commit certificate
+    ordered: BTreeMap<u64, ClientRequest>,
+    /// The sequence number up to which all requests are executed.
+    last_executed: u64,
+    /// Requests up to this sequence number are final
+    final_up_to: u64,
+    /// The sequence number up to which `final_state` is executed
+    final_executed: u64,
+    /// The executed requests, in order.
+    executed: Vec<RequestId>,
+    /// Number of executions rolled back since the last call of `take_rolled_back`
+    rolled_back: u64,
+}
+
+impl Execution {
+    pub fn new(application: Box<dyn Application>, speculative: bool) -> Self {
+        Execution {
+            speculative,
+            final_state: application.snapshot(),
+            application,
+            ordered: BTreeMap::new(),
+            last_executed: 0,
+            final_up_to: 0,
+            final_executed: 0,
+            executed: Vec::new(),
+            rolled_back: 0,
+        }
+    }
+
+    pub fn is_speculative(&self) -> bool {
+        self.speculative
+    }
+
+    /// Switches speculative execution on or off, before anything is executed.
+    pub fn set_speculative(&mut self, speculative: bool) {
+        assert_eq!(
+            self.last_executed, 0,
+            "the execution model is in use already"
+        );
+        self.speculative = speculative;
+    }
+
+    /// The sequence number up to which all requests are executed.
+    pub fn last_executed(&self) -> u64 {
+        self.last_executed
+    }
+
+    /// The executed requests, in the order of their execution.
+    pub fn executed(&self) -> &[RequestId] {
+        &self.executed
+    }
+
+    /// Digest of the application state after executing all requests up to `last_executed`.
+    pub fn digest(&self) -> u64 {
+        self.application.digest()
+    }
+
+    /// Returns the number of executions rolled back since the last call and resets it.
+    pub fn take_rolled_back(&mut self) -> u64 {
+        std::mem::replace(&mut self.rolled_back, 0)
+    }
+
+    /// The primary ordered `c_req` at `seq_number`. A different request ordered at the same
+    /// sequence number before is invalidated, together with everything ordered after it. Returns
+    /// the requests that were rolled back.
+    pub fn order(&mut self, seq_number: u64, c_req: ClientRequest) -> Vec<RequestId> {
+        if seq_number <= self.final_up_to {
+            // final requests are never replaced, but a missing one may still arrive
+            if seq_number > self.last_executed {
+                self.ordered.entry(seq_number).or_insert(c_req);
+                self.advance();
+            }
+            return Vec::new();
+        }
+
+        let undone = match self.ordered.get(&seq_number) {
+            Some(ordered) if *ordered != c_req => self.rollback(seq_number),
+            _ => Vec::new(),
+        };
+        self.ordered.insert(seq_number, c_req);
+        self.advance();
+        undone
+    }
+
+    /// A commit certificate for `c_req` at `seq_number` makes it and all its predecessors final.
+    /// Returns the requests that were rolled back.
+    pub fn finalize(&mut self, seq_number: u64, c_req: ClientRequest) -> Vec<RequestId> {
+        let undone = self.order(seq_number, c_req);
+        self.final_up_to = self.final_up_to.max(seq_number);
+        self.advance();
+        undone
+    }
+
+    /// Rolls back all executions that are not final, e.g. when a view change discards the
+    /// speculative history. Returns the requests that were rolled back.
+    pub fn rollback_speculation(&mut self) -> Vec<RequestId> {
+        self.rollback(self.final_up_to + 1)
+    }
+
+    /// Executes the ordered requests in sequence, with speculative execution up to the last
+    /// consecutive one, otherwise only up to the last final one.
+    fn advance(&mut self) {
+        let limit = if self.speculative {
+            u64::MAX
+        } else {
+            self.final_up_to
+        };
+
+        while self.last_executed < limit {
+            let c_req = match self.ordered.get(&(self.last_executed + 1)) {
+                Some(c_req) => *c_req,
+                None => break,
+            };
+            self.application.execute(c_req.operation);
+            self.executed.push(c_req.id);
+            self.last_executed += 1;
+        }
+
+        if self.speculative {
+            while self.final_executed < self.final_up_to.min(self.last_executed) {
+                self.final_executed += 1;
+                let c_req = self.ordered[&self.final_executed];
+                self.final_state.execute(c_req.operation);
+            }
+        }
+        // executed requests are kept only while they may have to be re-executed
+        let keep_from = if self.speculative {
+            self.final_executed + 1
+        } else {
+            self.last_executed + 1
+        };
+        self.ordered = self.ordered.split_off(&keep_from);
+    }
+
+    /// Discards the requests ordered at `seq_number` and after, and restores the state before
+    /// `seq_number`. Returns the requests that were rolled back.
+    fn rollback(&mut self, seq_number: u64) -> Vec<RequestId> {
+        let seq_number = seq_number.max(self.final_executed + 1);
+        self.ordered.split_off(&seq_number);
+        if self.last_executed < seq_number {
+            return Vec::new();
+        }
+
+        let undone = self
+            .executed
+            .split_off(self.executed.len() - (self.last_executed - seq_number + 1) as usize);
+        self.application = self.final_state.snapshot();
+        for c_req in self.ordered.values() {
+            self.application.execute(c_req.operation);
+        }
+        self.last_executed = seq_number - 1;
+        self.rolled_back += undone.len() as u64;
+
+        undone
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::application::CounterApplication;
+
+    fn c_req(seq: u32) -> ClientRequest {
+        ClientRequest::new(RequestId::new(2, seq), seq)
+    }
+
+    fn execution(speculative: bool) -> Execution {
+        Execution::new(Box::new(CounterApplication::default()), speculative)
+    }
+
+    #[test]
+    fn conflicting_order_rolls_back_the_speculation() {
+        let mut reference = execution(true);
+        reference.order(1, c_req(1));
+        reference.order(2, c_req(5));
+
+        let mut execution = execution(true);
+        execution.order(1, c_req(1));
+        execution.finalize(1, c_req(1));
+        execution.order(2, c_req(2));
+        execution.order(3, c_req(3));
+        assert_eq!(execution.last_executed(), 3);
+
+        assert_eq!(
+            execution.order(2, c_req(5)),
+            vec![RequestId::new(2, 2), RequestId::new(2, 3)]
+        );
+        assert_eq!(execution.take_rolled_back(), 2);
+        assert_eq!(execution.take_rolled_back(), 0);
+        assert_eq!(execution.last_executed(), 2);
+        assert_eq!(execution.executed(), reference.executed());
+        assert_eq!(execution.digest(), reference.digest());
+    }
+
+    #[test]
+    fn final_requests_are_not_rolled_back() {
+        let mut execution = execution(true);
+        execution.order(1, c_req(1));
+        execution.order(2, c_req(2));
+        execution.finalize(2, c_req(2));
+
+        assert!(execution.order(1, c_req(7)).is_empty());
+        assert!(execution.rollback_speculation().is_empty());
+        assert_eq!(execution.last_executed(), 2);
+
+        execution.order(3, c_req(3));
+        assert_eq!(execution.rollback_speculation(), vec![RequestId::new(2, 3)]);
+        assert_eq!(execution.last_executed(), 2);
+    }
+
+    #[test]
+    fn without_speculation_only_final_requests_are_executed() {
+        let mut execution = execution(false);
+        execution.order(1, c_req(1));
+        execution.order(2, c_req(2));
+        assert_eq!(execution.last_executed(), 0);
+
+        execution.finalize(1, c_req(1));
+        assert_eq!(execution.last_executed(), 1);
+        execution.finalize(2, c_req(2));
+        assert_eq!(execution.executed().len(), 2);
+    }
+}
@@ -1,4 +1,12 @@
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::node::request::RequestId;
+use crate::simulation::event::Priority;
+
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
 pub enum ZyzzyvaMessage {
     ClientRequest(ClientRequest),
     ClientTimeout(ClientTimeout),
@@ -8,31 +16,100 @@ pub enum ZyzzyvaMessage {
     LocalCommit(LocalCommit),
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+/// Size of a client request: client id, sequence number and operation
+const CLIENT_REQUEST_SIZE: u32 = 12;
+/// Size of the fields shared by the ordering messages: view, sequence number and sender id
+const ORDER_HEADER_SIZE: u32 = 20;
+
+impl ZyzzyvaMessage {
+    /// Name of the message type, e.g. for statistics.
+    pub fn message_type(&self) -> &'static str {
+        match self {
+            ZyzzyvaMessage::ClientRequest(_) => "ClientRequest",
+            ZyzzyvaMessage::ClientTimeout(_) => "ClientTimeout",
+            ZyzzyvaMessage::OrderRequest(_) => "OrderRequest",
+            ZyzzyvaMessage::SpeculativeResponse(_) => "SpeculativeResponse",
+            ZyzzyvaMessage::Commit(_) => "Commit",
+            ZyzzyvaMessage::LocalCommit(_) => "LocalCommit",
+        }
+    }
+
+    /// Priority class at an overloaded node, the messages to and from the client are client traffic.
+    pub fn priority(&self) -> Priority {
+        match self {
+            ZyzzyvaMessage::ClientRequest(_)
+            | ZyzzyvaMessage::ClientTimeout(_)
+            | ZyzzyvaMessage::SpeculativeResponse(_) => Priority::Client,
+            ZyzzyvaMessage::OrderRequest(_)
+            | ZyzzyvaMessage::Commit(_)
+            | ZyzzyvaMessage::LocalCommit(_) => Priority::Ordering,
+        }
+    }
+
+    /// Whether the sender appends the message to its log before it sends it, see `[storage]`.
+    pub fn is_persisted(&self) -> bool {
+        matches!(
+            self,
+            ZyzzyvaMessage::OrderRequest(_)
+                | ZyzzyvaMessage::SpeculativeResponse(_)
+                | ZyzzyvaMessage::LocalCommit(_)
+        )
+    }
+
+    /// Id of the client request the message belongs to.
+    pub fn request_id(&self) -> Option<u64> {
+        let id = match self {
+            ZyzzyvaMessage::ClientRequest(m) => m.id,
+            ZyzzyvaMessage::ClientTimeout(m) => m.req_id,
+            ZyzzyvaMessage::OrderRequest(m) => m.c_req.id,
+            ZyzzyvaMessage::SpeculativeResponse(m) => m.c_req.id,
+            ZyzzyvaMessage::Commit(m) => m.req_id,
+            ZyzzyvaMessage::LocalCommit(m) => m.c_req.id,
+        };
+        Some(u64::from(id.sequence))
+    }
+
+    /// Size of the message in bytes.
+    pub fn payload_size(&self) -> u32 {
+        match self {
+            ZyzzyvaMessage::ClientRequest(_) => CLIENT_REQUEST_SIZE,
+            ZyzzyvaMessage::ClientTimeout(_) => 8,
+            ZyzzyvaMessage::OrderRequest(_) => ORDER_HEADER_SIZE + CLIENT_REQUEST_SIZE,
+            ZyzzyvaMessage::SpeculativeResponse(_) => ORDER_HEADER_SIZE + CLIENT_REQUEST_SIZE,
+            ZyzzyvaMessage::Commit(m) => {
+                12 + m.certificate.len() as u32 * (ORDER_HEADER_SIZE + CLIENT_REQUEST_SIZE)
+            }
+            ZyzzyvaMessage::LocalCommit(_) => ORDER_HEADER_SIZE + CLIENT_REQUEST_SIZE,
+        }
+    }
+}
+
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
 pub struct ClientTimeout {
-    pub req_id: u32,
+    pub req_id: RequestId,
 }
 impl ClientTimeout {
-    pub fn new(req_id: u32) -> Self {
+    pub fn new(req_id: RequestId) -> Self {
         ClientTimeout { req_id }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
 pub struct ClientRequest {
+    /// The issuing client and its sequence number
+    pub id: RequestId,
     pub operation: u32,
-    pub sender_id: u32,
 }
 impl ClientRequest {
-    pub fn new(operation: u32, sender_id: u32) -> Self {
-        ClientRequest {
-            operation,
-            sender_id,
-        }
+    pub fn new(id: RequestId, operation: u32) -> Self {
+        ClientRequest { id, operation }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
 pub struct OrderRequest {
     pub c_req: ClientRequest,
     pub view: u64,
@@ -50,7 +127,8 @@ impl OrderRequest {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
 pub struct SpeculativeResponse {
     pub c_req: ClientRequest,
     pub view: u64,
@@ -68,23 +146,26 @@ impl SpeculativeResponse {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+/// The certificate is shared by the copies of the message sent to the replicas
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
 pub struct Commit {
-    pub req_id: u32,
-    pub certificate: Vec<SpeculativeResponse>,
+    pub req_id: RequestId,
+    pub certificate: Arc<Vec<SpeculativeResponse>>,
     pub sender_id: u32,
 }
 impl Commit {
-    pub fn new(req_id: u32, certificate: Vec<SpeculativeResponse>, sender_id: u32) -> Self {
+    pub fn new(req_id: RequestId, certificate: Vec<SpeculativeResponse>, sender_id: u32) -> Self {
         Commit {
             req_id,
-            certificate,
+            certificate: Arc::new(certificate),
             sender_id,
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
 pub struct LocalCommit {
     pub c_req: ClientRequest,
     pub view: u64,
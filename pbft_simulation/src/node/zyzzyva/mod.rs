@@ -1,2 +1,4 @@
+pub mod client;
+pub mod execution;
 pub mod messages;
 pub mod state;
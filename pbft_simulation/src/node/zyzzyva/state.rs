@@ -1,12 +1,15 @@
 use std::collections::{HashMap, HashSet};
-use std::iter::FromIterator;
 
+use super::execution::Execution;
 use super::messages::*;
-use crate::simulation::config::log_result;
+use crate::node::application::{Application, CounterApplication};
+use crate::node::error::ProtocolError;
+use crate::node::quorum::QuorumConfig;
+use crate::node::request::RequestId;
+use crate::simulation::config::ResultLogger;
+use crate::simulation::results::ResultEvent;
 use crate::simulation::time::Time;
 
-pub const CLIENT_ID: u32 = 2;
-
 type Output = Vec<(u32, ZyzzyvaMessage)>;
 
 /// Creates an `Output` such that the host broadcasts `msg_out` to all other
@@ -23,22 +26,29 @@ fn create_peer_broadcast_output(msg_out: ZyzzyvaMessage, peers: &Vec<u32>) -> Ou
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum Role {
-    Client,
     Primary,
     Backup,
 }
 
+/// The progress of a request at a replica, see `State::status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryStatus {
+    /// The replica has no entry of the request.
+    Unknown,
+    /// The request was ordered, by the replica as primary or by an _OrderRequest_.
+    Ordered,
+    /// The replica received a _Commit_ of the request, its entry was removed from the log.
+    CommittedLocal,
+}
+
 #[derive(Debug)]
 pub struct LogEntry {
     c_req: ClientRequest,
     view: u64,
     seq_number: u64,
     commit_certificate: HashSet<SpeculativeResponse>,
-    local_commits: HashSet<u32>,
     speculative_execution: bool,
     committed_local: bool,
-    completed: bool,
-    timed_out: bool,
 }
 
 impl LogEntry {
@@ -48,214 +58,265 @@ impl LogEntry {
             view,
             seq_number,
             commit_certificate: HashSet::new(),
-            local_commits: HashSet::new(),
             speculative_execution: false,
             committed_local: false,
-            completed: false,
-            timed_out: false,
         }
     }
+
+    pub fn client_request(&self) -> &ClientRequest {
+        &self.c_req
+    }
+
+    pub fn view(&self) -> u64 {
+        self.view
+    }
+
+    pub fn seq_number(&self) -> u64 {
+        self.seq_number
+    }
+
+    /// The speculative responses of the commit certificate, empty until a _Commit_ arrived.
+    pub fn commit_certificate(&self) -> &HashSet<SpeculativeResponse> {
+        &self.commit_certificate
+    }
+
+    /// `true` if the request was executed when it was ordered.
+    pub fn is_speculatively_executed(&self) -> bool {
+        self.speculative_execution
+    }
+
+    pub fn is_committed_local(&self) -> bool {
+        self.committed_local
+    }
 }
 
 #[derive(Debug)]
 pub struct State {
     id: u32,
-    log: HashMap<u32, LogEntry>,
+    log: HashMap<RequestId, LogEntry>,
     /// For garbage collection purposes we store here IDs of locally
     /// commited requests. This allows us to remove the associated log entry and
     /// ignore all subsequent incoming messages related to the request.
-    cl_reqs: HashSet<u32>,
-    num_of_nodes: u32,
+    cl_reqs: HashSet<RequestId>,
     current_view: u64,
     next_seq_num: u64,
     role: Role,
     peers: Vec<u32>,
-    client_id: u32,
     quorum_size: usize,
     lc_seq_num: u64,
+    /// Executes the requests, speculatively or once they are final.
+    execution: Execution,
+    /// The result log the phases of the requests are written to
+    results: ResultLogger,
 }
 
 impl State {
+    /// Creates the replica `id` of the replicas `1..=num_of_nodes`, the clients are not part of
+    /// the cluster (see `Client`).
     pub fn new(id: u32, num_of_nodes: u32) -> Self {
-        if num_of_nodes < 5 {
+        if num_of_nodes < 4 {
             panic!(
-                "Need 5 Zyzzyva nodes (client is part of the cluster) but got only {}",
+                "Need at least 4 Zyzzyva replicas but got only {}",
                 num_of_nodes
             );
         }
 
-        // NOTE: as for now we model a client as a node and thus require
-        // for the simulation to specify 5 nodes so it also creates a client
-        // node. Interanlly we work with 4 replicas, therefore we subtract 1.
-        let num_of_nodes = num_of_nodes - 1;
-
-        let nn = num_of_nodes as usize;
-        let f: usize = nn / 3 + nn % 3 - 1;
-
         State {
             id,
             log: HashMap::new(),
-            num_of_nodes,
             current_view: 1,
             next_seq_num: 0,
             cl_reqs: HashSet::new(),
             lc_seq_num: 0,
             role: match id {
                 1 => Role::Primary,
-                CLIENT_ID => Role::Client,
                 _ => Role::Backup,
             },
-            client_id: CLIENT_ID,
-            peers: (1..=num_of_nodes + 1)
-                .into_iter()
-                .filter(|i| *i != id && *i != CLIENT_ID)
-                .collect(),
-            quorum_size: 2 * f + 1,
+            peers: (1..=num_of_nodes).filter(|i| *i != id).collect(),
+            quorum_size: QuorumConfig::new(num_of_nodes).quorum_size(),
+            execution: Execution::new(Box::new(CounterApplication::default()), true),
+            results: ResultLogger::default(),
+        }
+    }
+
+    /// Replaces the default counter application executed by the replicas.
+    pub fn application(mut self, application: Box<dyn Application>) -> Self {
+        self.execution = Execution::new(application, self.execution.is_speculative());
+        self
+    }
+
+    /// Writes the phases of the requests to `results` instead of discarding them.
+    pub fn result_logger(mut self, results: ResultLogger) -> Self {
+        self.results = results;
+        self
+    }
+
+    /// Without speculative execution the replicas execute requests once they are committed and
+    /// the client always collects a commit certificate.
+    pub fn speculative_execution(mut self, speculative: bool) -> Self {
+        self.execution.set_speculative(speculative);
+        self
+    }
+
+    /// The sequence number up to which all requests are executed.
+    pub fn last_executed(&self) -> u64 {
+        self.execution.last_executed()
+    }
+
+    /// The executed requests, in the order of their execution.
+    pub fn executed(&self) -> &[RequestId] {
+        self.execution.executed()
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn current_view(&self) -> u64 {
+        self.current_view
+    }
+
+    pub fn role(&self) -> &Role {
+        &self.role
+    }
+
+    /// The size of the commit certificates the clients collect.
+    pub fn quorum_size(&self) -> usize {
+        self.quorum_size
+    }
+
+    /// The entry of the request `request_id` while it is not committed, committed requests are
+    /// removed from the log, see `status`.
+    pub fn entry(&self, request_id: &RequestId) -> Option<&LogEntry> {
+        self.log.get(request_id)
+    }
+
+    /// The progress of the request `request_id` at the replica.
+    pub fn status(&self, request_id: &RequestId) -> EntryStatus {
+        if self.cl_reqs.contains(request_id) {
+            EntryStatus::CommittedLocal
+        } else if self.log.contains_key(request_id) {
+            EntryStatus::Ordered
+        } else {
+            EntryStatus::Unknown
         }
     }
 
+    /// Digest of the application state after executing all requests up to `last_executed`.
+    pub fn digest(&self) -> u64 {
+        self.execution.digest()
+    }
+
+    /// Returns the number of executions rolled back since the last call and resets it.
+    pub fn take_rolled_back(&mut self) -> u64 {
+        self.execution.take_rolled_back()
+    }
+
+    /// Rolls back all executions that are not committed, e.g. when a view change discards the
+    /// speculative history.
+    pub fn rollback_speculation(&mut self, time: Time) {
+        let undone = self.execution.rollback_speculation();
+        self.discard(undone, time);
+    }
+
+    /// Replaces the standard quorum system of the replicas.
+    pub fn quorum(mut self, quorum: QuorumConfig) -> Self {
+        self.quorum_size = quorum.quorum_size();
+        self
+    }
+
+    /// Handles a message of a peer or a client. Messages the replica cannot handle, e.g. those
+    /// meant for the client or orders conflicting with its log, are returned as `ProtocolError`.
     pub fn handle_message(
         &mut self,
         zyzzyva_message: ZyzzyvaMessage,
         time: Time,
-    ) -> Option<Output> {
+    ) -> Result<Option<Output>, ProtocolError> {
         // we only process a message if we not already committed locally the
         // associated request
         if self.can_ignore_message(&zyzzyva_message) {
-            return None;
+            return Ok(None);
         }
 
         match zyzzyva_message {
             ZyzzyvaMessage::ClientRequest(m) => self.handle_client_request(m, time),
-            ZyzzyvaMessage::ClientTimeout(m) => self.handle_client_timeout(m, time),
             ZyzzyvaMessage::OrderRequest(m) => self.handle_order_request(m, time),
-            ZyzzyvaMessage::SpeculativeResponse(m) => self.handle_speculative_response(m, time),
             ZyzzyvaMessage::Commit(m) => self.handle_commit(m, time),
-            ZyzzyvaMessage::LocalCommit(m) => self.handle_local_commit(m, time),
+            _ => Err(self.unexpected(zyzzyva_message.message_type())),
+        }
+    }
+
+    /// The error for a message the replica is not meant to receive in its role
+    fn unexpected(&self, message_type: &'static str) -> ProtocolError {
+        ProtocolError::UnexpectedMessage {
+            node: self.id,
+            message_type,
         }
     }
 
     fn can_ignore_message(&self, message: &ZyzzyvaMessage) -> bool {
         match message {
-            ZyzzyvaMessage::LocalCommit(m) => self.cl_reqs.contains(&m.c_req.operation),
-            ZyzzyvaMessage::SpeculativeResponse(m) => self.cl_reqs.contains(&m.c_req.operation),
-            ZyzzyvaMessage::OrderRequest(m) => self.cl_reqs.contains(&m.c_req.operation),
+            ZyzzyvaMessage::LocalCommit(m) => self.cl_reqs.contains(&m.c_req.id),
+            ZyzzyvaMessage::SpeculativeResponse(m) => self.cl_reqs.contains(&m.c_req.id),
+            ZyzzyvaMessage::OrderRequest(m) => self.cl_reqs.contains(&m.c_req.id),
+            ZyzzyvaMessage::Commit(m) => self.cl_reqs.contains(&m.req_id),
             _ => false,
         }
     }
 
-    /// Gets the `id` of the primary for the current view.
-    fn curr_primary(&self) -> u32 {
-        (self.current_view % (self.num_of_nodes as u64)) as u32
-    }
-
     /// Increments the sequence number counter and returns the value.
     fn next_seq_num(&mut self) -> u64 {
         self.next_seq_num += 1;
         self.next_seq_num
     }
 
-    fn gc_entry(&mut self, req_id: u32) {
-        // we don't need the entry anymore. Therefore, remove it from the log
-        self.log.remove(&req_id);
-        // update the committed local set so we ignore subsequent incoming messages
-        // related to this request
-        self.cl_reqs.insert(req_id);
-    }
-
-    fn handle_local_commit(&mut self, msg_in: LocalCommit, time: Time) -> Option<Output> {
-        match self.log.get_mut(&msg_in.c_req.operation) {
-            Some(entry) => {
-                entry.local_commits.insert(msg_in.sender_id);
-
-                if entry.local_commits.len() >= self.quorum_size && !entry.completed {
-                    log_result(
-                        time,
-                        Some(self.id),
-                        &format!("{};completed", msg_in.c_req.operation),
-                    );
-                    // entry.completed = true;
-                    let id = entry.c_req.operation;
-                    self.gc_entry(id);
-                }
-            }
-            None => panic!(
-                "Received a local commit message for entry {} that is not stored at the client",
-                msg_in.c_req.operation
-            ),
+    /// Drops the log entries of rolled back requests, they are ordered anew.
+    fn discard(&mut self, undone: Vec<RequestId>, time: Time) {
+        for req_id in undone {
+            self.results
+                .log(time, Some(self.id), &ResultEvent::RolledBack(req_id.into()));
+            self.log.remove(&req_id);
         }
-        None
     }
 
-    fn handle_client_timeout(&mut self, msg_in: ClientTimeout, time: Time) -> Option<Output> {
-        if self.role == Role::Client {
-            if let Some(entry) = self.log.get_mut(&msg_in.req_id) {
-                entry.timed_out = true;
-                let cert_len = entry.commit_certificate.len();
-
-                // Zyzzyva 4.b
-                if cert_len >= self.quorum_size && cert_len < self.peers.len() {
-                    return Some(create_peer_broadcast_output(
-                        ZyzzyvaMessage::Commit(Commit::new(
-                            msg_in.req_id,
-                            entry.commit_certificate.clone().into_iter().collect(),
-                            self.id,
-                        )),
-                        &self.peers,
-                    ));
-                }
-
-                // Zyzzyva 4.c
-                if cert_len < self.quorum_size {
-                    log_result(time, Some(self.id), &format!("{};timed-out", msg_in.req_id));
-                }
-            }
+    /// Logs the ordering of a request, which is executed only with speculative execution.
+    fn log_ordered(&self, req_id: RequestId, time: Time) {
+        let event = if self.execution.is_speculative() {
+            ResultEvent::SpeculativeCommit(req_id.into())
         } else {
-            panic!(
-                "Non-client node {} received a ClientTimeout message",
-                self.id
-            );
-        }
+            ResultEvent::Ordered(req_id.into())
+        };
+        self.results.log(time, Some(self.id), &event);
+    }
 
-        None
+    fn gc_entry(&mut self, req_id: RequestId) {
+        // we don't need the entry anymore. Therefore, remove it from the log
+        self.log.remove(&req_id);
+        // update the committed local set so we ignore subsequent incoming messages
+        // related to this request
+        self.cl_reqs.insert(req_id);
     }
 
-    fn handle_client_request(&mut self, msg_in: ClientRequest, time: Time) -> Option<Output> {
+    fn handle_client_request(
+        &mut self,
+        msg_in: ClientRequest,
+        time: Time,
+    ) -> Result<Option<Output>, ProtocolError> {
         match self.role {
-            // The client will receive the request from the simulation and create
-            // a "real" request to the primary
-            Role::Client => {
-                let request = ClientRequest::new(msg_in.operation, self.id);
-                let entry = LogEntry::new(request, 0, 0);
-                let mut output = Output::with_capacity(2);
-
-                self.log.insert(msg_in.operation, entry);
-
-                output.push((self.curr_primary(), ZyzzyvaMessage::ClientRequest(request)));
-                // add a timeout event for the client itself.
-                output.push((
-                    self.id,
-                    ZyzzyvaMessage::ClientTimeout(ClientTimeout::new(msg_in.operation)),
-                ));
-
-                return Some(output);
-            }
             Role::Primary => {
                 let seq_number = self.next_seq_num();
                 let mut entry = LogEntry::new(msg_in, self.current_view, seq_number);
                 let mut output = Output::with_capacity(self.peers.len() + 1);
 
-                log_result(
-                    time,
-                    Some(self.id),
-                    &format!("{};speculative_commit", msg_in.operation),
-                );
+                self.log_ordered(msg_in.id, time);
 
-                entry.speculative_execution = true;
-                self.log.insert(msg_in.operation, entry);
+                entry.speculative_execution = self.execution.is_speculative();
+                self.log.insert(msg_in.id, entry);
+                let undone = self.execution.order(seq_number, msg_in);
+                self.discard(undone, time);
 
                 output.push((
-                    CLIENT_ID,
+                    msg_in.id.client_id,
                     ZyzzyvaMessage::SpeculativeResponse(SpeculativeResponse::new(
                         msg_in,
                         self.current_view,
@@ -274,165 +335,120 @@ impl State {
                     &self.peers,
                 ));
 
-                return Some(output);
+                Ok(Some(output))
             }
-            Role::Backup => panic!("Backup received client request {:?}", msg_in),
+            Role::Backup => Err(self.unexpected("ClientRequest")),
         }
     }
 
-    fn handle_order_request(&mut self, msg_in: OrderRequest, time: Time) -> Option<Output> {
+    fn handle_order_request(
+        &mut self,
+        msg_in: OrderRequest,
+        time: Time,
+    ) -> Result<Option<Output>, ProtocolError> {
         match self.role {
-            Role::Backup => match self.log.get(&msg_in.c_req.operation) {
-                Some(_) => panic!(
-                    "Received a OrderRequest for operation {} although there is already an entry. {:?}",
-                    msg_in.c_req.operation,
-                    msg_in
-                ),
+            Role::Backup => match self.log.get(&msg_in.c_req.id) {
+                Some(_) => Err(ProtocolError::Conflict {
+                    node: self.id,
+                    message_type: "OrderRequest",
+                    reason: format!("request {} is ordered already", msg_in.c_req.id),
+                }),
                 None => {
                     let mut entry = LogEntry::new(msg_in.c_req, msg_in.view, msg_in.seq_number);
 
-                    entry.speculative_execution = true;
+                    entry.speculative_execution = self.execution.is_speculative();
 
-                    self.log.insert(msg_in.c_req.operation, entry);
+                    // a conflicting order invalidates the speculative history
+                    let undone = self.execution.order(msg_in.seq_number, msg_in.c_req);
+                    self.discard(undone, time);
+                    self.log.insert(msg_in.c_req.id, entry);
 
-                    log_result(
-                        time,
-                        Some(self.id),
-                        &format!("{};speculative_commit", msg_in.c_req.operation),
-                    );
+                    self.log_ordered(msg_in.c_req.id, time);
 
-                    return Some(vec![(
-                        CLIENT_ID,
+                    Ok(Some(vec![(
+                        msg_in.c_req.id.client_id,
                         ZyzzyvaMessage::SpeculativeResponse(SpeculativeResponse::new(
                             msg_in.c_req,
                             msg_in.view,
                             msg_in.seq_number,
                             self.id,
                         )),
-                    )]);
+                    )]))
                 }
             },
-            _ => {
-                panic!(
-                    "Only Backups may received a Order Request! But node {} received one!",
-                    self.id
-                );
-            }
+            // only the backups are ordered by the primary
+            Role::Primary => Err(self.unexpected("OrderRequest")),
         }
     }
 
-    fn handle_speculative_response(
+    fn handle_commit(
         &mut self,
-        msg_in: SpeculativeResponse,
+        msg_in: Commit,
         time: Time,
-    ) -> Option<Output> {
-        match self.role {
-            Role::Client => {
-                match self.log.get_mut(&msg_in.c_req.operation) {
-                    Some(entry) => {
-                        // in case we timed-out we only accept commit messages
-                        // for the associated request
-                        if entry.timed_out {
-                            return None;
-                        }
-
-                        let cert = &mut entry.commit_certificate;
-                        cert.insert(msg_in);
-
-                        if cert.len() == self.quorum_size {
-                            log_result(
-                                time,
-                                Some(self.id),
-                                &format!("{};commit_certificate", msg_in.c_req.operation),
-                            );
-                        }
-
-                        // Zyzzyva 4.a
-                        if cert.len() == self.num_of_nodes as usize {
-                            log_result(
-                                time,
-                                Some(self.id),
-                                &format!("{};completed", msg_in.c_req.operation),
-                            );
-                            // entry.completed = true;
-
-                            let req_id = entry.c_req.operation;
-                            self.gc_entry(req_id);
-                        }
-                    }
-                    None => {
-                        panic!("Received a speculative response for an operation that was not requested!");
-                    }
-                }
-            }
-            _ => panic!(
-                "Only the Client should receive a Speculative Reponse! But node {} received one!",
-                self.id
-            ),
-        }
-        None
-    }
-
-    fn handle_commit(&mut self, msg_in: Commit, time: Time) -> Option<Output> {
-        match self.role {
-            Role::Client => panic!("A client should not receive a Commit message!"),
-            _ => {
-                if let Some(entry) = self.log.get_mut(&msg_in.req_id) {
-                    entry.commit_certificate = HashSet::from_iter(msg_in.certificate.into_iter());
-                    entry.committed_local = true;
-
-                    let mut output = Output::new();
-
-                    output.push((
-                        CLIENT_ID,
-                        ZyzzyvaMessage::LocalCommit(LocalCommit::new(
-                            entry.c_req,
-                            entry.view,
-                            entry.seq_number,
-                            self.id,
-                        )),
-                    ));
-                    self.gc_entry(msg_in.req_id);
-                    return Some(output);
-                } else {
-                    let spec_res = msg_in.certificate[0];
-                    let mut entry =
-                        LogEntry::new(spec_res.c_req, spec_res.view, spec_res.seq_number);
-                    entry.commit_certificate = HashSet::from_iter(msg_in.certificate.into_iter());
-                    entry.committed_local = true;
-
-                    log_result(
-                        time,
-                        Some(self.id),
-                        &format!("{};committed_local", entry.c_req.operation),
-                    );
-
-                    let mut output = Output::with_capacity(1);
+    ) -> Result<Option<Output>, ProtocolError> {
+        if let Some(entry) = self.log.get_mut(&msg_in.req_id) {
+            entry.commit_certificate = msg_in.certificate.iter().copied().collect();
+            entry.committed_local = true;
+
+            let output = vec![(
+                entry.c_req.id.client_id,
+                ZyzzyvaMessage::LocalCommit(LocalCommit::new(
+                    entry.c_req,
+                    entry.view,
+                    entry.seq_number,
+                    self.id,
+                )),
+            )];
+            let (seq_number, c_req) = (entry.seq_number, entry.c_req);
+            let undone = self.execution.finalize(seq_number, c_req);
+            self.discard(undone, time);
+            self.gc_entry(msg_in.req_id);
+            Ok(Some(output))
+        } else {
+            let spec_res = *msg_in
+                .certificate
+                .first()
+                .ok_or_else(|| ProtocolError::Conflict {
+                    node: self.id,
+                    message_type: "Commit",
+                    reason: format!("request {} comes without a certificate", msg_in.req_id),
+                })?;
+            let mut entry = LogEntry::new(spec_res.c_req, spec_res.view, spec_res.seq_number);
+            entry.commit_certificate = msg_in.certificate.iter().copied().collect();
+            entry.committed_local = true;
+
+            self.results.log(
+                time,
+                Some(self.id),
+                &ResultEvent::CommittedLocal(entry.c_req.id.into()),
+            );
 
-                    output.push((
-                        CLIENT_ID,
-                        ZyzzyvaMessage::LocalCommit(LocalCommit::new(
-                            entry.c_req,
-                            entry.view,
-                            entry.seq_number,
-                            self.id,
-                        )),
-                    ));
+            let output = vec![(
+                entry.c_req.id.client_id,
+                ZyzzyvaMessage::LocalCommit(LocalCommit::new(
+                    entry.c_req,
+                    entry.view,
+                    entry.seq_number,
+                    self.id,
+                )),
+            )];
 
-                    // self.log.insert(msg_in.req_id, entry);
-                    let req_id = msg_in.req_id;
-                    self.gc_entry(req_id);
+            // the request was never ordered at this replica, execute it now
+            let undone = self.execution.finalize(entry.seq_number, entry.c_req);
+            self.discard(undone, time);
 
-                    return Some(output);
-                }
-                // let output = self.process_history(time);
+            // self.log.insert(msg_in.req_id, entry);
+            let req_id = msg_in.req_id;
+            self.gc_entry(req_id);
 
-                // return match output.len() {
-                //     0 => None,
-                //     _ => Some(output),
-                // };
-            }
+            Ok(Some(output))
         }
+        // let output = self.process_history(time);
+
+        // return match output.len() {
+        //     0 => None,
+        //     _ => Some(output),
+        // };
     }
 
     /// This method is used when requiring a consistent history before executing
@@ -452,14 +468,14 @@ impl State {
             {
                 if entry.commit_certificate.len() >= self.quorum_size {
                     entry.committed_local = true;
-                    log_result(
+                    self.results.log(
                         time,
                         Some(self.id),
-                        &format!("{};committed_local", entry.c_req.operation),
+                        &ResultEvent::CommittedLocal(entry.c_req.id.into()),
                     );
 
                     output.push((
-                        CLIENT_ID,
+                        entry.c_req.id.client_id,
                         ZyzzyvaMessage::LocalCommit(LocalCommit::new(
                             entry.c_req,
                             entry.view,
@@ -469,13 +485,13 @@ impl State {
                     ));
                 } else {
                     entry.speculative_execution = true;
-                    log_result(
+                    self.results.log(
                         time,
                         Some(self.id),
-                        &format!("{};speculative_commit", entry.c_req.operation),
+                        &ResultEvent::SpeculativeCommit(entry.c_req.id.into()),
                     );
                     output.push((
-                        CLIENT_ID,
+                        entry.c_req.id.client_id,
                         ZyzzyvaMessage::SpeculativeResponse(SpeculativeResponse::new(
                             entry.c_req,
                             entry.view,
@@ -493,3 +509,62 @@ impl State {
         output
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_messages_are_reported() {
+        let c_req = ClientRequest::new(RequestId::new(5, 1), 1);
+        let mut backup = State::new(2, 4);
+        let order = ZyzzyvaMessage::OrderRequest(OrderRequest::new(c_req, 1, 1, 1));
+
+        // a backup is ordered by the primary, not by the client
+        let error = backup
+            .handle_message(ZyzzyvaMessage::ClientRequest(c_req), Time::new(1))
+            .unwrap_err();
+        assert_eq!(error.message_type(), "ClientRequest");
+
+        assert!(backup.handle_message(order.clone(), Time::new(2)).is_ok());
+        match backup.handle_message(order.clone(), Time::new(3)) {
+            Err(ProtocolError::Conflict { node: 2, .. }) => {}
+            other => panic!("Expected a conflicting order, got {:?}", other),
+        }
+
+        let commit = Commit::new(RequestId::new(5, 2), Vec::new(), 5);
+        assert!(backup
+            .handle_message(ZyzzyvaMessage::Commit(commit), Time::new(4))
+            .is_err());
+
+        let mut primary = State::new(1, 4);
+        assert!(primary.handle_message(order, Time::new(5)).is_err());
+    }
+
+    #[test]
+    fn progress_of_requests_is_inspected() {
+        let c_req = ClientRequest::new(RequestId::new(5, 1), 1);
+        let mut backup = State::new(2, 4);
+        assert_eq!((backup.id(), backup.current_view()), (2, 1));
+        assert_eq!(backup.role(), &Role::Backup);
+        assert_eq!(backup.quorum_size(), 3);
+        assert_eq!(backup.status(&c_req.id), EntryStatus::Unknown);
+
+        let order = ZyzzyvaMessage::OrderRequest(OrderRequest::new(c_req, 1, 1, 1));
+        backup.handle_message(order, Time::new(1)).unwrap();
+        assert_eq!(backup.status(&c_req.id), EntryStatus::Ordered);
+        let entry = backup.entry(&c_req.id).unwrap();
+        assert_eq!(entry.client_request(), &c_req);
+        assert_eq!((entry.view(), entry.seq_number()), (1, 1));
+        assert!(entry.is_speculatively_executed() && !entry.is_committed_local());
+        assert!(entry.commit_certificate().is_empty());
+
+        let certificate = (1..=3)
+            .map(|id| SpeculativeResponse::new(c_req, 1, 1, id))
+            .collect();
+        let commit = ZyzzyvaMessage::Commit(Commit::new(c_req.id, certificate, 5));
+        backup.handle_message(commit, Time::new(2)).unwrap();
+        assert_eq!(backup.status(&c_req.id), EntryStatus::CommittedLocal);
+        assert!(backup.entry(&c_req.id).is_none());
+    }
+}
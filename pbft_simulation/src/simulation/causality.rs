@@ -0,0 +1,188 @@
+/***************************************************************************************************
+Causality trace: records every message of a client request, i.e. who sent what to whom and when it
+arrived. The trace is exported as JSON (all requests) and as Graphviz DOT (a single request), where
+every node of the simulation gets a lane of events connected by the messages.
+***************************************************************************************************/
+
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::simulation::event::Message;
+use crate::simulation::observer::Observer;
+use crate::simulation::time::Time;
+
+/// A single message of a client request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageRecord {
+    pub request_id: u64,
+    pub message_type: String,
+    pub from: u32,
+    pub to: u32,
+    /// Time (ms) the message was handed to the network
+    pub sent: u64,
+    /// Time (ms) the message arrived, `None` if it was lost
+    pub received: Option<u64>,
+}
+
+/// All messages that belong to a client request, in the order they were sent
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CausalityTrace {
+    records: Vec<MessageRecord>,
+}
+
+impl CausalityTrace {
+    /// Records a message sent at `sent`, messages without a request id are ignored
+    pub fn record(
+        &mut self,
+        from: u32,
+        to: u32,
+        message: &Message,
+        sent: Time,
+        received: Option<Time>,
+    ) {
+        if let Some(request_id) = message.request_id() {
+            self.records.push(MessageRecord {
+                request_id,
+                message_type: message.message_type().to_string(),
+                from,
+                to,
+                sent: sent.milli(),
+                received: received.map(|t| t.milli()),
+            });
+        }
+    }
+
+    /// Returns the messages of the request `request_id`
+    pub fn request(&self, request_id: u64) -> impl Iterator<Item = &MessageRecord> {
+        self.records
+            .iter()
+            .filter(move |r| r.request_id == request_id)
+    }
+
+    /// Returns all records
+    pub fn records(&self) -> &[MessageRecord] {
+        &self.records
+    }
+
+    /// Writes all records as JSON
+    pub fn write_json<W: Write>(&self, writer: W) -> io::Result<()> {
+        serde_json::to_writer_pretty(writer, &self.records)?;
+        Ok(())
+    }
+
+    /// Reads a trace written by `write_json`
+    pub fn read_json<R: Read>(reader: R) -> io::Result<Self> {
+        let records = serde_json::from_reader(reader)?;
+        Ok(CausalityTrace { records })
+    }
+
+    /// Renders the message flow of the request `request_id` as a Graphviz digraph. Every send and
+    /// reception is a vertex on the lane of its node, lost messages end in a cross.
+    pub fn to_dot(&self, request_id: u64) -> String {
+        let records: Vec<&MessageRecord> = self.request(request_id).collect();
+
+        // all points in time at which a node sent or received a message of the request
+        let mut vertices = BTreeSet::new();
+        for r in &records {
+            vertices.insert((r.from, r.sent));
+            if let Some(received) = r.received {
+                vertices.insert((r.to, received));
+            }
+        }
+
+        let mut dot = String::new();
+        let _ = writeln!(dot, "digraph request_{} {{", request_id);
+        let _ = writeln!(dot, "    rankdir=LR;");
+        let _ = writeln!(dot, "    node [shape=point];");
+
+        let nodes: BTreeSet<u32> = vertices.iter().map(|(node, _)| *node).collect();
+        for node in nodes {
+            let lane: Vec<String> = vertices
+                .iter()
+                .filter(|(n, _)| *n == node)
+                .map(|(n, t)| format!("\"{}@{}\"", n, t))
+                .collect();
+            let _ = writeln!(dot, "    subgraph cluster_node_{} {{", node);
+            let _ = writeln!(dot, "        label=\"node {}\";", node);
+            let _ = writeln!(
+                dot,
+                "        {} [style=dashed, arrowhead=none];",
+                lane.join(" -> ")
+            );
+            let _ = writeln!(dot, "    }}");
+        }
+
+        for (i, r) in records.iter().enumerate() {
+            match r.received {
+                Some(received) => {
+                    let _ = writeln!(
+                        dot,
+                        "    \"{}@{}\" -> \"{}@{}\" [label=\"{}\"];",
+                        r.from, r.sent, r.to, received, r.message_type
+                    );
+                }
+                None => {
+                    let _ = writeln!(dot, "    lost_{} [shape=none, label=\"x\"];", i);
+                    let _ = writeln!(
+                        dot,
+                        "    \"{}@{}\" -> lost_{} [label=\"{} to {}\", style=dotted];",
+                        r.from, r.sent, i, r.message_type, r.to
+                    );
+                }
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Writes the JSON trace to '<prefix>.json' and the DOT graph of `request_id` to
+    /// '<prefix>_request_<request_id>.dot'
+    pub fn write_files(&self, prefix: &str, request_id: u64) -> io::Result<()> {
+        self.write_json(BufWriter::new(File::create(format!("{}.json", prefix))?))?;
+        let mut dot = File::create(format!("{}_request_{}.dot", prefix, request_id))?;
+        dot.write_all(self.to_dot(request_id).as_bytes())
+    }
+}
+
+impl Observer for CausalityTrace {
+    fn on_message_sent(
+        &mut self,
+        from: u32,
+        to: u32,
+        message: &Message,
+        sent: Time,
+        received: Option<Time>,
+    ) {
+        self.record(from, to, message, sent, received);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::pbft::messages::{ClientRequest, PBFTMessage, PrepareMessage};
+    use crate::node::request::RequestId;
+
+    #[test]
+    fn check_causality_of_a_request() {
+        let c_req = ClientRequest::new(RequestId::new(0, 3), 3);
+        let prepare = Message::PBFT(PBFTMessage::Prepare(PrepareMessage::new(c_req, 0, 1, 2)));
+
+        let mut trace = CausalityTrace::default();
+        trace.record(2, 3, &prepare, Time::new(10), Some(Time::new(40)));
+        trace.record(2, 4, &prepare, Time::new(10), None);
+        trace.record(1, 2, &Message::Dummy, Time::new(10), Some(Time::new(20)));
+
+        assert_eq!(trace.request(3).count(), 2);
+        assert_eq!(trace.request(1).count(), 0);
+
+        let dot = trace.to_dot(3);
+        assert!(dot.contains("\"2@10\" -> \"3@40\" [label=\"Prepare\"];"));
+        assert!(dot.contains("\"2@10\" -> lost_1"));
+    }
+}
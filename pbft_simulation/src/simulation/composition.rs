@@ -0,0 +1,142 @@
+/***************************************************************************************************
+Composition of a run out of clusters of different node types, e.g. a PBFT cluster and a Zyzzyva
+cluster side by side in the same network, to study how the protocols interfere over the links they
+share. The first cluster is the configured node type, `node.composition` adds the others, each with
+clients of its own. The nodes of a cluster only know the ids `1..` of their cluster: the simulation
+gives them the ids after those of the cluster before and routes their messages within their cluster,
+so the network, the result log and the admin events see the ids of the whole run.
+***************************************************************************************************/
+
+use std::sync::Arc;
+
+use crate::node::NodeType;
+use crate::simulation::settings::Settings;
+
+/// A cluster of replicas of one node type with its clients
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    pub node_type: NodeType,
+    /// Number of replicas
+    pub nodes: u32,
+    /// Number of nodes of the clusters before, the first node of the cluster has the id `offset + 1`
+    pub offset: u32,
+    /// The settings the nodes of the cluster are created with, the [node] section and the requests
+    /// are those of the cluster
+    pub settings: Arc<Settings>,
+}
+
+impl Cluster {
+    /// Number of replicas and clients
+    pub fn total_nodes(&self) -> u32 {
+        self.nodes + self.settings.node.client_nodes()
+    }
+
+    /// Whether node `id` of the run belongs to the cluster
+    pub fn contains(&self, id: u32) -> bool {
+        id > self.offset && id <= self.offset + self.total_nodes()
+    }
+
+    /// The id in the run of the node `local` of the cluster
+    pub fn global(&self, local: u32) -> u32 {
+        self.offset + local
+    }
+
+    /// Ids of the replicas in the run
+    pub fn replicas(&self) -> impl Iterator<Item = u32> {
+        self.offset + 1..=self.offset + self.nodes
+    }
+}
+
+/// The clusters of a run, in the order of their ids
+#[derive(Debug, Clone)]
+pub struct Composition {
+    clusters: Vec<Cluster>,
+}
+
+impl Composition {
+    /// The configured cluster of `settings` followed by those of `node.composition`
+    pub fn new(settings: &Arc<Settings>) -> Self {
+        let mut clusters = vec![Cluster {
+            node_type: settings.node.node_type,
+            nodes: settings.node.nodes,
+            offset: 0,
+            settings: Arc::clone(settings),
+        }];
+        for cluster in &settings.node.composition {
+            let last = &clusters[clusters.len() - 1];
+            let offset = last.offset + last.total_nodes();
+            let mut own = (**settings).clone();
+            own.node.node_type = cluster.node_type;
+            own.node.nodes = cluster.nodes;
+            own.node.composition = Vec::new();
+            if let Some(requests) = cluster.requests {
+                own.simulation.requests = requests;
+            }
+            clusters.push(Cluster {
+                node_type: cluster.node_type,
+                nodes: cluster.nodes,
+                offset,
+                settings: Arc::new(own),
+            });
+        }
+        Composition { clusters }
+    }
+
+    pub fn clusters(&self) -> &[Cluster] {
+        &self.clusters
+    }
+
+    /// Number of nodes of all clusters
+    pub fn total_nodes(&self) -> u32 {
+        let last = &self.clusters[self.clusters.len() - 1];
+        last.offset + last.total_nodes()
+    }
+
+    /// The cluster of node `id`, the first one for ids that are no node of the run
+    pub fn cluster_of(&self, id: u32) -> &Cluster {
+        self.clusters
+            .iter()
+            .find(|cluster| cluster.contains(id))
+            .unwrap_or(&self.clusters[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::settings::ClusterSettings;
+
+    #[test]
+    fn check_clusters_follow_each_other() {
+        let mut settings = Settings::default();
+        settings.node.composition = vec![
+            ClusterSettings {
+                node_type: NodeType::Zyzzyva,
+                nodes: 4,
+                requests: Some(7),
+            },
+            ClusterSettings {
+                node_type: NodeType::DAG,
+                nodes: 7,
+                requests: None,
+            },
+        ];
+        let composition = Composition::new(&Arc::new(settings));
+
+        // 4 pbft replicas and their client, 4 zyzzyva replicas and their client, 7 dag replicas
+        // without clients of their own
+        let offsets: Vec<u32> = composition.clusters().iter().map(|c| c.offset).collect();
+        assert_eq!(offsets, vec![0, 5, 10]);
+        assert_eq!(composition.total_nodes(), 17);
+
+        let zyzzyva = composition.cluster_of(10);
+        assert_eq!(zyzzyva.node_type, NodeType::Zyzzyva);
+        assert_eq!(zyzzyva.global(1), 6);
+        assert_eq!(zyzzyva.replicas().collect::<Vec<_>>(), vec![6, 7, 8, 9]);
+        assert_eq!(zyzzyva.settings.node.nodes, 4);
+        assert_eq!(zyzzyva.settings.simulation.requests, 7);
+        assert_eq!(composition.cluster_of(5).node_type, NodeType::PBFT);
+        assert_eq!(composition.cluster_of(11).settings.simulation.requests, 100);
+        assert_eq!(composition.cluster_of(31415).node_type, NodeType::PBFT);
+    }
+}
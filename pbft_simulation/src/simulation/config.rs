@@ -3,69 +3,168 @@ Configuration abstractions for the simulation and nodes
 Also contains methods called for initialization (ini, log, etc.)
 ***************************************************************************************************/
 
-use log::{debug, LevelFilter};
-use log4rs::append::console::ConsoleAppender;
-use log4rs::append::file::FileAppender;
-use log4rs::config::{Appender, Config, Logger, Root};
-use log4rs::encode::pattern::PatternEncoder;
-use mc_utils::ini::env2var;
-
-use crate::node::NodeType;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::mem;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use log::info;
+#[cfg(feature = "file-logging")]
+use log4rs::Handle;
+use mc_utils::ini::{ConfigBuilder, IniValues, KeyDef, Overrides, Schema, ValueType};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::node::adaptive::messages::{AdaptiveMessage, ClientRequest as AdaptiveCR};
+use crate::node::dag::messages::{ClientRequest as DAGCR, DAGMessage};
+use crate::node::factory::{NodeConstructor, NodeFactory};
 use crate::node::pbft::messages::{ClientRequest as PBFTCR, PBFTMessage};
-use crate::node::zyzzyva::{
-    messages::{ClientRequest as ZyzzyvaCR, ZyzzyvaMessage},
-    state::CLIENT_ID,
-};
+use crate::node::pbft::state::leader_of_request;
+use crate::node::quorum::check_fault_tolerance;
+use crate::node::request::RequestId;
+use crate::node::sbft::messages::{ClientRequest as SBFTCR, SBFTMessage};
+use crate::node::steward::messages::{ClientRequest as StewardCR, StewardMessage};
+use crate::node::tendermint::messages::{ClientRequest as TendermintCR, TendermintMessage};
+use crate::node::zyzzyva::messages::{ClientRequest as ZyzzyvaCR, ZyzzyvaMessage};
+use crate::node::{Node, NodeType};
+use crate::simulation::composition::Cluster;
+use crate::simulation::config_file::ConfigFile;
 use crate::simulation::event::{Event, Message};
-use crate::simulation::time::Time;
+#[cfg(feature = "file-logging")]
+use crate::simulation::logging::{LoggingBuilder, LoggingError};
+use crate::simulation::observer::Observers;
+use crate::simulation::output::RunId;
+use crate::simulation::results::ResultEvent;
+use crate::simulation::results_writer::ResultsWriter;
+use crate::simulation::settings::Settings;
+use crate::simulation::time::{Duration, Time};
 
 /// Config to initialize the simulation
 pub struct SimulationConfig {
     pub node_type: NodeType,
     pub number_of_nodes: u32,
+    /// Parameters of the run, shared with all nodes
+    pub settings: Arc<Settings>,
+    /// The id of the run, its files are written to its directory
+    pub run: RunId,
     next_id: u32,
+    /// The node types and the name of the one the nodes are created as
+    factory: NodeFactory,
+    node_name: String,
 }
 
 impl SimulationConfig {
+    /// Creates the config of a run with the given settings
+    pub fn new(settings: Settings) -> Self {
+        SimulationConfig {
+            node_type: settings.node.node_type,
+            number_of_nodes: settings.node.nodes,
+            node_name: settings.node.node_type.name().to_string(),
+            run: RunId::seeded(settings.simulation.seed),
+            settings: Arc::new(settings),
+            next_id: 0,
+            factory: NodeFactory::default(),
+        }
+    }
+
+    /// Writes the files of the run to the directory of `run`, e.g. to share it with other runs of
+    /// the same process
+    pub fn run(mut self, run: RunId) -> Self {
+        self.run = run;
+        self
+    }
+
+    /// The config of the nodes of `cluster` of the same run, they are built-in node types
+    pub fn of_cluster(&self, cluster: &Cluster) -> SimulationConfig {
+        SimulationConfig::new((*cluster.settings).clone()).run(self.run.clone())
+    }
+
+    /// Creates the nodes as the node type `name` of `factory`, the protocol of the node type
+    /// replaces the configured node type
+    pub fn nodes_from(mut self, factory: NodeFactory, name: &str) -> Self {
+        let (protocol, _) = factory.resolve(name).unwrap_or_else(|e| panic!("{}", e));
+        self.node_type = protocol;
+        Arc::make_mut(&mut self.settings).node.node_type = protocol;
+        self.factory = factory;
+        self.node_name = name.to_string();
+        self
+    }
+
+    /// The constructor of the nodes
+    pub fn node_constructor(&self) -> NodeConstructor {
+        match self.factory.resolve(&self.node_name) {
+            Ok((_, constructor)) => constructor,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// The constructor of the node of `config`, the clients are built-in node types unless the
+    /// factory replaces them
+    pub fn constructor_of(&self, config: &NodeConfig) -> NodeConstructor {
+        if config.id <= self.number_of_nodes {
+            return self.node_constructor();
+        }
+        let name = config.node_type.name();
+        match self.factory.resolve(name) {
+            Ok((_, constructor)) => constructor,
+            Err(_) => match NodeFactory::default().resolve(name) {
+                Ok((_, constructor)) => constructor,
+                Err(e) => panic!("{}", e),
+            },
+        }
+    }
+
+    /// Number of replicas and clients, the clients get the ids after the replicas
+    pub fn total_nodes(&self) -> u32 {
+        self.number_of_nodes + self.settings.node.client_nodes()
+    }
+
+    /// Creates the next node
+    pub fn build_node(&mut self, results: &ResultLogger) -> Box<dyn Node> {
+        let config = self.create_node_config(results);
+        let constructor = self.constructor_of(&config);
+        constructor(config)
+    }
+
     /// Used internally to increment the id counter for each new node
     fn increment_next_id(&mut self) -> u32 {
         self.next_id += 1;
         self.next_id
     }
 
-    /// Creates a new NodeConfig
-    pub fn create_node_config(&mut self) -> NodeConfig {
+    /// Creates a new NodeConfig, of a client once all replicas are created
+    pub fn create_node_config(&mut self, results: &ResultLogger) -> NodeConfig {
+        // increment the counter
+        let id = self.increment_next_id();
+        let (node_type, name) = match self.node_type.client_type() {
+            Some(client_type) if id > self.number_of_nodes => {
+                (client_type, client_type.name().to_string())
+            }
+            _ => (self.node_type, self.node_name.clone()),
+        };
         NodeConfig {
-            node_type: self.node_type,
-            // increment the counter
-            id: self.increment_next_id(),
+            node_type,
+            id,
             number_of_nodes: self.number_of_nodes,
+            params: self.settings.params_of(&name),
+            settings: Arc::clone(&self.settings),
+            results: results.clone(),
         }
     }
 }
+/// Uses the default settings, see `Settings::default`
 impl Default for SimulationConfig {
     fn default() -> Self {
-        let node_type = env2var::<String>("node.node_type");
-        let node_type = match node_type.as_str() {
-            "dummy" => NodeType::Dummy,
-            "pbft" => NodeType::PBFT,
-            "zyzzyva" => NodeType::Zyzzyva,
-            "rbft" => NodeType::RBFT,
-            _ => panic!(
-                "node_type in ini is not available, allowed are 'dummy', 'pbft', 'zyzzyva', 'rbft'"
-            ),
-        };
-
-        SimulationConfig {
-            node_type,
-            number_of_nodes: 0,
-            next_id: 0,
-        }
+        SimulationConfig::new(Settings::default())
     }
 }
 impl SimulationConfig {
     pub fn number_of_nodes(mut self, number_of_nodes: u32) -> SimulationConfig {
         self.number_of_nodes = number_of_nodes;
+        Arc::make_mut(&mut self.settings).node.nodes = number_of_nodes;
         self
     }
 }
@@ -75,163 +174,998 @@ pub struct NodeConfig {
     pub node_type: NodeType,
     pub id: u32,
     pub number_of_nodes: u32,
+    pub settings: Arc<Settings>,
+    /// Free-form parameters of the node type, see `Settings::params`
+    pub params: HashMap<String, String>,
+    /// Result log of the run the node is part of
+    pub results: ResultLogger,
+}
+
+impl NodeConfig {
+    /// Parses the parameter `key`, `default` if the parameter is not given
+    pub fn param<T: FromStr>(&self, key: &str, default: T) -> T {
+        match self.params.get(key) {
+            Some(value) => value.parse().unwrap_or_else(|_| {
+                panic!(
+                    "Can't parse the parameter {} = '{}' of {}",
+                    key,
+                    value,
+                    self.node_type.name()
+                )
+            }),
+            None => default,
+        }
+    }
+}
+
+/// Id of the simulated PBFT client, which is not a node of the simulation
+pub const PBFT_CLIENT_ID: u32 = 31415;
+
+/// The message carrying the client request `id` to the replicas (the client for zyzzyva) of
+/// `node_type`
+pub fn client_request(node_type: NodeType, id: RequestId, operation: u32) -> Message {
+    match node_type {
+        NodeType::PBFT => Message::PBFT(PBFTMessage::ClientRequest(PBFTCR::new(id, operation))),
+        NodeType::SBFT => Message::SBFT(SBFTMessage::ClientRequest(SBFTCR::new(id, operation))),
+        NodeType::Tendermint => Message::Tendermint(TendermintMessage::ClientRequest(
+            TendermintCR::new(id, operation),
+        )),
+        NodeType::Zyzzyva => {
+            Message::Zyzzyva(ZyzzyvaMessage::ClientRequest(ZyzzyvaCR::new(id, operation)))
+        }
+        NodeType::Adaptive => Message::Adaptive(AdaptiveMessage::ClientRequest(AdaptiveCR::new(
+            id, operation,
+        ))),
+        NodeType::DAG => Message::DAG(DAGMessage::ClientRequest(DAGCR::new(id, operation))),
+        NodeType::Steward => Message::Steward(StewardMessage::ClientRequest(StewardCR::new(
+            id, operation,
+        ))),
+        _ => panic!(
+            "Received client requests for node type {:?}, which is not implemented yet",
+            node_type
+        ),
+    }
+}
+
+/// Replica the client sends its PBFT requests to. Unless the client sends to the primary, backups
+/// forward the requests they receive to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ClientTarget {
+    Node(u32),
+    RoundRobin,
+    Random,
+    /// The primary of the first view, or with parallel PBFT instances the leader of the instance
+    /// ordering the request, out of the given number of instances
+    Primary(u32),
+    /// The replica with the lowest latency from the client, see `resolve`
+    Nearest,
+}
+
+impl ClientTarget {
+    pub fn from_config(target: &str) -> Self {
+        match target {
+            "round_robin" => ClientTarget::RoundRobin,
+            "random" => ClientTarget::Random,
+            "primary" => ClientTarget::Primary(1),
+            "nearest" => ClientTarget::Nearest,
+            id => match id.parse() {
+                Ok(id) => ClientTarget::Node(id),
+                Err(_) => panic!(
+                    "client_target in ini is not available, allowed are 'round_robin', 'random', 'primary', 'nearest' or a node id"
+                ),
+            },
+        }
+    }
+
+    /// Fills in what the target depends on: the number of PBFT instances the primary is picked
+    /// from and the nearest replica. The client's latencies to the replicas are the row of the
+    /// latency matrix after those of the nodes.
+    pub fn resolve(self, settings: &Settings, number_of_nodes: u32) -> Self {
+        match self {
+            ClientTarget::Primary(_) => {
+                ClientTarget::Primary(settings.pbft.ordering_instances(number_of_nodes))
+            }
+            ClientTarget::Nearest => {
+                let latency = settings
+                    .network
+                    .latency
+                    .get(number_of_nodes as usize)
+                    .filter(|row| row.len() >= number_of_nodes as usize)
+                    .unwrap_or_else(|| {
+                        panic!(
+                            "client_target 'nearest' needs the latencies of the client as row {} of [network]latency",
+                            number_of_nodes + 1
+                        )
+                    });
+                let nearest = (1..=number_of_nodes)
+                    .min_by_key(|id| latency[*id as usize - 1])
+                    .unwrap_or(1);
+                ClientTarget::Node(nearest)
+            }
+            target => target,
+        }
+    }
+
+    /// Picks the receiver of the request `request_id` among the nodes `1..=number_of_nodes`
+    pub fn pick(&self, request_id: u64, number_of_nodes: u32) -> u32 {
+        match self {
+            ClientTarget::Node(id) => *id,
+            ClientTarget::RoundRobin => (request_id % u64::from(number_of_nodes)) as u32 + 1,
+            // drawn from the request id, so a seeded run picks the same replicas again
+            ClientTarget::Random => {
+                StdRng::seed_from_u64(request_id).gen_range(1, number_of_nodes + 1)
+            }
+            ClientTarget::Primary(instances) => {
+                leader_of_request(request_id as u32, 1, *instances, number_of_nodes)
+            }
+            ClientTarget::Nearest => panic!("the nearest replica is only known once resolved"),
+        }
+    }
 }
 
 /// Config for a batch of requests
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct RequestBatchConfig {
     pub number: u32,
     pub interval: u32,
+    /// Index of the cluster the requests are sent to, see `Composition`
+    #[serde(default)]
+    pub cluster: usize,
 }
 
 impl RequestBatchConfig {
     pub fn new(number: u32, interval: u32) -> Self {
-        RequestBatchConfig { number, interval }
+        RequestBatchConfig {
+            number,
+            interval,
+            cluster: 0,
+        }
     }
 
-    // create a vector of events, corresponding to the config
+    /// Sends the requests to the cluster with the index `cluster`
+    pub fn cluster(mut self, cluster: usize) -> Self {
+        self.cluster = cluster;
+        self
+    }
+
+    // create a vector of events, corresponding to the config. `number_of_nodes` is the number of
+    // replicas, the `clients` that are nodes of their own follow them
     pub fn create_events(
         &self,
         request_id_counter: &mut u64,
         time: Time,
         node_type: NodeType,
+        number_of_nodes: u32,
+        clients: u32,
+        client_target: ClientTarget,
     ) -> Vec<Event> {
         let mut result = Vec::with_capacity(self.number as usize);
 
         for counter in 1..=self.number {
-            match node_type {
-                NodeType::PBFT => {
-                    // the message containing the client request
-                    let message = Message::PBFT(PBFTMessage::ClientRequest(PBFTCR {
-                        sender_id: 31415,
-                        operation: (*request_id_counter as u32),
-                    }));
-                    //TODO Client requests will go to node '1' by default, add option to define receiver in RequestConfig?
-                    let new_time = time.add_milli(u64::from((counter - 1) * self.interval));
-                    result.push(Event::new_reception(1, message, new_time));
-                }
-                NodeType::Zyzzyva => {
-                    let message = Message::Zyzzyva(ZyzzyvaMessage::ClientRequest(ZyzzyvaCR {
-                        sender_id: 0,
-                        operation: (*request_id_counter as u32),
-                    }));
-                    let new_time = time.add_milli(u64::from((counter - 1) * self.interval));
-                    result.push(Event::new_reception(CLIENT_ID, message, new_time));
+            // the zyzzyva clients take turns and issue their requests themselves, the other
+            // clients pick a replica
+            let (client_id, receiver) = match node_type.client_type() {
+                Some(_) => {
+                    let client = number_of_nodes
+                        + 1
+                        + ((*request_id_counter - 1) % u64::from(clients)) as u32;
+                    (client, client)
                 }
-                _ => panic!(
-                    "Received client requests for node type {:?}, which is not implemented yet",
-                    node_type
+                None => (
+                    PBFT_CLIENT_ID,
+                    client_target.pick(*request_id_counter, number_of_nodes),
                 ),
-            }
+            };
+            let message = client_request(
+                node_type,
+                RequestId::new(client_id, *request_id_counter as u32),
+                *request_id_counter as u32,
+            );
+            let new_time = time + Duration::from_millis(u64::from((counter - 1) * self.interval));
+            result.push(Event::new_reception(receiver, message, new_time));
             *request_id_counter += 1;
         }
         result
     }
 }
 
-pub fn log_result(time: Time, node_id: Option<u32>, message: &str) {
-    let n: u32 = mc_utils::ini::env2var("node.nodes");
+/// The result log of a run
+#[derive(Debug, Default)]
+struct ResultLog {
+    /// The file the lines are written to, if the run writes one
+    writer: Option<ResultsWriter>,
+    /// Lines kept for the report of the run, if it was asked for one
+    lines: Option<Vec<String>>,
+}
 
-    let mut result = String::new();
-    result.push_str(&time.to_string());
-    result.push(';');
-    if let Some(id) = node_id {
-        result.push_str(&id.to_string());
-    } else {
-        result.push_str("-1");
+thread_local! {
+    /// Lines logged on this thread while `ResultLogger::capture` runs, they are written on replay
+    static CAPTURED: RefCell<Option<Vec<CapturedLine>>> = const { RefCell::new(None) };
+    /// Time the lines logged on this thread while `ResultLogger::at` runs are written at
+    static LOG_TIME: Cell<Option<Time>> = const { Cell::new(None) };
+}
+
+/// A line of a result log that was held back, see `ResultLogger::capture`
+#[derive(Debug)]
+struct CapturedLine {
+    logger: ResultLogger,
+    time: Time,
+    node_id: Option<u32>,
+    event: ResultEvent,
+}
+
+/// The lines a node logged while it was handled on a worker thread, in the order they were logged
+#[derive(Debug, Default)]
+pub struct CapturedLines(Vec<CapturedLine>);
+
+impl CapturedLines {
+    /// Writes the lines to their result logs and tells the observers, as if they were logged now
+    pub fn replay(self) {
+        for line in self.0 {
+            line.logger.log(line.time, line.node_id, &line.event);
+        }
     }
-    result.push(';');
-    result.push_str(message);
+}
 
-    debug!(target: &format!("result_{}", n), "{}", &result);
+/// Handle to the result log of a run. The simulation creates it and hands it to its nodes, so the
+/// results of simulations running at the same time are kept apart. A new handle writes to no file,
+/// e.g. for nodes created outside of a simulation.
+#[derive(Debug, Clone, Default)]
+pub struct ResultLogger {
+    log: Arc<Mutex<ResultLog>>,
+    /// Observers of the run, told about every line
+    observers: Observers,
+    /// Added to the ids of the nodes, so the nodes of a cluster log the ids they have in the run
+    offset: u32,
 }
 
-/// Read values from the ini and store in environment
-pub fn initialize_ini() {
-    let ini = mc_utils::ini::get_ini("simulation.ini");
-    mc_utils::ini::ini2env("node", "node_type", &ini, None);
-    mc_utils::ini::ini2env("node", "nodes_vec", &ini, None);
-    mc_utils::ini::ini2env("node", "client_timeout", &ini, None);
-    mc_utils::ini::ini2env("simulation", "requests", &ini, None);
-    mc_utils::ini::ini2env("log", "debug", &ini, None);
-    mc_utils::ini::ini2env("log", "result", &ini, None);
-    mc_utils::ini::ini2env("network", "omission_probability", &ini, None);
-    mc_utils::ini::ini2env("network", "delay_min", &ini, None);
-    mc_utils::ini::ini2env("network", "delay_max", &ini, None);
-}
-
-/// Initialize the loggers
-pub fn initialize_logging() {
-    let stdout = ConsoleAppender::builder().build();
-
-    let mut config =
-        Config::builder().appender(Appender::builder().build("stdout", Box::new(stdout)));
-
-    if mc_utils::ini::env2var("log.debug") {
-        let log_node = FileAppender::builder()
-            .encoder(Box::new(PatternEncoder::new("{l} - {m}{n}")))
-            .append(false)
-            .build("log/debug_nodes.log")
-            .unwrap();
+impl ResultLogger {
+    /// Creates the result log of a run
+    pub fn new() -> Self {
+        ResultLogger::default()
+    }
 
-        let log_simulation = FileAppender::builder()
-            .encoder(Box::new(PatternEncoder::new("{l} - {m}{n}")))
-            .append(false)
-            .build("log/debug_simulation.log")
-            .unwrap();
+    /// Writes the lines to `writer` from now on
+    pub fn write_to(&self, writer: ResultsWriter) {
+        info!("Writing the result log to {}", writer.path());
+        self.lock().writer = Some(writer);
+    }
 
-        config = config
-            .appender(Appender::builder().build("log_node", Box::new(log_node)))
-            .appender(Appender::builder().build("log_simulation", Box::new(log_simulation)))
-            .logger(
-                Logger::builder()
-                    .appender("log_node")
-                    .additive(false)
-                    .build("node", LevelFilter::Debug),
+    /// Writes the buffered lines and closes the file of the result log, the lines logged later are
+    /// not written to it
+    pub fn finish(&self) {
+        if let Some(writer) = self.lock().writer.take() {
+            writer.finish();
+        }
+    }
+
+    /// The observers of the run the lines are passed to
+    pub fn observers(&self) -> &Observers {
+        &self.observers
+    }
+
+    /// A handle to the same log for the nodes of the cluster that starts after `offset` nodes, see
+    /// `Composition`
+    pub fn for_cluster(&self, offset: u32) -> Self {
+        ResultLogger {
+            offset,
+            ..self.clone()
+        }
+    }
+
+    /// Keeps the lines in memory from now on, until `take_lines` is called
+    pub fn collect(&self) {
+        self.lock().lines.get_or_insert_with(Vec::new);
+    }
+
+    /// Returns the lines collected since `collect` was called
+    pub fn take_lines(&self) -> Vec<String> {
+        self.lock().lines.take().unwrap_or_default()
+    }
+
+    /// Returns the lines collected since `collect` or the last call, and keeps collecting
+    pub fn drain_lines(&self) -> Vec<String> {
+        self.lock().lines.as_mut().map(mem::take).unwrap_or_default()
+    }
+
+    /// Runs `f` and holds back the lines it logs on this thread, to any result log, instead of
+    /// writing them. Nodes handled in parallel log through it, so their lines can be written in a
+    /// fixed order afterwards.
+    pub fn capture<R, F: FnOnce() -> R>(f: F) -> (R, CapturedLines) {
+        let outer = CAPTURED.with(|captured| captured.replace(Some(Vec::new())));
+        let result = f();
+        let lines = CAPTURED.with(|captured| captured.replace(outer));
+        (result, CapturedLines(lines.unwrap_or_default()))
+    }
+
+    /// Runs `f` and writes the lines it logs on this thread, to any result log, at `time`. A node
+    /// reads its local clock, see `Clock`, the lines it logs carry the time of the simulation.
+    pub fn at<R, F: FnOnce() -> R>(time: Time, f: F) -> R {
+        let outer = LOG_TIME.with(|log_time| log_time.replace(Some(time)));
+        let result = f();
+        LOG_TIME.with(|log_time| log_time.set(outer));
+        result
+    }
+
+    /// Writes the line 'time;node id (-1 for none);message' with the encoded event as message
+    pub fn log(&self, time: Time, node_id: Option<u32>, event: &ResultEvent) {
+        let time = LOG_TIME.with(Cell::get).unwrap_or(time);
+        let held_back = CAPTURED.with(|captured| match &mut *captured.borrow_mut() {
+            Some(lines) => {
+                lines.push(CapturedLine {
+                    logger: self.clone(),
+                    time,
+                    node_id,
+                    event: event.clone(),
+                });
+                true
+            }
+            None => false,
+        });
+        if held_back {
+            return;
+        }
+
+        let node_id = node_id.map(|id| id + self.offset);
+        let message = event.to_string();
+        let mut result = String::new();
+        result.push_str(&time.to_string());
+        result.push(';');
+        if let Some(id) = node_id {
+            result.push_str(&id.to_string());
+        } else {
+            result.push_str("-1");
+        }
+        result.push(';');
+        result.push_str(&message);
+
+        {
+            let mut log = self.lock();
+            if let Some(writer) = &mut log.writer {
+                writer.write_line(&result);
+            }
+            if let Some(lines) = &mut log.lines {
+                lines.push(result);
+            }
+        }
+        self.observers.result(time, node_id, event);
+
+        #[cfg(feature = "sqlite")]
+        crate::simulation::sqlite::record(time, node_id, &message);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ResultLog> {
+        self.log
+            .lock()
+            .expect("Mutex lock poisoned, a node panicked while writing a result")
+    }
+}
+
+/// The keys of simulation.ini with their types, defaults and allowed ranges
+pub fn ini_schema() -> Schema {
+    use ValueType::*;
+    let uint = |section, key| KeyDef::new(section, key, UInt);
+    let flag = |section, key| KeyDef::new(section, key, Bool).default("false");
+
+    Schema::new()
+        .key(KeyDef::new(
+            "node",
+            "node_type",
+            OneOf(vec![
+                "pbft",
+                "zyzzyva",
+                "sbft",
+                "tendermint",
+                "adaptive",
+                "dag",
+                "steward",
+            ]),
+        ))
+        .key(KeyDef::new("node", "nodes_vec", List(Box::new(UInt))).range(1.0, 10_000.0))
+        .key(uint("node", "clients").default("1").range(1.0, 10_000.0))
+        .key(uint("node", "client_timeout").default("400"))
+        .key(KeyDef::new("node", "application", OneOf(vec!["counter", "kv"])).default("counter"))
+        .key(uint("node", "processing_delay").default("5"))
+        .key(KeyDef::new("node", "processing_delays", Text).default("none"))
+        .key(
+            uint("node", "max_rate")
+                .default("none")
+                .or_keyword("none")
+                .range(1.0, 1_000_000.0),
+        )
+        .key(KeyDef::new("node", "overload_policy", OneOf(vec!["queue", "drop"])).default("queue"))
+        .key(uint("node", "inbox_size").default("100"))
+        .key(uint("node", "clock_offset_max").default("0"))
+        .key(
+            KeyDef::new("node", "clock_drift_max", Float)
+                .default("0.0")
+                .range(0.0, 1.0),
+        )
+        .key(KeyDef::new("node", "on_panic", OneOf(vec!["abort", "crash"])).default("abort"))
+        .key(uint("node", "reception_history").default("20"))
+        .key(KeyDef::new("node", "composition", Text).default("none"))
+        .key(uint("simulation", "requests"))
+        .key(uint("simulation", "request_interval").default("1000"))
+        .key(KeyDef::new("simulation", "scenario", Text).default("none"))
+        .key(uint("simulation", "liveness_bound").default("5000"))
+        .key(
+            KeyDef::new("simulation", "speedup", Float)
+                .default("none")
+                .or_keyword("none")
+                .range(0.001, 1_000_000.0),
+        )
+        .key(
+            KeyDef::new(
+                "simulation",
+                "time_violation",
+                OneOf(vec!["abort", "clamp"]),
+            )
+            .default("abort"),
+        )
+        .key(uint("simulation", "event_history").default("0"))
+        .key(
+            KeyDef::new(
+                "simulation",
+                "scheduler",
+                OneOf(vec!["heap", "buckets", "calendar"]),
+            )
+            .default("heap"),
+        )
+        .key(
+            uint("simulation", "seed")
+                .default("none")
+                .or_keyword("none"),
+        )
+        .key(flag("simulation", "self_check"))
+        .key(
+            uint("simulation", "threads")
+                .default("1")
+                .range(1.0, 1024.0),
+        )
+        .key(flag("log", "debug"))
+        .key(flag("log", "result"))
+        .key(KeyDef::new("log", "sqlite_file", Text).default("none"))
+        .key(flag("trace", "causality"))
+        .key(uint("trace", "request").default("1"))
+        .key(flag("trace", "message_flow"))
+        .key(uint("stats", "warmup_ms").default("0"))
+        .key(
+            uint("stats", "cooldown_ms")
+                .default("none")
+                .or_keyword("none"),
+        )
+        .key(flag("stats", "hgrm"))
+        .key(flag("control", "enabled"))
+        .key(KeyDef::new("control", "address", Text).default("127.0.0.1:9899"))
+        .key(KeyDef::new("grpc", "address", Text).default("127.0.0.1:50051"))
+        .key(flag("metrics", "enabled"))
+        .key(KeyDef::new("metrics", "address", Text).default("127.0.0.1:9898"))
+        .key(uint("emulation", "timeout").default("60"))
+        .key(
+            KeyDef::new("network", "omission_probability", Float)
+                .default("0.0")
+                .range(0.0, 1.0),
+        )
+        .key(KeyDef::new("network", "loss", Text).default("none"))
+        .key(
+            KeyDef::new("network", "burst_enter", Float)
+                .default("0.0")
+                .range(0.0, 1.0),
+        )
+        .key(
+            KeyDef::new("network", "burst_exit", Float)
+                .default("0.5")
+                .range(0.0, 1.0),
+        )
+        .key(
+            KeyDef::new("network", "burst_loss", Float)
+                .default("1.0")
+                .range(0.0, 1.0),
+        )
+        .key(uint("network", "ttl").default("none").or_keyword("none"))
+        .key(
+            KeyDef::new("network", "delay_min", Float)
+                .default("20")
+                .range(0.0, 1_000_000.0),
+        )
+        .key(
+            KeyDef::new("network", "delay_max", Float)
+                .default("200")
+                .range(0.0, 1_000_000.0),
+        )
+        .key(
+            KeyDef::new(
+                "network",
+                "jitter",
+                OneOf(vec!["none", "gaussian", "pareto"]),
+            )
+            .default("none"),
+        )
+        .key(
+            KeyDef::new("network", "jitter_std_dev", Float)
+                .default("10.0")
+                .range(0.0, 100_000.0),
+        )
+        .key(
+            KeyDef::new("network", "jitter_scale", Float)
+                .default("10.0")
+                .range(0.0, 100_000.0),
+        )
+        .key(
+            KeyDef::new("network", "jitter_shape", Float)
+                .default("1.5")
+                .range(0.01, 100.0),
+        )
+        .key(KeyDef::new("network", "schedule", Text).default("none"))
+        .key(
+            KeyDef::new(
+                "network",
+                "topology",
+                OneOf(vec!["mesh", "star", "ring", "clusters", "matrix"]),
             )
-            .logger(
-                Logger::builder()
-                    .appender("log_simulation")
-                    .additive(false)
-                    .build("simulation", LevelFilter::Debug),
+            .default("mesh"),
+        )
+        .key(uint("network", "hub").default("1"))
+        .key(uint("network", "hub_delay").default("10"))
+        .key(
+            uint("network", "clusters")
+                .default("2")
+                .range(1.0, 10_000.0),
+        )
+        .key(uint("network", "cluster_delay").default("100"))
+        .key(uint("network", "gossip_fanout").default("3"))
+        .key(uint("network", "gossip_rounds").default("4"))
+        .key(uint("network", "gossip_hop_delay").default("1"))
+        .key(KeyDef::new("network", "latency", Text).default("none"))
+        .key(uint("network", "adversary_delta").default("100"))
+        .key(
+            KeyDef::new("network", "bandwidth", Float)
+                .default("none")
+                .or_keyword("none")
+                .range(0.001, 1_000_000.0),
+        )
+        .key(uint("quorum", "f").default("auto").or_keyword("auto"))
+        .key(uint("quorum", "size").default("auto").or_keyword("auto"))
+        .key(KeyDef::new("quorum", "weights", Text).default("none"))
+        .key(
+            uint("quorum", "quorum_weight")
+                .default("auto")
+                .or_keyword("auto"),
+        )
+        .key(KeyDef::new("pbft", "authentication", OneOf(vec!["mac", "signature"])).default("mac"))
+        .key(
+            uint("pbft", "watermark_window")
+                .default("100")
+                .range(1.0, 1e9),
+        )
+        .key(
+            uint("pbft", "client_target")
+                .default("1")
+                .or_keyword("round_robin")
+                .or_keyword("random")
+                .or_keyword("primary")
+                .or_keyword("nearest"),
+        )
+        .key(flag("pbft", "gossip_pre_prepare"))
+        .key(uint("pbft", "instances").default("1").range(1.0, 10_000.0))
+        .key(
+            KeyDef::new("pbft", "leader_rotation", OneOf(vec!["stable", "per_request"]))
+                .default("stable"),
+        )
+        .key(
+            uint("pbft", "request_size")
+                .default("12")
+                .range(1.0, 100_000_000.0),
+        )
+        .key(
+            KeyDef::new(
+                "pbft",
+                "dissemination",
+                OneOf(vec!["inline", "primary", "client"]),
             )
+            .default("inline"),
+        )
+        .key(KeyDef::new("pbft", "params", Text).default("none"))
+        .key(uint("tendermint", "timeout_propose").default("1000"))
+        .key(uint("tendermint", "timeout_prevote").default("500"))
+        .key(uint("tendermint", "timeout_precommit").default("500"))
+        .key(uint("tendermint", "timeout_delta").default("500"))
+        .key(uint("tendermint", "retransmit_interval").default("1000"))
+        .key(KeyDef::new("tendermint", "params", Text).default("none"))
+        .key(KeyDef::new("zyzzyva", "speculative_execution", Bool).default("true"))
+        .key(uint("zyzzyva", "rollback_cost").default("100"))
+        .key(
+            KeyDef::new("zyzzyva", "authentication", OneOf(vec!["mac", "signature"]))
+                .default("mac"),
+        )
+        .key(KeyDef::new("zyzzyva", "params", Text).default("none"))
+        .key(
+            KeyDef::new("byzantine", "nodes", List(Box::new(UInt)))
+                .default("none")
+                .or_keyword("none"),
+        )
+        .key(
+            KeyDef::new("byzantine", "invalid_authentication", Float)
+                .default("0.0")
+                .range(0.0, 1.0),
+        )
+        .key(
+            KeyDef::new(
+                "adaptive",
+                "protocols",
+                List(Box::new(OneOf(vec!["pbft", "sbft", "tendermint"]))),
+            )
+            .default("sbft pbft"),
+        )
+        .key(uint("adaptive", "switch_timeout").default("3000"))
+        .key(uint("adaptive", "backup_requests").default("50"))
+        .key(KeyDef::new("adaptive", "params", Text).default("none"))
+        .key(
+            uint("dag", "batch_size")
+                .default("100")
+                .range(1.0, 1_000_000.0),
+        )
+        .key(KeyDef::new("dag", "params", Text).default("none"))
+        .key(KeyDef::new("sbft", "params", Text).default("none"))
+        .key(KeyDef::new("steward", "params", Text).default("none"))
+        .key(uint("crypto", "mac_cost").default("2"))
+        .key(uint("crypto", "sign_cost").default("1500"))
+        .key(uint("crypto", "verify_cost").default("300"))
+        .key(uint("crypto", "mac_size").default("10"))
+        .key(uint("crypto", "signature_size").default("128"))
+        .key(uint("crypto", "share_sign_cost").default("1000"))
+        .key(uint("crypto", "share_verify_cost").default("2000"))
+        .key(uint("crypto", "combine_cost").default("100"))
+        .key(uint("crypto", "aggregate_verify_cost").default("2000"))
+        .key(uint("crypto", "share_size").default("48"))
+        .key(uint("crypto", "aggregate_size").default("48"))
+        .key(flag("crypto", "calibrate"))
+        .key(
+            uint("compression", "threshold")
+                .default("none")
+                .or_keyword("none"),
+        )
+        .key(
+            KeyDef::new("compression", "ratio", Float)
+                .default("1.0")
+                .range(0.001, 1.0),
+        )
+        .key(
+            KeyDef::new("compression", "cost", Float)
+                .default("0.0")
+                .range(0.0, 1_000_000.0),
+        )
+        .key(
+            KeyDef::new(
+                "storage",
+                "protocols",
+                List(Box::new(OneOf(vec![
+                    "pbft",
+                    "zyzzyva",
+                    "sbft",
+                    "tendermint",
+                    "adaptive",
+                    "dag",
+                    "steward",
+                ]))),
+            )
+            .default("none")
+            .or_keyword("none"),
+        )
+        .key(KeyDef::new("storage", "mode", OneOf(vec!["sync", "async"])).default("sync"))
+        .key(
+            KeyDef::new(
+                "storage",
+                "distribution",
+                OneOf(vec!["uniform", "exponential"]),
+            )
+            .default("uniform"),
+        )
+        .key(uint("storage", "latency_min").default("100"))
+        .key(uint("storage", "latency_max").default("1000"))
+}
+
+/// Reads the config of the binary from its command line arguments: the path of a config file
+/// followed by overrides of the form 'section.key=value'. The overrides take precedence over the
+/// file, which takes precedence over the defaults of `ini_schema`. Without a path, simulation.ini
+/// is read if it exists. Files ending with '.toml', '.yaml' or '.yml' are read as such, all others
+/// as ini. All misconfigured keys are reported at once.
+pub fn load_config<I: IntoIterator<Item = String>>(args: I) -> IniValues {
+    try_load_config(args).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Like `load_config`, but returns what is misconfigured instead of panicking, e.g. for configs
+/// received over the gRPC control API
+pub fn try_load_config<I: IntoIterator<Item = String>>(args: I) -> Result<IniValues, String> {
+    let mut args: Vec<String> = args.into_iter().collect();
+    let path = match args.first() {
+        Some(arg) if !arg.contains('=') => Some(args.remove(0)),
+        _ if Path::new("simulation.ini").exists() => Some("simulation.ini".to_string()),
+        _ => None,
+    };
+    let overrides = Overrides::parse(&args)?;
+
+    let builder = ConfigBuilder::new(ini_schema());
+    let builder = match &path {
+        Some(path) if !Path::new(path).exists() => {
+            return Err(format!("The config {} does not exist", path))
+        }
+        Some(path) => match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("toml") | Some("yaml") | Some("yml") => builder.layer(
+                ConfigFile::from_path(Path::new(path))
+                    .map_err(|e| format!("Failed to read the config {}: {}", path, e))?,
+            ),
+            _ => builder.layer(mc_utils::ini::get_ini(path)),
+        },
+        None => builder,
+    };
+
+    let errors: Vec<String> = match builder.layer(overrides).build() {
+        Ok(values) => {
+            let errors = fault_tolerance_errors(&values);
+            if errors.is_empty() {
+                return Ok(values);
+            }
+            errors
+        }
+        Err(errors) => errors.iter().map(|e| e.to_string()).collect(),
+    };
+    let errors: Vec<String> = errors.iter().map(|e| format!("  {}", e)).collect();
+    Err(format!(
+        "{} is misconfigured:\n{}",
+        path.as_deref().unwrap_or("the config"),
+        errors.join("\n")
+    ))
+}
+
+// every number of replicas has to tolerate the faults given by '[quorum]f', the numbers of faults
+// derived from the numbers of replicas always hold
+fn fault_tolerance_errors(values: &IniValues) -> Vec<String> {
+    let f = match values.require::<String>("quorum", "f").parse::<u32>() {
+        Ok(f) => f,
+        Err(_) => return Vec::new(),
+    };
+    values
+        .get_vec::<u32>("node", "nodes_vec")
+        .into_iter()
+        .filter_map(|n| check_fault_tolerance(n, f).err())
+        .map(|e| format!("[node]nodes_vec and [quorum]f: {}", e))
+        .collect()
+}
+
+/// Read values from the ini and store in environment. All misconfigured keys are reported at once.
+#[deprecated(note = "use `load_config` and read the values directly")]
+#[allow(deprecated)]
+pub fn initialize_ini() {
+    initialize_config("simulation.ini");
+}
+
+/// Read values from the config file at `path` and store in environment, see `load_config`
+#[deprecated(note = "use `load_config` and read the values directly")]
+#[allow(deprecated)]
+pub fn initialize_config(path: &str) {
+    load_config(vec![path.to_string()]).export();
+}
+
+/// Path of the result log of the run `run` with `nodes` replicas, without the extension. Other
+/// files describing the run share it.
+pub fn result_log_prefix(
+    run: &RunId,
+    nodes: u32,
+    requests: u32,
+    omission_probability: f64,
+) -> String {
+    run.path(&format!(
+        "result_{:0>3}_{:0>3}_{}",
+        nodes,
+        requests,
+        (omission_probability * 100.0) as u32
+    ))
+}
+
+/// Installs the loggers of the diagnostics as the logger of the process, the debug logs are written
+/// to the directory of `run`. The results are not logged, see `ResultsWriter`. Fails instead of
+/// replacing a logger the host installed, see `LoggingBuilder` to configure the logs in detail.
+#[cfg(feature = "file-logging")]
+pub fn initialize_logging(values: &IniValues, run: &RunId) -> Result<Handle, LoggingError> {
+    LoggingBuilder::from_values(values, run).init()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::pbft::state::LeaderRotation;
+
+    #[test]
+    fn check_client_target() {
+        assert_eq!(ClientTarget::from_config("3"), ClientTarget::Node(3));
+        assert_eq!(ClientTarget::from_config("3").pick(5, 4), 3);
+
+        let targets: Vec<u32> = (1..=5)
+            .map(|id| ClientTarget::from_config("round_robin").pick(id, 4))
+            .collect();
+        assert_eq!(targets, vec![2, 3, 4, 1, 2]);
+
+        for id in 1..100 {
+            let target = ClientTarget::Random.pick(id, 4);
+            assert!((1..=4).contains(&target));
+        }
     }
 
-    if mc_utils::ini::env2var("log.result") {
-        for n in mc_utils::ini::env2var_vec::<u32>("node.nodes_vec") {
-            let r: u32 = mc_utils::ini::env2var("simulation.requests");
-            let p: f64 = mc_utils::ini::env2var("network.omission_probability");
+    #[test]
+    fn check_client_target_resolves_primary_and_nearest() {
+        let mut settings = Settings::default();
+        settings.pbft.instances = 2;
+        settings.network.latency = vec![
+            vec![0, 10, 10],
+            vec![10, 0, 10],
+            vec![10, 10, 0],
+            vec![50, 5, 20],
+        ];
 
-            let name_result_logger = format!("result_{}", n);
-            let name_result_log_file = format!(
-                "log/result_{:0>3}_{:0>3}_{}.log",
-                n,
-                r,
-                (p * 100 as f64) as u32
-            );
+        let primary = ClientTarget::from_config("primary").resolve(&settings, 3);
+        assert_eq!(primary, ClientTarget::Primary(2));
+        let leaders: Vec<u32> = (1..=4).map(|id| primary.pick(id, 3)).collect();
+        assert_eq!(leaders, vec![2, 1, 2, 1]);
 
-            let log_result = FileAppender::builder()
-                .encoder(Box::new(PatternEncoder::new("{m}{n}")))
-                .append(false)
-                .build(name_result_log_file.clone())
-                .unwrap();
+        settings.pbft.leader_rotation = LeaderRotation::PerRequest;
+        let rotating = ClientTarget::from_config("primary").resolve(&settings, 3);
+        assert_eq!(rotating, ClientTarget::Primary(3));
+        let leaders: Vec<u32> = (1..=4).map(|id| rotating.pick(id, 3)).collect();
+        assert_eq!(leaders, vec![2, 3, 1, 2]);
 
-            config = config
-                .appender(
-                    Appender::builder().build(name_result_log_file.clone(), Box::new(log_result)),
-                )
-                .logger(
-                    Logger::builder()
-                        .appender(name_result_log_file)
-                        .additive(false)
-                        .build(name_result_logger, LevelFilter::Debug),
-                )
+        let nearest = ClientTarget::from_config("nearest").resolve(&settings, 3);
+        assert_eq!(nearest, ClientTarget::Node(2));
+        assert_eq!(
+            ClientTarget::Random.resolve(&settings, 3),
+            ClientTarget::Random
+        );
+    }
+
+    #[test]
+    fn check_result_loggers_are_kept_apart() {
+        let first = ResultLogger::new();
+        let second = ResultLogger::new();
+        first.collect();
+        second.collect();
+
+        first.log(Time::new(10), Some(1), &ResultEvent::Request(1));
+        second.clone().log(Time::new(20), None, &ResultEvent::Heal);
+        first.log(Time::new(30), Some(2), &ResultEvent::CommittedLocal(1));
+
+        assert_eq!(
+            first.take_lines(),
+            vec!["10;1;1;request", "30;2;1;committed_local"]
+        );
+        assert_eq!(second.drain_lines(), vec!["20;-1;heal"]);
+        second.log(Time::new(40), None, &ResultEvent::Heal);
+        assert_eq!(second.take_lines(), vec!["40;-1;heal"]);
+        assert!(first.take_lines().is_empty());
+    }
+
+    #[test]
+    fn check_ini_schema_reports_all_errors() {
+        let ini = mc_utils::ini::Ini::from_buffer(
+            "[node]\nnode_type = raft\nnodes_vec = 4 7\n\n[network]\nomission_probability = 1.5\n",
+        );
+        let errors: Vec<String> = ini_schema()
+            .load(&ini)
+            .unwrap_err()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "[node]node_type is 'raft', expected one of 'pbft', 'zyzzyva', 'sbft', 'tendermint', 'adaptive', 'dag', 'steward'",
+                "no key [simulation]requests",
+                "[network]omission_probability is '1.5', expected a value from 0 to 1",
+            ]
+        );
+
+        let ini = mc_utils::ini::Ini::from_buffer(
+            "[node]\nnode_type = pbft\nnodes_vec = 4 7\n\n[simulation]\nrequests = 10\n",
+        );
+        let values = ini_schema().load(&ini).unwrap();
+        assert_eq!(values.get::<u32>("simulation", "requests"), Some(10));
+        assert_eq!(values.get::<u64>("crypto", "sign_cost"), Some(1500));
+        assert_eq!(
+            values.get::<String>("quorum", "f"),
+            Some("auto".to_string())
+        );
+    }
+
+    #[test]
+    fn check_speedup_must_be_positive() {
+        let load = |speedup: &str| {
+            let ini = mc_utils::ini::Ini::from_buffer(format!(
+                "[node]\nnode_type = pbft\nnodes_vec = 4\n\n\
+                 [simulation]\nrequests = 10\nspeedup = {}\n",
+                speedup
+            ));
+            ini_schema()
+                .load(&ini)
+                .map_err(|errors| errors.iter().map(|e| e.to_string()).collect::<Vec<_>>())
+        };
+        assert!(load("none").is_ok());
+        assert_eq!(
+            load("0.5").unwrap().get::<f64>("simulation", "speedup"),
+            Some(0.5)
+        );
+        for speedup in &["0", "-2"] {
+            assert_eq!(
+                load(speedup).unwrap_err(),
+                vec![format!(
+                    "[simulation]speedup is '{}', expected a value from 0.001 to 1000000",
+                    speedup
+                )]
+            );
         }
     }
 
-    let config = config
-        .build(Root::builder().appender("stdout").build(LevelFilter::Info))
+    #[test]
+    fn check_config_layers_take_precedence() {
+        let ini = mc_utils::ini::Ini::from_buffer(
+            "[node]\nnode_type = pbft\nnodes_vec = 4\nprocessing_delay = 7\n\n\
+             [simulation]\nrequests = 10\n",
+        );
+        let overrides = Overrides::parse(vec![
+            "--node.nodes_vec=7 10",
+            "simulation.requests=20",
+            "simulation.request_interval=250",
+        ])
         .unwrap();
+        let values = ConfigBuilder::new(ini_schema())
+            .layer(ini)
+            .layer(overrides)
+            .build()
+            .unwrap();
+
+        let settings = Settings::from_values(&values);
+        assert_eq!(settings.node.nodes, 7);
+        assert_eq!(settings.simulation.requests, 20);
+        assert_eq!(settings.simulation.request_interval, 250);
+        assert_eq!(settings.node.processing_delay, 7);
+        assert_eq!(settings.network.delay_max, 200.0);
+        assert_eq!(values.get_vec::<u32>("node", "nodes_vec"), vec![7, 10]);
+
+        assert!(Overrides::parse(vec!["simulation.ini"]).is_err());
+    }
 
-    log4rs::init_config(config).unwrap();
+    #[test]
+    fn check_too_few_replicas_for_the_faults_are_rejected() {
+        let values = |f: &str| {
+            let ini = mc_utils::ini::Ini::from_buffer(format!(
+                "[node]\nnode_type = pbft\nnodes_vec = 4 7 10\n\n[simulation]\nrequests = 10\n\n\
+                 [quorum]\nf = {}\n",
+                f
+            ));
+            ini_schema().load(&ini).unwrap()
+        };
+        assert!(fault_tolerance_errors(&values("auto")).is_empty());
+        assert!(fault_tolerance_errors(&values("1")).is_empty());
+        assert_eq!(
+            fault_tolerance_errors(&values("2")),
+            vec!["[node]nodes_vec and [quorum]f: 4 replicas cannot tolerate 2 faults, at least 7 are required"]
+        );
+        assert_eq!(fault_tolerance_errors(&values("3")).len(), 2);
+    }
+
+    #[test]
+    fn check_protocol_params_reach_the_nodes() {
+        let ini = mc_utils::ini::Ini::from_buffer(
+            "[node]\nnode_type = pbft\nnodes_vec = 4\n\n[simulation]\nrequests = 10\n\n\
+             [pbft]\nparams = batch_size:10 name:fast\n\n[steward]\nparams = name:sites\n",
+        );
+        let values = ini_schema().load(&ini).unwrap();
+        let settings = Settings::from_values(&values);
+        assert!(settings.params_of("zyzzyva").is_empty());
+        assert_eq!(settings.params_of("steward")["name"], "sites");
+
+        let mut config = SimulationConfig::new(settings);
+        let node = config.create_node_config(&ResultLogger::new());
+        assert_eq!(node.param("batch_size", 1), 10);
+        assert_eq!(node.param("name", String::new()), "fast");
+        assert_eq!(node.param("checkpoint_interval", 100), 100);
+    }
 }
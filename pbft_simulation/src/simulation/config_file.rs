@@ -0,0 +1,306 @@
+/***************************************************************************************************
+Configuration in TOML or YAML as an alternative to simulation.ini. The sections and keys are the
+same as in the ini, values may also be lists. On top, the files may contain nested sections that do
+not map well onto flat ini keys:
+
+    nodes:        # per-node overrides
+      3:
+        processing_delay: 50
+    network:
+      topology: matrix
+      latency:    # latency (ms) of the link from the node of the row to the node of the column
+        - [0, 10, 80]
+        - [10, 0, 80]
+        - [80, 80, 0]
+      loss:       # omission probability of the link from a node to another, '*' for any node
+        1-3: 0.2
+        "*-2": 0.1
+    pbft:
+      params:     # free-form parameters of the protocol
+        batch_size: 10
+    composition:  # further clusters next to the configured one, by name
+      side:
+        node_type: zyzzyva
+        nodes: 4
+        requests: 500
+
+They are translated into the flat keys the ini uses for them, '[node]processing_delays = 3:50',
+'[network]latency = 0 10 80, 10 0 80, 80 80 0', '[network]loss = 1-3:0.2 *-2:0.1',
+'[pbft]params = batch_size:10' and '[node]composition = zyzzyva:4:500', so the values of all formats
+are checked by the same schema.
+***************************************************************************************************/
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use mc_utils::ini::ConfigSource;
+use serde_yaml::Value;
+
+/// The values of a TOML or YAML config file, keyed by section and key
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigFile {
+    values: BTreeMap<(String, String), String>,
+}
+
+impl ConfigSource for ConfigFile {
+    fn value(&self, section: &str, key: &str) -> Option<String> {
+        self.values
+            .get(&(section.to_string(), key.to_string()))
+            .cloned()
+    }
+
+    fn sections(&self) -> Vec<String> {
+        let mut sections: Vec<String> = self.values.keys().map(|(s, _)| s.clone()).collect();
+        sections.dedup();
+        sections
+    }
+}
+
+impl ConfigFile {
+    /// Reads the config file at `path`, the format is chosen by the extension
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => ConfigFile::from_toml(&text),
+            Some("yaml") | Some("yml") => ConfigFile::from_yaml(&text),
+            _ => Err(format!(
+                "{} is neither a '.toml' nor a '.yaml' file",
+                path.display()
+            )),
+        }
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let root: Value = toml::from_str(text).map_err(|e| e.to_string())?;
+        ConfigFile::from_value(&root)
+    }
+
+    pub fn from_yaml(text: &str) -> Result<Self, String> {
+        let root: Value = serde_yaml::from_str(text).map_err(|e| e.to_string())?;
+        ConfigFile::from_value(&root)
+    }
+
+    // flattens the sections of the file into the keys of the ini
+    fn from_value(root: &Value) -> Result<Self, String> {
+        let mut config = ConfigFile::default();
+
+        for (section, keys) in mapping(root, "the config file")? {
+            if section == "nodes" {
+                config.add_node_overrides(keys)?;
+                continue;
+            }
+            if section == "composition" {
+                config.add_clusters(keys)?;
+                continue;
+            }
+            for (key, value) in mapping(keys, &section)? {
+                let value = match (section.as_str(), key.as_str()) {
+                    ("network", "latency") => latency_matrix(value)?,
+                    ("network", "loss") => pairs(value, "[network]loss")?,
+                    (_, "params") => pairs(value, &format!("[{}]params", section))?,
+                    _ => list(value)
+                        .ok_or_else(|| format!("[{}]{} must be a value or a list", section, key))?,
+                };
+                config.values.insert((section.clone(), key), value);
+            }
+        }
+        Ok(config)
+    }
+
+    // translates the per-node sections into '<id>:<value> ...' lists
+    fn add_node_overrides(&mut self, nodes: &Value) -> Result<(), String> {
+        let mut processing_delays = Vec::new();
+        for (id, keys) in mapping(nodes, "nodes")? {
+            let id: u32 = id
+                .parse()
+                .map_err(|_| format!("nodes must be keyed by node id, got '{}'", id))?;
+            for (key, value) in mapping(keys, &id.to_string())? {
+                match (key.as_str(), scalar(value)) {
+                    ("processing_delay", Some(delay)) => {
+                        processing_delays.push(format!("{}:{}", id, delay))
+                    }
+                    _ => {
+                        return Err(format!(
+                            "nodes.{}.{} is not available, allowed is 'processing_delay'",
+                            id, key
+                        ))
+                    }
+                }
+            }
+        }
+        self.values.insert(
+            ("node".to_string(), "processing_delays".to_string()),
+            processing_delays.join(" "),
+        );
+        Ok(())
+    }
+
+    // translates the sections of the clusters into a '<node_type>:<nodes>[:<requests>] ...' list
+    fn add_clusters(&mut self, clusters: &Value) -> Result<(), String> {
+        let mut composition = Vec::new();
+        for (name, keys) in mapping(clusters, "composition")? {
+            let mut node_type = None;
+            let mut nodes = None;
+            let mut requests = None;
+            for (key, value) in mapping(keys, &name)? {
+                let slot = match key.as_str() {
+                    "node_type" => &mut node_type,
+                    "nodes" => &mut nodes,
+                    "requests" => &mut requests,
+                    _ => {
+                        return Err(format!(
+                            "composition.{}.{} is not available, allowed are 'node_type', 'nodes', 'requests'",
+                            name, key
+                        ))
+                    }
+                };
+                *slot = Some(
+                    scalar(value)
+                        .ok_or_else(|| format!("composition.{}.{} must be a value", name, key))?,
+                );
+            }
+            match (node_type, nodes) {
+                (Some(node_type), Some(nodes)) => composition.push(match requests {
+                    Some(requests) => format!("{}:{}:{}", node_type, nodes, requests),
+                    None => format!("{}:{}", node_type, nodes),
+                }),
+                _ => {
+                    return Err(format!(
+                        "composition.{} needs a 'node_type' and a number of 'nodes'",
+                        name
+                    ))
+                }
+            }
+        }
+        self.values.insert(
+            ("node".to_string(), "composition".to_string()),
+            composition.join(" "),
+        );
+        Ok(())
+    }
+}
+
+// returns the entries of a mapping with their keys as text
+fn mapping<'a>(value: &'a Value, name: &str) -> Result<Vec<(String, &'a Value)>, String> {
+    match value {
+        Value::Mapping(entries) => entries
+            .iter()
+            .map(|(key, value)| {
+                scalar(key)
+                    .map(|key| (key, value))
+                    .ok_or_else(|| format!("{} has a key that is not a name", name))
+            })
+            .collect(),
+        _ => Err(format!("{} must consist of named sections", name)),
+    }
+}
+
+// returns a single value as text
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+// returns a value or a list of values as text, lists separated by whitespace like in the ini
+fn list(value: &Value) -> Option<String> {
+    match value {
+        Value::Sequence(values) => {
+            let values: Option<Vec<String>> = values.iter().map(scalar).collect();
+            values.map(|values| values.join(" "))
+        }
+        value => scalar(value),
+    }
+}
+
+// returns the rows of a latency matrix separated by commas
+fn latency_matrix(value: &Value) -> Result<String, String> {
+    if let Some(value) = scalar(value) {
+        return Ok(value);
+    }
+    let error = || "[network]latency must be a list of rows of latencies".to_string();
+    match value {
+        Value::Sequence(rows) => {
+            let rows: Option<Vec<String>> = rows
+                .iter()
+                .map(|row| match row {
+                    Value::Sequence(_) => list(row),
+                    _ => None,
+                })
+                .collect();
+            rows.map(|rows| rows.join(", ")).ok_or_else(error)
+        }
+        _ => Err(error()),
+    }
+}
+
+// returns a mapping as '<key>:<value> ...', e.g. the omission probabilities of the links or the
+// parameters of a protocol
+fn pairs(value: &Value, name: &str) -> Result<String, String> {
+    if let Some(value) = scalar(value) {
+        return Ok(value);
+    }
+    let pairs: Option<Vec<String>> = mapping(value, name)?
+        .into_iter()
+        .map(|(key, value)| scalar(value).map(|v| format!("{}:{}", key, v)))
+        .collect();
+    pairs
+        .map(|pairs| pairs.join(" "))
+        .ok_or_else(|| format!("{} must map names to values", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_config_file_yaml_and_toml_agree() {
+        let yaml = ConfigFile::from_yaml(
+            "node:\n  node_type: sbft\n  nodes_vec: [4, 7]\nnodes:\n  3:\n    processing_delay: 50\n  \
+             4:\n    processing_delay: 20\nnetwork:\n  latency:\n    - [0, 10]\n    - [10, 0]\n  \
+             loss:\n    1-2: 0.5\n",
+        )
+        .unwrap();
+        let toml = ConfigFile::from_toml(
+            "[node]\nnode_type = \"sbft\"\nnodes_vec = [4, 7]\n\n[nodes.3]\nprocessing_delay = 50\n\n\
+             [nodes.4]\nprocessing_delay = 20\n\n[network]\nlatency = [[0, 10], [10, 0]]\n\n\
+             [network.loss]\n1-2 = 0.5\n",
+        )
+        .unwrap();
+
+        assert_eq!(yaml, toml);
+        assert_eq!(yaml.value("node", "nodes_vec"), Some("4 7".to_string()));
+        assert_eq!(
+            yaml.value("node", "processing_delays"),
+            Some("3:50 4:20".to_string())
+        );
+        assert_eq!(
+            yaml.value("network", "latency"),
+            Some("0 10, 10 0".to_string())
+        );
+        assert_eq!(yaml.value("network", "loss"), Some("1-2:0.5".to_string()));
+        assert_eq!(yaml.value("network", "topology"), None);
+
+        assert!(ConfigFile::from_yaml("nodes:\n  3:\n    clock: 5\n").is_err());
+    }
+
+    #[test]
+    fn check_composition_sections() {
+        let yaml = ConfigFile::from_yaml(
+            "composition:\n  a:\n    node_type: zyzzyva\n    nodes: 4\n    requests: 50\n  \
+             b:\n    node_type: dag\n    nodes: 7\n",
+        )
+        .unwrap();
+        assert_eq!(
+            yaml.value("node", "composition"),
+            Some("zyzzyva:4:50 dag:7".to_string())
+        );
+
+        assert!(ConfigFile::from_yaml("composition:\n  a:\n    node_type: pbft\n").is_err());
+        assert!(ConfigFile::from_yaml("composition:\n  a:\n    f: 1\n").is_err());
+    }
+}
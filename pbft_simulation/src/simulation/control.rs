@@ -0,0 +1,195 @@
+/***************************************************************************************************
+Control socket of the simulation. Accepts TCP connections and reads one command per line, e.g. with
+'nc 127.0.0.1 9899'. Commands are forwarded to the running simulation as admin events.
+***************************************************************************************************/
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use log::{info, warn};
+
+use crate::simulation::config::RequestBatchConfig;
+use crate::simulation::event::{AdminType, EventType};
+use crate::simulation::metrics::metrics;
+use crate::simulation::time::Time;
+
+const HELP: &str = "commands: inject-requests <number> [interval ms] [at ms], crash-node <id>, \
+                    partition <id>[,<id>...], heal, pause, resume, step [number], stats, \
+                    stop [at ms], help";
+
+/// A command received on the control socket
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Admin(AdminType),
+    Stats,
+    Help,
+}
+
+/// Parses a single line of the control protocol
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let mut words = line.split_whitespace();
+    let command = words.next().ok_or_else(|| "empty command".to_string())?;
+    let mut number = |name: &str| -> Result<Option<u32>, String> {
+        match words.next() {
+            Some(word) => word
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("{} must be a number, got '{}'", name, word)),
+            None => Ok(None),
+        }
+    };
+
+    let command = match command {
+        "inject-requests" => {
+            let requests = number("number")?.ok_or("inject-requests needs the number of requests")?;
+            let interval = number("interval")?.unwrap_or(1000);
+            let config = RequestBatchConfig::new(requests, interval);
+            match number("at")? {
+                Some(at) => Command::Admin(AdminType::ClientRequestsAt(
+                    Time::new(u64::from(at)),
+                    config,
+                )),
+                None => Command::Admin(AdminType::ClientRequests(config)),
+            }
+        }
+        "crash-node" => Command::Admin(AdminType::CrashNode(
+            number("id")?.ok_or("crash-node needs the id of the node")?,
+        )),
+        "partition" => {
+            let nodes = words.next().ok_or("partition needs the ids of the nodes")?;
+            let nodes = nodes
+                .split(',')
+                .map(|id| id.parse().map_err(|_| format!("'{}' is not a node id", id)))
+                .collect::<Result<Vec<u32>, String>>()?;
+            Command::Admin(AdminType::Partition(nodes))
+        }
+        "heal" => Command::Admin(AdminType::Heal),
+        "pause" => Command::Admin(AdminType::Pause),
+        "resume" => Command::Admin(AdminType::Resume),
+        "step" => Command::Admin(AdminType::Step(number("number")?.unwrap_or(1))),
+        "stats" => Command::Stats,
+        "stop" => match number("at")? {
+            Some(at) => Command::Admin(AdminType::StopAt(Time::new(u64::from(at)))),
+            None => Command::Admin(AdminType::Stop),
+        },
+        "help" => Command::Help,
+        _ => return Err(format!("unknown command '{}', {}", command, HELP)),
+    };
+    Ok(command)
+}
+
+/// The control socket, commands are forwarded to the simulation attached last
+#[derive(Debug, Clone)]
+pub struct ControlServer {
+    simulation: Arc<Mutex<Option<Sender<EventType>>>>,
+}
+
+impl ControlServer {
+    /// Starts a thread accepting connections on `address`
+    pub fn serve(address: &str) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        info!("Control socket listening on {}", address);
+
+        let server = ControlServer {
+            simulation: Arc::new(Mutex::new(None)),
+        };
+        let server_clone = server.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let server = server_clone.clone();
+                        thread::spawn(move || {
+                            if let Err(e) = server.handle_connection(stream) {
+                                warn!("Control connection failed: {}", e);
+                            }
+                        });
+                    }
+                    Err(e) => warn!("Failed to accept a control connection: {}", e),
+                }
+            }
+        });
+        Ok(server)
+    }
+
+    /// Forwards all following commands to the simulation behind `sender`
+    pub fn attach(&self, sender: Sender<EventType>) {
+        *self.simulation.lock().unwrap() = Some(sender);
+    }
+
+    fn handle_connection(&self, stream: TcpStream) -> io::Result<()> {
+        let mut writer = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let reply = match parse_command(&line) {
+                Ok(Command::Admin(admin_type)) => self.send(admin_type),
+                Ok(Command::Stats) => metrics().render(),
+                Ok(Command::Help) => format!("{}\n", HELP),
+                Err(e) => format!("error: {}\n", e),
+            };
+            writer.write_all(reply.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn send(&self, admin_type: AdminType) -> String {
+        let simulation = self.simulation.lock().unwrap();
+        match &*simulation {
+            Some(sender) if sender.send(EventType::Admin(admin_type)).is_ok() => "ok\n".to_string(),
+            _ => "error: no simulation is running\n".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_parse_commands() {
+        assert_eq!(
+            parse_command("inject-requests 10"),
+            Ok(Command::Admin(AdminType::ClientRequests(
+                RequestBatchConfig::new(10, 1000)
+            )))
+        );
+        assert_eq!(
+            parse_command("inject-requests 10 5 2000"),
+            Ok(Command::Admin(AdminType::ClientRequestsAt(
+                Time::new(2000),
+                RequestBatchConfig::new(10, 5)
+            )))
+        );
+        assert_eq!(
+            parse_command("crash-node 3"),
+            Ok(Command::Admin(AdminType::CrashNode(3)))
+        );
+        assert_eq!(
+            parse_command(" partition 1,2 "),
+            Ok(Command::Admin(AdminType::Partition(vec![1, 2])))
+        );
+        assert_eq!(parse_command("stats"), Ok(Command::Stats));
+        assert_eq!(
+            parse_command("step"),
+            Ok(Command::Admin(AdminType::Step(1)))
+        );
+        assert_eq!(
+            parse_command("step 5"),
+            Ok(Command::Admin(AdminType::Step(5)))
+        );
+        assert_eq!(parse_command("stop"), Ok(Command::Admin(AdminType::Stop)));
+        assert_eq!(
+            parse_command("stop 3000"),
+            Ok(Command::Admin(AdminType::StopAt(Time::new(3000))))
+        );
+        assert!(parse_command("crash-node").is_err());
+        assert!(parse_command("partition 1,x").is_err());
+        assert!(parse_command("reboot").is_err());
+    }
+}
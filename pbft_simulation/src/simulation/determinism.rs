@@ -0,0 +1,118 @@
+/***************************************************************************************************
+Self-check of the reproducibility of a run: the same configuration is run twice with the same seed
+and the result logs of both runs have to be identical. A source of randomness that is not drawn from
+the seed of the run, e.g. the iteration order of a hash map deciding what a node sends, shows up as
+the first line at which the runs differ.
+***************************************************************************************************/
+
+use std::fmt;
+
+use log::{error, info};
+use rand::Rng;
+
+use crate::simulation::report::SimulationReport;
+use crate::simulation::run_simulation;
+use crate::simulation::settings::Settings;
+
+/// Two runs of the same configuration and seed that differ
+#[derive(Debug, Clone, PartialEq)]
+pub struct Nondeterminism {
+    /// The seed both runs were started with
+    pub seed: u64,
+    /// Index of the first line of the result logs that differs
+    pub line: usize,
+    /// The line of the first and of the second run, `None` if that run ended before it
+    pub first: Option<String>,
+    pub second: Option<String>,
+}
+
+impl fmt::Display for Nondeterminism {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let line = |line: &Option<String>| match line {
+            Some(line) => line.clone(),
+            None => "<end of the result log>".to_string(),
+        };
+        write!(
+            f,
+            "Two runs with the seed {} diverge at line {} of the result log:\n  first:  {}\n  second: {}",
+            self.seed,
+            self.line,
+            line(&self.first),
+            line(&self.second)
+        )
+    }
+}
+
+/// Runs `settings` twice with the same seed, `simulation.seed` or a random one, and compares the
+/// result logs of the runs line by line. Returns the report of the first run if they are identical.
+pub fn check(mut settings: Settings) -> Result<SimulationReport, Nondeterminism> {
+    let seed = *settings
+        .simulation
+        .seed
+        .get_or_insert_with(|| rand::thread_rng().gen());
+    info!(
+        "Checking that two runs with the seed {} are identical",
+        seed
+    );
+
+    let first = run_simulation(settings.clone());
+    let second = run_simulation(settings);
+    match first_difference(&first.results, &second.results) {
+        None => {
+            info!(
+                "Both runs are identical, {} lines of the result log",
+                first.results.len()
+            );
+            Ok(first)
+        }
+        Some(line) => {
+            let nondeterminism = Nondeterminism {
+                seed,
+                line,
+                first: first.results.get(line).cloned(),
+                second: second.results.get(line).cloned(),
+            };
+            error!("{}", nondeterminism);
+            Err(nondeterminism)
+        }
+    }
+}
+
+/// Index of the first line in which `first` and `second` differ, `None` if they are equal
+fn first_difference(first: &[String], second: &[String]) -> Option<usize> {
+    first
+        .iter()
+        .zip(second)
+        .position(|(a, b)| a != b)
+        .or_else(|| Some(first.len().min(second.len())).filter(|_| first.len() != second.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_first_difference() {
+        let lines = |lines: &[&str]| lines.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        let run = lines(&["0;1;request;1", "5;2;prepare;1"]);
+        assert_eq!(first_difference(&run, &run), None);
+        assert_eq!(
+            first_difference(&run, &lines(&["0;1;request;1", "5;3;prepare;1"])),
+            Some(1)
+        );
+        assert_eq!(first_difference(&run, &lines(&["0;1;request;1"])), Some(1));
+    }
+
+    #[test]
+    fn check_seeded_runs_are_identical() {
+        let mut settings = Settings::default();
+        settings.simulation.requests = 5;
+        settings.network.omission_probability = 0.1;
+        settings.network.delay_min = 20.0;
+        settings.network.delay_max = 200.0;
+
+        let report = check(settings).unwrap();
+        assert_eq!(report.agreement, Ok(()));
+        assert!(!report.results.is_empty());
+    }
+}
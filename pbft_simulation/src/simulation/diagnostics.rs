@@ -0,0 +1,192 @@
+/***************************************************************************************************
+Diagnostics of events that are before the current time of the simulation. Such an event is a bug of
+a node or of the simulation; it is dumped with the event at the head of the queue and the events
+handled last, before the run aborts or the event is moved to the current time.
+***************************************************************************************************/
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::simulation::event::Event;
+use crate::simulation::time::Time;
+
+/// What happens to an event that is before the current time
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum TimeViolationPolicy {
+    /// The run panics with the diagnostics
+    Abort,
+    /// The event is handled at the current time, the diagnostics are logged as a warning
+    Clamp,
+}
+
+impl FromStr for TimeViolationPolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy {
+            "abort" => Ok(TimeViolationPolicy::Abort),
+            "clamp" => Ok(TimeViolationPolicy::Clamp),
+            _ => Err(format!(
+                "time violation policy '{}' is not available, allowed are 'abort', 'clamp'",
+                policy
+            )),
+        }
+    }
+}
+
+/// The last events handled by the simulation, only kept if a capacity is configured
+#[derive(Debug, Default)]
+pub struct EventHistory {
+    capacity: usize,
+    events: VecDeque<String>,
+}
+
+impl EventHistory {
+    pub fn new(capacity: usize) -> Self {
+        EventHistory {
+            capacity,
+            events: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Remembers `event`, the oldest one is forgotten once the history is full
+    pub fn record(&mut self, event: &Event) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(format!("{:?}", event));
+    }
+
+    /// The remembered events, the oldest first
+    pub fn events(&self) -> impl Iterator<Item = &String> {
+        self.events.iter()
+    }
+}
+
+/// An event that is before the current time, with what is known about how it came to be
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeViolation {
+    /// The current time of the simulation
+    pub now: Time,
+    pub event: String,
+    pub event_time: Time,
+    /// The node that created the event, see `Event::node`
+    pub node: Option<u32>,
+    /// The message the event carries
+    pub message: Option<String>,
+    /// The event handled after this one
+    pub queue_head: Option<String>,
+    /// The events handled before this one, the oldest first
+    pub history: Vec<String>,
+}
+
+impl TimeViolation {
+    pub fn new(
+        now: Time,
+        event: &Event,
+        queue_head: Option<&Event>,
+        history: &EventHistory,
+    ) -> Self {
+        TimeViolation {
+            now,
+            event: format!("{:?}", event),
+            event_time: event.time,
+            node: event.node(),
+            message: event
+                .message()
+                .map(|m| format!("{} {}", m.protocol(), m.message_type())),
+            queue_head: queue_head.map(|e| format!("{:?}", e)),
+            history: history.events().cloned().collect(),
+        }
+    }
+}
+
+impl fmt::Display for TimeViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "The simulation handled an event that was before its current time: {}µs < {}µs",
+            self.event_time.micros(),
+            self.now.micros()
+        )?;
+        writeln!(f, "  event: {}", self.event)?;
+        match self.node {
+            Some(node) => writeln!(f, "  created by node: {}", node)?,
+            None => writeln!(f, "  created by node: none")?,
+        }
+        writeln!(
+            f,
+            "  message: {}",
+            self.message.as_deref().unwrap_or("none")
+        )?;
+        writeln!(
+            f,
+            "  queue head: {}",
+            self.queue_head.as_deref().unwrap_or("none")
+        )?;
+        if self.history.is_empty() {
+            write!(f, "  history: not recorded, see simulation.event_history")
+        } else {
+            write!(f, "  history (oldest first):")?;
+            for event in &self.history {
+                write!(f, "\n    {}", event)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::event::Message;
+
+    #[test]
+    fn check_history_keeps_the_last_events() {
+        let mut history = EventHistory::new(2);
+        for time in 1..=3 {
+            history.record(&Event::new_inbox_ready(time as u32, Time::new(time)));
+        }
+        let events: Vec<&String> = history.events().collect();
+        assert_eq!(events.len(), 2);
+        assert!(events[0].contains("InboxReady(2)"));
+        assert!(events[1].contains("InboxReady(3)"));
+
+        let mut disabled = EventHistory::new(0);
+        disabled.record(&Event::new_inbox_ready(1, Time::new(1)));
+        assert_eq!(disabled.events().count(), 0);
+    }
+
+    #[test]
+    fn check_violation_shows_its_context() {
+        let mut history = EventHistory::new(4);
+        history.record(&Event::new_timer_fired(3, 7, 1, Time::new(20)));
+        let event = Event::new_broadcast(2, 4, Message::Dummy, Time::new(10));
+        let head = Event::new_reception(4, Message::Dummy, Time::new(25));
+
+        let violation = TimeViolation::new(Time::new(20), &event, Some(&head), &history);
+        assert_eq!(violation.node, Some(2));
+        assert_eq!(violation.message.as_deref(), Some("Dummy Dummy"));
+
+        let dump = violation.to_string();
+        assert!(dump.contains("before its current time: 10000µs < 20000µs"));
+        assert!(dump.contains("created by node: 2"));
+        assert!(dump.contains("queue head: Event"));
+        assert!(dump.contains("TimerFired"));
+
+        let violation = TimeViolation::new(Time::new(20), &event, None, &EventHistory::new(0));
+        assert!(violation.to_string().contains("history: not recorded"));
+    }
+
+    #[test]
+    fn check_policy_from_str() {
+        assert_eq!("clamp".parse(), Ok(TimeViolationPolicy::Clamp));
+        assert!("ignore".parse::<TimeViolationPolicy>().is_err());
+    }
+}
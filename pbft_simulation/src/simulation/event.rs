@@ -3,75 +3,201 @@ Everything related to events.
 ***************************************************************************************************/
 
 use std::cmp::Ordering;
+use std::sync::Arc;
 
+use serde::{Deserialize, Serialize};
+
+use crate::network::event::NetworkEvent;
+use crate::node::adaptive::messages::AdaptiveMessage;
+use crate::node::dag::messages::DAGMessage;
 use crate::node::pbft::messages::PBFTMessage;
+use crate::node::sbft::messages::SBFTMessage;
+use crate::node::steward::messages::StewardMessage;
+use crate::node::tendermint::messages::TendermintMessage;
 use crate::node::zyzzyva::messages::ZyzzyvaMessage;
 use crate::simulation::config::RequestBatchConfig;
 use crate::simulation::time::Time;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum AdminType {
     ClientRequests(RequestBatchConfig),
+    /// A batch of requests whose first request is issued at the given time, instead of when the
+    /// admin event is handled
+    ClientRequestsAt(Time, RequestBatchConfig),
+    /// The node stops sending, receiving and handling timers
+    CrashNode(u32),
+    /// The given nodes are cut off from all other nodes
+    Partition(Vec<u32>),
+    /// Removes the partition
+    Heal,
+    /// Only admin events are handled until the simulation is resumed
+    Pause,
+    Resume,
+    /// A paused simulation handles the next `n` events
+    Step(u32),
     Stop,
+    /// The simulation stops once it reached the given time
+    StopAt(Time),
 }
 
-/// The types of events that can happen in the simulation.
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+/// The types of events that can happen in the simulation. They are not ordered, the queue orders
+/// the events by their time.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum EventType {
     Admin(AdminType),
-    Network,
+    /// Reconfigures the network once the simulation reached the time of the event
+    Network(NetworkEvent),
     Broadcast(Broadcast),
+    Gossip(Gossip),
     Reception(Reception),
-    Timeout(Timeout),
+    SetTimer(TimerRequest),
+    CancelTimer(TimerCancel),
+    TimerFired(TimerFired),
+    /// A node whose rate is limited is ready for the next message waiting in its inbox
+    InboxReady(u32),
 }
 
 // An event abstraction, contains the time of the event and the event_type
-#[derive(Debug, Eq, PartialEq, PartialOrd)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Event {
     pub time: Time,
     pub event_type: EventType,
+    /// Immediate admin events are handled before all pending events, regardless of their time
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub immediate: bool,
 }
 
 impl Event {
     fn new(event_type: EventType, time: Time) -> Self {
-        Event { event_type, time }
+        Event {
+            event_type,
+            time,
+            immediate: false,
+        }
     }
 
-    /// To generate a new admin event
+    /// To generate a stop that is handled before all pending events
     pub fn new_admin_stop() -> Self {
-        Event::new(EventType::Admin(AdminType::Stop), Time::new(0))
+        Event::new_admin(AdminType::Stop)
     }
 
-    // Generate a batch of requests
+    /// To generate a stop once the simulation reached `time`, the events before are handled
+    pub fn new_admin_stop_at(time: Time) -> Self {
+        Event::new_admin_at(AdminType::StopAt(time), time)
+    }
+
+    // Generate a batch of requests, issued from the start of the simulation
     pub fn new_admin_requests(number: u32, interval: u32) -> Self {
-        Event::new(
-            EventType::Admin(AdminType::ClientRequests(RequestBatchConfig::new(
-                number, interval,
-            ))),
-            Time::new(0),
-        )
+        Event::new_admin_requests_from_config(RequestBatchConfig::new(number, interval))
+    }
+
+    /// To generate any admin event that is handled before all pending events
+    pub fn new_admin(admin_type: AdminType) -> Self {
+        Event {
+            immediate: true,
+            ..Event::new(EventType::Admin(admin_type), Time::new(0))
+        }
+    }
+
+    /// To generate an admin event that is executed once the simulation reached `time`
+    pub fn new_admin_at(admin_type: AdminType, time: Time) -> Self {
+        Event::new(EventType::Admin(admin_type), time)
+    }
+
+    /// Generate a batch of requests, the first one is issued at `time`
+    pub fn new_admin_requests_at(time: Time, config: RequestBatchConfig) -> Self {
+        Event::new_admin_at(AdminType::ClientRequestsAt(time, config), time)
     }
 
+    /// Generate a batch of requests, issued from the start of the simulation
     pub fn new_admin_requests_from_config(config: RequestBatchConfig) -> Self {
-        Event::new(
-            EventType::Admin(AdminType::ClientRequests(config)),
-            Time::new(0),
-        )
+        Event::new_admin_at(AdminType::ClientRequests(config), Time::new(0))
+    }
+
+    /// To generate a network event that is executed once the simulation reached `time`
+    pub fn new_network(network_event: NetworkEvent, time: Time) -> Self {
+        Event::new(EventType::Network(network_event), time)
+    }
+
+    /// To hand the next waiting message to node `id`, only created by the simulation
+    pub fn new_inbox_ready(id: u32, time: Time) -> Self {
+        Event::new(EventType::InboxReady(id), time)
+    }
+
+    /// Returns `true` for admin events
+    pub fn is_admin(&self) -> bool {
+        matches!(self.event_type, EventType::Admin(_))
+    }
+
+    /// The node that created the event, i.e. the sender of a message or the node of a timer, and
+    /// the receiver of a reception
+    pub fn node(&self) -> Option<u32> {
+        match &self.event_type {
+            EventType::Admin(_) | EventType::Network(_) => None,
+            EventType::Broadcast(b) => Some(b.id_from),
+            EventType::Gossip(g) => Some(g.id_from),
+            EventType::Reception(r) => Some(r.id),
+            EventType::SetTimer(t) => Some(t.id),
+            EventType::CancelTimer(t) => Some(t.id),
+            EventType::TimerFired(t) => Some(t.id),
+            EventType::InboxReady(id) => Some(*id),
+        }
+    }
+
+    /// The message the event carries, if any
+    pub fn message(&self) -> Option<&Message> {
+        match &self.event_type {
+            EventType::Broadcast(b) => Some(&b.message),
+            EventType::Gossip(g) => Some(&g.message),
+            EventType::Reception(r) => Some(&r.message),
+            _ => None,
+        }
     }
 
     /// To generate a new broadcast event
-    pub fn new_broadcast(id_from: u32, id_to: u32, message: Message, time: Time) -> Self {
+    pub fn new_broadcast(
+        id_from: u32,
+        id_to: u32,
+        message: impl Into<Arc<Message>>,
+        time: Time,
+    ) -> Self {
         Event::new(
             EventType::Broadcast(Broadcast::new(id_from, id_to, message)),
             time,
         )
     }
 
+    /// To generate a new broadcast of a message with a known size in bytes
+    pub fn new_broadcast_sized(
+        id_from: u32,
+        id_to: u32,
+        message: impl Into<Arc<Message>>,
+        time: Time,
+        size: u32,
+    ) -> Self {
+        let mut broadcast = Broadcast::new(id_from, id_to, message);
+        broadcast.size = size;
+        Event::new(EventType::Broadcast(broadcast), time)
+    }
+
+    /// To generate a new gossip, the network disseminates the message to all other nodes
+    pub fn new_gossip(
+        id_from: u32,
+        message: impl Into<Arc<Message>>,
+        time: Time,
+        size: u32,
+    ) -> Self {
+        Event::new(
+            EventType::Gossip(Gossip::new(id_from, message, size)),
+            time,
+        )
+    }
+
     /// To generate a new broadcast with custom parameters
     pub fn new_broadcast_custom(
         id_from: u32,
         id_to: u32,
-        message: Message,
+        message: impl Into<Arc<Message>>,
         time: Time,
         reliable: bool,
         fixed_delay: Option<Time>,
@@ -85,90 +211,320 @@ impl Event {
     }
 
     /// To generate a new reception event
-    pub fn new_reception(id: u32, message: Message, time: Time) -> Self {
+    pub fn new_reception(id: u32, message: impl Into<Arc<Message>>, time: Time) -> Self {
         Event::new(EventType::Reception(Reception::new(id, message)), time)
     }
 
-    pub fn new_timeout(c_id: u32, message: Message, time: Time) -> Self {
-        Event::new(EventType::Timeout(Timeout::new(c_id, message)), time)
+    /// The message of the broadcast, gossip or reception was compressed, its receiver needs
+    /// `micros` to decompress it. Other events are left as they are.
+    pub fn with_decompression(mut self, micros: u64) -> Self {
+        match &mut self.event_type {
+            EventType::Broadcast(b) => b.decompression = micros,
+            EventType::Gossip(g) => g.decompression = micros,
+            EventType::Reception(r) => r.decompression = micros,
+            _ => {}
+        }
+        self
+    }
+
+    /// To generate a new reception of a message whose authenticator is invalid
+    pub fn new_forged_reception(id: u32, message: impl Into<Arc<Message>>, time: Time) -> Self {
+        let mut reception = Reception::new(id, message);
+        reception.forged = true;
+        Event::new(EventType::Reception(reception), time)
+    }
+
+    /// To request a timer for node `id` that fires after `duration` ms on the node's local clock
+    pub fn new_timer_request(id: u32, token: u64, duration: u64, time: Time) -> Self {
+        Event::new(
+            EventType::SetTimer(TimerRequest::new(id, token, duration)),
+            time,
+        )
+    }
+
+    /// To cancel the timer `token` of node `id`
+    pub fn new_cancel_timer(id: u32, token: u64, time: Time) -> Self {
+        Event::new(EventType::CancelTimer(TimerCancel::new(id, token)), time)
+    }
+
+    /// To generate the expiry of a timer, only created by the simulation
+    pub fn new_timer_fired(id: u32, token: u64, generation: u64, time: Time) -> Self {
+        Event::new(
+            EventType::TimerFired(TimerFired::new(id, token, generation)),
+            time,
+        )
     }
 }
 
-// Order the events according to 'Time', with Admin events always having priority
+// Order the events as they are handled: immediate admin events before all others, then according
+// to 'Time' and scheduled admin events before the other events at the same time. The schedulers hand
+// out events that are equal in this order in the order they were pushed.
 impl Ord for Event {
     fn cmp(&self, other: &Self) -> Ordering {
-        match self.event_type {
-            EventType::Admin(_) => Ordering::Greater,
-            _ => self.time.cmp(&other.time),
-        }
+        other
+            .immediate
+            .cmp(&self.immediate)
+            .then_with(|| self.time.cmp(&other.time))
+            .then_with(|| other.is_admin().cmp(&self.is_admin()))
     }
 }
 
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Events are equal if they are handled at the same position of the order, their types are not
+// compared
+impl PartialEq for Event {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Event {}
+
 /// Broadcast abstraction, is part of the EventType
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Broadcast {
     pub id_from: u32,
     pub id_to: u32,
-    pub message: Message,
+    pub message: Arc<Message>,
     pub reliable: bool,
     pub fixed_delay: Option<Time>,
+    /// Size of the message in bytes, 0 if unknown
+    pub size: u32,
+    /// CPU time (µs) the receiver needs to decompress the message, 0 if it is sent as is
+    #[serde(default)]
+    pub decompression: u64,
 }
 impl Broadcast {
-    pub fn new(id_from: u32, id_to: u32, message: Message) -> Self {
+    pub fn new(id_from: u32, id_to: u32, message: impl Into<Arc<Message>>) -> Self {
         Broadcast {
             id_from,
             id_to,
-            message,
+            message: message.into(),
             reliable: false,
             fixed_delay: None,
+            size: 0,
+            decompression: 0,
         }
     }
 
     pub fn new_custom(
         id_from: u32,
         id_to: u32,
-        message: Message,
+        message: impl Into<Arc<Message>>,
         reliable: bool,
         fixed_delay: Option<Time>,
     ) -> Self {
         Broadcast {
             id_from,
             id_to,
-            message,
+            message: message.into(),
             reliable,
             fixed_delay,
+            size: 0,
+            decompression: 0,
+        }
+    }
+}
+
+/// Gossip abstraction, is part of the EventType
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Gossip {
+    pub id_from: u32,
+    pub message: Arc<Message>,
+    /// Size of the message in bytes, 0 if unknown
+    pub size: u32,
+    /// CPU time (µs) the receivers need to decompress the message, 0 if it is sent as is
+    #[serde(default)]
+    pub decompression: u64,
+}
+impl Gossip {
+    pub fn new(id_from: u32, message: impl Into<Arc<Message>>, size: u32) -> Self {
+        Gossip {
+            id_from,
+            message: message.into(),
+            size,
+            decompression: 0,
         }
     }
 }
 
 /// Reception abstraction, is part of the EventType
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Reception {
     pub id: u32,
-    pub message: Message,
+    pub message: Arc<Message>,
+    /// The authenticator of the message is invalid, the receiver drops it once verified
+    #[serde(default)]
+    pub forged: bool,
+    /// CPU time (µs) the receiver needs to decompress the message before it handles it
+    #[serde(default)]
+    pub decompression: u64,
 }
 impl Reception {
-    pub fn new(id: u32, message: Message) -> Self {
-        Reception { id, message }
+    pub fn new(id: u32, message: impl Into<Arc<Message>>) -> Self {
+        Reception {
+            id,
+            message: message.into(),
+            forged: false,
+            decompression: 0,
+        }
+    }
+
+    /// Takes the message out of the reception, it is only copied if other receptions still
+    /// share it
+    pub fn into_message(self) -> Message {
+        Arc::try_unwrap(self.message).unwrap_or_else(|message| (*message).clone())
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Timeout {
-    pub c_id: u32,
-    pub message: Message,
+/// Timer request abstraction, is part of the EventType. The `token` is chosen by the node and
+/// handed back once the timer fired.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TimerRequest {
+    pub id: u32,
+    pub token: u64,
+    /// Duration in ms on the local clock of the node
+    pub duration: u64,
 }
-impl Timeout {
-    pub fn new(c_id: u32, message: Message) -> Self {
-        Timeout { c_id, message }
+impl TimerRequest {
+    pub fn new(id: u32, token: u64, duration: u64) -> Self {
+        TimerRequest {
+            id,
+            token,
+            duration,
+        }
     }
 }
 
+/// Timer cancellation abstraction, is part of the EventType
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TimerCancel {
+    pub id: u32,
+    pub token: u64,
+}
+impl TimerCancel {
+    pub fn new(id: u32, token: u64) -> Self {
+        TimerCancel { id, token }
+    }
+}
+
+/// Expiry of a timer, is part of the EventType
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TimerFired {
+    pub id: u32,
+    pub token: u64,
+    /// Distinguishes a timer from earlier ones with the same token
+    pub generation: u64,
+}
+impl TimerFired {
+    pub fn new(id: u32, token: u64, generation: u64) -> Self {
+        TimerFired {
+            id,
+            token,
+            generation,
+        }
+    }
+}
+
+/// Priority class of a message, an overloaded node handles the waiting messages of a higher class
+/// first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Client requests and replies
+    Client,
+    /// The normal ordering traffic
+    Ordering,
+    /// View changes, protocol switches and state transfer
+    Control,
+}
+
 /// Message abstraction
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Message {
     Dummy,
     PBFT(PBFTMessage),
     Zyzzyva(ZyzzyvaMessage),
+    SBFT(SBFTMessage),
+    Tendermint(TendermintMessage),
+    Adaptive(AdaptiveMessage),
+    DAG(DAGMessage),
+    Steward(StewardMessage),
     //RBFT(RBFTMessage),
 }
+
+impl Message {
+    /// Name of the protocol the message belongs to
+    pub fn protocol(&self) -> &'static str {
+        match self {
+            Message::Dummy => "Dummy",
+            Message::PBFT(_) => "PBFT",
+            Message::Zyzzyva(_) => "Zyzzyva",
+            Message::SBFT(_) => "SBFT",
+            Message::Tendermint(_) => "Tendermint",
+            Message::Adaptive(_) => "Adaptive",
+            Message::DAG(_) => "DAG",
+            Message::Steward(_) => "Steward",
+        }
+    }
+
+    /// Name of the message type within its protocol
+    pub fn message_type(&self) -> &'static str {
+        match self {
+            Message::Dummy => "Dummy",
+            Message::PBFT(m) => m.message_type(),
+            Message::Zyzzyva(m) => m.message_type(),
+            Message::SBFT(m) => m.message_type(),
+            Message::Tendermint(m) => m.message_type(),
+            Message::Adaptive(m) => m.message_type(),
+            Message::DAG(m) => m.message_type(),
+            Message::Steward(m) => m.message_type(),
+        }
+    }
+
+    /// Priority class of the message as assigned by its protocol
+    pub fn priority(&self) -> Priority {
+        match self {
+            Message::Dummy => Priority::Ordering,
+            Message::PBFT(m) => m.priority(),
+            Message::Zyzzyva(m) => m.priority(),
+            Message::SBFT(m) => m.priority(),
+            Message::Tendermint(m) => m.priority(),
+            Message::Adaptive(m) => m.priority(),
+            Message::DAG(m) => m.priority(),
+            Message::Steward(m) => m.priority(),
+        }
+    }
+
+    /// Whether the sender appends the message to its log before it sends it
+    pub fn is_persisted(&self) -> bool {
+        match self {
+            Message::Dummy => false,
+            Message::PBFT(m) => m.is_persisted(),
+            Message::Zyzzyva(m) => m.is_persisted(),
+            Message::SBFT(m) => m.is_persisted(),
+            Message::Tendermint(m) => m.is_persisted(),
+            Message::Adaptive(m) => m.is_persisted(),
+            Message::DAG(m) => m.is_persisted(),
+            Message::Steward(m) => m.is_persisted(),
+        }
+    }
+
+    /// Id of the client request that caused the message, if any
+    pub fn request_id(&self) -> Option<u64> {
+        match self {
+            Message::Dummy => None,
+            Message::PBFT(m) => m.request_id(),
+            Message::Zyzzyva(m) => m.request_id(),
+            Message::SBFT(m) => m.request_id(),
+            Message::Tendermint(m) => m.request_id(),
+            Message::Adaptive(m) => m.request_id(),
+            Message::DAG(m) => m.request_id(),
+            Message::Steward(m) => m.request_id(),
+        }
+    }
+}
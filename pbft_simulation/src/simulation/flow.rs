@@ -0,0 +1,142 @@
+/***************************************************************************************************
+Message flow of a run for animations, e.g. with D3: every message handed to the network, when it was
+sent and arrived, between which nodes, its type and its request. Unlike the causality trace it keeps
+the messages without a request too. As a run sends many messages, the rows are arrays in the order
+of `COLUMNS` and the types are indices into a table of their names:
+
+{ "columns": ["sent", "received", "from", "to", "type", "request"],
+  "types": ["ClientRequest", "PrePrepare"],
+  "messages": [[0, 12, 5, 1, 0, 1], [12, 31, 1, 2, 1, 1], [12, null, 1, 3, 1, 1]] }
+
+The times are in ms, `received` is `null` for a lost message and so is `request` for a message that
+does not belong to a client request.
+***************************************************************************************************/
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use serde::Serialize;
+
+use crate::simulation::event::Message;
+use crate::simulation::observer::Observer;
+use crate::simulation::time::Time;
+
+/// The fields of a row of `messages`
+pub const COLUMNS: [&str; 6] = ["sent", "received", "from", "to", "type", "request"];
+
+/// A message sent at `sent` (ms) and received at `received` (ms), from and to a node, the index of
+/// its type and its request
+pub type FlowRow = (u64, Option<u64>, u32, u32, usize, Option<u64>);
+
+/// All messages of a run, in the order they were sent
+#[derive(Debug, Default)]
+pub struct MessageFlow {
+    types: Vec<&'static str>,
+    // the index of every type in `types`
+    indices: HashMap<&'static str, usize>,
+    messages: Vec<FlowRow>,
+}
+
+#[derive(Serialize)]
+struct FlowFile<'a> {
+    columns: [&'static str; 6],
+    types: &'a [&'static str],
+    messages: &'a [FlowRow],
+}
+
+impl MessageFlow {
+    /// Records a message sent at `sent`, `received` is `None` if it was lost
+    pub fn record(
+        &mut self,
+        from: u32,
+        to: u32,
+        message: &Message,
+        sent: Time,
+        received: Option<Time>,
+    ) {
+        let message_type = message.message_type();
+        let types = &mut self.types;
+        let index = *self.indices.entry(message_type).or_insert_with(|| {
+            types.push(message_type);
+            types.len() - 1
+        });
+        self.messages.push((
+            sent.milli(),
+            received.map(|t| t.milli()),
+            from,
+            to,
+            index,
+            message.request_id(),
+        ));
+    }
+
+    /// The names of the types, indexed by the rows
+    pub fn types(&self) -> &[&'static str] {
+        &self.types
+    }
+
+    /// All rows, see `COLUMNS`
+    pub fn messages(&self) -> &[FlowRow] {
+        &self.messages
+    }
+
+    /// Writes the flow as compact JSON
+    pub fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let file = FlowFile {
+            columns: COLUMNS,
+            types: &self.types,
+            messages: &self.messages,
+        };
+        serde_json::to_writer(&mut writer, &file)?;
+        writer.flush()
+    }
+
+    /// Writes the flow to the file at `path`
+    pub fn write_file(&self, path: &str) -> io::Result<()> {
+        self.write_json(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl Observer for MessageFlow {
+    fn on_message_sent(
+        &mut self,
+        from: u32,
+        to: u32,
+        message: &Message,
+        sent: Time,
+        received: Option<Time>,
+    ) {
+        self.record(from, to, message, sent, received);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::pbft::messages::{ClientRequest, PBFTMessage, PrepareMessage};
+    use crate::node::request::RequestId;
+
+    #[test]
+    fn check_messages_are_written_as_rows() {
+        let c_req = ClientRequest::new(RequestId::new(0, 3), 3);
+        let prepare = Message::PBFT(PBFTMessage::Prepare(PrepareMessage::new(c_req, 0, 1, 2)));
+
+        let mut flow = MessageFlow::default();
+        flow.record(2, 3, &prepare, Time::new(10), Some(Time::new(40)));
+        flow.record(1, 2, &Message::Dummy, Time::new(10), Some(Time::new(20)));
+        flow.record(2, 4, &prepare, Time::new(10), None);
+        assert_eq!(flow.types(), ["Prepare", "Dummy"]);
+
+        let mut json = Vec::new();
+        flow.write_json(&mut json).unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            concat!(
+                r#"{"columns":["sent","received","from","to","type","request"],"#,
+                r#""types":["Prepare","Dummy"],"#,
+                r#""messages":[[10,40,2,3,0,3],[10,20,1,2,1,null],[10,null,2,4,0,3]]}"#
+            )
+        );
+    }
+}
@@ -0,0 +1,302 @@
+/***************************************************************************************************
+gRPC control API of the simulation, only built with the feature 'grpc' and served by the binary
+'grpc_server'. A client configures the runs like the arguments of the binary, starts them one at a
+time, steers the running one with the commands of the control socket and collects its report, so
+parameter sweeps can be driven from e.g. a Jupyter notebook without parsing the result logs. The
+service is defined in 'proto/control.proto'.
+***************************************************************************************************/
+
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+use log::info;
+use tonic::{Request, Response, Status};
+
+use crate::simulation::config::{try_load_config, RequestBatchConfig, SimulationConfig};
+use crate::simulation::control::{parse_command, Command};
+use crate::simulation::event::{AdminType, Event, EventType};
+use crate::simulation::metrics::metrics;
+use crate::simulation::output::RunId;
+use crate::simulation::report::SimulationReport;
+use crate::simulation::settings::Settings;
+use crate::simulation::time::Time;
+use crate::simulation::Simulation;
+
+/// The messages and the service generated from 'proto/control.proto'
+pub mod proto {
+    #![allow(clippy::all)]
+    tonic::include_proto!("bft_simulation.control");
+}
+
+use proto::simulation_control_server::{SimulationControl, SimulationControlServer};
+use proto::{
+    Ack, CommandRequest, ConfigureReply, ConfigureRequest, InjectRequest, RunReport, StartReply,
+    StartRequest, StatsReply, StatsRequest, StopRequest, WaitRequest,
+};
+
+/// The settings of the next runs and the run that was started last
+#[derive(Debug)]
+struct State {
+    settings: Settings,
+    /// Admin events to the run, they are lost once it ended
+    sender: Option<Sender<EventType>>,
+    /// The run until its report was collected
+    handle: Option<JoinHandle<SimulationReport>>,
+}
+
+/// The service of the control API
+#[derive(Debug, Clone)]
+pub struct ControlService {
+    state: Arc<Mutex<State>>,
+}
+
+impl ControlService {
+    /// Runs with `settings` until a client configures others
+    pub fn new(settings: Settings) -> Self {
+        ControlService {
+            state: Arc::new(Mutex::new(State {
+                settings,
+                sender: None,
+                handle: None,
+            })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .expect("Mutex lock poisoned, a call of the control API panicked")
+    }
+
+    // forwards `admin_type` to the running simulation
+    fn send(&self, admin_type: AdminType) -> Result<Response<Ack>, Status> {
+        match &self.lock().sender {
+            Some(sender) if sender.send(EventType::Admin(admin_type)).is_ok() => {
+                Ok(Response::new(Ack {}))
+            }
+            _ => Err(Status::failed_precondition("no simulation is running")),
+        }
+    }
+
+    // waits for the end of the run, after stopping it if `stop`, and returns its report
+    async fn collect(&self, stop: bool) -> Result<Response<RunReport>, Status> {
+        let handle = {
+            let mut state = self.lock();
+            if stop {
+                if let Some(sender) = &state.sender {
+                    let _ = sender.send(EventType::Admin(AdminType::Stop));
+                }
+            }
+            state.handle.take()
+        };
+        let handle = handle.ok_or_else(|| {
+            Status::failed_precondition("no simulation was started or its report was collected")
+        })?;
+        let report = tokio::task::spawn_blocking(move || handle.join())
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map_err(|_| Status::internal("the simulation panicked"))?;
+        Ok(Response::new(report.into()))
+    }
+}
+
+#[tonic::async_trait]
+impl SimulationControl for ControlService {
+    async fn configure(
+        &self,
+        request: Request<ConfigureRequest>,
+    ) -> Result<Response<ConfigureReply>, Status> {
+        let request = request.into_inner();
+        let path = Some(request.config_file).filter(|path| !path.is_empty());
+        let values = try_load_config(path.into_iter().chain(request.overrides))
+            .map_err(Status::invalid_argument)?;
+        let settings = Settings::from_values(&values);
+        let settings_json =
+            serde_json::to_string(&settings).map_err(|e| Status::internal(e.to_string()))?;
+        self.lock().settings = settings;
+        Ok(Response::new(ConfigureReply {
+            settings_json,
+            nodes: values.get_vec::<u32>("node", "nodes_vec"),
+        }))
+    }
+
+    async fn start(&self, request: Request<StartRequest>) -> Result<Response<StartReply>, Status> {
+        let request = request.into_inner();
+        let mut state = self.lock();
+        if state.handle.is_some() {
+            return Err(Status::failed_precondition(
+                "a simulation was started, stop it or wait for it first",
+            ));
+        }
+        let mut settings = state.settings.clone();
+        if request.nodes > 0 {
+            settings.node.nodes = request.nodes;
+        }
+        settings.simulation.wait_for_commands = request.interactive;
+        let run = RunId::seeded(settings.simulation.seed);
+        let run_dir = run.dir();
+        let requests = RequestBatchConfig::new(
+            settings.simulation.requests,
+            settings.simulation.request_interval,
+        );
+
+        let (started, on_start) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            let mut simulation = Simulation::new(SimulationConfig::new(settings).run(run));
+            simulation.results.collect();
+            simulation.add_event_to_queue(Event::new_admin_requests_from_config(requests));
+            let _ = started.send(simulation.get_sender());
+            simulation.handle_until_stopped()
+        });
+        state.sender = on_start.recv().ok();
+        state.handle = Some(handle);
+        info!(
+            "Started a run of the control API, writing its files to {}",
+            run_dir
+        );
+        Ok(Response::new(StartReply { run_dir }))
+    }
+
+    async fn inject(&self, request: Request<InjectRequest>) -> Result<Response<Ack>, Status> {
+        let request = request.into_inner();
+        let interval = match request.interval_ms {
+            0 => 1000,
+            interval => interval,
+        };
+        let config = RequestBatchConfig::new(request.number, interval);
+        self.send(match request.at_ms {
+            0 => AdminType::ClientRequests(config),
+            at => AdminType::ClientRequestsAt(Time::new(at), config),
+        })
+    }
+
+    async fn command(&self, request: Request<CommandRequest>) -> Result<Response<Ack>, Status> {
+        match parse_command(&request.into_inner().line) {
+            Ok(Command::Admin(admin_type)) => self.send(admin_type),
+            Ok(_) => Err(Status::invalid_argument(
+                "only the commands steering the simulation are forwarded, see the rpc Stats",
+            )),
+            Err(e) => Err(Status::invalid_argument(e)),
+        }
+    }
+
+    async fn stats(&self, _: Request<StatsRequest>) -> Result<Response<StatsReply>, Status> {
+        let snapshot = metrics().snapshot();
+        Ok(Response::new(StatsReply {
+            nodes: snapshot.nodes,
+            events: snapshot.events,
+            simulated_time_ms: snapshot.simulated_time_ms,
+            queue_depth: snapshot.queue_depth,
+            committed: snapshot.committed,
+            invalid: snapshot.invalid,
+        }))
+    }
+
+    async fn wait(&self, _: Request<WaitRequest>) -> Result<Response<RunReport>, Status> {
+        self.collect(false).await
+    }
+
+    async fn stop(&self, _: Request<StopRequest>) -> Result<Response<RunReport>, Status> {
+        self.collect(true).await
+    }
+}
+
+impl From<SimulationReport> for RunReport {
+    fn from(report: SimulationReport) -> Self {
+        RunReport {
+            end_time_ms: report.end_time.milli(),
+            events: report.events,
+            divergence: report
+                .agreement
+                .err()
+                .map_or_else(String::new, |divergence| divergence.to_string()),
+            issued: report.requests.issued,
+            done: report.requests.done,
+            mean_latency_ms: report.requests.mean_latency,
+            max_latency_ms: report.requests.max_latency,
+            throughput: report.requests.throughput,
+            overdue: report.overdue,
+            results: report.results,
+        }
+    }
+}
+
+/// Serves the control API on `address` until the process ends, the runs take the `settings` until
+/// a client configures others
+pub fn serve(address: &str, settings: Settings) -> Result<(), Box<dyn Error>> {
+    let address: SocketAddr = address.parse()?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    info!("gRPC control API listening on {}", address);
+    runtime.block_on(
+        tonic::transport::Server::builder()
+            .add_service(SimulationControlServer::new(ControlService::new(settings)))
+            .serve(address),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_runs_are_configured_and_collected() {
+        let service = ControlService::new(Settings::default());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let configure = |overrides: &[&str]| ConfigureRequest {
+                config_file: String::new(),
+                overrides: overrides.iter().map(|o| o.to_string()).collect(),
+            };
+            let error = service
+                .configure(Request::new(configure(&["simulation.requests=many"])))
+                .await
+                .unwrap_err();
+            assert_eq!(error.code(), tonic::Code::InvalidArgument);
+
+            let reply = service
+                .configure(Request::new(configure(&[
+                    "node.nodes_vec=4 7",
+                    "simulation.requests=3",
+                    "network.omission_probability=0.0",
+                    "log.result=false",
+                ])))
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!(reply.nodes, vec![4, 7]);
+
+            let start = || {
+                Request::new(StartRequest {
+                    nodes: 0,
+                    interactive: false,
+                })
+            };
+            service.start(start()).await.unwrap();
+            let error = service.start(start()).await.unwrap_err();
+            assert_eq!(error.code(), tonic::Code::FailedPrecondition);
+
+            let report = service
+                .wait(Request::new(WaitRequest {}))
+                .await
+                .unwrap()
+                .into_inner();
+            assert_eq!(report.divergence, "");
+            assert_eq!(report.done, 3);
+            assert!(!report.results.is_empty());
+
+            let error = service
+                .inject(Request::new(InjectRequest {
+                    number: 1,
+                    interval_ms: 0,
+                    at_ms: 0,
+                }))
+                .await
+                .unwrap_err();
+            assert_eq!(error.code(), tonic::Code::FailedPrecondition);
+        });
+    }
+}
@@ -0,0 +1,240 @@
+/***************************************************************************************************
+Latency histogram in the layout of an HDR histogram: values below 2 * 10^digits are counted exactly,
+larger values in buckets that double in width, so every value is kept with a relative error below
+10^-digits while the histogram stays small however long the tail is. It is exported as the percentile
+distribution of HdrHistogram (.hgrm), which its plotting tools read.
+***************************************************************************************************/
+
+use std::io::{self, Write};
+
+/// Number of significant decimal digits the values are kept with
+pub const SIGNIFICANT_DIGITS: u32 = 3;
+
+/// The percentiles reported at the end of a run
+pub const PERCENTILES: [f64; 6] = [50.0, 90.0, 99.0, 99.9, 99.99, 100.0];
+
+/// Counts of the recorded values (ms)
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyHistogram {
+    // number of sub-buckets of a bucket, a power of two
+    sub_buckets: u64,
+    counts: Vec<u64>,
+    total: u64,
+    min: u64,
+    max: u64,
+    sum: u128,
+}
+
+impl LatencyHistogram {
+    /// An empty histogram that keeps the values with `significant_digits` decimal digits
+    pub fn new(significant_digits: u32) -> Self {
+        let exact = 2 * 10u64.pow(significant_digits);
+        LatencyHistogram {
+            sub_buckets: exact.next_power_of_two(),
+            counts: Vec::new(),
+            total: 0,
+            min: u64::MAX,
+            max: 0,
+            sum: 0,
+        }
+    }
+
+    // the index of the counter of `value`, exact below `sub_buckets`. Above, the values of a bucket
+    // share their leading bits and the buckets double in width.
+    fn index(&self, value: u64) -> usize {
+        if value < self.sub_buckets {
+            return value as usize;
+        }
+        let half = self.sub_buckets / 2;
+        let shift = 64 - value.leading_zeros() as u64 - self.sub_buckets.trailing_zeros() as u64;
+        let sub = value >> shift;
+        (self.sub_buckets + (shift - 1) * half + (sub - half)) as usize
+    }
+
+    // the largest value counted by the counter `index`
+    fn highest_value(&self, index: usize) -> u64 {
+        let index = index as u64;
+        if index < self.sub_buckets {
+            return index;
+        }
+        let half = self.sub_buckets / 2;
+        let shift = (index - self.sub_buckets) / half + 1;
+        let sub = (index - self.sub_buckets) % half + half;
+        ((sub + 1) << shift) - 1
+    }
+
+    /// Counts the `value`
+    pub fn record(&mut self, value: u64) {
+        let index = self.index(value);
+        if index >= self.counts.len() {
+            self.counts.resize(index + 1, 0);
+        }
+        self.counts[index] += 1;
+        self.total += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += u128::from(value);
+    }
+
+    /// Number of recorded values
+    pub fn count(&self) -> u64 {
+        self.total
+    }
+
+    /// Smallest recorded value, 0 if there is none
+    pub fn min(&self) -> u64 {
+        if self.total == 0 {
+            0
+        } else {
+            self.min
+        }
+    }
+
+    /// Largest recorded value
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    /// Mean of the recorded values, 0 if there is none
+    pub fn mean(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.sum as f64 / self.total as f64
+    }
+
+    /// The value that `percentile` percent of the recorded values don't exceed, 0 if there is none
+    pub fn value_at_percentile(&self, percentile: f64) -> u64 {
+        let rank = ((percentile / 100.0) * self.total as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return self.highest_value(index).min(self.max);
+            }
+        }
+        0
+    }
+
+    /// Writes the percentile distribution in the format of HdrHistogram, a line per counter that
+    /// holds values
+    pub fn write_hgrm<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(
+            writer,
+            "{:>12} {:>14} {:>10} {:>14}\n",
+            "Value", "Percentile", "TotalCount", "1/(1-Percentile)"
+        )?;
+        let mut seen = 0;
+        for (index, count) in self.counts.iter().enumerate().filter(|(_, c)| **c > 0) {
+            seen += count;
+            let quantile = seen as f64 / self.total as f64;
+            let value = self.highest_value(index).min(self.max);
+            if seen < self.total {
+                writeln!(
+                    writer,
+                    "{:>12.3} {:>14.12} {:>10} {:>14.2}",
+                    value as f64,
+                    quantile,
+                    seen,
+                    1.0 / (1.0 - quantile)
+                )?;
+            } else {
+                writeln!(
+                    writer,
+                    "{:>12.3} {:>14.12} {:>10}",
+                    value as f64, quantile, seen
+                )?;
+            }
+        }
+
+        let variance = if self.total == 0 {
+            0.0
+        } else {
+            let mean = self.mean();
+            self.counts
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| **c > 0)
+                .map(|(index, count)| {
+                    let deviation = self.highest_value(index).min(self.max) as f64 - mean;
+                    deviation * deviation * *count as f64
+                })
+                .sum::<f64>()
+                / self.total as f64
+        };
+        writeln!(
+            writer,
+            "#[Mean    = {:>12.3}, StdDeviation   = {:>12.3}]",
+            self.mean(),
+            variance.sqrt()
+        )?;
+        writeln!(
+            writer,
+            "#[Max     = {:>12.3}, Total count    = {:>12}]",
+            self.max as f64, self.total
+        )?;
+        writeln!(
+            writer,
+            "#[Buckets = {:>12}, SubBuckets     = {:>12}]",
+            self.counts.len(),
+            self.sub_buckets
+        )
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        LatencyHistogram::new(SIGNIFICANT_DIGITS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_values_keep_their_significant_digits() {
+        let histogram = LatencyHistogram::default();
+        assert_eq!(histogram.sub_buckets, 2048);
+        let mut previous = 0;
+        for value in (0..50_000_000).step_by(997) {
+            let index = histogram.index(value);
+            // the counters are ordered like the values and hold them
+            assert!(index >= previous, "{}", value);
+            previous = index;
+            let highest = histogram.highest_value(index);
+            assert!(highest >= value);
+            assert!(
+                (highest - value) as f64 <= value as f64 / 1000.0,
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn check_percentiles_of_a_long_tail() {
+        let mut histogram = LatencyHistogram::default();
+        for latency in 1..=1000 {
+            histogram.record(latency);
+        }
+        histogram.record(1_000_000);
+
+        assert_eq!(histogram.count(), 1001);
+        assert_eq!(histogram.min(), 1);
+        assert_eq!(histogram.value_at_percentile(50.0), 501);
+        assert_eq!(histogram.value_at_percentile(99.0), 991);
+        assert_eq!(histogram.value_at_percentile(100.0), 1_000_000);
+        assert!((histogram.mean() - 1499.0).abs() < 0.01);
+
+        let mut hgrm = Vec::new();
+        histogram.write_hgrm(&mut hgrm).unwrap();
+        let hgrm = String::from_utf8(hgrm).unwrap();
+        // the header and a line per value
+        let lines = hgrm
+            .lines()
+            .filter(|l| !l.is_empty() && !l.starts_with('#'));
+        assert_eq!(lines.count(), 1 + 1001);
+        assert!(hgrm.contains("#[Max     =  1000000.000, Total count    =         1001]"));
+    }
+}
@@ -0,0 +1,250 @@
+/***************************************************************************************************
+The inbox of a node that handles at most a given number of messages per simulated second. Messages
+arriving while the node is busy wait in the inbox, so their delay grows with the backlog, or they
+are dropped once the inbox is full. Waiting messages of a higher priority class are handled first
+and a full inbox makes room for them by dropping the latest message of the lowest class. Timers are
+not limited.
+***************************************************************************************************/
+
+use std::collections::{BTreeMap, VecDeque};
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::simulation::event::{Priority, Reception};
+use crate::simulation::time::Time;
+
+/// What happens to the messages an overloaded node receives
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum OverloadPolicy {
+    /// They wait however long the inbox is
+    Queue,
+    /// They are dropped if the inbox is full
+    Drop,
+}
+
+impl FromStr for OverloadPolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy {
+            "queue" => Ok(OverloadPolicy::Queue),
+            "drop" => Ok(OverloadPolicy::Drop),
+            _ => Err(format!(
+                "overload policy '{}' is not available, allowed are 'queue', 'drop'",
+                policy
+            )),
+        }
+    }
+}
+
+/// What becomes of a message arriving at the inbox
+#[derive(Debug, PartialEq)]
+pub enum Admission {
+    /// The node is idle and handles the message right away
+    Handle(Reception),
+    /// The message waits, the node is ready for the first waiting message at the given time if
+    /// none waited before
+    Queued(Option<Time>),
+    /// The inbox is full, either the arriving message or a waiting one of a lower class is dropped
+    Dropped(Reception),
+}
+
+/// The inbox of a single node
+#[derive(Debug)]
+pub struct Inbox {
+    // time (µs) the node needs per message
+    service: u64,
+    policy: OverloadPolicy,
+    capacity: usize,
+    // the node is busy with the last message until then (µs)
+    busy_until: u64,
+    // the waiting messages by their class, first come first served within a class
+    waiting: BTreeMap<Priority, VecDeque<Reception>>,
+    // number of waiting messages
+    len: usize,
+}
+
+impl Inbox {
+    /// An inbox of a node handling `max_rate` messages per second, `capacity` is the number of
+    /// waiting messages before they are dropped with `OverloadPolicy::Drop`
+    pub fn new(max_rate: u32, policy: OverloadPolicy, capacity: usize) -> Self {
+        assert!(max_rate > 0, "The maximal rate of a node must be positive");
+        Inbox {
+            service: 1_000_000 / u64::from(max_rate),
+            policy,
+            capacity,
+            busy_until: 0,
+            waiting: BTreeMap::new(),
+            len: 0,
+        }
+    }
+
+    /// Number of messages waiting
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Admits the `reception` of the class `priority` that arrives at `time`
+    pub fn arrive(&mut self, reception: Reception, priority: Priority, time: Time) -> Admission {
+        let now = time.micros();
+        if self.is_empty() && self.busy_until <= now {
+            self.busy_until = now + self.service;
+            return Admission::Handle(reception);
+        }
+
+        let dropped = if self.policy == OverloadPolicy::Drop && self.len >= self.capacity {
+            match self.evict(priority) {
+                Some(evicted) => Some(evicted),
+                None => return Admission::Dropped(reception),
+            }
+        } else {
+            None
+        };
+        self.waiting
+            .entry(priority)
+            .or_default()
+            .push_back(reception);
+        self.len += 1;
+
+        match (dropped, self.len) {
+            (Some(evicted), _) => Admission::Dropped(evicted),
+            (None, 1) => Admission::Queued(Some(self.ready_at())),
+            (None, _) => Admission::Queued(None),
+        }
+    }
+
+    /// Takes the next waiting message the node is ready for at `time`, together with the time it
+    /// is ready for the one after, if another one waits
+    pub fn next(&mut self, time: Time) -> (Option<Reception>, Option<Time>) {
+        let reception = self.pop();
+        if reception.is_some() {
+            self.busy_until = self.busy_until.max(time.micros()) + self.service;
+        }
+        if self.waiting.is_empty() {
+            (reception, None)
+        } else {
+            (reception, Some(self.ready_at()))
+        }
+    }
+
+    // drops the latest waiting message of the lowest class if it is lower than `priority`
+    fn evict(&mut self, priority: Priority) -> Option<Reception> {
+        let lowest = *self
+            .waiting
+            .keys()
+            .next()
+            .filter(|lowest| **lowest < priority)?;
+        let messages = self.waiting.get_mut(&lowest)?;
+        let reception = messages.pop_back();
+        if messages.is_empty() {
+            self.waiting.remove(&lowest);
+        }
+        self.len -= 1;
+        reception
+    }
+
+    // takes the first waiting message of the highest class
+    fn pop(&mut self) -> Option<Reception> {
+        let highest = *self.waiting.keys().next_back()?;
+        let messages = self.waiting.get_mut(&highest)?;
+        let reception = messages.pop_front();
+        if messages.is_empty() {
+            self.waiting.remove(&highest);
+        }
+        self.len -= 1;
+        reception
+    }
+
+    // the time the node is no longer busy
+    fn ready_at(&self) -> Time {
+        Time::from_micros(self.busy_until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::event::Message;
+
+    fn reception(id: u32) -> Reception {
+        Reception::new(id, Message::Dummy)
+    }
+
+    #[test]
+    fn check_backlog_delays_and_drops() {
+        // 4 messages per second, i.e. 250ms per message
+        let mut inbox = Inbox::new(4, OverloadPolicy::Drop, 2);
+        assert_eq!(
+            inbox.arrive(reception(1), Priority::Ordering, Time::new(0)),
+            Admission::Handle(reception(1))
+        );
+        assert_eq!(
+            inbox.arrive(reception(2), Priority::Ordering, Time::new(10)),
+            Admission::Queued(Some(Time::new(250)))
+        );
+        assert_eq!(
+            inbox.arrive(reception(3), Priority::Ordering, Time::new(20)),
+            Admission::Queued(None)
+        );
+        assert_eq!(
+            inbox.arrive(reception(4), Priority::Ordering, Time::new(30)),
+            Admission::Dropped(reception(4))
+        );
+
+        assert_eq!(
+            inbox.next(Time::new(250)),
+            (Some(reception(2)), Some(Time::new(500)))
+        );
+        assert_eq!(inbox.next(Time::new(500)), (Some(reception(3)), None));
+        assert!(inbox.is_empty());
+        assert_eq!(
+            inbox.arrive(reception(5), Priority::Ordering, Time::new(900)),
+            Admission::Handle(reception(5))
+        );
+    }
+
+    #[test]
+    fn check_queue_policy_keeps_everything() {
+        let mut inbox = Inbox::new(1000, OverloadPolicy::Queue, 0);
+        inbox.arrive(reception(1), Priority::Ordering, Time::new(5));
+        for id in 2..=10 {
+            inbox.arrive(reception(id), Priority::Ordering, Time::new(5));
+        }
+        assert_eq!(inbox.len(), 9);
+        assert_eq!(
+            inbox.next(Time::new(6)),
+            (Some(reception(2)), Some(Time::new(7)))
+        );
+        assert_eq!("drop".parse(), Ok(OverloadPolicy::Drop));
+        assert!("lifo".parse::<OverloadPolicy>().is_err());
+    }
+
+    #[test]
+    fn check_higher_classes_go_first() {
+        let mut inbox = Inbox::new(10, OverloadPolicy::Drop, 2);
+        inbox.arrive(reception(1), Priority::Ordering, Time::new(0));
+        inbox.arrive(reception(2), Priority::Client, Time::new(1));
+        inbox.arrive(reception(3), Priority::Ordering, Time::new(2));
+        // the inbox is full, the client request makes room for the control message
+        assert_eq!(
+            inbox.arrive(reception(4), Priority::Control, Time::new(3)),
+            Admission::Dropped(reception(2))
+        );
+        assert_eq!(
+            inbox.arrive(reception(5), Priority::Client, Time::new(4)),
+            Admission::Dropped(reception(5))
+        );
+
+        assert_eq!(
+            inbox.next(Time::new(100)),
+            (Some(reception(4)), Some(Time::new(200)))
+        );
+        assert_eq!(inbox.next(Time::new(200)), (Some(reception(3)), None));
+        assert!(inbox.is_empty());
+    }
+}
@@ -0,0 +1,53 @@
+/***************************************************************************************************
+Injection of fully-formed events into a simulation by library users, e.g. harnesses for adversarial
+testing that let a crafted message arrive at a chosen node at a chosen time
+***************************************************************************************************/
+
+use std::sync::{Arc, Mutex};
+
+use log::debug;
+
+use crate::simulation::event::{Event, Message};
+use crate::simulation::time::Time;
+use crate::simulation::EventQueue;
+
+/// Handle to the event queue of a simulation, obtained with `Simulation::injector`. It can be
+/// cloned and sent to other threads; the events are handled as if a node had scheduled them.
+/// An event before the current time of the simulation is treated as every other time violation,
+/// following `simulation.time_violation`.
+#[derive(Debug, Clone)]
+pub struct Injector {
+    queue: Arc<Mutex<EventQueue>>,
+}
+
+impl Injector {
+    pub(crate) fn new(queue: Arc<Mutex<EventQueue>>) -> Self {
+        Injector { queue }
+    }
+
+    /// Schedules `event` as it is
+    pub fn inject(&self, event: Event) {
+        debug!(target: "simulation", "Injecting event: {:?}", event);
+        self.queue
+            .lock()
+            .expect("Mutex lock on queue poisoned. It appears that someone panicked, that wasn't allowed to panic.")
+            .push(event);
+    }
+
+    /// Schedules all `events`
+    pub fn inject_all(&self, events: impl IntoIterator<Item = Event>) {
+        for event in events {
+            self.inject(event);
+        }
+    }
+
+    /// Lets `message` arrive at node `id` at `time`, bypassing the network and its delays
+    pub fn inject_message(&self, id: u32, message: impl Into<Arc<Message>>, time: Time) {
+        self.inject(Event::new_reception(id, message, time));
+    }
+
+    /// Lets `message` with an invalid authenticator arrive at node `id` at `time`
+    pub fn inject_forged_message(&self, id: u32, message: impl Into<Arc<Message>>, time: Time) {
+        self.inject(Event::new_forged_reception(id, message, time));
+    }
+}
@@ -0,0 +1,198 @@
+/***************************************************************************************************
+Setup of the diagnostic logs with log4rs, only built with the feature 'file-logging'. The simulation
+itself only writes to the `log` facade and never installs a logger, so a host application keeps its
+own. The binaries install one with `LoggingBuilder::init`, a host that uses log4rs already can hand
+its `Handle` to `LoggingBuilder::apply` instead:
+
+    let handle = LoggingBuilder::new().debug_files(&run).init()?;
+***************************************************************************************************/
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use log::{LevelFilter, SetLoggerError};
+use log4rs::append::console::ConsoleAppender;
+use log4rs::append::file::FileAppender;
+use log4rs::config::{Appender, Config, Errors, Logger, Root};
+use log4rs::encode::pattern::PatternEncoder;
+use log4rs::Handle;
+use mc_utils::ini::IniValues;
+
+use crate::simulation::output::RunId;
+
+/// Why the logs could not be set up
+#[derive(Debug)]
+pub enum LoggingError {
+    /// The log file at `path` could not be opened
+    File { path: PathBuf, error: io::Error },
+    /// The appenders and loggers do not form a valid config, e.g. two files of the same target
+    Config(Errors),
+    /// The process has a logger already
+    Logger(SetLoggerError),
+}
+
+impl fmt::Display for LoggingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoggingError::File { path, error } => {
+                write!(f, "Failed to open the log {}: {}", path.display(), error)
+            }
+            LoggingError::Config(errors) => write!(f, "Invalid logging config: {}", errors),
+            LoggingError::Logger(error) => write!(f, "Failed to install the logger: {}", error),
+        }
+    }
+}
+
+impl Error for LoggingError {}
+
+/// The logs of a target written to a file instead of the console
+#[derive(Debug, Clone)]
+struct FileLog {
+    target: String,
+    path: PathBuf,
+    level: LevelFilter,
+}
+
+/// The appenders and loggers of the diagnostic logs
+#[derive(Debug, Clone)]
+pub struct LoggingBuilder {
+    /// Level of the logs on stdout, `None` for none
+    console: Option<LevelFilter>,
+    files: Vec<FileLog>,
+    /// Pattern of the lines of the files
+    pattern: String,
+}
+
+impl Default for LoggingBuilder {
+    fn default() -> Self {
+        LoggingBuilder {
+            console: Some(LevelFilter::Info),
+            files: Vec::new(),
+            pattern: "{l} - {m}{n}".to_string(),
+        }
+    }
+}
+
+impl LoggingBuilder {
+    /// Logs of level info and above to stdout, no files
+    pub fn new() -> Self {
+        LoggingBuilder::default()
+    }
+
+    /// The loggers of the ini: `log.debug` adds the debug logs of the run `run`
+    pub fn from_values(values: &IniValues, run: &RunId) -> Self {
+        let builder = LoggingBuilder::new();
+        if values.require("log", "debug") {
+            builder.debug_files(run)
+        } else {
+            builder
+        }
+    }
+
+    /// Logs `level` and above to stdout, `None` disables the console
+    pub fn console(mut self, level: Option<LevelFilter>) -> Self {
+        self.console = level;
+        self
+    }
+
+    /// Writes the logs of `target` of `level` and above to the file at `path`, replacing it, and no
+    /// longer to the console
+    pub fn file<P: Into<PathBuf>>(mut self, target: &str, path: P, level: LevelFilter) -> Self {
+        self.files.push(FileLog {
+            target: target.to_string(),
+            path: path.into(),
+            level,
+        });
+        self
+    }
+
+    /// The pattern of the lines of the files, see `log4rs::encode::pattern`
+    pub fn pattern(mut self, pattern: &str) -> Self {
+        self.pattern = pattern.to_string();
+        self
+    }
+
+    /// Writes the debug logs of the nodes and of the simulation to the directory of `run`
+    pub fn debug_files(self, run: &RunId) -> Self {
+        self.file("node", run.path("debug_nodes.log"), LevelFilter::Debug)
+            .file(
+                "simulation",
+                run.path("debug_simulation.log"),
+                LevelFilter::Debug,
+            )
+    }
+
+    /// Opens the files and builds the config of log4rs
+    pub fn build(self) -> Result<Config, LoggingError> {
+        let mut config = Config::builder();
+        let mut root = Root::builder();
+        if self.console.is_some() {
+            let stdout = ConsoleAppender::builder().build();
+            config = config.appender(Appender::builder().build("stdout", Box::new(stdout)));
+            root = root.appender("stdout");
+        }
+
+        for (i, file) in self.files.into_iter().enumerate() {
+            let appender = FileAppender::builder()
+                .encoder(Box::new(PatternEncoder::new(&self.pattern)))
+                .append(false)
+                .build(&file.path)
+                .map_err(|error| LoggingError::File {
+                    path: file.path.clone(),
+                    error,
+                })?;
+            let name = format!("file_{}", i);
+            config = config
+                .appender(Appender::builder().build(name.as_str(), Box::new(appender)))
+                .logger(
+                    Logger::builder()
+                        .appender(name.as_str())
+                        .additive(false)
+                        .build(file.target.as_str(), file.level),
+                );
+        }
+
+        config
+            .build(root.build(self.console.unwrap_or(LevelFilter::Off)))
+            .map_err(LoggingError::Config)
+    }
+
+    /// Installs the loggers as the logger of the process, fails if it has one already
+    pub fn init(self) -> Result<Handle, LoggingError> {
+        log4rs::init_config(self.build()?).map_err(LoggingError::Logger)
+    }
+
+    /// Replaces the config of the log4rs logger the host installed
+    pub fn apply(self, handle: &Handle) -> Result<(), LoggingError> {
+        handle.set_config(self.build()?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_files_are_opened_on_build() {
+        let dir = std::env::temp_dir().join("bft_simulation_logging");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("node.log");
+        let builder = LoggingBuilder::new()
+            .console(None)
+            .file("node", &path, LevelFilter::Debug);
+        assert!(builder.build().is_ok());
+        assert!(path.exists());
+
+        // a log cannot be opened below a file
+        let below_file = path.join("node.log");
+        let builder = LoggingBuilder::new().file("node", &below_file, LevelFilter::Debug);
+        match builder.build() {
+            Err(LoggingError::File { path, .. }) => assert_eq!(path, below_file),
+            Err(other) => panic!("Expected the file to fail, got {}", other),
+            Ok(_) => panic!("Expected the file to fail"),
+        }
+    }
+}
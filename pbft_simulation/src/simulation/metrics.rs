@@ -0,0 +1,253 @@
+/***************************************************************************************************
+Live metrics of the running simulation, exposed via HTTP in the Prometheus text format. The
+counters are updated by the `MetricsObserver` of the simulation, the endpoint is only started if
+enabled in the ini.
+***************************************************************************************************/
+
+use std::collections::HashSet;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use log::{info, warn};
+
+use crate::simulation::observer::Observer;
+use crate::simulation::results::ResultEvent;
+use crate::simulation::time::Time;
+use crate::simulation::wall_clock::Instant;
+
+static METRICS: Metrics = Metrics::new();
+
+/// Returns the metrics of the current run
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+/// Metrics of the current run, reset whenever a new simulation is created
+#[derive(Debug)]
+pub struct Metrics {
+    nodes: AtomicU64,
+    events: AtomicU64,
+    simulated_time: AtomicU64,
+    queue_depth: AtomicU64,
+    committed: AtomicU64,
+    invalid: AtomicU64,
+    // request ids that were committed at least once, so a request is counted only once
+    committed_ids: Mutex<Option<HashSet<u64>>>,
+    started: Mutex<Option<Instant>>,
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Metrics {
+            nodes: AtomicU64::new(0),
+            events: AtomicU64::new(0),
+            simulated_time: AtomicU64::new(0),
+            queue_depth: AtomicU64::new(0),
+            committed: AtomicU64::new(0),
+            invalid: AtomicU64::new(0),
+            committed_ids: Mutex::new(None),
+            started: Mutex::new(None),
+        }
+    }
+
+    /// Resets all metrics for a new run with `number_of_nodes` nodes
+    pub fn start_run(&self, number_of_nodes: u32) {
+        self.nodes.store(u64::from(number_of_nodes), Ordering::Relaxed);
+        self.events.store(0, Ordering::Relaxed);
+        self.simulated_time.store(0, Ordering::Relaxed);
+        self.queue_depth.store(0, Ordering::Relaxed);
+        self.committed.store(0, Ordering::Relaxed);
+        self.invalid.store(0, Ordering::Relaxed);
+        *self.committed_ids.lock().unwrap() = Some(HashSet::new());
+        *self.started.lock().unwrap() = Some(Instant::now());
+    }
+
+    /// Called by the simulation for every handled event
+    pub fn record_event(&self, time: Time, queue_depth: usize) {
+        self.events.fetch_add(1, Ordering::Relaxed);
+        self.simulated_time.store(time.milli(), Ordering::Relaxed);
+        self.queue_depth.store(queue_depth as u64, Ordering::Relaxed);
+    }
+
+    /// Called for every event of the result log, counts the requests that were committed and the
+    /// messages the replicas dropped as invalid
+    pub fn record_result(&self, event: &ResultEvent) {
+        if let ResultEvent::Invalid { .. } = event {
+            self.invalid.fetch_add(1, Ordering::Relaxed);
+        }
+        if let Some(request_id) = event.done_request() {
+            if let Some(ids) = &mut *self.committed_ids.lock().unwrap() {
+                if ids.insert(request_id) {
+                    self.committed.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    /// The counters as they are now
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            nodes: self.nodes.load(Ordering::Relaxed),
+            events: self.events.load(Ordering::Relaxed),
+            simulated_time_ms: self.simulated_time.load(Ordering::Relaxed),
+            queue_depth: self.queue_depth.load(Ordering::Relaxed),
+            committed: self.committed.load(Ordering::Relaxed),
+            invalid: self.invalid.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Renders all metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        let events = self.events.load(Ordering::Relaxed);
+        let elapsed = self
+            .started
+            .lock()
+            .unwrap()
+            .map_or(0.0, |started| started.elapsed().as_secs_f64());
+        let events_per_second = if elapsed > 0.0 {
+            events as f64 / elapsed
+        } else {
+            0.0
+        };
+
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            out.push_str(&format!(
+                "# HELP {} {}\n# TYPE {} {}\n{} {}\n",
+                name, help, name, kind, name, value
+            ));
+        };
+        metric(
+            "bft_nodes",
+            "gauge",
+            "Number of nodes in the current run",
+            self.nodes.load(Ordering::Relaxed).to_string(),
+        );
+        metric(
+            "bft_events_total",
+            "counter",
+            "Events handled in the current run",
+            events.to_string(),
+        );
+        metric(
+            "bft_events_per_second",
+            "gauge",
+            "Events handled per second of wall clock time",
+            format!("{:.1}", events_per_second),
+        );
+        metric(
+            "bft_simulated_time_ms",
+            "gauge",
+            "Current simulation time in ms",
+            self.simulated_time.load(Ordering::Relaxed).to_string(),
+        );
+        metric(
+            "bft_queue_depth",
+            "gauge",
+            "Events waiting in the queue",
+            self.queue_depth.load(Ordering::Relaxed).to_string(),
+        );
+        metric(
+            "bft_committed_requests_total",
+            "counter",
+            "Requests committed by at least one node",
+            self.committed.load(Ordering::Relaxed).to_string(),
+        );
+        metric(
+            "bft_invalid_messages_total",
+            "counter",
+            "Messages the replicas could not handle and dropped",
+            self.invalid.load(Ordering::Relaxed).to_string(),
+        );
+        out
+    }
+}
+
+/// The counters of the current run at one instant, e.g. for the gRPC control API
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    pub nodes: u64,
+    pub events: u64,
+    pub simulated_time_ms: u64,
+    pub queue_depth: u64,
+    pub committed: u64,
+    pub invalid: u64,
+}
+
+/// Feeds the metrics of the current run, registered on every simulation
+#[derive(Debug, Default)]
+pub struct MetricsObserver;
+
+impl Observer for MetricsObserver {
+    fn on_event_processed(&mut self, time: Time, queue_depth: usize) {
+        metrics().record_event(time, queue_depth);
+    }
+
+    fn on_result(&mut self, _time: Time, _node_id: Option<u32>, event: &ResultEvent) {
+        metrics().record_result(event);
+    }
+}
+
+/// Starts a thread answering every HTTP request on `address` with the current metrics
+pub fn serve(address: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    info!("Serving metrics on http://{}/metrics", address);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            if let Err(e) = stream.and_then(respond) {
+                warn!("Failed to serve metrics: {}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn respond(mut stream: TcpStream) -> io::Result<()> {
+    // the request itself is irrelevant, every path returns the metrics
+    let mut buffer = [0; 1024];
+    let _ = stream.read(&mut buffer)?;
+
+    let body = metrics().render();
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_metrics_count_committed_requests_once() {
+        let metrics = Metrics::new();
+        metrics.start_run(4);
+
+        metrics.record_event(Time::new(40), 12);
+        metrics.record_result(&ResultEvent::Prepared(1));
+        metrics.record_result(&ResultEvent::CommittedLocal(1));
+        metrics.record_result(&ResultEvent::Executed(1));
+        metrics.record_result(&ResultEvent::Executed(1));
+        metrics.record_result(&ResultEvent::Completed(2));
+        metrics.record_result(&ResultEvent::Finished);
+        metrics.record_result(&ResultEvent::Invalid {
+            protocol: "PBFT".to_string(),
+            message_type: "ClientResponse".to_string(),
+        });
+
+        let rendered = metrics.render();
+        assert!(rendered.contains("\nbft_events_total 1\n"));
+        assert!(rendered.contains("\nbft_simulated_time_ms 40\n"));
+        assert!(rendered.contains("\nbft_queue_depth 12\n"));
+        assert!(rendered.contains("\nbft_committed_requests_total 2\n"));
+        assert!(rendered.contains("\nbft_invalid_messages_total 1\n"));
+        assert_eq!(metrics.snapshot().committed, 2);
+    }
+}
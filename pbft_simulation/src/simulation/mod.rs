@@ -1,149 +1,452 @@
-use std::collections::{binary_heap::BinaryHeap, HashMap};
+use std::any::Any;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
 use std::sync::{
     Arc,
     mpsc,
     mpsc::{Receiver, Sender}, Mutex,
 };
 use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
-use log::{debug, info, warn};
-use mc_utils::ini::env2var;
+use log::{debug, error, info, warn};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 
-use config::SimulationConfig;
-use event::{AdminType, Event, EventType};
+use causality::CausalityTrace;
+use composition::Composition;
+use config::{RequestBatchConfig, SimulationConfig};
+use diagnostics::{EventHistory, TimeViolation, TimeViolationPolicy};
+use event::{AdminType, Event, EventType, Message, Reception};
+use flow::MessageFlow;
+use inbox::{Admission, Inbox};
+use injector::Injector;
+use metrics::MetricsObserver;
+use observer::{Observers, SharedObserver};
+use output::RunId;
+use postmortem::{NodeDump, PanicPolicy, ReceptionLog};
+use report::SimulationReport;
+use results::ResultEvent;
+use results_writer::ResultsWriter;
+use safety::{Divergence, ExecutionRecord};
+use scenario::Scenario;
+use scheduler::Scheduler;
+use settings::Settings;
+use statistics::RunStatistics;
+use storage::Disk;
 use time::Time;
+use wall_clock::Instant;
 
+use crate::network::adversary::Adversary;
+use crate::network::event::NetworkEvent;
 use crate::network::Network;
-use crate::node::{build_node, Node, NodeType};
-use crate::simulation::config::log_result;
+use crate::node::timer::{Clock, Timers};
+use crate::node::{Node, NodeOutput, NodeType};
+use crate::simulation::config::{CapturedLines, ResultLogger};
 
+pub mod causality;
+pub mod composition;
 pub mod config;
+pub mod config_file;
+pub mod control;
+pub mod determinism;
+pub mod diagnostics;
 pub mod event;
+pub mod flow;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod histogram;
+pub mod inbox;
+pub mod injector;
+#[cfg(feature = "file-logging")]
+pub mod logging;
+pub mod metrics;
+pub mod observer;
+pub mod output;
+pub mod postmortem;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod report;
+pub mod results;
+pub mod results_writer;
+pub mod safety;
+pub mod scenario;
+pub mod scheduler;
+pub mod settings;
+pub mod snapshot;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod statistics;
+pub mod storage;
 pub mod time;
+pub mod timeline;
+pub mod trace;
+pub mod wall_clock;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 /***************************************************************************************************
 Core of the simulation based on an event queue
 ***************************************************************************************************/
 
+/// Runs a simulation with the `settings` to completion: sends the configured client requests and
+/// handles all events until the queue is empty. Neither the ini nor the environment is read and
+/// only the loggers configured by the caller are written to.
+pub fn run_simulation(settings: Settings) -> SimulationReport {
+    let requests = RequestBatchConfig::new(
+        settings.simulation.requests,
+        settings.simulation.request_interval,
+    );
+    let mut simulation = Simulation::new(SimulationConfig::new(settings));
+    simulation.results.collect();
+    simulation.add_event_to_queue(Event::new_admin_requests_from_config(requests));
+    simulation.run_to_completion()
+}
+
+/// Like `run_simulation`, but the actions of the `scenario` replace the configured client requests
+pub fn run_scenario(settings: Settings, scenario: Scenario) -> SimulationReport {
+    let mut simulation = Simulation::new(SimulationConfig::new(settings));
+    simulation.results.collect();
+    simulation.add_events_to_queue(scenario.into_events());
+    simulation.run_to_completion()
+}
+
+/// The events of a simulation, handed out in the order of `Event` by the scheduler chosen with
+/// `simulation.scheduler`
+pub(crate) type EventQueue = Box<dyn Scheduler>;
+
 /// Simulation abstraction, based on an event queue
 #[derive(Debug)]
 pub struct Simulation {
     // Queue for all events
-    event_queue: Arc<Mutex<BinaryHeap<Event>>>,
+    event_queue: Arc<Mutex<EventQueue>>,
     // Map with nodes, referenced by id
     node_map: HashMap<u32, Box<dyn Node>>,
     // Type of nodes in the current simulation
     node_type: NodeType,
+    // Clusters of the nodes, the first one of `node_type`
+    composition: Composition,
     // Network abstraction
     network: Network,
     // Global simulation time, update with each received event
     time: Time,
     // This channel is a preparation so we can feed events to the simulation from an external source, e.g. administrative
     external_sender: Sender<EventType>,
+    // The other end of the channel, received from before every step as there are no threads
+    #[cfg(feature = "wasm")]
+    external_receiver: Receiver<EventType>,
     // Request counter
     request_counter: u64,
+    // Timers of the nodes, including their local clocks
+    timers: Timers,
+    // Inboxes of the nodes, only if the rate of the nodes is limited
+    inboxes: HashMap<u32, Inbox>,
+    // Disks of the replicas, only if their protocol persists its log entries
+    disks: HashMap<u32, Disk>,
+    // Stop once the queue is empty for a while, disabled if commands can arrive on the control socket
+    stop_when_idle: bool,
+    // Messages of all client requests, only recorded if enabled in the ini
+    causality: Option<Arc<Mutex<CausalityTrace>>>,
+    // All messages of the run for animations, only recorded if enabled in the ini
+    flow: Option<Arc<Mutex<MessageFlow>>>,
+    // Watchdog and request statistics of the run
+    statistics: Arc<Mutex<RunStatistics>>,
+    // Observers of the run, shared with the result log
+    observers: Observers,
+    // Parameters of the run
+    settings: Arc<Settings>,
+    // Number of events handled so far
+    events: u64,
+    // Result log of the run, shared with the nodes
+    results: ResultLogger,
+    // Wall-clock time the handling of the events started, the simulation is paced relative to it
+    started: Instant,
+    // Pausing and stepping, shared with the thread receiving the admin events so they take effect
+    // while the queue is held up
+    pause: Arc<Mutex<PauseState>>,
+    // The id of the run, its files are written to its directory
+    run: RunId,
+    // The last handled events, dumped with an event before the current time
+    history: EventHistory,
+    // The last receptions of every node, dumped with a node that panicked
+    receptions: ReceptionLog,
+    // Buffer the nodes push their outputs to, reused for all receptions so handling a message
+    // does not allocate
+    outputs: Vec<NodeOutput>,
+    // Threads handling the receptions of distinct nodes at the same time, only if
+    // `simulation.threads` is more than 1
+    workers: Option<ThreadPool>,
+}
+
+/// A reception handled on a worker thread, merged into the simulation afterwards
+struct Handled {
+    node: Box<dyn Node>,
+    message: Arc<Message>,
+    outputs: thread::Result<Vec<NodeOutput>>,
+    lines: CapturedLines,
+}
+
+/// What a single step of the simulation did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// An event was handled
+    Handled,
+    /// No event is handled until the simulation is resumed or stepped, see `AdminType::Pause`
+    Paused,
+    /// No event is left
+    Empty,
+    /// A stop event was handled, the run has to be finished
+    Stopped,
+}
+
+/// A paused simulation handles no events but the `steps` it was asked for
+#[derive(Debug, Default)]
+struct PauseState {
+    // Wall-clock time the simulation was paused at, if it is paused
+    paused: Option<Instant>,
+    // Number of events a paused simulation still handles
+    steps: u32,
+    // Wall-clock time the simulation was paused for in total, it does not count towards pacing
+    paused_for: Duration,
+}
+
+impl PauseState {
+    /// Applies `Pause`, `Resume` and `Step`, returns `false` for all other admin events
+    fn apply(&mut self, admin_type: &AdminType) -> bool {
+        match admin_type {
+            AdminType::Pause => {
+                if self.paused.is_none() {
+                    info!("Pausing the simulation");
+                    self.paused = Some(Instant::now());
+                    self.steps = 0;
+                }
+            }
+            AdminType::Resume => {
+                if let Some(paused) = self.paused.take() {
+                    info!("Resuming the simulation");
+                    self.paused_for += paused.elapsed();
+                    self.steps = 0;
+                }
+            }
+            AdminType::Step(n) => match self.paused {
+                Some(_) => self.steps += n,
+                None => warn!(
+                    "Ignoring the step of {} events, the simulation is not paused",
+                    n
+                ),
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    /// Returns `true` if the next event must not be handled yet, counts down the steps otherwise
+    fn holds_up(&mut self) -> bool {
+        match self.paused {
+            Some(_) if self.steps == 0 => true,
+            Some(_) => {
+                self.steps -= 1;
+                false
+            }
+            None => false,
+        }
+    }
 }
 
 impl Simulation {
     pub fn new(mut config: SimulationConfig) -> Self {
         // initialize a channel so we can interact with the simulation
         let (external_sender, external_receiver) = mpsc::channel();
-        // binary heap, so all events are automatically ordered according to their time
-        let event_queue = Arc::new(Mutex::new(BinaryHeap::new()));
-        // Create the nodes and store in a hash map
-        let mut node_map = HashMap::with_capacity(config.number_of_nodes as usize);
+        // all events are ordered according to their time, the earliest is handed out first
+        let event_queue = Arc::new(Mutex::new(config.settings.simulation.scheduler.build()));
+        // Create the replicas and the clients that are nodes of their own and store in a hash map,
+        // the nodes of further clusters follow them
+        let composition = Composition::new(&config.settings);
+        let total_nodes = composition.total_nodes();
+        let mut node_map = HashMap::with_capacity(total_nodes as usize);
+        let mut timers = Timers::default();
+
+        let mut inboxes = HashMap::new();
+        // all random numbers of the run are drawn from its seed, so it can be repeated
+        let mut rng = StdRng::seed_from_u64(config.run.seed);
+
+        let results = ResultLogger::new();
+        if let Some(writer) = ResultsWriter::for_run(&config.run, &config.settings) {
+            results.write_to(writer);
+        }
+        for n in 1..=config.total_nodes() {
+            node_map.insert(n, config.build_node(&results));
+        }
+        for cluster in &composition.clusters()[1..] {
+            let mut cluster_config = config.of_cluster(cluster);
+            let results = results.for_cluster(cluster.offset);
+            for n in 1..=cluster.total_nodes() {
+                node_map.insert(cluster.global(n), cluster_config.build_node(&results));
+            }
+        }
+        for n in 1..=total_nodes {
+            timers.add_clock(n, Clock::random_with(&config.settings.node, &mut rng));
+            let node = &config.settings.node;
+            if let Some(max_rate) = node.max_rate {
+                inboxes.insert(
+                    n,
+                    Inbox::new(max_rate, node.overload_policy, node.inbox_size),
+                );
+            }
+        }
+
+        let mut disks = HashMap::new();
+        for cluster in composition.clusters() {
+            if config.settings.storage.persists(cluster.node_type) {
+                for n in cluster.replicas() {
+                    let mut disk = Disk::new(&config.settings.storage);
+                    disk.seed(rng.gen());
+                    disks.insert(n, disk);
+                }
+            }
+        }
 
-        for n in 1..=config.number_of_nodes {
-            node_map.insert(n, build_node(config.create_node_config()));
+        let settings = config.settings;
+        let run = config.run;
+        metrics::metrics().start_run(total_nodes);
+        let observers = results.observers().clone();
+        observers.register(Arc::new(Mutex::new(MetricsObserver)));
+        let statistics = Arc::new(Mutex::new(RunStatistics::new(&settings)));
+        observers.register(statistics.clone());
+        let causality = if settings.trace.causality {
+            let trace = Arc::new(Mutex::new(CausalityTrace::default()));
+            observers.register(trace.clone());
+            Some(trace)
+        } else {
+            None
+        };
+        let flow = if settings.trace.message_flow {
+            let flow = Arc::new(Mutex::new(MessageFlow::default()));
+            observers.register(flow.clone());
+            Some(flow)
+        } else {
+            None
+        };
+        #[cfg(feature = "sqlite")]
+        if let Some(path) = &settings.sqlite_file {
+            sqlite::start_run(path, config.node_type, config.number_of_nodes, &settings);
         }
 
+        let mut network = Network::new(total_nodes, &settings.network);
+        network.seed(rng.gen());
+        network.forge_authentication(
+            &settings.byzantine.nodes,
+            settings.byzantine.invalid_authentication,
+        );
+
+        let history = EventHistory::new(settings.simulation.event_history);
+        let receptions = ReceptionLog::new(settings.node.reception_history);
+        let workers = match settings.simulation.threads {
+            0 | 1 => None,
+            threads => Some(
+                ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .thread_name(|i| format!("simulation-worker-{}", i))
+                    .build()
+                    .expect("Failed to start the worker threads of the simulation"),
+            ),
+        };
         let result = Simulation {
             node_map,
             node_type: config.node_type,
+            composition,
             event_queue,
             external_sender,
-            network: Network::new(),
+            #[cfg(feature = "wasm")]
+            external_receiver,
+            network,
             time: Time::new(0),
             request_counter: 1,
+            timers,
+            inboxes,
+            disks,
+            stop_when_idle: !settings.simulation.wait_for_commands,
+            causality,
+            flow,
+            statistics,
+            observers,
+            settings,
+            events: 0,
+            results,
+            started: Instant::now(),
+            pause: Arc::new(Mutex::new(PauseState::default())),
+            run,
+            history,
+            receptions,
+            outputs: Vec::new(),
+            workers,
         };
 
+        // the periods of degraded links are network events at their time
+        for period in &result.settings.network.schedule {
+            result.add_events_to_queue(period.clone().into_events());
+        }
+        // the clients of the further clusters issue their requests from the start
+        for (index, cluster) in result.composition.clusters().iter().enumerate().skip(1) {
+            let run = &cluster.settings.simulation;
+            let requests =
+                RequestBatchConfig::new(run.requests, run.request_interval).cluster(index);
+            result.add_event_to_queue(Event::new_admin_requests_from_config(requests));
+        }
+
         // start receiving on the channel
+        #[cfg(not(feature = "wasm"))]
         result.start_receiving(external_receiver);
+        snapshot::write_next_to_result_log(&result.settings, &result.run);
         result
     }
 
+    /// Registers `observer` for the hooks of the simulation, see `observer::Observer`
+    pub fn add_observer(&mut self, observer: SharedObserver) {
+        self.observers.register(observer);
+    }
+
+    /// Schedules all actions of the scenario at `path` and runs the simulation
+    pub fn run_scenario<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let scenario = Scenario::from_file(path)?;
+        info!("Running a scenario of {} actions", scenario.actions.len());
+        self.add_events_to_queue(scenario.into_events());
+        self.start_handling();
+        Ok(())
+    }
+
     // Starts the action: loops over events in the queue and executes them sequentially
     pub fn start_handling(&mut self) {
-        info!(
-            "Simulation started for n = {} of type {:?}",
-            self.node_map.len(),
-            self.node_type
-        );
+        if let Err(divergence) = self.handle_until_stopped().agreement {
+            panic!("Safety violated: {}", divergence);
+        }
+    }
 
+    /// Handles the events until a stop event arrives and finishes the run. Unless the simulation
+    /// waits for commands, it stops itself once the queue stayed empty for a second.
+    pub fn handle_until_stopped(&mut self) -> SimulationReport {
         let mut timeout_active: Option<Instant> = None;
+        self.start();
 
         loop {
-            // access the queue, get the latest element and free the mutex
-            let mut queue = self.event_queue.lock().expect("Mutex lock poisoned. It appears that someone panicked, that wasn't allowed to panic");
-            let event = (*queue).pop();
-            drop(queue);
-
             // if an event was returned, handle it
-            if let Some(event) = event {
-                debug!(target: "simulation", "Processing event: {:?}", &event);
-
+            if let Some(event) = self.next_event() {
                 if timeout_active.is_some() {
                     timeout_active = None;
                 }
 
-                match event.event_type {
-                    EventType::Admin(admin_type) => match admin_type {
-                        AdminType::Stop => {
-                            info!("Received admin event, stopping simulation!");
-                            log_result(self.time, None, "Simulation finished");
-                            break;
-                        }
-                        AdminType::ClientRequests(config) => {
-                            let new_events = config.create_events(
-                                &mut self.request_counter,
-                                self.time,
-                                self.node_type,
-                            );
-                            for event in new_events {
-                                self.add_event_to_queue(event);
-                            }
-                        }
-                    },
-                    EventType::Network => {
-                        warn!(target: "simulation", "Network event still unimplemented")
-                    }
-                    EventType::Reception(r) => {
-                        self.update_time(event.time);
-                        let receiver = self.node_map.get_mut(&r.id).unwrap_or_else(|| {
-                            panic!("A message was sent to a non-existent node id {}", &r.id)
-                        });
-                        if let Some(new_events) = (**receiver).handle_event(r, self.time) {
-                            self.add_events_to_queue(new_events);
-                        }
-                    }
-                    EventType::Broadcast(b) => {
-                        self.update_time(event.time);
-                        if let Some(r) = self.network.handle_broadcast(self.time, b) {
-                            self.add_event_to_queue(r);
-                        }
-                    }
-                    EventType::Timeout(t) => {
-                        self.update_time(event.time);
-                        let timeout = env2var::<u64>("node.client_timeout");
-                        let time = self.time.add_milli(timeout);
-                        let event = Event::new_reception(t.c_id, t.message, time);
-
-                        self.add_event_to_queue(event);
-                    }
+                if !self.process_event(event) {
+                    return self.finish();
                 }
             } else {
-                if let Some(time) = timeout_active {
+                if !self.stop_when_idle || self.is_paused() {
+                    thread::sleep(Duration::from_millis(10));
+                } else if let Some(time) = timeout_active {
                     if Instant::now().duration_since(time) > Duration::from_secs(1) {
                         // Well, this is a little with the shotgun through the knee to hit the eye. nut iit should do the job:
                         // We inform our external receiver to stop the simulation, which should stop this loop
@@ -163,14 +466,848 @@ impl Simulation {
         }
     }
 
+    /// Handles the events until the queue is empty or a stop event arrives, without waiting for
+    /// external commands, and finishes the run
+    pub fn run_to_completion(&mut self) -> SimulationReport {
+        self.start();
+        while self.step() == Step::Handled {}
+        self.finish()
+    }
+
+    /// Lets the nodes schedule their initial outputs, the events are then handled one at a time
+    /// by `step` until the run is finished with `finish`
+    pub fn start(&mut self) {
+        info!(
+            "Simulation started for n = {} of type {:?}",
+            self.settings.node.nodes, self.node_type
+        );
+
+        self.started = Instant::now();
+        self.start_nodes();
+    }
+
+    /// Handles the next event, with `simulation.threads` above 1 a reception together with the
+    /// receptions of other nodes at the same time
+    pub fn step(&mut self) -> Step {
+        self.step_with(|_| {})
+    }
+
+    /// Like `step`, hands the event to `inspect` before it is handled
+    fn step_with<F: FnOnce(&Event)>(&mut self, inspect: F) -> Step {
+        #[cfg(feature = "wasm")]
+        self.receive_external();
+        match self.next_event() {
+            Some(event) => {
+                inspect(&event);
+                if self.process_event(event) {
+                    Step::Handled
+                } else {
+                    Step::Stopped
+                }
+            }
+            None if self.is_paused() => Step::Paused,
+            None => Step::Empty,
+        }
+    }
+
+    // lets the nodes schedule their initial outputs, in the order of their ids
+    fn start_nodes(&mut self) {
+        for id in self.node_ids() {
+            let outputs =
+                self.call_node(id, || "start".to_string(), |node, time| node.on_start(time));
+            if let Some(outputs) = outputs {
+                self.schedule_outputs(id, outputs);
+            }
+        }
+    }
+
+    // the ids of all nodes, in ascending order
+    fn node_ids(&self) -> Vec<u32> {
+        let mut ids: Vec<u32> = self.node_map.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    // access the queue, get the latest element and free the mutex
+    fn next_event(&mut self) -> Option<Event> {
+        let mut queue = self.event_queue.lock().expect(
+            "Mutex lock poisoned. It appears that someone panicked, that wasn't allowed to panic",
+        );
+        let stepping = match (*queue).peek() {
+            Some(_) => {
+                let mut pause = self.pause.lock().unwrap();
+                if pause.holds_up() {
+                    return None;
+                }
+                pause.paused.is_some()
+            }
+            None => false,
+        };
+        let event = (*queue).pop_next();
+        if let (true, Some(event)) = (stepping, &event) {
+            info!("Step: {:?}", event);
+        }
+        drop(queue);
+
+        if let Some(event) = &event {
+            self.pace(event.time);
+            debug!(target: "simulation", "Processing event: {:?}", event);
+            self.events += 1;
+        }
+        event
+    }
+
+    /// Handles a single event and tells the observers, returns `false` if the simulation has to stop
+    fn process_event(&mut self, event: Event) -> bool {
+        if self.workers.is_some() && matches!(event.event_type, EventType::Reception(_)) {
+            self.process_receptions(event);
+            return true;
+        }
+        let event = self.check_time(event);
+        self.history.record(&event);
+        let time = event.time;
+        let running = self.handle_event(event);
+        let queue_depth = self.event_queue.lock().unwrap().len();
+        self.observers.event_processed(time, queue_depth);
+        running
+    }
+
+    /// Handles the reception `first` together with the receptions of other nodes that follow it in
+    /// the queue at the same time. The nodes handle them on the worker threads. Their outputs and
+    /// result lines, and what the inboxes did with the receptions, are merged in the order the
+    /// receptions were taken from the queue, as if they were handled one after the other. So the
+    /// run does not depend on the number of threads or how they were scheduled.
+    fn process_receptions(&mut self, first: Event) {
+        let time = first.time;
+        let mut events = vec![first];
+        let mut ids = Vec::new();
+        if let EventType::Reception(r) = &events[0].event_type {
+            ids.push(r.id);
+        }
+        // a paused simulation handles a single event per step
+        if !self.is_paused() {
+            let mut queue = self.event_queue.lock().unwrap();
+            while let Some(next) = queue.peek() {
+                let id = match &next.event_type {
+                    EventType::Reception(r) if next.time == time && !next.immediate => r.id,
+                    _ => break,
+                };
+                if ids.contains(&id) {
+                    break;
+                }
+                ids.push(id);
+                let next = queue.pop_next().unwrap();
+                debug!(target: "simulation", "Processing event: {:?}", next);
+                events.push(next);
+            }
+        }
+        self.events += events.len() as u64 - 1;
+
+        let mut arrivals = Vec::with_capacity(events.len());
+        for event in events {
+            let event = self.check_time(event);
+            self.history.record(&event);
+            self.update_time(event.time);
+            if let EventType::Reception(r) = event.event_type {
+                let id = r.id;
+                if let Some(admission) = self.arrive(r) {
+                    arrivals.push((id, admission));
+                }
+            }
+        }
+        self.deliver_concurrently(arrivals);
+
+        let queue_depth = self.event_queue.lock().unwrap().len();
+        for _ in &ids {
+            self.observers.event_processed(time, queue_depth);
+        }
+    }
+
+    /// Returns the event unless it is before the current time, which only admin and network events
+    /// may be. Otherwise the run aborts or the event is moved to the current time, depending on
+    /// `simulation.time_violation`, both with a dump of the event and what led to it.
+    fn check_time(&self, mut event: Event) -> Event {
+        let scheduled = matches!(
+            event.event_type,
+            EventType::Admin(_) | EventType::Network(_)
+        );
+        if scheduled || event.time >= self.time {
+            return event;
+        }
+
+        let violation = {
+            let queue = self.event_queue.lock().unwrap();
+            let head = queue.peek();
+            TimeViolation::new(self.time, &event, head, &self.history)
+        };
+        match self.settings.simulation.time_violation {
+            TimeViolationPolicy::Abort => panic!("{}", violation),
+            TimeViolationPolicy::Clamp => {
+                warn!(
+                    "{}\n\
+                     The event is handled at the current time",
+                    violation
+                );
+                event.time = self.time;
+                event
+            }
+        }
+    }
+
+    /// Returns `true` if the simulation is paused
+    fn is_paused(&self) -> bool {
+        self.pause.lock().unwrap().paused.is_some()
+    }
+
+    /// Waits until the wall clock caught up with the simulated `time`, if the simulation is paced
+    fn pace(&self, time: Time) {
+        if let Some(speedup) = self.settings.simulation.speedup {
+            let due = self.started
+                + self.pause.lock().unwrap().paused_for
+                + Duration::from_secs_f64(time.micros() as f64 / 1_000_000.0 / speedup);
+            let now = Instant::now();
+            if due > now {
+                thread::sleep(due - now);
+            }
+        }
+    }
+
+    /// Handles a single event, returns `false` if the simulation has to stop
+    fn handle_event(&mut self, event: Event) -> bool {
+        match event.event_type {
+            EventType::Admin(admin_type) => {
+                // scheduled admin events move the simulation to their time, immediate ones and
+                // those scheduled in the past execute at the current time
+                if !event.immediate && event.time.micros() > self.time.micros() {
+                    self.update_time(event.time);
+                }
+                match admin_type {
+                    AdminType::Stop | AdminType::StopAt(_) => {
+                        info!("Received admin event, stopping simulation!");
+                        return false;
+                    }
+                    AdminType::ClientRequests(config) => {
+                        self.inject_requests(&config, self.time);
+                    }
+                    AdminType::ClientRequestsAt(time, config) => {
+                        // the simulation cannot go back in time
+                        if time.micros() < self.time.micros() {
+                            warn!(
+                                "Requests scheduled at {}ms are issued at the current time {}ms",
+                                time.milli(),
+                                self.time.milli()
+                            );
+                        }
+                        let time = Time::from_micros(time.micros().max(self.time.micros()));
+                        self.inject_requests(&config, time);
+                    }
+                    AdminType::CrashNode(id) => {
+                        info!("Crashing node {}", id);
+                        self.network.crash(id);
+                        self.results.log(self.time, Some(id), &ResultEvent::Crashed);
+                    }
+                    AdminType::Partition(nodes) => {
+                        info!("Partitioning nodes {:?} from the others", &nodes);
+                        self.results
+                            .log(self.time, None, &ResultEvent::Partition(nodes.clone()));
+                        self.network.partition(nodes);
+                    }
+                    AdminType::Heal => {
+                        info!("Healing the partition");
+                        self.network.heal();
+                        self.results.log(self.time, None, &ResultEvent::Heal);
+                    }
+                    admin_type => {
+                        self.pause.lock().unwrap().apply(&admin_type);
+                    }
+                }
+            }
+            EventType::Network(network_event) => {
+                if event.time.micros() > self.time.micros() {
+                    self.update_time(event.time);
+                }
+                info!("Reconfiguring the network: {:?}", &network_event);
+                match &network_event {
+                    NetworkEvent::PartitionStart(nodes) => {
+                        self.results
+                            .log(self.time, None, &ResultEvent::Partition(nodes.clone()))
+                    }
+                    NetworkEvent::PartitionEnd => {
+                        self.results.log(self.time, None, &ResultEvent::Heal)
+                    }
+                    _ => {}
+                }
+                self.network.handle_network_event(network_event);
+            }
+            EventType::Reception(r) => {
+                self.update_time(event.time);
+                if let Some(r) = self.admit(r) {
+                    self.deliver(r);
+                }
+            }
+            EventType::InboxReady(id) => {
+                self.update_time(event.time);
+                let inbox = self.inboxes.get_mut(&id).unwrap_or_else(|| {
+                    panic!(
+                        "The inbox of node {} is ready, but its rate is not limited",
+                        &id
+                    )
+                });
+                let (reception, ready_at) = inbox.next(self.time);
+                if let Some(ready_at) = ready_at {
+                    self.add_event_to_queue(Event::new_inbox_ready(id, ready_at));
+                }
+                // the waiting messages of a crashed node are lost
+                if let Some(r) = reception.filter(|_| !self.network.is_crashed(id)) {
+                    self.deliver(r);
+                }
+            }
+            EventType::Broadcast(b) => {
+                self.update_time(event.time);
+                let (from, to) = (b.id_from, b.id_to);
+                let message = Arc::clone(&b.message);
+                let reception = self.network.handle_broadcast(self.time, b);
+                let received = reception.as_ref().map(|r| r.time);
+                self.observers
+                    .message_sent(from, to, &message, self.time, received);
+                if let Some(r) = reception {
+                    self.add_event_to_queue(r);
+                }
+            }
+            EventType::Gossip(g) => {
+                self.update_time(event.time);
+                let from = g.id_from;
+                // the gossip spreads among the replicas of the sender's cluster, its clients and
+                // the other clusters neither relay nor handle it
+                let peers: Vec<u32> = self.composition.cluster_of(from).replicas().collect();
+                let receptions = self.network.handle_gossip(self.time, g, &peers);
+                for reception in &receptions {
+                    if let EventType::Reception(r) = &reception.event_type {
+                        self.observers.message_sent(
+                            from,
+                            r.id,
+                            &r.message,
+                            self.time,
+                            Some(reception.time),
+                        );
+                    }
+                }
+                self.add_events_to_queue(receptions);
+            }
+            EventType::SetTimer(request) => {
+                self.update_time(event.time);
+                let fired = self.timers.set_timer(request, self.time);
+                self.add_event_to_queue(fired);
+            }
+            EventType::CancelTimer(cancel) => {
+                self.update_time(event.time);
+                self.timers.cancel_timer(cancel.id, cancel.token);
+            }
+            EventType::TimerFired(fired) => {
+                self.update_time(event.time);
+                if self.timers.fire(&fired) && !self.network.is_crashed(fired.id) {
+                    if !self.node_map.contains_key(&fired.id) {
+                        panic!("A timer expired for a non-existent node id {}", &fired.id)
+                    }
+                    let outputs = self.call_node(
+                        fired.id,
+                        || format!("{:?}", fired),
+                        |node, time| node.handle_timer(fired.token, time),
+                    );
+                    if let Some(outputs) = outputs {
+                        self.schedule_outputs(fired.id, outputs);
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Passes the reception through the inbox of its node, returns it if the node handles it now
+    fn admit(&mut self, r: Reception) -> Option<Reception> {
+        let id = r.id;
+        let admission = self.arrive(r)?;
+        self.admitted(id, admission)
+    }
+
+    /// Hands the reception to the inbox of its node, `None` if the node crashed
+    fn arrive(&mut self, r: Reception) -> Option<Admission> {
+        let id = r.id;
+        // messages that were in flight when the node crashed are lost
+        if self.network.is_crashed(id) {
+            return None;
+        }
+        let admission = match self.inboxes.get_mut(&id) {
+            Some(inbox) => {
+                let priority = match self.node_map.get(&id) {
+                    Some(node) => node.priority(&r.message),
+                    None => r.message.priority(),
+                };
+                inbox.arrive(r, priority, self.time)
+            }
+            None => Admission::Handle(r),
+        };
+        Some(admission)
+    }
+
+    /// Schedules the inbox of node `id` or logs the drop, depending on the `admission`. Returns the
+    /// reception if the node handles it now.
+    fn admitted(&mut self, id: u32, admission: Admission) -> Option<Reception> {
+        match admission {
+            Admission::Handle(r) => return Some(r),
+            Admission::Queued(Some(ready_at)) => {
+                self.add_event_to_queue(Event::new_inbox_ready(id, ready_at))
+            }
+            Admission::Queued(None) => {}
+            Admission::Dropped(r) => {
+                debug!(target: "simulation", "Node {} is overloaded and drops {:?}", id, r.message);
+                let event = ResultEvent::Overloaded {
+                    protocol: r.message.protocol().to_string(),
+                    message_type: r.message.message_type().to_string(),
+                };
+                self.results.log(self.time, Some(id), &event);
+            }
+        }
+        None
+    }
+
+    /// Hands the receptions of distinct nodes their inboxes admitted to their nodes at the current
+    /// time, in parallel on the worker threads. The admissions, outputs, result lines and panics
+    /// are handled in the order of the `arrivals` afterwards.
+    fn deliver_concurrently(&mut self, arrivals: Vec<(u32, Admission)>) {
+        let handled_now = arrivals
+            .iter()
+            .filter(|(_, admission)| matches!(admission, Admission::Handle(_)))
+            .count();
+        if handled_now < 2 {
+            for (id, admission) in arrivals {
+                if let Some(r) = self.admitted(id, admission) {
+                    self.deliver(r);
+                }
+            }
+            return;
+        }
+
+        let time = self.time;
+        let mut jobs = Vec::with_capacity(handled_now);
+        let mut waiting = Vec::with_capacity(arrivals.len());
+        for (id, admission) in arrivals {
+            match admission {
+                Admission::Handle(r) => {
+                    let node = self.node_map.remove(&r.id).unwrap_or_else(|| {
+                        panic!("A message was sent to a non-existent node id {}", r.id)
+                    });
+                    self.receptions.record(time, &r);
+                    let local = self.timers.clock(r.id).local_time(time);
+                    jobs.push((r, node, local));
+                    waiting.push((id, None));
+                }
+                admission => waiting.push((id, Some(admission))),
+            }
+        }
+
+        let workers = self.workers.as_ref().unwrap();
+        let handled: Vec<(u32, Handled)> = workers.install(|| {
+            jobs.into_par_iter()
+                .map(|(reception, mut node, local)| {
+                    let id = reception.id;
+                    let message = Arc::clone(&reception.message);
+                    let (outputs, lines) = ResultLogger::capture(|| {
+                        ResultLogger::at(time, || {
+                            panic::catch_unwind(AssertUnwindSafe(|| {
+                                let mut outputs = Vec::new();
+                                if reception.decompression > 0 {
+                                    outputs.push(NodeOutput::Compute {
+                                        micros: reception.decompression,
+                                    });
+                                }
+                                node.handle_event_into(reception, local, &mut outputs);
+                                outputs
+                            }))
+                        })
+                    });
+                    let handled = Handled {
+                        node,
+                        message,
+                        outputs,
+                        lines,
+                    };
+                    (id, handled)
+                })
+                .collect()
+        });
+
+        let mut handled = handled.into_iter();
+        for (id, admission) in waiting {
+            if let Some(admission) = admission {
+                self.admitted(id, admission);
+                continue;
+            }
+            let (id, handled) = handled.next().unwrap();
+            self.node_map.insert(id, handled.node);
+            handled.lines.replay();
+            match handled.outputs {
+                Ok(outputs) => self.schedule_outputs(id, outputs),
+                Err(payload) => {
+                    let handling = format!("Reception of {:?}", handled.message);
+                    self.node_panicked(id, handling, payload);
+                }
+            }
+        }
+    }
+
+    /// Hands the reception to its node at the current time
+    fn deliver(&mut self, reception: Reception) {
+        let id = reception.id;
+        if !self.node_map.contains_key(&id) {
+            panic!("A message was sent to a non-existent node id {}", &id)
+        }
+        self.receptions.record(self.time, &reception);
+        let message = Arc::clone(&reception.message);
+        // the node pushes its outputs to the buffer of the simulation, which keeps its capacity
+        let mut outputs = std::mem::take(&mut self.outputs);
+        // the node decompresses the message before it handles it
+        if reception.decompression > 0 {
+            outputs.push(NodeOutput::Compute {
+                micros: reception.decompression,
+            });
+        }
+        let handled = self.call_node(
+            id,
+            || format!("Reception of {:?}", message),
+            |node, time| {
+                node.handle_event_into(reception, time, &mut outputs);
+                Some(())
+            },
+        );
+        if handled.is_some() {
+            self.schedule_outputs(id, outputs.drain(..));
+        }
+        outputs.clear();
+        self.outputs = outputs;
+    }
+
+    /// Lets node `id` handle what `call` hands it at the current time of its local clock. If the
+    /// node panics, it is written to a post-mortem dump of the run with what it was `handling` and
+    /// its last receptions. The panic ends the run unless `node.on_panic=crash`, which only
+    /// crashes the node.
+    fn call_node<R, H, C>(&mut self, id: u32, handling: H, call: C) -> Option<R>
+    where
+        H: FnOnce() -> String,
+        C: FnOnce(&mut dyn Node, Time) -> Option<R>,
+    {
+        let local = self.timers.clock(id).local_time(self.time);
+        let node = self.node_map.get_mut(&id).unwrap();
+        let handled = ResultLogger::at(self.time, || {
+            panic::catch_unwind(AssertUnwindSafe(|| call(&mut **node, local)))
+        });
+        match handled {
+            Ok(outputs) => outputs,
+            Err(payload) => {
+                self.node_panicked(id, handling(), payload);
+                None
+            }
+        }
+    }
+
+    /// Writes node `id`, which panicked with `payload` while `handling` something at the current
+    /// time, to a post-mortem dump and ends the run or crashes the node, see `call_node`
+    fn node_panicked(&mut self, id: u32, handling: String, payload: Box<dyn Any + Send>) {
+        let time = self.time;
+        let node = &self.node_map[&id];
+        let dump = NodeDump {
+            id,
+            time,
+            handling,
+            panic: postmortem::panic_message(&*payload),
+            state: format!("{:#?}", node),
+            receptions: self.receptions.of(id),
+        };
+        let path = self.run.path(&format!(
+            "panic_{:0>3}_node_{}.txt",
+            self.settings.node.nodes, id
+        ));
+        match dump.write(&path) {
+            Ok(()) => error!("Node {} panicked, wrote its dump to {}", id, path),
+            Err(e) => error!("Failed to write the dump {}: {}\n{}", path, e, dump),
+        }
+        match self.settings.node.on_panic {
+            PanicPolicy::Abort => panic::resume_unwind(payload),
+            PanicPolicy::Crash => {
+                self.network.crash(id);
+                self.results
+                    .log(time, Some(id), &ResultEvent::Panicked { dump: path });
+            }
+        }
+    }
+
+    /// Queues the requests of `config` to its cluster, the first one is issued at `time`
+    fn inject_requests(&mut self, config: &RequestBatchConfig, time: Time) {
+        let cluster = match self.composition.clusters().get(config.cluster) {
+            Some(cluster) => cluster,
+            None => {
+                warn!(
+                    "Ignoring {} requests to cluster {}, the run has {} clusters",
+                    config.number,
+                    config.cluster,
+                    self.composition.clusters().len()
+                );
+                return;
+            }
+        };
+        let settings = &cluster.settings;
+        let first_request = self.request_counter;
+        let mut new_events = config.create_events(
+            &mut self.request_counter,
+            time,
+            cluster.node_type,
+            cluster.nodes,
+            settings.node.clients,
+            settings.pbft.client_target.resolve(settings, cluster.nodes),
+        );
+        for (event, request_id) in new_events.iter_mut().zip(first_request..) {
+            if let EventType::Reception(r) = &mut event.event_type {
+                r.id = cluster.global(r.id);
+            }
+            self.observers.request_issued(request_id, event.time);
+        }
+        self.add_events_to_queue(new_events);
+    }
+
+    /// Writes the reports of the run and checks the agreement of the replicas
+    pub fn finish(&mut self) -> SimulationReport {
+        info!(
+            "Simulation stopped after {} of simulated time and {} events",
+            self.time.since_start(),
+            self.events
+        );
+        // the nodes write what they kept track of until the end
+        for id in self.node_ids() {
+            if self.network.is_crashed(id) {
+                continue;
+            }
+            let local = self.timers.clock(id).local_time(self.time);
+            let node = self.node_map.get_mut(&id).unwrap();
+            for event in ResultLogger::at(self.time, || node.on_stop(local)) {
+                self.results.log(self.time, Some(id), &event);
+            }
+        }
+        self.network.traffic().report(self.time, &self.results);
+        let mut disks: Vec<_> = self.disks.iter().collect();
+        disks.sort_by_key(|(id, _)| **id);
+        for (id, disk) in disks {
+            info!(
+                "Storage of node {}: {} writes, busy for {}µs",
+                id,
+                disk.writes(),
+                disk.busy()
+            );
+        }
+        // the reports are not observed, the statistics report themselves
+        self.observers.clear();
+        let (overdue, requests, views) = self
+            .statistics
+            .lock()
+            .unwrap()
+            .report(self.time, &self.results);
+        self.results.log(self.time, None, &ResultEvent::Finished);
+        self.results.finish();
+        self.write_causality();
+        self.write_flow();
+        self.write_latencies();
+        let agreement = self.check_agreement();
+        #[cfg(feature = "sqlite")]
+        sqlite::finish_run(self.time);
+
+        SimulationReport {
+            settings: (*self.settings).clone(),
+            end_time: self.time,
+            events: self.events,
+            results: self.results.take_lines(),
+            traffic: self.network.traffic().clone(),
+            executions: self.executions(),
+            agreement,
+            overdue,
+            requests,
+            views,
+        }
+    }
+
+    /// Writes the causality trace, if it was recorded
+    fn write_causality(&self) {
+        if let Some(trace) = &self.causality {
+            let prefix = self
+                .run
+                .path(&format!("causality_{:0>3}", self.settings.node.nodes));
+            match trace
+                .lock()
+                .unwrap()
+                .write_files(&prefix, self.settings.trace.request)
+            {
+                Ok(()) => info!("Wrote the causality trace to {}.json", prefix),
+                Err(e) => warn!("Failed to write the causality trace {}: {}", prefix, e),
+            }
+        }
+    }
+
+    /// Writes the message flow, if it was recorded
+    fn write_flow(&self) {
+        if let Some(flow) = &self.flow {
+            let path = self
+                .run
+                .path(&format!("message_flow_{:0>3}.json", self.settings.node.nodes));
+            match flow.lock().unwrap().write_file(&path) {
+                Ok(()) => info!("Wrote the message flow to {}", path),
+                Err(e) => warn!("Failed to write the message flow {}: {}", path, e),
+            }
+        }
+    }
+
+    fn write_latencies(&self) {
+        if !self.settings.stats.hgrm {
+            return;
+        }
+        let path = self
+            .run
+            .path(&format!("latency_{:0>3}.hgrm", self.settings.node.nodes));
+        let latencies = self.statistics.lock().unwrap().latencies(self.time);
+        match File::create(&path).and_then(|f| latencies.write_hgrm(BufWriter::new(f))) {
+            Ok(()) => info!("Wrote the latency distribution to {}", path),
+            Err(e) => warn!("Failed to write the latency distribution {}: {}", path, e),
+        }
+    }
+
+    /// The executions of all correct replicas by their ids in the run, crashed replicas are not
+    /// considered
+    fn executions(&self) -> Vec<ExecutionRecord> {
+        let mut records: Vec<ExecutionRecord> = self
+            .node_map
+            .iter()
+            .filter(|(id, _)| !self.network.is_crashed(**id))
+            .filter_map(|(id, node)| {
+                let mut record = node.execution()?;
+                record.node_id = *id;
+                Some(record)
+            })
+            .collect();
+        records.sort_by_key(|r| r.node_id);
+        records
+    }
+
+    /// Verifies that all correct replicas of each cluster executed the same requests in the same
+    /// order
+    fn check_agreement(&self) -> Result<(), Divergence> {
+        let records = self.executions();
+        let agreement = self.composition.clusters().iter().try_for_each(|cluster| {
+            let records: Vec<ExecutionRecord> = records
+                .iter()
+                .filter(|r| cluster.contains(r.node_id))
+                .cloned()
+                .collect();
+            safety::check_agreement(&records)
+        });
+        match &agreement {
+            Ok(()) => info!(
+                "All {} correct replicas agree on the executed requests",
+                records.len()
+            ),
+            Err(divergence) => error!("Safety violated: {}", divergence),
+        }
+        agreement
+    }
+
     fn update_time(&mut self, time: Time) {
-        // logically, it would have to be "<", but time was rewritten to be sorted reverse, so we check for the new time to be "smaller", i.e. after the current time
-        if time > self.time {
+        if time < self.time {
             panic!("The simulation handled an event that was before its current time!");
         }
         self.time = time;
     }
 
+    /// Converts the outputs of node `id` into events. Messages leave the node after the processing
+    /// delay, all computation the node requested before them and, if the protocol of the node
+    /// persists its log entries, the writes of them. A multicast is written once. Messages large
+    /// enough for `[compression]` are compressed, a multicast once as well.
+    fn schedule_outputs<I>(&mut self, id: u32, outputs: I)
+    where
+        I: IntoIterator<Item = NodeOutput>,
+    {
+        // the node sends to the ids of its cluster
+        let offset = self.composition.cluster_of(id).offset;
+        let departure =
+            self.time + time::Duration::from_millis(self.settings.node.processing_delay_of(id));
+        let mut compute = 0;
+        let mut written: Option<Arc<Message>> = None;
+        let mut compressed: Option<Arc<Message>> = None;
+
+        for output in outputs {
+            let message = match &output {
+                NodeOutput::Send { message, .. } | NodeOutput::Gossip { message, .. } => {
+                    Some(message)
+                }
+                _ => None,
+            };
+            if let (Some(message), Some(disk)) = (message, self.disks.get_mut(&id)) {
+                let multicast = matches!(&written, Some(w) if Arc::ptr_eq(w, message));
+                if message.is_persisted() && !multicast {
+                    let now = departure.micros() + compute;
+                    compute += disk.write(now);
+                    written = Some(Arc::clone(message));
+                }
+            }
+
+            let compression = match &output {
+                NodeOutput::Send { message, size, .. } | NodeOutput::Gossip { message, size } => {
+                    let compression = self.settings.compression.apply(*size);
+                    let multicast = matches!(&compressed, Some(c) if Arc::ptr_eq(c, message));
+                    if let Some(c) = compression.filter(|_| !multicast) {
+                        compute += c.cost;
+                        compressed = Some(Arc::clone(message));
+                    }
+                    compression
+                }
+                _ => None,
+            };
+            let decompression = compression.map_or(0, |c| c.cost);
+
+            let send_time = departure + time::Duration::from_micros(compute);
+
+            let event = match output {
+                NodeOutput::Send { to, message, size } => {
+                    let size = compression.map_or(size, |c| c.size);
+                    Event::new_broadcast_sized(id, offset + to, message, send_time, size)
+                        .with_decompression(decompression)
+                }
+                NodeOutput::Gossip { message, size } => {
+                    let size = compression.map_or(size, |c| c.size);
+                    Event::new_gossip(id, message, send_time, size)
+                        .with_decompression(decompression)
+                }
+                NodeOutput::DeliverToClient { to, message } => Event::new_broadcast_custom(
+                    id,
+                    offset + to,
+                    message,
+                    send_time,
+                    true,
+                    Some(Time::new(0)),
+                ),
+                NodeOutput::SetTimer { token, duration } => {
+                    Event::new_timer_request(id, token, duration, self.time)
+                }
+                NodeOutput::CancelTimer { token } => Event::new_cancel_timer(id, token, self.time),
+                NodeOutput::Compute { micros } => {
+                    compute += micros;
+                    continue;
+                }
+                NodeOutput::Log { event } => {
+                    self.results.log(self.time, Some(id), &event);
+                    continue;
+                }
+            };
+
+            self.add_event_to_queue(event);
+        }
+    }
+
     fn add_event_to_queue(&self, event: Event) {
         let mut queue = self.event_queue.lock().expect(
             "Mutex lock poisoned. It appears that someone panicked, that wasn't allowed to panic",
@@ -190,53 +1327,738 @@ impl Simulation {
         self.external_sender.clone()
     }
 
+    /// Return a handle to schedule fully-formed events, e.g. crafted messages for a node, which the
+    /// sender only accepts as administrative commands
+    pub fn injector(&self) -> Injector {
+        Injector::new(Arc::clone(&self.event_queue))
+    }
+
+    /// Lets `adversary` choose the delays of the messages of the network, up to
+    /// `network.adversary_delta`
+    pub fn set_adversary(&mut self, adversary: Box<dyn Adversary>) {
+        self.network.set_adversary(adversary);
+    }
+
     /// Starts the listener thread
+    #[cfg(not(feature = "wasm"))]
     fn start_receiving(&self, receiver: Receiver<EventType>) {
         let queue_clone = Arc::clone(&self.event_queue);
+        let pause = Arc::clone(&self.pause);
 
         debug!(target: "simulation", "Receiver thread: Starting");
-        thread::spawn(move || loop {
-            if let Ok(event_type) = receiver.recv() {
+        thread::spawn(move || {
+            while let Ok(event_type) = receiver.recv() {
                 debug!(target: "simulation", "Receiver thread: Received event type: {:?}", &event_type);
                 let mut queue = queue_clone.lock().expect("Mutex lock on queue poisoned. It appears that someone panicked, that wasn't allowed to panic.");
-                match event_type {
-                    EventType::Admin(admin_type) => {
-                        match admin_type{
-                            AdminType::Stop => {
-                                (*queue).push(Event::new_admin_stop());
-                                debug!(target: "simulation", "Receiver thread: Terminating");
-                                break;
-                            },
-                            AdminType::ClientRequests(config) => (*queue).push(Event::new_admin_requests_from_config(config)),
-                        }
-                    },
-                    _ => panic!(" Receiver thread: Received '{:?}' from external channel, but only Admin events are configured to be arrive from an external channel", event_type)
+                if !enqueue_external(&mut queue, &pause, event_type) {
+                    debug!(target: "simulation", "Receiver thread: Terminating");
+                    break;
                 }
             }
         });
     }
+
+    /// Queues the events received on the external channel so far, there is no thread receiving
+    /// them in the browser
+    #[cfg(feature = "wasm")]
+    fn receive_external(&mut self) {
+        while let Ok(event_type) = self.external_receiver.try_recv() {
+            let mut queue = self.event_queue.lock().unwrap();
+            if !enqueue_external(&mut queue, &self.pause, event_type) {
+                break;
+            }
+        }
+    }
+}
+
+/// Queues an event of the external channel, pausing and stepping take effect right away. Returns
+/// `false` once the simulation was told to stop.
+fn enqueue_external(
+    queue: &mut EventQueue,
+    pause: &Mutex<PauseState>,
+    event_type: EventType,
+) -> bool {
+    match event_type {
+        EventType::Admin(admin_type) => {
+            match admin_type {
+                AdminType::Stop => {
+                    // a paused simulation has to get to the stop
+                    pause.lock().unwrap().apply(&AdminType::Resume);
+                    (*queue).push(Event::new_admin_stop());
+                    return false;
+                }
+                // the events before the stop are still handled, so are further commands
+                AdminType::StopAt(time) => {
+                    pause.lock().unwrap().apply(&AdminType::Resume);
+                    (*queue).push(Event::new_admin_stop_at(time));
+                }
+                AdminType::ClientRequestsAt(time, config) => {
+                    (*queue).push(Event::new_admin_requests_at(time, config));
+                }
+                admin_type => {
+                    // pausing must not wait for the events in the queue
+                    if !pause.lock().unwrap().apply(&admin_type) {
+                        (*queue).push(Event::new_admin(admin_type));
+                    }
+                }
+            }
+        }
+        _ => panic!(
+            "Receiver thread: Received '{:?}' from external channel, but only Admin events are \
+             configured to arrive from an external channel, other events are scheduled with an \
+             Injector",
+            event_type
+        ),
+    }
+    true
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::simulation::event::{Event, Message};
-    use crate::simulation::time::Time;
+    use crate::network::adversary::SlowNodes;
+    use crate::node::factory::NodeFactory;
+    use crate::node::pbft::messages::{ClientRequest, CommitMessage, PBFTMessage};
+    use crate::node::request::RequestId;
+    use crate::node::{Node, NodeOutput, NodeType};
+    use crate::simulation::results::ResultEvent;
+    use std::cmp::Ordering;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use crate::simulation::config::{RequestBatchConfig, ResultLogger, SimulationConfig};
+    use crate::simulation::diagnostics::{TimeViolation, TimeViolationPolicy};
+    use crate::simulation::event::{AdminType, Event, EventType, Message, Reception};
+    use crate::simulation::inbox::OverloadPolicy;
+    use crate::simulation::observer::Observer;
+    use crate::simulation::postmortem::PanicPolicy;
+    use crate::simulation::report::SimulationReport;
+    use crate::simulation::scheduler::SchedulerKind;
+    use crate::simulation::settings::Settings;
+    use crate::simulation::time::{Duration, Time};
+    use crate::simulation::{run_simulation, EventQueue, Simulation, Step};
 
     #[test]
-    /// Check the ordering of time (lower time must be greater, so the heap removes it first)
+    /// Check the ordering of time (earlier times are smaller)
     fn check_time_ordering() {
         let time_small = Time::new(1);
         let time_large = Time::new(100);
-        assert!(time_small > time_large);
+        assert!(time_small < time_large);
+        assert_eq!(time_small.cmp(&Time::new(1)), Ordering::Equal);
+        assert_eq!(time_small.max(time_large), time_large);
     }
 
     #[test]
-    /// Check the ordering of Events (admin and time ordering)
+    /// Check the ordering of Events (admin and time ordering), the events handled first are smaller
     fn check_event_ordering() {
         let event_early = Event::new_broadcast(1, 2, Message::Dummy, Time::new(1));
         let event_late = Event::new_broadcast(1, 2, Message::Dummy, Time::new(100));
         let event_admin = Event::new_admin_stop();
-        assert!(event_early > event_late);
-        assert!(event_admin > event_early);
+        assert!(event_early < event_late);
+        assert!(event_admin < event_early);
+        assert!(event_early > event_admin);
+    }
+
+    /// Pushes the `events` to an event queue and returns them in the order they are popped
+    fn pop_order(events: Vec<Event>) -> Vec<(bool, bool, u64)> {
+        let mut queue: EventQueue = SchedulerKind::Heap.build();
+        for event in events {
+            queue.push(event);
+        }
+        std::iter::from_fn(|| queue.pop_next())
+            .map(|e| (e.immediate, e.is_admin(), e.time.micros()))
+            .collect()
+    }
+
+    #[test]
+    /// Check that the queue pops immediate admin events first and scheduled admin events in time
+    /// order, ahead of the other events at the same time
+    fn check_admin_event_scheduling() {
+        let popped = pop_order(vec![
+            Event::new_broadcast(1, 2, Message::Dummy, Time::new(100)),
+            Event::new_admin_stop_at(Time::new(100)),
+            Event::new_admin_requests_at(Time::new(50), RequestBatchConfig::new(1, 10)),
+            Event::new_broadcast(1, 2, Message::Dummy, Time::new(10)),
+            Event::new_broadcast(1, 2, Message::Dummy, Time::new(200)),
+            Event::new_admin(AdminType::CrashNode(1)),
+        ]);
+        assert_eq!(
+            popped,
+            vec![
+                (true, true, 0),
+                (false, false, 10_000),
+                (false, true, 50_000),
+                (false, true, 100_000),
+                (false, false, 100_000),
+                (false, false, 200_000),
+            ]
+        );
+    }
+
+    #[test]
+    /// Check that the queue pops the earliest event first, also when the times are only a few
+    /// microseconds apart or pushed in reverse
+    fn check_queue_pops_earliest_event_first() {
+        let times = [2_001, 2_000, 1_999, 7, 0, 2_000, 1_000_000];
+        let popped = pop_order(
+            times
+                .iter()
+                .map(|&t| Event::new_broadcast(1, 2, Message::Dummy, Time::from_micros(t)))
+                .collect(),
+        );
+        let popped: Vec<u64> = popped.into_iter().map(|(_, _, time)| time).collect();
+        assert_eq!(popped, vec![0, 7, 1_999, 2_000, 2_000, 2_001, 1_000_000]);
+    }
+
+    /// A simulation at 20ms that already handled a timer, with a reception waiting in the queue
+    fn simulation_at_20ms(time_violation: TimeViolationPolicy) -> Simulation {
+        let mut settings = Settings::default();
+        settings.simulation.time_violation = time_violation;
+        settings.simulation.event_history = 4;
+        let mut simulation = Simulation::new(SimulationConfig::new(settings));
+        simulation.results.collect();
+        simulation.process_event(Event::new_cancel_timer(3, 7, Time::new(20)));
+        simulation.add_event_to_queue(Event::new_reception(4, Message::Dummy, Time::new(25)));
+        simulation
+    }
+
+    #[test]
+    #[should_panic(expected = "created by node: 2")]
+    /// Check that an event before the current time aborts the run with its context
+    fn check_event_before_current_time_aborts() {
+        let mut simulation = simulation_at_20ms(TimeViolationPolicy::Abort);
+        simulation.process_event(Event::new_cancel_timer(2, 1, Time::new(10)));
+    }
+
+    #[test]
+    /// Check that an event before the current time can be moved to the current time instead
+    fn check_event_before_current_time_is_clamped() {
+        let simulation = simulation_at_20ms(TimeViolationPolicy::Clamp);
+        let event = simulation.check_time(Event::new_cancel_timer(2, 1, Time::new(10)));
+        assert_eq!(event.time, Time::new(20));
+
+        // admin events before the current time are handled at the current time anyway
+        let stop = simulation.check_time(Event::new_admin_stop_at(Time::new(5)));
+        assert_eq!(stop.time, Time::new(5));
+
+        let violation = TimeViolation::new(
+            simulation.time,
+            &Event::new_cancel_timer(2, 1, Time::new(10)),
+            None,
+            &simulation.history,
+        );
+        assert!(violation.history[0].contains("CancelTimer"));
+    }
+
+    #[test]
+    /// Check that a stop at a time lets the pending events before it run, an immediate stop not
+    fn check_stop_at_a_time() {
+        let run = |stop: Event| {
+            let mut settings = Settings::default();
+            settings.simulation.requests = 0;
+            // the first two requests are done long before the stop, the third is issued after it
+            settings.network.delay_min = 50.0;
+            settings.network.delay_max = 50.0;
+            let mut simulation = Simulation::new(SimulationConfig::new(settings));
+            simulation.results.collect();
+            simulation.add_event_to_queue(Event::new_admin_requests_from_config(
+                RequestBatchConfig::new(3, 1000),
+            ));
+            simulation.add_event_to_queue(stop);
+            simulation.run_to_completion()
+        };
+
+        let report = run(Event::new_admin_stop_at(Time::new(1500)));
+        assert_eq!(report.end_time.milli(), 1500);
+        assert_eq!(report.done_requests(), (1..=2).collect());
+        // nothing happens after the stop
+        for line in &report.results {
+            let time: u64 = line.split(';').next().unwrap().parse().unwrap();
+            assert!(time <= 1500, "{} is logged after the stop", line);
+        }
+
+        let report = run(Event::new_admin_stop());
+        assert_eq!(report.events, 1);
+        assert!(report.done_requests().is_empty());
+    }
+
+    #[test]
+    /// Check that the receptions of a multicast share the message until it is taken out
+    fn check_receptions_share_message() {
+        let message = Arc::new(Message::Dummy);
+        let first = Reception::new(1, Arc::clone(&message));
+        let second = Reception::new(2, Arc::clone(&message));
+        assert_eq!(Arc::strong_count(&message), 3);
+
+        assert_eq!(first.into_message(), Message::Dummy);
+        drop(message);
+        assert_eq!(Arc::strong_count(&second.message), 1);
+        assert_eq!(second.into_message(), Message::Dummy);
+    }
+
+    #[test]
+    /// Check that a registered observer is told about the events, messages and commits of a run
+    fn check_observer_follows_the_run() {
+        #[derive(Default)]
+        struct Counter {
+            events: u64,
+            messages: u64,
+            committed: Vec<u64>,
+        }
+
+        impl Observer for Counter {
+            fn on_event_processed(&mut self, _: Time, _: usize) {
+                self.events += 1;
+            }
+
+            fn on_message_sent(&mut self, _: u32, _: u32, _: &Message, _: Time, _: Option<Time>) {
+                self.messages += 1;
+            }
+
+            fn on_request_committed(&mut self, request_id: u64, _: Time) {
+                self.committed.push(request_id);
+            }
+        }
+
+        let mut settings = Settings::default();
+        settings.simulation.requests = 3;
+        let mut simulation = Simulation::new(SimulationConfig::new(settings));
+        let counter = Arc::new(Mutex::new(Counter::default()));
+        simulation.add_observer(counter.clone());
+        simulation.add_event_to_queue(Event::new_admin_requests_from_config(
+            RequestBatchConfig::new(3, 10),
+        ));
+
+        let report = simulation.run_to_completion();
+        let mut counter = counter.lock().unwrap();
+        assert_eq!(counter.events, report.events);
+        assert!(counter.messages > 0);
+        counter.committed.sort_unstable();
+        assert_eq!(counter.committed, vec![1, 2, 3]);
+    }
+
+    #[test]
+    /// Check that the inbox of a node with a limited rate delays its messages, or drops them once
+    /// it is full
+    fn check_overloaded_nodes() {
+        let run = |max_rate, policy| {
+            let mut settings = Settings::default();
+            settings.node.nodes = 4;
+            settings.simulation.requests = 3;
+            settings.node.max_rate = max_rate;
+            settings.node.overload_policy = policy;
+            settings.node.inbox_size = 1;
+            // the runs only differ in the rate limit, not in the delays
+            settings.network.delay_min = 50.0;
+            settings.network.delay_max = 50.0;
+            let mut simulation = Simulation::new(SimulationConfig::new(settings));
+            simulation.results.collect();
+            simulation.add_event_to_queue(Event::new_admin_requests_from_config(
+                RequestBatchConfig::new(3, 1000),
+            ));
+            simulation.run_to_completion()
+        };
+        let overloaded = |report: &SimulationReport| {
+            report
+                .results
+                .iter()
+                .filter(|line| line.contains(";overloaded;"))
+                .count()
+        };
+
+        let unlimited = run(None, OverloadPolicy::Queue);
+        let queued = run(Some(20), OverloadPolicy::Queue);
+        assert_eq!(queued.done_requests(), (1..=3).collect());
+        assert_eq!(overloaded(&queued), 0);
+        assert!(queued.end_time.milli() > unlimited.end_time.milli());
+
+        let dropped = run(Some(20), OverloadPolicy::Drop);
+        assert!(overloaded(&dropped) > 0);
+    }
+
+    #[test]
+    /// Check that a paced run takes at least its simulated time divided by the speedup
+    fn check_paced_simulation_follows_wall_clock() {
+        let mut settings = Settings::default();
+        settings.node.nodes = 4;
+        settings.simulation.requests = 2;
+        settings.simulation.speedup = Some(20.0);
+
+        let started = std::time::Instant::now();
+        let report = run_simulation(settings);
+        let elapsed = started.elapsed().as_millis() as u64;
+        assert_eq!(report.done_requests(), (1..=2).collect());
+        assert!(
+            elapsed >= report.end_time.milli() / 20,
+            "{}ms simulated in {}ms",
+            report.end_time.milli(),
+            elapsed
+        );
+    }
+
+    #[test]
+    /// Check that requests scheduled at a time are issued at that time, not when the admin event
+    /// is handled
+    fn check_requests_scheduled_at_a_time() {
+        let mut settings = Settings::default();
+        settings.simulation.requests = 0;
+        let mut simulation = Simulation::new(SimulationConfig::new(settings));
+        simulation.results.collect();
+        simulation.add_event_to_queue(Event::new_admin_requests_at(
+            Time::new(500),
+            RequestBatchConfig::new(2, 100),
+        ));
+
+        let report = simulation.run_to_completion();
+        // the client 5 issues the requests, the primary receives them later
+        let issued: Vec<&str> = report
+            .results
+            .iter()
+            .filter(|line| line.ends_with(";request"))
+            .filter(|line| line.split(';').nth(1) == Some("5"))
+            .filter_map(|line| line.split(';').next())
+            .collect();
+        assert_eq!(issued, vec!["500", "600"]);
+        assert_eq!(report.done_requests(), (1..=2).collect());
+    }
+
+    #[test]
+    /// Check that a paused simulation only handles the events it steps through
+    fn check_pause_and_step() {
+        let mut settings = Settings::default();
+        settings.simulation.requests = 2;
+        let mut simulation = Simulation::new(SimulationConfig::new(settings));
+        simulation.results.collect();
+        simulation.add_event_to_queue(Event::new_admin_requests_from_config(
+            RequestBatchConfig::new(2, 1000),
+        ));
+        let apply = |simulation: &Simulation, admin_type| {
+            simulation.pause.lock().unwrap().apply(&admin_type);
+        };
+        let handle = |simulation: &mut Simulation| {
+            let mut handled = 0;
+            while let Some(event) = simulation.next_event() {
+                simulation.handle_event(event);
+                handled += 1;
+            }
+            handled
+        };
+
+        apply(&simulation, AdminType::Pause);
+        assert_eq!(handle(&mut simulation), 0);
+
+        apply(&simulation, AdminType::Step(3));
+        assert_eq!(handle(&mut simulation), 3);
+        assert_eq!(simulation.events, 3);
+        assert_eq!(handle(&mut simulation), 0);
+
+        apply(&simulation, AdminType::Resume);
+        let report = simulation.run_to_completion();
+        assert_eq!(report.agreement, Ok(()));
+        assert_eq!(report.done_requests(), (1..=2).collect());
+    }
+
+    #[test]
+    /// Check that stepping through the events runs like `run_simulation`
+    fn check_step_by_step() {
+        let mut settings = Settings::default();
+        settings.simulation.requests = 2;
+        settings.network.omission_probability = 0.0;
+        settings.simulation.seed = Some(7);
+        let reference = run_simulation(settings.clone());
+
+        let mut simulation = Simulation::new(SimulationConfig::new(settings));
+        simulation.results.collect();
+        simulation.add_event_to_queue(Event::new_admin_requests_from_config(
+            RequestBatchConfig::new(2, 1000),
+        ));
+        simulation.start();
+        let mut receptions = 0;
+        let step = |simulation: &mut Simulation, receptions: &mut u32| {
+            simulation.step_with(|event| {
+                if let EventType::Reception(_) = event.event_type {
+                    *receptions += 1;
+                }
+            })
+        };
+        assert_eq!(step(&mut simulation, &mut receptions), Step::Handled);
+        simulation.pause.lock().unwrap().apply(&AdminType::Pause);
+        assert_eq!(simulation.step(), Step::Paused);
+        simulation.pause.lock().unwrap().apply(&AdminType::Resume);
+        while step(&mut simulation, &mut receptions) == Step::Handled {}
+        assert_eq!(simulation.step(), Step::Empty);
+
+        let report = simulation.finish();
+        assert!(receptions > 0);
+        assert_eq!(report.events, reference.events);
+        assert_eq!(report.results, reference.results);
+    }
+
+    #[test]
+    /// Check that the nodes are started before and stopped after all events
+    fn check_node_lifecycle_hooks() {
+        /// Sets a timer on start and counts the timers it saw until it is stopped
+        #[derive(Debug, Default)]
+        struct TickingNode {
+            ticks: u64,
+        }
+
+        impl Node for TickingNode {
+            fn handle_event(&mut self, _: Reception, _: Time) -> Option<Vec<NodeOutput>> {
+                None
+            }
+
+            fn on_start(&mut self, _: Time) -> Option<Vec<NodeOutput>> {
+                Some(vec![NodeOutput::SetTimer {
+                    token: 1,
+                    duration: 100,
+                }])
+            }
+
+            fn handle_timer(&mut self, token: u64, _: Time) -> Option<Vec<NodeOutput>> {
+                self.ticks += 1;
+                match self.ticks {
+                    1..=2 => Some(vec![NodeOutput::SetTimer {
+                        token,
+                        duration: 100,
+                    }]),
+                    _ => None,
+                }
+            }
+
+            fn on_stop(&mut self, _: Time) -> Vec<ResultEvent> {
+                vec![ResultEvent::Reply(self.ticks)]
+            }
+        }
+
+        let mut factory = NodeFactory::default();
+        factory.register("ticking", NodeType::PBFT, |_| {
+            Box::new(TickingNode::default())
+        });
+        let mut simulation = Simulation::new(
+            SimulationConfig::new(Settings::default()).nodes_from(factory, "ticking"),
+        );
+        simulation.results.collect();
+        let report = simulation.run_to_completion();
+
+        assert_eq!(report.end_time, Time::new(300));
+        let stopped: Vec<&String> = report
+            .results
+            .iter()
+            .filter(|line| line.ends_with(";3;reply"))
+            .collect();
+        assert_eq!(stopped.len(), 4);
+        assert!(stopped.iter().all(|line| line.starts_with("300;")));
+    }
+
+    #[test]
+    /// Check that the nodes read their local clocks, while the result log keeps the global time
+    fn check_nodes_read_their_local_clocks() {
+        /// Writes the local time at which its timer fired to the result log
+        #[derive(Debug)]
+        struct ClockNode {
+            id: u32,
+            results: ResultLogger,
+        }
+
+        impl Node for ClockNode {
+            fn handle_event(&mut self, _: Reception, _: Time) -> Option<Vec<NodeOutput>> {
+                None
+            }
+
+            fn on_start(&mut self, _: Time) -> Option<Vec<NodeOutput>> {
+                Some(vec![NodeOutput::SetTimer {
+                    token: 1,
+                    duration: 100,
+                }])
+            }
+
+            fn handle_timer(&mut self, _: u64, time: Time) -> Option<Vec<NodeOutput>> {
+                self.results
+                    .log(time, Some(self.id), &ResultEvent::Reply(time.milli()));
+                None
+            }
+        }
+
+        let mut factory = NodeFactory::default();
+        factory.register("clock", NodeType::PBFT, |config| {
+            Box::new(ClockNode {
+                id: config.id,
+                results: config.results,
+            })
+        });
+        let mut settings = Settings::default();
+        settings.node.clock_offset_max = 50;
+        settings.simulation.seed = Some(1);
+        let mut simulation =
+            Simulation::new(SimulationConfig::new(settings).nodes_from(factory, "clock"));
+        simulation.results.collect();
+        let report = simulation.run_to_completion();
+
+        let local: Vec<u64> = report
+            .results
+            .iter()
+            .filter(|line| line.ends_with(";reply"))
+            .map(|line| {
+                assert!(line.starts_with("100;"), "{} is not logged at 100ms", line);
+                line.split(';').nth(2).unwrap().parse().unwrap()
+            })
+            .collect();
+        assert_eq!(local.len(), 4);
+        assert!(local.iter().all(|time| (50..=150).contains(time)));
+        assert!(local.iter().any(|time| *time != 100));
+    }
+
+    /// Node 1 sends two messages to node 2, which panics on the second one
+    #[derive(Debug, Default)]
+    struct FragileNode {
+        id: u32,
+        received: u64,
+    }
+
+    impl Node for FragileNode {
+        fn handle_event(&mut self, _: Reception, _: Time) -> Option<Vec<NodeOutput>> {
+            self.received += 1;
+            if self.id == 2 && self.received == 2 {
+                panic!("Backup received client request");
+            }
+            None
+        }
+
+        fn on_start(&mut self, _: Time) -> Option<Vec<NodeOutput>> {
+            let send = || NodeOutput::DeliverToClient {
+                to: 2,
+                message: Arc::new(Message::Dummy),
+            };
+            match self.id {
+                1 => Some(vec![send(), send()]),
+                _ => None,
+            }
+        }
+
+        fn on_stop(&mut self, _: Time) -> Vec<ResultEvent> {
+            vec![ResultEvent::Reply(self.received)]
+        }
+    }
+
+    fn run_fragile_nodes(on_panic: PanicPolicy) -> SimulationReport {
+        let mut factory = NodeFactory::default();
+        factory.register("fragile", NodeType::PBFT, |config| {
+            Box::new(FragileNode {
+                id: config.id,
+                received: 0,
+            })
+        });
+        let mut settings = Settings::default();
+        settings.node.on_panic = on_panic;
+        let mut simulation =
+            Simulation::new(SimulationConfig::new(settings).nodes_from(factory, "fragile"));
+        simulation.results.collect();
+        simulation.run_to_completion()
+    }
+
+    #[test]
+    /// Check that a node that panics is dumped and, if asked for, only the node is crashed
+    fn check_panicking_node_is_dumped_and_crashed() {
+        let report = run_fragile_nodes(PanicPolicy::Crash);
+
+        let dump = report
+            .results
+            .iter()
+            .find_map(|line| match line.splitn(3, ';').collect::<Vec<_>>()[..] {
+                [_, "2", event] => match ResultEvent::parse(event) {
+                    Some(ResultEvent::Panicked { dump }) => Some(dump),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .expect("node 2 is reported as panicked");
+        assert!(dump.ends_with("panic_004_node_2.txt"));
+        // the others keep running until the end, the panicked node is not stopped
+        let stopped = report.results.iter().filter(|l| l.ends_with("reply"));
+        assert_eq!(stopped.count(), 3);
+
+        let text = std::fs::read_to_string(&dump).unwrap();
+        assert!(text.starts_with("Node 2 panicked at 5000µs: Backup received client request"));
+        assert!(text.contains("FragileNode {\n    id: 2,\n    received: 2,"));
+        assert_eq!(text.matches("µs: Dummy").count(), 2);
+        std::fs::remove_file(dump).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Backup received client request")]
+    /// Check that the panic of a node ends the run by default
+    fn check_panicking_node_aborts_the_run() {
+        run_fragile_nodes(PanicPolicy::Abort);
+    }
+
+    #[test]
+    /// Check that a crafted message injected at a time arrives at its node at that time, and a
+    /// forged one is rejected there without keeping the replicas from committing
+    fn check_injected_messages_arrive_at_their_node() {
+        let mut settings = Settings::default();
+        settings.node.nodes = 4;
+        settings.simulation.requests = 0;
+        let mut simulation = Simulation::new(SimulationConfig::new(settings));
+        simulation.results.collect();
+        simulation.add_event_to_queue(Event::new_admin_requests_from_config(
+            RequestBatchConfig::new(3, 10),
+        ));
+
+        let commit = |sender_id| {
+            Message::PBFT(PBFTMessage::Commit(CommitMessage::new(
+                ClientRequest::new(RequestId::new(0, 2), 7),
+                0,
+                2,
+                sender_id,
+            )))
+        };
+        let injector = simulation.injector();
+        let handle = thread::spawn(move || {
+            injector.inject_forged_message(3, commit(1), Time::new(5));
+            injector.inject_message(3, commit(4), Time::new(7));
+        });
+        handle.join().unwrap();
+
+        let report = simulation.run_to_completion();
+        assert_eq!(report.agreement, Ok(()));
+        assert_eq!(report.done_requests(), (1..=3).collect());
+        assert!(report
+            .results
+            .iter()
+            .any(|line| line.starts_with("5;3;rejected;PBFT;Commit")));
+    }
+
+    #[test]
+    /// Check that an adversary delaying the messages of the primary slows down the requests, but
+    /// only up to the bound of the run
+    fn check_adversary_is_bounded() {
+        let run = |adversary: Option<SlowNodes>| {
+            let mut settings = Settings::default();
+            settings.node.nodes = 4;
+            settings.simulation.requests = 0;
+            settings.network.delay_min = 10.0;
+            settings.network.delay_max = 10.0;
+            settings.network.adversary_delta = 50;
+            settings.simulation.seed = Some(1);
+            let mut simulation = Simulation::new(SimulationConfig::new(settings));
+            simulation.results.collect();
+            if let Some(adversary) = adversary {
+                simulation.set_adversary(Box::new(adversary));
+            }
+            simulation.add_event_to_queue(Event::new_admin_requests_from_config(
+                RequestBatchConfig::new(3, 10),
+            ));
+            simulation.run_to_completion()
+        };
+
+        let honest = run(None);
+        let slowed = run(Some(SlowNodes::new(vec![1], Duration::from_millis(50))));
+        let beyond_bound = run(Some(SlowNodes::new(vec![1], Duration::from_millis(5000))));
+        for report in &[&honest, &slowed, &beyond_bound] {
+            assert_eq!(report.agreement, Ok(()));
+            assert_eq!(report.done_requests(), (1..=3).collect());
+        }
+        assert!(slowed.end_time > honest.end_time);
+        assert_eq!(beyond_bound.end_time, slowed.end_time);
     }
 }
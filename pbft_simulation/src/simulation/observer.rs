@@ -0,0 +1,201 @@
+/***************************************************************************************************
+Hooks into a running simulation. Observers are registered on the simulation and are told about
+every handled event, every issued request, every message handed to the network and every entry of
+the result log, from which the committed requests and the view changes are derived. The statistics, the causality trace
+and the live metrics are observers themselves, external code registers its own next to them:
+
+    let counter = Arc::new(Mutex::new(CommitCounter::default()));
+    simulation.add_observer(counter.clone());
+    simulation.run_to_completion();
+
+Observers are called while the simulation or a node is busy, so they must not write to the result
+log themselves. The reports written once the run is finished are not observed.
+***************************************************************************************************/
+
+use std::collections::HashSet;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use crate::simulation::event::Message;
+use crate::simulation::results::ResultEvent;
+use crate::simulation::time::Time;
+
+/// Hooks of the simulation, all of them do nothing by default
+pub trait Observer: Send {
+    /// Called after the simulation handled an event at `time`, `queue_depth` events are left
+    fn on_event_processed(&mut self, _time: Time, _queue_depth: usize) {}
+
+    /// Called for every message `from` handed to the network for `to` at `sent`, `received` is
+    /// the time it arrives or `None` if it is lost
+    fn on_message_sent(
+        &mut self,
+        _from: u32,
+        _to: u32,
+        _message: &Message,
+        _sent: Time,
+        _received: Option<Time>,
+    ) {
+    }
+
+    /// Called once per request when the simulation schedules it, `time` is when its client issues
+    /// it
+    fn on_request_issued(&mut self, _request_id: u64, _time: Time) {}
+
+    /// Called for every entry of the result log
+    fn on_result(&mut self, _time: Time, _node_id: Option<u32>, _event: &ResultEvent) {}
+
+    /// Called once per request, when any node executed or completed it for the first time
+    fn on_request_committed(&mut self, _request_id: u64, _time: Time) {}
+
+    /// Called when the node `node_id` moves on to `view`, i.e. when an adaptive node switches to
+    /// its next protocol instance
+    fn on_view_change(&mut self, _node_id: u32, _view: u64, _time: Time) {}
+}
+
+/// An observer shared between the simulation and its owner, who reads it once the run is finished
+pub type SharedObserver = Arc<Mutex<dyn Observer>>;
+
+/// The observers of a run, shared by the simulation and the result log
+#[derive(Clone, Default)]
+pub struct Observers {
+    registry: Arc<Mutex<Registry>>,
+}
+
+#[derive(Default)]
+struct Registry {
+    observers: Vec<SharedObserver>,
+    /// Requests committed so far, each is passed to `on_request_committed` once
+    committed: HashSet<u64>,
+}
+
+impl Observers {
+    /// Registers `observer` for all hooks from now on
+    pub fn register(&self, observer: SharedObserver) {
+        self.registry.lock().unwrap().observers.push(observer);
+    }
+
+    /// Removes all observers, e.g. before the reports of the run are written
+    pub fn clear(&self) {
+        self.registry.lock().unwrap().observers.clear();
+    }
+
+    /// Returns `true` if no observer is registered
+    pub fn is_empty(&self) -> bool {
+        self.registry.lock().unwrap().observers.is_empty()
+    }
+
+    pub fn event_processed(&self, time: Time, queue_depth: usize) {
+        self.each(|o| o.on_event_processed(time, queue_depth));
+    }
+
+    pub fn request_issued(&self, request_id: u64, time: Time) {
+        self.each(|o| o.on_request_issued(request_id, time));
+    }
+
+    pub fn message_sent(
+        &self,
+        from: u32,
+        to: u32,
+        message: &Message,
+        sent: Time,
+        received: Option<Time>,
+    ) {
+        self.each(|o| o.on_message_sent(from, to, message, sent, received));
+    }
+
+    /// Passes an entry of the result log and the commits and view changes it stands for
+    pub fn result(&self, time: Time, node_id: Option<u32>, event: &ResultEvent) {
+        let (observers, committed) = {
+            let mut registry = self.registry.lock().unwrap();
+            let committed = event
+                .done_request()
+                .filter(|id| registry.committed.insert(*id));
+            (registry.observers.clone(), committed)
+        };
+        let view_change = match (node_id, event) {
+            (Some(id), ResultEvent::Switched { instance, .. }) => Some((id, *instance)),
+            _ => None,
+        };
+
+        for observer in observers {
+            let mut observer = observer.lock().unwrap();
+            observer.on_result(time, node_id, event);
+            if let Some(request_id) = committed {
+                observer.on_request_committed(request_id, time);
+            }
+            if let Some((id, view)) = view_change {
+                observer.on_view_change(id, view, time);
+            }
+        }
+    }
+
+    // calls `hook` on every observer, the registry is not locked meanwhile
+    fn each<F: FnMut(&mut dyn Observer)>(&self, mut hook: F) {
+        let observers = self.registry.lock().unwrap().observers.clone();
+        for observer in observers {
+            hook(&mut *observer.lock().unwrap());
+        }
+    }
+}
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Observers")
+            .field("observers", &self.registry.lock().unwrap().observers.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts the calls of the hooks
+    #[derive(Debug, Default)]
+    struct Counter {
+        results: u32,
+        committed: Vec<u64>,
+        view_changes: Vec<(u32, u64)>,
+    }
+
+    impl Observer for Counter {
+        fn on_result(&mut self, _time: Time, _node_id: Option<u32>, _event: &ResultEvent) {
+            self.results += 1;
+        }
+
+        fn on_request_committed(&mut self, request_id: u64, _time: Time) {
+            self.committed.push(request_id);
+        }
+
+        fn on_view_change(&mut self, node_id: u32, view: u64, _time: Time) {
+            self.view_changes.push((node_id, view));
+        }
+    }
+
+    #[test]
+    fn check_commits_are_passed_once() {
+        let observers = Observers::default();
+        let counter = Arc::new(Mutex::new(Counter::default()));
+        observers.register(counter.clone());
+
+        observers.result(Time::new(1), Some(1), &ResultEvent::Request(7));
+        observers.result(Time::new(2), Some(1), &ResultEvent::Executed(7));
+        observers.result(Time::new(3), Some(2), &ResultEvent::Executed(7));
+        observers.result(
+            Time::new(4),
+            Some(2),
+            &ResultEvent::Switched {
+                instance: 2,
+                protocol: "pbft".to_string(),
+            },
+        );
+
+        let counter = counter.lock().unwrap();
+        assert_eq!(counter.results, 4);
+        assert_eq!(counter.committed, vec![7]);
+        assert_eq!(counter.view_changes, vec![(2, 2)]);
+
+        observers.clear();
+        assert!(observers.is_empty());
+    }
+}
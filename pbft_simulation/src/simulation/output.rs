@@ -0,0 +1,88 @@
+/***************************************************************************************************
+The namespace of the files a run writes. Every run gets an id of its start time and its seed, all
+its files go to the directory 'log/<run id>', so simulations running at the same time in the same
+working directory don't overwrite each other's logs.
+***************************************************************************************************/
+
+use std::fmt;
+use std::fs;
+
+use log::warn;
+use rand::Rng;
+use serde::Serialize;
+
+use crate::simulation::wall_clock;
+
+/// The directory the directories of the runs are created in
+const OUTPUT_DIR: &str = "log";
+
+/// Identifies a run and the directory of its files
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RunId {
+    /// Start of the run, seconds since the Unix epoch
+    pub started: u64,
+    /// Seed of the random numbers of the run, `simulation.seed` or drawn at random. It also tells
+    /// runs apart that started in the same second.
+    pub seed: u64,
+}
+
+impl RunId {
+    /// The id of a run starting now with a random seed
+    pub fn new() -> Self {
+        RunId::seeded(None)
+    }
+
+    /// The id of a run starting now with `seed`, a random one if `None`
+    pub fn seeded(seed: Option<u64>) -> Self {
+        RunId {
+            started: wall_clock::unix_seconds(),
+            seed: seed.unwrap_or_else(|| rand::thread_rng().gen()),
+        }
+    }
+
+    /// The directory of the files of the run
+    pub fn dir(&self) -> String {
+        format!("{}/{}", OUTPUT_DIR, self)
+    }
+
+    /// The path of the file `name` of the run, the directory of the run is created if needed
+    pub fn path(&self, name: &str) -> String {
+        let dir = self.dir();
+        if let Err(e) = fs::create_dir_all(&dir) {
+            warn!("Failed to create the output directory {}: {}", dir, e);
+        }
+        format!("{}/{}", dir, name)
+    }
+}
+
+impl Default for RunId {
+    fn default() -> Self {
+        RunId::new()
+    }
+}
+
+impl fmt::Display for RunId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{:016x}", self.started, self.seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_runs_get_their_own_directory() {
+        let run = RunId {
+            started: 1_700_000_000,
+            seed: 0xbeef,
+        };
+        assert_eq!(run.dir(), "log/1700000000_000000000000beef");
+        // runs started at the same time are told apart by their seed
+        let other = RunId {
+            seed: 0xcafe,
+            ..run.clone()
+        };
+        assert_ne!(run.dir(), other.dir());
+    }
+}
@@ -0,0 +1,196 @@
+/***************************************************************************************************
+Post-mortem dumps of nodes that panicked. The simulation keeps the last receptions of every node, a
+node that panics is written to a file of the run with its state, what it was handling and these
+receptions. Afterwards the run ends or, with `node.on_panic=crash`, only the node is crashed.
+***************************************************************************************************/
+
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::simulation::event::{Message, Reception};
+use crate::simulation::time::Time;
+
+/// What happens to the run once a node panicked
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum PanicPolicy {
+    /// The panic ends the run
+    Abort,
+    /// The node is crashed, the others keep running
+    Crash,
+}
+
+impl FromStr for PanicPolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy {
+            "abort" => Ok(PanicPolicy::Abort),
+            "crash" => Ok(PanicPolicy::Crash),
+            _ => Err(format!(
+                "panic policy '{}' is not available, allowed are 'abort', 'crash'",
+                policy
+            )),
+        }
+    }
+}
+
+/// A message a node received
+#[derive(Debug, Clone)]
+struct Received {
+    time: Time,
+    message: Arc<Message>,
+    forged: bool,
+}
+
+/// The last receptions of every node, at most `capacity` per node
+#[derive(Debug, Default)]
+pub struct ReceptionLog {
+    capacity: usize,
+    receptions: HashMap<u32, VecDeque<Received>>,
+}
+
+impl ReceptionLog {
+    pub fn new(capacity: usize) -> Self {
+        ReceptionLog {
+            capacity,
+            receptions: HashMap::new(),
+        }
+    }
+
+    /// Remembers that the node of `reception` handles it at `time`, the message is shared
+    pub fn record(&mut self, time: Time, reception: &Reception) {
+        if self.capacity == 0 {
+            return;
+        }
+        let receptions = self.receptions.entry(reception.id).or_default();
+        if receptions.len() == self.capacity {
+            receptions.pop_front();
+        }
+        receptions.push_back(Received {
+            time,
+            message: Arc::clone(&reception.message),
+            forged: reception.forged,
+        });
+    }
+
+    /// The remembered receptions of node `id`, the oldest first
+    pub fn of(&self, id: u32) -> Vec<String> {
+        self.receptions
+            .get(&id)
+            .map_or_else(Vec::new, |receptions| {
+                receptions
+                    .iter()
+                    .map(|r| {
+                        let forged = if r.forged { " (forged)" } else { "" };
+                        format!("{}µs{}: {:?}", r.time.micros(), forged, r.message)
+                    })
+                    .collect()
+            })
+    }
+}
+
+/// Everything known about a node when it panicked
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeDump {
+    pub id: u32,
+    pub time: Time,
+    /// What the node was handling, e.g. the message it received
+    pub handling: String,
+    /// The message of the panic
+    pub panic: String,
+    /// The state of the node after the panic, as far as it got
+    pub state: String,
+    /// The last receptions of the node, the oldest first
+    pub receptions: Vec<String>,
+}
+
+impl NodeDump {
+    /// Writes the dump to the file at `path`
+    pub fn write(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+impl fmt::Display for NodeDump {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Node {} panicked at {}µs: {}",
+            self.id,
+            self.time.micros(),
+            self.panic
+        )?;
+        writeln!(f, "\nHandling:\n{}", self.handling)?;
+        writeln!(f, "\nState:\n{}", self.state)?;
+        writeln!(f, "\nLast receptions (oldest first):")?;
+        if self.receptions.is_empty() {
+            writeln!(f, "none, see node.reception_history")?;
+        }
+        for reception in &self.receptions {
+            writeln!(f, "{}", reception)?;
+        }
+        Ok(())
+    }
+}
+
+/// The message of a panic caught with `std::panic::catch_unwind`
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_reception_log_keeps_the_last_receptions_per_node() {
+        let mut log = ReceptionLog::new(2);
+        for time in 1..=3 {
+            log.record(Time::new(time), &Reception::new(1, Message::Dummy));
+        }
+        log.record(Time::new(4), &Reception::new(2, Message::Dummy));
+
+        assert_eq!(log.of(1), vec!["2000µs: Dummy", "3000µs: Dummy"]);
+        assert_eq!(log.of(2).len(), 1);
+        assert!(log.of(3).is_empty());
+        assert!(ReceptionLog::new(0).of(1).is_empty());
+    }
+
+    #[test]
+    fn check_dump_shows_the_node() {
+        let dump = NodeDump {
+            id: 3,
+            time: Time::new(12),
+            handling: "Reception of Dummy".to_string(),
+            panic: "Backup received client request".to_string(),
+            state: "TestNode { view: 1 }".to_string(),
+            receptions: vec!["11000µs: Dummy".to_string()],
+        };
+        let text = dump.to_string();
+        assert!(text.starts_with("Node 3 panicked at 12000µs: Backup received client request"));
+        assert!(text.contains("State:\nTestNode { view: 1 }"));
+        assert!(text.contains("11000µs: Dummy"));
+    }
+
+    #[test]
+    fn check_panic_message() {
+        let payload = std::panic::catch_unwind(|| panic!("failed at {}", 7)).unwrap_err();
+        assert_eq!(panic_message(&*payload), "failed at 7");
+        let payload = std::panic::catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(panic_message(&*payload), "static");
+        assert_eq!("crash".parse(), Ok(PanicPolicy::Crash));
+    }
+}
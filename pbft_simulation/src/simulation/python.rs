@@ -0,0 +1,241 @@
+/***************************************************************************************************
+Python bindings of the simulation, only built with the feature 'python', e.g. with maturin. The
+module 'bft_simulation' runs a simulation in the process of the interpreter and returns its report,
+so analysis pipelines in pandas can configure runs and read the latencies without parsing the result
+logs:
+
+    import bft_simulation
+    report = bft_simulation.run_simulation({"node.nodes_vec": 4, "simulation.requests": 100})
+    pandas.DataFrame({"issued": report.issued_ms(), "latency": report.latencies_ms()},
+                     index=report.request_ids())
+***************************************************************************************************/
+
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyString};
+
+use crate::simulation::config::try_load_config;
+use crate::simulation::report::SimulationReport;
+use crate::simulation::scenario::Scenario;
+use crate::simulation::settings::Settings;
+
+/// Outcome of a run, see `SimulationReport`
+#[pyclass(name = "Report")]
+#[derive(Debug)]
+pub struct PyReport {
+    report: SimulationReport,
+    /// Id, issue time (ms) and latency (ms) of the done requests after the warm-up
+    latencies: Vec<(u64, u64, u64)>,
+}
+
+impl From<SimulationReport> for PyReport {
+    fn from(report: SimulationReport) -> Self {
+        let latencies = report
+            .request_latencies()
+            .into_iter()
+            .map(|(id, (issued, latency))| (id, issued, latency))
+            .collect();
+        PyReport { report, latencies }
+    }
+}
+
+#[pymethods]
+impl PyReport {
+    /// Simulation time (ms) the run ended at
+    #[getter]
+    fn end_time_ms(&self) -> u64 {
+        self.report.end_time.milli()
+    }
+
+    /// Number of events handled
+    #[getter]
+    fn events(&self) -> u64 {
+        self.report.events
+    }
+
+    /// How the correct replicas diverged, `None` if they executed the same requests in order
+    #[getter]
+    fn divergence(&self) -> Option<String> {
+        self.report.agreement.as_ref().err().map(|d| d.to_string())
+    }
+
+    #[getter]
+    fn issued(&self) -> u64 {
+        self.report.requests.issued
+    }
+
+    #[getter]
+    fn done(&self) -> u64 {
+        self.report.requests.done
+    }
+
+    #[getter]
+    fn mean_latency_ms(&self) -> f64 {
+        self.report.requests.mean_latency
+    }
+
+    #[getter]
+    fn max_latency_ms(&self) -> u64 {
+        self.report.requests.max_latency
+    }
+
+    /// Done requests per second
+    #[getter]
+    fn throughput(&self) -> f64 {
+        self.report.requests.throughput
+    }
+
+    /// Requests that were not done within the liveness bound
+    #[getter]
+    fn overdue(&self) -> Vec<u64> {
+        self.report.overdue.clone()
+    }
+
+    /// Lines of the result log, 'time;node id (-1 for none);message'
+    #[getter]
+    fn results(&self) -> Vec<String> {
+        self.report.results.clone()
+    }
+
+    /// Ids of the done requests after the warm-up, in the order of the other arrays
+    fn request_ids(&self) -> Vec<u64> {
+        self.latencies.iter().map(|(id, _, _)| *id).collect()
+    }
+
+    /// Simulation time (ms) each done request was issued at
+    fn issued_ms(&self) -> Vec<u64> {
+        self.latencies
+            .iter()
+            .map(|(_, issued, _)| *issued)
+            .collect()
+    }
+
+    /// Latency (ms) of each done request
+    fn latencies_ms(&self) -> Vec<u64> {
+        self.latencies
+            .iter()
+            .map(|(_, _, latency)| *latency)
+            .collect()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Report(end_time_ms={}, issued={}, done={}, mean_latency_ms={:.3})",
+            self.end_time_ms(),
+            self.issued(),
+            self.done(),
+            self.mean_latency_ms()
+        )
+    }
+}
+
+// the overrides 'section.key=value' of a dict {"section.key": value}, a single string or a list of
+// strings, the booleans of Python are written like those of the ini
+fn parse_overrides(settings: Option<&PyAny>) -> PyResult<Vec<String>> {
+    let settings = match settings {
+        Some(settings) => settings,
+        None => return Ok(Vec::new()),
+    };
+    if let Ok(dict) = settings.downcast::<PyDict>() {
+        dict.iter()
+            .map(|(key, value)| {
+                let value = match value.downcast::<PyBool>() {
+                    Ok(flag) => flag.is_true().to_string(),
+                    Err(_) => value.str()?.to_string(),
+                };
+                Ok(format!("{}={}", key.str()?, value))
+            })
+            .collect()
+    } else if let Ok(line) = settings.downcast::<PyString>() {
+        Ok(vec![line.to_string()])
+    } else {
+        settings.extract()
+    }
+}
+
+// a scenario file, or the actions of a scenario as Python objects in the layout of its JSON
+fn parse_scenario(py: Python, scenario: &PyAny) -> PyResult<Scenario> {
+    if let Ok(path) = scenario.extract::<&str>() {
+        return Scenario::from_file(path).map_err(|e| {
+            PyIOError::new_err(format!("Failed to read the scenario {}: {}", path, e))
+        });
+    }
+    let json: String = py
+        .import("json")?
+        .call_method1("dumps", (scenario,))?
+        .extract()?;
+    serde_json::from_str(&json)
+        .map_err(|e| PyValueError::new_err(format!("Invalid scenario: {}", e)))
+}
+
+/// Runs a simulation to completion and returns its Report. The settings override single keys of
+/// the config like the arguments of the binary, as a dict {"section.key": value} or a list of
+/// strings 'section.key=value'. Without a config_file, 'simulation.ini' of the working directory is
+/// read if it exists. A scenario, a JSON or YAML file or its actions as a dict, replaces the
+/// configured client requests.
+#[pyfunction(settings = "None", config_file = "None", scenario = "None")]
+#[pyo3(text_signature = "(settings=None, config_file=None, scenario=None)")]
+fn run_simulation(
+    py: Python,
+    settings: Option<&PyAny>,
+    config_file: Option<String>,
+    scenario: Option<&PyAny>,
+) -> PyResult<PyReport> {
+    let args = config_file.into_iter().chain(parse_overrides(settings)?);
+    let values = try_load_config(args).map_err(PyValueError::new_err)?;
+    let settings = Settings::from_values(&values);
+    let scenario = scenario
+        .map(|scenario| parse_scenario(py, scenario))
+        .transpose()?;
+
+    // the run does not need the interpreter
+    let report = py.allow_threads(move || match scenario {
+        Some(scenario) => crate::simulation::run_scenario(settings, scenario),
+        None => crate::simulation::run_simulation(settings),
+    });
+    Ok(report.into())
+}
+
+/// The module 'bft_simulation' of Python
+#[pymodule]
+fn bft_simulation(_py: Python, module: &PyModule) -> PyResult<()> {
+    module.add_class::<PyReport>()?;
+    module.add_function(wrap_pyfunction!(run_simulation, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_runs_return_the_latencies_of_the_requests() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let settings = PyDict::new(py);
+            settings.set_item("node.nodes_vec", 4).unwrap();
+            settings.set_item("simulation.requests", 3).unwrap();
+            settings
+                .set_item("network.omission_probability", 0.0)
+                .unwrap();
+            settings.set_item("log.result", false).unwrap();
+            assert_eq!(
+                parse_overrides(Some(settings.as_ref())).unwrap()[3],
+                "log.result=false".to_string()
+            );
+
+            let report = run_simulation(py, Some(settings.as_ref()), None, None).unwrap();
+            assert_eq!(report.divergence(), None);
+            assert_eq!(report.done(), 3);
+            assert_eq!(report.request_ids().len(), 3);
+            assert_eq!(
+                report.latencies_ms().into_iter().max(),
+                Some(report.max_latency_ms())
+            );
+
+            let broken = vec!["simulation.requests=many"].into_py(py);
+            let error = run_simulation(py, Some(broken.as_ref(py)), None, None).unwrap_err();
+            assert!(error.is_instance::<PyValueError>(py));
+        });
+    }
+}
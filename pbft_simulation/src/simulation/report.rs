@@ -0,0 +1,97 @@
+/***************************************************************************************************
+Structured outcome of a simulation run, returned by `run_simulation` so callers do not have to parse
+the result logs.
+***************************************************************************************************/
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::simulation::results::ResultEvent;
+use crate::simulation::safety::{Divergence, ExecutionRecord};
+use crate::simulation::settings::Settings;
+use crate::simulation::statistics::{
+    RequestStatistics, RequestSummary, TrafficStatistics, ViewSummary,
+};
+use crate::simulation::time::Time;
+
+/// Outcome of a simulation run
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationReport {
+    pub settings: Settings,
+    /// Simulation time the run ended at
+    pub end_time: Time,
+    /// Number of events handled
+    pub events: u64,
+    /// Lines of the result log, 'time;node id (-1 for none);message', only collected by
+    /// `run_simulation`
+    pub results: Vec<String>,
+    pub traffic: TrafficStatistics,
+    /// Executions of the correct replicas, ordered by node id
+    pub executions: Vec<ExecutionRecord>,
+    pub agreement: Result<(), Divergence>,
+    /// Requests that were not done within the liveness bound
+    pub overdue: Vec<u64>,
+    /// Latency and throughput of the requests issued after the warm-up
+    pub requests: RequestSummary,
+    /// View changes of the run and the throughput of each view
+    pub views: ViewSummary,
+}
+
+impl SimulationReport {
+    /// Ids of the requests a replica executed or the client completed
+    pub fn done_requests(&self) -> BTreeSet<u64> {
+        self.results
+            .iter()
+            .filter_map(|line| ResultEvent::parse(line.splitn(3, ';').nth(2)?)?.done_request())
+            .collect()
+    }
+
+    /// The time (ms) each done request after the warm-up was issued at and its latency (ms), by
+    /// id, replayed from the result log like the latency summary of `requests`
+    pub fn request_latencies(&self) -> BTreeMap<u64, (u64, u64)> {
+        let stats = &self.settings.stats;
+        let mut statistics = RequestStatistics::new(stats.warmup_ms, stats.cooldown_ms);
+        for line in &self.results {
+            let mut fields = line.splitn(3, ';');
+            let time = fields.next().and_then(|time| time.parse::<u64>().ok());
+            let event = fields.nth(1).and_then(ResultEvent::parse);
+            if let (Some(time), Some(event)) = (time, event) {
+                statistics.record(Time::new(time), &event);
+            }
+        }
+        statistics.latencies_by_request(self.end_time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_done_requests_are_parsed() {
+        let report = SimulationReport {
+            settings: Settings::default(),
+            end_time: Time::new(100),
+            events: 0,
+            results: vec![
+                "10;1;1;request".to_string(),
+                "20;2;1;committed_local".to_string(),
+                "25;2;1;executed".to_string(),
+                "30;-1;watchdog;2;request;10;10;-1".to_string(),
+                // committed locally, but not executed
+                "35;3;4;committed_local".to_string(),
+                "40;5;3;completed".to_string(),
+            ],
+            traffic: TrafficStatistics::default(),
+            executions: Vec::new(),
+            agreement: Ok(()),
+            overdue: vec![2],
+            requests: RequestSummary::default(),
+            views: ViewSummary::default(),
+        };
+        assert_eq!(report.done_requests(), vec![1, 3].into_iter().collect());
+        assert_eq!(
+            report.request_latencies(),
+            vec![(1, (10, 15)), (3, (40, 0))].into_iter().collect()
+        );
+    }
+}
@@ -0,0 +1,446 @@
+/***************************************************************************************************
+The events written to the result log. Nodes and the simulation report what happened as typed
+events, which are encoded into the lines of the result log in a single place. Tools reading the
+result log decode the lines with `ResultEvent::parse` instead of splitting them on their own.
+***************************************************************************************************/
+
+use std::fmt;
+
+use crate::simulation::statistics::{RequestSummary, TrafficCounter, ViewPeriod};
+
+/// An entry of the result log. Requests are referred to by their sequence number, which is unique
+/// within a simulation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResultEvent {
+    /// A replica received the client request
+    Request(u64),
+    PrePrepared(u64),
+    Prepared(u64),
+    CommittedLocal(u64),
+    /// A replica executed the request, after all requests ordered before it
+    Executed(u64),
+    /// The client accepted the result of the request
+    Completed(u64),
+    /// The client timed out waiting for the result
+    TimedOut(u64),
+    /// A replica fetched the request from its peers
+    StateTransfer(u64),
+    /// A replica ordered the request without executing it yet
+    Ordered(u64),
+    /// A replica executed the request speculatively
+    SpeculativeCommit(u64),
+    /// The client collected a commit certificate for the request
+    CommitCertificate(u64),
+    /// A replica rolled back the speculative execution of the request
+    RolledBack(u64),
+    /// A replica resent its reply to the client
+    Reply(u64),
+    Crashed,
+    /// The node panicked and was crashed, its post-mortem dump is the file `dump`
+    Panicked {
+        dump: String,
+    },
+    /// The nodes were cut off from all others
+    Partition(Vec<u32>),
+    Heal,
+    Finished,
+    /// An adaptive node aborted the protocol `instance`
+    Aborted {
+        instance: u64,
+    },
+    /// An adaptive node switched to `protocol` for `instance`
+    Switched {
+        instance: u64,
+        protocol: String,
+    },
+    /// The traffic of a message type in the run
+    Traffic {
+        protocol: String,
+        message_type: String,
+        counter: TrafficCounter,
+    },
+    /// A request that was not done within the liveness bound
+    Watchdog {
+        request: u64,
+        last_phase: String,
+        first_seen: u64,
+        last_seen: u64,
+        done: Option<u64>,
+    },
+    /// A replica dropped a message whose authenticator is invalid
+    Rejected {
+        protocol: String,
+        message_type: String,
+    },
+    /// A replica dropped a message it cannot handle, see `ProtocolError`
+    Invalid {
+        protocol: String,
+        message_type: String,
+    },
+    /// An overloaded node dropped a message because its inbox was full
+    Overloaded {
+        protocol: String,
+        message_type: String,
+    },
+    /// Latency and throughput of the run
+    Requests(RequestSummary),
+    /// `percentile` percent of the requests of the run took at most `latency` ms
+    Percentile {
+        percentile: f64,
+        latency: u64,
+    },
+    /// The view changes of the run
+    ViewChanges {
+        view_changes: u64,
+        unstable_ms: u64,
+        aborted_requests: u64,
+        reproposed_requests: u64,
+    },
+    /// The throughput of a view of the run
+    View(ViewPeriod),
+}
+
+impl ResultEvent {
+    /// The request and the name of the phase it reached, for the events of a single request
+    pub fn request_phase(&self) -> Option<(u64, &'static str)> {
+        let phase = match self {
+            ResultEvent::Request(id) => (*id, "request"),
+            ResultEvent::PrePrepared(id) => (*id, "pre-prepared"),
+            ResultEvent::Prepared(id) => (*id, "prepared"),
+            ResultEvent::CommittedLocal(id) => (*id, "committed_local"),
+            ResultEvent::Executed(id) => (*id, "executed"),
+            ResultEvent::Completed(id) => (*id, "completed"),
+            ResultEvent::TimedOut(id) => (*id, "timed-out"),
+            ResultEvent::StateTransfer(id) => (*id, "state_transfer"),
+            ResultEvent::Ordered(id) => (*id, "ordered"),
+            ResultEvent::SpeculativeCommit(id) => (*id, "speculative_commit"),
+            ResultEvent::CommitCertificate(id) => (*id, "commit_certificate"),
+            ResultEvent::RolledBack(id) => (*id, "rolled_back"),
+            ResultEvent::Reply(id) => (*id, "reply"),
+            _ => return None,
+        };
+        Some(phase)
+    }
+
+    /// The request that is done with this event, i.e. executed by a replica or completed by the
+    /// client. A replica that committed a request locally may still wait for requests ordered
+    /// before it, so the local commit alone does not count.
+    pub fn done_request(&self) -> Option<u64> {
+        match self {
+            ResultEvent::Executed(id) | ResultEvent::Completed(id) => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// Decodes the message part of a line of the result log, i.e. without time and node id
+    pub fn parse(message: &str) -> Option<ResultEvent> {
+        let fields: Vec<&str> = message.split(';').collect();
+        let number = |i: usize| fields.get(i)?.parse::<u64>().ok();
+
+        let event = match fields.as_slice() {
+            ["crashed"] => ResultEvent::Crashed,
+            ["panicked", dump] => ResultEvent::Panicked {
+                dump: dump.to_string(),
+            },
+            ["heal"] => ResultEvent::Heal,
+            ["Simulation finished"] => ResultEvent::Finished,
+            ["partition", ids] => ResultEvent::Partition(
+                ids.split(',')
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()
+                    .ok()?,
+            ),
+            ["aborted", _] => ResultEvent::Aborted {
+                instance: number(1)?,
+            },
+            ["switched", _, protocol] => ResultEvent::Switched {
+                instance: number(1)?,
+                protocol: protocol.to_string(),
+            },
+            ["rejected", protocol, message_type] => ResultEvent::Rejected {
+                protocol: protocol.to_string(),
+                message_type: message_type.to_string(),
+            },
+            ["invalid", protocol, message_type] => ResultEvent::Invalid {
+                protocol: protocol.to_string(),
+                message_type: message_type.to_string(),
+            },
+            ["overloaded", protocol, message_type] => ResultEvent::Overloaded {
+                protocol: protocol.to_string(),
+                message_type: message_type.to_string(),
+            },
+            ["traffic", protocol, message_type, _, _, _] => ResultEvent::Traffic {
+                protocol: protocol.to_string(),
+                message_type: message_type.to_string(),
+                counter: TrafficCounter {
+                    sent: number(3)?,
+                    bytes: number(4)?,
+                    dropped: number(5)?,
+                },
+            },
+            ["watchdog", _, last_phase, _, _, done] => ResultEvent::Watchdog {
+                request: number(1)?,
+                last_phase: last_phase.to_string(),
+                first_seen: number(3)?,
+                last_seen: number(4)?,
+                done: match *done {
+                    "-1" => None,
+                    _ => Some(number(5)?),
+                },
+            },
+            ["requests", _, _, mean_latency, _, throughput] => {
+                ResultEvent::Requests(RequestSummary {
+                    issued: number(1)?,
+                    done: number(2)?,
+                    mean_latency: mean_latency.parse().ok()?,
+                    max_latency: number(4)?,
+                    throughput: throughput.parse().ok()?,
+                })
+            }
+            ["view_changes", _, _, _, _] => ResultEvent::ViewChanges {
+                view_changes: number(1)?,
+                unstable_ms: number(2)?,
+                aborted_requests: number(3)?,
+                reproposed_requests: number(4)?,
+            },
+            ["view", _, _, _, throughput] => ResultEvent::View(ViewPeriod {
+                view: number(1)?,
+                duration: number(2)?,
+                done: number(3)?,
+                throughput: throughput.parse().ok()?,
+            }),
+            ["percentile", percentile, _] => ResultEvent::Percentile {
+                percentile: percentile.parse().ok()?,
+                latency: number(2)?,
+            },
+            [_, phase] => {
+                let id = number(0)?;
+                match *phase {
+                    "request" => ResultEvent::Request(id),
+                    "pre-prepared" => ResultEvent::PrePrepared(id),
+                    "prepared" => ResultEvent::Prepared(id),
+                    "committed_local" => ResultEvent::CommittedLocal(id),
+                    "executed" => ResultEvent::Executed(id),
+                    "completed" => ResultEvent::Completed(id),
+                    "timed-out" => ResultEvent::TimedOut(id),
+                    "state_transfer" => ResultEvent::StateTransfer(id),
+                    "ordered" => ResultEvent::Ordered(id),
+                    "speculative_commit" => ResultEvent::SpeculativeCommit(id),
+                    "commit_certificate" => ResultEvent::CommitCertificate(id),
+                    "rolled_back" => ResultEvent::RolledBack(id),
+                    "reply" => ResultEvent::Reply(id),
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        Some(event)
+    }
+}
+
+/// Encodes the event as the message part of a line of the result log
+impl fmt::Display for ResultEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some((id, phase)) = self.request_phase() {
+            return write!(f, "{};{}", id, phase);
+        }
+
+        match self {
+            ResultEvent::Crashed => write!(f, "crashed"),
+            ResultEvent::Panicked { dump } => write!(f, "panicked;{}", dump),
+            ResultEvent::Partition(ids) => {
+                let ids: Vec<String> = ids.iter().map(u32::to_string).collect();
+                write!(f, "partition;{}", ids.join(","))
+            }
+            ResultEvent::Heal => write!(f, "heal"),
+            ResultEvent::Finished => write!(f, "Simulation finished"),
+            ResultEvent::Aborted { instance } => write!(f, "aborted;{}", instance),
+            ResultEvent::Switched { instance, protocol } => {
+                write!(f, "switched;{};{}", instance, protocol)
+            }
+            ResultEvent::Rejected {
+                protocol,
+                message_type,
+            } => write!(f, "rejected;{};{}", protocol, message_type),
+            ResultEvent::Invalid {
+                protocol,
+                message_type,
+            } => write!(f, "invalid;{};{}", protocol, message_type),
+            ResultEvent::Overloaded {
+                protocol,
+                message_type,
+            } => write!(f, "overloaded;{};{}", protocol, message_type),
+            ResultEvent::Traffic {
+                protocol,
+                message_type,
+                counter,
+            } => write!(
+                f,
+                "traffic;{};{};{};{};{}",
+                protocol, message_type, counter.sent, counter.bytes, counter.dropped
+            ),
+            ResultEvent::Watchdog {
+                request,
+                last_phase,
+                first_seen,
+                last_seen,
+                done,
+            } => write!(
+                f,
+                "watchdog;{};{};{};{};{}",
+                request,
+                last_phase,
+                first_seen,
+                last_seen,
+                done.map_or(-1, |done| done as i64)
+            ),
+            ResultEvent::Requests(summary) => write!(
+                f,
+                "requests;{};{};{:.1};{};{:.2}",
+                summary.issued,
+                summary.done,
+                summary.mean_latency,
+                summary.max_latency,
+                summary.throughput
+            ),
+            ResultEvent::Percentile {
+                percentile,
+                latency,
+            } => write!(f, "percentile;{};{}", percentile, latency),
+            ResultEvent::ViewChanges {
+                view_changes,
+                unstable_ms,
+                aborted_requests,
+                reproposed_requests,
+            } => write!(
+                f,
+                "view_changes;{};{};{};{}",
+                view_changes, unstable_ms, aborted_requests, reproposed_requests
+            ),
+            ResultEvent::View(period) => write!(
+                f,
+                "view;{};{};{};{:.2}",
+                period.view, period.duration, period.done, period.throughput
+            ),
+            _ => unreachable!("the events of a request are encoded above"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_events_survive_encoding() {
+        let events = vec![
+            ResultEvent::Request(1),
+            ResultEvent::PrePrepared(2),
+            ResultEvent::Prepared(3),
+            ResultEvent::CommittedLocal(4),
+            ResultEvent::Completed(5),
+            ResultEvent::TimedOut(6),
+            ResultEvent::StateTransfer(7),
+            ResultEvent::Ordered(8),
+            ResultEvent::SpeculativeCommit(9),
+            ResultEvent::CommitCertificate(10),
+            ResultEvent::RolledBack(11),
+            ResultEvent::Reply(12),
+            ResultEvent::Executed(13),
+            ResultEvent::Crashed,
+            ResultEvent::Panicked {
+                dump: "log/1700000000_000000000000beef/panic_004_node_2.txt".to_string(),
+            },
+            ResultEvent::Partition(vec![1, 3]),
+            ResultEvent::Heal,
+            ResultEvent::Finished,
+            ResultEvent::Aborted { instance: 2 },
+            ResultEvent::Switched {
+                instance: 3,
+                protocol: "PBFT".to_string(),
+            },
+            ResultEvent::Traffic {
+                protocol: "PBFT".to_string(),
+                message_type: "Prepare".to_string(),
+                counter: TrafficCounter {
+                    sent: 4,
+                    bytes: 80,
+                    dropped: 1,
+                },
+            },
+            ResultEvent::Rejected {
+                protocol: "Zyzzyva".to_string(),
+                message_type: "OrderRequest".to_string(),
+            },
+            ResultEvent::Invalid {
+                protocol: "PBFT".to_string(),
+                message_type: "ClientResponse".to_string(),
+            },
+            ResultEvent::Overloaded {
+                protocol: "PBFT".to_string(),
+                message_type: "Commit".to_string(),
+            },
+            ResultEvent::Watchdog {
+                request: 2,
+                last_phase: "prepared".to_string(),
+                first_seen: 10,
+                last_seen: 40,
+                done: None,
+            },
+            ResultEvent::Watchdog {
+                request: 3,
+                last_phase: "committed_local".to_string(),
+                first_seen: 20,
+                last_seen: 150,
+                done: Some(150),
+            },
+            ResultEvent::Requests(RequestSummary {
+                issued: 3,
+                done: 2,
+                mean_latency: 60.5,
+                max_latency: 80,
+                throughput: 0.25,
+            }),
+            ResultEvent::Percentile {
+                percentile: 99.9,
+                latency: 120,
+            },
+            ResultEvent::ViewChanges {
+                view_changes: 2,
+                unstable_ms: 1500,
+                aborted_requests: 4,
+                reproposed_requests: 3,
+            },
+            ResultEvent::View(ViewPeriod {
+                view: 1,
+                duration: 2000,
+                done: 50,
+                throughput: 25.0,
+            }),
+        ];
+
+        for event in events {
+            assert_eq!(ResultEvent::parse(&event.to_string()), Some(event));
+        }
+    }
+
+    #[test]
+    fn check_lines_of_the_result_log_are_decoded() {
+        assert_eq!(
+            ResultEvent::parse("42;prepared"),
+            Some(ResultEvent::Prepared(42))
+        );
+        assert_eq!(
+            ResultEvent::parse("42;committed_local").and_then(|e| e.done_request()),
+            None
+        );
+        assert_eq!(
+            ResultEvent::parse("42;executed").and_then(|e| e.done_request()),
+            Some(42)
+        );
+        assert_eq!(ResultEvent::Request(7).to_string(), "7;request");
+        assert_eq!(ResultEvent::parse("42;unknown"), None);
+        assert_eq!(ResultEvent::parse("x;prepared"), None);
+        assert_eq!(ResultEvent::parse("partition;1,x"), None);
+    }
+}
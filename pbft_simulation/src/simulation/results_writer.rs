@@ -0,0 +1,142 @@
+/***************************************************************************************************
+Writes the result log of a run to its file. The results don't go through the logging framework, so
+they don't depend on the configuration of the loggers and their level filters, which are only meant
+for diagnostics. The lines are buffered and appended to the file, the buffer is flushed when the run
+stops and when the writer is dropped, so a run that panics keeps the lines logged until then.
+***************************************************************************************************/
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use log::warn;
+use mc_utils::filehelper::FileHelper;
+
+use crate::simulation::config::result_log_prefix;
+use crate::simulation::output::RunId;
+use crate::simulation::settings::Settings;
+
+/// The result log file of a run
+#[derive(Debug)]
+pub struct ResultsWriter {
+    path: String,
+    writer: BufWriter<File>,
+    // the first failed write is reported, the later ones are dropped silently
+    failed: bool,
+}
+
+impl ResultsWriter {
+    /// Creates the result log at `path` and the missing directories, replacing an existing file
+    pub fn create(path: &str) -> io::Result<Self> {
+        FileHelper::create_parent_dirs(path)?;
+        Ok(ResultsWriter {
+            path: path.to_string(),
+            writer: BufWriter::new(File::create(path)?),
+            failed: false,
+        })
+    }
+
+    /// Creates the result log of the run `run` in its directory, if the `settings` ask for one
+    pub fn for_run(run: &RunId, settings: &Settings) -> Option<Self> {
+        if !settings.result_log {
+            return None;
+        }
+        let path = format!(
+            "{}.log",
+            result_log_prefix(
+                run,
+                settings.node.nodes,
+                settings.simulation.requests,
+                settings.network.omission_probability
+            )
+        );
+        match ResultsWriter::create(&path) {
+            Ok(writer) => Some(writer),
+            Err(e) => {
+                warn!("Failed to create the result log {}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// Path of the result log
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Appends the `line` to the result log
+    pub fn write_line(&mut self, line: &str) {
+        if let Err(e) = writeln!(self.writer, "{}", line) {
+            self.report(e);
+        }
+    }
+
+    /// Writes the buffered lines to the file
+    pub fn flush(&mut self) {
+        if let Err(e) = self.writer.flush() {
+            self.report(e);
+        }
+    }
+
+    /// Writes the buffered lines and closes the file
+    pub fn finish(mut self) {
+        self.flush();
+    }
+
+    fn report(&mut self, e: io::Error) {
+        if !self.failed {
+            warn!("Failed to write the result log {}: {}", self.path, e);
+            self.failed = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn check_lines_are_written_once_flushed() {
+        let path = std::env::temp_dir().join("bft_simulation_results.log");
+        let path = path.to_str().unwrap();
+
+        let mut writer = ResultsWriter::create(path).unwrap();
+        writer.write_line("10;1;1;request");
+        writer.write_line("30;-1;heal");
+        writer.flush();
+        assert_eq!(
+            fs::read_to_string(path).unwrap(),
+            "10;1;1;request\n30;-1;heal\n"
+        );
+        writer.write_line("40;-1;finished");
+        writer.finish();
+        assert!(fs::read_to_string(path)
+            .unwrap()
+            .ends_with("40;-1;finished\n"));
+
+        // a new run replaces the log
+        ResultsWriter::create(path).unwrap().finish();
+        assert_eq!(fs::read_to_string(path).unwrap(), "");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn check_lines_are_kept_if_the_run_panics() {
+        let path = std::env::temp_dir()
+            .join("bft_simulation_results_panic")
+            .join("run.log");
+        let path = path.to_str().unwrap().to_string();
+
+        let run = std::thread::spawn({
+            let path = path.clone();
+            move || {
+                let mut writer = ResultsWriter::create(&path).unwrap();
+                writer.write_line("10;1;1;request");
+                panic!("the run panics before it stops");
+            }
+        });
+        assert!(run.join().is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "10;1;1;request\n");
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+/***************************************************************************************************
+Safety oracle checked at the end of a run. All correct replicas must execute the same requests in
+the same order: of any two replicas, the execution of one is a prefix of the execution of the other,
+and replicas that executed the same number of requests have the same application state.
+***************************************************************************************************/
+
+use std::fmt;
+
+use crate::node::request::RequestId;
+
+/// The requests a replica executed, in order, and the digest of its application state
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionRecord {
+    pub node_id: u32,
+    pub executed: Vec<RequestId>,
+    pub digest: u64,
+}
+
+/// Two replicas that violated agreement or total order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub node_a: u32,
+    pub node_b: u32,
+    /// Sequence number (1-based) of the first request the replicas disagree on, `None` if they
+    /// executed the same requests but their application states differ
+    pub sequence: Option<usize>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.sequence {
+            Some(sequence) => write!(
+                f,
+                "replicas {} and {} executed different requests at sequence number {}",
+                self.node_a, self.node_b, sequence
+            ),
+            None => write!(
+                f,
+                "replicas {} and {} executed the same requests but their states differ",
+                self.node_a, self.node_b
+            ),
+        }
+    }
+}
+
+/// Compares the executions of all correct replicas, returns the first divergence found
+pub fn check_agreement(records: &[ExecutionRecord]) -> Result<(), Divergence> {
+    // every execution has to be a prefix of the longest one
+    let longest = match records.iter().max_by_key(|r| r.executed.len()) {
+        Some(longest) => longest,
+        None => return Ok(()),
+    };
+
+    for record in records {
+        if let Some(position) = record
+            .executed
+            .iter()
+            .zip(&longest.executed)
+            .position(|(a, b)| a != b)
+        {
+            return Err(Divergence {
+                node_a: longest.node_id,
+                node_b: record.node_id,
+                sequence: Some(position + 1),
+            });
+        }
+    }
+
+    for (i, a) in records.iter().enumerate() {
+        for b in &records[i + 1..] {
+            if a.executed.len() == b.executed.len() && a.digest != b.digest {
+                return Err(Divergence {
+                    node_a: a.node_id,
+                    node_b: b.node_id,
+                    sequence: None,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(node_id: u32, sequences: &[u32], digest: u64) -> ExecutionRecord {
+        ExecutionRecord {
+            node_id,
+            executed: sequences.iter().map(|s| RequestId::new(0, *s)).collect(),
+            digest,
+        }
+    }
+
+    #[test]
+    fn check_prefixes_agree() {
+        let records = vec![
+            record(1, &[1, 2, 3], 7),
+            record(2, &[1, 2], 5),
+            record(3, &[1, 2, 3], 7),
+        ];
+        assert_eq!(check_agreement(&records), Ok(()));
+        assert_eq!(check_agreement(&[]), Ok(()));
+    }
+
+    #[test]
+    fn check_divergence_is_detected() {
+        let records = vec![record(1, &[1, 2, 3], 7), record(2, &[1, 3], 5)];
+        assert_eq!(
+            check_agreement(&records),
+            Err(Divergence {
+                node_a: 1,
+                node_b: 2,
+                sequence: Some(2),
+            })
+        );
+
+        let records = vec![record(1, &[1, 2], 7), record(2, &[1, 2], 5)];
+        assert_eq!(check_agreement(&records).unwrap_err().sequence, None);
+    }
+}
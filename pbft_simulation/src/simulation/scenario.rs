@@ -0,0 +1,169 @@
+/***************************************************************************************************
+Scenarios describe an experiment as a list of timed admin actions and network events, e.g. inject
+requests at 0ms, crash node 1 at 5000ms, slow down the links from node 2 and stop at 20000ms. They
+are read from JSON or YAML files:
+
+{ "actions": [
+    { "at": 0, "action": { "ClientRequests": { "number": 1000, "interval": 10 } } },
+    { "at": 5000, "action": { "CrashNode": 1 } },
+    { "at": 6000, "action": { "SetLinkDelay": { "from": 2, "to": null, "delay": 500 } } },
+    { "at": 8000, "action": "Heal" },
+    { "at": 20000, "action": "Stop" } ] }
+***************************************************************************************************/
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::network::event::NetworkEvent;
+use crate::simulation::event::{AdminType, Event};
+use crate::simulation::time::Time;
+
+/// An admin action or a network event, told apart by their names
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Action {
+    Admin(AdminType),
+    Network(NetworkEvent),
+}
+
+impl From<AdminType> for Action {
+    fn from(admin_type: AdminType) -> Self {
+        Action::Admin(admin_type)
+    }
+}
+
+impl From<NetworkEvent> for Action {
+    fn from(network_event: NetworkEvent) -> Self {
+        Action::Network(network_event)
+    }
+}
+
+/// A single action of a scenario, executed at simulation time `at` (ms)
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioAction {
+    pub at: u64,
+    pub action: Action,
+}
+
+/// A scripted experiment
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    pub actions: Vec<ScenarioAction>,
+}
+
+impl Scenario {
+    /// Reads a scenario, files ending with '.yaml' or '.yml' are parsed as YAML, all others as JSON
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)?;
+        let is_yaml = matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("yaml") | Some("yml")
+        );
+
+        if is_yaml {
+            serde_yaml::from_str(&content)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        } else {
+            serde_json::from_str(&content)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        }
+    }
+
+    /// Adds an action to the scenario
+    pub fn at(mut self, at: u64, action: impl Into<Action>) -> Self {
+        self.actions.push(ScenarioAction {
+            at,
+            action: action.into(),
+        });
+        self
+    }
+
+    /// Converts the actions to admin and network events, scheduled at their time
+    pub fn into_events(self) -> Vec<Event> {
+        self.actions
+            .into_iter()
+            .map(|a| match a.action {
+                Action::Admin(admin_type) => Event::new_admin_at(admin_type, Time::new(a.at)),
+                Action::Network(network_event) => {
+                    Event::new_network(network_event, Time::new(a.at))
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::config::RequestBatchConfig;
+    use crate::simulation::event::EventType;
+    use crate::simulation::run_scenario;
+    use crate::simulation::settings::Settings;
+
+    #[test]
+    fn check_scenario_events_are_timed() {
+        let events = Scenario::default()
+            .at(5000, AdminType::CrashNode(1))
+            .at(6000, NetworkEvent::PartitionStart(vec![2]))
+            .at(20000, AdminType::Stop)
+            .into_events();
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].time, Time::new(5000));
+        assert_eq!(
+            events[0].event_type,
+            EventType::Admin(AdminType::CrashNode(1))
+        );
+        assert_eq!(
+            events[1].event_type,
+            EventType::Network(NetworkEvent::PartitionStart(vec![2]))
+        );
+        assert_eq!(events[2].time, Time::new(20000));
+    }
+
+    #[test]
+    fn check_scenario_json_round_trip() {
+        let json = r#"{ "actions": [
+            { "at": 0, "action": { "ClientRequests": { "number": 10, "interval": 5 } } },
+            { "at": 6000, "action": { "SetLossRate": { "from": 2, "to": 3, "probability": 0.5 } } },
+            { "at": 8000, "action": "Heal" } ] }"#;
+        let scenario: Scenario = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            scenario,
+            Scenario::default()
+                .at(0, AdminType::ClientRequests(RequestBatchConfig::new(10, 5)))
+                .at(
+                    6000,
+                    NetworkEvent::SetLossRate {
+                        from: Some(2),
+                        to: Some(3),
+                        probability: Some(0.5)
+                    }
+                )
+                .at(8000, AdminType::Heal)
+        );
+    }
+
+    #[test]
+    /// Check that a scenario run handles the pending work before its stop, which comes last
+    fn check_scenario_stops_after_pending_work() {
+        let mut settings = Settings::default();
+        settings.simulation.requests = 0;
+        let scenario = Scenario::default()
+            .at(
+                0,
+                AdminType::ClientRequests(RequestBatchConfig::new(5, 100)),
+            )
+            .at(250, AdminType::CrashNode(4))
+            .at(20000, AdminType::Stop);
+
+        let report = run_scenario(settings, scenario);
+        assert_eq!(report.end_time, Time::new(20000));
+        assert_eq!(report.done_requests(), (1..=5).collect());
+        assert!(report.agreement.is_ok());
+    }
+}
@@ -0,0 +1,459 @@
+/***************************************************************************************************
+The event queue of the simulation. All schedulers hand out the events in the order of `Event`, i.e.
+immediate admin events first, then by time and at the same time admin events before the others.
+Events that are equal in this order are handed out first in, first out, so every scheduler hands
+out the events of a run in the same sequence. They differ in how they get there, which matters for
+runs of many nodes with millions of events in flight, so the scheduler is chosen with
+`simulation.scheduler`.
+***************************************************************************************************/
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, VecDeque};
+use std::fmt::Debug;
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use crate::simulation::event::Event;
+use crate::simulation::time::Time;
+
+/// Number of buckets a calendar queue starts with and never shrinks below
+const MIN_CALENDAR_BUCKETS: usize = 16;
+/// Number of events at the head of a calendar queue whose spacing sets the width of its buckets
+const CALENDAR_SAMPLE: usize = 25;
+
+/// An event and the number of events pushed before it, which breaks the ties of the order of
+/// `Event` first in, first out
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Queued {
+    event: Event,
+    seq: u64,
+}
+
+/// The event queue of a simulation
+pub trait Scheduler: Debug + Send {
+    /// Adds `event` to the queue
+    fn push(&mut self, event: Event);
+
+    /// Removes and returns the event handled next, `None` if the queue is empty
+    fn pop_next(&mut self) -> Option<Event>;
+
+    /// The event handled next, without removing it
+    fn peek(&self) -> Option<&Event>;
+
+    /// Number of events in the queue
+    fn len(&self) -> usize;
+
+    /// The time of the event handled next
+    fn peek_time(&self) -> Option<Time> {
+        self.peek().map(|event| event.time)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The schedulers to choose from in the ini
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum SchedulerKind {
+    /// A binary heap, the events are reversed so the smallest is on top
+    Heap,
+    /// Buckets of the events at the same time in a B-tree, events of the same time are handled in
+    /// the order they were pushed
+    Buckets,
+    /// A calendar queue, buckets of a fixed width of time that are visited round-robin and resized
+    /// with the number of events
+    Calendar,
+}
+
+impl SchedulerKind {
+    /// Creates an empty queue of this kind
+    pub fn build(self) -> Box<dyn Scheduler> {
+        match self {
+            SchedulerKind::Heap => Box::new(HeapScheduler::default()),
+            SchedulerKind::Buckets => Box::new(BucketScheduler::default()),
+            SchedulerKind::Calendar => Box::new(CalendarScheduler::default()),
+        }
+    }
+}
+
+impl FromStr for SchedulerKind {
+    type Err = String;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "heap" => Ok(SchedulerKind::Heap),
+            "buckets" => Ok(SchedulerKind::Buckets),
+            "calendar" => Ok(SchedulerKind::Calendar),
+            _ => Err(format!(
+                "scheduler '{}' is not available, allowed are 'heap', 'buckets', 'calendar'",
+                kind
+            )),
+        }
+    }
+}
+
+/// The heap keeps the greatest on top, so the events are reversed to handle the smallest first
+#[derive(Debug, Default)]
+pub struct HeapScheduler {
+    heap: BinaryHeap<Reverse<Queued>>,
+    pushed: u64,
+}
+
+impl Scheduler for HeapScheduler {
+    fn push(&mut self, event: Event) {
+        let seq = self.pushed;
+        self.pushed += 1;
+        self.heap.push(Reverse(Queued { event, seq }));
+    }
+
+    fn pop_next(&mut self) -> Option<Event> {
+        self.heap.pop().map(|Reverse(queued)| queued.event)
+    }
+
+    fn peek(&self) -> Option<&Event> {
+        self.heap.peek().map(|Reverse(queued)| &queued.event)
+    }
+
+    fn len(&self) -> usize {
+        self.heap.len()
+    }
+}
+
+/// Position of an event in the order of `Event`: immediate first, then the time, admin first
+type BucketKey = (bool, Time, bool);
+
+fn bucket_key(event: &Event) -> BucketKey {
+    (!event.immediate, event.time, !event.is_admin())
+}
+
+/// The events with the same position in the order share a bucket and are handled first in, first
+/// out
+#[derive(Debug, Default)]
+pub struct BucketScheduler {
+    buckets: BTreeMap<BucketKey, VecDeque<Event>>,
+    len: usize,
+}
+
+impl Scheduler for BucketScheduler {
+    fn push(&mut self, event: Event) {
+        self.buckets
+            .entry(bucket_key(&event))
+            .or_default()
+            .push_back(event);
+        self.len += 1;
+    }
+
+    fn pop_next(&mut self) -> Option<Event> {
+        let mut first = self.buckets.first_entry()?;
+        let event = first.get_mut().pop_front();
+        if first.get().is_empty() {
+            first.remove();
+        }
+        self.len -= 1;
+        event
+    }
+
+    fn peek(&self) -> Option<&Event> {
+        self.buckets
+            .first_key_value()
+            .and_then(|(_, bucket)| bucket.front())
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Calendar queue after R. Brown (1988): the time is split into buckets of `width` µs, which are
+/// mapped onto the days of a year of `buckets.len()` days. Dequeuing walks through the days and
+/// takes the events of the current year only, so it is O(1) on average if the width fits the
+/// spacing of the events. The calendar doubles or halves its days with the number of events and
+/// fits the width to the events at its head. Immediate events bypass the calendar.
+#[derive(Debug)]
+pub struct CalendarScheduler {
+    immediate: BinaryHeap<Reverse<Queued>>,
+    /// The events of a day, sorted so the last one is handled first
+    buckets: Vec<Vec<Queued>>,
+    /// Width of a day in µs
+    width: u64,
+    /// The day the search for the next event starts at
+    current: usize,
+    /// End of the current day in the current year, in µs
+    day_end: u64,
+    /// Number of events in the calendar, without the immediate ones
+    len: usize,
+    /// Number of events pushed so far
+    pushed: u64,
+}
+
+impl Default for CalendarScheduler {
+    fn default() -> Self {
+        CalendarScheduler {
+            immediate: BinaryHeap::new(),
+            buckets: (0..MIN_CALENDAR_BUCKETS).map(|_| Vec::new()).collect(),
+            width: 1_000,
+            current: 0,
+            day_end: 1_000,
+            len: 0,
+            pushed: 0,
+        }
+    }
+}
+
+impl CalendarScheduler {
+    /// The day of the year an event at `micros` falls on
+    fn day_of(&self, micros: u64) -> usize {
+        ((micros / self.width) % self.buckets.len() as u64) as usize
+    }
+
+    /// Starts the search for the next event at the day of `micros`
+    fn move_to(&mut self, micros: u64) {
+        self.current = self.day_of(micros);
+        self.day_end = (micros / self.width + 1) * self.width;
+    }
+
+    /// Puts `queued` into its day, in front of the events of the day pushed before it
+    fn insert(&mut self, queued: Queued) {
+        let day = self.day_of(queued.event.time.micros());
+        let bucket = &mut self.buckets[day];
+        let position = bucket.partition_point(|e| *e > queued);
+        bucket.insert(position, queued);
+    }
+
+    /// The day holding the next event and the end of that day, searching the current year first
+    /// and all days for the earliest event if the current year is empty
+    fn next_day(&self) -> Option<(usize, u64)> {
+        if self.len == 0 {
+            return None;
+        }
+        let days = self.buckets.len();
+        let (mut day, mut day_end) = (self.current, self.day_end);
+        for _ in 0..days {
+            if let Some(queued) = self.buckets[day].last() {
+                if queued.event.time.micros() < day_end {
+                    return Some((day, day_end));
+                }
+            }
+            day = (day + 1) % days;
+            day_end += self.width;
+        }
+        // the next event is more than a year ahead
+        let earliest = self
+            .buckets
+            .iter()
+            .filter_map(|bucket| bucket.last())
+            .min()?
+            .event
+            .time
+            .micros();
+        Some((
+            self.day_of(earliest),
+            (earliest / self.width + 1) * self.width,
+        ))
+    }
+
+    /// Redistributes the events onto `days` days, with a width fitted to the events at the head
+    fn resize(&mut self, days: usize) {
+        let mut events: Vec<Queued> = self.buckets.drain(..).flatten().collect();
+        events.sort();
+        let head = &events[..events.len().min(CALENDAR_SAMPLE)];
+        if head.len() > 1 {
+            let span = head[head.len() - 1].event.time.micros() - head[0].event.time.micros();
+            self.width = (3 * span / (head.len() as u64 - 1)).max(1);
+        }
+        self.buckets = (0..days).map(|_| Vec::new()).collect();
+        match events.first() {
+            Some(first) => self.move_to(first.event.time.micros()),
+            None => self.move_to(0),
+        }
+        for event in events {
+            self.insert(event);
+        }
+    }
+}
+
+impl Scheduler for CalendarScheduler {
+    fn push(&mut self, event: Event) {
+        let queued = Queued {
+            event,
+            seq: self.pushed,
+        };
+        self.pushed += 1;
+        if queued.event.immediate {
+            self.immediate.push(Reverse(queued));
+            return;
+        }
+        // an event before the current day moves the search back
+        let micros = queued.event.time.micros();
+        if self.len == 0 || micros + self.width < self.day_end {
+            self.move_to(micros);
+        }
+        self.insert(queued);
+        self.len += 1;
+        if self.len > 2 * self.buckets.len() {
+            self.resize(2 * self.buckets.len());
+        }
+    }
+
+    fn pop_next(&mut self) -> Option<Event> {
+        if let Some(Reverse(queued)) = self.immediate.pop() {
+            return Some(queued.event);
+        }
+        let (day, day_end) = self.next_day()?;
+        self.current = day;
+        self.day_end = day_end;
+        let queued = self.buckets[day].pop();
+        self.len -= 1;
+        let days = self.buckets.len();
+        if days > MIN_CALENDAR_BUCKETS && self.len < days / 2 {
+            self.resize(days / 2);
+        }
+        queued.map(|queued| queued.event)
+    }
+
+    fn peek(&self) -> Option<&Event> {
+        if let Some(Reverse(queued)) = self.immediate.peek() {
+            return Some(&queued.event);
+        }
+        let (day, _) = self.next_day()?;
+        self.buckets[day].last().map(|queued| &queued.event)
+    }
+
+    fn len(&self) -> usize {
+        self.immediate.len() + self.len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::simulation::config::RequestBatchConfig;
+    use crate::simulation::event::{AdminType, EventType, Message};
+
+    const KINDS: [SchedulerKind; 3] = [
+        SchedulerKind::Heap,
+        SchedulerKind::Buckets,
+        SchedulerKind::Calendar,
+    ];
+
+    /// Pops all events and returns their positions in the order of `Event`
+    fn drain(queue: &mut dyn Scheduler) -> Vec<(bool, bool, u64)> {
+        let mut popped = Vec::new();
+        while let Some(event) = queue.pop_next() {
+            popped.push((event.immediate, event.is_admin(), event.time.micros()));
+        }
+        popped
+    }
+
+    #[test]
+    fn check_schedulers_follow_the_event_order() {
+        for kind in KINDS.iter() {
+            let mut queue = kind.build();
+            queue.push(Event::new_broadcast(1, 2, Message::Dummy, Time::new(100)));
+            queue.push(Event::new_admin_stop_at(Time::new(100)));
+            queue.push(Event::new_admin_requests_at(
+                Time::new(50),
+                RequestBatchConfig::new(1, 10),
+            ));
+            queue.push(Event::new_broadcast(1, 2, Message::Dummy, Time::new(10)));
+            queue.push(Event::new_admin(AdminType::CrashNode(1)));
+
+            assert_eq!(queue.len(), 5);
+            assert_eq!(queue.peek_time(), Some(Time::new(0)));
+            assert_eq!(
+                drain(&mut *queue),
+                vec![
+                    (true, true, 0),
+                    (false, false, 10_000),
+                    (false, true, 50_000),
+                    (false, true, 100_000),
+                    (false, false, 100_000),
+                ],
+                "{:?}",
+                kind
+            );
+            assert!(queue.is_empty());
+        }
+    }
+
+    #[test]
+    fn check_schedulers_agree_on_random_times() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let times: Vec<u64> = (0..5_000).map(|_| rng.gen_range(0, 2_000_000)).collect();
+
+        for kind in KINDS.iter() {
+            let mut queue = kind.build();
+            // interleave pushes and pops like the simulation, the pushed events are never before
+            // the last popped one
+            let mut popped = Vec::new();
+            let mut now = 0;
+            for (i, time) in times.iter().enumerate() {
+                let time = now.max(*time);
+                queue.push(Event::new_broadcast(
+                    1,
+                    2,
+                    Message::Dummy,
+                    Time::from_micros(time),
+                ));
+                if i % 3 == 0 {
+                    let event = queue.pop_next().unwrap();
+                    now = event.time.micros();
+                    popped.push(now);
+                }
+            }
+            popped.extend(drain(&mut *queue).into_iter().map(|(_, _, time)| time));
+
+            assert_eq!(popped.len(), times.len(), "{:?}", kind);
+            assert!(
+                popped.windows(2).all(|w| w[0] <= w[1]),
+                "{:?} popped an event before its predecessor",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn check_schedulers_hand_out_ties_first_in_first_out() {
+        for kind in KINDS.iter() {
+            let mut queue = kind.build();
+            for to in 1..=40 {
+                queue.push(Event::new_broadcast(
+                    1,
+                    to,
+                    Message::Dummy,
+                    Time::new(to as u64 % 3),
+                ));
+                queue.push(Event::new_admin(AdminType::CrashNode(to)));
+            }
+
+            let mut popped = Vec::new();
+            while let Some(event) = queue.pop_next() {
+                popped.push(match event.event_type {
+                    EventType::Broadcast(b) => (event.time.micros(), b.id_to),
+                    EventType::Admin(AdminType::CrashNode(id)) => (0, 100 + id),
+                    other => panic!("Unexpected event {:?}", other),
+                });
+            }
+            let mut expected: Vec<(u64, u32)> = (1..=40).map(|id| (0, 100 + id)).collect();
+            for time in 0..3 {
+                expected.extend(
+                    (1..=40)
+                        .filter(|to| to % 3 == time)
+                        .map(|to| (time as u64 * 1000, to)),
+                );
+            }
+            assert_eq!(popped, expected, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn check_scheduler_from_str() {
+        assert_eq!("calendar".parse(), Ok(SchedulerKind::Calendar));
+        assert!("splay".parse::<SchedulerKind>().is_err());
+    }
+}
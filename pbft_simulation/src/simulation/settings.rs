@@ -0,0 +1,1002 @@
+/***************************************************************************************************
+All parameters of a simulation run. The binary reads them from its layered config (see
+`load_config`), while tests and other crates build them directly, so a run does not depend on any global state. The
+structure mirrors the sections of the ini.
+***************************************************************************************************/
+
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use log::info;
+use mc_utils::ini::{env2var, IniValues};
+use serde::Serialize;
+
+use crate::network::compression::Compression;
+use crate::network::conditions::{LinkCondition, ScheduledCondition};
+use crate::network::jitter::Jitter;
+use crate::node::crypto::{measure_costs, AuthenticationMode};
+use crate::node::pbft::messages::{Dissemination, CLIENT_REQUEST_SIZE};
+use crate::node::pbft::state::LeaderRotation;
+use crate::node::NodeType;
+use crate::simulation::config::ClientTarget;
+use crate::simulation::diagnostics::TimeViolationPolicy;
+use crate::simulation::inbox::OverloadPolicy;
+use crate::simulation::postmortem::PanicPolicy;
+use crate::simulation::scheduler::SchedulerKind;
+use crate::simulation::storage::{LatencyDistribution, StorageMode};
+
+/// Parameters of a simulation run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Settings {
+    pub node: NodeSettings,
+    pub simulation: RunSettings,
+    pub network: NetworkSettings,
+    pub quorum: QuorumSettings,
+    pub pbft: PBFTSettings,
+    pub tendermint: TendermintSettings,
+    pub zyzzyva: ZyzzyvaSettings,
+    pub adaptive: AdaptiveSettings,
+    pub dag: DAGSettings,
+    pub crypto: CryptoSettings,
+    /// The [compression] section
+    pub compression: Compression,
+    pub storage: StorageSettings,
+    pub trace: TraceSettings,
+    pub stats: StatsSettings,
+    pub byzantine: ByzantineSettings,
+    /// Free-form parameters of the protocols, by the name of the node type. Read from the key
+    /// 'params' of the sections of the protocols, other node types may be added directly.
+    pub params: BTreeMap<String, HashMap<String, String>>,
+    /// Database the results are written to, only used if built with the feature 'sqlite'
+    pub sqlite_file: Option<String>,
+    /// Whether the result log is written to a file, the configuration of the run is written next
+    /// to it, see `ConfigSnapshot`
+    pub result_log: bool,
+}
+
+/// The sections of the protocols, each may hold free-form 'params'
+pub const PROTOCOL_SECTIONS: [&str; 7] = [
+    "pbft",
+    "zyzzyva",
+    "sbft",
+    "tendermint",
+    "adaptive",
+    "dag",
+    "steward",
+];
+
+/// Runs of every primitive measured by `crypto.calibrate`
+const CALIBRATION_ITERATIONS: u32 = 200;
+
+/// The [node] section
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeSettings {
+    pub node_type: NodeType,
+    /// Number of replicas
+    pub nodes: u32,
+    /// Number of clients taking part as nodes of their own, after the replicas. Only the clients
+    /// of Zyzzyva do, see `client_nodes`.
+    pub clients: u32,
+    /// 'counter' or 'kv'
+    pub application: String,
+    /// Timer (ms on the local clock) of the zyzzyva client for each request
+    pub client_timeout: u64,
+    /// Time (ms) a node needs to process a message before its outputs leave the node
+    pub processing_delay: u64,
+    /// Processing delays of single nodes that differ from `processing_delay`
+    pub processing_delays: BTreeMap<u32, u64>,
+    /// Messages a node handles per simulated second, `None` for no limit
+    pub max_rate: Option<u32>,
+    /// What happens to the messages a node receives while it is busy
+    pub overload_policy: OverloadPolicy,
+    /// Messages waiting at a busy node before it drops them with `OverloadPolicy::Drop`
+    pub inbox_size: usize,
+    /// Maximal offset (ms) and drift of the local clocks
+    pub clock_offset_max: i64,
+    pub clock_drift_max: f64,
+    /// What happens to the run once a node panicked
+    pub on_panic: PanicPolicy,
+    /// Receptions of every node kept for the dump of a node that panicked, 0 keeps none
+    pub reception_history: usize,
+    /// Further clusters of other node types in the same network, see `composition`
+    pub composition: Vec<ClusterSettings>,
+}
+
+/// A cluster of replicas running next to the configured one, with clients of its own
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClusterSettings {
+    pub node_type: NodeType,
+    /// Number of replicas
+    pub nodes: u32,
+    /// Number of client requests of the cluster, `None` for `simulation.requests`
+    pub requests: Option<u32>,
+}
+
+/// The [simulation] section
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunSettings {
+    /// Number of client requests
+    pub requests: u32,
+    /// Time (ms) between two client requests
+    pub request_interval: u32,
+    /// Scenario file (json or yaml) that replaces the requests, see `Simulation::run_scenario`
+    pub scenario: Option<String>,
+    /// Requests not done within this many ms are reported, 0 disables the watchdog
+    pub liveness_bound: u64,
+    /// Keep waiting for commands once the queue is empty instead of stopping
+    pub wait_for_commands: bool,
+    /// Simulated time passing per wall-clock time, `None` runs as fast as possible
+    pub speedup: Option<f64>,
+    /// What happens to an event that is before the current time
+    pub time_violation: TimeViolationPolicy,
+    /// Number of handled events that are dumped with an event before the current time, 0 keeps none
+    pub event_history: usize,
+    /// Seed of the random numbers of the run, `None` draws one. The seed is written to the
+    /// snapshot of the configuration
+    pub seed: Option<u64>,
+    /// Run the configuration twice with the same seed and fail at the first line of the result
+    /// logs that differs, see `determinism`
+    pub self_check: bool,
+    /// The event queue, see `Scheduler`
+    pub scheduler: SchedulerKind,
+    /// Number of threads handling the receptions of distinct nodes at the same time, 1 handles
+    /// all events on the thread of the simulation
+    pub threads: usize,
+}
+
+/// The [network] section
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NetworkSettings {
+    pub omission_probability: f64,
+    /// Omission probabilities of single links that differ from `omission_probability`
+    pub loss: Vec<LinkLoss>,
+    /// Gilbert-Elliott model: probability per message that a link turns bad and good again, and
+    /// the omission probability of a bad link. 0 for `burst_enter` disables the model
+    pub burst_enter: f64,
+    pub burst_exit: f64,
+    pub burst_loss: f64,
+    /// Messages delayed longer than this (ms) are dropped, `None` delivers them however late
+    pub ttl: Option<u64>,
+    /// Range of the delay (ms) of a hop, fractions of a millisecond are kept to the microsecond
+    pub delay_min: f64,
+    pub delay_max: f64,
+    /// Drawn for every hop on top of the delay between `delay_min` and `delay_max`
+    pub jitter: Jitter,
+    /// Periods in which single links take their own delay and loss
+    pub schedule: Vec<ScheduledCondition>,
+    /// 'mesh', 'star', 'ring', 'clusters' or 'matrix'
+    pub topology: String,
+    pub hub: u32,
+    pub hub_delay: u64,
+    pub clusters: u32,
+    pub cluster_delay: u64,
+    pub gossip_fanout: u32,
+    pub gossip_rounds: u32,
+    pub gossip_hop_delay: u64,
+    /// Latency (ms) of the link from node i + 1 to node j + 1 in row i, column j, for 'matrix'
+    pub latency: Vec<Vec<u64>>,
+    /// Bound Δ (ms) on the delay an adversary chooses for a message, see `Simulation::set_adversary`
+    pub adversary_delta: u64,
+    /// Bandwidth (Mbit/s) of every link, a message takes its size over it on top of the delay of
+    /// each hop. `None` transfers messages of any size at once
+    pub bandwidth: Option<f64>,
+}
+
+/// The omission probability of the links from `from` to `to`, `None` stands for any node
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LinkLoss {
+    pub from: Option<u32>,
+    pub to: Option<u32>,
+    pub probability: f64,
+}
+
+impl LinkLoss {
+    /// Returns `true` if the link from `from` to `to` is one of the links
+    pub fn matches(&self, from: u32, to: u32) -> bool {
+        self.from.unwrap_or(from) == from && self.to.unwrap_or(to) == to
+    }
+
+    /// Links named by both ends take precedence over links with a wildcard
+    pub fn is_exact(&self) -> bool {
+        self.from.is_some() && self.to.is_some()
+    }
+}
+
+/// The [quorum] section, `None` stands for 'auto'
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuorumSettings {
+    pub f: Option<u32>,
+    pub size: Option<u32>,
+    /// 'none', 'wheat' or '<id>:<weight> ...'
+    pub weights: String,
+    pub quorum_weight: Option<u32>,
+}
+
+/// The [pbft] section
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PBFTSettings {
+    pub authentication: AuthenticationMode,
+    pub watermark_window: u64,
+    pub gossip_pre_prepare: bool,
+    pub client_target: ClientTarget,
+    /// Number of parallel ordering instances with round-robin leaders
+    pub instances: u32,
+    /// Whether the primary rotates with every request, which runs an instance per replica
+    pub leader_rotation: LeaderRotation,
+    /// Size (bytes) of the body of a client request
+    pub request_size: u32,
+    /// How the bodies of the requests reach the backups
+    pub dissemination: Dissemination,
+}
+
+/// The [tendermint] section, timeouts in ms on the local clock
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TendermintSettings {
+    pub timeout_propose: u64,
+    pub timeout_prevote: u64,
+    pub timeout_precommit: u64,
+    /// Added to the timeouts with every round
+    pub timeout_delta: u64,
+    /// Interval of resending the proposal and votes of a round that is not done
+    pub retransmit_interval: u64,
+}
+
+/// The [zyzzyva] section
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ZyzzyvaSettings {
+    /// Replicas execute requests as soon as they are ordered, otherwise once they are committed
+    pub speculative_execution: bool,
+    /// Cost (µs) of rolling back a single speculative execution
+    pub rollback_cost: u64,
+    pub authentication: AuthenticationMode,
+}
+
+/// The [adaptive] section
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AdaptiveSettings {
+    /// The protocols the node switches between, the first one is used while nothing goes wrong
+    pub protocols: Vec<NodeType>,
+    /// Time (ms on the local clock) a replica waits for the execution of a client request before
+    /// it aborts the current protocol
+    pub switch_timeout: u64,
+    /// Number of requests a backup protocol executes before switching back, 0 to stay with it
+    pub backup_requests: u64,
+}
+
+/// The [dag] section
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DAGSettings {
+    /// Maximal number of client requests in the vertex of a replica
+    pub batch_size: usize,
+}
+
+impl PBFTSettings {
+    /// Number of ordering instances of `number_of_nodes` replicas, one per replica if the primary
+    /// rotates with every request
+    pub fn ordering_instances(&self, number_of_nodes: u32) -> u32 {
+        match self.leader_rotation {
+            LeaderRotation::Stable => self.instances,
+            LeaderRotation::PerRequest => number_of_nodes,
+        }
+    }
+}
+
+impl NodeSettings {
+    /// Number of clients simulated as nodes next to the replicas
+    pub fn client_nodes(&self) -> u32 {
+        match self.node_type.client_type() {
+            Some(_) => self.clients,
+            None => 0,
+        }
+    }
+
+    /// The processing delay of the node `id`
+    pub fn processing_delay_of(&self, id: u32) -> u64 {
+        self.processing_delays
+            .get(&id)
+            .copied()
+            .unwrap_or(self.processing_delay)
+    }
+}
+
+impl Settings {
+    /// The free-form parameters of the node type `name`
+    pub fn params_of(&self, name: &str) -> HashMap<String, String> {
+        self.params.get(name).cloned().unwrap_or_default()
+    }
+}
+
+impl AdaptiveSettings {
+    /// The protocol of `instance`, the instances cycle through the protocols
+    pub fn protocol(&self, instance: u64) -> NodeType {
+        self.protocols[(instance % self.protocols.len() as u64) as usize]
+    }
+}
+
+/// The [crypto] section, costs in microseconds and sizes in bytes
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CryptoSettings {
+    pub mac_cost: u64,
+    pub sign_cost: u64,
+    pub verify_cost: u64,
+    pub mac_size: u32,
+    pub signature_size: u32,
+    /// Threshold signatures: creating and verifying a share, combining a share into the
+    /// aggregate and verifying the aggregate
+    pub share_sign_cost: u64,
+    pub share_verify_cost: u64,
+    pub combine_cost: u64,
+    pub aggregate_verify_cost: u64,
+    pub share_size: u32,
+    pub aggregate_size: u32,
+    /// The costs of the MACs and signatures were measured on the host, see `calibrated`
+    pub calibrate: bool,
+}
+
+impl CryptoSettings {
+    /// With `calibrate`, the costs of the MACs and signatures are replaced by those of keyed
+    /// BLAKE3 and ed25519 measured on the host for messages the size of a client request. The
+    /// clock of the feature 'wasm' stands still, so its costs stay configured.
+    pub fn calibrated(mut self) -> Self {
+        if self.calibrate && cfg!(not(feature = "wasm")) {
+            let costs = measure_costs(CLIENT_REQUEST_SIZE as usize, CALIBRATION_ITERATIONS);
+            info!(
+                "Measured the crypto costs on this host: mac {}µs, sign {}µs, verify {}µs",
+                costs.mac_cost, costs.sign_cost, costs.verify_cost
+            );
+            costs.apply(&mut self);
+        }
+        self
+    }
+}
+
+/// The [storage] section, latencies in microseconds
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StorageSettings {
+    /// The protocols whose replicas persist their log entries before they send them
+    pub protocols: Vec<NodeType>,
+    pub mode: StorageMode,
+    pub distribution: LatencyDistribution,
+    pub latency_min: u64,
+    pub latency_max: u64,
+}
+
+impl StorageSettings {
+    /// Whether the replicas of `node_type` persist their log entries
+    pub fn persists(&self, node_type: NodeType) -> bool {
+        self.protocols.contains(&node_type)
+    }
+}
+
+/// The [trace] section
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TraceSettings {
+    pub causality: bool,
+    pub request: u64,
+    /// Record every message for animations, see `flow::MessageFlow`
+    pub message_flow: bool,
+}
+
+/// The [stats] section
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatsSettings {
+    /// Requests issued before this time (ms) are not part of the request statistics
+    pub warmup_ms: u64,
+    /// Requests issued from this time (ms) on are not part of the request statistics either
+    pub cooldown_ms: Option<u64>,
+    /// Whether the latencies of the requests are written as HdrHistogram percentile distribution
+    /// to 'latency_<nodes>.hgrm' of the run
+    pub hgrm: bool,
+}
+
+/// The [byzantine] section
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ByzantineSettings {
+    /// Ids of the Byzantine nodes
+    pub nodes: Vec<u32>,
+    /// Probability that a message of a Byzantine node carries an invalid authenticator
+    pub invalid_authentication: f64,
+}
+
+// returns the raw value of the key 'section.key'
+type Lookup<'a> = &'a dyn Fn(&str) -> String;
+
+// parses the value of a key
+fn value<T: FromStr>(get: Lookup, key: &str) -> T {
+    let value = get(key);
+    match value.parse() {
+        Ok(value) => value,
+        Err(_) => panic!("Can't parse {} = '{}' into desired variable", key, value),
+    }
+}
+
+// parses the protocols an adaptive node switches between
+fn protocols(get: Lookup, key: &str) -> Vec<NodeType> {
+    let protocols: Vec<NodeType> = get(key)
+        .split_whitespace()
+        .map(NodeType::from_config)
+        .collect();
+    if protocols.is_empty()
+        || protocols
+            .iter()
+            .any(|p| !matches!(p, NodeType::PBFT | NodeType::SBFT | NodeType::Tendermint))
+    {
+        panic!(
+            "{} must list protocols out of 'pbft', 'sbft', 'tendermint'",
+            key
+        );
+    }
+    protocols
+}
+
+// parses the jitter of the [network] section, its parameters depend on the process
+fn jitter(get: Lookup) -> Jitter {
+    match get("network.jitter").as_str() {
+        "none" => Jitter::None,
+        "gaussian" => Jitter::Gaussian {
+            std_dev: value(get, "network.jitter_std_dev"),
+        },
+        "pareto" => Jitter::Pareto {
+            scale: value(get, "network.jitter_scale"),
+            shape: value(get, "network.jitter_shape"),
+        },
+        jitter => panic!(
+            "network.jitter '{}' is not available, allowed are 'none', 'gaussian', 'pareto'",
+            jitter
+        ),
+    }
+}
+
+// parses a list of node types, 'none' for no node type
+fn node_types(get: Lookup, key: &str) -> Vec<NodeType> {
+    let list = get(key);
+    if list == "none" {
+        return Vec::new();
+    }
+    list.split_whitespace().map(NodeType::from_config).collect()
+}
+
+// parses per-node values of the form '<id>:<value> ...', 'none' for no values
+fn node_values(get: Lookup, key: &str) -> BTreeMap<u32, u64> {
+    let list = get(key);
+    if list == "none" {
+        return BTreeMap::new();
+    }
+    list.split_whitespace()
+        .map(|pair| {
+            let mut parts = pair.splitn(2, ':');
+            let id = parts.next().and_then(|id| id.parse().ok());
+            let value = parts.next().and_then(|value| value.parse().ok());
+            match (id, value) {
+                (Some(id), Some(value)) => (id, value),
+                _ => panic!(
+                    "Can't parse '{}' of {}, allowed are 'none' or '<id>:<value> ...'",
+                    pair, key
+                ),
+            }
+        })
+        .collect()
+}
+
+// parses the clusters of the form '<node_type>:<nodes>[:<requests>] ...', 'none' for no clusters
+fn clusters(get: Lookup, key: &str) -> Vec<ClusterSettings> {
+    let list = get(key);
+    if list == "none" {
+        return Vec::new();
+    }
+    list.split_whitespace()
+        .map(|entry| {
+            let parts: Vec<&str> = entry.split(':').collect();
+            let nodes = parts.get(1).and_then(|nodes| nodes.parse().ok());
+            let requests = match parts.get(2) {
+                Some(requests) => requests.parse().ok().map(Some),
+                None => Some(None),
+            };
+            match (nodes, requests) {
+                (Some(nodes), Some(requests)) if parts.len() <= 3 => ClusterSettings {
+                    node_type: NodeType::from_config(parts[0]),
+                    nodes,
+                    requests,
+                },
+                _ => panic!(
+                    "Can't parse '{}' of {}, allowed are 'none' or '<node_type>:<nodes>[:<requests>] ...'",
+                    entry, key
+                ),
+            }
+        })
+        .collect()
+}
+
+// parses a list of node ids, 'none' for no nodes
+fn ids(get: Lookup, key: &str) -> Vec<u32> {
+    let list = get(key);
+    if list == "none" {
+        return Vec::new();
+    }
+    list.split_whitespace()
+        .map(|id| {
+            id.parse()
+                .unwrap_or_else(|_| panic!("Can't parse '{}' of {} into a node id", id, key))
+        })
+        .collect()
+}
+
+// parses per-link omission probabilities of the form '<from>-<to>:<probability> ...', either end
+// may be '*' for any node, 'none' for no links
+fn link_loss(get: Lookup, key: &str) -> Vec<LinkLoss> {
+    let list = get(key);
+    if list == "none" {
+        return Vec::new();
+    }
+    let end = |id: &str| match id {
+        "*" => Some(None),
+        id => id.parse().ok().map(Some),
+    };
+    list.split_whitespace()
+        .map(|entry| {
+            let mut parts = entry.splitn(2, ':');
+            let mut link = parts.next().unwrap_or_default().splitn(2, '-');
+            let from = link.next().and_then(end);
+            let to = link.next().and_then(end);
+            let probability = parts
+                .next()
+                .and_then(|p| p.parse().ok())
+                .filter(|p| (0.0..=1.0).contains(p));
+            match (from, to, probability) {
+                (Some(from), Some(to), Some(probability)) => LinkLoss {
+                    from,
+                    to,
+                    probability,
+                },
+                _ => panic!(
+                    "Can't parse '{}' of {}, allowed are 'none' or '<from>-<to>:<probability> ...' with '*' for any node",
+                    entry, key
+                ),
+            }
+        })
+        .collect()
+}
+
+// parses periods of degraded links of the form '<from>-<to>@<start>..<end>:delay=<ms>,loss=<p> ...',
+// '<a><><b>' for both directions between a and b, 'none' for no periods
+fn link_schedule(get: Lookup, key: &str) -> Vec<ScheduledCondition> {
+    let list = get(key);
+    if list == "none" {
+        return Vec::new();
+    }
+    list.split_whitespace()
+        .flat_map(|entry| {
+            scheduled_conditions(entry).unwrap_or_else(|| {
+                panic!(
+                    "Can't parse '{}' of {}, allowed are 'none' or '<from>-<to>@<start>..<end>:delay=<ms>,loss=<probability> ...' with '*' for any node, '<a><><b>' for both directions and an empty <end> to never restore the links",
+                    entry, key
+                )
+            })
+        })
+        .collect()
+}
+
+// a single period of the schedule, one per direction
+fn scheduled_conditions(entry: &str) -> Option<Vec<ScheduledCondition>> {
+    let end = |id: &str| match id {
+        "*" => Some(None),
+        id => id.parse().ok().map(Some),
+    };
+    let (link, rest) = entry.split_once('@')?;
+    let (period, params) = rest.split_once(':')?;
+    let (symmetric, (from, to)) = match link.split_once("<>") {
+        Some(ends) => (true, ends),
+        None => (false, link.split_once('-')?),
+    };
+    let (from, to) = (end(from)?, end(to)?);
+    let (start, stop) = period.split_once("..")?;
+    let start = start.parse().ok()?;
+    let stop = match stop {
+        "" => None,
+        stop => Some(stop.parse().ok()?),
+    };
+
+    let mut condition = LinkCondition {
+        from,
+        to,
+        delay: None,
+        loss: None,
+    };
+    for param in params.split(',') {
+        match param.split_once('=')? {
+            ("delay", delay) => condition.delay = Some(delay.parse().ok()?),
+            ("loss", loss) => {
+                condition.loss = Some(loss.parse().ok().filter(|p| (0.0..=1.0).contains(p))?)
+            }
+            _ => return None,
+        }
+    }
+
+    let mut conditions = vec![condition];
+    if symmetric && from != to {
+        conditions.push(LinkCondition {
+            from: to,
+            to: from,
+            ..condition
+        });
+    }
+    Some(
+        conditions
+            .into_iter()
+            .map(|condition| ScheduledCondition {
+                start,
+                end: stop,
+                condition,
+            })
+            .collect(),
+    )
+}
+
+// parses free-form parameters of the form '<key>:<value> ...', 'none' for no parameters
+fn params(get: Lookup, key: &str) -> HashMap<String, String> {
+    let list = get(key);
+    if list == "none" {
+        return HashMap::new();
+    }
+    list.split_whitespace()
+        .map(|pair| {
+            let mut parts = pair.splitn(2, ':');
+            match (parts.next(), parts.next()) {
+                (Some(name), Some(value)) if !name.is_empty() => {
+                    (name.to_string(), value.to_string())
+                }
+                _ => panic!(
+                    "Can't parse '{}' of {}, allowed are 'none' or '<key>:<value> ...'",
+                    pair, key
+                ),
+            }
+        })
+        .collect()
+}
+
+// parses a matrix whose rows are separated by commas, 'none' for an empty matrix
+fn matrix(get: Lookup, key: &str) -> Vec<Vec<u64>> {
+    let rows = get(key);
+    if rows == "none" {
+        return Vec::new();
+    }
+    rows.split(',')
+        .map(|row| {
+            row.split_whitespace()
+                .map(|value| {
+                    value.parse().unwrap_or_else(|_| {
+                        panic!("Can't parse '{}' of {} into a number", value, key)
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// parses a value that may be `keyword` instead of a number
+fn keyword_or<T: FromStr>(get: Lookup, key: &str, keyword: &str) -> Option<T> {
+    let value = get(key);
+    if value == keyword {
+        return None;
+    }
+    match value.parse() {
+        Ok(value) => Some(value),
+        Err(_) => panic!("{} must be '{}' or a number, got '{}'", key, keyword, value),
+    }
+}
+
+// parses a value that may be 'auto'
+fn auto_or<T: FromStr>(get: Lookup, key: &str) -> Option<T> {
+    keyword_or(get, key, "auto")
+}
+
+// parses a value that may be 'none'
+fn none_or<T: FromStr>(get: Lookup, key: &str) -> Option<T> {
+    keyword_or(get, key, "none")
+}
+
+impl Settings {
+    /// Reads the settings from the environment, where `initialize_ini` stored the ini
+    #[deprecated(note = "values are no longer passed via the environment, use `from_values`")]
+    pub fn from_env() -> Self {
+        Settings::from_lookup(&|key| env2var(key), env2var("node.nodes"))
+    }
+
+    /// Reads the settings from the values of a config, see `load_config`. The number of nodes is
+    /// the first of '[node]nodes_vec', `SimulationConfig::number_of_nodes` sets the others.
+    pub fn from_values(values: &IniValues) -> Self {
+        let get = |key: &str| {
+            let mut parts = key.splitn(2, '.');
+            let section = parts.next().unwrap_or_default();
+            values.require::<String>(section, parts.next().unwrap_or_default())
+        };
+        let nodes = values.get_vec::<u32>("node", "nodes_vec")[0];
+        Settings::from_lookup(&get, nodes)
+    }
+
+    fn from_lookup(get: Lookup, nodes: u32) -> Self {
+        let sqlite_file = get("log.sqlite_file");
+
+        Settings {
+            node: NodeSettings {
+                node_type: NodeType::from_config(&get("node.node_type")),
+                nodes,
+                clients: value(get, "node.clients"),
+                application: value(get, "node.application"),
+                client_timeout: value(get, "node.client_timeout"),
+                processing_delay: value(get, "node.processing_delay"),
+                processing_delays: node_values(get, "node.processing_delays"),
+                max_rate: none_or(get, "node.max_rate"),
+                overload_policy: value(get, "node.overload_policy"),
+                inbox_size: value(get, "node.inbox_size"),
+                clock_offset_max: value(get, "node.clock_offset_max"),
+                clock_drift_max: value(get, "node.clock_drift_max"),
+                on_panic: value(get, "node.on_panic"),
+                reception_history: value(get, "node.reception_history"),
+                composition: clusters(get, "node.composition"),
+            },
+            simulation: RunSettings {
+                requests: value(get, "simulation.requests"),
+                request_interval: value(get, "simulation.request_interval"),
+                scenario: none_or(get, "simulation.scenario"),
+                liveness_bound: value(get, "simulation.liveness_bound"),
+                wait_for_commands: value(get, "control.enabled"),
+                speedup: none_or(get, "simulation.speedup"),
+                time_violation: value(get, "simulation.time_violation"),
+                event_history: value(get, "simulation.event_history"),
+                seed: none_or(get, "simulation.seed"),
+                self_check: value(get, "simulation.self_check"),
+                scheduler: value(get, "simulation.scheduler"),
+                threads: value(get, "simulation.threads"),
+            },
+            network: NetworkSettings {
+                omission_probability: value(get, "network.omission_probability"),
+                loss: link_loss(get, "network.loss"),
+                burst_enter: value(get, "network.burst_enter"),
+                burst_exit: value(get, "network.burst_exit"),
+                burst_loss: value(get, "network.burst_loss"),
+                ttl: none_or(get, "network.ttl"),
+                delay_min: value(get, "network.delay_min"),
+                delay_max: value(get, "network.delay_max"),
+                jitter: jitter(get),
+                schedule: link_schedule(get, "network.schedule"),
+                topology: value(get, "network.topology"),
+                hub: value(get, "network.hub"),
+                hub_delay: value(get, "network.hub_delay"),
+                clusters: value(get, "network.clusters"),
+                cluster_delay: value(get, "network.cluster_delay"),
+                gossip_fanout: value(get, "network.gossip_fanout"),
+                gossip_rounds: value(get, "network.gossip_rounds"),
+                gossip_hop_delay: value(get, "network.gossip_hop_delay"),
+                latency: matrix(get, "network.latency"),
+                adversary_delta: value(get, "network.adversary_delta"),
+                bandwidth: none_or(get, "network.bandwidth"),
+            },
+            quorum: QuorumSettings {
+                f: auto_or(get, "quorum.f"),
+                size: auto_or(get, "quorum.size"),
+                weights: value(get, "quorum.weights"),
+                quorum_weight: auto_or(get, "quorum.quorum_weight"),
+            },
+            pbft: PBFTSettings {
+                authentication: value(get, "pbft.authentication"),
+                watermark_window: value(get, "pbft.watermark_window"),
+                gossip_pre_prepare: value(get, "pbft.gossip_pre_prepare"),
+                client_target: ClientTarget::from_config(&get("pbft.client_target")),
+                instances: value(get, "pbft.instances"),
+                leader_rotation: value(get, "pbft.leader_rotation"),
+                request_size: value(get, "pbft.request_size"),
+                dissemination: value(get, "pbft.dissemination"),
+            },
+            tendermint: TendermintSettings {
+                timeout_propose: value(get, "tendermint.timeout_propose"),
+                timeout_prevote: value(get, "tendermint.timeout_prevote"),
+                timeout_precommit: value(get, "tendermint.timeout_precommit"),
+                timeout_delta: value(get, "tendermint.timeout_delta"),
+                retransmit_interval: value(get, "tendermint.retransmit_interval"),
+            },
+            zyzzyva: ZyzzyvaSettings {
+                speculative_execution: value(get, "zyzzyva.speculative_execution"),
+                rollback_cost: value(get, "zyzzyva.rollback_cost"),
+                authentication: value(get, "zyzzyva.authentication"),
+            },
+            adaptive: AdaptiveSettings {
+                protocols: protocols(get, "adaptive.protocols"),
+                switch_timeout: value(get, "adaptive.switch_timeout"),
+                backup_requests: value(get, "adaptive.backup_requests"),
+            },
+            dag: DAGSettings {
+                batch_size: value(get, "dag.batch_size"),
+            },
+            crypto: CryptoSettings {
+                mac_cost: value(get, "crypto.mac_cost"),
+                sign_cost: value(get, "crypto.sign_cost"),
+                verify_cost: value(get, "crypto.verify_cost"),
+                mac_size: value(get, "crypto.mac_size"),
+                signature_size: value(get, "crypto.signature_size"),
+                share_sign_cost: value(get, "crypto.share_sign_cost"),
+                share_verify_cost: value(get, "crypto.share_verify_cost"),
+                combine_cost: value(get, "crypto.combine_cost"),
+                aggregate_verify_cost: value(get, "crypto.aggregate_verify_cost"),
+                share_size: value(get, "crypto.share_size"),
+                aggregate_size: value(get, "crypto.aggregate_size"),
+                calibrate: value(get, "crypto.calibrate"),
+            }
+            .calibrated(),
+            compression: Compression {
+                threshold: none_or(get, "compression.threshold"),
+                ratio: value(get, "compression.ratio"),
+                cost: value(get, "compression.cost"),
+            },
+            storage: StorageSettings {
+                protocols: node_types(get, "storage.protocols"),
+                mode: value(get, "storage.mode"),
+                distribution: value(get, "storage.distribution"),
+                latency_min: value(get, "storage.latency_min"),
+                latency_max: value(get, "storage.latency_max"),
+            },
+            trace: TraceSettings {
+                causality: value(get, "trace.causality"),
+                request: value(get, "trace.request"),
+                message_flow: value(get, "trace.message_flow"),
+            },
+            stats: StatsSettings {
+                warmup_ms: value(get, "stats.warmup_ms"),
+                cooldown_ms: none_or(get, "stats.cooldown_ms"),
+                hgrm: value(get, "stats.hgrm"),
+            },
+            byzantine: ByzantineSettings {
+                nodes: ids(get, "byzantine.nodes"),
+                invalid_authentication: value(get, "byzantine.invalid_authentication"),
+            },
+            params: PROTOCOL_SECTIONS
+                .iter()
+                .map(|section| {
+                    let key = format!("{}.params", section);
+                    (section.to_string(), params(get, &key))
+                })
+                .collect(),
+            sqlite_file: if sqlite_file == "none" {
+                None
+            } else {
+                Some(sqlite_file)
+            },
+            result_log: value(get, "log.result"),
+        }
+    }
+}
+
+/// The defaults of the ini, but a reliable network and nothing is written to disk
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            node: NodeSettings {
+                node_type: NodeType::PBFT,
+                nodes: 4,
+                clients: 1,
+                application: "counter".to_string(),
+                client_timeout: 400,
+                processing_delay: 5,
+                processing_delays: BTreeMap::new(),
+                max_rate: None,
+                overload_policy: OverloadPolicy::Queue,
+                inbox_size: 100,
+                clock_offset_max: 0,
+                clock_drift_max: 0.0,
+                on_panic: PanicPolicy::Abort,
+                reception_history: 20,
+                composition: Vec::new(),
+            },
+            simulation: RunSettings {
+                requests: 100,
+                request_interval: 1000,
+                scenario: None,
+                liveness_bound: 5000,
+                wait_for_commands: false,
+                speedup: None,
+                time_violation: TimeViolationPolicy::Abort,
+                event_history: 0,
+                seed: None,
+                self_check: false,
+                scheduler: SchedulerKind::Heap,
+                threads: 1,
+            },
+            network: NetworkSettings {
+                omission_probability: 0.0,
+                loss: Vec::new(),
+                burst_enter: 0.0,
+                burst_exit: 0.5,
+                burst_loss: 1.0,
+                ttl: None,
+                delay_min: 20.0,
+                delay_max: 200.0,
+                jitter: Jitter::None,
+                schedule: Vec::new(),
+                topology: "mesh".to_string(),
+                hub: 1,
+                hub_delay: 10,
+                clusters: 2,
+                cluster_delay: 100,
+                gossip_fanout: 3,
+                gossip_rounds: 4,
+                gossip_hop_delay: 1,
+                latency: Vec::new(),
+                adversary_delta: 100,
+                bandwidth: None,
+            },
+            quorum: QuorumSettings {
+                f: None,
+                size: None,
+                weights: "none".to_string(),
+                quorum_weight: None,
+            },
+            pbft: PBFTSettings {
+                authentication: AuthenticationMode::Mac,
+                watermark_window: 100,
+                gossip_pre_prepare: false,
+                client_target: ClientTarget::Node(1),
+                instances: 1,
+                leader_rotation: LeaderRotation::Stable,
+                request_size: CLIENT_REQUEST_SIZE,
+                dissemination: Dissemination::Inline,
+            },
+            tendermint: TendermintSettings {
+                timeout_propose: 1000,
+                timeout_prevote: 500,
+                timeout_precommit: 500,
+                timeout_delta: 500,
+                retransmit_interval: 1000,
+            },
+            zyzzyva: ZyzzyvaSettings {
+                speculative_execution: true,
+                rollback_cost: 100,
+                authentication: AuthenticationMode::Mac,
+            },
+            adaptive: AdaptiveSettings {
+                protocols: vec![NodeType::SBFT, NodeType::PBFT],
+                switch_timeout: 3000,
+                backup_requests: 50,
+            },
+            dag: DAGSettings { batch_size: 100 },
+            crypto: CryptoSettings {
+                mac_cost: 2,
+                sign_cost: 1500,
+                verify_cost: 300,
+                mac_size: 10,
+                signature_size: 128,
+                share_sign_cost: 1000,
+                share_verify_cost: 2000,
+                combine_cost: 100,
+                aggregate_verify_cost: 2000,
+                share_size: 48,
+                aggregate_size: 48,
+                calibrate: false,
+            },
+            compression: Compression::none(),
+            storage: StorageSettings {
+                protocols: Vec::new(),
+                mode: StorageMode::Sync,
+                distribution: LatencyDistribution::Uniform,
+                latency_min: 100,
+                latency_max: 1000,
+            },
+            trace: TraceSettings {
+                causality: false,
+                request: 1,
+                message_flow: false,
+            },
+            stats: StatsSettings {
+                warmup_ms: 0,
+                cooldown_ms: None,
+                hgrm: false,
+            },
+            byzantine: ByzantineSettings {
+                nodes: Vec::new(),
+                invalid_authentication: 0.0,
+            },
+            params: BTreeMap::new(),
+            sqlite_file: None,
+            result_log: false,
+        }
+    }
+}
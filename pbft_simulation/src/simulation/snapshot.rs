@@ -0,0 +1,111 @@
+/***************************************************************************************************
+The effective configuration of a run, written as JSON next to its result log, so the results can be
+interpreted without the ini they came from.
+***************************************************************************************************/
+
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::process::Command;
+
+use log::{info, warn};
+use mc_utils::filehelper::AtomicFile;
+use serde::Serialize;
+
+use crate::simulation::config::result_log_prefix;
+use crate::simulation::output::RunId;
+use crate::simulation::settings::Settings;
+
+/// All settings of a run and what else it depends on
+#[derive(Debug, Serialize)]
+pub struct ConfigSnapshot<'a> {
+    /// Version of the simulation
+    pub version: &'static str,
+    /// The commit of the working directory the simulation ran in, if it is a git repository
+    pub commit: Option<String>,
+    /// Seed of the random numbers, the run is repeated with `simulation.seed` set to it
+    pub seed: u64,
+    /// The run, its files are in its directory
+    pub run: &'a RunId,
+    pub settings: &'a Settings,
+}
+
+impl<'a> ConfigSnapshot<'a> {
+    pub fn new(settings: &'a Settings, run: &'a RunId) -> Self {
+        ConfigSnapshot {
+            version: env!("CARGO_PKG_VERSION"),
+            commit: git_commit(),
+            seed: run.seed,
+            run,
+            settings,
+        }
+    }
+
+    /// Writes the snapshot as JSON to `path`, atomically, so a crash does not leave half of it
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(AtomicFile::create(path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.into_inner().map_err(io::Error::from)?.commit()
+    }
+}
+
+/// Writes the snapshot of `settings` next to the result log of the run, if that is written to a file
+pub fn write_next_to_result_log(settings: &Settings, run: &RunId) {
+    if !settings.result_log {
+        return;
+    }
+    let path = format!(
+        "{}.json",
+        result_log_prefix(
+            run,
+            settings.node.nodes,
+            settings.simulation.requests,
+            settings.network.omission_probability
+        )
+    );
+    match ConfigSnapshot::new(settings, run).write(&path) {
+        Ok(()) => info!("Wrote the configuration of the run to {}", path),
+        Err(e) => warn!("Failed to write the configuration {}: {}", path, e),
+    }
+}
+
+// the commit checked out in the working directory, if git is available
+fn git_commit() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let commit = String::from_utf8(output.stdout).ok()?;
+    Some(commit.trim().to_string()).filter(|commit| !commit.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn check_snapshot_is_written_as_json() {
+        let mut settings = Settings::default();
+        settings.node.nodes = 7;
+        let path = std::env::temp_dir()
+            .join("bft_simulation_snapshot")
+            .join("config.json");
+
+        let run = RunId::new();
+        ConfigSnapshot::new(&settings, &run).write(&path).unwrap();
+        let json = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        // no temporary file is left behind
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 0);
+        assert!(json.contains("\"version\""), "{}", json);
+        assert!(json.contains("\"commit\""), "{}", json);
+        assert!(
+            json.contains(&format!("\"seed\": {}", run.seed)),
+            "{}",
+            json
+        );
+        assert!(json.contains("\"nodes\": 7"), "{}", json);
+        assert!(json.contains("\"omission_probability\": 0.0"), "{}", json);
+    }
+}
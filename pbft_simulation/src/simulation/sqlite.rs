@@ -0,0 +1,229 @@
+/***************************************************************************************************
+Optional sink writing the results of all runs into a SQLite database (feature 'sqlite'). Each run
+gets a row in 'runs', its parameters are stored in 'config'. Result lines of the form
+'request id;phase' end up in 'request_phases', all other result lines in 'results'.
+***************************************************************************************************/
+
+use std::cell::RefCell;
+
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde_json::Value;
+
+use crate::node::NodeType;
+use crate::simulation::settings::Settings;
+use crate::simulation::time::Time;
+
+thread_local! {
+    // the database of the run the simulation on this thread is currently executing
+    static DATABASE: RefCell<Option<ResultDatabase>> = const { RefCell::new(None) };
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        run_id INTEGER PRIMARY KEY,
+        node_type TEXT NOT NULL,
+        nodes INTEGER NOT NULL,
+        finished_at INTEGER
+    );
+    CREATE TABLE IF NOT EXISTS config (
+        run_id INTEGER NOT NULL REFERENCES runs(run_id),
+        key TEXT NOT NULL,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS request_phases (
+        run_id INTEGER NOT NULL REFERENCES runs(run_id),
+        request_id INTEGER NOT NULL,
+        node_id INTEGER NOT NULL,
+        phase TEXT NOT NULL,
+        time INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS results (
+        run_id INTEGER NOT NULL REFERENCES runs(run_id),
+        node_id INTEGER NOT NULL,
+        message TEXT NOT NULL,
+        time INTEGER NOT NULL
+    );";
+
+/// Connection to the database, bound to a single run. All rows of the run are written in one
+/// transaction, which is committed once the run finished.
+pub struct ResultDatabase {
+    connection: Connection,
+    run_id: i64,
+}
+
+impl ResultDatabase {
+    /// Creates the tables if necessary and inserts a new run with the given parameters
+    pub fn new(
+        connection: Connection,
+        node_type: &str,
+        number_of_nodes: u32,
+        config: &[(String, String)],
+    ) -> rusqlite::Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        connection.execute_batch("BEGIN")?;
+        connection.execute(
+            "INSERT INTO runs (node_type, nodes) VALUES (?1, ?2)",
+            params![node_type, number_of_nodes],
+        )?;
+        let run_id = connection.last_insert_rowid();
+
+        for (key, value) in config {
+            connection.execute(
+                "INSERT INTO config (run_id, key, value) VALUES (?1, ?2, ?3)",
+                params![run_id, key, value],
+            )?;
+        }
+
+        Ok(ResultDatabase { connection, run_id })
+    }
+
+    /// Stores a line of the result log
+    pub fn insert(&self, time: Time, node_id: Option<u32>, message: &str) -> rusqlite::Result<()> {
+        let node_id = node_id.map_or(-1, i64::from);
+        let time = time.milli() as i64;
+        let mut fields = message.splitn(2, ';');
+
+        if let (Some(request_id), Some(phase)) = (fields.next(), fields.next()) {
+            if let Ok(request_id) = request_id.parse::<i64>() {
+                self.connection.execute(
+                    "INSERT INTO request_phases (run_id, request_id, node_id, phase, time)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![self.run_id, request_id, node_id, phase, time],
+                )?;
+                return Ok(());
+            }
+        }
+
+        self.connection.execute(
+            "INSERT INTO results (run_id, node_id, message, time) VALUES (?1, ?2, ?3, ?4)",
+            params![self.run_id, node_id, message, time],
+        )?;
+        Ok(())
+    }
+
+    /// Marks the run as finished and commits all of its rows
+    pub fn finish(self, time: Time) -> rusqlite::Result<()> {
+        self.connection.execute(
+            "UPDATE runs SET finished_at = ?1 WHERE run_id = ?2",
+            params![time.milli() as i64, self.run_id],
+        )?;
+        self.connection.execute_batch("COMMIT")
+    }
+}
+
+/// Parameters of the run, i.e. the settings in use, keyed like 'section.key'
+fn config_parameters(settings: &Settings) -> Vec<(String, String)> {
+    let mut config = Vec::new();
+    match serde_json::to_value(settings) {
+        Ok(value) => flatten("", &value, &mut config),
+        Err(e) => warn!("Failed to serialize the settings: {}", e),
+    }
+    config.sort();
+    config
+}
+
+// collects the leaves of `value`, nested keys are joined by '.'
+fn flatten(prefix: &str, value: &Value, config: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(fields) if !fields.is_empty() => {
+            for (key, field) in fields {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&key, field, config);
+            }
+        }
+        Value::String(string) => config.push((prefix.to_string(), string.clone())),
+        _ => config.push((prefix.to_string(), value.to_string())),
+    }
+}
+
+/// Opens the database at `path` and starts a new run with `settings` on the current thread
+pub fn start_run(path: &str, node_type: NodeType, number_of_nodes: u32, settings: &Settings) {
+    let database = Connection::open(path).and_then(|connection| {
+        ResultDatabase::new(
+            connection,
+            &format!("{:?}", node_type),
+            number_of_nodes,
+            &config_parameters(settings),
+        )
+    });
+
+    match database {
+        Ok(database) => {
+            info!("Writing results of run {} to {}", database.run_id, path);
+            DATABASE.with(|db| *db.borrow_mut() = Some(database));
+        }
+        Err(e) => warn!("Failed to open the result database {}: {}", path, e),
+    }
+}
+
+/// Stores a line of the result log in the current run, if any
+pub fn record(time: Time, node_id: Option<u32>, message: &str) {
+    DATABASE.with(|db| {
+        if let Some(database) = &*db.borrow() {
+            if let Err(e) = database.insert(time, node_id, message) {
+                warn!("Failed to write a result to the database: {}", e);
+            }
+        }
+    });
+}
+
+/// Finishes the current run, if any
+pub fn finish_run(time: Time) {
+    DATABASE.with(|db| {
+        if let Some(database) = db.borrow_mut().take() {
+            if let Err(e) = database.finish(time) {
+                warn!("Failed to commit the results to the database: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_sqlite_sink_splits_phases() {
+        let config = vec![("node.nodes".to_string(), "4".to_string())];
+        let database =
+            ResultDatabase::new(Connection::open_in_memory().unwrap(), "PBFT", 4, &config).unwrap();
+
+        database
+            .insert(Time::new(10), Some(1), "3;prepared")
+            .unwrap();
+        database
+            .insert(Time::new(12), Some(2), "3;prepared")
+            .unwrap();
+        database
+            .insert(Time::new(20), None, "Simulation finished")
+            .unwrap();
+
+        let count = |sql: &str| -> i64 {
+            database
+                .connection
+                .query_row(sql, params![], |row| row.get(0))
+                .unwrap()
+        };
+        assert_eq!(
+            count("SELECT COUNT(*) FROM request_phases WHERE request_id = 3"),
+            2
+        );
+        assert_eq!(count("SELECT COUNT(*) FROM results WHERE node_id = -1"), 1);
+        assert_eq!(count("SELECT COUNT(*) FROM config"), 1);
+    }
+
+    #[test]
+    fn check_config_parameters_come_from_the_settings() {
+        let mut settings = Settings::default();
+        settings.simulation.request_interval = 250;
+        let config = config_parameters(&settings);
+
+        assert!(config.contains(&("simulation.request_interval".to_string(), "250".to_string())));
+        assert!(config.iter().any(|(key, _)| key == "node.nodes"));
+    }
+}
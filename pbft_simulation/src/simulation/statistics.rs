@@ -0,0 +1,748 @@
+/***************************************************************************************************
+Statistics collected while the simulation runs, reported once it is finished.
+***************************************************************************************************/
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use log::{info, warn};
+
+use crate::simulation::config::ResultLogger;
+use crate::simulation::event::Message;
+use crate::simulation::histogram::{LatencyHistogram, PERCENTILES};
+use crate::simulation::observer::Observer;
+use crate::simulation::results::ResultEvent;
+use crate::simulation::settings::Settings;
+use crate::simulation::time::Time;
+
+/// Traffic counters of a single message type
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TrafficCounter {
+    /// Number of messages handed to the network
+    pub sent: u64,
+    /// Bytes of all messages handed to the network
+    pub bytes: u64,
+    /// Number of messages the network omitted
+    pub dropped: u64,
+}
+
+/// Network traffic, counted per (protocol, message type)
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TrafficStatistics {
+    counters: BTreeMap<(&'static str, &'static str), TrafficCounter>,
+}
+
+impl TrafficStatistics {
+    fn counter_mut(&mut self, message: &Message) -> &mut TrafficCounter {
+        self.counters
+            .entry((message.protocol(), message.message_type()))
+            .or_default()
+    }
+
+    /// Counts a message of `size` bytes that was handed to the network
+    pub fn record_sent(&mut self, message: &Message, size: u32) {
+        let counter = self.counter_mut(message);
+        counter.sent += 1;
+        counter.bytes += u64::from(size);
+    }
+
+    /// Counts a message that the network omitted
+    pub fn record_dropped(&mut self, message: &Message) {
+        self.counter_mut(message).dropped += 1;
+    }
+
+    /// Returns the counter for the given protocol and message type, if any message was sent
+    pub fn get(&self, protocol: &str, message_type: &str) -> Option<&TrafficCounter> {
+        self.counters
+            .iter()
+            .find(|((p, t), _)| *p == protocol && *t == message_type)
+            .map(|(_, counter)| counter)
+    }
+
+    /// Writes one line per message type to the result log:
+    /// 'time;-1;traffic;protocol;message type;sent;bytes;dropped'
+    pub fn report(&self, time: Time, results: &ResultLogger) {
+        for ((protocol, message_type), counter) in &self.counters {
+            info!(
+                "Traffic {} {}: sent {}, bytes {}, dropped {}",
+                protocol, message_type, counter.sent, counter.bytes, counter.dropped
+            );
+            results.log(
+                time,
+                None,
+                &ResultEvent::Traffic {
+                    protocol: protocol.to_string(),
+                    message_type: message_type.to_string(),
+                    counter: *counter,
+                },
+            );
+        }
+    }
+}
+
+/// Progress of a single request as observed in the result log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestProgress {
+    /// Time (ms) the request was first seen
+    pub first_seen: u64,
+    /// The last phase any node reported and its time (ms)
+    pub last_phase: String,
+    pub last_seen: u64,
+    /// Time (ms) the request was executed or completed, if so
+    pub done: Option<u64>,
+}
+
+/// Flags requests that are not executed or completed within `bound` ms of simulated time, or not
+/// at all by the end of the run
+#[derive(Debug)]
+pub struct RequestWatchdog {
+    bound: u64,
+    requests: BTreeMap<u64, RequestProgress>,
+}
+
+impl RequestWatchdog {
+    pub fn new(bound: u64) -> Self {
+        RequestWatchdog {
+            bound,
+            requests: BTreeMap::new(),
+        }
+    }
+
+    /// Starts to track a request its client issues at `time`, so it is flagged even if no node
+    /// ever sees it
+    pub fn issue(&mut self, time: Time, request_id: u64) {
+        let time = time.milli();
+        self.requests
+            .entry(request_id)
+            .or_insert_with(|| RequestProgress {
+                first_seen: time,
+                last_phase: "issued".to_string(),
+                last_seen: time,
+                done: None,
+            });
+    }
+
+    /// Tracks the events of single requests, all other events are ignored
+    pub fn record(&mut self, time: Time, event: &ResultEvent) {
+        let (request_id, phase) = match event.request_phase() {
+            Some(request_phase) => request_phase,
+            None => return,
+        };
+
+        let time = time.milli();
+        let progress = self
+            .requests
+            .entry(request_id)
+            .or_insert_with(|| RequestProgress {
+                first_seen: time,
+                last_phase: String::new(),
+                last_seen: time,
+                done: None,
+            });
+        progress.last_phase = phase.to_string();
+        progress.last_seen = time;
+        if progress.done.is_none() && event.done_request().is_some() {
+            progress.done = Some(time);
+        }
+    }
+
+    /// Returns the requests that were not done within the bound, i.e. those that finished late,
+    /// and those that are not done at all. The run is over, so they never will be, even if it went
+    /// idle before their bound passed.
+    pub fn overdue(&self) -> Vec<(u64, &RequestProgress)> {
+        self.requests
+            .iter()
+            .filter(|(_, p)| !matches!(p.done, Some(done) if done <= p.first_seen + self.bound))
+            .map(|(id, p)| (*id, p))
+            .collect()
+    }
+
+    /// Writes one line per overdue request to the result log of the run that ended at `time`:
+    /// 'time;-1;watchdog;request id;last phase;first seen;last seen;done (-1 if stuck)'
+    pub fn report(&self, time: Time, results: &ResultLogger) {
+        let overdue = self.overdue();
+        let stuck = overdue.iter().filter(|(_, p)| p.done.is_none()).count();
+        if !overdue.is_empty() {
+            warn!(
+                "{} requests were not done within {}ms, {} of them are stuck",
+                overdue.len(),
+                self.bound,
+                stuck
+            );
+        }
+
+        for (request_id, progress) in overdue {
+            results.log(
+                time,
+                None,
+                &ResultEvent::Watchdog {
+                    request: request_id,
+                    last_phase: progress.last_phase.clone(),
+                    first_seen: progress.first_seen,
+                    last_seen: progress.last_seen,
+                    done: progress.done,
+                },
+            );
+        }
+    }
+}
+
+/// Latency and throughput of the requests issued within the measurement window
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RequestSummary {
+    /// Number of requests issued within the window
+    pub issued: u64,
+    /// Number of them that were executed or completed
+    pub done: u64,
+    /// Mean and maximal latency (ms) of the done requests
+    pub mean_latency: f64,
+    pub max_latency: u64,
+    /// Done requests per second of the window
+    pub throughput: f64,
+}
+
+/// Collects the latency of requests. Only requests issued within the measurement window, i.e.
+/// after the warm-up and before the cool-down, are taken into account, so the numbers describe the
+/// steady state of the run. A request is done once its client completed it, the first replica
+/// executing it stands in for clients that are not simulated as nodes.
+#[derive(Debug)]
+pub struct RequestStatistics {
+    warmup: u64,
+    cooldown: Option<u64>,
+    /// Time (ms) each request was first seen, the time it was done, if so, and whether it was
+    /// completed by its client
+    requests: BTreeMap<u64, (u64, Option<u64>, bool)>,
+}
+
+impl RequestStatistics {
+    /// Measures the requests issued from `warmup` (ms) until `cooldown` (ms), if any
+    pub fn new(warmup: u64, cooldown: Option<u64>) -> Self {
+        RequestStatistics {
+            warmup,
+            cooldown,
+            requests: BTreeMap::new(),
+        }
+    }
+
+    /// Tracks the events of single requests, all other events are ignored
+    pub fn record(&mut self, time: Time, event: &ResultEvent) {
+        let (request_id, _) = match event.request_phase() {
+            Some(request_phase) => request_phase,
+            None => return,
+        };
+
+        let time = time.milli();
+        let (_, done, completed) = self
+            .requests
+            .entry(request_id)
+            .or_insert((time, None, false));
+        match event {
+            ResultEvent::Completed(_) if !*completed => {
+                *done = Some(time);
+                *completed = true;
+            }
+            _ if done.is_none() && event.done_request().is_some() => *done = Some(time),
+            _ => {}
+        }
+    }
+
+    // the end of the window of a run that ended at `time`
+    fn window_end(&self, time: Time) -> u64 {
+        self.cooldown.map_or(time.milli(), |c| c.min(time.milli()))
+    }
+
+    /// The latencies (ms) of the done requests of the window, which ends with the run at `time` at
+    /// the latest
+    pub fn latencies(&self, time: Time) -> LatencyHistogram {
+        let end = self.window_end(time);
+        let mut histogram = LatencyHistogram::default();
+        for (issued, done, _) in self.requests.values() {
+            match done {
+                Some(done) if *issued >= self.warmup && *issued < end => {
+                    histogram.record(done - issued)
+                }
+                _ => {}
+            }
+        }
+        histogram
+    }
+
+    /// The time (ms) each done request of the window was issued at and its latency (ms), by id
+    pub fn latencies_by_request(&self, time: Time) -> BTreeMap<u64, (u64, u64)> {
+        let end = self.window_end(time);
+        self.requests
+            .iter()
+            .filter_map(|(id, (issued, done, _))| match done {
+                Some(done) if *issued >= self.warmup && *issued < end => {
+                    Some((*id, (*issued, done - issued)))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Summarizes the requests of the window, which ends with the run at `time` at the latest
+    pub fn summary(&self, time: Time) -> RequestSummary {
+        let end = self.window_end(time);
+        let mut summary = RequestSummary::default();
+        let mut total_latency = 0;
+
+        for (issued, done, _) in self.requests.values() {
+            if *issued < self.warmup || *issued >= end {
+                continue;
+            }
+            summary.issued += 1;
+            if let Some(done) = done {
+                let latency = done - issued;
+                summary.done += 1;
+                total_latency += latency;
+                summary.max_latency = summary.max_latency.max(latency);
+            }
+        }
+
+        if summary.done > 0 {
+            summary.mean_latency = total_latency as f64 / summary.done as f64;
+        }
+        if end > self.warmup {
+            summary.throughput = summary.done as f64 * 1000.0 / (end - self.warmup) as f64;
+        }
+        summary
+    }
+
+    /// Writes the summary to the result log:
+    /// 'time;-1;requests;issued;done;mean latency;max latency;throughput', followed by the
+    /// percentiles of the latency: 'time;-1;percentile;percentile;latency'
+    pub fn report(&self, time: Time, results: &ResultLogger) -> RequestSummary {
+        let summary = self.summary(time);
+        info!(
+            "{} of {} requests issued after {}ms are done, mean latency {:.1}ms, throughput {:.2}/s",
+            summary.done, summary.issued, self.warmup, summary.mean_latency, summary.throughput
+        );
+        results.log(time, None, &ResultEvent::Requests(summary.clone()));
+
+        let latencies = self.latencies(time);
+        if latencies.count() > 0 {
+            for percentile in PERCENTILES.iter().copied() {
+                let latency = latencies.value_at_percentile(percentile);
+                results.log(
+                    time,
+                    None,
+                    &ResultEvent::Percentile {
+                        percentile,
+                        latency,
+                    },
+                );
+            }
+        }
+        summary
+    }
+}
+
+/// Throughput of the requests done in a view
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ViewPeriod {
+    pub view: u64,
+    /// Time (ms) from the switch to the view until the switch to the next one or the end of the run
+    pub duration: u64,
+    /// Number of requests done for the first time in the view
+    pub done: u64,
+    /// Done requests per second of the view
+    pub throughput: f64,
+}
+
+/// The view changes of a run
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ViewSummary {
+    /// Number of times the nodes moved on to the next view
+    pub view_changes: u64,
+    /// Time (ms) between the first abort of a view and the switch to the next one, i.e. without a
+    /// stable leader
+    pub unstable_ms: u64,
+    /// Requests that were pending when their view was aborted
+    pub aborted_requests: u64,
+    /// Aborted requests that were proposed again in a later view
+    pub reproposed_requests: u64,
+    /// The views of the run in their order
+    pub views: Vec<ViewPeriod>,
+}
+
+/// Collects the view changes of a run from the aborts and switches of the protocol instances of
+/// the adaptive nodes, in which an instance is a view. The first node aborting a view and the first
+/// node switching to the next one stand in for all nodes.
+#[derive(Debug)]
+pub struct ViewStatistics {
+    view: u64,
+    /// Time (ms) of the switch to each view and the number of requests done in it
+    views: BTreeMap<u64, (u64, u64)>,
+    /// Time (ms) of the first abort of the current view, if any
+    unstable_since: Option<u64>,
+    unstable_ms: u64,
+    /// The requests that are not done yet and the view they were last proposed in
+    pending: BTreeMap<u64, u64>,
+    done: HashSet<u64>,
+    aborted: BTreeSet<u64>,
+    reproposed: BTreeSet<u64>,
+}
+
+impl Default for ViewStatistics {
+    fn default() -> Self {
+        ViewStatistics {
+            view: 0,
+            views: vec![(0, (0, 0))].into_iter().collect(),
+            unstable_since: None,
+            unstable_ms: 0,
+            pending: BTreeMap::new(),
+            done: HashSet::new(),
+            aborted: BTreeSet::new(),
+            reproposed: BTreeSet::new(),
+        }
+    }
+}
+
+impl ViewStatistics {
+    /// Tracks the aborts, the switches and the events of single requests, all other events are
+    /// ignored
+    pub fn record(&mut self, time: Time, event: &ResultEvent) {
+        let time = time.milli();
+        match event {
+            ResultEvent::Aborted { instance }
+                if *instance == self.view && self.unstable_since.is_none() =>
+            {
+                self.unstable_since = Some(time);
+                self.aborted.extend(self.pending.keys().copied());
+            }
+            ResultEvent::Switched { instance, .. } if *instance > self.view => {
+                let since = self.unstable_since.take().unwrap_or(time);
+                self.unstable_ms += time.saturating_sub(since);
+                self.view = *instance;
+                self.views.insert(self.view, (time, 0));
+            }
+            _ => {}
+        }
+
+        let request_id = match event.request_phase() {
+            Some((request_id, _)) if !self.done.contains(&request_id) => request_id,
+            _ => return,
+        };
+        if event.done_request().is_some() {
+            self.done.insert(request_id);
+            self.pending.remove(&request_id);
+            if let Some((_, done)) = self.views.get_mut(&self.view) {
+                *done += 1;
+            }
+            return;
+        }
+        let view = self.pending.entry(request_id).or_insert(self.view);
+        if *view < self.view && self.aborted.contains(&request_id) {
+            self.reproposed.insert(request_id);
+            *view = self.view;
+        }
+    }
+
+    /// Summarizes the view changes of a run that ended at `time`
+    pub fn summary(&self, time: Time) -> ViewSummary {
+        let time = time.milli();
+        let unstable = self
+            .unstable_since
+            .map_or(0, |since| time.saturating_sub(since));
+
+        let mut views = Vec::new();
+        let mut starts = self.views.iter().peekable();
+        while let Some((view, (start, done))) = starts.next() {
+            let end = starts.peek().map_or(time, |(_, (next, _))| *next);
+            let duration = end.saturating_sub(*start);
+            views.push(ViewPeriod {
+                view: *view,
+                duration,
+                done: *done,
+                throughput: if duration > 0 {
+                    *done as f64 * 1000.0 / duration as f64
+                } else {
+                    0.0
+                },
+            });
+        }
+
+        ViewSummary {
+            view_changes: self.views.len() as u64 - 1,
+            unstable_ms: self.unstable_ms + unstable,
+            aborted_requests: self.aborted.len() as u64,
+            reproposed_requests: self.reproposed.len() as u64,
+            views,
+        }
+    }
+
+    /// Writes the summary to the result log if any view was aborted:
+    /// 'time;-1;view_changes;view changes;unstable ms;aborted requests;reproposed requests',
+    /// followed by the throughput of each view: 'time;-1;view;view;duration;done;throughput'
+    pub fn report(&self, time: Time, results: &ResultLogger) -> ViewSummary {
+        let summary = self.summary(time);
+        if summary.view_changes == 0 && self.unstable_since.is_none() {
+            return summary;
+        }
+        info!(
+            "{} view changes, {}ms without a stable leader, {} of {} aborted requests proposed again",
+            summary.view_changes,
+            summary.unstable_ms,
+            summary.reproposed_requests,
+            summary.aborted_requests
+        );
+        results.log(
+            time,
+            None,
+            &ResultEvent::ViewChanges {
+                view_changes: summary.view_changes,
+                unstable_ms: summary.unstable_ms,
+                aborted_requests: summary.aborted_requests,
+                reproposed_requests: summary.reproposed_requests,
+            },
+        );
+        for period in &summary.views {
+            results.log(time, None, &ResultEvent::View(period.clone()));
+        }
+        summary
+    }
+}
+
+/// The statistics of a run: the watchdog, if enabled, the request statistics and the view changes.
+/// They observe the result log while the run is going on.
+#[derive(Debug)]
+pub struct RunStatistics {
+    watchdog: Option<RequestWatchdog>,
+    requests: RequestStatistics,
+    views: ViewStatistics,
+}
+
+impl RunStatistics {
+    /// The statistics configured in `settings`, a liveness bound of 0 disables the watchdog
+    pub fn new(settings: &Settings) -> Self {
+        let bound = settings.simulation.liveness_bound;
+        RunStatistics {
+            watchdog: (bound > 0).then(|| RequestWatchdog::new(bound)),
+            requests: RequestStatistics::new(settings.stats.warmup_ms, settings.stats.cooldown_ms),
+            views: ViewStatistics::default(),
+        }
+    }
+
+    /// Passes an event of the result log to the watchdog, the request statistics and the view
+    /// changes
+    pub fn record(&mut self, time: Time, event: &ResultEvent) {
+        if let Some(watchdog) = &mut self.watchdog {
+            watchdog.record(time, event);
+        }
+        self.requests.record(time, event);
+        self.views.record(time, event);
+    }
+
+    /// Reports the overdue requests, the request statistics and the view changes, returns the ids
+    /// of the overdue requests and the summaries
+    pub fn report(
+        &self,
+        time: Time,
+        results: &ResultLogger,
+    ) -> (Vec<u64>, RequestSummary, ViewSummary) {
+        let overdue = match &self.watchdog {
+            Some(watchdog) => {
+                watchdog.report(time, results);
+                watchdog.overdue().into_iter().map(|(id, _)| id).collect()
+            }
+            None => Vec::new(),
+        };
+        (
+            overdue,
+            self.requests.report(time, results),
+            self.views.report(time, results),
+        )
+    }
+
+    /// The latencies of the requests of the measurement window, see `RequestStatistics::latencies`
+    pub fn latencies(&self, time: Time) -> LatencyHistogram {
+        self.requests.latencies(time)
+    }
+}
+
+impl Observer for RunStatistics {
+    fn on_request_issued(&mut self, request_id: u64, time: Time) {
+        if let Some(watchdog) = &mut self.watchdog {
+            watchdog.issue(time, request_id);
+        }
+    }
+
+    fn on_result(&mut self, time: Time, _node_id: Option<u32>, event: &ResultEvent) {
+        self.record(time, event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_traffic_counting() {
+        let mut traffic = TrafficStatistics::default();
+
+        traffic.record_sent(&Message::Dummy, 10);
+        traffic.record_sent(&Message::Dummy, 20);
+        traffic.record_dropped(&Message::Dummy);
+
+        assert_eq!(
+            traffic.get("Dummy", "Dummy"),
+            Some(&TrafficCounter {
+                sent: 2,
+                bytes: 30,
+                dropped: 1,
+            })
+        );
+        assert_eq!(traffic.get("PBFT", "Prepare"), None);
+    }
+
+    #[test]
+    fn check_watchdog_flags_overdue_requests() {
+        let mut watchdog = RequestWatchdog::new(100);
+
+        watchdog.record(Time::new(0), &ResultEvent::Request(1));
+        watchdog.record(Time::new(50), &ResultEvent::Executed(1));
+        watchdog.record(Time::new(10), &ResultEvent::Request(2));
+        watchdog.record(Time::new(40), &ResultEvent::Prepared(2));
+        watchdog.record(Time::new(20), &ResultEvent::Request(3));
+        watchdog.record(Time::new(60), &ResultEvent::CommittedLocal(3));
+        watchdog.record(Time::new(150), &ResultEvent::Executed(3));
+        watchdog.record(Time::new(150), &ResultEvent::Heal);
+
+        let overdue = watchdog.overdue();
+        let ids: Vec<u64> = overdue.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![2, 3]);
+        assert_eq!(overdue[0].1.last_phase, "prepared");
+        assert_eq!(overdue[0].1.done, None);
+        assert_eq!(overdue[1].1.done, Some(150));
+
+        // a request that is not done when the run ends is stuck, even within the bound
+        let mut watchdog = RequestWatchdog::new(5000);
+        watchdog.issue(Time::new(3000), 4);
+        watchdog.record(Time::new(3100), &ResultEvent::CommittedLocal(4));
+        let overdue = watchdog.overdue();
+        assert_eq!(overdue.len(), 1);
+        assert_eq!(overdue[0].1.last_phase, "committed_local");
+        assert_eq!(overdue[0].1.done, None);
+    }
+
+    #[test]
+    fn check_watchdog_watches_requests_from_their_issue() {
+        let mut watchdog = RequestWatchdog::new(100);
+
+        // the replicas see request 1 long after its client issued it, request 2 is lost
+        watchdog.issue(Time::new(0), 1);
+        watchdog.record(Time::new(90), &ResultEvent::SpeculativeCommit(1));
+        watchdog.record(Time::new(120), &ResultEvent::Completed(1));
+        watchdog.issue(Time::new(10), 2);
+
+        let overdue = watchdog.overdue();
+        assert_eq!(overdue.len(), 2);
+        assert_eq!(overdue[0].1.first_seen, 0);
+        assert_eq!(overdue[0].1.done, Some(120));
+        assert_eq!(overdue[1].0, 2);
+        assert_eq!(overdue[1].1.last_phase, "issued");
+        assert_eq!(overdue[1].1.done, None);
+    }
+
+    #[test]
+    fn check_request_statistics_exclude_warmup_and_cooldown() {
+        let mut statistics = RequestStatistics::new(100, Some(300));
+
+        // issued during the warm-up
+        statistics.record(Time::new(50), &ResultEvent::Request(1));
+        statistics.record(Time::new(150), &ResultEvent::Executed(1));
+        statistics.record(Time::new(100), &ResultEvent::Request(2));
+        statistics.record(Time::new(140), &ResultEvent::Executed(2));
+        statistics.record(Time::new(160), &ResultEvent::Executed(2));
+        statistics.record(Time::new(200), &ResultEvent::Request(3));
+        statistics.record(Time::new(280), &ResultEvent::Completed(3));
+        statistics.record(Time::new(250), &ResultEvent::Request(4));
+        // issued during the cool-down
+        statistics.record(Time::new(300), &ResultEvent::Request(5));
+        statistics.record(Time::new(310), &ResultEvent::Executed(5));
+
+        let summary = statistics.summary(Time::new(1000));
+        assert_eq!(summary.issued, 3);
+        assert_eq!(summary.done, 2);
+        assert_eq!(summary.mean_latency, 60.0);
+        assert_eq!(summary.max_latency, 80);
+        assert_eq!(summary.throughput, 10.0);
+        assert_eq!(
+            statistics.latencies_by_request(Time::new(1000)),
+            vec![(2, (100, 40)), (3, (200, 80))].into_iter().collect()
+        );
+
+        let mut statistics = RequestStatistics::new(0, None);
+        statistics.record(Time::new(0), &ResultEvent::Request(1));
+        statistics.record(Time::new(100), &ResultEvent::Executed(1));
+        assert_eq!(statistics.summary(Time::new(500)).throughput, 2.0);
+    }
+
+    #[test]
+    fn check_request_latency_ends_with_the_client() {
+        let mut statistics = RequestStatistics::new(0, None);
+        statistics.record(Time::new(0), &ResultEvent::Request(1));
+        statistics.record(Time::new(40), &ResultEvent::Executed(1));
+        statistics.record(Time::new(70), &ResultEvent::Completed(1));
+        statistics.record(Time::new(90), &ResultEvent::Completed(1));
+        statistics.record(Time::new(0), &ResultEvent::Request(2));
+        statistics.record(Time::new(30), &ResultEvent::Executed(2));
+
+        let summary = statistics.summary(Time::new(100));
+        assert_eq!(summary.done, 2);
+        assert_eq!(summary.max_latency, 70);
+        assert_eq!(summary.mean_latency, 50.0);
+
+        let latencies = statistics.latencies(Time::new(100));
+        assert_eq!(latencies.count(), 2);
+        assert_eq!(latencies.value_at_percentile(50.0), 30);
+        assert_eq!(latencies.value_at_percentile(100.0), 70);
+    }
+
+    #[test]
+    fn check_view_changes_and_reproposed_requests() {
+        let mut views = ViewStatistics::default();
+        let switched = |instance| ResultEvent::Switched {
+            instance,
+            protocol: "PBFT".to_string(),
+        };
+
+        views.record(Time::new(0), &ResultEvent::Request(1));
+        views.record(Time::new(100), &ResultEvent::Executed(1));
+        views.record(Time::new(200), &ResultEvent::Request(2));
+        views.record(Time::new(300), &ResultEvent::Request(3));
+        // the view is aborted with requests 2 and 3 pending, by one node after the other
+        views.record(Time::new(1000), &ResultEvent::Aborted { instance: 0 });
+        views.record(Time::new(1100), &ResultEvent::Aborted { instance: 0 });
+        views.record(Time::new(1500), &switched(1));
+        views.record(Time::new(1600), &switched(1));
+        views.record(Time::new(1500), &ResultEvent::Request(2));
+        views.record(Time::new(1700), &ResultEvent::Executed(2));
+        views.record(Time::new(1800), &ResultEvent::Executed(2));
+        // the next view is still being aborted at the end of the run
+        views.record(Time::new(2500), &ResultEvent::Aborted { instance: 1 });
+
+        let summary = views.summary(Time::new(3000));
+        assert_eq!(summary.view_changes, 1);
+        assert_eq!(summary.unstable_ms, 500 + 500);
+        assert_eq!(summary.aborted_requests, 2);
+        assert_eq!(summary.reproposed_requests, 1);
+        assert_eq!(
+            summary.views,
+            vec![
+                ViewPeriod {
+                    view: 0,
+                    duration: 1500,
+                    done: 1,
+                    throughput: 1000.0 / 1500.0,
+                },
+                ViewPeriod {
+                    view: 1,
+                    duration: 1500,
+                    done: 1,
+                    throughput: 1000.0 / 1500.0,
+                },
+            ]
+        );
+    }
+}
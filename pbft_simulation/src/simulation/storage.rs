@@ -0,0 +1,194 @@
+/***************************************************************************************************
+The stable storage of a replica whose protocol persists its log entries before it votes. Each node
+has a single disk that handles its writes one after the other, the latency of a write is random.
+With synchronous writes the node waits for a write before it sends the message it wrote, with
+asynchronous writes it sends right away and the writes only queue up at the disk.
+***************************************************************************************************/
+
+use std::str::FromStr;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+
+use crate::simulation::settings::StorageSettings;
+
+/// Whether a node waits for its writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum StorageMode {
+    /// The message is sent once it is on disk
+    Sync,
+    /// The message is sent right away, it is written in the background
+    Async,
+}
+
+impl FromStr for StorageMode {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "sync" => Ok(StorageMode::Sync),
+            "async" => Ok(StorageMode::Async),
+            _ => Err(format!(
+                "storage mode '{}' is not available, allowed are 'sync', 'async'",
+                mode
+            )),
+        }
+    }
+}
+
+/// The distribution of the latency of a single write
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum LatencyDistribution {
+    /// Uniform between the minimal and the maximal latency
+    Uniform,
+    /// The minimal latency plus an exponential tail, whose mean is the difference to the maximal
+    /// latency, so the maximal latency is the mean and not a bound
+    Exponential,
+}
+
+impl FromStr for LatencyDistribution {
+    type Err = String;
+
+    fn from_str(distribution: &str) -> Result<Self, Self::Err> {
+        match distribution {
+            "uniform" => Ok(LatencyDistribution::Uniform),
+            "exponential" => Ok(LatencyDistribution::Exponential),
+            _ => Err(format!(
+                "latency distribution '{}' is not available, allowed are 'uniform', 'exponential'",
+                distribution
+            )),
+        }
+    }
+}
+
+/// The disk of a single node
+#[derive(Debug)]
+pub struct Disk {
+    mode: StorageMode,
+    distribution: LatencyDistribution,
+    // latency (µs) of a write
+    latency_min: u64,
+    latency_max: u64,
+    // the disk is busy with the last write until then (µs)
+    busy_until: u64,
+    // number of writes so far
+    writes: u64,
+    // time (µs) the disk was busy with them
+    busy: u64,
+    rng: StdRng,
+}
+
+impl Disk {
+    pub fn new(settings: &StorageSettings) -> Self {
+        assert!(
+            settings.latency_min <= settings.latency_max,
+            "The minimal latency of a write must not exceed the maximal one"
+        );
+        Disk {
+            mode: settings.mode,
+            distribution: settings.distribution,
+            latency_min: settings.latency_min,
+            latency_max: settings.latency_max,
+            busy_until: 0,
+            writes: 0,
+            busy: 0,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Draws the latencies of the writes from `seed` instead of the entropy of the system
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Writes an entry handed to the disk at `now` (µs), after the writes before it. Returns the
+    /// time (µs) the node waits for it.
+    pub fn write(&mut self, now: u64) -> u64 {
+        let latency = self.latency();
+        self.busy_until = self.busy_until.max(now) + latency;
+        self.writes += 1;
+        self.busy += latency;
+
+        match self.mode {
+            StorageMode::Sync => self.busy_until - now,
+            StorageMode::Async => 0,
+        }
+    }
+
+    /// Number of writes so far
+    pub fn writes(&self) -> u64 {
+        self.writes
+    }
+
+    /// Time (µs) the disk was busy with the writes so far
+    pub fn busy(&self) -> u64 {
+        self.busy
+    }
+
+    // draws the latency (µs) of a write
+    fn latency(&mut self) -> u64 {
+        if self.latency_min == self.latency_max {
+            return self.latency_min;
+        }
+        match self.distribution {
+            LatencyDistribution::Uniform => {
+                self.rng.gen_range(self.latency_min, self.latency_max + 1)
+            }
+            LatencyDistribution::Exponential => {
+                let mean = (self.latency_max - self.latency_min) as f64;
+                let tail = -mean * (1.0 - self.rng.gen::<f64>()).ln();
+                self.latency_min + tail.round() as u64
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::NodeType;
+
+    fn settings(mode: StorageMode, latency_min: u64, latency_max: u64) -> StorageSettings {
+        StorageSettings {
+            protocols: vec![NodeType::PBFT],
+            mode,
+            distribution: LatencyDistribution::Uniform,
+            latency_min,
+            latency_max,
+        }
+    }
+
+    #[test]
+    fn check_writes_queue_up_at_the_disk() {
+        let mut disk = Disk::new(&settings(StorageMode::Sync, 300, 300));
+        assert_eq!(disk.write(1000), 300);
+        // the second write waits for the first one
+        assert_eq!(disk.write(1100), 500);
+        assert_eq!(disk.write(5000), 300);
+        assert_eq!((disk.writes(), disk.busy()), (3, 900));
+
+        let mut disk = Disk::new(&settings(StorageMode::Async, 300, 300));
+        assert_eq!(disk.write(1000), 0);
+        assert_eq!(disk.write(1100), 0);
+        assert_eq!(disk.busy(), 600);
+        assert_eq!("async".parse(), Ok(StorageMode::Async));
+        assert!("fsync".parse::<StorageMode>().is_err());
+    }
+
+    #[test]
+    fn check_latency_distributions() {
+        let mut disk = Disk::new(&settings(StorageMode::Sync, 100, 200));
+        assert!((0..100)
+            .map(|_| disk.latency())
+            .all(|l| (100..=200).contains(&l)));
+
+        let mut exponential = settings(StorageMode::Sync, 100, 200);
+        exponential.distribution = LatencyDistribution::Exponential;
+        let mut disk = Disk::new(&exponential);
+        let latencies: Vec<u64> = (0..10_000).map(|_| disk.latency()).collect();
+        assert!(latencies.iter().all(|l| *l >= 100));
+        let mean = latencies.iter().sum::<u64>() as f64 / latencies.len() as f64;
+        assert!((mean - 200.0).abs() < 10.0, "mean latency {}", mean);
+    }
+}
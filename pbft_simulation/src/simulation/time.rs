@@ -1,81 +1,286 @@
 /***************************************************************************************************
-Everything related to time.
+Everything related to time. A `Time` is a point of the simulated time, a `Duration` the span between
+two of them. Adding and subtracting saturate at the start and the end of the simulated time, the
+checked variants report an overflow or underflow instead.
 ***************************************************************************************************/
 
-use std::cmp::{Ord, Ordering};
-use std::ops::{Add, Sub};
+use std::fmt;
+use std::ops::{Add, AddAssign, Sub};
 
-/// The time abstraction used in the simulation.
-/// This struct is used as the sorting parameter for the events in the queue.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+use serde::{Deserialize, Serialize};
+
+/// The time abstraction used in the simulation, kept in microseconds so datacenter latencies and
+/// the costs of the crypto don't round to whole milliseconds. The settings, the timers of the nodes
+/// and the result log stay in milliseconds.
+/// Earlier times are smaller, the event queue reverses the order to handle them first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Time {
-    milli_seconds: u64,
+    micro_seconds: u64,
+}
+
+/// A span of simulated time, in microseconds like `Time`
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct Duration {
+    micro_seconds: u64,
+}
+
+/// Arithmetic that left the range of the simulated time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeError {
+    /// The result is beyond the end of the simulated time
+    Overflow,
+    /// The result is before the start of the simulation
+    Underflow,
 }
 
 impl Time {
+    /// The start of the simulation
+    pub const ZERO: Time = Time { micro_seconds: 0 };
+
+    /// The time `milli_seconds` ms after the start, same as `Time::from_millis`
     pub fn new(milli_seconds: u64) -> Self {
-        Time { milli_seconds }
+        Time::from_millis(milli_seconds)
     }
 
-    pub fn add_milli(self, milli: u64) -> Time {
-        Time {
-            milli_seconds: self.milli_seconds + milli,
-        }
+    /// The time `milli_seconds` ms after the start, saturating at the end of the simulated time
+    pub fn from_millis(milli_seconds: u64) -> Self {
+        Time::ZERO + Duration::from_millis(milli_seconds)
     }
 
-    pub fn sub_milli(self, milli: u64) -> Time {
-        Time {
-            milli_seconds: self.milli_seconds - milli,
+    pub fn from_micros(micro_seconds: u64) -> Self {
+        Time { micro_seconds }
+    }
+
+    /// The whole milliseconds since the start
+    pub fn milli(&self) -> u64 {
+        self.micro_seconds / 1000
+    }
+
+    pub fn micros(&self) -> u64 {
+        self.micro_seconds
+    }
+
+    /// The time since the start of the simulation
+    pub fn since_start(self) -> Duration {
+        Duration::from_micros(self.micro_seconds)
+    }
+
+    pub fn checked_add(self, duration: Duration) -> Result<Time, TimeError> {
+        self.micro_seconds
+            .checked_add(duration.micro_seconds)
+            .map(Time::from_micros)
+            .ok_or(TimeError::Overflow)
+    }
+
+    pub fn checked_sub(self, duration: Duration) -> Result<Time, TimeError> {
+        self.micro_seconds
+            .checked_sub(duration.micro_seconds)
+            .map(Time::from_micros)
+            .ok_or(TimeError::Underflow)
+    }
+
+    pub fn saturating_add(self, duration: Duration) -> Time {
+        Time::from_micros(self.micro_seconds.saturating_add(duration.micro_seconds))
+    }
+
+    pub fn saturating_sub(self, duration: Duration) -> Time {
+        Time::from_micros(self.micro_seconds.saturating_sub(duration.micro_seconds))
+    }
+
+    /// The time from `earlier` until this time, an error if `earlier` is after it
+    pub fn checked_duration_since(self, earlier: Time) -> Result<Duration, TimeError> {
+        self.micro_seconds
+            .checked_sub(earlier.micro_seconds)
+            .map(Duration::from_micros)
+            .ok_or(TimeError::Underflow)
+    }
+
+    /// The time from `earlier` until this time, zero if `earlier` is after it
+    pub fn duration_since(self, earlier: Time) -> Duration {
+        self.checked_duration_since(earlier).unwrap_or_default()
+    }
+}
+
+impl Duration {
+    pub const ZERO: Duration = Duration { micro_seconds: 0 };
+
+    /// `milli_seconds` ms, saturating at the longest duration
+    pub fn from_millis(milli_seconds: u64) -> Self {
+        Duration {
+            micro_seconds: milli_seconds.saturating_mul(1000),
         }
     }
 
+    pub fn from_micros(micro_seconds: u64) -> Self {
+        Duration { micro_seconds }
+    }
+
+    /// The whole milliseconds of the duration
     pub fn milli(&self) -> u64 {
-        self.milli_seconds
+        self.micro_seconds / 1000
+    }
+
+    pub fn micros(&self) -> u64 {
+        self.micro_seconds
+    }
+
+    pub fn checked_add(self, other: Duration) -> Result<Duration, TimeError> {
+        self.micro_seconds
+            .checked_add(other.micro_seconds)
+            .map(Duration::from_micros)
+            .ok_or(TimeError::Overflow)
+    }
+
+    pub fn checked_sub(self, other: Duration) -> Result<Duration, TimeError> {
+        self.micro_seconds
+            .checked_sub(other.micro_seconds)
+            .map(Duration::from_micros)
+            .ok_or(TimeError::Underflow)
+    }
+
+    pub fn saturating_add(self, other: Duration) -> Duration {
+        Duration::from_micros(self.micro_seconds.saturating_add(other.micro_seconds))
+    }
+
+    pub fn saturating_sub(self, other: Duration) -> Duration {
+        Duration::from_micros(self.micro_seconds.saturating_sub(other.micro_seconds))
     }
 }
 
-impl Sub for Time {
+/// Saturates at the end of the simulated time, see `Time::checked_add`
+impl Add<Duration> for Time {
     type Output = Time;
 
-    fn sub(self, other: Time) -> Time {
-        Time {
-            milli_seconds: self.milli_seconds - other.milli_seconds,
-        }
+    fn add(self, duration: Duration) -> Time {
+        self.saturating_add(duration)
     }
 }
 
-impl Add for Time {
+/// Saturates at the start of the simulation, see `Time::checked_sub`
+impl Sub<Duration> for Time {
     type Output = Time;
 
-    fn add(self, other: Time) -> Time {
-        Time {
-            milli_seconds: self.milli_seconds + other.milli_seconds,
-        }
+    fn sub(self, duration: Duration) -> Time {
+        self.saturating_sub(duration)
+    }
+}
+
+/// The duration between two times, zero if `other` is the later one
+impl Sub for Time {
+    type Output = Duration;
+
+    fn sub(self, other: Time) -> Duration {
+        self.duration_since(other)
+    }
+}
+
+impl Add for Duration {
+    type Output = Duration;
+
+    fn add(self, other: Duration) -> Duration {
+        self.saturating_add(other)
+    }
+}
+
+impl AddAssign for Duration {
+    fn add_assign(&mut self, other: Duration) {
+        *self = self.saturating_add(other);
+    }
+}
+
+impl Sub for Duration {
+    type Output = Duration;
+
+    fn sub(self, other: Duration) -> Duration {
+        self.saturating_sub(other)
+    }
+}
+
+// the result log and the debug logs are in whole milliseconds
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.milli())
     }
 }
 
-// We have to reverse the ordering, because the binary tree sorts with max first
-impl Ord for Time {
-    fn cmp(&self, other: &Self) -> Ordering {
-        if self.milli_seconds.ge(&other.milli_seconds) {
-            Ordering::Less
-        } else if self.milli_seconds.le(&other.milli_seconds) {
-            Ordering::Greater
+// seconds with the milliseconds, the microseconds are shown if there are any, e.g. "12.345s"
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (seconds, micros) = (
+            self.micro_seconds / 1_000_000,
+            self.micro_seconds % 1_000_000,
+        );
+        if micros % 1000 == 0 {
+            write!(f, "{}.{:03}s", seconds, micros / 1000)
         } else {
-            Ordering::Equal
+            write!(f, "{}.{:06}s", seconds, micros)
         }
     }
 }
 
-// I have no idea why i can't derive PartialOrd, but the tests fail if i do. So here it is
-impl PartialOrd for Time {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl fmt::Display for TimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimeError::Overflow => write!(f, "time is beyond the end of the simulated time"),
+            TimeError::Underflow => write!(f, "time is before the start of the simulation"),
+        }
     }
 }
 
-impl ToString for Time {
-    fn to_string(&self) -> String {
-        self.milli_seconds.to_string()
+impl std::error::Error for TimeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_time_keeps_microseconds() {
+        let time = Time::from_millis(2) + Duration::from_micros(250);
+        assert_eq!(time.micros(), 2250);
+        assert_eq!(time.milli(), 2);
+        assert_eq!(time.to_string(), "2");
+        assert_eq!(Time::new(2), Time::from_micros(2000));
+        assert_eq!((time - Duration::from_micros(300)).micros(), 1950);
+        assert!(time > Time::from_micros(2249));
+        assert!(time < Time::from_micros(2251));
+    }
+
+    #[test]
+    fn check_arithmetic_saturates_or_reports() {
+        let time = Time::new(10);
+        assert_eq!(time - Duration::from_millis(20), Time::ZERO);
+        assert_eq!(
+            time.checked_sub(Duration::from_millis(20)),
+            Err(TimeError::Underflow)
+        );
+        assert_eq!(
+            time.checked_add(Duration::from_millis(5)),
+            Ok(Time::new(15))
+        );
+
+        let end = Time::from_micros(u64::MAX);
+        assert_eq!(end + Duration::from_micros(1), end);
+        assert_eq!(
+            end.checked_add(Duration::from_micros(1)),
+            Err(TimeError::Overflow)
+        );
+        assert_eq!(Time::from_millis(u64::MAX), end);
+
+        assert_eq!(Time::new(30) - Time::new(10), Duration::from_millis(20));
+        assert_eq!(Time::new(10) - Time::new(30), Duration::ZERO);
+        assert_eq!(
+            Time::new(10).checked_duration_since(Time::new(30)),
+            Err(TimeError::Underflow)
+        );
+    }
+
+    #[test]
+    fn check_durations_are_shown_in_seconds() {
+        assert_eq!(Duration::from_millis(12_345).to_string(), "12.345s");
+        assert_eq!(Duration::from_micros(250).to_string(), "0.000250s");
+        assert_eq!(Duration::ZERO.to_string(), "0.000s");
+        assert_eq!(Time::new(1500).since_start().to_string(), "1.500s");
     }
 }
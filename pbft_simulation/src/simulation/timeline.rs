@@ -0,0 +1,246 @@
+/***************************************************************************************************
+Gantt-style timeline of a finished run: one lane per node showing its sends, receptions and phase
+transitions over the simulated time, rendered as SVG embedded in a HTML page. The sends and
+receptions are taken from a causality trace, the phases from the result log.
+***************************************************************************************************/
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use crate::simulation::causality::MessageRecord;
+use crate::simulation::results::ResultEvent;
+
+const WIDTH: f64 = 1400.0;
+const LANE_HEIGHT: f64 = 40.0;
+const LABEL_WIDTH: f64 = 80.0;
+
+/// Something that happened on a node
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarkKind {
+    Send { to: u32, message_type: String },
+    Reception { from: u32, message_type: String },
+    Phase(String),
+}
+
+/// A mark on the lane of a node
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mark {
+    pub time: u64,
+    pub request_id: u64,
+    pub kind: MarkKind,
+}
+
+/// The lanes of all nodes
+#[derive(Debug, Default)]
+pub struct Timeline {
+    lanes: BTreeMap<u32, Vec<Mark>>,
+    // only marks of this request are added, if set
+    request: Option<u64>,
+}
+
+impl Timeline {
+    /// Creates a timeline that only shows the request `request`, or all requests if `None`
+    pub fn new(request: Option<u64>) -> Self {
+        Timeline {
+            lanes: BTreeMap::new(),
+            request,
+        }
+    }
+
+    fn add(&mut self, node: u32, mark: Mark) {
+        if self.request.is_none_or(|r| r == mark.request_id) {
+            self.lanes.entry(node).or_default().push(mark);
+        }
+    }
+
+    /// Adds the sends and receptions of the messages of a causality trace
+    pub fn add_messages(&mut self, records: &[MessageRecord]) {
+        for r in records {
+            self.add(
+                r.from,
+                Mark {
+                    time: r.sent,
+                    request_id: r.request_id,
+                    kind: MarkKind::Send {
+                        to: r.to,
+                        message_type: r.message_type.clone(),
+                    },
+                },
+            );
+            if let Some(received) = r.received {
+                self.add(
+                    r.to,
+                    Mark {
+                        time: received,
+                        request_id: r.request_id,
+                        kind: MarkKind::Reception {
+                            from: r.from,
+                            message_type: r.message_type.clone(),
+                        },
+                    },
+                );
+            }
+        }
+    }
+
+    /// Adds the phase transitions of a result log, i.e. all lines 'time;node;request id;phase'
+    pub fn add_result_log(&mut self, log: &str) {
+        for line in log.lines() {
+            let fields: Vec<&str> = line.trim().splitn(3, ';').collect();
+            if fields.len() != 3 {
+                continue;
+            }
+            let event = ResultEvent::parse(fields[2]);
+            if let (Ok(time), Ok(node), Some((request_id, phase))) = (
+                fields[0].parse(),
+                fields[1].parse(),
+                event.as_ref().and_then(ResultEvent::request_phase),
+            ) {
+                self.add(
+                    node,
+                    Mark {
+                        time,
+                        request_id,
+                        kind: MarkKind::Phase(phase.to_string()),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Returns the marks on the lane of `node`
+    pub fn lane(&self, node: u32) -> &[Mark] {
+        self.lanes.get(&node).map_or(&[], |marks| marks.as_slice())
+    }
+
+    fn end_time(&self) -> u64 {
+        self.lanes
+            .values()
+            .flatten()
+            .map(|m| m.time)
+            .max()
+            .unwrap_or(0)
+            .max(1)
+    }
+
+    /// Renders the timeline as SVG
+    pub fn to_svg(&self) -> String {
+        let scale = (WIDTH - LABEL_WIDTH) / self.end_time() as f64;
+        let height = LANE_HEIGHT * (self.lanes.len() as f64 + 1.0);
+        let x = |time: u64| LABEL_WIDTH + time as f64 * scale;
+
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"monospace\" font-size=\"11\">",
+            WIDTH, height
+        );
+
+        for (i, (node, marks)) in self.lanes.iter().enumerate() {
+            let y = LANE_HEIGHT * (i as f64 + 0.5);
+            let _ = writeln!(svg, "<text x=\"4\" y=\"{}\">node {}</text>", y + 4.0, node);
+            let _ = writeln!(
+                svg,
+                "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#ccc\"/>",
+                LABEL_WIDTH, y, WIDTH, y
+            );
+
+            for mark in marks {
+                let mx = x(mark.time);
+                match &mark.kind {
+                    MarkKind::Send { to, message_type } => {
+                        let _ = writeln!(
+                            svg,
+                            "<rect x=\"{:.1}\" y=\"{}\" width=\"3\" height=\"10\" fill=\"#1f77b4\"><title>{}ms: request {} {} to {}</title></rect>",
+                            mx, y - 12.0, mark.time, mark.request_id, message_type, to
+                        );
+                    }
+                    MarkKind::Reception { from, message_type } => {
+                        let _ = writeln!(
+                            svg,
+                            "<circle cx=\"{:.1}\" cy=\"{}\" r=\"3\" fill=\"#2ca02c\"><title>{}ms: request {} {} from {}</title></circle>",
+                            mx, y + 6.0, mark.time, mark.request_id, message_type, from
+                        );
+                    }
+                    MarkKind::Phase(phase) => {
+                        let _ = writeln!(
+                            svg,
+                            "<line x1=\"{:.1}\" y1=\"{}\" x2=\"{:.1}\" y2=\"{}\" stroke=\"#d62728\" stroke-width=\"2\"><title>{}ms: request {} {}</title></line>",
+                            mx, y - 16.0, mx, y + 16.0, mark.time, mark.request_id, phase
+                        );
+                    }
+                }
+            }
+        }
+
+        // time axis
+        let axis = LANE_HEIGHT * (self.lanes.len() as f64 + 0.5);
+        for tick in 0..=10 {
+            let time = self.end_time() * tick / 10;
+            let _ = writeln!(
+                svg,
+                "<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\">{}ms</text>",
+                x(time),
+                axis,
+                time
+            );
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Renders the timeline as a standalone HTML page
+    pub fn to_html(&self, title: &str) -> String {
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{}</title></head>\n<body>\n<h3>{}</h3>\n<p>blue: send, green: reception, red: phase transition (hover for details)</p>\n{}</body>\n</html>\n",
+            title,
+            title,
+            self.to_svg()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_timeline_lanes() {
+        let records = vec![MessageRecord {
+            request_id: 1,
+            message_type: "PrePrepare".to_string(),
+            from: 1,
+            to: 2,
+            sent: 5,
+            received: Some(60),
+        }];
+        let log =
+            "5;1;1;request\n61;2;1;pre-prepared\n70;2;2;pre-prepared\n90;-1;Simulation finished\n";
+
+        let mut timeline = Timeline::new(Some(1));
+        timeline.add_messages(&records);
+        timeline.add_result_log(log);
+
+        assert_eq!(timeline.lane(1).len(), 2);
+        assert_eq!(
+            timeline.lane(2),
+            &[
+                Mark {
+                    time: 60,
+                    request_id: 1,
+                    kind: MarkKind::Reception {
+                        from: 1,
+                        message_type: "PrePrepare".to_string()
+                    },
+                },
+                Mark {
+                    time: 61,
+                    request_id: 1,
+                    kind: MarkKind::Phase("pre-prepared".to_string()),
+                },
+            ]
+        );
+        assert_eq!(timeline.to_svg().matches("<title>").count(), 4);
+    }
+}
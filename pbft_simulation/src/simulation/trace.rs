@@ -0,0 +1,108 @@
+/***************************************************************************************************
+Traces of events, written as JSON with one event per line. A trace can be loaded again for replays
+or analysed with external tools.
+***************************************************************************************************/
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::Value;
+
+use crate::simulation::event::Event;
+
+/// Writes the `events` to `writer`, one JSON object per line
+pub fn write_trace<W: Write>(mut writer: W, events: &[Event]) -> io::Result<()> {
+    for event in events {
+        serde_json::to_writer(&mut writer, event)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+}
+
+/// Reads a trace written by `write_trace`, empty lines are skipped. Traces written while the
+/// simulated time was kept in milliseconds are converted.
+pub fn read_trace<R: BufRead>(reader: R) -> io::Result<Vec<Event>> {
+    let invalid_data = |e| io::Error::new(io::ErrorKind::InvalidData, e);
+    let mut events = Vec::new();
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut value = serde_json::from_str(&line).map_err(invalid_data)?;
+        convert_milli_seconds(&mut value);
+        let event = serde_json::from_value(value).map_err(invalid_data)?;
+        events.push(event);
+    }
+
+    Ok(events)
+}
+
+// replaces the times '{"milli_seconds": <ms>}' of old traces by '{"micro_seconds": <ms * 1000>}'
+fn convert_milli_seconds(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            let milli_seconds = match map.get("milli_seconds") {
+                Some(milli_seconds) if map.len() == 1 => milli_seconds.as_u64(),
+                _ => None,
+            };
+            match milli_seconds {
+                Some(milli_seconds) => {
+                    map.clear();
+                    map.insert(
+                        "micro_seconds".to_string(),
+                        Value::from(milli_seconds.saturating_mul(1000)),
+                    );
+                }
+                None => map.values_mut().for_each(convert_milli_seconds),
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(convert_milli_seconds),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::pbft::messages::{ClientRequest, PBFTMessage};
+    use crate::node::request::RequestId;
+    use crate::simulation::event::Message;
+    use crate::simulation::time::Time;
+
+    #[test]
+    fn check_trace_round_trip() {
+        let message = Message::PBFT(PBFTMessage::ClientRequest(ClientRequest::new(
+            RequestId::new(0, 7),
+            7,
+        )));
+        let events = vec![
+            Event::new_broadcast_sized(1, 2, message.clone(), Time::new(5), 28),
+            Event::new_reception(2, message, Time::new(10)),
+            Event::new_timer_request(3, 7, 400, Time::new(12)),
+            Event::new_admin_stop(),
+        ];
+
+        let mut buffer = Vec::new();
+        write_trace(&mut buffer, &events).unwrap();
+        assert_eq!(read_trace(&buffer[..]).unwrap(), events);
+    }
+
+    #[test]
+    fn check_traces_in_milliseconds_are_converted() {
+        let events = vec![
+            Event::new_timer_request(3, 7, 400, Time::new(12)),
+            Event::new_admin_stop_at(Time::new(1500)),
+        ];
+        let mut buffer = Vec::new();
+        write_trace(&mut buffer, &events).unwrap();
+
+        // the times as written before they were kept in microseconds
+        let old = String::from_utf8(buffer)
+            .unwrap()
+            .replace(r#"{"micro_seconds":12000}"#, r#"{"milli_seconds":12}"#)
+            .replace(r#"{"micro_seconds":1500000}"#, r#"{"milli_seconds":1500}"#);
+        assert!(!old.contains("micro_seconds"));
+        assert_eq!(read_trace(old.as_bytes()).unwrap(), events);
+    }
+}
@@ -0,0 +1,67 @@
+/***************************************************************************************************
+The wall clock of the process, as opposed to the simulated time. It paces the simulation, measures
+pauses and stamps the ids of the runs. On wasm32-unknown-unknown the standard library cannot read
+the clock of the browser, so with the feature 'wasm' the wall clock stands still: runs are not
+paced, pauses take no time and the ids of the runs start at 0.
+***************************************************************************************************/
+
+#[cfg(not(feature = "wasm"))]
+pub use std::time::Instant;
+
+#[cfg(feature = "wasm")]
+pub use still::Instant;
+
+/// Seconds since the Unix epoch, 0 if the clock is not available
+pub fn unix_seconds() -> u64 {
+    #[cfg(not(feature = "wasm"))]
+    {
+        use std::time::{SystemTime, UNIX_EPOCH};
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+    }
+    #[cfg(feature = "wasm")]
+    {
+        0
+    }
+}
+
+#[cfg(feature = "wasm")]
+mod still {
+    use std::ops::{Add, Sub};
+    use std::time::Duration;
+
+    /// A point in time of a clock that stands still, all of them are the same
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct Instant;
+
+    impl Instant {
+        pub fn now() -> Self {
+            Instant
+        }
+
+        pub fn elapsed(&self) -> Duration {
+            Duration::from_secs(0)
+        }
+
+        pub fn duration_since(&self, _earlier: Instant) -> Duration {
+            Duration::from_secs(0)
+        }
+    }
+
+    impl Add<Duration> for Instant {
+        type Output = Instant;
+
+        fn add(self, _duration: Duration) -> Instant {
+            Instant
+        }
+    }
+
+    impl Sub for Instant {
+        type Output = Duration;
+
+        fn sub(self, _earlier: Instant) -> Duration {
+            Duration::from_secs(0)
+        }
+    }
+}
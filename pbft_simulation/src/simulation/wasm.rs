@@ -0,0 +1,149 @@
+/***************************************************************************************************
+The simulation in the browser, only built with the feature 'wasm' for wasm32-unknown-unknown, e.g.
+with 'cargo rustc --lib --release --target wasm32-unknown-unknown --no-default-features --features
+wasm --crate-type cdylib' and 'wasm-bindgen --target web' on the resulting module, the library is
+an rlib otherwise. There are no threads and no files:
+the page steps through the events itself, the commands of the control socket are handled before
+the next step and the result lines are handed to the page instead of a result log.
+
+    const simulation = new WasmSimulation("node.nodes_vec=4\nsimulation.requests=10");
+    let event;
+    while ((event = simulation.step()) !== undefined) { draw(JSON.parse(event)); }
+***************************************************************************************************/
+
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+use crate::simulation::config::{try_load_config, RequestBatchConfig, SimulationConfig};
+use crate::simulation::control::{parse_command, Command};
+use crate::simulation::event::{Event, EventType};
+use crate::simulation::report::SimulationReport;
+use crate::simulation::settings::Settings;
+use crate::simulation::{Simulation, Step};
+
+/// A simulation stepped through by the page
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct WasmSimulation {
+    simulation: Simulation,
+    /// The report, once the run is finished
+    report: Option<SimulationReport>,
+}
+
+#[wasm_bindgen]
+impl WasmSimulation {
+    /// A simulation of the overrides 'section.key=value', one per line, the configured client
+    /// requests are issued from the start. Threads, pacing and all files of the run are disabled.
+    #[wasm_bindgen(constructor)]
+    pub fn new(overrides: &str) -> Result<WasmSimulation, JsValue> {
+        let overrides = overrides
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string);
+        let values = try_load_config(overrides).map_err(|e| JsValue::from_str(&e))?;
+        let mut settings = Settings::from_values(&values);
+        settings.simulation.threads = 1;
+        settings.simulation.speedup = None;
+        settings.result_log = false;
+        settings.stats.hgrm = false;
+        settings.trace.causality = false;
+        settings.trace.message_flow = false;
+
+        let requests = RequestBatchConfig::new(
+            settings.simulation.requests,
+            settings.simulation.request_interval,
+        );
+        let mut simulation = Simulation::new(SimulationConfig::new(settings));
+        simulation.results.collect();
+        simulation.add_event_to_queue(Event::new_admin_requests_from_config(requests));
+        simulation.start();
+        Ok(WasmSimulation {
+            simulation,
+            report: None,
+        })
+    }
+
+    /// Handles the next event and returns it as JSON, `undefined` if the simulation is paused or
+    /// the run is finished
+    pub fn step(&mut self) -> Option<String> {
+        if self.report.is_some() {
+            return None;
+        }
+        let mut handled = None;
+        let step = self
+            .simulation
+            .step_with(|event| handled = serde_json::to_string(event).ok());
+        match step {
+            Step::Handled => handled,
+            Step::Paused => None,
+            Step::Empty | Step::Stopped => {
+                self.report = Some(self.simulation.finish());
+                None
+            }
+        }
+    }
+
+    /// Handles up to `events` events without returning them, e.g. to skip ahead, and returns how
+    /// many were handled
+    pub fn run(&mut self, events: u32) -> u32 {
+        let mut handled = 0;
+        while handled < events && self.step().is_some() {
+            handled += 1;
+        }
+        handled
+    }
+
+    /// Runs a command of the control socket, e.g. 'crash-node 3', before the next step
+    pub fn command(&self, line: &str) -> Result<(), JsValue> {
+        match parse_command(line) {
+            Ok(Command::Admin(admin_type)) => self
+                .simulation
+                .get_sender()
+                .send(EventType::Admin(admin_type))
+                .map_err(|e| JsValue::from_str(&e.to_string())),
+            Ok(_) => Err(JsValue::from_str(
+                "only the commands steering the simulation are supported",
+            )),
+            Err(e) => Err(JsValue::from_str(&e)),
+        }
+    }
+
+    /// The simulated time (ms)
+    pub fn time_ms(&self) -> f64 {
+        self.simulation.time.milli() as f64
+    }
+
+    /// Number of events handled so far
+    pub fn events(&self) -> f64 {
+        self.simulation.events as f64
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.report.is_some()
+    }
+
+    /// The lines of the result log since the last call, 'time;node id (-1 for none);message', one
+    /// per line
+    pub fn take_results(&self) -> String {
+        self.simulation.results.drain_lines().join("\n")
+    }
+
+    /// The report as JSON, `undefined` until the run is finished
+    pub fn report(&self) -> Option<String> {
+        let report = self.report.as_ref()?;
+        let requests = &report.requests;
+        let report = json!({
+            "end_time_ms": report.end_time.milli(),
+            "events": report.events,
+            "divergence": report.agreement.as_ref().err().map(|d| d.to_string()),
+            "issued": requests.issued,
+            "done": requests.done,
+            "mean_latency_ms": requests.mean_latency,
+            "max_latency_ms": requests.max_latency,
+            "throughput": requests.throughput,
+            "overdue": report.overdue,
+        });
+        Some(report.to_string())
+    }
+}
@@ -0,0 +1,132 @@
+/***************************************************************************************************
+Protocol conformance: replays the canonical scenarios in 'scenarios' with PBFT and Zyzzyva and
+compares the lines of their result logs, 'time;node id (-1 for none);message', with the golden
+traces in 'golden'. A refactoring that changes how a protocol reacts to the scenarios, e.g. which
+phases a replica reaches or when, fails these tests instead of going unnoticed.
+
+The network and the randomness are fixed, so a run yields the same trace every time. After an
+intended change of a protocol the traces are recorded anew with
+
+    UPDATE_GOLDEN=1 cargo test --test conformance
+
+and the differences are reviewed with the change. A missing trace fails the test, so a checkout
+without the traces compares nothing unnoticed.
+
+Every scenario lets the replicas finish all requests. Neither protocol implements a view change, so
+a crash of the primary would only stall them and is left out.
+***************************************************************************************************/
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bft_simulation::node::NodeType;
+use bft_simulation::simulation::run_scenario;
+use bft_simulation::simulation::scenario::Scenario;
+use bft_simulation::simulation::settings::Settings;
+
+// the directory of this test suite
+fn suite_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/conformance")
+}
+
+// 4 replicas over links of constant delay, the replicas of PBFT and Zyzzyva tolerate one fault
+fn settings(node_type: NodeType) -> Settings {
+    let mut settings = Settings::default();
+    settings.node.node_type = node_type;
+    settings.node.nodes = 4;
+    settings.simulation.seed = Some(42);
+    settings.network.delay_min = 50.0;
+    settings.network.delay_max = 50.0;
+    settings.result_log = false;
+    settings
+}
+
+// the result log of `scenario` run by the replicas of `node_type`, which finish all requests
+fn trace(node_type: NodeType, scenario: &str) -> Vec<String> {
+    let path = suite_dir()
+        .join("scenarios")
+        .join(format!("{}.json", scenario));
+    let scenario = Scenario::from_file(&path)
+        .unwrap_or_else(|e| panic!("Failed to read the scenario {}: {}", path.display(), e));
+    let report = run_scenario(settings(node_type), scenario);
+    assert_eq!(report.agreement, Ok(()), "the replicas diverged");
+    assert_eq!(report.done_requests(), (1..=5).collect(), "requests are stuck");
+    assert!(report.overdue.is_empty(), "requests are overdue");
+    report.results
+}
+
+// compares the trace with the golden one of `name`, records it if it is updated
+fn check_golden(name: &str, trace: &[String]) {
+    let path = suite_dir().join("golden").join(format!("{}.trace", name));
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut content = trace.join("\n");
+        content.push('\n');
+        fs::write(&path, content).unwrap();
+        eprintln!("Recorded the golden trace {}", path.display());
+        return;
+    }
+
+    let golden = fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "Failed to read the golden trace {}: {}\n\
+             Run with UPDATE_GOLDEN=1 to record it",
+            path.display(),
+            e
+        )
+    });
+    let golden: Vec<&str> = golden.lines().collect();
+    if let Some(i) = (0..golden.len().max(trace.len()))
+        .find(|&i| golden.get(i).copied() != trace.get(i).map(String::as_str))
+    {
+        panic!(
+            "The trace of {} deviates from {} at line {}:\n  golden: {}\n  actual: {}\n\
+             Run with UPDATE_GOLDEN=1 to record it anew if the change is intended",
+            name,
+            path.display(),
+            i + 1,
+            golden.get(i).copied().unwrap_or("<end of trace>"),
+            trace.get(i).map_or("<end of trace>", String::as_str)
+        );
+    }
+}
+
+fn check_scenario(node_type: NodeType, protocol: &str, scenario: &str) {
+    let trace = trace(node_type, scenario);
+    assert!(!trace.is_empty(), "{} logged no results", protocol);
+    // the run is deterministic, otherwise no golden trace could hold
+    assert_eq!(trace, self::trace(node_type, scenario));
+    check_golden(&format!("{}_{}", protocol, scenario), &trace);
+}
+
+#[test]
+fn check_pbft_happy_path() {
+    check_scenario(NodeType::PBFT, "pbft", "happy_path");
+}
+
+#[test]
+fn check_pbft_backup_failure() {
+    check_scenario(NodeType::PBFT, "pbft", "backup_failure");
+}
+
+#[test]
+fn check_pbft_link_loss() {
+    check_scenario(NodeType::PBFT, "pbft", "link_loss");
+}
+
+#[test]
+fn check_zyzzyva_happy_path() {
+    check_scenario(NodeType::Zyzzyva, "zyzzyva", "happy_path");
+}
+
+#[test]
+fn check_zyzzyva_backup_failure() {
+    check_scenario(NodeType::Zyzzyva, "zyzzyva", "backup_failure");
+}
+
+#[test]
+fn check_zyzzyva_link_loss() {
+    check_scenario(NodeType::Zyzzyva, "zyzzyva", "link_loss");
+}
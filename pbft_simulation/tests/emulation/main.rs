@@ -0,0 +1,31 @@
+/***************************************************************************************************
+Runs over real TCP connections, see 'src/emulation.rs'. The nodes bind sockets on localhost and the
+timers expire in real time, so the tests are left out of the default run and started with
+
+    cargo test --test emulation -- --ignored
+***************************************************************************************************/
+
+use std::time::Duration;
+
+use bft_simulation::emulation::run_emulation;
+use bft_simulation::node::NodeType;
+use bft_simulation::simulation::settings::Settings;
+
+#[test]
+#[ignore = "binds TCP sockets on localhost"]
+/// Check that the nodes of the simulation commit their requests over real TCP connections
+fn check_emulation_commits_requests_over_tcp() {
+    for &node_type in &[NodeType::PBFT, NodeType::Zyzzyva, NodeType::SBFT] {
+        let mut settings = Settings::default();
+        settings.node.node_type = node_type;
+        settings.node.nodes = 5;
+        settings.node.processing_delay = 0;
+        settings.simulation.requests = 3;
+        settings.simulation.request_interval = 50;
+
+        let report = run_emulation(settings, Duration::from_secs(20)).unwrap();
+        assert_eq!(report.agreement, Ok(()));
+        assert_eq!(report.done_requests(), (1..=3).collect());
+        assert!(report.events > 0);
+    }
+}
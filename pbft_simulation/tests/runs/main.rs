@@ -0,0 +1,637 @@
+/***************************************************************************************************
+Whole runs of the protocols without an ini: each test changes the default settings, runs the
+simulation to completion and checks its report, e.g. that the replicas agree and executed every
+request, or how a setting changes the traffic and the latencies.
+***************************************************************************************************/
+
+use std::collections::{HashMap, HashSet};
+
+use bft_simulation::network::compression::Compression;
+use bft_simulation::network::conditions::{LinkCondition, ScheduledCondition};
+use bft_simulation::node::pbft::messages::Dissemination;
+use bft_simulation::node::pbft::state::LeaderRotation;
+use bft_simulation::node::NodeType;
+use bft_simulation::simulation::report::SimulationReport;
+use bft_simulation::simulation::run_simulation;
+use bft_simulation::simulation::scheduler::SchedulerKind;
+use bft_simulation::simulation::settings::{ClusterSettings, Settings};
+use bft_simulation::simulation::storage::StorageMode;
+use bft_simulation::simulation::time::{Duration, Time};
+
+// runs the simulation with the default settings changed by `configure`, the replicas must agree
+fn run_with(configure: impl FnOnce(&mut Settings)) -> SimulationReport {
+    let mut settings = Settings::default();
+    configure(&mut settings);
+    let report = run_simulation(settings);
+    assert_eq!(report.agreement, Ok(()));
+    report
+}
+
+// every correct replica executed `requests` requests
+fn assert_executed(report: &SimulationReport, requests: usize) {
+    for record in &report.executions {
+        assert_eq!(
+            record.executed.len(),
+            requests,
+            "node {} lags behind",
+            record.node_id
+        );
+    }
+}
+
+#[test]
+/// Check that a run without any ini commits all requests on all replicas
+fn check_run_simulation_reports_results() {
+    for &node_type in &[
+        NodeType::PBFT,
+        NodeType::Zyzzyva,
+        NodeType::SBFT,
+        NodeType::Tendermint,
+        NodeType::Adaptive,
+        NodeType::DAG,
+    ] {
+        let report = run_with(|settings| {
+            settings.node.node_type = node_type;
+            settings.node.nodes = 5;
+            settings.simulation.requests = 5;
+        });
+        assert!(report.overdue.is_empty());
+        assert_eq!(report.done_requests(), (1..=5).collect());
+        assert!(report.events > 0);
+        assert_executed(&report, 5);
+    }
+}
+
+#[test]
+/// Check that a PBFT run whose primary gossips its PrePrepares only reaches the replicas, and
+/// that every replica gets them under any schedule
+fn check_run_with_gossiped_pre_prepares() {
+    for seed in 0..30 {
+        let report = run_with(|settings| {
+            settings.node.node_type = NodeType::PBFT;
+            settings.node.nodes = 7;
+            settings.simulation.requests = 5;
+            settings.simulation.seed = Some(seed);
+            settings.pbft.gossip_pre_prepare = true;
+        });
+        assert!(report.overdue.is_empty(), "seed {}", seed);
+        assert_eq!(report.done_requests(), (1..=5).collect(), "seed {}", seed);
+        assert!(report.traffic.get("PBFT", "PrePrepare").unwrap().sent > 0);
+        assert_executed(&report, 5);
+    }
+}
+
+#[test]
+/// Check that a cluster of a hundred replicas commits its requests
+fn check_run_simulation_with_many_replicas() {
+    let report = run_with(|settings| {
+        settings.node.nodes = 100;
+        settings.simulation.requests = 3;
+    });
+    assert_eq!(report.executions.len(), 100);
+    assert_executed(&report, 3);
+}
+
+#[test]
+/// Check that handling the receptions at the same time on worker threads commits the same
+/// requests as a single thread and merges the outputs of the nodes in the same order every time
+fn check_parallel_receptions_are_deterministic() {
+    let run = |threads| {
+        run_with(|settings| {
+            settings.node.nodes = 16;
+            settings.simulation.requests = 5;
+            settings.simulation.threads = threads;
+            settings.network.delay_min = 50.0;
+            settings.network.delay_max = 50.0;
+        })
+    };
+
+    let sequential = run(1);
+    let parallel = run(4);
+    assert_eq!(parallel.done_requests(), sequential.done_requests());
+    assert_eq!(parallel.end_time, sequential.end_time);
+    assert_eq!(run(4).results, parallel.results);
+}
+
+#[test]
+/// Check that all schedulers hand out the events of a run in the same order
+fn check_runs_with_all_schedulers() {
+    // a few runs, each the same for all schedulers
+    for &seed in &[1, 7, 42] {
+        let run = |scheduler| {
+            run_with(|settings| {
+                settings.simulation.requests = 5;
+                settings.simulation.scheduler = scheduler;
+                settings.simulation.seed = Some(seed);
+            })
+        };
+
+        let heap = run(SchedulerKind::Heap);
+        for scheduler in &[SchedulerKind::Buckets, SchedulerKind::Calendar] {
+            let report = run(*scheduler);
+            assert_eq!(
+                report.results, heap.results,
+                "{:?}, seed {}",
+                scheduler, seed
+            );
+            assert_eq!(
+                report.end_time, heap.end_time,
+                "{:?}, seed {}",
+                scheduler, seed
+            );
+        }
+    }
+}
+
+#[test]
+/// Check that parallel PBFT instances with their own leaders merge into a single total order,
+/// even if an instance is short of client requests
+fn check_pbft_with_parallel_instances() {
+    let report = run_with(|settings| {
+        settings.node.nodes = 5;
+        settings.simulation.requests = 7;
+        settings.pbft.instances = 3;
+    });
+    assert_eq!(report.done_requests(), (1..=7).collect());
+    assert_executed(&report, 7);
+
+    // every leader orders the requests of its bucket, the client 6 issues them
+    let leaders: HashSet<&str> = report
+        .results
+        .iter()
+        .filter(|line| line.ends_with(";request"))
+        .filter_map(|line| line.split(';').nth(1))
+        .filter(|id| *id != "6")
+        .collect();
+    assert_eq!(leaders, ["1", "2", "3"].iter().cloned().collect());
+}
+
+#[test]
+/// Check that a primary rotating with every request lets every replica order requests in
+/// turn, while a stable primary orders all of them
+fn check_pbft_with_rotating_leader() {
+    let leaders = |leader_rotation| {
+        let report = run_with(|settings| {
+            settings.node.nodes = 4;
+            settings.simulation.requests = 8;
+            settings.pbft.leader_rotation = leader_rotation;
+        });
+        assert_eq!(report.done_requests(), (1..=8).collect());
+        report
+            .results
+            .iter()
+            .filter(|line| line.ends_with(";request"))
+            .filter_map(|line| line.split(';').nth(1)?.parse().ok())
+            .filter(|id| *id != 5)
+            .collect::<HashSet<u32>>()
+    };
+
+    assert_eq!(
+        leaders(LeaderRotation::Stable),
+        vec![1].into_iter().collect()
+    );
+    assert_eq!(leaders(LeaderRotation::PerRequest), (1..=4).collect());
+}
+
+#[test]
+/// Check that Zyzzyva replicas without speculative execution execute the committed requests
+fn check_zyzzyva_without_speculative_execution() {
+    let report = run_with(|settings| {
+        settings.node.node_type = NodeType::Zyzzyva;
+        settings.node.nodes = 4;
+        settings.simulation.requests = 5;
+        settings.zyzzyva.speculative_execution = false;
+    });
+    assert_eq!(report.done_requests(), (1..=5).collect());
+    assert!(report
+        .results
+        .iter()
+        .all(|line| !line.contains("speculative_commit")));
+    assert_executed(&report, 5);
+}
+
+#[test]
+/// Check that synchronous writes of the log entries delay every phase of PBFT, while
+/// asynchronous ones do not delay the requests at all
+fn check_disk_writes_delay_the_phases() {
+    let run = |mode: Option<StorageMode>| {
+        let report = run_with(|settings| {
+            settings.node.nodes = 4;
+            settings.simulation.requests = 5;
+            settings.network.delay_min = 50.0;
+            settings.network.delay_max = 50.0;
+            if let Some(mode) = mode {
+                settings.storage.protocols = vec![NodeType::PBFT];
+                settings.storage.mode = mode;
+                settings.storage.latency_min = 20_000;
+                settings.storage.latency_max = 20_000;
+            }
+        });
+        assert_eq!(report.requests.done, 5);
+        report.requests.mean_latency
+    };
+
+    let volatile = run(None);
+    assert_eq!(run(Some(StorageMode::Async)), volatile);
+    // the PrePrepare, the Prepare and the Commit are written one after the other
+    assert!(run(Some(StorageMode::Sync)) >= volatile + 60.0);
+}
+
+#[test]
+/// Check that the scheduled periods of degraded links slow down only the requests within them
+fn check_degraded_links_follow_the_schedule() {
+    let run = |schedule: Vec<ScheduledCondition>| {
+        let report = run_with(|settings| {
+            settings.node.nodes = 4;
+            settings.simulation.requests = 10;
+            settings.network.delay_min = 50.0;
+            settings.network.delay_max = 50.0;
+            settings.network.schedule = schedule;
+        });
+        assert_eq!(report.requests.done, 10);
+        report.requests.mean_latency
+    };
+    let degraded = |start: u64, end: Option<u64>| ScheduledCondition {
+        start,
+        end,
+        condition: LinkCondition {
+            from: None,
+            to: None,
+            delay: Some(500),
+            loss: None,
+        },
+    };
+
+    let healthy = run(Vec::new());
+    let always = run(vec![degraded(0, None)]);
+    // the requests are issued every second, only the first half is slowed down
+    let first_half = run(vec![degraded(0, Some(5000))]);
+    assert!(always >= healthy + 1000.0, "{} vs {}", always, healthy);
+    assert!(first_half > healthy && first_half < always);
+}
+
+#[test]
+/// Check that the PBFT client completes its requests with the replies of the replicas, so the
+/// latency covers the whole round trip
+fn check_pbft_client_completes_with_replies() {
+    let report = run_with(|settings| {
+        settings.node.nodes = 4;
+        settings.simulation.requests = 5;
+    });
+    let time_of = |event: &str| -> HashMap<&str, u64> {
+        let mut times = HashMap::new();
+        for fields in report
+            .results
+            .iter()
+            .map(|line| line.split(';').collect::<Vec<_>>())
+        {
+            if fields.len() == 4 && fields[3] == event {
+                times.entry(fields[2]).or_insert(fields[0].parse().unwrap());
+            }
+        }
+        times
+    };
+    let committed = time_of("committed_local");
+    let completed = time_of("completed");
+    assert_eq!(completed.len(), 5);
+    for (request, time) in &completed {
+        assert!(
+            *time > committed[request],
+            "request {} completed early",
+            request
+        );
+    }
+    assert_eq!(report.requests.done, 5);
+}
+
+#[test]
+/// Check that PrePrepares carry digests of large requests, whose bodies the primary or the
+/// client disseminate separately
+fn check_pre_prepare_with_digests() {
+    let run = |dissemination| {
+        let report = run_with(|settings| {
+            settings.node.nodes = 4;
+            settings.simulation.requests = 5;
+            settings.pbft.request_size = 4096;
+            settings.pbft.dissemination = dissemination;
+        });
+        assert_eq!(report.requests.done, 5);
+        let bytes = |message_type| {
+            report
+                .traffic
+                .get("PBFT", message_type)
+                .map_or(0, |counter| counter.bytes)
+        };
+        (bytes("PrePrepare"), bytes("Request"))
+    };
+
+    let (inline, bodies) = run(Dissemination::Inline);
+    assert_eq!(bodies, 0);
+    for dissemination in &[Dissemination::Primary, Dissemination::Client] {
+        let (digests, bodies) = run(*dissemination);
+        assert!(digests * 10 < inline, "{:?}", dissemination);
+        assert!(bodies >= 5 * 3 * 4096, "{:?}", dissemination);
+    }
+}
+
+#[test]
+/// Check that compressing large PrePrepares saves bandwidth and, on slow links, latency
+fn check_compression_of_large_messages() {
+    let run = |compression| {
+        let report = run_with(|settings| {
+            settings.node.nodes = 4;
+            settings.simulation.requests = 5;
+            settings.network.delay_min = 20.0;
+            settings.network.delay_max = 20.0;
+            settings.network.bandwidth = Some(10.0);
+            settings.pbft.request_size = 100_000;
+            settings.compression = compression;
+        });
+        assert_eq!(report.requests.done, 5);
+        let bytes = report
+            .traffic
+            .get("PBFT", "PrePrepare")
+            .map_or(0, |counter| counter.bytes);
+        (bytes, report.requests.mean_latency)
+    };
+
+    let (plain_bytes, plain_latency) = run(Compression::none());
+    let (bytes, latency) = run(Compression {
+        threshold: Some(1000),
+        ratio: 0.1,
+        cost: 0.001,
+    });
+    assert!(
+        bytes * 5 < plain_bytes,
+        "{} of {} bytes",
+        bytes,
+        plain_bytes
+    );
+    // 100kB take 80ms over a link of 10 Mbit/s, compressed 8ms
+    assert!(
+        latency + 50.0 < plain_latency,
+        "latency {}ms, uncompressed {}ms",
+        latency,
+        plain_latency
+    );
+}
+
+#[test]
+/// Check that the Zyzzyva clients are nodes after the replicas, which take turns with the
+/// requests and do not count as replicas
+fn check_zyzzyva_clients_follow_the_replicas() {
+    let report = run_with(|settings| {
+        settings.node.node_type = NodeType::Zyzzyva;
+        settings.node.nodes = 4;
+        settings.node.clients = 2;
+        settings.simulation.requests = 4;
+    });
+    assert_eq!(report.done_requests(), (1..=4).collect());
+    let replicas: Vec<u32> = report.executions.iter().map(|r| r.node_id).collect();
+    assert_eq!(replicas, vec![1, 2, 3, 4]);
+    for client in &[5, 6] {
+        let completed = report
+            .results
+            .iter()
+            .filter(|line| line.ends_with(";completed"))
+            .filter(|line| line.split(';').nth(1) == Some(&client.to_string()))
+            .count();
+        assert_eq!(
+            completed, 2,
+            "client {} did not complete its requests",
+            client
+        );
+    }
+}
+
+#[test]
+/// Check that the correct replicas drop the messages of a Byzantine replica whose
+/// authenticators are all invalid and still commit every request without it
+fn check_forged_messages_are_rejected() {
+    let node_types = [
+        NodeType::PBFT,
+        NodeType::Zyzzyva,
+        NodeType::SBFT,
+        NodeType::Tendermint,
+        NodeType::Adaptive,
+        NodeType::DAG,
+        NodeType::Steward,
+    ];
+    for &node_type in &node_types {
+        let report = run_with(|settings| {
+            settings.node.node_type = node_type;
+            settings.node.nodes = 4;
+            if node_type == NodeType::Steward {
+                // three sites of four replicas, the Byzantine replica is part of the first one
+                settings.node.nodes = 12;
+                settings.network.topology = "clusters".to_string();
+                settings.network.clusters = 3;
+            }
+            // without the rejected responses of the Byzantine replica a Zyzzyva client needs the
+            // responses of the slowest replicas for its commit certificate, wait for them
+            settings.node.client_timeout = 1000;
+            settings.simulation.requests = 5;
+            settings.byzantine.nodes = vec![4];
+            settings.byzantine.invalid_authentication = 1.0;
+        });
+        assert_eq!(
+            report.done_requests(),
+            (1..=5).collect(),
+            "{:?} did not commit every request",
+            node_type
+        );
+        let rejected = report
+            .results
+            .iter()
+            .filter(|line| line.contains(";rejected;"))
+            .count();
+        assert!(rejected > 0, "no message of {:?} was rejected", node_type);
+        // only the messages of the Byzantine replica are forged, it rejects none itself
+        assert!(report
+            .results
+            .iter()
+            .all(|line| !line.contains(";4;rejected;")));
+    }
+}
+
+#[test]
+/// Check that an adaptive node switches away from a protocol that does not make progress and
+/// keeps the requests committed before the switch
+fn check_adaptive_switches_protocols() {
+    let report = run_with(|settings| {
+        settings.node.node_type = NodeType::Adaptive;
+        settings.node.nodes = 4;
+        settings.simulation.requests = 10;
+        // the first protocol never commits within the switch timeout, the backup takes over
+        settings.adaptive.protocols = vec![NodeType::Tendermint, NodeType::PBFT];
+        settings.adaptive.switch_timeout = 100;
+        settings.adaptive.backup_requests = 0;
+    });
+    assert!(report
+        .results
+        .iter()
+        .any(|line| line.contains("switched;1;PBFT")));
+    assert!(report.views.view_changes >= 1);
+    assert!(report.views.unstable_ms > 0);
+    assert_eq!(
+        report.views.views.len() as u64,
+        report.views.view_changes + 1
+    );
+    assert!(report
+        .results
+        .iter()
+        .any(|line| line.contains(";-1;view_changes;")));
+    assert_executed(&report, 10);
+}
+
+#[test]
+/// Check that a PBFT and a Zyzzyva cluster run side by side, each one ordering the requests of
+/// its own clients
+fn check_clusters_side_by_side() {
+    let report = run_with(|settings| {
+        settings.node.nodes = 4;
+        settings.simulation.requests = 4;
+        settings.node.composition = vec![ClusterSettings {
+            node_type: NodeType::Zyzzyva,
+            nodes: 4,
+            requests: Some(3),
+        }];
+    });
+    assert_eq!(report.done_requests(), (1..=7).collect());
+    // the pbft client is node 5, the zyzzyva replicas and their client follow it
+    let replicas: Vec<u32> = report.executions.iter().map(|r| r.node_id).collect();
+    assert_eq!(replicas, vec![1, 2, 3, 4, 6, 7, 8, 9]);
+    for record in &report.executions {
+        let executed = if record.node_id < 5 { 4 } else { 3 };
+        assert_eq!(record.executed.len(), executed, "node {}", record.node_id);
+    }
+    let completed = report
+        .results
+        .iter()
+        .filter(|line| line.ends_with(";completed"))
+        .filter(|line| line.split(';').nth(1) == Some("10"))
+        .count();
+    assert_eq!(completed, 3);
+}
+
+#[test]
+/// Check that the sites of a Steward cluster order the requests over the slow links between
+/// the data centers
+fn check_steward_sites() {
+    let report = run_with(|settings| {
+        settings.node.node_type = NodeType::Steward;
+        settings.node.nodes = 12;
+        settings.simulation.requests = 5;
+        settings.network.topology = "clusters".to_string();
+        settings.network.clusters = 3;
+        settings.network.cluster_delay = 100;
+    });
+    assert_eq!(report.done_requests(), (1..=5).collect());
+    assert_eq!(report.executions.len(), 12);
+    assert_executed(&report, 5);
+    // the proposal and the accepts cross the wide area before the last request is ordered
+    let last_request = Time::new(4000);
+    assert!(report.end_time > last_request + Duration::from_millis(200));
+    // per request: the proposal to two representatives and the accepts of two sites to two
+    // representatives each
+    assert_eq!(
+        report.traffic.get("Steward", "Proposal").unwrap().sent,
+        5 * 2
+    );
+    assert_eq!(
+        report.traffic.get("Steward", "Accept").unwrap().sent,
+        5 * 2 * 2
+    );
+}
+
+#[test]
+/// Check that a Steward cluster of a single site orders the requests without wide-area messages
+fn check_steward_single_site() {
+    let report = run_with(|settings| {
+        settings.node.node_type = NodeType::Steward;
+        settings.node.nodes = 4;
+        settings.simulation.requests = 3;
+        settings.network.topology = "clusters".to_string();
+        settings.network.clusters = 1;
+    });
+    assert_executed(&report, 3);
+    assert!(report.traffic.get("Steward", "Proposal").is_none());
+    assert!(report.traffic.get("Steward", "Accept").is_none());
+}
+
+#[test]
+/// Check that the messages of SBFT grow linearly with the replicas
+fn check_sbft_communication_is_linear() {
+    for &n in &[4, 10, 40] {
+        let report = run_with(|settings| {
+            settings.node.node_type = NodeType::SBFT;
+            settings.node.nodes = n;
+            settings.simulation.requests = 1;
+        });
+        assert_executed(&report, 1);
+        // pre-prepare, prepare shares, prepare certificate, commit shares and commit
+        // certificate, each n - 1 messages or fewer
+        let messages: u64 = [
+            "PrePrepare",
+            "PrepareShare",
+            "PrepareCertificate",
+            "CommitShare",
+            "CommitCertificate",
+        ]
+        .iter()
+        .map(|message_type| report.traffic.get("SBFT", message_type).unwrap().sent)
+        .sum();
+        assert!(
+            messages <= 5 * (u64::from(n) - 1),
+            "n = {}: {}",
+            n,
+            messages
+        );
+    }
+}
+
+// cuts the links of `node` in both directions from `start` until `end`
+fn cut_off(node: u32, start: u64, end: u64) -> Vec<ScheduledCondition> {
+    let cut = |from, to| ScheduledCondition {
+        start,
+        end: Some(end),
+        condition: LinkCondition {
+            from,
+            to,
+            delay: None,
+            loss: Some(1.0),
+        },
+    };
+    vec![cut(Some(node), None), cut(None, Some(node))]
+}
+
+#[test]
+/// Check that the Tendermint replicas skip the rounds of a proposer that is cut off by timeouts
+fn check_tendermint_skips_unreachable_proposer() {
+    // replica 2 proposes in round 0 of height 2, the outage ends long after the last request
+    let report = run_with(|settings| {
+        settings.node.node_type = NodeType::Tendermint;
+        settings.node.nodes = 4;
+        settings.simulation.requests = 3;
+        settings.network.schedule = cut_off(2, 0, 20000);
+    });
+    assert!(report.overdue.is_empty());
+    assert_eq!(report.done_requests(), (1..=3).collect());
+    for record in &report.executions {
+        let executed = if record.node_id == 2 { 0 } else { 3 };
+        assert_eq!(record.executed.len(), executed, "node {}", record.node_id);
+    }
+}
+
+#[test]
+/// Check that a Tendermint replica that missed the first decisions catches up with the later ones
+fn check_tendermint_lagging_replica_catches_up() {
+    let report = run_with(|settings| {
+        settings.node.node_type = NodeType::Tendermint;
+        settings.node.nodes = 4;
+        settings.simulation.requests = 6;
+        settings.network.schedule = cut_off(4, 0, 2500);
+    });
+    assert_eq!(report.done_requests(), (1..=6).collect());
+    assert_executed(&report, 6);
+}